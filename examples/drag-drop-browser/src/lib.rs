@@ -8,7 +8,7 @@ use web_sys::*;
 
 use css_rs_macro::css;
 
-use psd::Psd;
+use psd::{LayerRgbaCache, Psd};
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::ops::Deref;
@@ -89,6 +89,7 @@ impl App {
 
         let state = State {
             psd: None,
+            layer_cache: LayerRgbaCache::new(),
             layer_visibility: HashMap::new(),
             is_rendering: false,
         };
@@ -233,14 +234,16 @@ impl App {
     fn update(&mut self, vdom: VirtualNode) -> Result<(), JsValue> {
         self.dom_updater.update(vdom);
 
-        let psd = &self.store.borrow();
-        let psd = &psd.psd;
-        let psd = psd.as_ref().unwrap();
+        let store = &self.store.borrow();
+        let psd = store.psd.as_ref().unwrap();
 
         // Flatten the PSD into only the pixels from the layers that are currently
         // toggled on.
         let mut psd_pixels = psd
-            .flatten_layers_rgba(&|(idx, layer)| {
+            .render()
+            .layer_cache(&store.layer_cache)
+            .visible_only()
+            .filter(|(idx, layer)| {
                 let layer_visible = *self
                     .store
                     .borrow()
@@ -250,6 +253,7 @@ impl App {
 
                 layer_visible
             })
+            .to_rgba()
             .unwrap();
 
         let psd_pixels = Clamped(&psd_pixels[..]);
@@ -295,6 +299,8 @@ impl Deref for Store {
 struct State {
     /// The current PSD that is being displayed
     psd: Option<Psd>,
+    /// The decoded pixels of the current PSD's layers, reused every time that a layer is toggled
+    layer_cache: LayerRgbaCache,
     /// Layer name -> whether or not it currently toggled on
     layer_visibility: HashMap<String, bool>,
     /// Whether or not we've already requested to render on the next animation frame
@@ -331,6 +337,7 @@ impl State {
                 }
 
                 self.psd = Some(psd);
+                self.layer_cache.clear();
                 self.layer_visibility = layer_visibility;
             }
             // Set whether or not a layer is currently toggled on/off
@@ -0,0 +1,452 @@
+//! Applying the adjustment layers that `RenderPipeline::adjustment_layers` supports to the pixels
+//! below them: brightness/contrast, levels, curves, hue/saturation and exposure.
+
+use std::f64::consts::FRAC_PI_4;
+
+use crate::blend;
+use crate::sections::layer_and_mask_information_section::adjustment::{
+    Adjustment, BrightnessContrast, Curves, Exposure, HueSaturation, Levels, LevelsRecord,
+};
+use crate::{Psd, PsdLayer};
+
+/// What each value of the red, green and blue channels becomes
+type Tables = [[u8; 256]; 3];
+
+/// An adjustment layer that is ready to be applied to the pixels below it
+pub(crate) struct AdjustmentLayer {
+    adjustment: ColorAdjustment,
+    opacity: u8,
+    /// The layer's enabled user mask, the size of the PSD
+    mask: Option<Vec<u8>>,
+    /// The width of the PSD
+    width: usize,
+}
+
+/// How an adjustment changes the color of a pixel
+enum ColorAdjustment {
+    /// Adjustments that change each channel on its own
+    Tables(Box<Tables>),
+    /// Hue/saturation depends on all of a pixel's channels
+    HueSaturation(HueSaturation),
+}
+
+impl AdjustmentLayer {
+    /// Returns `None` if the layer isn't an adjustment layer, or if we don't render its
+    /// adjustment.
+    pub(crate) fn new(psd: &Psd, layer: &PsdLayer) -> Option<AdjustmentLayer> {
+        let adjustment = match layer.adjustment()? {
+            Adjustment::BrightnessContrast(settings) => {
+                ColorAdjustment::Tables(Box::new(brightness_contrast_tables(&settings)))
+            }
+            Adjustment::Levels(levels) => ColorAdjustment::Tables(Box::new(levels_tables(&levels))),
+            Adjustment::Curves(curves) => ColorAdjustment::Tables(Box::new(curves_tables(&curves))),
+            Adjustment::HueSaturation(settings) => ColorAdjustment::HueSaturation(settings),
+            Adjustment::Exposure(exposure) => {
+                ColorAdjustment::Tables(Box::new(exposure_tables(&exposure)))
+            }
+            Adjustment::Other(_) => return None,
+        };
+
+        let mask = match layer.mask() {
+            Some(mask) if !mask.flags().disabled() => layer.mask_pixels(),
+            _ => None,
+        };
+
+        Some(AdjustmentLayer {
+            adjustment,
+            opacity: blend::multiply_alpha(layer.opacity, layer.fill_opacity()),
+            mask,
+            width: psd.width() as usize,
+        })
+    }
+
+    /// Adjust the pixel at a coordinate (left, top), faded by the layer's opacity and mask.
+    pub(crate) fn apply(&self, pixel: blend::Pixel, pixel_coord: (usize, usize)) -> blend::Pixel {
+        let (left, top) = pixel_coord;
+
+        let opacity = match &self.mask {
+            Some(mask) => {
+                let mask = mask[top * self.width + left] as u16;
+                ((self.opacity as u16 * mask + 127) / 255) as u8
+            }
+            None => self.opacity,
+        };
+        if opacity == 0 {
+            return pixel;
+        }
+
+        let adjusted = match &self.adjustment {
+            ColorAdjustment::Tables(tables) => [
+                tables[0][pixel[0] as usize],
+                tables[1][pixel[1] as usize],
+                tables[2][pixel[2] as usize],
+                pixel[3],
+            ],
+            ColorAdjustment::HueSaturation(settings) => adjust_hue_saturation(settings, pixel),
+        };
+
+        blend::fade(pixel, adjusted, opacity)
+    }
+}
+
+/// Build a table for each channel by mapping every value, given the channel's index.
+fn tables<F: Fn(usize, u8) -> u8>(map: F) -> Tables {
+    let mut tables = [[0; 256]; 3];
+
+    for (channel, table) in tables.iter_mut().enumerate() {
+        for (value, mapped) in table.iter_mut().enumerate() {
+            *mapped = map(channel, value as u8);
+        }
+    }
+
+    tables
+}
+
+/// Convert a value from 0 to 1 into a channel value
+fn to_channel(value: f64) -> u8 {
+    (value * 255.).round().clamp(0., 255.) as u8
+}
+
+/// Photoshop doesn't document how it applies brightness and contrast, so we brighten or darken
+/// towards white or black and then scale the contrast around the mean value.
+fn brightness_contrast_tables(settings: &BrightnessContrast) -> Tables {
+    let brightness = (settings.brightness as f64 / 150.).clamp(-1., 1.);
+    let contrast = (settings.contrast as f64 / 100.).clamp(-1., 1.);
+    let slope = ((contrast + 1.) * FRAC_PI_4).tan();
+    let mean = settings.mean.clamp(0, 255) as f64 / 255.;
+
+    tables(|_, value| {
+        let value = value as f64 / 255.;
+        let value = if brightness < 0. {
+            value * (1. + brightness)
+        } else {
+            value + (1. - value) * brightness
+        };
+
+        to_channel((value - mean) * slope + mean)
+    })
+}
+
+/// Each channel's own levels are applied before the composite levels.
+fn levels_tables(levels: &Levels) -> Tables {
+    tables(|channel, value| {
+        let value = value as f64 / 255.;
+        let value = apply_levels(&levels.records[channel + 1], value);
+
+        to_channel(apply_levels(levels.composite(), value))
+    })
+}
+
+/// Map a value from 0 to 1 through a levels record.
+fn apply_levels(record: &LevelsRecord, value: f64) -> f64 {
+    let input_floor = record.input_floor as f64 / 255.;
+    let input_ceiling = record.input_ceiling as f64 / 255.;
+    let output_floor = record.output_floor as f64 / 255.;
+    let output_ceiling = record.output_ceiling as f64 / 255.;
+
+    let value = if input_ceiling > input_floor {
+        ((value - input_floor) / (input_ceiling - input_floor)).clamp(0., 1.)
+    } else if value >= input_ceiling {
+        1.
+    } else {
+        0.
+    };
+    let gamma = if record.gamma > 0. { record.gamma } else { 1. };
+
+    output_floor + value.powf(1. / gamma) * (output_ceiling - output_floor)
+}
+
+/// Each channel's own curve is applied before the composite curve.
+fn curves_tables(curves: &Curves) -> Tables {
+    let curve_table = |channel: u32| {
+        curves
+            .curves
+            .iter()
+            .find(|curve| curve.channel == channel)
+            .map(|curve| curve_table(&curve.points))
+    };
+    let composite = curve_table(0);
+    let channels = [curve_table(1), curve_table(2), curve_table(3)];
+
+    tables(|channel, value| {
+        let value = match &channels[channel] {
+            Some(table) => table[value as usize],
+            None => value,
+        };
+
+        match &composite {
+            Some(table) => table[value as usize],
+            None => value,
+        }
+    })
+}
+
+/// What each value becomes along a curve, using a natural cubic spline through its (input,
+/// output) points. Values outside of the first and last points keep those points' outputs.
+fn curve_table(points: &[(u16, u16)]) -> [u8; 256] {
+    let mut points: Vec<(f64, f64)> = points
+        .iter()
+        .map(|(input, output)| (*input as f64, *output as f64))
+        .collect();
+    points.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+    points.dedup_by(|a, b| a.0 == b.0);
+
+    let mut table = [0; 256];
+    if points.is_empty() {
+        for (value, mapped) in table.iter_mut().enumerate() {
+            *mapped = value as u8;
+        }
+        return table;
+    }
+
+    // The second derivative of the spline at each point
+    let count = points.len();
+    let mut second = vec![0.; count];
+    let mut partial = vec![0.; count];
+    for idx in 1..count.saturating_sub(1) {
+        let (prev, point, next) = (points[idx - 1], points[idx], points[idx + 1]);
+
+        let sig = (point.0 - prev.0) / (next.0 - prev.0);
+        let p = sig * second[idx - 1] + 2.;
+        second[idx] = (sig - 1.) / p;
+
+        let slopes =
+            (next.1 - point.1) / (next.0 - point.0) - (point.1 - prev.1) / (point.0 - prev.0);
+        partial[idx] = (6. * slopes / (next.0 - prev.0) - sig * partial[idx - 1]) / p;
+    }
+    for idx in (0..count.saturating_sub(1)).rev() {
+        second[idx] = second[idx] * second[idx + 1] + partial[idx];
+    }
+
+    let (first, last) = (points[0], points[count - 1]);
+    for (input, mapped) in table.iter_mut().enumerate() {
+        let x = input as f64;
+
+        let output = if x <= first.0 {
+            first.1
+        } else if x >= last.0 {
+            last.1
+        } else {
+            let hi = points.iter().position(|point| point.0 >= x).unwrap();
+            let lo = hi - 1;
+
+            let width = points[hi].0 - points[lo].0;
+            let a = (points[hi].0 - x) / width;
+            let b = (x - points[lo].0) / width;
+
+            a * points[lo].1
+                + b * points[hi].1
+                + ((a.powi(3) - a) * second[lo] + (b.powi(3) - b) * second[hi]) * width * width / 6.
+        };
+
+        *mapped = output.round().clamp(0., 255.) as u8;
+    }
+
+    table
+}
+
+/// Exposure is applied to linear light, `(value * 2^exposure + offset)^(1 / gamma)`.
+fn exposure_tables(exposure: &Exposure) -> Tables {
+    let scale = 2f64.powf(exposure.exposure as f64);
+    let offset = exposure.offset as f64;
+    let gamma = if exposure.gamma > 0. {
+        exposure.gamma as f64
+    } else {
+        1.
+    };
+
+    tables(|_, value| {
+        let linear = srgb_to_linear(value as f64 / 255.);
+        let linear = (linear * scale + offset).max(0.).powf(1. / gamma);
+
+        to_channel(linear_to_srgb(linear.min(1.)))
+    })
+}
+
+fn srgb_to_linear(value: f64) -> f64 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(value: f64) -> f64 {
+    if value <= 0.003_130_8 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1. / 2.4) - 0.055
+    }
+}
+
+/// Shift the hue of a pixel and scale its saturation and lightness by the master settings plus
+/// the settings of each color range that its hue falls in, or colorize it.
+fn adjust_hue_saturation(settings: &HueSaturation, pixel: blend::Pixel) -> blend::Pixel {
+    let (hue, saturation, lightness) = rgb_to_hsl(pixel);
+
+    let (hue, saturation, lightness) = if settings.colorize {
+        let colorization = settings.colorization;
+
+        (
+            colorization.hue as f64,
+            colorization.saturation as f64 / 100.,
+            adjust_lightness(lightness, colorization.lightness as f64),
+        )
+    } else {
+        let master = settings.master;
+        let (mut hue_shift, mut saturation_change, mut lightness_change) = (
+            master.hue as f64,
+            master.saturation as f64,
+            master.lightness as f64,
+        );
+
+        // Grays don't have a hue, so they aren't in any of the ranges
+        if saturation > 0. {
+            for range in settings.ranges.iter() {
+                let weight = range_weight(range.range, hue);
+                hue_shift += weight * range.settings.hue as f64;
+                saturation_change += weight * range.settings.saturation as f64;
+                lightness_change += weight * range.settings.lightness as f64;
+            }
+        }
+
+        let saturation_change = (saturation_change / 100.).clamp(-1., 1.);
+
+        (
+            hue + hue_shift,
+            (saturation * (1. + saturation_change)).clamp(0., 1.),
+            adjust_lightness(lightness, lightness_change),
+        )
+    };
+
+    let [red, green, blue] = hsl_to_rgb(hue.rem_euclid(360.), saturation, lightness);
+    [red, green, blue, pixel[3]]
+}
+
+/// Lighten towards white or darken towards black by an amount from -100 to 100
+fn adjust_lightness(lightness: f64, amount: f64) -> f64 {
+    let amount = (amount / 100.).clamp(-1., 1.);
+
+    if amount < 0. {
+        lightness * (1. + amount)
+    } else {
+        lightness + (1. - lightness) * amount
+    }
+}
+
+/// How much a hue/saturation color range applies to a hue, from 0 to 1. The range fades in
+/// between its first two hues and fades out between its last two, wrapping around 360 degrees.
+fn range_weight(range: [i16; 4], hue: f64) -> f64 {
+    let start = range[0] as f64;
+    let from_start = |degrees: f64| (degrees - start).rem_euclid(360.);
+
+    let full_start = from_start(range[1] as f64);
+    let full_end = from_start(range[2] as f64);
+    let end = from_start(range[3] as f64);
+    let hue = from_start(hue);
+
+    if hue < full_start {
+        hue / full_start
+    } else if hue <= full_end {
+        1.
+    } else if hue < end {
+        (end - hue) / (end - full_end)
+    } else {
+        0.
+    }
+}
+
+/// The (hue in degrees, saturation, lightness) of a pixel
+fn rgb_to_hsl(pixel: blend::Pixel) -> (f64, f64, f64) {
+    let [red, green, blue] = [
+        pixel[0] as f64 / 255.,
+        pixel[1] as f64 / 255.,
+        pixel[2] as f64 / 255.,
+    ];
+    let max = red.max(green).max(blue);
+    let min = red.min(green).min(blue);
+    let lightness = (max + min) / 2.;
+
+    let chroma = max - min;
+    if chroma == 0. {
+        return (0., 0., lightness);
+    }
+
+    let saturation = chroma / (1. - (2. * lightness - 1.).abs());
+    let hue = if max == red {
+        ((green - blue) / chroma).rem_euclid(6.)
+    } else if max == green {
+        (blue - red) / chroma + 2.
+    } else {
+        (red - green) / chroma + 4.
+    };
+
+    (hue * 60., saturation, lightness)
+}
+
+fn hsl_to_rgb(hue: f64, saturation: f64, lightness: f64) -> [u8; 3] {
+    let chroma = (1. - (2. * lightness - 1.).abs()) * saturation;
+    let sector = hue / 60.;
+    let x = chroma * (1. - (sector % 2. - 1.).abs());
+
+    let (red, green, blue) = match sector as u8 {
+        0 => (chroma, x, 0.),
+        1 => (x, chroma, 0.),
+        2 => (0., chroma, x),
+        3 => (0., x, chroma),
+        4 => (x, 0., chroma),
+        _ => (chroma, 0., x),
+    };
+    let min = lightness - chroma / 2.;
+
+    [
+        to_channel(red + min),
+        to_channel(green + min),
+        to_channel(blue + min),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Verify that a curve through its two corners doesn't change anything, and that a curve
+    /// passes through each of its points.
+    #[test]
+    fn curve_through_points() {
+        let identity = curve_table(&[(0, 0), (255, 255)]);
+        assert!(identity
+            .iter()
+            .enumerate()
+            .all(|(idx, value)| idx == *value as usize));
+
+        let curve = curve_table(&[(0, 0), (64, 128), (192, 200), (255, 255)]);
+        assert_eq!(curve[64], 128);
+        assert_eq!(curve[192], 200);
+        assert!(curve.windows(2).all(|pair| pair[0] <= pair[1]));
+
+        let clamped = curve_table(&[(50, 20), (200, 220)]);
+        assert_eq!(clamped[0], 20);
+        assert_eq!(clamped[255], 220);
+    }
+
+    /// Verify that converting to HSL and back gives back the same color.
+    #[test]
+    fn hsl_round_trip() {
+        for pixel in [[200, 100, 50, 255], [50, 150, 250, 255], [0, 255, 0, 255]].iter() {
+            let (hue, saturation, lightness) = rgb_to_hsl(*pixel);
+            assert_eq!(&hsl_to_rgb(hue, saturation, lightness), &pixel[..3]);
+        }
+    }
+
+    /// Verify that a range is fully applied between its middle hues and fades in and out around
+    /// them, including the reds that wrap around 0 degrees.
+    #[test]
+    fn range_weights() {
+        let reds = [315, 345, 15, 45];
+        assert_eq!(range_weight(reds, 0.), 1.);
+        assert_eq!(range_weight(reds, 30.), 0.5);
+        assert_eq!(range_weight(reds, 330.), 0.5);
+        assert_eq!(range_weight(reds, 180.), 0.);
+    }
+}
@@ -1,13 +1,94 @@
+use crate::sections::file_header_section::PsdDepth;
 use crate::sections::layer_and_mask_information_section::layer::BlendMode;
 
 pub(crate) type Pixel = [u8; 4];
 
+/// The largest value of an 8-bit color or alpha channel, which `Normalized` maps to 1.0
+pub(crate) const CHANNEL_MAX: f32 = 255.;
+
+/// An 8-bit color or alpha channel divided by `CHANNEL_MAX`, the 0.0 to 1.0 range that blending
+/// works in.
+///
+/// The blend functions themselves take plain `f32`s, since 32-bit documents let their results
+/// leave that range while compositing, see `blend_pixels_at_depth`.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub(crate) struct Normalized(pub(crate) f32);
+
+impl Normalized {
+    pub(crate) fn from_channel(channel: u8) -> Normalized {
+        Normalized(channel as f32 / CHANNEL_MAX)
+    }
+
+    /// Back to 0 to 255, rounded to the nearest value. Casting saturates, so values outside of
+    /// 0.0 to 1.0 are clipped.
+    pub(crate) fn to_channel(self) -> u8 {
+        (self.0 * CHANNEL_MAX).round() as u8
+    }
+}
+
 // Multiplies the pixel's current alpha by the passed in `opacity`
 pub(crate) fn apply_opacity(pixel: &mut Pixel, opacity: u8) {
-    let alpha = opacity as f32 / 255.;
-    pixel[3] = (pixel[3] as f32 * alpha) as u8;
+    pixel[3] = multiply_alpha(pixel[3], opacity);
+}
+
+// Multiplies the alpha of every pixel in a buffer of RGBA pixels by the passed in `opacity`
+pub(crate) fn apply_opacity_buffer(rgba: &mut [u8], opacity: u8) {
+    if opacity == 255 {
+        return;
+    }
+
+    for pixel in rgba.chunks_exact_mut(4) {
+        pixel[3] = multiply_alpha(pixel[3], opacity);
+    }
+}
+
+/// Multiplies the alpha of every pixel in a buffer of a layer's RGBA pixels by the layer's
+/// `opacity` and by its `fill_opacity`, which only fades the layer's own pixels and not its effects
+pub(crate) fn apply_layer_opacity_buffer(rgba: &mut [u8], opacity: u8, fill_opacity: u8) {
+    apply_opacity_buffer(rgba, multiply_alpha(opacity, fill_opacity));
+}
+
+/// `alpha * opacity / 255`, rounded to the nearest integer without converting to floats
+#[inline(always)]
+pub(crate) fn multiply_alpha(alpha: u8, opacity: u8) -> u8 {
+    ((alpha as u16 * opacity as u16 + 127) / 255) as u8
 }
 
+/// Interpolate from the `below` pixel to the `above` pixel by `opacity`, using premultiplied
+/// colors so that transparent pixels don't darken the result.
+///
+/// This is how a pass through group's opacity works, since its contents were already blended
+/// onto the pixels below the group.
+pub(crate) fn fade(below: Pixel, above: Pixel, opacity: u8) -> Pixel {
+    #[cfg(feature = "integer-compositor")]
+    return crate::integer_blend::fade(below, above, opacity);
+
+    #[cfg(not(feature = "integer-compositor"))]
+    fade_float(below, above, opacity)
+}
+
+/// `fade` in floating point, see `integer_blend::fade` for the integer version.
+#[cfg_attr(feature = "integer-compositor", allow(dead_code))]
+pub(crate) fn fade_float(below: Pixel, above: Pixel, opacity: u8) -> Pixel {
+    let Normalized(opacity) = Normalized::from_channel(opacity);
+    let Normalized(alpha_b) = Normalized::from_channel(below[3]);
+    let Normalized(alpha_a) = Normalized::from_channel(above[3]);
+    let alpha_output = alpha_b + (alpha_a - alpha_b) * opacity;
+
+    let mut out = [0; 4];
+    if alpha_output > 0. {
+        for channel in 0..3 {
+            let color_b = below[channel] as f32 * alpha_b;
+            let color_a = above[channel] as f32 * alpha_a;
+            let color = color_b + (color_a - color_b) * opacity;
+
+            out[channel] = (color / alpha_output).round() as u8;
+        }
+    }
+    out[3] = Normalized(alpha_output).to_channel();
+
+    out
+}
 ///
 /// https://www.w3.org/TR/compositing-1/#simplealphacompositing
 /// `Cs = (1 - αb) x Cs + αb x B(Cb, Cs)`
@@ -31,41 +112,106 @@ pub(crate) fn apply_opacity(pixel: &mut Pixel, opacity: u8) {
 /// `Co = co / αo`
 ///
 /// *The backdrop is the content behind the element and is what the element is composited with. This means that the backdrop is the result of compositing all previous elements.
+///
+/// Uses the clamping rules of 8-bit documents, see `blend_pixels_at_depth`.
 pub(crate) fn blend_pixels(top: Pixel, bottom: Pixel, blend_mode: BlendMode, out: &mut Pixel) {
+    blend_pixels_at_depth(top, bottom, blend_mode, PsdDepth::Eight, out)
+}
+
+/// Blend two pixels the way that Photoshop does for a document of the given depth.
+///
+/// Photoshop clips the results of some blend modes differently depending on the depth:
+///
+/// - In 1, 8 and 16-bit documents every blend mode's result is clipped to [0, 1], so `Subtract`
+///   can't go below black and `Divide` can't go above white.
+/// - 32-bit documents are high dynamic range, so `Subtract` and `Divide` keep values outside of
+///   [0, 1] while compositing. They are only clipped when converting the final pixel to 8 bits.
+///
+/// See `subtract` and `divide`.
+///
+/// With the `integer-compositor` cargo feature the pixels are blended without floats, see
+/// `integer_blend`. Both compositors panic on the blend modes that aren't `is_supported`, see
+/// `RenderPipeline::unsupported_placeholder` for rendering the layers that use them anyway.
+pub(crate) fn blend_pixels_at_depth(
+    top: Pixel,
+    bottom: Pixel,
+    blend_mode: BlendMode,
+    depth: PsdDepth,
+    out: &mut Pixel,
+) {
+    // Checked here so that the integer compositor doesn't quietly blend these as normal
+    if !is_supported(blend_mode) {
+        unsupported_blend_mode(blend_mode);
+    }
+
+    #[cfg(feature = "integer-compositor")]
+    crate::integer_blend::blend_pixels_at_depth(top, bottom, blend_mode, depth, out);
+
+    #[cfg(not(feature = "integer-compositor"))]
+    blend_pixels_at_depth_float(top, bottom, blend_mode, depth, out);
+}
+
+/// `blend_pixels_at_depth` in floating point.
+#[cfg_attr(feature = "integer-compositor", allow(dead_code))]
+pub(crate) fn blend_pixels_at_depth_float(
+    top: Pixel,
+    bottom: Pixel,
+    blend_mode: BlendMode,
+    depth: PsdDepth,
+    out: &mut Pixel,
+) {
     // TODO: make some optimizations
-    let alpha_s = top[3] as f32 / 255.;
-    let alpha_b = bottom[3] as f32 / 255.;
+    let [r_s, g_s, b_s, alpha_s] = top.map(|channel| Normalized::from_channel(channel).0);
+    let [r_b, g_b, b_b, alpha_b] = bottom.map(|channel| Normalized::from_channel(channel).0);
     let alpha_output = alpha_s + alpha_b * (1. - alpha_s);
 
-    let (r_s, g_s, b_s) = (
-        top[0] as f32 / 255.,
-        top[1] as f32 / 255.,
-        top[2] as f32 / 255.,
-    );
-    let (r_b, g_b, b_b) = (
-        bottom[0] as f32 / 255.,
-        bottom[1] as f32 / 255.,
-        bottom[2] as f32 / 255.,
-    );
-
-    let blend_f = map_blend_mode(blend_mode);
+    let blend_f = map_blend_mode(blend_mode, depth);
     let (r, g, b) = (
-        composite(r_s, alpha_s, r_b, alpha_b, blend_f) * 255.,
-        composite(g_s, alpha_s, g_b, alpha_b, blend_f) * 255.,
-        composite(b_s, alpha_s, b_b, alpha_b, blend_f) * 255.,
+        composite(r_s, alpha_s, r_b, alpha_b, blend_f) * CHANNEL_MAX,
+        composite(g_s, alpha_s, g_b, alpha_b, blend_f) * CHANNEL_MAX,
+        composite(b_s, alpha_s, b_b, alpha_b, blend_f) * CHANNEL_MAX,
     );
 
     // NOTE: make all assignments _after_ all reads to avoid issues when top or bottom is out
+    // Casting saturates, which clips 32-bit values that ended up outside of [0, 255]
     out[0] = (r.round() / alpha_output) as u8;
     out[1] = (g.round() / alpha_output) as u8;
     out[2] = (b.round() / alpha_output) as u8;
-    out[3] = (255. * alpha_output).round() as u8;
+    out[3] = Normalized(alpha_output).to_channel();
 }
 
 type BlendFunction = dyn Fn(f32, f32) -> f32;
 
-/// Returns blend function for given BlendMode
-fn map_blend_mode(blend_mode: BlendMode) -> &'static BlendFunction {
+/// Panic on a blend mode that we can't blend yet, see `blend_pixels_at_depth`
+#[cold]
+fn unsupported_blend_mode(blend_mode: BlendMode) -> ! {
+    unimplemented!("the {:?} blend mode is not supported yet", blend_mode)
+}
+
+/// Whether `blend_pixels` can blend layers with the blend mode. Pass through only applies to
+/// groups, so it isn't a layer blend mode.
+pub(crate) fn is_supported(blend_mode: BlendMode) -> bool {
+    !matches!(
+        blend_mode,
+        BlendMode::PassThrough
+            | BlendMode::Dissolve
+            | BlendMode::DarkerColor
+            | BlendMode::LighterColor
+            | BlendMode::VividLight
+            | BlendMode::LinearLight
+            | BlendMode::PinLight
+            | BlendMode::HardMix
+            | BlendMode::Hue
+            | BlendMode::Saturation
+            | BlendMode::Color
+            | BlendMode::Luminosity
+    )
+}
+
+/// Returns blend function for given BlendMode, using the clamping rules of the document's depth
+fn map_blend_mode(blend_mode: BlendMode, depth: PsdDepth) -> &'static BlendFunction {
+    let high_dynamic_range = depth == PsdDepth::ThirtyTwo;
+
     // Modes are sorted like in Photoshop UI
     // TODO: make other modes
     match blend_mode {
@@ -96,7 +242,9 @@ fn map_blend_mode(blend_mode: BlendMode) -> &'static BlendFunction {
         // --------------------------------------
         BlendMode::Difference => &difference,
         BlendMode::Exclusion => &exclusion,
+        BlendMode::Subtract if high_dynamic_range => &subtract_unclamped,
         BlendMode::Subtract => &subtract,
+        BlendMode::Divide if high_dynamic_range => &divide_unclamped,
         BlendMode::Divide => &divide,
         // --------------------------------------
         BlendMode::Hue => &hue,
@@ -362,23 +510,47 @@ fn exclusion(color_b: f32, color_s: f32) -> f32 {
 /// https://helpx.adobe.com/photoshop/using/blending-modes.html
 ///
 /// Looks at the color information in each channel and subtracts the blend color from the base color.
+/// In 8- and 16-bit images, any resulting negative values are clipped to zero.
 ///
-/// `B(Cb, Cs) = Cb - Cs`
+/// `B(Cb, Cs) = max(0, Cb - Cs)`
 #[inline(always)]
 fn subtract(color_b: f32, color_s: f32) -> f32 {
-    (color_b - color_s).max(0.)
+    subtract_unclamped(color_b, color_s).max(0.)
+}
+
+/// `subtract` for 32-bit documents, where negative values are kept.
+///
+/// `B(Cb, Cs) = Cb - Cs`
+#[inline(always)]
+fn subtract_unclamped(color_b: f32, color_s: f32) -> f32 {
+    color_b - color_s
 }
 
 /// https://helpx.adobe.com/photoshop/using/blending-modes.html
 ///
 /// Looks at the color information in each channel and divides the blend color from the base color.
-/// In 8- and 16-bit images, any resulting negative values are clipped to zero.
+/// In 8- and 16-bit images the result is clipped to white.
 ///
-/// `B(Cb, Cs) = Cb / Cs`
+/// `B(Cb, Cs) = min(1, Cb / Cs)`
 #[inline(always)]
 fn divide(color_b: f32, color_s: f32) -> f32 {
+    divide_unclamped(color_b, color_s).min(1.)
+}
+
+/// `divide` for 32-bit documents, where values brighter than white are kept.
+///
+/// Dividing by black gives white (or black when the base is also black) at every depth, since
+/// there is no brightest value to use in a 32-bit document.
+///
+/// `B(Cb, Cs) = Cb / Cs`
+#[inline(always)]
+fn divide_unclamped(color_b: f32, color_s: f32) -> f32 {
     if color_s == 0. {
-        color_b
+        if color_b == 0. {
+            0.
+        } else {
+            1.
+        }
     } else {
         color_b / color_s
     }
@@ -426,3 +598,101 @@ fn composite(
     let cb = color_b * alpha_b;
     cs + cb * (1. - alpha_s)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Verify that applying opacity rounds to the nearest alpha and leaves the color alone.
+    #[test]
+    fn opacity() {
+        let mut pixel = [10, 20, 30, 255];
+        apply_opacity(&mut pixel, 128);
+        assert_eq!(pixel, [10, 20, 30, 128]);
+
+        let mut rgba = [1, 2, 3, 255, 4, 5, 6, 100, 7, 8, 9, 0];
+        apply_opacity_buffer(&mut rgba, 51);
+        assert_eq!(rgba, [1, 2, 3, 51, 4, 5, 6, 20, 7, 8, 9, 0]);
+
+        for alpha in 0..=255 {
+            assert_eq!(multiply_alpha(alpha, 255), alpha);
+            assert_eq!(multiply_alpha(alpha, 0), 0);
+        }
+    }
+
+    /// Every channel value survives being normalized and converted back, and values outside of
+    /// 0.0 to 1.0 are clipped.
+    #[test]
+    fn normalized_channels() {
+        for channel in 0..=255 {
+            assert_eq!(Normalized::from_channel(channel).to_channel(), channel);
+        }
+
+        assert_eq!(Normalized::from_channel(255), Normalized(1.));
+        assert_eq!(Normalized(1.5).to_channel(), 255);
+        assert_eq!(Normalized(-0.5).to_channel(), 0);
+    }
+
+    /// Verify that the float and the integer compositors both panic on the blend modes that we
+    /// don't support, instead of one of them blending them as normal.
+    #[test]
+    fn unsupported_blend_modes_panic() {
+        let blend_modes = [BlendMode::Dissolve, BlendMode::VividLight, BlendMode::Hue];
+
+        for blend_mode in blend_modes.iter() {
+            assert!(!is_supported(*blend_mode));
+
+            let blended = std::panic::catch_unwind(|| {
+                let mut out = [0; 4];
+                blend_pixels_at_depth([1; 4], [2; 4], *blend_mode, PsdDepth::Eight, &mut out);
+            });
+            assert!(blended.is_err(), "{:?}", blend_mode);
+        }
+    }
+
+    /// Verify that 32-bit documents keep the out of range results of `Subtract` and `Divide`
+    /// while compositing, whereas other depths clip them first.
+    #[test]
+    fn depth_dependent_clamping() {
+        let blend = |top: Pixel, bottom: Pixel, blend_mode: BlendMode, depth: PsdDepth| {
+            let mut out = [0; 4];
+            blend_pixels_at_depth(top, bottom, blend_mode, depth, &mut out);
+            out
+        };
+
+        // Subtracting white from grey clips to black, or goes below black in 32-bit
+        let (top, bottom) = ([255, 255, 255, 128], [128, 128, 128, 255]);
+        assert_eq!(
+            blend(top, bottom, BlendMode::Subtract, PsdDepth::Eight),
+            [64, 64, 64, 255]
+        );
+        assert_eq!(
+            blend(top, bottom, BlendMode::Subtract, PsdDepth::Sixteen),
+            [64, 64, 64, 255]
+        );
+        assert_eq!(
+            blend(top, bottom, BlendMode::Subtract, PsdDepth::ThirtyTwo),
+            [0, 0, 0, 255]
+        );
+
+        // Dividing grey by dark grey clips to white, or goes above white in 32-bit
+        let (top, bottom) = ([64, 64, 64, 128], [128, 128, 128, 255]);
+        assert_eq!(
+            blend(top, bottom, BlendMode::Divide, PsdDepth::Eight),
+            [192, 192, 192, 255]
+        );
+        assert_eq!(
+            blend(top, bottom, BlendMode::Divide, PsdDepth::ThirtyTwo),
+            [255, 255, 255, 255]
+        );
+
+        // Dividing by black gives white at every depth
+        let (top, bottom) = ([0, 0, 0, 255], [128, 0, 128, 255]);
+        for depth in [PsdDepth::Eight, PsdDepth::ThirtyTwo].iter() {
+            assert_eq!(
+                blend(top, bottom, BlendMode::Divide, *depth),
+                [255, 0, 255, 255]
+            );
+        }
+    }
+}
@@ -0,0 +1,107 @@
+//! A line based text form of a PSD that stays the same until the document changes, meant to be
+//! committed next to design files so that changes to them show up in diffs.
+
+use std::fmt::Write;
+
+use crate::channel_integrity::{crc32, decode_channel};
+use crate::geometry::json_string;
+use crate::layer_tree::{layer_tree, LayerTreeNode};
+use crate::sections::layer_and_mask_information_section::layer::LayerProperties;
+use crate::{Psd, PsdGroup, PsdLayer};
+
+/// The canonical summary of a PSD, see [`Psd::canonical_summary`].
+///
+/// [`Psd::canonical_summary`]: crate::Psd::canonical_summary
+pub(crate) fn canonical_summary(psd: &Psd) -> String {
+    let mut summary = String::new();
+
+    writeln!(
+        summary,
+        "document {}x{} {:?} {:?} layers={} groups={} composite_crc32={:08x}",
+        psd.width(),
+        psd.height(),
+        psd.color_mode(),
+        psd.depth(),
+        psd.layers().len(),
+        psd.groups().len(),
+        crc32(0, &psd.final_image_rgba()),
+    )
+    .unwrap();
+
+    for entry in layer_tree(psd) {
+        match entry.node {
+            LayerTreeNode::Group(group) => write_group(&mut summary, &entry.path, group),
+            LayerTreeNode::Layer(layer) => write_layer(&mut summary, &entry.path, layer, psd),
+        }
+    }
+
+    summary
+}
+
+fn write_group(summary: &mut String, path: &str, group: &PsdGroup) {
+    write!(summary, "group {}", json_string(path)).unwrap();
+    write_properties(summary, group);
+    writeln!(
+        summary,
+        " blend={:?} opacity={}",
+        group.blend_mode(),
+        group.opacity()
+    )
+    .unwrap();
+}
+
+fn write_layer(summary: &mut String, path: &str, layer: &PsdLayer, psd: &Psd) {
+    write!(summary, "layer {}", json_string(path)).unwrap();
+    if let Some(id) = layer.layer_id() {
+        write!(summary, " id={}", id).unwrap();
+    }
+    write!(
+        summary,
+        " size={}x{} at={},{}",
+        layer.width(),
+        layer.height(),
+        layer.layer_left(),
+        layer.layer_top()
+    )
+    .unwrap();
+    write_properties(summary, layer);
+    writeln!(
+        summary,
+        " blend={:?} opacity={} fill={} clipping={} pixels_crc32={}",
+        layer.blend_mode(),
+        layer.opacity(),
+        layer.fill_opacity(),
+        layer.is_clipping_mask(),
+        pixels_crc32(layer, psd)
+    )
+    .unwrap();
+}
+
+/// The properties that groups and layers share, other than the blend mode and opacity which
+/// groups read from a different record.
+fn write_properties(summary: &mut String, properties: &LayerProperties) {
+    write!(summary, " visible={}", properties.visible()).unwrap();
+    if properties.mask().is_some() {
+        summary.push_str(" mask");
+    }
+}
+
+/// The CRC-32 of every decoded channel of the layer, including its masks, in channel order, or
+/// `corrupt` if a channel doesn't decode.
+fn pixels_crc32(layer: &PsdLayer, psd: &Psd) -> String {
+    let mut channels: Vec<_> = layer.channels.iter().collect();
+    channels.sort_by_key(|(kind, _)| **kind);
+
+    let mut crc = 0;
+    for (kind, channel_bytes) in channels {
+        match decode_channel(layer, *kind, channel_bytes, psd.depth()) {
+            Ok(samples) => {
+                crc = crc32(crc, &(*kind as i16).to_be_bytes());
+                crc = crc32(crc, &samples);
+            }
+            Err(_) => return "corrupt".to_string(),
+        }
+    }
+
+    format!("{:08x}", crc)
+}
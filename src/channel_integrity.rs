@@ -0,0 +1,393 @@
+//! Decoding every layer channel up front to find the ones whose compressed data is corrupt, and
+//! checksumming the decoded channels so that they can be compared across imports.
+
+use thiserror::Error;
+
+use crate::psd_channel::zip_decompress;
+use crate::sections::image_data_section::ChannelBytes;
+use crate::sections::PsdCursor;
+use crate::{Psd, PsdChannelCompression, PsdChannelKind, PsdDepth, PsdLayer};
+
+/// The result of decoding every layer channel of a PSD.
+///
+/// Created by [`Psd::verify_layer_channels`] and [`Psd::verify_layer_channels_with_checksums`].
+///
+/// [`Psd::verify_layer_channels`]: crate::Psd::verify_layer_channels
+/// [`Psd::verify_layer_channels_with_checksums`]: crate::Psd::verify_layer_channels_with_checksums
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChannelReport {
+    pub(crate) checksums: Vec<ChannelChecksum>,
+    pub(crate) corrupt_channels: Vec<CorruptChannel>,
+}
+
+/// The checksum of a layer channel that decoded without any problems.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct ChannelChecksum {
+    pub(crate) layer_idx: usize,
+    pub(crate) layer_name: String,
+    pub(crate) channel: PsdChannelKind,
+    pub(crate) len: usize,
+    pub(crate) crc32: u32,
+}
+
+/// A layer channel whose data couldn't be decoded.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct CorruptChannel {
+    pub(crate) layer_idx: usize,
+    pub(crate) layer_name: String,
+    pub(crate) channel: PsdChannelKind,
+    pub(crate) compression: PsdChannelCompression,
+    pub(crate) corruption: ChannelCorruption,
+}
+
+/// What is wrong with the data of a corrupt layer channel.
+///
+/// ZIP compressed channels are decompressed when the PSD is parsed, so a zlib stream that is
+/// corrupt is a [`PsdLayerError::InvalidZipData`] parse error instead, which names the layer and
+/// the channel.
+///
+/// [`PsdLayerError::InvalidZipData`]: crate::PsdLayerError::InvalidZipData
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Error)]
+pub enum ChannelCorruption {
+    /// The compressed lengths of the rows don't add up to the length of the RLE data
+    #[error("The rows' compressed lengths add up to {expected} bytes, but there are {actual} bytes of RLE data")]
+    RleByteCounts {
+        /// The sum of the rows' compressed lengths
+        expected: usize,
+        /// The length of the RLE data
+        actual: usize,
+    },
+    /// An RLE packet runs past the end of its row's compressed bytes
+    #[error("An RLE packet in row {row} runs past the end of the row")]
+    TruncatedRlePacket {
+        /// The row, from the top of the channel
+        row: usize,
+    },
+    /// A row decodes to more or fewer bytes than the channel is wide
+    #[error("Row {row} decodes to {actual} bytes instead of {expected}")]
+    RowLength {
+        /// The row, from the top of the channel
+        row: usize,
+        /// The number of bytes in each row of the channel
+        expected: usize,
+        /// The number of bytes that the row decodes to
+        actual: usize,
+    },
+    /// The channel decodes to more or fewer bytes than its rectangle holds
+    #[error("The channel decodes to {actual} bytes instead of {expected}")]
+    SampleCount {
+        /// The number of bytes that the channel's rectangle holds
+        expected: usize,
+        /// The number of bytes that the channel decodes to
+        actual: usize,
+    },
+}
+
+impl ChannelReport {
+    /// The checksums of the channels that decoded without any problems, in the order of the
+    /// PSD's layers. Empty unless checksums were requested.
+    pub fn checksums(&self) -> &[ChannelChecksum] {
+        &self.checksums
+    }
+
+    /// The channels that couldn't be decoded, in the order of the PSD's layers
+    pub fn corrupt_channels(&self) -> &[CorruptChannel] {
+        &self.corrupt_channels
+    }
+
+    /// Whether every channel decoded without any problems
+    pub fn is_ok(&self) -> bool {
+        self.corrupt_channels.is_empty()
+    }
+}
+
+impl ChannelChecksum {
+    /// The index of the channel's layer in [`Psd::layers`]
+    ///
+    /// [`Psd::layers`]: crate::Psd::layers
+    pub fn layer_idx(&self) -> usize {
+        self.layer_idx
+    }
+
+    /// The name of the channel's layer
+    pub fn layer_name(&self) -> &str {
+        &self.layer_name
+    }
+
+    /// The kind of channel
+    pub fn channel(&self) -> PsdChannelKind {
+        self.channel
+    }
+
+    /// The number of bytes that the channel decodes to, at the PSD's bit depth
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the channel doesn't have any samples, such as the channels of empty layers
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The CRC-32 of the bytes that the channel decodes to, at the PSD's bit depth, the same
+    /// checksum that zip files and PNGs use
+    pub fn crc32(&self) -> u32 {
+        self.crc32
+    }
+}
+
+impl CorruptChannel {
+    /// The index of the channel's layer in [`Psd::layers`]
+    ///
+    /// [`Psd::layers`]: crate::Psd::layers
+    pub fn layer_idx(&self) -> usize {
+        self.layer_idx
+    }
+
+    /// The name of the channel's layer
+    pub fn layer_name(&self) -> &str {
+        &self.layer_name
+    }
+
+    /// The kind of channel
+    pub fn channel(&self) -> PsdChannelKind {
+        self.channel
+    }
+
+    /// How the channel's data is compressed
+    pub fn compression(&self) -> PsdChannelCompression {
+        self.compression
+    }
+
+    /// What is wrong with the channel's data
+    pub fn corruption(&self) -> ChannelCorruption {
+        self.corruption
+    }
+}
+
+/// Decode every channel of every layer, checksumming the ones that decode when `checksums` is
+/// true.
+pub(crate) fn verify_layer_channels(psd: &Psd, checksums: bool) -> ChannelReport {
+    let mut report = ChannelReport::default();
+    let depth = psd.depth();
+
+    for (layer_idx, layer) in psd.layers().iter().enumerate() {
+        let mut channels: Vec<_> = layer.channels.iter().collect();
+        channels.sort_by_key(|(kind, _)| **kind);
+
+        for (kind, channel_bytes) in channels {
+            let compression = match layer.compression(*kind) {
+                Ok(compression) => compression,
+                Err(_) => continue,
+            };
+
+            match decode_channel(layer, *kind, channel_bytes, depth) {
+                Ok(samples) => {
+                    if checksums {
+                        report.checksums.push(ChannelChecksum {
+                            layer_idx,
+                            layer_name: layer.name().to_string(),
+                            channel: *kind,
+                            len: samples.len(),
+                            crc32: crc32(0, &samples),
+                        });
+                    }
+                }
+                Err(corruption) => report.corrupt_channels.push(CorruptChannel {
+                    layer_idx,
+                    layer_name: layer.name().to_string(),
+                    channel: *kind,
+                    compression,
+                    corruption,
+                }),
+            }
+        }
+    }
+
+    report
+}
+
+/// Decode a channel into its samples at the PSD's bit depth, making sure that every row is
+/// whole.
+pub(crate) fn decode_channel(
+    layer: &PsdLayer,
+    kind: PsdChannelKind,
+    channel_bytes: &ChannelBytes,
+    depth: PsdDepth,
+) -> Result<Vec<u8>, ChannelCorruption> {
+    let (width, height) = channel_size(layer, kind);
+    let row_len = match depth {
+        PsdDepth::One => width.div_ceil(8),
+        PsdDepth::Eight => width,
+        PsdDepth::Sixteen => width * 2,
+        PsdDepth::ThirtyTwo => width * 4,
+    };
+
+    let samples = match (layer.zip_compressed_channels.get(&kind), channel_bytes) {
+        // The channel was checked when it was decompressed while parsing, but it was mapped down
+        // to 8 bits, so we decompress it again to checksum the original samples
+        (Some((compression, zip_data)), _) => {
+            zip_decompress(zip_data, *compression, width, depth).unwrap_or_default()
+        }
+        (None, ChannelBytes::RawData(bytes)) => bytes.to_vec(),
+        (None, ChannelBytes::RleCompressed(bytes)) => {
+            let byte_counts = layer
+                .scanline_byte_counts
+                .get(&kind)
+                .map(|counts| counts.as_slice())
+                .unwrap_or(&[]);
+            decode_rle_rows(bytes, byte_counts, row_len)?
+        }
+    };
+
+    let expected = row_len * height;
+    if samples.len() != expected {
+        return Err(ChannelCorruption::SampleCount {
+            expected,
+            actual: samples.len(),
+        });
+    }
+
+    Ok(samples)
+}
+
+/// The (width, height) of a channel. Masks have their own rectangles.
+fn channel_size(layer: &PsdLayer, kind: PsdChannelKind) -> (usize, usize) {
+    let mask = layer.mask();
+    let rect = match kind {
+        PsdChannelKind::UserSuppliedLayerMask => mask.map(|mask| mask.rect()),
+        PsdChannelKind::RealUserSuppliedLayerMask => mask
+            .and_then(|mask| mask.real_user_mask())
+            .map(|real| real.rect()),
+        _ => None,
+    };
+
+    match rect {
+        Some(rect) => (rect.width() as usize, rect.height() as usize),
+        None => (layer.width() as usize, layer.height() as usize),
+    }
+}
+
+/// Decompress the rows of an RLE compressed channel one at a time, so that a problem can be
+/// traced back to its row.
+fn decode_rle_rows(
+    bytes: &[u8],
+    byte_counts: &[u32],
+    row_len: usize,
+) -> Result<Vec<u8>, ChannelCorruption> {
+    let total: usize = byte_counts.iter().map(|count| *count as usize).sum();
+    if total != bytes.len() {
+        return Err(ChannelCorruption::RleByteCounts {
+            expected: total,
+            actual: bytes.len(),
+        });
+    }
+
+    let mut samples = Vec::with_capacity(row_len * byte_counts.len());
+    let mut row_start = 0;
+
+    for (row, byte_count) in byte_counts.iter().enumerate() {
+        let row_end = row_start + *byte_count as usize;
+        let row_samples_start = samples.len();
+
+        decode_rle_row(&bytes[row_start..row_end], &mut samples)
+            .ok_or(ChannelCorruption::TruncatedRlePacket { row })?;
+
+        let actual = samples.len() - row_samples_start;
+        if actual != row_len {
+            return Err(ChannelCorruption::RowLength {
+                row,
+                expected: row_len,
+                actual,
+            });
+        }
+
+        row_start = row_end;
+    }
+
+    Ok(samples)
+}
+
+/// Decompress a single PackBits compressed row, returning `None` if a packet runs past the end of
+/// the row.
+fn decode_rle_row(row: &[u8], samples: &mut Vec<u8>) -> Option<()> {
+    let mut cursor = PsdCursor::new(row);
+    let len = row.len() as u64;
+
+    while cursor.position() < len {
+        let header = cursor.read_i8() as i16;
+
+        if header == -128 {
+            continue;
+        } else if header >= 0 {
+            let bytes_to_read = 1 + header as u64;
+            samples.extend_from_slice(cursor.read_checked(bytes_to_read)?);
+        } else {
+            let byte = cursor.read_checked(1)?[0];
+            samples.resize(samples.len() + (1 - header) as usize, byte);
+        }
+    }
+
+    Some(())
+}
+
+/// Continue the CRC-32 `crc` of some bytes with more bytes. Start with a `crc` of 0.
+pub(crate) fn crc32(crc: u32, bytes: &[u8]) -> u32 {
+    let mut crc = !crc;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                0xEDB8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+        }
+    }
+
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Verify the CRC of the empty IEND chunk that ends every PNG, both at once and in parts.
+    #[test]
+    fn crc_of_iend() {
+        assert_eq!(crc32(0, b"IEND"), 0xAE42_6082);
+        assert_eq!(crc32(crc32(0, b"IE"), b"ND"), 0xAE42_6082);
+    }
+
+    /// Verify that rows that are cut short or that decode to the wrong length are found.
+    #[test]
+    fn rle_rows() {
+        // A run of three 7s, then a literal 1, 2, 3
+        let rows = [254, 7, 2, 1, 2, 3];
+        assert_eq!(
+            decode_rle_rows(&rows, &[2, 4], 3),
+            Ok(vec![7, 7, 7, 1, 2, 3])
+        );
+
+        assert_eq!(
+            decode_rle_rows(&rows, &[2, 3], 3),
+            Err(ChannelCorruption::RleByteCounts {
+                expected: 5,
+                actual: 6
+            })
+        );
+        assert_eq!(
+            decode_rle_rows(&rows, &[2, 4], 4),
+            Err(ChannelCorruption::RowLength {
+                row: 0,
+                expected: 4,
+                actual: 3
+            })
+        );
+        // The literal packet at the end of the first row is missing its bytes
+        assert_eq!(
+            decode_rle_rows(&rows, &[3, 3], 3),
+            Err(ChannelCorruption::TruncatedRlePacket { row: 0 })
+        );
+    }
+}
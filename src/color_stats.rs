@@ -0,0 +1,214 @@
+use crate::psd_channel::PsdChannelKind;
+use crate::PsdLayer;
+
+/// The number of pixels with each value, for each of a layer's channels.
+///
+/// Created by [`PsdLayer::histogram`].
+///
+/// [`PsdLayer::histogram`]: crate::PsdLayer::histogram
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct ColorHistogram {
+    pub(crate) red: [u32; 256],
+    pub(crate) green: [u32; 256],
+    pub(crate) blue: [u32; 256],
+    pub(crate) alpha: [u32; 256],
+}
+
+impl ColorHistogram {
+    /// `red()[value]` is the number of visible pixels whose red is `value`
+    pub fn red(&self) -> &[u32; 256] {
+        &self.red
+    }
+
+    /// `green()[value]` is the number of visible pixels whose green is `value`
+    pub fn green(&self) -> &[u32; 256] {
+        &self.green
+    }
+
+    /// `blue()[value]` is the number of visible pixels whose blue is `value`
+    pub fn blue(&self) -> &[u32; 256] {
+        &self.blue
+    }
+
+    /// `alpha()[value]` is the number of pixels whose alpha is `value`, including fully
+    /// transparent pixels
+    pub fn alpha(&self) -> &[u32; 256] {
+        &self.alpha
+    }
+}
+
+/// The lowest and highest alpha of a layer's pixels, which tells whether the layer is fully
+/// opaque, fully transparent or a mix of both.
+///
+/// Created by [`PsdLayer::alpha_summary`].
+///
+/// [`PsdLayer::alpha_summary`]: crate::PsdLayer::alpha_summary
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct AlphaSummary {
+    pub(crate) min: u8,
+    pub(crate) max: u8,
+}
+
+impl AlphaSummary {
+    /// Stream the rows of the layer's transparency channel, without decoding its other channels.
+    ///
+    /// Samples deeper than 8 bits are mapped down to 8 bits, the same way as in the layer's
+    /// RGBA.
+    pub(crate) fn new(layer: &PsdLayer) -> AlphaSummary {
+        let width = layer.width() as usize;
+        let height = layer.height() as usize;
+
+        if width == 0 || height == 0 {
+            return AlphaSummary { min: 0, max: 0 };
+        }
+
+        // Without a transparency channel the layer is opaque
+        if layer
+            .get_channel(PsdChannelKind::TransparencyMask)
+            .is_none()
+        {
+            return AlphaSummary { min: 255, max: 255 };
+        }
+
+        let mut summary = AlphaSummary { min: 255, max: 0 };
+        let mut rows = 0;
+        layer.for_each_eight_bit_row(PsdChannelKind::TransparencyMask, width, |row_idx, row| {
+            if row_idx < height {
+                rows += 1;
+                for alpha in row.iter() {
+                    summary.min = summary.min.min(*alpha);
+                    summary.max = summary.max.max(*alpha);
+                }
+            }
+        });
+
+        // Pixels that are missing from the channel are fully transparent
+        if rows < height {
+            summary.min = 0;
+        }
+
+        summary
+    }
+
+    /// The lowest alpha of any of the layer's pixels
+    pub fn min(&self) -> u8 {
+        self.min
+    }
+
+    /// The highest alpha of any of the layer's pixels
+    pub fn max(&self) -> u8 {
+        self.max
+    }
+
+    /// Whether every pixel is fully opaque
+    pub fn is_opaque(&self) -> bool {
+        self.min == 255
+    }
+
+    /// Whether every pixel is fully transparent, such as in empty layers
+    pub fn is_transparent(&self) -> bool {
+        self.max == 0
+    }
+
+    /// Whether the layer is neither fully opaque nor fully transparent
+    pub fn is_mixed(&self) -> bool {
+        !self.is_opaque() && !self.is_transparent()
+    }
+}
+
+/// The alpha weighted sum of each color channel and the histograms of a layer, gathered
+/// by streaming each channel's rows.
+pub(crate) struct LayerColorStats {
+    pub(crate) weighted_sums: [u64; 3],
+    pub(crate) alpha_sum: u64,
+    pub(crate) histogram: ColorHistogram,
+}
+
+impl LayerColorStats {
+    pub(crate) fn new(layer: &PsdLayer) -> LayerColorStats {
+        let width = layer.width() as usize;
+        let height = layer.height() as usize;
+        let pixel_count = width * height;
+
+        let mut histogram = ColorHistogram {
+            red: [0; 256],
+            green: [0; 256],
+            blue: [0; 256],
+            alpha: [0; 256],
+        };
+
+        // Without a transparency channel the layer is opaque
+        let alpha = match layer.get_channel(PsdChannelKind::TransparencyMask) {
+            Some(_) => {
+                let mut alpha = vec![0; pixel_count];
+                layer.for_each_eight_bit_row(
+                    PsdChannelKind::TransparencyMask,
+                    width,
+                    |row_idx, row| {
+                        if row_idx < height {
+                            alpha[row_idx * width..(row_idx + 1) * width].copy_from_slice(row);
+                        }
+                    },
+                );
+                alpha
+            }
+            None => vec![255; pixel_count],
+        };
+        for value in alpha.iter() {
+            histogram.alpha[*value as usize] += 1;
+        }
+        let alpha_sum = alpha.iter().map(|value| *value as u64).sum();
+
+        let mut weighted_sums = [0; 3];
+        let channels = [
+            (PsdChannelKind::Red, &mut histogram.red),
+            (PsdChannelKind::Green, &mut histogram.green),
+            (PsdChannelKind::Blue, &mut histogram.blue),
+        ];
+
+        for ((kind, channel_histogram), weighted_sum) in
+            IntoIterator::into_iter(channels).zip(weighted_sums.iter_mut())
+        {
+            // Single channel layers are grey, see `IntoRgba::generate_rgba`
+            let kind = match layer.get_channel(kind) {
+                Some(_) => kind,
+                None => PsdChannelKind::Red,
+            };
+
+            layer.for_each_eight_bit_row(kind, width, |row_idx, row| {
+                if row_idx >= height {
+                    return;
+                }
+                let row_alpha = &alpha[row_idx * width..(row_idx + 1) * width];
+
+                for (value, alpha) in row.iter().zip(row_alpha.iter()) {
+                    if *alpha > 0 {
+                        channel_histogram[*value as usize] += 1;
+                        *weighted_sum += *value as u64 * *alpha as u64;
+                    }
+                }
+            });
+        }
+
+        LayerColorStats {
+            weighted_sums,
+            alpha_sum,
+            histogram,
+        }
+    }
+
+    /// The alpha weighted average [R, G, B], or `None` if every pixel is transparent.
+    pub(crate) fn average_color(&self) -> Option<[u8; 3]> {
+        if self.alpha_sum == 0 {
+            return None;
+        }
+
+        let average = |sum: u64| ((sum as f64 / self.alpha_sum as f64).round()) as u8;
+
+        Some([
+            average(self.weighted_sums[0]),
+            average(self.weighted_sums[1]),
+            average(self.weighted_sums[2]),
+        ])
+    }
+}
@@ -0,0 +1,179 @@
+//! Comparing the final image that is stored in a PSD with the result of flattening its layers.
+
+use crate::{CropRect, Psd, PsdError};
+
+/// How the final image that is stored in a PSD differs from flattening its layers.
+///
+/// Created by [`Psd::verify_composite`].
+///
+/// [`Psd::verify_composite`]: crate::Psd::verify_composite
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompositeDiff {
+    pub(crate) threshold: u8,
+    pub(crate) pixel_count: usize,
+    pub(crate) mismatched_pixels: usize,
+    pub(crate) max_difference: u8,
+    pub(crate) mean_difference: f64,
+    pub(crate) mismatch_bounds: Option<CropRect>,
+}
+
+impl CompositeDiff {
+    /// The largest difference of a channel of a pixel that doesn't count as a mismatch
+    pub fn threshold(&self) -> u8 {
+        self.threshold
+    }
+
+    /// The number of pixels that were compared
+    pub fn pixel_count(&self) -> usize {
+        self.pixel_count
+    }
+
+    /// The number of pixels with a channel that differs by more than the threshold
+    pub fn mismatched_pixels(&self) -> usize {
+        self.mismatched_pixels
+    }
+
+    /// The fraction of the pixels that are mismatched, from 0 to 1
+    pub fn mismatch_ratio(&self) -> f64 {
+        if self.pixel_count == 0 {
+            return 0.;
+        }
+
+        self.mismatched_pixels as f64 / self.pixel_count as f64
+    }
+
+    /// The largest difference of any channel of any pixel
+    pub fn max_difference(&self) -> u8 {
+        self.max_difference
+    }
+
+    /// The average difference of every channel of every pixel
+    pub fn mean_difference(&self) -> f64 {
+        self.mean_difference
+    }
+
+    /// The smallest rectangle that holds every mismatched pixel, or `None` if there aren't any
+    pub fn mismatch_bounds(&self) -> Option<CropRect> {
+        self.mismatch_bounds
+    }
+
+    /// Whether none of the pixels are mismatched
+    pub fn is_match(&self) -> bool {
+        self.mismatched_pixels == 0
+    }
+}
+
+/// Flatten the PSD's visible layers and compare the result with the final image.
+///
+/// Final images without an alpha channel are flattened onto white, the same way that Photoshop
+/// flattens them. Pixels that are fully transparent in both images match, whatever their colors.
+pub(crate) fn verify_composite(psd: &Psd, threshold: u8) -> Result<CompositeDiff, PsdError> {
+    let mut render = psd.render().visible_only();
+    if psd.image_data_section.alpha.is_none() {
+        render = render.background([255; 4]);
+    }
+
+    let flattened = render.to_rgba()?;
+    let stored = psd.final_image_rgba();
+
+    Ok(diff(&stored, &flattened, psd.width(), threshold))
+}
+
+fn diff(stored: &[u8], flattened: &[u8], width: u32, threshold: u8) -> CompositeDiff {
+    let width = width.max(1) as usize;
+
+    let mut mismatched_pixels = 0;
+    let mut max_difference = 0;
+    let mut total_difference = 0u64;
+    // Left, top, right and bottom, inclusive
+    let mut bounds: Option<(usize, usize, usize, usize)> = None;
+
+    let pixels = stored.chunks_exact(4).zip(flattened.chunks_exact(4));
+    let pixel_count = pixels.len();
+
+    for (idx, (stored, flattened)) in pixels.enumerate() {
+        if stored[3] == 0 && flattened[3] == 0 {
+            continue;
+        }
+
+        let difference = stored
+            .iter()
+            .zip(flattened.iter())
+            .map(|(a, b)| (*a as i16 - *b as i16).unsigned_abs() as u8);
+        let mut pixel_max = 0;
+        for channel in difference {
+            total_difference += channel as u64;
+            pixel_max = pixel_max.max(channel);
+        }
+        max_difference = max_difference.max(pixel_max);
+
+        if pixel_max > threshold {
+            mismatched_pixels += 1;
+
+            let (left, top) = (idx % width, idx / width);
+            bounds = Some(match bounds {
+                Some((l, t, r, b)) => (l.min(left), t.min(top), r.max(left), b.max(top)),
+                None => (left, top, left, top),
+            });
+        }
+    }
+
+    let mean_difference = if pixel_count == 0 {
+        0.
+    } else {
+        total_difference as f64 / (pixel_count * 4) as f64
+    };
+
+    CompositeDiff {
+        threshold,
+        pixel_count,
+        mismatched_pixels,
+        max_difference,
+        mean_difference,
+        mismatch_bounds: bounds.map(|(left, top, right, bottom)| {
+            CropRect::new(
+                left as u32,
+                top as u32,
+                (right - left + 1) as u32,
+                (bottom - top + 1) as u32,
+            )
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 2x2 image whose right column differs by 10 and 2, and whose transparent pixels have
+    /// different colors.
+    #[test]
+    fn diff_pixels() {
+        let stored = [
+            [0, 0, 0, 0],
+            [100, 100, 100, 255],
+            [255, 255, 255, 0],
+            [50, 50, 50, 255],
+        ]
+        .concat();
+        let flattened = [
+            [9, 9, 9, 0],
+            [110, 100, 100, 255],
+            [0, 0, 0, 0],
+            [50, 52, 50, 255],
+        ]
+        .concat();
+
+        let loose = diff(&stored, &flattened, 2, 5);
+        assert_eq!(loose.pixel_count(), 4);
+        assert_eq!(loose.mismatched_pixels(), 1);
+        assert_eq!(loose.max_difference(), 10);
+        assert_eq!(loose.mean_difference(), 12. / 16.);
+        assert_eq!(loose.mismatch_bounds(), Some(CropRect::new(1, 0, 1, 1)));
+
+        let strict = diff(&stored, &flattened, 2, 1);
+        assert_eq!(strict.mismatched_pixels(), 2);
+        assert_eq!(strict.mismatch_ratio(), 0.5);
+        assert_eq!(strict.mismatch_bounds(), Some(CropRect::new(1, 0, 1, 2)));
+    }
+}
@@ -0,0 +1,431 @@
+//! Rendering the layer effects that `RenderPipeline::layer_effects` supports: drop shadows, color
+//! overlays and strokes.
+
+use crate::blend;
+use crate::fills;
+use crate::sections::image_resources_section::RESOURCE_GLOBAL_ANGLE;
+use crate::sections::layer_and_mask_information_section::layer::BlendMode;
+use crate::sections::layer_and_mask_information_section::layer_effects::{
+    ColorOverlay, Shadow, Stroke, StrokePosition,
+};
+use crate::{Psd, PsdLayer};
+
+/// The angle of the global light in new Photoshop documents, in degrees
+const DEFAULT_GLOBAL_ANGLE: f64 = 120.;
+
+/// The RGBA pixels of a layer, spanning the entire PSD, with its masks, supported effects, fill
+/// opacity and opacity applied.
+///
+/// The fill opacity only fades the layer's content. It also fades the color overlay when the
+/// layer blends its interior effects as a group, but never the stroke or the drop shadow.
+///
+/// Returns `None` if the layer doesn't have any enabled effects that we render, so that it can be
+/// rendered like any other layer.
+pub(crate) fn layer_rgba_with_effects(psd: &Psd, layer: &PsdLayer) -> Option<Vec<u8>> {
+    let effects = layer.effects().filter(|effects| effects.enabled())?;
+    let drop_shadow = effects.drop_shadow().filter(|shadow| shadow.enabled());
+    let color_overlay = effects.color_overlay().filter(|overlay| overlay.enabled());
+    let stroke = effects.stroke().filter(|stroke| stroke.enabled());
+    if drop_shadow.is_none() && color_overlay.is_none() && stroke.is_none() {
+        return None;
+    }
+
+    let canvas = Canvas {
+        width: psd.width() as usize,
+        height: psd.height() as usize,
+        scale: effects.scale() / 100.,
+    };
+
+    // Fill layers get their effects applied to their fill
+    let mut rgba = match fills::fill_rgba(psd, layer) {
+        Some(rgba) => rgba,
+        None => layer.rgba(),
+    };
+    if rgba.is_empty() {
+        return None;
+    }
+    layer.apply_masks(&mut rgba);
+
+    // Strokes and shadows follow the shape of the content before its fill opacity fades it
+    let shape = alpha_plane(&rgba);
+    let fill_opacity = layer.fill_opacity();
+
+    match color_overlay {
+        // The overlay is part of the content, so the fill opacity fades it as well
+        Some(overlay) if fill_opacity == 255 || layer.blend_interior_elements() => {
+            apply_color_overlay(&mut rgba, overlay);
+            blend::apply_opacity_buffer(&mut rgba, fill_opacity);
+        }
+        Some(overlay) => {
+            let mut overlay_rgba = rgba.clone();
+            apply_color_overlay(&mut overlay_rgba, overlay);
+            let overlay_rgba = colorize_pixels(&overlay_rgba, &shape, overlay.opacity());
+
+            blend::apply_opacity_buffer(&mut rgba, fill_opacity);
+            rgba = over(&overlay_rgba, &rgba);
+        }
+        None => blend::apply_opacity_buffer(&mut rgba, fill_opacity),
+    }
+
+    let mut shadow_shape = shape.clone();
+    if let Some(stroke) = stroke {
+        rgba = canvas.apply_stroke(&rgba, &shape, stroke);
+        if fill_opacity != 255 {
+            let opaque = colorize(&shape, [0; 3], 255);
+            shadow_shape = alpha_plane(&canvas.apply_stroke(&opaque, &shape, stroke));
+        } else {
+            shadow_shape = alpha_plane(&rgba);
+        }
+    }
+    if let Some(shadow) = drop_shadow {
+        let global_angle = psd
+            .resource_block_data(RESOURCE_GLOBAL_ANGLE)
+            .filter(|data| data.len() >= 4)
+            .map(|data| i32::from_be_bytes([data[0], data[1], data[2], data[3]]) as f64)
+            .unwrap_or(DEFAULT_GLOBAL_ANGLE);
+
+        rgba = canvas.apply_drop_shadow(&rgba, &shadow_shape, shadow, global_angle);
+    }
+
+    blend::apply_opacity_buffer(&mut rgba, layer.opacity);
+
+    Some(rgba)
+}
+
+/// Fill the layer's pixels with the overlay's color, keeping their alpha.
+fn apply_color_overlay(rgba: &mut [u8], overlay: &ColorOverlay) {
+    let [r, g, b] = overlay.color();
+    let color = [r, g, b, overlay.opacity()];
+    let blend_mode = supported_blend_mode(overlay.blend_mode());
+
+    for pixel in rgba.chunks_exact_mut(4) {
+        if pixel[3] == 0 {
+            continue;
+        }
+
+        let mut blended = [0; 4];
+        blend::blend_pixels(
+            color,
+            [pixel[0], pixel[1], pixel[2], 255],
+            blend_mode,
+            &mut blended,
+        );
+        pixel[..3].copy_from_slice(&blended[..3]);
+    }
+}
+
+/// Effects are blended with the blend modes that we support, falling back to normal.
+fn supported_blend_mode(blend_mode: BlendMode) -> BlendMode {
+    if blend::is_supported(blend_mode) {
+        blend_mode
+    } else {
+        BlendMode::Normal
+    }
+}
+
+/// The size of the PSD that effects are rendered into
+struct Canvas {
+    width: usize,
+    height: usize,
+    /// How much the sizes and distances of the effects are scaled by, see `LayerEffects::scale`
+    scale: f64,
+}
+
+impl Canvas {
+    /// Draw a stroke around the edges of the `alpha` shape of the layer's content.
+    ///
+    /// Strokes outside of the edges are drawn below the content, the others above it. Strokes
+    /// are always blended normally.
+    fn apply_stroke(&self, rgba: &[u8], alpha: &[u8], stroke: &Stroke) -> Vec<u8> {
+        let size = stroke.size() * self.scale;
+
+        let coverage = match stroke.position() {
+            StrokePosition::Outside => subtract(&self.dilate(alpha, size), alpha),
+            StrokePosition::Inside => subtract(alpha, &self.erode(alpha, size)),
+            StrokePosition::Center => subtract(
+                &self.dilate(alpha, size / 2.),
+                &self.erode(alpha, size / 2.),
+            ),
+        };
+        let stroke_rgba = colorize(&coverage, stroke.color(), stroke.opacity());
+
+        match stroke.position() {
+            StrokePosition::Outside => over(rgba, &stroke_rgba),
+            StrokePosition::Inside | StrokePosition::Center => over(&stroke_rgba, rgba),
+        }
+    }
+
+    /// Draw a shadow of the `alpha` shape of the layer's content below it. The shadow is always
+    /// blended normally.
+    fn apply_drop_shadow(
+        &self,
+        rgba: &[u8],
+        alpha: &[u8],
+        shadow: &Shadow,
+        global_angle: f64,
+    ) -> Vec<u8> {
+        let size = shadow.size() * self.scale;
+        let distance = shadow.distance() * self.scale;
+        // The spread hardens part of the shadow's size, the rest of the size is blurred
+        let spread = size * shadow.spread() / 100.;
+
+        // The angle is where the light comes from, so the shadow falls the opposite way
+        let angle = if shadow.use_global_light() {
+            global_angle
+        } else {
+            shadow.angle()
+        }
+        .to_radians();
+        let offset_x = (-distance * angle.cos()).round() as isize;
+        let offset_y = (distance * angle.sin()).round() as isize;
+
+        let shape = self.dilate(alpha, spread);
+        let shape = self.blur(&shape, size - spread);
+        let shape = self.offset(&shape, offset_x, offset_y);
+
+        over(rgba, &colorize(&shape, shadow.color(), shadow.opacity()))
+    }
+
+    /// The highest alpha within `radius` pixels of each pixel
+    fn dilate(&self, alpha: &[u8], radius: f64) -> Vec<u8> {
+        self.max_filter(alpha, radius, 0)
+    }
+
+    /// The lowest alpha within `radius` pixels of each pixel. Pixels outside of the canvas are
+    /// transparent, so shapes shrink away from the edges of the canvas too.
+    fn erode(&self, alpha: &[u8], radius: f64) -> Vec<u8> {
+        let inverted: Vec<u8> = alpha.iter().map(|alpha| 255 - alpha).collect();
+
+        self.max_filter(&inverted, radius, 255)
+            .iter()
+            .map(|alpha| 255 - alpha)
+            .collect()
+    }
+
+    /// The highest value within `radius` pixels of each pixel, using `outside` for the pixels
+    /// outside of the canvas
+    fn max_filter(&self, values: &[u8], radius: f64, outside: u8) -> Vec<u8> {
+        if radius < 0.5 {
+            return values.to_vec();
+        }
+
+        let reach = radius.round() as isize;
+        let offsets: Vec<(isize, isize)> = (-reach..=reach)
+            .flat_map(|y| (-reach..=reach).map(move |x| (x, y)))
+            .filter(|(x, y)| ((x * x + y * y) as f64).sqrt() <= radius)
+            .collect();
+
+        let mut filtered = vec![0; values.len()];
+        for y in 0..self.height as isize {
+            for x in 0..self.width as isize {
+                filtered[y as usize * self.width + x as usize] = offsets
+                    .iter()
+                    .map(|(offset_x, offset_y)| {
+                        self.get(values, x + offset_x, y + offset_y)
+                            .unwrap_or(outside)
+                    })
+                    .max()
+                    .unwrap_or(outside);
+            }
+        }
+
+        filtered
+    }
+
+    /// Approximate a gaussian blur that spreads out `radius` pixels with three box blurs
+    fn blur(&self, alpha: &[u8], radius: f64) -> Vec<u8> {
+        let box_radius = (radius / 3.).round() as usize;
+        if box_radius == 0 {
+            return alpha.to_vec();
+        }
+
+        let mut blurred: Vec<f64> = alpha.iter().map(|alpha| *alpha as f64).collect();
+        for _ in 0..3 {
+            blurred = self.box_blur(&blurred, box_radius, true);
+            blurred = self.box_blur(&blurred, box_radius, false);
+        }
+
+        blurred.iter().map(|alpha| alpha.round() as u8).collect()
+    }
+
+    /// Average every pixel with the pixels up to `radius` pixels away along one axis
+    fn box_blur(&self, values: &[f64], radius: usize, is_horizontal: bool) -> Vec<f64> {
+        let (lines, len) = if is_horizontal {
+            (self.height, self.width)
+        } else {
+            (self.width, self.height)
+        };
+        let idx = |line: usize, position: usize| {
+            if is_horizontal {
+                line * self.width + position
+            } else {
+                position * self.width + line
+            }
+        };
+        let window = (radius * 2 + 1) as f64;
+
+        let mut blurred = vec![0.; values.len()];
+        for line in 0..lines {
+            // Pixels outside of the canvas are transparent
+            let mut sum: f64 = (0..=radius.min(len - 1))
+                .map(|position| values[idx(line, position)])
+                .sum();
+
+            for position in 0..len {
+                blurred[idx(line, position)] = sum / window;
+
+                if position + radius + 1 < len {
+                    sum += values[idx(line, position + radius + 1)];
+                }
+                if position >= radius {
+                    sum -= values[idx(line, position - radius)];
+                }
+            }
+        }
+
+        blurred
+    }
+
+    /// Move every pixel by `(x, y)` pixels
+    fn offset(&self, alpha: &[u8], x: isize, y: isize) -> Vec<u8> {
+        let mut moved = vec![0; alpha.len()];
+        for top in 0..self.height as isize {
+            for left in 0..self.width as isize {
+                if let Some(alpha) = self.get(alpha, left - x, top - y) {
+                    moved[top as usize * self.width + left as usize] = alpha;
+                }
+            }
+        }
+
+        moved
+    }
+
+    /// The value at a coordinate, or `None` if it is outside of the canvas
+    fn get(&self, values: &[u8], x: isize, y: isize) -> Option<u8> {
+        if x < 0 || y < 0 || x >= self.width as isize || y >= self.height as isize {
+            return None;
+        }
+
+        Some(values[y as usize * self.width + x as usize])
+    }
+}
+
+fn alpha_plane(rgba: &[u8]) -> Vec<u8> {
+    rgba.chunks_exact(4).map(|pixel| pixel[3]).collect()
+}
+
+/// `a * (1 - b)` for every pair of alphas
+fn subtract(a: &[u8], b: &[u8]) -> Vec<u8> {
+    a.iter()
+        .zip(b.iter())
+        .map(|(a, b)| ((*a as u16 * (255 - *b as u16) + 127) / 255) as u8)
+        .collect()
+}
+
+/// RGBA pixels of one color, using the alpha plane faded by the opacity
+fn colorize(alpha: &[u8], [r, g, b]: [u8; 3], opacity: u8) -> Vec<u8> {
+    let mut rgba = Vec::with_capacity(alpha.len() * 4);
+    for alpha in alpha.iter() {
+        let mut pixel = [r, g, b, *alpha];
+        blend::apply_opacity(&mut pixel, opacity);
+        rgba.extend_from_slice(&pixel);
+    }
+
+    rgba
+}
+
+/// The colors of RGBA pixels with the alpha plane faded by the opacity
+fn colorize_pixels(rgba: &[u8], alpha: &[u8], opacity: u8) -> Vec<u8> {
+    let mut colorized = Vec::with_capacity(rgba.len());
+    for (pixel, alpha) in rgba.chunks_exact(4).zip(alpha.iter()) {
+        let mut pixel = [pixel[0], pixel[1], pixel[2], *alpha];
+        blend::apply_opacity(&mut pixel, opacity);
+        colorized.extend_from_slice(&pixel);
+    }
+
+    colorized
+}
+
+/// Composite every pixel of `above` over the pixel at the same index of `below`
+fn over(above: &[u8], below: &[u8]) -> Vec<u8> {
+    let mut composited = Vec::with_capacity(above.len());
+    for (above, below) in above.chunks_exact(4).zip(below.chunks_exact(4)) {
+        let mut pixel = [0; 4];
+        match (above[3], below[3]) {
+            (_, 0) => pixel.copy_from_slice(above),
+            (0, _) => pixel.copy_from_slice(below),
+            _ => {
+                let mut top = [0; 4];
+                let mut bottom = [0; 4];
+                top.copy_from_slice(above);
+                bottom.copy_from_slice(below);
+                blend::blend_pixels(top, bottom, BlendMode::Normal, &mut pixel);
+            }
+        }
+        composited.extend_from_slice(&pixel);
+    }
+
+    composited
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn canvas() -> Canvas {
+        Canvas {
+            width: 5,
+            height: 5,
+            scale: 1.,
+        }
+    }
+
+    /// A single opaque pixel in the middle of a 5x5 canvas
+    fn dot() -> Vec<u8> {
+        let mut alpha = vec![0; 25];
+        alpha[12] = 255;
+        alpha
+    }
+
+    /// Verify that dilating grows shapes into a circle and eroding shrinks them.
+    #[test]
+    fn dilates_and_erodes() {
+        let dilated = canvas().dilate(&dot(), 1.);
+        assert_eq!(
+            dilated,
+            vec![
+                0, 0, 0, 0, 0, //
+                0, 0, 255, 0, 0, //
+                0, 255, 255, 255, 0, //
+                0, 0, 255, 0, 0, //
+                0, 0, 0, 0, 0, //
+            ]
+        );
+
+        assert_eq!(canvas().erode(&dilated, 1.), dot());
+        assert_eq!(canvas().erode(&[255; 25], 1.)[0], 0);
+    }
+
+    /// Verify that blurring keeps the total alpha and spreads it out.
+    #[test]
+    fn blurs() {
+        let blurred = canvas().blur(&dot(), 3.);
+
+        assert!(blurred[12] < 255);
+        assert!(blurred[11] > 0);
+        assert_eq!(blurred[11], blurred[13]);
+    }
+
+    /// Verify that offsetting moves pixels and drops the ones that leave the canvas.
+    #[test]
+    fn offsets() {
+        let moved = canvas().offset(&dot(), 2, -1);
+        assert_eq!(moved[12], 0);
+        assert_eq!(moved[5 + 4], 255);
+
+        assert!(canvas()
+            .offset(&dot(), 3, 0)
+            .iter()
+            .all(|alpha| *alpha == 0));
+    }
+}
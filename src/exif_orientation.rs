@@ -0,0 +1,209 @@
+/// The EXIF tag that holds the orientation
+const TAG_ORIENTATION: u16 = 0x0112;
+/// The EXIF type of a 16-bit unsigned integer
+const TYPE_SHORT: u16 = 3;
+
+/// How an image should be transformed for display, from the orientation tag of the EXIF data in
+/// the image resources section (resource 1058).
+///
+/// Scanners and cameras often store pixels in the orientation that they were captured in and
+/// use this to say how they should be viewed.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[allow(missing_docs)]
+pub enum ExifOrientation {
+    Normal = 1,
+    FlipHorizontal = 2,
+    Rotate180 = 3,
+    FlipVertical = 4,
+    /// Flipped over the top-left to bottom-right diagonal
+    Transpose = 5,
+    /// Rotated 90 degrees clockwise
+    Rotate90 = 6,
+    /// Flipped over the top-right to bottom-left diagonal
+    Transverse = 7,
+    /// Rotated 270 degrees clockwise
+    Rotate270 = 8,
+}
+
+impl ExifOrientation {
+    /// Read the orientation out of the raw EXIF data (a TIFF header followed by IFDs).
+    ///
+    /// `None` if the data is malformed or doesn't have an orientation.
+    pub(crate) fn from_exif(exif: &[u8]) -> Option<ExifOrientation> {
+        let big_endian = match exif.get(0..4)? {
+            b"MM\0*" => true,
+            b"II*\0" => false,
+            _ => return None,
+        };
+
+        let read_u16 = |offset: usize| -> Option<u16> {
+            let bytes = [*exif.get(offset)?, *exif.get(offset + 1)?];
+            Some(match big_endian {
+                true => u16::from_be_bytes(bytes),
+                false => u16::from_le_bytes(bytes),
+            })
+        };
+        let read_u32 = |offset: usize| -> Option<u32> {
+            let mut bytes = [0; 4];
+            bytes.copy_from_slice(exif.get(offset..offset + 4)?);
+            Some(match big_endian {
+                true => u32::from_be_bytes(bytes),
+                false => u32::from_le_bytes(bytes),
+            })
+        };
+
+        // The orientation lives in the first IFD, which is a count followed by 12 byte entries
+        let ifd_offset = read_u32(4)? as usize;
+        let entry_count = read_u16(ifd_offset)? as usize;
+
+        for entry in 0..entry_count {
+            let entry_offset = ifd_offset + 2 + entry * 12;
+
+            if read_u16(entry_offset)? == TAG_ORIENTATION
+                && read_u16(entry_offset + 2)? == TYPE_SHORT
+            {
+                return ExifOrientation::from_value(read_u16(entry_offset + 8)?);
+            }
+        }
+
+        None
+    }
+
+    fn from_value(value: u16) -> Option<ExifOrientation> {
+        Some(match value {
+            1 => ExifOrientation::Normal,
+            2 => ExifOrientation::FlipHorizontal,
+            3 => ExifOrientation::Rotate180,
+            4 => ExifOrientation::FlipVertical,
+            5 => ExifOrientation::Transpose,
+            6 => ExifOrientation::Rotate90,
+            7 => ExifOrientation::Transverse,
+            8 => ExifOrientation::Rotate270,
+            _ => return None,
+        })
+    }
+
+    /// Whether the width and height trade places when the orientation is applied
+    pub fn swaps_dimensions(&self) -> bool {
+        matches!(
+            self,
+            ExifOrientation::Transpose
+                | ExifOrientation::Rotate90
+                | ExifOrientation::Transverse
+                | ExifOrientation::Rotate270
+        )
+    }
+
+    /// The (width, height) of an image of the given size after the orientation is applied
+    pub fn dimensions(&self, width: u32, height: u32) -> (u32, u32) {
+        match self.swaps_dimensions() {
+            true => (height, width),
+            false => (width, height),
+        }
+    }
+
+    /// Apply the orientation to RGBA pixels, returning pixels that are
+    /// [`ExifOrientation::dimensions`] in size.
+    /// [ R,G,B,A, R,G,B,A, R,G,B,A, ...]
+    ///
+    /// Useful for [`Psd::rgba`]. [`RenderPipeline::exif_orientation`] applies the orientation to
+    /// rendered layers.
+    ///
+    /// [`Psd::rgba`]: crate::Psd::rgba
+    /// [`RenderPipeline::exif_orientation`]: crate::RenderPipeline::exif_orientation
+    pub fn apply_to_rgba(&self, rgba: &[u8], width: u32, height: u32) -> Vec<u8> {
+        let (width, height) = (width as usize, height as usize);
+        let (oriented_width, oriented_height) = match self.swaps_dimensions() {
+            true => (height, width),
+            false => (width, height),
+        };
+
+        let mut oriented = Vec::with_capacity(rgba.len());
+        for top in 0..oriented_height {
+            for left in 0..oriented_width {
+                let (source_left, source_top) = self.source_coord((left, top), (width, height));
+                let idx = (source_top * width + source_left) * 4;
+                oriented.extend_from_slice(&rgba[idx..idx + 4]);
+            }
+        }
+
+        oriented
+    }
+
+    /// The (left, top) in the stored image of size (width, height) that ends up at the
+    /// (left, top) of the oriented image.
+    pub(crate) fn source_coord(
+        &self,
+        (left, top): (usize, usize),
+        (width, height): (usize, usize),
+    ) -> (usize, usize) {
+        match self {
+            ExifOrientation::Normal => (left, top),
+            ExifOrientation::FlipHorizontal => (width - 1 - left, top),
+            ExifOrientation::Rotate180 => (width - 1 - left, height - 1 - top),
+            ExifOrientation::FlipVertical => (left, height - 1 - top),
+            ExifOrientation::Transpose => (top, left),
+            ExifOrientation::Rotate90 => (top, height - 1 - left),
+            ExifOrientation::Transverse => (width - 1 - top, height - 1 - left),
+            ExifOrientation::Rotate270 => (width - 1 - top, left),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Verify that we find the orientation in big and little endian EXIF data.
+    #[test]
+    fn orientation_from_exif() {
+        let big_endian = [
+            b'M', b'M', 0, 42, 0, 0, 0, 8, // Header
+            0, 2, // Entry count
+            1, 0x1A, 0, 5, 0, 0, 0, 1, 0, 0, 0, 0, // XResolution
+            1, 0x12, 0, 3, 0, 0, 0, 1, 0, 6, 0, 0, // Orientation
+        ];
+        assert_eq!(
+            ExifOrientation::from_exif(&big_endian),
+            Some(ExifOrientation::Rotate90)
+        );
+
+        let little_endian = [
+            b'I', b'I', 42, 0, 8, 0, 0, 0, // Header
+            1, 0, // Entry count
+            0x12, 1, 3, 0, 1, 0, 0, 0, 8, 0, 0, 0, // Orientation
+        ];
+        assert_eq!(
+            ExifOrientation::from_exif(&little_endian),
+            Some(ExifOrientation::Rotate270)
+        );
+
+        assert_eq!(ExifOrientation::from_exif(&big_endian[..20]), None);
+        assert_eq!(ExifOrientation::from_exif(b"Exif"), None);
+    }
+
+    /// Verify each orientation against a 3x2 image whose pixels are numbered
+    ///
+    /// 1 2 3
+    /// 4 5 6
+    #[test]
+    fn apply_each_orientation() {
+        let rgba: Vec<u8> = (1..=6).flat_map(|n| vec![n; 4]).collect();
+        let apply = |orientation: ExifOrientation| -> Vec<u8> {
+            orientation
+                .apply_to_rgba(&rgba, 3, 2)
+                .chunks(4)
+                .map(|pixel| pixel[0])
+                .collect()
+        };
+
+        assert_eq!(apply(ExifOrientation::Normal), [1, 2, 3, 4, 5, 6]);
+        assert_eq!(apply(ExifOrientation::FlipHorizontal), [3, 2, 1, 6, 5, 4]);
+        assert_eq!(apply(ExifOrientation::Rotate180), [6, 5, 4, 3, 2, 1]);
+        assert_eq!(apply(ExifOrientation::FlipVertical), [4, 5, 6, 1, 2, 3]);
+        assert_eq!(apply(ExifOrientation::Transpose), [1, 4, 2, 5, 3, 6]);
+        assert_eq!(apply(ExifOrientation::Rotate90), [4, 1, 5, 2, 6, 3]);
+        assert_eq!(apply(ExifOrientation::Transverse), [6, 3, 5, 2, 4, 1]);
+        assert_eq!(apply(ExifOrientation::Rotate270), [3, 6, 2, 5, 1, 4]);
+    }
+}
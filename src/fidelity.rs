@@ -0,0 +1,245 @@
+use std::fmt;
+
+use crate::blend;
+use crate::sections::layer_and_mask_information_section::layer::LayerProperties;
+use crate::{BlendMode, ColorMode, NodeId, Psd, PsdDepth};
+
+/// Keys of the tagged blocks that hold layer effects (drop shadows, strokes, ...)
+const EFFECTS_KEYS: [&[u8; 4]; 3] = [b"lfx2", b"lrFX", b"lmfx"];
+/// Keys of the tagged blocks that make a layer an adjustment layer
+pub(crate) const ADJUSTMENT_KEYS: [&[u8; 4]; 16] = [
+    b"brit", b"levl", b"curv", b"expA", b"vibA", b"hue ", b"hue2", b"blnc", b"blwh", b"phfl",
+    b"mixr", b"clrL", b"nvrt", b"post", b"thrs", b"selc",
+];
+/// Keys of the tagged blocks that hold a vector mask
+const VECTOR_MASK_KEYS: [&[u8; 4]; 2] = [b"vmsk", b"vsms"];
+
+/// The parts of a PSD that rendering approximates or ignores, so that its output may not match
+/// Photoshop's.
+///
+/// Created by [`Psd::render_fidelity_report`].
+///
+/// [`Psd::render_fidelity_report`]: crate::Psd::render_fidelity_report
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderFidelityReport {
+    pub(crate) issues: Vec<FidelityIssue>,
+}
+
+/// Something that rendering approximates or ignores, along with the layer or group that it
+/// belongs to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FidelityIssue {
+    pub(crate) node: Option<NodeId>,
+    pub(crate) name: String,
+    pub(crate) kind: FidelityIssueKind,
+}
+
+/// What rendering approximates or ignores.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FidelityIssueKind {
+    /// A blend mode that we can't blend with yet. Rendering the layer or group panics.
+    UnsupportedBlendMode(BlendMode),
+    /// The layer is clipped to the layer below it, but it's rendered without the clipping.
+    ClippingMask,
+    /// Layer effects such as drop shadows and strokes aren't rendered.
+    LayerEffects,
+    /// An adjustment layer (levels, curves, ...), identified by the key of its tagged block.
+    /// Adjustments aren't applied.
+    AdjustmentLayer([u8; 4]),
+    /// A vector mask. Only pixel masks are applied.
+    VectorMask,
+    /// A group's mask. Only layer masks are applied.
+    GroupMask,
+    /// "Blend If" ranges that hide some of the layer's pixels, which are only applied by
+    /// [`RenderPipeline::blending_ranges`].
+    ///
+    /// [`RenderPipeline::blending_ranges`]: crate::RenderPipeline::blending_ranges
+    BlendingRanges,
+    /// Layers in a color mode other than RGB, grayscale, bitmap or indexed. CMYK is converted to RGB
+    /// without its color profile, the other modes are rendered as if their channels were RGB.
+    ColorMode(ColorMode),
+    /// Layers in a 32 bit document are tonemapped to 8 bits and blended in sRGB rather than in
+    /// linear light.
+    HighDynamicRange,
+}
+
+impl RenderFidelityReport {
+    /// Everything that rendering approximates or ignores, with the issues that affect the
+    /// whole document first followed by each layer's and group's issues.
+    pub fn issues(&self) -> &[FidelityIssue] {
+        &self.issues
+    }
+
+    /// Whether rendering should match Photoshop, because nothing is approximated or ignored.
+    pub fn is_faithful(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+impl FidelityIssue {
+    /// The layer or group with the issue, or `None` if the issue affects the whole document.
+    pub fn node(&self) -> Option<NodeId> {
+        self.node
+    }
+
+    /// The name of the layer or group with the issue. Empty if the issue affects the whole
+    /// document.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// What rendering approximates or ignores
+    pub fn kind(&self) -> FidelityIssueKind {
+        self.kind
+    }
+}
+
+impl fmt::Display for RenderFidelityReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for issue in self.issues.iter() {
+            writeln!(f, "{}", issue)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for FidelityIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.node {
+            Some(NodeId::Layer(_)) => write!(f, "Layer '{}': ", self.name)?,
+            Some(NodeId::Group(_)) => write!(f, "Group '{}': ", self.name)?,
+            None => write!(f, "Document: ")?,
+        };
+
+        match self.kind {
+            FidelityIssueKind::UnsupportedBlendMode(blend_mode) => {
+                write!(f, "unsupported blend mode {:?}", blend_mode)
+            }
+            FidelityIssueKind::ClippingMask => write!(f, "clipping mask is ignored"),
+            FidelityIssueKind::LayerEffects => write!(f, "layer effects are ignored"),
+            FidelityIssueKind::AdjustmentLayer(key) => write!(
+                f,
+                "adjustment layer '{}' is ignored",
+                String::from_utf8_lossy(&key)
+            ),
+            FidelityIssueKind::VectorMask => write!(f, "vector mask is ignored"),
+            FidelityIssueKind::GroupMask => write!(f, "group mask is ignored"),
+            FidelityIssueKind::BlendingRanges => write!(f, "blend if ranges are ignored"),
+            FidelityIssueKind::ColorMode(ColorMode::Cmyk) => {
+                write!(
+                    f,
+                    "Cmyk color mode is converted to RGB without color management"
+                )
+            }
+            FidelityIssueKind::ColorMode(color_mode) => {
+                write!(f, "{:?} color mode is rendered as RGB", color_mode)
+            }
+            FidelityIssueKind::HighDynamicRange => {
+                write!(f, "32 bit layers are blended in 8 bit sRGB")
+            }
+        }
+    }
+}
+
+/// Find everything in the PSD that rendering approximates or ignores.
+pub(crate) fn render_fidelity_report(psd: &Psd) -> RenderFidelityReport {
+    let mut issues = vec![];
+
+    let mut document_issue = |kind| {
+        issues.push(FidelityIssue {
+            node: None,
+            name: String::new(),
+            kind,
+        })
+    };
+    match psd.color_mode() {
+        ColorMode::Rgb | ColorMode::Grayscale | ColorMode::Bitmap | ColorMode::Indexed => {}
+        color_mode => document_issue(FidelityIssueKind::ColorMode(color_mode)),
+    }
+    if psd.depth() == PsdDepth::ThirtyTwo && !psd.layers().is_empty() {
+        document_issue(FidelityIssueKind::HighDynamicRange);
+    }
+
+    for (idx, layer) in psd.layers().iter().enumerate() {
+        let node = NodeId::Layer(idx);
+        for kind in properties_issues(layer) {
+            issues.push(FidelityIssue {
+                node: Some(node),
+                name: layer.name().to_string(),
+                kind,
+            });
+        }
+    }
+
+    for group in psd.groups_in_order() {
+        let mut kinds = vec![];
+
+        if group.blend_mode() != BlendMode::PassThrough && !blend::is_supported(group.blend_mode())
+        {
+            kinds.push(FidelityIssueKind::UnsupportedBlendMode(group.blend_mode()));
+        }
+        // The opening record holds the group's mask, effects, etc
+        let folder = &group.folder_properties;
+        if folder.layer_mask.is_some() || has_block(folder, &VECTOR_MASK_KEYS) {
+            kinds.push(FidelityIssueKind::GroupMask);
+        }
+        if has_block(folder, &EFFECTS_KEYS) {
+            kinds.push(FidelityIssueKind::LayerEffects);
+        }
+
+        for kind in kinds {
+            issues.push(FidelityIssue {
+                node: Some(NodeId::Group(group.id())),
+                name: group.name().to_string(),
+                kind,
+            });
+        }
+    }
+
+    RenderFidelityReport { issues }
+}
+
+/// The issues of a layer
+fn properties_issues(properties: &LayerProperties) -> Vec<FidelityIssueKind> {
+    let mut kinds = vec![];
+
+    if !blend::is_supported(properties.blend_mode) {
+        kinds.push(FidelityIssueKind::UnsupportedBlendMode(
+            properties.blend_mode,
+        ));
+    }
+    // `clipping_mask` is set for layers that other layers can be clipped to, see
+    // `LayerProperties::is_clipping_mask`
+    if !properties.clipping_mask {
+        kinds.push(FidelityIssueKind::ClippingMask);
+    }
+    if has_block(properties, &EFFECTS_KEYS) {
+        kinds.push(FidelityIssueKind::LayerEffects);
+    }
+    for block in properties.record_data.tagged_blocks.iter() {
+        if ADJUSTMENT_KEYS.contains(&block.key()) {
+            kinds.push(FidelityIssueKind::AdjustmentLayer(*block.key()));
+        }
+    }
+    if has_block(properties, &VECTOR_MASK_KEYS) {
+        kinds.push(FidelityIssueKind::VectorMask);
+    }
+    if let Some(false) = properties
+        .blending_ranges()
+        .map(|ranges| ranges.is_default())
+    {
+        kinds.push(FidelityIssueKind::BlendingRanges);
+    }
+
+    kinds
+}
+
+/// Whether the layer record has a tagged block with one of the keys
+fn has_block(properties: &LayerProperties, keys: &[&[u8; 4]]) -> bool {
+    properties
+        .record_data
+        .tagged_blocks
+        .iter()
+        .any(|block| keys.contains(&block.key()))
+}
@@ -0,0 +1,213 @@
+//! Rendering solid color, gradient and pattern fill layers, which often don't have any pixels of
+//! their own.
+
+use std::f64::consts::TAU;
+
+use crate::blend;
+use crate::sections::layer_and_mask_information_section::fill::{
+    Fill, GradientFill, GradientKind, PatternFill,
+};
+use crate::{Pattern, Psd, PsdLayer};
+
+/// The RGBA pixels of a fill layer, spanning the entire PSD, with its masks, opacity and fill
+/// opacity applied.
+///
+/// Returns `None` if the layer isn't a fill layer that we render, see `fill_rgba`.
+pub(crate) fn layer_rgba_with_fill(psd: &Psd, layer: &PsdLayer) -> Option<Vec<u8>> {
+    let mut rgba = fill_rgba(psd, layer)?;
+
+    layer.apply_masks(&mut rgba);
+    blend::apply_layer_opacity_buffer(&mut rgba, layer.opacity, layer.fill_opacity());
+
+    Some(rgba)
+}
+
+/// The RGBA pixels of a fill layer's fill, spanning the entire PSD.
+///
+/// Returns `None` if the layer has pixels of its own, which already show its fill, or if it isn't
+/// filled with a solid color, a gradient made of stops or a pattern that we can decode.
+pub(crate) fn fill_rgba(psd: &Psd, layer: &PsdLayer) -> Option<Vec<u8>> {
+    if !layer.is_empty() {
+        return None;
+    }
+
+    let (width, height) = (psd.width() as usize, psd.height() as usize);
+
+    match layer.fill()? {
+        Fill::SolidColor(fill) => {
+            let [r, g, b] = fill.color;
+            Some([r, g, b, 255].repeat(width * height))
+        }
+        Fill::Gradient(gradient) if !gradient.color_stops.is_empty() => {
+            Some(gradient_rgba(&gradient, width, height))
+        }
+        Fill::Pattern(fill) => {
+            let pattern = psd
+                .patterns()
+                .iter()
+                .find(|pattern| pattern.id == fill.id)?;
+            pattern_rgba(&fill, pattern, width, height)
+        }
+        _ => None,
+    }
+}
+
+/// Repeat a pattern across the whole PSD, with a tile starting at the fill's phase. Scaled
+/// patterns use the nearest pixel of the pattern.
+fn pattern_rgba(
+    fill: &PatternFill,
+    pattern: &Pattern,
+    width: usize,
+    height: usize,
+) -> Option<Vec<u8>> {
+    let tile = pattern.rgba()?;
+    if tile.is_empty() {
+        return None;
+    }
+
+    let (tile_width, tile_height) = (pattern.width as f64, pattern.height as f64);
+    let scale = (fill.scale / 100.).max(f64::EPSILON);
+    // The pixel of the tile that a pixel of the PSD shows, along one axis
+    let tile_pixel = |pixel: usize, phase: f64, tile_len: f64| {
+        ((pixel as f64 + 0.5 - phase) / scale)
+            .floor()
+            .rem_euclid(tile_len) as usize
+    };
+
+    let mut rgba = Vec::with_capacity(width * height * 4);
+    for top in 0..height {
+        let y = tile_pixel(top, fill.phase.1, tile_height);
+        for left in 0..width {
+            let x = tile_pixel(left, fill.phase.0, tile_width);
+            let idx = (y * pattern.width as usize + x) * 4;
+            rgba.extend_from_slice(&tile[idx..idx + 4]);
+        }
+    }
+
+    Some(rgba)
+}
+
+/// Render a gradient across the whole PSD.
+///
+/// A linear gradient at 100% scale reaches from one side of the PSD to the other along its
+/// angle, and the other kinds of gradients reach half as far from the center.
+fn gradient_rgba(gradient: &GradientFill, width: usize, height: usize) -> Vec<u8> {
+    let (width, height) = (width as f64, height as f64);
+
+    let radians = gradient.angle.to_radians();
+    // The direction of the gradient, with y going down
+    let (dx, dy) = (radians.cos(), -radians.sin());
+    let center = (
+        width / 2. + gradient.offset.0 / 100. * width,
+        height / 2. + gradient.offset.1 / 100. * height,
+    );
+    let half_length = ((width * dx).abs() + (height * dy).abs()) / 2. * gradient.scale / 100.;
+    let half_length = half_length.max(f64::EPSILON);
+
+    let color_stops: Vec<_> = gradient
+        .color_stops
+        .iter()
+        .map(|stop| (stop.location, stop.midpoint))
+        .collect();
+    let transparency_stops: Vec<_> = gradient
+        .transparency_stops
+        .iter()
+        .map(|stop| (stop.location, stop.midpoint))
+        .collect();
+
+    let mut rgba = Vec::with_capacity(width as usize * height as usize * 4);
+    for top in 0..height as usize {
+        for left in 0..width as usize {
+            // The center of the pixel, relative to the center of the gradient
+            let x = left as f64 + 0.5 - center.0;
+            let y = top as f64 + 0.5 - center.1;
+            let along = x * dx + y * dy;
+            let across = y * dx - x * dy;
+
+            let position = match gradient.kind {
+                GradientKind::Linear => along / half_length / 2. + 0.5,
+                GradientKind::Radial => (x * x + y * y).sqrt() / half_length,
+                GradientKind::Angle => ((-y).atan2(x) - radians).rem_euclid(TAU) / TAU,
+                GradientKind::Reflected => along.abs() / half_length,
+                GradientKind::Diamond => along.abs().max(across.abs()) / half_length,
+            };
+            let position = position.clamp(0., 1.);
+            let position = if gradient.reverse {
+                1. - position
+            } else {
+                position
+            };
+
+            let (start, end, fraction) = between(&color_stops, position);
+            let (start, end) = (
+                gradient.color_stops[start].color,
+                gradient.color_stops[end].color,
+            );
+            for channel in 0..3 {
+                rgba.push(lerp(start[channel], end[channel], fraction));
+            }
+
+            let alpha = if transparency_stops.is_empty() {
+                255
+            } else {
+                let (start, end, fraction) = between(&transparency_stops, position);
+                lerp(
+                    gradient.transparency_stops[start].opacity,
+                    gradient.transparency_stops[end].opacity,
+                    fraction,
+                )
+            };
+            rgba.push(alpha);
+        }
+    }
+
+    rgba
+}
+
+/// The indices of the (location, midpoint) stops before and after a position along a gradient,
+/// and how far the position is from the first stop to the second, from 0 to 1.
+///
+/// Each stop's midpoint is where its color is halfway between the previous stop's and its own.
+fn between(stops: &[(f64, f64)], position: f64) -> (usize, usize, f64) {
+    let end = match stops.iter().position(|(location, _)| *location > position) {
+        Some(0) => return (0, 0, 0.),
+        Some(end) => end,
+        None => return (stops.len() - 1, stops.len() - 1, 0.),
+    };
+    let start = end - 1;
+
+    let (start_location, _) = stops[start];
+    let (end_location, midpoint) = stops[end];
+    let fraction = (position - start_location) / (end_location - start_location);
+
+    let fraction = if fraction < midpoint {
+        fraction / midpoint * 0.5
+    } else {
+        0.5 + (fraction - midpoint) / (1. - midpoint) * 0.5
+    };
+
+    (start, end, fraction)
+}
+
+fn lerp(start: u8, end: u8, fraction: f64) -> u8 {
+    let value = start as f64 + (end as f64 - start as f64) * fraction;
+    value.round().clamp(0., 255.) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Verify that positions between two stops are spread out around the second stop's midpoint.
+    #[test]
+    fn between_stops() {
+        let stops = [(0.25, 0.5), (0.75, 0.25)];
+
+        assert_eq!(between(&stops, 0.), (0, 0, 0.));
+        assert_eq!(between(&stops, 0.9), (1, 1, 0.));
+        assert_eq!(between(&stops, 0.375), (0, 1, 0.5));
+        let (start, end, fraction) = between(&stops, 0.5);
+        assert_eq!((start, end), (0, 1));
+        assert!((fraction - 2. / 3.).abs() < 1e-9);
+    }
+}
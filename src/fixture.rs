@@ -0,0 +1,617 @@
+//! Build small PSD files in code, for tests that need a PSD with a particular depth, compression,
+//! group structure, mask or blend mode without exporting one from Photoshop.
+//!
+//! Only available with the `fixture-builder` cargo feature. This is a tool for our own tests, so
+//! it may change in any release.
+//!
+//! ```
+//! # use psd::fixture::{FixtureLayer, PsdFixture};
+//! # use psd::{BlendMode, PsdChannelCompression, PsdDepth};
+//! let psd = PsdFixture::new(4, 4)
+//!     .depth(PsdDepth::Sixteen)
+//!     .compression(PsdChannelCompression::RleCompressed)
+//!     .layer(FixtureLayer::solid("Background", 0, 0, 4, 4, [255, 255, 255, 255]))
+//!     .group("Group", |group| {
+//!         group.layer(
+//!             FixtureLayer::solid("Red", 1, 1, 2, 2, [255, 0, 0, 255])
+//!                 .blend_mode(BlendMode::Multiply),
+//!         )
+//!     })
+//!     .build()
+//!     .unwrap();
+//!
+//! assert_eq!(psd.layers().len(), 2);
+//! assert_eq!(psd.groups().len(), 1);
+//! ```
+
+use crate::psd_channel::rle_compress_rows;
+use crate::sections::file_header_section::{ChannelCount, FileHeaderSection, PsdHeight, PsdWidth};
+use crate::sections::image_resources_section::ImageResourceBlock;
+use crate::sections::layer_and_mask_information_section::layer::{GroupDivider, LayerRecordData};
+use crate::sections::layer_and_mask_information_section::{
+    write_layer_records, ChannelToWrite, KEY_SECTION_DIVIDER_SETTING,
+};
+use crate::sections::serialize::{PsdBuffer, PsdSerialize};
+use crate::{
+    BlendMode, ColorMode, LayerFlags, LayerProperties, Psd, PsdChannelCompression, PsdChannelKind,
+    PsdDepth, PsdError, PsdVersion, TaggedBlock,
+};
+
+/// The zlib compression level of ZIP compressed channels
+const ZIP_COMPRESSION_LEVEL: u8 = 6;
+
+/// The name that Photoshop gives the hidden record that closes a group
+const GROUP_BOUNDING_NAME: &str = "</Layer group>";
+
+/// The color channels of a layer or of the final image, in the order of their RGBA samples
+const COLOR_CHANNELS: [PsdChannelKind; 3] = [
+    PsdChannelKind::Red,
+    PsdChannelKind::Green,
+    PsdChannelKind::Blue,
+];
+
+/// A PSD that is being built, see the [module docs](self).
+///
+/// Layers and groups are added from the bottom of the PSD to the top, the same order that they
+/// are stored in the file. [`Psd::layers`] lists them the other way around, from the top down.
+#[derive(Debug, Clone)]
+pub struct PsdFixture {
+    width: u32,
+    height: u32,
+    version: PsdVersion,
+    depth: PsdDepth,
+    compression: PsdChannelCompression,
+    resources: Vec<ImageResourceBlock>,
+    /// The layer records from the bottom of the PSD to the top
+    records: Vec<FixtureRecord>,
+    final_rgba: Option<Vec<u8>>,
+}
+
+/// A layer of a [`PsdFixture`].
+#[derive(Debug, Clone)]
+pub struct FixtureLayer {
+    name: String,
+    left: i32,
+    top: i32,
+    width: u32,
+    rgba: Vec<u8>,
+    blend_mode: BlendMode,
+    opacity: u8,
+    visible: bool,
+    clipped: bool,
+    mask: Option<FixtureMask>,
+    tagged_blocks: Vec<TaggedBlock>,
+}
+
+/// The user mask of a [`FixtureLayer`]
+#[derive(Debug, Clone)]
+struct FixtureMask {
+    left: i32,
+    top: i32,
+    width: u32,
+    pixels: Vec<u8>,
+    default_color: u8,
+}
+
+#[derive(Debug, Clone)]
+enum FixtureRecord {
+    Layer(FixtureLayer),
+    /// The record that opens a group, which comes after the group's layers
+    GroupFolder {
+        name: String,
+        blend_mode: BlendMode,
+    },
+    /// The hidden record that closes a group, which comes before the group's layers
+    GroupBounding,
+}
+
+impl PsdFixture {
+    /// An 8 bit RGB PSD with raw channels and no layers. The final image is transparent unless
+    /// it's set with [`PsdFixture::final_rgba`].
+    pub fn new(width: u32, height: u32) -> PsdFixture {
+        PsdFixture {
+            width,
+            height,
+            version: PsdVersion::One,
+            depth: PsdDepth::Eight,
+            compression: PsdChannelCompression::RawData,
+            resources: vec![],
+            records: vec![],
+            final_rgba: None,
+        }
+    }
+
+    /// Write a PSD ([`PsdVersion::One`]) or a PSB ([`PsdVersion::Two`]) file
+    pub fn version(mut self, version: PsdVersion) -> PsdFixture {
+        self.version = version;
+        self
+    }
+
+    /// The number of bits per channel. Only 8 and 16 bits are supported, 16 bit samples are the
+    /// 8 bit samples scaled up so that 255 becomes 65535.
+    pub fn depth(mut self, depth: PsdDepth) -> PsdFixture {
+        assert!(
+            depth == PsdDepth::Eight || depth == PsdDepth::Sixteen,
+            "Fixtures can only be 8 or 16 bit, not {:?}",
+            depth
+        );
+        self.depth = depth;
+        self
+    }
+
+    /// How every layer channel and the final image are compressed
+    pub fn compression(mut self, compression: PsdChannelCompression) -> PsdFixture {
+        self.compression = compression;
+        self
+    }
+
+    /// Add an image resource block
+    pub fn resource(mut self, id: i16, data: Vec<u8>) -> PsdFixture {
+        self.resources.push(ImageResourceBlock {
+            resource_id: id,
+            name: vec![],
+            data,
+        });
+        self
+    }
+
+    /// Add a layer above the layers that have been added so far
+    pub fn layer(mut self, layer: FixtureLayer) -> PsdFixture {
+        self.records.push(FixtureRecord::Layer(layer));
+        self
+    }
+
+    /// Add a pass through group above the layers that have been added so far, with the layers
+    /// and groups that `build` adds inside of it.
+    pub fn group(self, name: &str, build: impl FnOnce(PsdFixture) -> PsdFixture) -> PsdFixture {
+        self.group_with_blend_mode(name, BlendMode::PassThrough, build)
+    }
+
+    /// [`PsdFixture::group`] for a group with another blend mode
+    pub fn group_with_blend_mode(
+        mut self,
+        name: &str,
+        blend_mode: BlendMode,
+        build: impl FnOnce(PsdFixture) -> PsdFixture,
+    ) -> PsdFixture {
+        self.records.push(FixtureRecord::GroupBounding);
+        let mut fixture = build(self);
+        fixture.records.push(FixtureRecord::GroupFolder {
+            name: name.to_string(),
+            blend_mode,
+        });
+        fixture
+    }
+
+    /// The RGBA pixels of the final image, the size of the PSD
+    pub fn final_rgba(mut self, rgba: Vec<u8>) -> PsdFixture {
+        assert_eq!(rgba.len(), self.width as usize * self.height as usize * 4);
+        self.final_rgba = Some(rgba);
+        self
+    }
+
+    /// Parse the fixture with [`Psd::from_bytes`]
+    pub fn build(&self) -> Result<Psd, PsdError> {
+        Psd::from_bytes(&self.to_bytes())
+    }
+
+    /// The bytes of the PSD file
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buffer = PsdBuffer::new();
+
+        FileHeaderSection {
+            version: self.version,
+            channel_count: ChannelCount::new(4).unwrap(),
+            width: PsdWidth::new(self.width, self.version).expect("Invalid fixture width"),
+            height: PsdHeight::new(self.height, self.version).expect("Invalid fixture height"),
+            depth: self.depth,
+            color_mode: ColorMode::Rgb,
+        }
+        .write(&mut buffer);
+
+        // No color mode data
+        buffer.write_u32(0);
+
+        let resources_length = buffer.start_length();
+        for block in self.resources.iter() {
+            block.write(&mut buffer);
+        }
+        buffer.end_length(resources_length);
+
+        self.write_layer_and_mask_information(&mut buffer);
+        self.write_image_data(&mut buffer);
+
+        buffer.into_inner()
+    }
+
+    fn write_layer_and_mask_information(&self, buffer: &mut PsdBuffer) {
+        let section_length = buffer.start_length_for(self.version);
+
+        let records: Vec<(LayerProperties, Vec<FixtureChannel>)> = self
+            .records
+            .iter()
+            .map(|record| match record {
+                FixtureRecord::Layer(layer) => (self.layer_properties(layer), self.channels(layer)),
+                FixtureRecord::GroupFolder { name, blend_mode } => (
+                    self.group_properties(name, *blend_mode, GroupDivider::OpenFolder),
+                    empty_channels(),
+                ),
+                FixtureRecord::GroupBounding => (
+                    self.group_properties(
+                        GROUP_BOUNDING_NAME,
+                        BlendMode::Normal,
+                        GroupDivider::BoundingSection,
+                    ),
+                    empty_channels(),
+                ),
+            })
+            .collect();
+        let records: Vec<(&LayerProperties, Vec<ChannelToWrite>)> = records
+            .iter()
+            .map(|(properties, channels)| {
+                let channels = channels
+                    .iter()
+                    .map(|channel| ChannelToWrite {
+                        kind: channel.kind,
+                        compression: channel.compression,
+                        scanline_byte_counts: &channel.scanline_byte_counts,
+                        data: &channel.data,
+                    })
+                    .collect();
+                (properties, channels)
+            })
+            .collect();
+
+        let layer_info_length = buffer.start_length_for(self.version);
+        write_layer_records(buffer, self.version, false, &records);
+        buffer.end_length_for(layer_info_length, self.version);
+
+        // No global layer mask info
+        buffer.write_u32(0);
+
+        buffer.end_length_for(section_length, self.version);
+    }
+
+    fn write_image_data(&self, buffer: &mut PsdBuffer) {
+        let pixel_count = self.width as usize * self.height as usize;
+        let transparent = vec![0; pixel_count * 4];
+        let rgba = self.final_rgba.as_ref().unwrap_or(&transparent);
+
+        let planes: Vec<Vec<u8>> = (0..4)
+            .map(|offset| {
+                let plane: Vec<u8> = rgba.iter().skip(offset).step_by(4).copied().collect();
+                self.samples(&plane)
+            })
+            .collect();
+        let row_len = self.width as usize * self.bytes_per_sample();
+
+        buffer.write_u16(self.compression as u16);
+        match self.compression {
+            PsdChannelCompression::RawData => {
+                for plane in planes.iter() {
+                    buffer.write(plane);
+                }
+            }
+            PsdChannelCompression::RleCompressed => {
+                let compressed: Vec<(Vec<u8>, Vec<u32>)> = planes
+                    .iter()
+                    .map(|plane| rle_compress_rows(plane, row_len))
+                    .collect();
+
+                for (_, byte_counts) in compressed.iter() {
+                    for byte_count in byte_counts.iter() {
+                        buffer.write_rle_byte_count(self.version, *byte_count);
+                    }
+                }
+                for (plane, _) in compressed.iter() {
+                    buffer.write(plane);
+                }
+            }
+            PsdChannelCompression::ZipWithoutPrediction
+            | PsdChannelCompression::ZipWithPrediction => {
+                let planes: Vec<u8> = planes
+                    .iter()
+                    .flat_map(|plane| self.zip_samples(plane, row_len))
+                    .collect();
+                buffer.write(&miniz_oxide::deflate::compress_to_vec_zlib(
+                    &planes,
+                    ZIP_COMPRESSION_LEVEL,
+                ));
+            }
+        }
+    }
+
+    fn layer_properties(&self, layer: &FixtureLayer) -> LayerProperties {
+        let height = (layer.rgba.len() / 4 / layer.width.max(1) as usize) as i32;
+
+        let mut mask_data = vec![];
+        if let Some(mask) = &layer.mask {
+            let height = (mask.pixels.len() / mask.width.max(1) as usize) as i32;
+            for edge in [
+                mask.top,
+                mask.left,
+                mask.top + height,
+                mask.left + mask.width as i32,
+            ]
+            .iter()
+            {
+                mask_data.extend_from_slice(&edge.to_be_bytes());
+            }
+            // The default color and flags, padded to 20 bytes
+            mask_data.extend_from_slice(&[mask.default_color, 0, 0, 0]);
+        }
+
+        LayerProperties {
+            name: layer.name.clone(),
+            layer_top: layer.top,
+            layer_left: layer.left,
+            layer_bottom: layer.top + height - 1,
+            layer_right: layer.left + layer.width as i32 - 1,
+            opacity: layer.opacity,
+            clipping_mask: !layer.clipped,
+            flags: LayerFlags::new(if layer.visible { 1 << 1 } else { 0 }),
+            blend_mode: layer.blend_mode,
+            record_data: LayerRecordData {
+                mask_data,
+                pascal_name: layer.name.as_bytes().to_vec(),
+                tagged_blocks: layer.tagged_blocks.clone(),
+                ..LayerRecordData::default()
+            },
+            ..self.empty_properties()
+        }
+    }
+
+    fn group_properties(
+        &self,
+        name: &str,
+        blend_mode: BlendMode,
+        divider: GroupDivider,
+    ) -> LayerProperties {
+        let mut divider_data = (divider as i32).to_be_bytes().to_vec();
+        divider_data.extend_from_slice(b"8BIM");
+        divider_data.extend_from_slice(&blend_mode.key());
+
+        LayerProperties {
+            name: name.to_string(),
+            flags: LayerFlags::new(1 << 1),
+            record_data: LayerRecordData {
+                pascal_name: name.as_bytes().to_vec(),
+                tagged_blocks: vec![TaggedBlock {
+                    key: *KEY_SECTION_DIVIDER_SETTING,
+                    data: divider_data,
+                }],
+                ..LayerRecordData::default()
+            },
+            ..self.empty_properties()
+        }
+    }
+
+    /// The properties of an empty, opaque and visible layer without a name
+    fn empty_properties(&self) -> LayerProperties {
+        LayerProperties {
+            name: String::new(),
+            layer_top: 0,
+            layer_left: 0,
+            layer_bottom: -1,
+            layer_right: -1,
+            flags: LayerFlags::new(0),
+            opacity: 255,
+            clipping_mask: true,
+            psd_width: self.width,
+            psd_height: self.height,
+            blend_mode: BlendMode::Normal,
+            group_id: None,
+            layer_id: None,
+            layer_mask: None,
+            record_data: LayerRecordData::default(),
+        }
+    }
+
+    /// The compressed transparency, color and user mask channels of a layer
+    fn channels(&self, layer: &FixtureLayer) -> Vec<FixtureChannel> {
+        let mut channels: Vec<FixtureChannel> = [PsdChannelKind::TransparencyMask]
+            .iter()
+            .chain(COLOR_CHANNELS.iter())
+            .map(|kind| {
+                let offset = kind.rgba_channel().unwrap().offset();
+                let plane: Vec<u8> = layer.rgba.iter().skip(offset).step_by(4).copied().collect();
+                self.channel(*kind, &plane, layer.width)
+            })
+            .collect();
+
+        if let Some(mask) = &layer.mask {
+            channels.push(self.channel(
+                PsdChannelKind::UserSuppliedLayerMask,
+                &mask.pixels,
+                mask.width,
+            ));
+        }
+
+        channels
+    }
+
+    fn channel(&self, kind: PsdChannelKind, plane: &[u8], width: u32) -> FixtureChannel {
+        let samples = self.samples(plane);
+        let row_len = width as usize * self.bytes_per_sample();
+
+        let (data, scanline_byte_counts) = match self.compression {
+            PsdChannelCompression::RawData => (samples, vec![]),
+            PsdChannelCompression::RleCompressed => rle_compress_rows(&samples, row_len),
+            PsdChannelCompression::ZipWithoutPrediction
+            | PsdChannelCompression::ZipWithPrediction => (
+                miniz_oxide::deflate::compress_to_vec_zlib(
+                    &self.zip_samples(&samples, row_len),
+                    ZIP_COMPRESSION_LEVEL,
+                ),
+                vec![],
+            ),
+        };
+
+        FixtureChannel {
+            kind,
+            compression: self.compression,
+            scanline_byte_counts,
+            data,
+        }
+    }
+
+    /// The big endian samples of an 8 bit plane at the fixture's depth
+    fn samples(&self, plane: &[u8]) -> Vec<u8> {
+        match self.depth {
+            PsdDepth::Sixteen => plane
+                .iter()
+                .flat_map(|sample| (*sample as u16 * 257).to_be_bytes().to_vec())
+                .collect(),
+            _ => plane.to_vec(),
+        }
+    }
+
+    /// With prediction every sample is stored as the difference from the sample to its left
+    fn zip_samples(&self, samples: &[u8], row_len: usize) -> Vec<u8> {
+        if self.compression != PsdChannelCompression::ZipWithPrediction {
+            return samples.to_vec();
+        }
+
+        let mut predicted = Vec::with_capacity(samples.len());
+        for row in samples.chunks(row_len.max(1)) {
+            match self.depth {
+                PsdDepth::Sixteen => {
+                    let mut left = 0u16;
+                    for sample in row.chunks_exact(2) {
+                        let sample = u16::from_be_bytes([sample[0], sample[1]]);
+                        predicted.extend_from_slice(&sample.wrapping_sub(left).to_be_bytes());
+                        left = sample;
+                    }
+                }
+                _ => {
+                    let mut left = 0u8;
+                    for sample in row.iter() {
+                        predicted.push(sample.wrapping_sub(left));
+                        left = *sample;
+                    }
+                }
+            }
+        }
+
+        predicted
+    }
+
+    fn bytes_per_sample(&self) -> usize {
+        match self.depth {
+            PsdDepth::Sixteen => 2,
+            _ => 1,
+        }
+    }
+}
+
+impl FixtureLayer {
+    /// A visible, opaque, normal layer whose top left pixel is at `left` and `top`, with `width`
+    /// pixels in each row of its RGBA pixels.
+    pub fn new(name: &str, left: i32, top: i32, width: u32, rgba: Vec<u8>) -> FixtureLayer {
+        assert_eq!(rgba.len() % (width.max(1) as usize * 4), 0);
+
+        FixtureLayer {
+            name: name.to_string(),
+            left,
+            top,
+            width,
+            rgba,
+            blend_mode: BlendMode::Normal,
+            opacity: 255,
+            visible: true,
+            clipped: false,
+            mask: None,
+            tagged_blocks: vec![],
+        }
+    }
+
+    /// A layer that is filled with one color
+    pub fn solid(
+        name: &str,
+        left: i32,
+        top: i32,
+        width: u32,
+        height: u32,
+        color: [u8; 4],
+    ) -> FixtureLayer {
+        let rgba = color.repeat(width as usize * height as usize);
+        FixtureLayer::new(name, left, top, width, rgba)
+    }
+
+    /// How the layer blends with the layers below it
+    pub fn blend_mode(mut self, blend_mode: BlendMode) -> FixtureLayer {
+        self.blend_mode = blend_mode;
+        self
+    }
+
+    /// The opacity of the layer, from 0 to 255
+    pub fn opacity(mut self, opacity: u8) -> FixtureLayer {
+        self.opacity = opacity;
+        self
+    }
+
+    /// Hide the layer
+    pub fn hidden(mut self) -> FixtureLayer {
+        self.visible = false;
+        self
+    }
+
+    /// Clip the layer to the layer below it
+    pub fn clipped(mut self) -> FixtureLayer {
+        self.clipped = true;
+        self
+    }
+
+    /// Give the layer a user mask whose top left pixel is at `left` and `top`, with `width`
+    /// pixels in each row. Pixels outside of the mask take the `default_color`.
+    pub fn mask(
+        mut self,
+        left: i32,
+        top: i32,
+        width: u32,
+        pixels: Vec<u8>,
+        default_color: u8,
+    ) -> FixtureLayer {
+        assert_eq!(pixels.len() % width.max(1) as usize, 0);
+
+        self.mask = Some(FixtureMask {
+            left,
+            top,
+            width,
+            pixels,
+            default_color,
+        });
+        self
+    }
+
+    /// Add an additional layer information block to the layer's record, such as a block that
+    /// we can't parse
+    pub fn tagged_block(mut self, key: [u8; 4], data: Vec<u8>) -> FixtureLayer {
+        self.tagged_blocks.push(TaggedBlock { key, data });
+        self
+    }
+}
+
+/// The compressed data of one of a fixture layer's channels
+#[derive(Debug)]
+struct FixtureChannel {
+    kind: PsdChannelKind,
+    compression: PsdChannelCompression,
+    scanline_byte_counts: Vec<u32>,
+    data: Vec<u8>,
+}
+
+/// Groups don't have any pixels, but Photoshop still gives them empty color and transparency
+/// channels
+fn empty_channels() -> Vec<FixtureChannel> {
+    [PsdChannelKind::TransparencyMask]
+        .iter()
+        .chain(COLOR_CHANNELS.iter())
+        .map(|kind| FixtureChannel {
+            kind: *kind,
+            compression: PsdChannelCompression::RawData,
+            scanline_byte_counts: vec![],
+            data: vec![],
+        })
+        .collect()
+}
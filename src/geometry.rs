@@ -0,0 +1,242 @@
+use std::fmt::Write;
+
+use crate::layer_tree::{layer_tree, LayerTreeNode};
+use crate::Psd;
+
+/// Whether a node in the layer tree is a group or a layer
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum LayerNodeKind {
+    /// A group of layers
+    Group,
+    /// A layer
+    Layer,
+}
+
+impl LayerNodeKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            LayerNodeKind::Group => "group",
+            LayerNodeKind::Layer => "layer",
+        }
+    }
+}
+
+/// The position and size of a group or layer, without any of its pixels.
+///
+/// Useful for reconstructing a layout designed in Photoshop inside of a game engine or UI
+/// framework. See [`Psd::layer_geometry`].
+///
+/// [`Psd::layer_geometry`]: crate::Psd::layer_geometry
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayerGeometry {
+    pub(crate) path: String,
+    pub(crate) kind: LayerNodeKind,
+    pub(crate) left: i32,
+    pub(crate) top: i32,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) anchor: (f32, f32),
+}
+
+impl LayerGeometry {
+    /// The names of the groups that the node is nested in followed by the node's name,
+    /// separated by `/`. For example, "Character/Face/Eyes".
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Whether the node is a group or a layer
+    pub fn kind(&self) -> LayerNodeKind {
+        self.kind
+    }
+
+    /// The left edge of the node, in document pixels
+    pub fn left(&self) -> i32 {
+        self.left
+    }
+
+    /// The top edge of the node, in document pixels
+    pub fn top(&self) -> i32 {
+        self.top
+    }
+
+    /// The width of the node, in pixels.
+    ///
+    /// A group's bounds are the union of the bounds of the layers inside of it.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// The height of the node, in pixels
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The center of the node as a fraction of the document's (width, height), where
+    /// (0.0, 0.0) is the top left corner of the document and (1.0, 1.0) is the bottom right.
+    pub fn anchor(&self) -> (f32, f32) {
+        self.anchor
+    }
+}
+
+/// The geometry of every group and layer in the PSD, with groups coming right before their
+/// children.
+pub(crate) fn layer_geometry(psd: &Psd) -> Vec<LayerGeometry> {
+    let (psd_width, psd_height) = (psd.width() as f32, psd.height() as f32);
+
+    layer_tree(psd)
+        .into_iter()
+        .map(|entry| {
+            let (kind, left, top, right, bottom) = match entry.node {
+                LayerTreeNode::Layer(layer) => (
+                    LayerNodeKind::Layer,
+                    layer.layer_left(),
+                    layer.layer_top(),
+                    layer.layer_left() + layer.width() as i32,
+                    layer.layer_top() + layer.height() as i32,
+                ),
+                LayerTreeNode::Group(group) => {
+                    let layers = &psd.layers()[group.contained_layers.clone()];
+                    let pixel_layers = layers.iter().filter(|layer| !layer.is_empty());
+                    let bounds = pixel_layers.fold(None, |bounds, layer| {
+                        let (left, top) = (layer.layer_left(), layer.layer_top());
+                        let right = left + layer.width() as i32;
+                        let bottom = top + layer.height() as i32;
+
+                        Some(match bounds {
+                            Some((l, t, r, b)) => {
+                                (left.min(l), top.min(t), right.max(r), bottom.max(b))
+                            }
+                            None => (left, top, right, bottom),
+                        })
+                    });
+                    let (left, top, right, bottom) = bounds.unwrap_or((0, 0, 0, 0));
+
+                    (LayerNodeKind::Group, left, top, right, bottom)
+                }
+            };
+
+            let anchor = (
+                (left + right) as f32 / 2. / psd_width,
+                (top + bottom) as f32 / 2. / psd_height,
+            );
+
+            LayerGeometry {
+                path: entry.path,
+                kind,
+                left,
+                top,
+                width: (right - left) as u32,
+                height: (bottom - top) as u32,
+                anchor,
+            }
+        })
+        .collect()
+}
+
+/// A JSON array with one object per node.
+pub(crate) fn to_json(geometry: &[LayerGeometry]) -> String {
+    let mut json = String::from("[");
+
+    for (idx, node) in geometry.iter().enumerate() {
+        if idx > 0 {
+            json.push(',');
+        }
+
+        write!(
+            json,
+            r#"{{"path":{},"kind":"{}","left":{},"top":{},"width":{},"height":{},"anchor":[{},{}]}}"#,
+            json_string(&node.path),
+            node.kind.as_str(),
+            node.left,
+            node.top,
+            node.width,
+            node.height,
+            node.anchor.0,
+            node.anchor.1
+        )
+        .unwrap();
+    }
+
+    json.push(']');
+    json
+}
+
+/// CSV with a header row and one row per node.
+pub(crate) fn to_csv(geometry: &[LayerGeometry]) -> String {
+    let mut csv = String::from("path,kind,left,top,width,height,anchor_x,anchor_y\n");
+
+    for node in geometry {
+        writeln!(
+            csv,
+            "{},{},{},{},{},{},{},{}",
+            csv_field(&node.path),
+            node.kind.as_str(),
+            node.left,
+            node.top,
+            node.width,
+            node.height,
+            node.anchor.0,
+            node.anchor.1
+        )
+        .unwrap();
+    }
+
+    csv
+}
+
+pub(crate) fn json_string(string: &str) -> String {
+    let mut escaped = String::with_capacity(string.len() + 2);
+    escaped.push('"');
+
+    for c in string.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(escaped, "\\u{:04x}", c as u32).unwrap(),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped.push('"');
+    escaped
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Verify that names that would break the output are escaped.
+    #[test]
+    fn escapes_names() {
+        let geometry = vec![LayerGeometry {
+            path: "a \"b\",\nc\\".to_string(),
+            kind: LayerNodeKind::Layer,
+            left: -1,
+            top: 2,
+            width: 3,
+            height: 4,
+            anchor: (0.25, 0.5),
+        }];
+
+        assert_eq!(
+            to_json(&geometry),
+            r#"[{"path":"a \"b\",\nc\\","kind":"layer","left":-1,"top":2,"width":3,"height":4,"anchor":[0.25,0.5]}]"#
+        );
+        assert_eq!(
+            to_csv(&geometry),
+            "path,kind,left,top,width,height,anchor_x,anchor_y\n\"a \"\"b\"\",\nc\\\",layer,-1,2,3,4,0.25,0.5\n"
+        );
+    }
+}
@@ -0,0 +1,141 @@
+//! Conversions between the linear floating point pixels of 32 bit PSDs and 8 bit sRGB pixels.
+
+use crate::psd_channel::{f32_samples, for_each_channel_row, RgbaChannel};
+use crate::sections::image_data_section::ChannelBytes;
+
+/// Interleave channels of 32 bit float samples into linear RGBA pixels.
+///
+/// Like 8 bit images, a single channel is grey and the pixels are opaque without an alpha
+/// channel.
+pub(crate) fn interleave_f32(
+    red: &ChannelBytes,
+    green: Option<&ChannelBytes>,
+    blue: Option<&ChannelBytes>,
+    alpha: Option<&ChannelBytes>,
+    width: u32,
+    height: u32,
+) -> Vec<f32> {
+    let width = width as usize;
+    let mut rgba = vec![0.; width * height as usize * 4];
+
+    let channels = [
+        (RgbaChannel::Red, Some(red)),
+        (RgbaChannel::Green, green.or(Some(red))),
+        (RgbaChannel::Blue, blue.or(Some(red))),
+        (RgbaChannel::Alpha, alpha),
+    ];
+    for (rgba_channel, channel) in channels.iter() {
+        let offset = rgba_channel.offset();
+
+        let channel = match channel {
+            Some(channel) => channel,
+            None => {
+                for pixel in rgba.chunks_exact_mut(4) {
+                    pixel[offset] = 1.;
+                }
+                continue;
+            }
+        };
+
+        for_each_channel_row(channel, None, width * 4, 0..usize::MAX, |row_idx, row| {
+            for (idx, sample) in f32_samples(row).enumerate() {
+                if let Some(value) = rgba.get_mut((row_idx * width + idx) * 4 + offset) {
+                    *value = sample;
+                }
+            }
+        });
+    }
+
+    rgba
+}
+
+/// Tonemap linear RGBA pixels into 8 bit sRGB pixels. Alpha is kept linear.
+pub(crate) fn tonemap_rgba(rgba: &[f32]) -> Vec<u8> {
+    rgba.chunks_exact(4)
+        .flat_map(|pixel| {
+            [
+                tonemap(pixel[0]),
+                tonemap(pixel[1]),
+                tonemap(pixel[2]),
+                unit_to_u8(pixel[3]),
+            ]
+        })
+        .collect()
+}
+
+/// Decode 8 bit sRGB pixels into linear RGBA pixels. Alpha is mapped to 0.0 - 1.0.
+pub(crate) fn linearize_rgba(rgba: &[u8]) -> Vec<f32> {
+    rgba.chunks_exact(4)
+        .flat_map(|pixel| {
+            [
+                srgb_to_linear(pixel[0]),
+                srgb_to_linear(pixel[1]),
+                srgb_to_linear(pixel[2]),
+                pixel[3] as f32 / 255.,
+            ]
+        })
+        .collect()
+}
+
+/// Encode a linear color value as an 8 bit sRGB value.
+///
+/// Values outside of 0.0 - 1.0 are clamped, the same as Photoshop's preview of a 32 bit
+/// document with an exposure of 0 and a gamma of 1.
+pub(crate) fn tonemap(linear: f32) -> u8 {
+    let linear = clamp_unit(linear);
+
+    let srgb = if linear <= 0.003_130_8 {
+        linear * 12.92
+    } else {
+        1.055 * linear.powf(1.0 / 2.4) - 0.055
+    };
+
+    (srgb * 255.0).round() as u8
+}
+
+/// Decode an 8 bit sRGB value into a linear color value between 0.0 and 1.0.
+pub(crate) fn srgb_to_linear(srgb: u8) -> f32 {
+    let srgb = srgb as f32 / 255.0;
+
+    if srgb <= 0.040_45 {
+        srgb / 12.92
+    } else {
+        ((srgb + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Map a value between 0.0 and 1.0, such as an alpha, to 0 - 255.
+pub(crate) fn unit_to_u8(value: f32) -> u8 {
+    (clamp_unit(value) * 255.0).round() as u8
+}
+
+/// Clamp to 0.0 - 1.0, treating NaN as 0.0
+fn clamp_unit(value: f32) -> f32 {
+    if value.is_nan() {
+        0.
+    } else {
+        value.clamp(0., 1.)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Verify that tonemapping clamps HDR values and round trips through sRGB decoding.
+    #[test]
+    fn tonemap_round_trip() {
+        assert_eq!(tonemap(-1.), 0);
+        assert_eq!(tonemap(0.), 0);
+        assert_eq!(tonemap(1.), 255);
+        assert_eq!(tonemap(8.), 255);
+        assert_eq!(tonemap(f32::NAN), 0);
+
+        // Linear 0.2159 is about halfway in sRGB
+        assert_eq!(tonemap(0.2159), 128);
+
+        for srgb in 0..=255 {
+            assert_eq!(tonemap(srgb_to_linear(srgb)), srgb);
+        }
+    }
+}
@@ -0,0 +1,362 @@
+//! Integer versions of the compositing in `blend`, used instead of it with the
+//! `integer-compositor` cargo feature.
+//!
+//! Colors and alphas stay in 0 to 255 instead of being normalized to 0.0 to 1.0, and every
+//! division is rounded to the nearest integer, so every target renders the same bits.
+
+use crate::blend::Pixel;
+use crate::sections::file_header_section::PsdDepth;
+use crate::sections::layer_and_mask_information_section::layer::BlendMode;
+
+/// The largest value of an 8-bit channel, which stands in for 1.0
+const MAX: i64 = 255;
+
+type BlendFunction = fn(i64, i64) -> i64;
+
+/// `blend::blend_pixels_at_depth` without floats.
+///
+/// `αo = αs + αb x (1 - αs)` and `co = Cs x αs + Cb x αb x (1 - αs)` are kept scaled by 255 and
+/// 255 cubed until the final division, so the only rounding is in the blend function and in the
+/// final division.
+pub(crate) fn blend_pixels_at_depth(
+    top: Pixel,
+    bottom: Pixel,
+    blend_mode: BlendMode,
+    depth: PsdDepth,
+    out: &mut Pixel,
+) {
+    let [r_s, g_s, b_s, alpha_s] = top.map(i64::from);
+    let [r_b, g_b, b_b, alpha_b] = bottom.map(i64::from);
+    // Scaled by 255 squared
+    let alpha_output = alpha_s * MAX + alpha_b * (MAX - alpha_s);
+
+    let blend_f = map_blend_mode(blend_mode, depth);
+    let (r, g, b) = (
+        composite(r_s, alpha_s, r_b, alpha_b, blend_f),
+        composite(g_s, alpha_s, g_b, alpha_b, blend_f),
+        composite(b_s, alpha_s, b_b, alpha_b, blend_f),
+    );
+
+    // NOTE: make all assignments _after_ all reads to avoid issues when top or bottom is out
+    // Clamping clips 32-bit values that ended up outside of [0, 255]
+    let channel = |color: i64| {
+        if alpha_output == 0 {
+            0
+        } else {
+            div_round(color, alpha_output).clamp(0, MAX) as u8
+        }
+    };
+    out[0] = channel(r);
+    out[1] = channel(g);
+    out[2] = channel(b);
+    out[3] = div_round(alpha_output, MAX) as u8;
+}
+
+/// `blend::fade` without floats.
+pub(crate) fn fade(below: Pixel, above: Pixel, opacity: u8) -> Pixel {
+    let opacity = opacity as i64;
+    let (alpha_b, alpha_a) = (below[3] as i64, above[3] as i64);
+    // Scaled by 255 squared
+    let alpha_output = alpha_b * MAX + (alpha_a - alpha_b) * opacity;
+
+    let mut out = [0; 4];
+    if alpha_output > 0 {
+        for channel in 0..3 {
+            let color_b = below[channel] as i64 * alpha_b;
+            let color_a = above[channel] as i64 * alpha_a;
+            let color = color_b * MAX + (color_a - color_b) * opacity;
+
+            out[channel] = div_round(color, alpha_output).clamp(0, MAX) as u8;
+        }
+    }
+    out[3] = div_round(alpha_output, MAX) as u8;
+
+    out
+}
+
+/// `numerator / denominator` rounded to the nearest integer, with halves rounded away from zero
+/// like `f32::round`. The denominator must be positive.
+fn div_round(numerator: i64, denominator: i64) -> i64 {
+    if numerator < 0 {
+        -((-numerator + denominator / 2) / denominator)
+    } else {
+        (numerator + denominator / 2) / denominator
+    }
+}
+
+/// The largest integer whose square is at most `n`
+fn isqrt(n: i64) -> i64 {
+    if n < 2 {
+        return n.max(0);
+    }
+
+    // Newton's method, starting above the root so that it descends onto it
+    let mut root = n;
+    let mut next = (root + 1) / 2;
+    while next < root {
+        root = next;
+        next = (root + n / root) / 2;
+    }
+    root
+}
+
+/// The same functions as `blend::map_blend_mode`, in 0 to 255. Only the blend modes that
+/// `blend::is_supported` are listed, since `blend::blend_pixels_at_depth` panics on the others
+/// before they get here.
+fn map_blend_mode(blend_mode: BlendMode, depth: PsdDepth) -> BlendFunction {
+    let high_dynamic_range = depth == PsdDepth::ThirtyTwo;
+
+    match blend_mode {
+        BlendMode::Darken => darken,
+        BlendMode::Multiply => multiply,
+        BlendMode::ColorBurn => color_burn,
+        BlendMode::LinearBurn => linear_burn,
+        BlendMode::Lighten => lighten,
+        BlendMode::Screen => screen,
+        BlendMode::ColorDodge => color_dodge,
+        BlendMode::LinearDodge => linear_dodge,
+        BlendMode::Overlay => overlay,
+        BlendMode::SoftLight => soft_light,
+        BlendMode::HardLight => hard_light,
+        BlendMode::Difference => difference,
+        BlendMode::Exclusion => exclusion,
+        BlendMode::Subtract if high_dynamic_range => subtract_unclamped,
+        BlendMode::Subtract => subtract,
+        BlendMode::Divide if high_dynamic_range => divide_unclamped,
+        BlendMode::Divide => divide,
+        BlendMode::Normal => normal,
+        _ => unreachable!(
+            "{:?} is checked by blend::blend_pixels_at_depth",
+            blend_mode
+        ),
+    }
+}
+
+fn normal(_color_b: i64, color_s: i64) -> i64 {
+    color_s
+}
+
+fn darken(color_b: i64, color_s: i64) -> i64 {
+    color_b.min(color_s)
+}
+
+fn multiply(color_b: i64, color_s: i64) -> i64 {
+    div_round(color_b * color_s, MAX)
+}
+
+fn color_burn(color_b: i64, color_s: i64) -> i64 {
+    if color_b == MAX {
+        MAX
+    } else if color_b == 0 {
+        0
+    } else {
+        (MAX - div_round((MAX - color_s) * MAX, color_b)).max(0)
+    }
+}
+
+/// Matches `blend::linear_burn`
+fn linear_burn(color_b: i64, color_s: i64) -> i64 {
+    (color_b - color_s - MAX).max(0)
+}
+
+fn lighten(color_b: i64, color_s: i64) -> i64 {
+    color_b.max(color_s)
+}
+
+fn screen(color_b: i64, color_s: i64) -> i64 {
+    color_b + color_s - div_round(color_b * color_s, MAX)
+}
+
+fn color_dodge(color_b: i64, color_s: i64) -> i64 {
+    if color_b == 0 {
+        0
+    } else if color_s == MAX {
+        MAX
+    } else {
+        div_round(color_b * MAX, MAX - color_s).min(MAX)
+    }
+}
+
+fn linear_dodge(color_b: i64, color_s: i64) -> i64 {
+    (color_b + color_s).min(MAX)
+}
+
+fn overlay(color_b: i64, color_s: i64) -> i64 {
+    hard_light(color_s, color_b)
+}
+
+fn soft_light(color_b: i64, color_s: i64) -> i64 {
+    // `((16 x Cb - 12) x Cb + 4) x Cb` below a quarter, `sqrt(Cb)` above it
+    let d = if color_b * 4 <= MAX {
+        div_round(
+            div_round((16 * color_b - 12 * MAX) * color_b, MAX) * color_b + 4 * MAX * color_b,
+            MAX,
+        )
+    } else {
+        isqrt(color_b * MAX)
+    };
+
+    if color_s * 2 <= MAX {
+        color_b - div_round((MAX - 2 * color_s) * color_b * (MAX - color_b), MAX * MAX)
+    } else {
+        color_b + div_round((2 * color_s - MAX) * (d - color_b), MAX)
+    }
+}
+
+fn hard_light(color_b: i64, color_s: i64) -> i64 {
+    if color_s * 2 < MAX {
+        multiply(color_b, 2 * color_s)
+    } else {
+        screen(color_b, 2 * color_s - MAX)
+    }
+}
+
+fn difference(color_b: i64, color_s: i64) -> i64 {
+    (color_b - color_s).abs()
+}
+
+fn exclusion(color_b: i64, color_s: i64) -> i64 {
+    color_b + color_s - div_round(2 * color_b * color_s, MAX)
+}
+
+fn subtract(color_b: i64, color_s: i64) -> i64 {
+    subtract_unclamped(color_b, color_s).max(0)
+}
+
+fn subtract_unclamped(color_b: i64, color_s: i64) -> i64 {
+    color_b - color_s
+}
+
+fn divide(color_b: i64, color_s: i64) -> i64 {
+    divide_unclamped(color_b, color_s).min(MAX)
+}
+
+fn divide_unclamped(color_b: i64, color_s: i64) -> i64 {
+    if color_s == 0 {
+        if color_b == 0 {
+            0
+        } else {
+            MAX
+        }
+    } else {
+        div_round(color_b * MAX, color_s)
+    }
+}
+
+/// `blend::composite` with `Cs`, `αs`, `Cb` and `αb` in 0 to 255, returning `co` scaled by 255
+/// cubed.
+fn composite(
+    color_s: i64,
+    alpha_s: i64,
+    color_b: i64,
+    alpha_b: i64,
+    blend_f: BlendFunction,
+) -> i64 {
+    let color_s = (MAX - alpha_b) * color_s + alpha_b * blend_f(color_b, color_s);
+    let cs = color_s * alpha_s;
+    let cb = color_b * alpha_b;
+    cs + cb * (MAX - alpha_s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blend;
+
+    /// The integer compositor stays within a couple of values of the float one for every
+    /// supported blend mode.
+    #[test]
+    fn matches_float_blending() {
+        let blend_modes = [
+            BlendMode::Normal,
+            BlendMode::Darken,
+            BlendMode::Multiply,
+            BlendMode::ColorBurn,
+            BlendMode::LinearBurn,
+            BlendMode::Lighten,
+            BlendMode::Screen,
+            BlendMode::ColorDodge,
+            BlendMode::LinearDodge,
+            BlendMode::Overlay,
+            BlendMode::SoftLight,
+            BlendMode::HardLight,
+            BlendMode::Difference,
+            BlendMode::Exclusion,
+            BlendMode::Subtract,
+            BlendMode::Divide,
+        ];
+        let values = [0, 1, 37, 64, 127, 128, 200, 254, 255];
+
+        for blend_mode in blend_modes.iter() {
+            for depth in [PsdDepth::Eight, PsdDepth::ThirtyTwo].iter() {
+                for &color_s in values.iter() {
+                    for &color_b in values.iter() {
+                        for &alpha in values.iter() {
+                            let top = [color_s, color_b, color_s, alpha];
+                            let bottom = [color_b, color_s, 255 - color_b, 255 - alpha / 2];
+
+                            let (mut float, mut integer) = ([0; 4], [0; 4]);
+                            blend::blend_pixels_at_depth_float(
+                                top,
+                                bottom,
+                                *blend_mode,
+                                *depth,
+                                &mut float,
+                            );
+                            blend_pixels_at_depth(top, bottom, *blend_mode, *depth, &mut integer);
+
+                            for channel in 0..4 {
+                                let difference =
+                                    (float[channel] as i16 - integer[channel] as i16).abs();
+                                assert!(
+                                    difference <= 2,
+                                    "{:?} {:?} {:?} over {:?}: {:?} vs {:?}",
+                                    blend_mode,
+                                    depth,
+                                    top,
+                                    bottom,
+                                    float,
+                                    integer
+                                );
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Fading stays within one value of the float fade.
+    #[test]
+    fn matches_float_fade() {
+        let values = [0, 1, 64, 128, 200, 255];
+
+        for &opacity in values.iter() {
+            for &alpha_b in values.iter() {
+                for &alpha_a in values.iter() {
+                    let below = [10, 128, 250, alpha_b];
+                    let above = [240, 0, 100, alpha_a];
+
+                    let float = blend::fade_float(below, above, opacity);
+                    let integer = fade(below, above, opacity);
+                    for channel in 0..4 {
+                        assert!(
+                            (float[channel] as i16 - integer[channel] as i16).abs() <= 1,
+                            "{:?} vs {:?}",
+                            float,
+                            integer
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn integer_square_root() {
+        for n in 0..100_000 {
+            let root = isqrt(n);
+            assert!(root * root <= n && (root + 1) * (root + 1) > n, "{}", n);
+        }
+    }
+}
@@ -0,0 +1,155 @@
+//! Writing each layer, and its mask, as a PNG file.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::channel_integrity::crc32;
+use crate::Psd;
+
+/// The 8 bytes that every PNG file starts with
+const PNG_SIGNATURE: [u8; 8] = [137, 80, 78, 71, 13, 10, 26, 10];
+/// The compression level of the image data, the same level that we use for ZIP channels
+const PNG_COMPRESSION_LEVEL: u8 = 6;
+
+/// The color type of a PNG's pixels
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub(crate) enum PngColor {
+    /// One byte per pixel
+    Gray = 0,
+    /// Four bytes per pixel
+    Rgba = 6,
+}
+
+impl PngColor {
+    fn bytes_per_pixel(&self) -> usize {
+        match self {
+            PngColor::Gray => 1,
+            PngColor::Rgba => 4,
+        }
+    }
+}
+
+/// Write every layer of the PSD into the directory as a PNG the size of the PSD, along with a
+/// grayscale PNG of its user mask for the layers that have one.
+///
+/// Returns the paths of the files that were written, in the same order as `Psd::layers`.
+pub(crate) fn write_layer_pngs(psd: &Psd, dir: &Path) -> io::Result<Vec<PathBuf>> {
+    fs::create_dir_all(dir)?;
+
+    let mut written = vec![];
+    for (idx, layer) in psd.layers().iter().enumerate() {
+        let file_name = layer_file_name(idx, layer.name());
+
+        let path = dir.join(format!("{}.png", file_name));
+        let mut rgba = layer.rgba();
+        // Empty layers don't have any pixels, so they're written as transparent images
+        rgba.resize(psd.width() as usize * psd.height() as usize * 4, 0);
+        let png = encode_png(psd.width(), psd.height(), PngColor::Rgba, &rgba);
+        fs::write(&path, png)?;
+        written.push(path);
+
+        if let Some(mask) = layer.mask_gray() {
+            let path = dir.join(format!("{}-mask.png", file_name));
+            fs::write(
+                &path,
+                encode_png(psd.width(), psd.height(), PngColor::Gray, &mask),
+            )?;
+            written.push(path);
+        }
+    }
+
+    Ok(written)
+}
+
+/// The layer's index in `Psd::layers` followed by its name, with the characters that aren't safe
+/// in file names replaced, such as `3-Arm_Left`. The index keeps layers with the same name apart.
+fn layer_file_name(idx: usize, name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    format!("{}-{}", idx, name)
+}
+
+/// Encode 8 bit pixels as a PNG, without any filtering.
+pub(crate) fn encode_png(width: u32, height: u32, color: PngColor, pixels: &[u8]) -> Vec<u8> {
+    let row_len = width as usize * color.bytes_per_pixel();
+
+    // Each row starts with its filter type, 0 for none
+    let mut rows = Vec::with_capacity((row_len + 1) * height as usize);
+    for row in pixels.chunks_exact(row_len.max(1)).take(height as usize) {
+        rows.push(0);
+        rows.extend_from_slice(row);
+    }
+
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&width.to_be_bytes());
+    header.extend_from_slice(&height.to_be_bytes());
+    // Bit depth, color type, compression, filter and interlace methods
+    header.extend_from_slice(&[8, color as u8, 0, 0, 0]);
+
+    let mut png = PNG_SIGNATURE.to_vec();
+    write_chunk(&mut png, b"IHDR", &header);
+    write_chunk(
+        &mut png,
+        b"IDAT",
+        &miniz_oxide::deflate::compress_to_vec_zlib(&rows, PNG_COMPRESSION_LEVEL),
+    );
+    write_chunk(&mut png, b"IEND", &[]);
+
+    png
+}
+
+/// | Length | Description                                    |
+/// |--------|------------------------------------------------|
+/// | 4      | Length of the data                             |
+/// | 4      | Chunk type                                     |
+/// | Variable | Data                                         |
+/// | 4      | CRC of the chunk type and data                 |
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+
+    let crc = crc32(crc32(0, kind), data);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Verify that the image data holds each row after its filter type.
+    #[test]
+    fn encodes_rows() {
+        let png = encode_png(2, 2, PngColor::Gray, &[1, 2, 3, 4]);
+        assert_eq!(&png[..8], &PNG_SIGNATURE);
+        assert_eq!(&png[12..16], b"IHDR");
+        // Width, height, bit depth and color type
+        assert_eq!(&png[16..26], &[0, 0, 0, 2, 0, 0, 0, 2, 8, 0]);
+
+        let idat_len = u32::from_be_bytes([png[33], png[34], png[35], png[36]]) as usize;
+        assert_eq!(&png[37..41], b"IDAT");
+        let rows = miniz_oxide::inflate::decompress_to_vec_zlib(&png[41..41 + idat_len]).unwrap();
+        assert_eq!(rows, vec![0, 1, 2, 0, 3, 4]);
+
+        assert_eq!(
+            &png[png.len() - 12..],
+            &[0, 0, 0, 0, 73, 69, 78, 68, 174, 66, 96, 130]
+        );
+    }
+
+    #[test]
+    fn layer_file_names() {
+        assert_eq!(layer_file_name(3, "Arm Left"), "3-Arm_Left");
+        assert_eq!(layer_file_name(0, "a/b.png"), "0-a_b_png");
+    }
+}
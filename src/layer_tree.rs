@@ -0,0 +1,155 @@
+use std::iter::Peekable;
+
+use crate::sections::layer_and_mask_information_section::layers::LAYER_PATH_SEPARATOR;
+use crate::{Psd, PsdGroup, PsdLayer};
+
+/// A group, along with the groups and layers inside of it, or a layer.
+///
+/// Created by [`Psd::tree`].
+///
+/// [`Psd::tree`]: crate::Psd::tree
+#[derive(Debug, Clone, PartialEq)]
+pub enum PsdNode<'a> {
+    /// A group and its children
+    Group {
+        /// The group. Its name, opacity, blend mode and so on are available through
+        /// [`PsdGroup`]'s `Deref` to `LayerProperties`.
+        properties: &'a PsdGroup,
+        /// The groups and layers directly inside of the group, in stacking order
+        children: Vec<PsdNode<'a>>,
+    },
+    /// A layer
+    Layer(&'a PsdLayer),
+}
+
+impl<'a> PsdNode<'a> {
+    /// The name of the group or layer
+    pub fn name(&self) -> &'a str {
+        match self {
+            PsdNode::Group { properties, .. } => properties.name(),
+            PsdNode::Layer(layer) => layer.name(),
+        }
+    }
+
+    /// The groups and layers directly inside of a group. Empty for layers.
+    pub fn children(&self) -> &[PsdNode<'a>] {
+        match self {
+            PsdNode::Group { children, .. } => children,
+            PsdNode::Layer(_) => &[],
+        }
+    }
+}
+
+/// A group or a layer in the tree of a PSD's layers
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum LayerTreeNode<'a> {
+    Group(&'a PsdGroup),
+    Layer(&'a PsdLayer),
+}
+
+/// A node in the tree of a PSD's layers, along with where it is in the tree.
+#[derive(Debug)]
+pub(crate) struct LayerTreeEntry<'a> {
+    pub(crate) node: LayerTreeNode<'a>,
+    /// The number of groups that the node is nested in
+    pub(crate) depth: usize,
+    /// The names of the groups that the node is nested in followed by the node's name,
+    /// separated by `LAYER_PATH_SEPARATOR`.
+    pub(crate) path: String,
+}
+
+/// Every group and layer in the PSD, with groups coming right before their children and nodes
+/// otherwise in the order that they are stored in the file.
+pub(crate) fn layer_tree(psd: &Psd) -> Vec<LayerTreeEntry<'_>> {
+    let mut entries = Vec::with_capacity(psd.layers().len() + psd.groups().len());
+    push_children(psd, None, 0, "", &mut entries);
+    entries
+}
+
+/// The top level groups and layers of the PSD, with every group holding its children.
+pub(crate) fn node_tree(psd: &Psd) -> Vec<PsdNode<'_>> {
+    let mut entries = layer_tree(psd).into_iter().peekable();
+    nodes_at_depth(&mut entries, 0)
+}
+
+/// Take the entries at `depth` (along with the entries nested in them) until an entry that is
+/// less deeply nested.
+fn nodes_at_depth<'a, I: Iterator<Item = LayerTreeEntry<'a>>>(
+    entries: &mut Peekable<I>,
+    depth: usize,
+) -> Vec<PsdNode<'a>> {
+    let mut nodes = vec![];
+
+    while let Some(entry) = entries.next_if(|entry| entry.depth == depth) {
+        nodes.push(match entry.node {
+            LayerTreeNode::Group(group) => PsdNode::Group {
+                properties: group,
+                children: nodes_at_depth(entries, depth + 1),
+            },
+            LayerTreeNode::Layer(layer) => PsdNode::Layer(layer),
+        });
+    }
+
+    nodes
+}
+
+/// Push the groups and layers whose parent is `parent_id` (`None` for the top level).
+fn push_children<'a>(
+    psd: &'a Psd,
+    parent_id: Option<u32>,
+    depth: usize,
+    parent_path: &str,
+    entries: &mut Vec<LayerTreeEntry<'a>>,
+) {
+    let mut child_groups: Vec<&PsdGroup> = psd
+        .groups_in_order()
+        .filter(|group| group.parent_id() == parent_id)
+        .collect();
+    // A group comes before the first layer that it contains
+    child_groups.sort_by_key(|group| group.contained_layers.start);
+    let mut child_groups = child_groups.into_iter().peekable();
+
+    for (idx, layer) in psd.layers().iter().enumerate() {
+        while let Some(group) = child_groups.next_if(|g| g.contained_layers.start <= idx) {
+            push_group(psd, group, depth, parent_path, entries);
+        }
+
+        if layer.parent_id() == parent_id {
+            entries.push(LayerTreeEntry {
+                node: LayerTreeNode::Layer(layer),
+                depth,
+                path: child_path(parent_path, layer.name()),
+            });
+        }
+    }
+
+    for group in child_groups {
+        push_group(psd, group, depth, parent_path, entries);
+    }
+}
+
+fn push_group<'a>(
+    psd: &'a Psd,
+    group: &'a PsdGroup,
+    depth: usize,
+    parent_path: &str,
+    entries: &mut Vec<LayerTreeEntry<'a>>,
+) {
+    let path = child_path(parent_path, group.name());
+
+    entries.push(LayerTreeEntry {
+        node: LayerTreeNode::Group(group),
+        depth,
+        path: path.clone(),
+    });
+
+    push_children(psd, Some(group.id()), depth + 1, &path, entries);
+}
+
+fn child_path(parent_path: &str, name: &str) -> String {
+    if parent_path.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}{}{}", parent_path, LAYER_PATH_SEPARATOR, name)
+    }
+}
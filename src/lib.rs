@@ -4,43 +4,205 @@
 //! This will help you better understand the current approach and discover ways to improve it.
 //!
 //! psd spec: https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/
+//!
+//! ## Unstable APIs
+//!
+//! Parsing, rendering and flattening follow semver. Subsystems that are still taking shape, such
+//! as writing PSDs, layer effects, smart objects and text layers, are only available with the
+//! `unstable` cargo feature and may change in any release.
+//!
+//! ## Parallel rendering
+//!
+//! With the `rayon` cargo feature the channels of each layer are decompressed at the same time,
+//! and [`RenderPipeline`] decodes the layers and flattens bands of rows on every core. The pixels
+//! are the same either way.
+//!
+//! ## Deterministic compositing
+//!
+//! Layers are blended in floating point by default. With the `integer-compositor` cargo feature
+//! they are blended and faded with integer math instead, so that a render is the same bits on
+//! every target, such as wasm and x86, which makes it safe to hash. The results can be a value or
+//! two off from the floating point ones. Resizing, effects and adjustments still use floats.
+//! Both compositors support the same blend modes, and panic on the same unsupported ones, see
+//! [`FidelityIssueKind::UnsupportedBlendMode`].
 
 #![deny(missing_docs)]
 
-use std::collections::HashMap;
-use std::ops::Deref;
+use std::any::Any;
+use std::io::{self, Read, Seek, Write};
+use std::panic::{RefUnwindSafe, UnwindSafe};
+#[cfg(feature = "unstable")]
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
 
 use thiserror::Error;
 
-use sections::file_header_section::FileHeaderSectionError;
-use sections::image_data_section::ImageDataSectionError;
-use sections::image_resources_section::ImageResourcesSectionError;
-use sections::layer_and_mask_information_section::layer::PsdLayerError;
+pub use sections::file_header_section::FileHeaderSectionError;
+pub use sections::image_data_section::ImageDataSectionError;
+pub use sections::image_resources_section::ImageResourcesSectionError;
+pub use sections::layer_and_mask_information_section::layer::PsdLayerError;
 
-use crate::psd_channel::IntoRgba;
-pub use crate::psd_channel::{PsdChannelCompression, PsdChannelKind};
-pub use crate::sections::file_header_section::{ColorMode, PsdDepth};
+pub use crate::channel_integrity::{
+    ChannelChecksum, ChannelCorruption, ChannelReport, CorruptChannel,
+};
+pub use crate::color_stats::{AlphaSummary, ColorHistogram};
+pub use crate::composite::CompositeDiff;
+pub use crate::exif_orientation::ExifOrientation;
+pub use crate::fidelity::{FidelityIssue, FidelityIssueKind, RenderFidelityReport};
+pub use crate::geometry::{LayerGeometry, LayerNodeKind};
+pub use crate::layer_tree::PsdNode;
+use crate::node_data::NodeDataMap;
+pub use crate::node_data::NodeId;
+pub use crate::parse_job::{ParseJob, ParseProgress};
+pub use crate::parse_stats::ParseStats;
+use crate::parse_stats::{compressed_len, timed};
+use crate::psd_channel::{IntoRgba, RleRows};
+pub use crate::psd_channel::{PsdChannelCompression, PsdChannelError, PsdChannelKind, RgbaChannel};
+pub use crate::psd_reader::PsdReader;
+pub use crate::render::{LayerRgbaCache, RenderPipeline, TrimmedRgba};
+pub use crate::render_plan::{DrawCommand, GroupDraw, LayerDraw, RenderPlan};
+use crate::resize::Resize;
+pub use crate::resize::ResizeFilter;
+pub use crate::rgba_layout::RgbaLayout;
+use crate::sections::color_mode_data_section;
+pub use crate::sections::file_header_section::{ColorMode, PsdDepth, PsdVersion};
 use crate::sections::image_data_section::ChannelBytes;
 use crate::sections::image_data_section::ImageDataSection;
-pub use crate::sections::image_resources_section::ImageResource;
-use crate::sections::image_resources_section::ImageResourcesSection;
+#[cfg(feature = "unstable")]
+use crate::sections::image_resources_section::path::{
+    RESOURCE_FIRST_SAVED_PATH, RESOURCE_LAST_SAVED_PATH,
+};
+use crate::sections::image_resources_section::url_list::read_unicode_string;
+pub use crate::sections::image_resources_section::ImageResourcesDescriptorError;
+pub use crate::sections::image_resources_section::{
+    AliasStructure, ClassStructure, DescriptorFields, DescriptorStructure, EnumeratedDescriptor,
+    EnumeratedReference, NameStructure, OffsetStructure, PropertyStructure,
+};
+#[cfg(feature = "unstable")]
+pub use crate::sections::image_resources_section::{
+    BezierKnot, BezierPath, PathPoint, SavedPath, Subpath,
+};
+pub use crate::sections::image_resources_section::{ChannelDisplayInfo, ChannelDisplayKind};
 pub use crate::sections::image_resources_section::{DescriptorField, UnitFloatStructure};
+pub use crate::sections::image_resources_section::{DimensionUnit, ResolutionInfo, ResolutionUnit};
+pub use crate::sections::image_resources_section::{GridAndGuides, Guide, GuideDirection};
+pub use crate::sections::image_resources_section::{ImageResource, ImageResourceBlock};
+use crate::sections::image_resources_section::{
+    ImageResourcesSection, RESOURCE_COLOR_TABLE_OBSOLETE, RESOURCE_EXIF_DATA,
+    RESOURCE_GRID_AND_GUIDES, RESOURCE_INDEXED_COLOR_COUNT, RESOURCE_TRANSPARENCY_INDEX,
+    RESOURCE_URL_LIST, RESOURCE_WORKFLOW_URL,
+};
+pub use crate::sections::image_resources_section::{Slice, SlicesImageResource, UrlListEntry};
+pub use crate::sections::image_resources_section::{Thumbnail, ThumbnailFormat, VersionInfo};
+#[cfg(feature = "unstable")]
+pub use crate::sections::layer_and_mask_information_section::adjustment::{
+    Adjustment, BrightnessContrast, Curve, Curves, Exposure, HueRange, HueSaturation,
+    HueSaturationLightness, Levels, LevelsRecord,
+};
+pub use crate::sections::layer_and_mask_information_section::blending_ranges::{
+    BlendIfRange, BlendingRange, BlendingRanges,
+};
+#[cfg(feature = "unstable")]
+pub use crate::sections::layer_and_mask_information_section::fill::{
+    ColorStop, Fill, GradientFill, GradientKind, PatternFill, SolidColorFill, TransparencyStop,
+};
+pub use crate::sections::layer_and_mask_information_section::global_layer_mask::{
+    GlobalLayerMask, GlobalLayerMaskKind,
+};
+pub use crate::sections::layer_and_mask_information_section::groups::{Groups, GroupsIter};
+pub use crate::sections::layer_and_mask_information_section::layer::BlendMode;
+pub use crate::sections::layer_and_mask_information_section::layer::LayerFlags;
+pub use crate::sections::layer_and_mask_information_section::layer::LayerProperties;
 pub use crate::sections::layer_and_mask_information_section::layer::PsdGroup;
 pub use crate::sections::layer_and_mask_information_section::layer::PsdLayer;
+pub use crate::sections::layer_and_mask_information_section::layer_count::LayerCount;
+#[cfg(feature = "unstable")]
+pub use crate::sections::layer_and_mask_information_section::layer_effects::{
+    ColorOverlay, Glow, GlowSource, LayerEffects, Shadow, Stroke, StrokePosition,
+};
+pub use crate::sections::layer_and_mask_information_section::layer_mask::{
+    LayerMask, LayerMaskFlags, MaskRect, RealUserMask,
+};
+pub use crate::sections::layer_and_mask_information_section::layers::Layers;
+#[cfg(feature = "unstable")]
+pub use crate::sections::layer_and_mask_information_section::pattern::Pattern;
+pub use crate::sections::layer_and_mask_information_section::smart_object::{
+    LinkedFile, LinkedFileKind, PlacedLayer, SmartFilter, SmartObject,
+};
+pub use crate::sections::layer_and_mask_information_section::tagged_block::TaggedBlock;
+#[cfg(feature = "unstable")]
+pub use crate::sections::layer_and_mask_information_section::text_layer::{
+    TextLayerInfo, TextWarp, WarpRotation, WarpStyle,
+};
+#[cfg(feature = "unstable")]
+pub use crate::sections::layer_and_mask_information_section::vector_mask::VectorMask;
 use crate::sections::layer_and_mask_information_section::LayerAndMaskInformationSection;
-use crate::sections::MajorSections;
+#[cfg(feature = "unstable")]
+use crate::sections::serialize::{PsdBuffer, PsdSerialize};
+pub use crate::sections::MajorSections;
+pub use crate::separations::{ExtraChannel, Separation, SeparationKind};
+use crate::shared_bytes::SharedBytes;
+pub use crate::slices::ExportedSlice;
+#[cfg(feature = "sprite-sheet")]
+pub use crate::sprite_sheet::{SpriteFrame, SpriteSheet, SpriteSheetBuilder};
+pub use crate::summary::PsdSummary;
+use crate::transform::CanvasTransform;
+pub use crate::transform::CropRect;
 
 use self::sections::file_header_section::FileHeaderSection;
 
+#[cfg(feature = "unstable")]
+mod adjustments;
 mod blend;
+mod canonical_summary;
+mod channel_integrity;
+mod color_stats;
+mod composite;
+#[cfg(feature = "unstable")]
+mod effects;
+mod exif_orientation;
+mod fidelity;
+#[cfg(feature = "unstable")]
+mod fills;
+#[cfg(feature = "fixture-builder")]
+#[doc(hidden)]
+pub mod fixture;
+mod geometry;
+mod hdr;
+#[cfg(any(feature = "integer-compositor", test))]
+mod integer_blend;
+#[cfg(feature = "unstable")]
+mod layer_pngs;
+mod layer_tree;
+mod node_data;
+mod parse_job;
+mod parse_stats;
+pub mod prelude;
 mod psd_channel;
+mod psd_reader;
 mod render;
+mod render_plan;
+mod resize;
+mod rgba16;
+mod rgba_layout;
 mod sections;
+mod separations;
+mod shared_bytes;
+mod slices;
+#[cfg(feature = "sprite-sheet")]
+mod sprite_sheet;
+mod summary;
+mod transform;
+mod ycbcr;
 
 /// An list of errors returned when processing PSD file.
 ///
 /// This list is intended to grow over time and it is not recommended to exhaustively match against it.
-#[derive(PartialEq, Debug, Error)]
+///
+/// Use [`PsdError::code`] instead of the error's message when you need to tell errors apart, such
+/// as when counting them in metrics. Messages may be reworded in any release, codes won't.
+#[derive(Debug, Clone, PartialEq, Error)]
 #[non_exhaustive]
 pub enum PsdError {
     /// Failed to parse PSD header
@@ -55,20 +217,85 @@ pub enum PsdError {
     /// Failed to parse PSD resource section
     #[error("Failed to parse PSD resource section: '{0}'.")]
     ResourceError(ImageResourcesSectionError),
+    /// A render pipeline was asked to render a group that doesn't exist
+    #[error("No group found at path '{0}'.")]
+    GroupNotFound(String),
+    /// A crop rectangle was empty or didn't lie within the canvas
+    #[error("Crop rectangle {0:?} is empty or doesn't lie within the canvas.")]
+    InvalidCropRect(CropRect),
+    /// A PSD was asked to resize to a width or height of zero
+    #[error("Can't resize to {0}x{1}, the width and height must be at least 1.")]
+    InvalidResizeDimensions(u32, u32),
+    /// A document was written as a PSD file, but it's too large to fit in one
+    #[error("A {width}x{height} document is too large to write as a PSD file, write it as a PSB file instead.")]
+    TooLargeForPsd {
+        /// The width of the document
+        width: u32,
+        /// The height of the document
+        height: u32,
+    },
+    /// Pixels were written into a buffer that is too small for them, or whose stride is shorter
+    /// than a line of pixels
+    #[error("A {width}x{height} image doesn't fit in a buffer of {len} bytes with a stride of {stride} bytes.")]
+    RgbaBufferTooSmall {
+        /// The width of the image
+        width: u32,
+        /// The height of the image
+        height: u32,
+        /// The number of bytes from the start of one line of pixels to the start of the next
+        stride: usize,
+        /// The length of the buffer
+        len: usize,
+    },
+    /// Reading a PSD from a reader failed
+    #[error("Failed to read the PSD: {message}")]
+    ReadError {
+        /// The kind of IO error
+        kind: std::io::ErrorKind,
+        /// The IO error's message
+        message: String,
+    },
+}
+
+impl PsdError {
+    /// A stable code that identifies the kind of error, such as `"header.invalid_signature"`.
+    ///
+    /// Errors that wrap a section's error return that error's code. Codes are never changed or
+    /// reused once released, new variants only ever add new codes.
+    pub fn code(&self) -> &'static str {
+        match self {
+            PsdError::HeaderError(err) => err.code(),
+            PsdError::LayerError(err) => err.code(),
+            PsdError::ImageError(err) => err.code(),
+            PsdError::ResourceError(err) => err.code(),
+            PsdError::GroupNotFound(_) => "render.group_not_found",
+            PsdError::InvalidCropRect(_) => "crop.invalid_rect",
+            PsdError::InvalidResizeDimensions(_, _) => "resize.invalid_dimensions",
+            PsdError::TooLargeForPsd { .. } => "write.too_large_for_psd",
+            PsdError::RgbaBufferTooSmall { .. } => "rgba.buffer_too_small",
+            PsdError::ReadError { .. } => "read.io",
+        }
+    }
 }
 
 /// Represents the contents of a PSD file
 ///
 /// ## PSB Support
 ///
-/// We do not currently support PSB since the original authors didn't need it, but adding
-/// support should be trivial. If you'd like to support PSB please open an issue.
-#[derive(Debug)]
+/// PSB (large document format) files are parsed the same way as PSD files, taking into account
+/// their larger length markers. If you run into a PSB file that we can't parse please open an issue.
+#[derive(Debug, Clone, PartialEq)]
 pub struct Psd {
     file_header_section: FileHeaderSection,
+    /// The color mode data section, which we only parse for the color table of indexed color
+    /// images
+    color_mode_data: Vec<u8>,
+    /// The color table of an indexed color image
+    palette: Option<[[u8; 3]; 256]>,
     image_resources_section: ImageResourcesSection,
     layer_and_mask_information_section: LayerAndMaskInformationSection,
     image_data_section: ImageDataSection,
+    node_data: NodeDataMap,
 }
 
 impl Psd {
@@ -84,40 +311,278 @@ impl Psd {
     /// let psd = Psd::from_bytes(psd_bytes);
     /// ```
     pub fn from_bytes(bytes: &[u8]) -> Result<Psd, PsdError> {
+        Psd::parse(bytes, None, None)
+    }
+
+    /// Create a Psd from the bytes of a PSD file, sharing them with the layers instead of copying
+    /// each layer's channels out of them.
+    ///
+    /// Raw and RLE compressed layer channels point into `bytes`, which are kept alive for as long
+    /// as any of the layers are, and they are only decompressed when the layer's pixels are asked
+    /// for, such as by [`PsdLayer::rgba`]. This cuts the peak memory of parsing files with
+    /// hundreds of layers, at the cost of holding on to the whole file. ZIP compressed channels
+    /// are still decompressed while parsing.
+    ///
+    /// Each layer keeps its pixels after the first time that they're decoded, so calling
+    /// [`PsdLayer::rgba`] again returns a copy of them instead of decompressing the channels
+    /// again.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let psd_bytes: std::sync::Arc<[u8]> = std::fs::read("./my-psd-file.psd")?.into();
+    ///
+    /// let psd = Psd::from_shared_bytes(psd_bytes)?;
+    /// ```
+    pub fn from_shared_bytes(bytes: Arc<[u8]>) -> Result<Psd, PsdError> {
+        let shared = SharedBytes::new(bytes);
+        Psd::parse(&shared, Some(&shared), None)
+    }
+
+    /// Read the file header of a PSD from a reader, leaving the rest of the file to be read when
+    /// it's asked for.
+    ///
+    /// This lets you look at the dimensions, layer names and resources of files that are too
+    /// large to comfortably hold in memory. See [`PsdReader`].
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let file = std::fs::File::open("./my-psb-file.psb")?;
+    ///
+    /// let mut reader = Psd::from_reader(std::io::BufReader::new(file))?;
+    /// println!("{:?} {:?}", reader.dimensions(), reader.layer_names()?);
+    /// ```
+    pub fn from_reader<R: Read + Seek>(reader: R) -> Result<PsdReader<R>, PsdError> {
+        PsdReader::new(reader)
+    }
+
+    /// Create a Psd from a byte slice, along with statistics about how long each section took
+    /// to parse.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let psd_bytes = include_bytes!("./my-psd-file.psd");
+    ///
+    /// let (psd, stats) = Psd::from_bytes_with_stats(psd_bytes)?;
+    /// println!("Parsed {} layers in {:?}", stats.layers_parsed(), stats.total());
+    /// ```
+    pub fn from_bytes_with_stats(bytes: &[u8]) -> Result<(Psd, ParseStats), PsdError> {
+        let mut stats = ParseStats::default();
+        let mut total = Duration::default();
+
+        let psd = timed(Some(&mut total), || {
+            Psd::parse(bytes, None, Some(&mut stats))
+        })?;
+        stats.total = total;
+
+        stats.layers_parsed = psd.layers().len();
+        stats.compressed_bytes = psd.compressed_bytes();
+
+        Ok((psd, stats))
+    }
+
+    /// Parse the bytes of a PSD file, which are a part of `shared` when the layers' channels
+    /// should point into it instead of being copied.
+    fn parse(
+        bytes: &[u8],
+        shared: Option<&SharedBytes>,
+        mut stats: Option<&mut ParseStats>,
+    ) -> Result<Psd, PsdError> {
         let major_sections = MajorSections::from_bytes(bytes).map_err(PsdError::HeaderError)?;
 
-        let file_header_section = FileHeaderSection::from_bytes(major_sections.file_header)
-            .map_err(PsdError::HeaderError)?;
+        let file_header_section = timed(stats.as_mut().map(|s| &mut s.file_header_section), || {
+            FileHeaderSection::from_bytes(major_sections.file_header)
+        })
+        .map_err(PsdError::HeaderError)?;
 
         let psd_width = file_header_section.width.0;
         let psd_height = file_header_section.height.0;
         let channel_count = file_header_section.channel_count.count();
 
-        let layer_and_mask_information_section = LayerAndMaskInformationSection::from_bytes(
-            major_sections.layer_and_mask,
-            psd_width,
-            psd_height,
+        let layer_and_mask_information_section = timed(
+            stats
+                .as_mut()
+                .map(|s| &mut s.layer_and_mask_information_section),
+            || {
+                LayerAndMaskInformationSection::from_bytes(
+                    major_sections.layer_and_mask,
+                    file_header_section.version,
+                    file_header_section.depth,
+                    psd_width,
+                    psd_height,
+                    shared,
+                )
+            },
         )
         .map_err(PsdError::LayerError)?;
 
-        let image_data_section = ImageDataSection::from_bytes(
-            major_sections.image_data,
-            file_header_section.depth,
-            psd_height,
-            channel_count,
-        )
+        let image_data_section = timed(stats.as_mut().map(|s| &mut s.image_data_section), || {
+            ImageDataSection::from_bytes(
+                major_sections.image_data,
+                file_header_section.depth,
+                file_header_section.version,
+                psd_width,
+                psd_height,
+                channel_count,
+            )
+        })
         .map_err(PsdError::ImageError)?;
 
-        let image_resources_section =
-            ImageResourcesSection::from_bytes(major_sections.image_resources)
-                .map_err(PsdError::ResourceError)?;
+        let image_resources_section = timed(
+            stats.as_mut().map(|s| &mut s.image_resources_section),
+            || ImageResourcesSection::from_bytes(major_sections.image_resources),
+        )
+        .map_err(PsdError::ResourceError)?;
 
-        Ok(Psd {
+        Ok(Psd::from_sections(
             file_header_section,
+            major_sections.color_mode_data,
             image_resources_section,
             layer_and_mask_information_section,
             image_data_section,
-        })
+        ))
+    }
+
+    /// Put the parsed sections together, given the bytes of the color mode data section
+    /// (including the length marker).
+    fn from_sections(
+        file_header_section: FileHeaderSection,
+        color_mode_data: &[u8],
+        image_resources_section: ImageResourcesSection,
+        layer_and_mask_information_section: LayerAndMaskInformationSection,
+        image_data_section: ImageDataSection,
+    ) -> Psd {
+        // Skip the length marker
+        let color_mode_data = &color_mode_data[4..];
+        let palette = match file_header_section.color_mode {
+            ColorMode::Indexed => color_mode_data_section::read_palette(color_mode_data),
+            _ => None,
+        };
+
+        Psd {
+            file_header_section,
+            color_mode_data: color_mode_data.to_vec(),
+            palette,
+            image_resources_section,
+            layer_and_mask_information_section,
+            image_data_section,
+            node_data: NodeDataMap::default(),
+        }
+    }
+
+    /// The number of RLE compressed bytes across all of the layers and the final image
+    fn compressed_bytes(&self) -> usize {
+        let layers: usize = self
+            .layers()
+            .iter()
+            .flat_map(|layer| layer.channels.values())
+            .map(compressed_len)
+            .sum();
+
+        let image_data = &self.image_data_section;
+        let final_image: usize = std::iter::once(&image_data.red)
+            .chain(image_data.green.iter())
+            .chain(image_data.blue.iter())
+            .chain(image_data.alpha.iter())
+            .map(compressed_len)
+            .sum();
+
+        layers + final_image
+    }
+}
+
+// Methods for writing the PSD back into bytes
+#[cfg(feature = "unstable")]
+impl Psd {
+    /// Serialize the PSD back into the bytes of a PSD (or PSB) file.
+    ///
+    /// Parsing the bytes with [`Psd::from_bytes`] gives back the same PSD. Data that the crate
+    /// doesn't parse, such as unknown image resources and tagged blocks, is written out as it
+    /// was read.
+    ///
+    /// A PSD that no longer fits in a PSD file, such as one that was resized to more than 30,000
+    /// pixels wide or whose file would be larger than 2 GB, is written as a PSB file instead. Use
+    /// [`Psd::to_bytes_as`] to pin the version.
+    ///
+    /// ```no_run
+    /// # use psd::Psd;
+    /// # let psd = Psd::from_bytes(&[]).unwrap();
+    /// std::fs::write("copy.psd", psd.to_bytes()).unwrap();
+    /// ```
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self.to_bytes_as(self.version()) {
+            Ok(bytes) => bytes,
+            Err(_) => self.serialize(PsdVersion::Two),
+        }
+    }
+
+    /// Serialize the PSD into the bytes of a PSD ([`PsdVersion::One`]) or PSB
+    /// ([`PsdVersion::Two`]) file, regardless of which one it was read from.
+    ///
+    /// Returns an error when asked for a PSD file if the document is more than 30,000 pixels wide
+    /// or tall, or if the file would be larger than 2 GB.
+    pub fn to_bytes_as(&self, version: PsdVersion) -> Result<Vec<u8>, PsdError> {
+        let (width, height) = (self.width(), self.height());
+        if !version.fits(width, height, 0) {
+            return Err(PsdError::TooLargeForPsd { width, height });
+        }
+
+        let bytes = self.serialize(version);
+        if !version.fits(width, height, bytes.len()) {
+            return Err(PsdError::TooLargeForPsd { width, height });
+        }
+
+        Ok(bytes)
+    }
+
+    fn serialize(&self, version: PsdVersion) -> Vec<u8> {
+        let header = FileHeaderSection {
+            version,
+            ..self.file_header_section.clone()
+        };
+        let mut buffer = PsdBuffer::new();
+
+        header.write(&mut buffer);
+
+        buffer.write_u32(self.color_mode_data.len() as u32);
+        buffer.write(&self.color_mode_data);
+
+        self.image_resources_section.write(&mut buffer);
+
+        self.layer_and_mask_information_section
+            .write(&mut buffer, version);
+
+        self.image_data_section.write(&mut buffer, version);
+
+        buffer.into_inner()
+    }
+
+    /// Serialize the PSD into a writer, such as a file.
+    ///
+    /// See [`Psd::to_bytes`].
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.to_bytes())
+    }
+}
+
+// Methods for exporting layers as images
+#[cfg(feature = "unstable")]
+impl Psd {
+    /// Write every layer into a directory as a PNG the size of the PSD, named after the layer's
+    /// index in [`Psd::layers`] and its name (E.g. `3-Arm_Left.png`). Layers with a user mask
+    /// also get a grayscale PNG of the mask next to them (E.g. `3-Arm_Left-mask.png`).
+    ///
+    /// The directory is created if it doesn't exist. Returns the paths of the written files.
+    ///
+    /// ```no_run
+    /// # use psd::Psd;
+    /// # let psd = Psd::from_bytes(&[]).unwrap();
+    /// psd.write_layer_pngs("rig/layers").unwrap();
+    /// ```
+    pub fn write_layer_pngs<P: AsRef<Path>>(&self, dir: P) -> io::Result<Vec<PathBuf>> {
+        layer_pngs::write_layer_pngs(self, dir.as_ref())
     }
 }
 
@@ -133,6 +598,12 @@ impl Psd {
         self.file_header_section.height.0
     }
 
+    /// Whether the PSD was read from a PSD ([`PsdVersion::One`]) or a PSB ([`PsdVersion::Two`])
+    /// file
+    pub fn version(&self) -> PsdVersion {
+        self.file_header_section.version
+    }
+
     /// The number of bits per channel
     pub fn depth(&self) -> PsdDepth {
         self.file_header_section.depth
@@ -142,15 +613,76 @@ impl Psd {
     pub fn color_mode(&self) -> ColorMode {
         self.file_header_section.color_mode
     }
+
+    /// The color table of an indexed color image, from the color mode data section. The final
+    /// image's pixels are indices into it.
+    ///
+    /// `None` if the PSD isn't in [`ColorMode::Indexed`]. Only the first
+    /// [`Psd::indexed_color_count`] colors are in use when the PSD says how many there are.
+    pub fn palette(&self) -> Option<&[[u8; 3]; 256]> {
+        self.palette.as_ref()
+    }
+
+    /// A compact, human readable tree of the document's groups and layers.
+    ///
+    /// `Psd` also implements `Display`, which writes the summary without a depth limit.
+    ///
+    /// ```no_run
+    /// # use psd::Psd;
+    /// # let psd = Psd::from_bytes(&[]).unwrap();
+    /// println!("{}", psd.summary().max_depth(1));
+    /// ```
+    pub fn summary(&self) -> PsdSummary<'_> {
+        PsdSummary::new(self)
+    }
+
+    /// A text form of the document for committing next to it, so that changes to the document
+    /// show up as readable diffs in version control.
+    ///
+    /// There is one line for the document and one line per group and layer, in the order of
+    /// [`Psd::summary`], each naming the group or layer by its full path. Lines hold the
+    /// properties and CRC-32s of the pixels but no pixels, and only change when the document
+    /// does. `composite_crc32` is the CRC-32 of the final image that was saved in the file.
+    ///
+    /// ```text
+    /// document 1x1 Rgb Eight layers=1 groups=1 composite_crc32=8b6d2a0f
+    /// group "group" visible=true blend=PassThrough opacity=255
+    /// layer "group/First Layer" id=2 size=1x1 at=0,0 visible=true blend=Normal opacity=255 fill=255 clipping=false pixels_crc32=1c291ca3
+    /// ```
+    pub fn canonical_summary(&self) -> String {
+        canonical_summary::canonical_summary(self)
+    }
 }
 
 // Methods for working with layers
 impl Psd {
-    /// Get all of the layers in the PSD
-    pub fn layers(&self) -> &Vec<PsdLayer> {
+    /// Get all of the layers in the PSD, from the top layer to the bottom layer
+    pub fn layers(&self) -> &Layers {
         &self.layer_and_mask_information_section.layers
     }
 
+    /// The groups and layers at the top level of the PSD, with each group holding the groups and
+    /// layers inside of it.
+    ///
+    /// Nodes are in the same stacking order as [`Psd::layers`], with a group coming right before
+    /// the layers inside of it.
+    ///
+    /// ```no_run
+    /// # use psd::{Psd, PsdNode};
+    /// # let psd = Psd::from_bytes(&[]).unwrap();
+    /// fn print(nodes: &[PsdNode], depth: usize) {
+    ///     for node in nodes {
+    ///         println!("{:indent$}{}", "", node.name(), indent = depth * 2);
+    ///         print(node.children(), depth + 1);
+    ///     }
+    /// }
+    ///
+    /// print(&psd.tree(), 0);
+    /// ```
+    pub fn tree(&self) -> Vec<PsdNode<'_>> {
+        layer_tree::node_tree(self)
+    }
+
     /// Get a layer by name
     pub fn layer_by_name(&self, name: &str) -> Option<&PsdLayer> {
         self.layer_and_mask_information_section
@@ -158,6 +690,39 @@ impl Psd {
             .item_by_name(name)
     }
 
+    /// Get every layer with the given name, in the same order as `Psd::layers`.
+    pub fn layers_by_name(&self, name: &str) -> Vec<&PsdLayer> {
+        let layers = &self.layer_and_mask_information_section.layers;
+
+        layers
+            .item_indices_by_name(name)
+            .iter()
+            .map(|idx| &layers[*idx])
+            .collect()
+    }
+
+    /// Get a layer by the unique ID that Photoshop assigned to it.
+    ///
+    /// See [`LayerProperties::layer_id`].
+    ///
+    /// [`LayerProperties::layer_id`]: crate::sections::layer_and_mask_information_section::layer::LayerProperties::layer_id
+    pub fn layer_by_id(&self, id: u32) -> Option<&PsdLayer> {
+        self.layer_and_mask_information_section
+            .layers
+            .item_by_id(id)
+    }
+
+    /// Get a layer by its path, which is the names of the groups that it is nested in followed by
+    /// its own name, separated by `/`.
+    ///
+    /// For example, a layer named "Eyes" inside of a group named "Face" that is inside of a group
+    /// named "Character" has the path "Character/Face/Eyes".
+    pub fn layer_by_path(&self, path: &str) -> Option<&PsdLayer> {
+        self.layer_and_mask_information_section
+            .layers
+            .item_by_path(path)
+    }
+
     /// Get a layer by index.
     ///
     /// index 0 is the bottom layer, index 1 is the layer above that, etc
@@ -168,30 +733,127 @@ impl Psd {
             .unwrap()
     }
 
+    /// Get the layers whose pixel at a coordinate isn't fully transparent, from top to bottom.
+    ///
+    /// Useful for finding the layer that a user clicked on in an inspector UI.
+    ///
+    /// See [`Psd::layers_at_with_alpha_threshold`].
+    pub fn layers_at(&self, left: i32, top: i32) -> Vec<&PsdLayer> {
+        self.layers_at_with_alpha_threshold(left, top, 0)
+    }
+
+    /// Get the layers whose alpha at a coordinate is greater than `alpha_threshold`, from top
+    /// to bottom.
+    ///
+    /// The alpha takes the layer's opacity into account, and layers that aren't visible are
+    /// skipped.
+    pub fn layers_at_with_alpha_threshold(
+        &self,
+        left: i32,
+        top: i32,
+        alpha_threshold: u8,
+    ) -> Vec<&PsdLayer> {
+        self.layers()
+            .iter()
+            .filter(|layer| (layer.opacity > 0 && layer.visible()) || layer.clipping_mask)
+            .filter(|layer| match layer.alpha_at(left, top) {
+                Some(alpha) => {
+                    let mut pixel = [0, 0, 0, alpha];
+                    blend::apply_opacity(&mut pixel, layer.opacity);
+                    pixel[3] > alpha_threshold
+                }
+                None => false,
+            })
+            .collect()
+    }
+
     /// Get all of the groups in the PSD, in the order that they appear in the PSD file.
-    pub fn groups(&self) -> &HashMap<u32, PsdGroup> {
+    pub fn groups(&self) -> &Groups {
         &self.layer_and_mask_information_section.groups
     }
 
-    /// Get the group ID's in the order that they appear in Photoshop.
-    /// (i.e. from the bottom of layers view to the top of the layers view).
+    /// Get the group ID's in the order that the groups are closed, from the top of the layers
+    /// view down with each group after the groups inside of it. See [`Groups`].
     pub fn group_ids_in_order(&self) -> &Vec<u32> {
         self.layer_and_mask_information_section
             .groups
             .group_ids_in_order()
     }
 
+    /// Get the groups in the order that they appear in Photoshop's layers panel, from the top
+    /// to the bottom, where each group comes before the groups inside of it.
+    ///
+    /// Unlike iterating over [`Psd::groups`], the order is always the same.
+    pub fn groups_in_order(&self) -> impl Iterator<Item = &PsdGroup> {
+        self.layer_and_mask_information_section
+            .groups
+            .iter_top_down()
+    }
+
     /// Returns sub layers of group by group id
     pub fn get_group_sub_layers(&self, id: &u32) -> Option<&[PsdLayer]> {
         match self.groups().get(id) {
             Some(group) => Some(
-                &self.layer_and_mask_information_section.layers.deref()
-                    [group.contained_layers.clone()],
+                &self.layer_and_mask_information_section.layers[group.contained_layers.clone()],
             ),
             None => None,
         }
     }
 
+    /// The layer count that the PSD declares, including its sign, along with the number of layer
+    /// records that were parsed, for spotting layer info that was cut short.
+    pub fn layer_count(&self) -> LayerCount {
+        self.layer_and_mask_information_section.layer_count
+    }
+
+    /// The global layer mask info, which says how Photoshop overlays masks that are being
+    /// edited. `None` if the PSD doesn't have any.
+    pub fn global_layer_mask(&self) -> Option<GlobalLayerMask> {
+        GlobalLayerMask::read(
+            &self
+                .layer_and_mask_information_section
+                .global_layer_mask_info,
+        )
+    }
+
+    /// The tagged blocks at the end of the layer and mask information section, such as
+    /// `Patt` or `FMsk`.
+    ///
+    /// The `Lr16`, `Lr32` or `Layr` block that holds the layers of a 16 or 32 bit PSD, or of a
+    /// PSD from a writer that doesn't store them at the start of the section, isn't included,
+    /// since its layers are available through [`Psd::layers`].
+    pub fn global_tagged_blocks(&self) -> &[TaggedBlock] {
+        &self.layer_and_mask_information_section.global_tagged_blocks
+    }
+
+    /// The path, bounds and anchor of every group and layer, without any pixels, with groups
+    /// coming right before their children.
+    ///
+    /// See [`Psd::layer_geometry_json`] and [`Psd::layer_geometry_csv`] for exporting the
+    /// geometry to a game engine or another tool.
+    pub fn layer_geometry(&self) -> Vec<LayerGeometry> {
+        geometry::layer_geometry(self)
+    }
+
+    /// [`Psd::layer_geometry`] as a JSON array of objects, such as
+    ///
+    /// ```text
+    /// [{"path":"UI/HUD","kind":"group","left":0,"top":0,"width":8,"height":2,"anchor":[0.5,0.125]}]
+    /// ```
+    pub fn layer_geometry_json(&self) -> String {
+        geometry::to_json(&self.layer_geometry())
+    }
+
+    /// [`Psd::layer_geometry`] as CSV with a header row, such as
+    ///
+    /// ```text
+    /// path,kind,left,top,width,height,anchor_x,anchor_y
+    /// UI/HUD,group,0,0,8,2,0.5,0.125
+    /// ```
+    pub fn layer_geometry_csv(&self) -> String {
+        geometry::to_csv(&self.layer_geometry())
+    }
+
     /// Given a filter, combine all layers in the PSD that pass the filter into a vector
     /// of RGBA pixels.
     ///
@@ -206,59 +868,307 @@ impl Psd {
         &self,
         filter: &dyn Fn((usize, &PsdLayer)) -> bool,
     ) -> Result<Vec<u8>, PsdError> {
-        // When you create a PSD but don't create any new layers the bottom layer might not
-        // show up in the layer and mask information section, so we won't see any layers.
-        //
-        // TODO: We should try and figure out where the layer name is so that we can return
-        // a completely transparent image if it is filtered out. But this should be a rare
-        // use case so we can just always return the final image for now.
-        if self.layers().is_empty() {
-            return Ok(self.rgba());
+        self.render()
+            .visible_only()
+            .filter(|(idx, layer)| filter((idx, layer)))
+            .to_rgba()
+    }
+
+    /// [`Psd::flatten_layers_rgba`], written into a buffer that you own with the given layout,
+    /// such as a mapped GPU staging buffer whose rows are padded for alignment.
+    ///
+    /// Each row of pixels is written as soon as it is rendered, so tools that redraw the PSD
+    /// often can keep rendering into the same frame buffer instead of allocating a new image.
+    pub fn flatten_layers_rgba_into(
+        &self,
+        filter: &dyn Fn((usize, &PsdLayer)) -> bool,
+        out: &mut [u8],
+        layout: RgbaLayout,
+    ) -> Result<(), PsdError> {
+        self.render()
+            .visible_only()
+            .filter(|(idx, layer)| filter((idx, layer)))
+            .to_rgba_into(out, layout)
+    }
+
+    /// Given a filter, combine all layers in the PSD that pass the filter into separate red,
+    /// green, blue and alpha planes.
+    /// [ [R,R,R, ...], [G,G,G, ...], [B,B,B, ...], [A,A,A, ...] ]
+    ///
+    /// See [`Psd::flatten_layers_rgba`].
+    pub fn flatten_layers_planar(
+        &self,
+        filter: &dyn Fn((usize, &PsdLayer)) -> bool,
+    ) -> Result<[Vec<u8>; 4], PsdError> {
+        self.render()
+            .visible_only()
+            .filter(|(idx, layer)| filter((idx, layer)))
+            .to_planar()
+    }
+
+    /// Given a filter, combine all layers in the PSD that pass the filter and crop the result
+    /// to the pixels that aren't fully transparent.
+    ///
+    /// The returned [`TrimmedRgba`] holds the offset of the cropped image within the PSD.
+    pub fn flatten_trimmed_rgba(
+        &self,
+        filter: &dyn Fn((usize, &PsdLayer)) -> bool,
+    ) -> Result<TrimmedRgba, PsdError> {
+        self.render()
+            .visible_only()
+            .filter(|(idx, layer)| filter((idx, layer)))
+            .to_trimmed_rgba()
+    }
+
+    /// Start building a render of the PSD's layers, such as a scaled down preview of one group
+    /// on top of a white background.
+    ///
+    /// ```no_run
+    /// # use psd::Psd;
+    /// # let psd = Psd::from_bytes(&[]).unwrap();
+    /// let preview = psd
+    ///     .render()
+    ///     .visible_only()
+    ///     .group("UI/HUD")
+    ///     .background([255; 4])
+    ///     .scale(0.5)
+    ///     .to_rgba()
+    ///     .unwrap();
+    /// ```
+    pub fn render(&self) -> RenderPipeline<'_> {
+        RenderPipeline::new(self)
+    }
+
+    /// Everything in the PSD that rendering approximates or ignores, such as layer effects,
+    /// adjustment layers and blend modes that we can't blend with yet.
+    ///
+    /// Pipelines can use this to send PSDs that won't render like they do in Photoshop for
+    /// manual review.
+    ///
+    /// ```no_run
+    /// # use psd::Psd;
+    /// # let psd = Psd::from_bytes(&[]).unwrap();
+    /// let report = psd.render_fidelity_report();
+    /// if !report.is_faithful() {
+    ///     eprintln!("{}", report);
+    /// }
+    /// ```
+    pub fn render_fidelity_report(&self) -> RenderFidelityReport {
+        fidelity::render_fidelity_report(self)
+    }
+
+    /// Flatten the visible layers and compare the result with the final image that is stored in
+    /// the PSD, counting the pixels with a channel that differs by more than `threshold`.
+    ///
+    /// A mismatch points to a file whose layers were edited by a tool that didn't update the
+    /// final image, or to something that rendering doesn't support yet, see
+    /// [`Psd::render_fidelity_report`].
+    ///
+    /// ```no_run
+    /// # use psd::Psd;
+    /// # let psd = Psd::from_bytes(&[]).unwrap();
+    /// let diff = psd.verify_composite(2).unwrap();
+    /// if diff.mismatch_ratio() > 0.01 {
+    ///     eprintln!("{:?} doesn't match the final image", diff.mismatch_bounds());
+    /// }
+    /// ```
+    pub fn verify_composite(&self, threshold: u8) -> Result<CompositeDiff, PsdError> {
+        composite::verify_composite(self, threshold)
+    }
+
+    /// Decode every channel of every layer, reporting the layer, channel and row of the channels
+    /// whose compressed data is corrupt.
+    ///
+    /// Layers are decoded when you ask for their pixels, and corrupt rows are skipped rather
+    /// than returning an error, so a corrupt channel otherwise only shows up as missing pixels.
+    ///
+    /// ```no_run
+    /// # use psd::Psd;
+    /// # let psd = Psd::from_bytes(&[]).unwrap();
+    /// for corrupt in psd.verify_layer_channels().corrupt_channels() {
+    ///     eprintln!(
+    ///         "{} {:?}: {}",
+    ///         corrupt.layer_name(),
+    ///         corrupt.channel(),
+    ///         corrupt.corruption()
+    ///     );
+    /// }
+    /// ```
+    pub fn verify_layer_channels(&self) -> ChannelReport {
+        channel_integrity::verify_layer_channels(self, false)
+    }
+
+    /// [`Psd::verify_layer_channels`], along with the CRC-32 of each channel that decodes, so
+    /// that a layer's pixels can be compared with an earlier copy of the file.
+    pub fn verify_layer_channels_with_checksums(&self) -> ChannelReport {
+        channel_integrity::verify_layer_channels(self, true)
+    }
+}
+
+// Methods for attaching your own data to groups and layers
+impl Psd {
+    /// Get the data of type `T` that was attached to a group or layer.
+    pub fn node_data<T: Any>(&self, node: NodeId) -> Option<&T> {
+        self.node_data.get(node)
+    }
+
+    /// Get the data of type `T` that is attached to a group or layer, attaching `T::default()`
+    /// if there isn't any yet.
+    ///
+    /// This lets tools built on top of the crate, such as editors, keep state for each node
+    /// without maintaining their own maps keyed by layer names (which aren't unique).
+    ///
+    /// ```no_run
+    /// # use psd::{NodeId, Psd};
+    /// # let mut psd = Psd::from_bytes(&[]).unwrap();
+    /// #[derive(Clone, Default)]
+    /// struct Selected(bool);
+    ///
+    /// psd.node_data_mut::<Selected>(NodeId::Layer(0)).0 = true;
+    /// ```
+    ///
+    /// Attached data is ignored when comparing two `Psd`s.
+    pub fn node_data_mut<T: Any + Clone + Default + Send + Sync + UnwindSafe + RefUnwindSafe>(
+        &mut self,
+        node: NodeId,
+    ) -> &mut T {
+        self.node_data.get_mut_or_default(node)
+    }
+
+    /// Attach data of type `T` to a group or layer, returning the data of type `T` that was
+    /// previously attached.
+    pub fn insert_node_data<T: Any + Clone + Send + Sync + UnwindSafe + RefUnwindSafe>(
+        &mut self,
+        node: NodeId,
+        data: T,
+    ) -> Option<T> {
+        self.node_data.insert(node, data)
+    }
+
+    /// Detach the data of type `T` from a group or layer.
+    pub fn remove_node_data<T: Any>(&mut self, node: NodeId) -> Option<T> {
+        self.node_data.remove(node)
+    }
+
+    /// Replace the data of a layer's or group's first tagged block with the key, or add a block
+    /// with the key if there isn't one, so that blocks that we don't parse can be patched before
+    /// writing the PSD with [`Psd::to_bytes`].
+    ///
+    /// ```no_run
+    /// # use psd::{NodeId, Psd};
+    /// # let mut psd = Psd::from_bytes(&[]).unwrap();
+    /// // Lock all of the first layer's properties
+    /// psd.set_raw_tagged_block(NodeId::Layer(0), *b"lspf", vec![0x80, 0, 0, 0]);
+    /// ```
+    ///
+    /// The data is written as is. Settings that we read from the blocks when asked for them, such
+    /// as [`LayerProperties::fill_opacity`], see the new data, while the ones that were read when
+    /// parsing the PSD, such as the name from the `luni` block, don't change. A group's blocks
+    /// are in the record that opens it, see [`PsdGroup::raw_tagged_block`].
+    ///
+    /// Returns `false` if there is no such layer or group.
+    pub fn set_raw_tagged_block(&mut self, node: NodeId, key: [u8; 4], data: Vec<u8>) -> bool {
+        match self.node_record_mut(node) {
+            Some(properties) => {
+                properties.set_raw_tagged_block(key, data);
+                true
+            }
+            None => false,
         }
+    }
 
-        // Filter out layers based on the passed in filter.
-        let layers_to_flatten_top_down: Vec<&PsdLayer> = self
-            .layers()
-            .iter()
-            .enumerate()
-            // here we filter transparent layers and invisible layers
-            .filter(|(_, layer)| (layer.opacity > 0 && layer.visible) || layer.clipping_mask)
-            .filter(|(idx, layer)| filter((*idx, layer)))
-            .map(|(_, layer)| layer)
-            .collect();
-
-        let pixel_count = self.width() * self.height();
-
-        // If there aren't any layers left after filtering we return a complete transparent image.
-        if layers_to_flatten_top_down.is_empty() {
-            return Ok(vec![0; pixel_count as usize * 4]);
+    /// Remove a layer's or group's first tagged block with the key, returning its data. See
+    /// [`Psd::set_raw_tagged_block`].
+    pub fn remove_raw_tagged_block(&mut self, node: NodeId, key: &[u8; 4]) -> Option<Vec<u8>> {
+        self.node_record_mut(node)?.remove_raw_tagged_block(key)
+    }
+
+    /// Set a layer's or group's opacity from 0.0 to 100.0, the way that Photoshop shows it,
+    /// rounded to the nearest of the 256 opacities that a PSD can store. Percentages outside of
+    /// that range are clamped. See [`LayerProperties::opacity_percent`].
+    ///
+    /// Returns `false` if there is no such layer or group.
+    pub fn set_opacity_percent(&mut self, node: NodeId, percent: f32) -> bool {
+        match self.node_record_mut(node) {
+            Some(properties) => {
+                properties.opacity = blend::Normalized(percent.clamp(0., 100.) / 100.).to_channel();
+                true
+            }
+            None => false,
         }
+    }
 
-        // During the process of flattening the PSD we might need to look at the pixels on one of
-        // the layers below if an upper layer is transparent.
-        //
-        // Anytime we need to calculate the RGBA for a layer we cache it so that we don't need
-        // to perform that operation again.
-        let renderer = render::Renderer::new(&layers_to_flatten_top_down, self.width() as usize);
+    /// The record of a layer, or the record that opens a group
+    fn node_record_mut(&mut self, node: NodeId) -> Option<&mut LayerProperties> {
+        let section = &mut self.layer_and_mask_information_section;
 
-        let mut flattened_pixels = Vec::with_capacity((pixel_count * 4) as usize);
+        match node {
+            NodeId::Layer(idx) => section
+                .layers
+                .iter_mut()
+                .nth(idx)
+                .map(|layer| &mut layer.layer_properties),
+            NodeId::Group(id) => section
+                .groups
+                .values_mut()
+                .find(|group| group.id == id)
+                .map(|group| &mut group.folder_properties),
+        }
+    }
+}
 
-        // Iterate over each pixel and, if it is transparent, blend it with the pixel below it
-        // recursively.
-        for pixel_idx in 0..pixel_count as usize {
-            let left = pixel_idx % self.width() as usize;
-            let top = pixel_idx / self.width() as usize;
-            let pixel_coord = (left, top);
+// Methods for working with smart objects
+#[cfg(feature = "unstable")]
+impl Psd {
+    /// Every smart object layer, in the same order as [`Psd::layers`], along with the file that
+    /// it places.
+    pub fn smart_objects(&self) -> Vec<SmartObject<'_>> {
+        self.layers()
+            .iter()
+            .filter_map(|layer| {
+                let placed_layer = layer.placed_layer.as_ref()?;
+                let file = self
+                    .linked_files()
+                    .iter()
+                    .find(|file| file.unique_id == placed_layer.unique_id);
 
-            let blended_pixel = renderer.flattened_pixel(pixel_coord);
+                Some(SmartObject {
+                    layer,
+                    placed_layer,
+                    file,
+                })
+            })
+            .collect()
+    }
 
-            flattened_pixels.push(blended_pixel[0]);
-            flattened_pixels.push(blended_pixel[1]);
-            flattened_pixels.push(blended_pixel[2]);
-            flattened_pixels.push(blended_pixel[3]);
-        }
+    /// The files of the PSD's smart objects, whether they are embedded or linked to, read from
+    /// the `lnk2`, `lnkD` and `lnk3` tagged blocks.
+    pub fn linked_files(&self) -> &[LinkedFile] {
+        &self.layer_and_mask_information_section.linked_files
+    }
 
-        Ok(flattened_pixels)
+    /// The patterns that the PSD's pattern fills repeat, read from the `Patt`, `Pat2` and `Pat3`
+    /// tagged blocks.
+    pub fn patterns(&self) -> &[Pattern] {
+        &self.layer_and_mask_information_section.patterns
+    }
+}
+
+// Methods for generating sprite sheets
+#[cfg(feature = "sprite-sheet")]
+impl Psd {
+    /// Start building a sprite sheet that renders each top level group (or the groups passed to
+    /// [`SpriteSheetBuilder::groups`]), trims it and packs it into a single image with a JSON
+    /// atlas.
+    ///
+    /// ```no_run
+    /// # use psd::Psd;
+    /// # let psd = Psd::from_bytes(&[]).unwrap();
+    /// let sheet = psd.sprite_sheet().max_width(1024).padding(2).pack().unwrap();
+    /// let atlas = sheet.atlas_json();
+    /// ```
+    pub fn sprite_sheet(&self) -> SpriteSheetBuilder<'_> {
+        SpriteSheetBuilder::new(self)
     }
 }
 
@@ -266,8 +1176,176 @@ impl Psd {
 impl Psd {
     /// Get the RGBA pixels for the PSD
     /// [ R,G,B,A, R,G,B,A, R,G,B,A, ...]
+    ///
+    /// The linear pixels of a 32 bit PSD are clamped and tonemapped into sRGB, see
+    /// [`Psd::rgba_f32`] for the original values.
+    ///
+    /// When the PSD was saved without a real final image (see [`Psd::has_real_merged_data`])
+    /// its visible layers are flattened instead, the same as [`Psd::flatten_layers_rgba`].
     pub fn rgba(&self) -> Vec<u8> {
-        self.generate_rgba()
+        match self.flattened_in_place_of_final_image() {
+            Some(rgba) => rgba,
+            None => self.final_image_rgba(),
+        }
+    }
+
+    /// The RGBA pixels of the final image that was saved in the file, even if it's blank.
+    pub(crate) fn final_image_rgba(&self) -> Vec<u8> {
+        match self.depth() {
+            PsdDepth::ThirtyTwo => hdr::tonemap_rgba(&self.rgba_f32()),
+            _ => self.generate_rgba(),
+        }
+    }
+
+    /// The flattened visible layers, if the final image is blank because the PSD was saved
+    /// without maximizing compatibility.
+    fn flattened_in_place_of_final_image(&self) -> Option<Vec<u8>> {
+        if self.has_real_merged_data() || self.layers().is_empty() {
+            return None;
+        }
+
+        self.flatten_layers_rgba(&|_| true).ok()
+    }
+
+    /// The RGBA pixels of a rectangle of the PSD, the same as cropping [`Psd::rgba`] to the
+    /// rectangle.
+    ///
+    /// Only the rows of the final image that overlap the rectangle are decompressed, so
+    /// thumbnailers and tiled viewers don't pay to decode the whole canvas. 32 bit PSDs are the
+    /// exception since they're tonemapped as a whole.
+    ///
+    /// Returns an error if the rectangle is empty or doesn't lie within the canvas.
+    pub fn rgba_region(&self, rect: CropRect) -> Result<Vec<u8>, PsdError> {
+        if !rect.fits(self.width(), self.height()) {
+            return Err(PsdError::InvalidCropRect(rect));
+        }
+
+        if let Some(rgba) = self.flattened_in_place_of_final_image() {
+            return Ok(rect.crop_rgba(&rgba, self.width()));
+        }
+
+        match self.depth() {
+            PsdDepth::ThirtyTwo => Ok(rect.crop_rgba(&self.rgba(), self.width())),
+            _ => Ok(self.generate_rgba_region(rect)),
+        }
+    }
+
+    /// Get the RGBA pixels for the PSD with 16 bits per channel
+    /// [ R,G,B,A, R,G,B,A, R,G,B,A, ...]
+    ///
+    /// The samples of a 16 bit RGB or grayscale PSD are returned as they were stored, without
+    /// being mapped down to 8 bits like [`Psd::rgba`] does. Other PSDs have the pixels of
+    /// [`Psd::rgba`] mapped onto the 16 bit range, so 255 becomes 65535. See [`Psd::rgba_f32`]
+    /// for the original values of a 32 bit PSD.
+    pub fn rgba16(&self) -> Vec<u16> {
+        rgba16::psd_rgba16(self)
+    }
+
+    /// [`Psd::rgba`], written into a buffer that you own with the given layout, such as a mapped
+    /// GPU staging buffer whose rows are padded for alignment.
+    pub fn rgba_into(&self, out: &mut [u8], layout: RgbaLayout) -> Result<(), PsdError> {
+        layout.write(&self.rgba(), self.width(), self.height(), out)
+    }
+
+    /// Get the linear RGBA pixels for the PSD
+    /// [ R,G,B,A, R,G,B,A, R,G,B,A, ...]
+    ///
+    /// A 32 bit PSD's pixels are returned as they were stored, so colors can be brighter than
+    /// 1.0. The sRGB pixels of 8 and 16 bit PSDs are converted to linear values between 0.0 and
+    /// 1.0.
+    pub fn rgba_f32(&self) -> Vec<f32> {
+        match self.depth() {
+            PsdDepth::ThirtyTwo => {
+                let image_data = &self.image_data_section;
+                hdr::interleave_f32(
+                    &image_data.red,
+                    self.green(),
+                    image_data.blue.as_ref(),
+                    image_data.alpha.as_ref(),
+                    self.width(),
+                    self.height(),
+                )
+            }
+            _ => hdr::linearize_rgba(&self.generate_rgba()),
+        }
+    }
+
+    /// Rotate the whole canvas 90° clockwise, the same as Photoshop's Image > Image Rotation >
+    /// 90° Clockwise.
+    ///
+    /// The final image, every layer and layer mask, the guides and the slices are all rotated, so
+    /// writing the PSD with [`Psd::to_bytes`] saves the rotated document. Channels keep their
+    /// compression, except that ZIP compressed channels are compressed without prediction.
+    ///
+    /// Vector masks, text and other data that we don't parse are left as they are.
+    pub fn rotate90(&mut self) {
+        transform::transform_psd(self, CanvasTransform::Rotate90);
+    }
+
+    /// Rotate the whole canvas 180°. See [`Psd::rotate90`] for what is transformed.
+    pub fn rotate180(&mut self) {
+        transform::transform_psd(self, CanvasTransform::Rotate180);
+    }
+
+    /// Mirror the whole canvas from left to right, the same as Photoshop's Image > Image
+    /// Rotation > Flip Canvas Horizontal. See [`Psd::rotate90`] for what is transformed.
+    pub fn flip_horizontal(&mut self) {
+        transform::transform_psd(self, CanvasTransform::FlipHorizontal);
+    }
+
+    /// Crop the whole canvas to a rectangle, the same as Photoshop's Image > Crop.
+    ///
+    /// The new PSD is the size of the rectangle. Layers, layer masks, guides and slices are moved
+    /// so that the rectangle's top left corner becomes the canvas's top left corner, and the
+    /// pixels of the final image, layers and masks that fall outside of the rectangle are
+    /// dropped. See [`Psd::rotate90`] for how channels are compressed and what is left as it is.
+    ///
+    /// Returns an error if the rectangle is empty or doesn't lie within the canvas.
+    pub fn crop(&self, rect: CropRect) -> Result<Psd, PsdError> {
+        if !rect.fits(self.width(), self.height()) {
+            return Err(PsdError::InvalidCropRect(rect));
+        }
+
+        let mut cropped = self.clone();
+        transform::transform_psd(&mut cropped, CanvasTransform::Crop(rect));
+
+        Ok(cropped)
+    }
+
+    /// Resize the whole canvas, the same as Photoshop's Image > Image Size with "Resample"
+    /// checked.
+    ///
+    /// The final image, every layer and layer mask are resampled with the filter, and the
+    /// rectangles of layers, masks and slices and the positions of guides are scaled. Layers and
+    /// masks that had pixels keep at least one pixel. Each channel is resampled on its own, so
+    /// the colors of partially transparent pixels next to transparent ones can bleed.
+    ///
+    /// Indexed color images always use [`ResizeFilter::NearestNeighbor`], since blending color
+    /// table indices doesn't blend their colors. See [`Psd::rotate90`] for how channels are
+    /// compressed and what is left as it is.
+    ///
+    /// Returns an error if the width or height is zero.
+    pub fn resize(&self, width: u32, height: u32, filter: ResizeFilter) -> Result<Psd, PsdError> {
+        if width == 0 || height == 0 {
+            return Err(PsdError::InvalidResizeDimensions(width, height));
+        }
+
+        let filter = match self.color_mode() {
+            ColorMode::Indexed => ResizeFilter::NearestNeighbor,
+            _ => filter,
+        };
+        let resize = Resize {
+            width: self.width(),
+            height: self.height(),
+            new_width: width,
+            new_height: height,
+            filter,
+        };
+
+        let mut resized = self.clone();
+        transform::transform_psd(&mut resized, CanvasTransform::Resize(resize));
+
+        Ok(resized)
     }
 
     /// Get the compression level for the flattened image data
@@ -282,6 +1360,290 @@ impl Psd {
     pub fn resources(&self) -> &Vec<ImageResource> {
         &self.image_resources_section.resources
     }
+
+    /// The physical resolution of the document, from the resolution info resource (1005).
+    pub fn resolution(&self) -> Option<&ResolutionInfo> {
+        self.resources().iter().find_map(|resource| match resource {
+            ImageResource::Resolution(resolution) => Some(resolution),
+            _ => None,
+        })
+    }
+
+    /// The raw bytes of the ICC color profile that the PSD's colors are in, from the ICC profile
+    /// resource (1039).
+    ///
+    /// Pixels are returned as they were stored, so hand the profile to a color management system
+    /// to convert [`Psd::rgba`] into another color space. `None` means that the colors are
+    /// untagged, which Photoshop treats as its working space (usually sRGB).
+    pub fn icc_profile(&self) -> Option<&[u8]> {
+        self.resources().iter().find_map(|resource| match resource {
+            ImageResource::IccProfile(profile) => Some(profile.as_slice()),
+            _ => None,
+        })
+    }
+
+    /// The small preview that Photoshop saves along with the document, from the thumbnail
+    /// resource (1036), for showing previews without decoding the document.
+    ///
+    /// Falls back to the Photoshop 4.0 thumbnail resource (1033), whose red and blue channels
+    /// are swapped, see [`Thumbnail::is_bgr`].
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// if let Some(thumbnail) = psd.thumbnail() {
+    ///     if thumbnail.format() == ThumbnailFormat::Jpeg {
+    ///         std::fs::write("thumbnail.jpg", thumbnail.data())?;
+    ///     }
+    /// }
+    /// ```
+    pub fn thumbnail(&self) -> Option<&Thumbnail> {
+        let mut thumbnails = self
+            .resources()
+            .iter()
+            .filter_map(|resource| match resource {
+                ImageResource::Thumbnail(thumbnail) => Some(thumbnail),
+                _ => None,
+            });
+
+        let first = thumbnails.next()?;
+        if first.is_bgr() {
+            Some(thumbnails.next().unwrap_or(first))
+        } else {
+            Some(first)
+        }
+    }
+
+    /// The application that wrote the file and whether it saved a real final image, from the
+    /// version info resource (1057).
+    pub fn version_info(&self) -> Option<&VersionInfo> {
+        self.resources().iter().find_map(|resource| match resource {
+            ImageResource::VersionInfo(version_info) => Some(version_info),
+            _ => None,
+        })
+    }
+
+    /// Whether the final image in the image data section is a real composite of the layers.
+    ///
+    /// False when the PSD was saved with "Maximize PSD and PSB File Compatibility" turned off,
+    /// which leaves the final image blank. True if the PSD doesn't have a version info resource.
+    pub fn has_real_merged_data(&self) -> bool {
+        self.version_info()
+            .is_none_or(|version_info| version_info.has_real_merged_data())
+    }
+
+    /// Every block in the image resources section in the order that they are stored, including
+    /// the ones that aren't parsed into [`Psd::resources`], along with their names.
+    pub fn resource_blocks(&self) -> &[ImageResourceBlock] {
+        &self.image_resources_section.blocks
+    }
+
+    /// How the image should be rotated or flipped for display, according to the orientation in
+    /// the PSD's EXIF data.
+    ///
+    /// Nothing is rotated automatically. Use [`RenderPipeline::exif_orientation`] when rendering
+    /// layers or [`ExifOrientation::apply_to_rgba`] on [`Psd::rgba`].
+    pub fn exif_orientation(&self) -> Option<ExifOrientation> {
+        self.resource_block_data(RESOURCE_EXIF_DATA)
+            .and_then(ExifOrientation::from_exif)
+    }
+
+    /// The paths that were saved in the paths panel, from the path resources (2000 - 2997), in
+    /// the order that they are stored.
+    #[cfg(feature = "unstable")]
+    pub fn paths(&self) -> Vec<SavedPath> {
+        self.resource_blocks()
+            .iter()
+            .filter(|block| {
+                (RESOURCE_FIRST_SAVED_PATH..=RESOURCE_LAST_SAVED_PATH).contains(&block.id())
+            })
+            .map(|block| SavedPath {
+                id: block.id(),
+                name: block.name(),
+                path: BezierPath::read(block.data()),
+            })
+            .collect()
+    }
+
+    /// The number of colors that an indexed color image uses from its color table, from the
+    /// indexed color table count resource (1046).
+    ///
+    /// The color table always has room for 256 colors, so this tells the colors that are in use
+    /// apart from the padding.
+    pub fn indexed_color_count(&self) -> Option<u16> {
+        self.resource_block_data(RESOURCE_INDEXED_COLOR_COUNT)
+            .and_then(|data| data.get(..2))
+            .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    /// The index of the color in an indexed color image's color table that is transparent, from
+    /// the transparency index resource (1047).
+    pub fn transparency_index(&self) -> Option<u16> {
+        self.resource_block_data(RESOURCE_TRANSPARENCY_INDEX)
+            .and_then(|data| data.get(..2))
+            .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    /// The color table that Photoshop 2.0 stored in a resource (1003) rather than in the color
+    /// mode data section, as RGB triples.
+    ///
+    /// Newer versions of Photoshop don't write this resource, but legacy indexed color files
+    /// may need it to be interpreted.
+    pub fn obsolete_color_table(&self) -> Option<Vec<[u8; 3]>> {
+        self.resource_block_data(RESOURCE_COLOR_TABLE_OBSOLETE)
+            .map(|data| {
+                data.chunks_exact(3)
+                    .map(|color| [color[0], color[1], color[2]])
+                    .collect()
+            })
+    }
+
+    /// The URLs of the document's slices, from the URL list resource (1054), in the order that
+    /// they are stored.
+    ///
+    /// Match [`UrlListEntry::id`] against the IDs of [`SlicesImageResource::slices`] to tell
+    /// which slice links where.
+    pub fn url_list(&self) -> Vec<UrlListEntry> {
+        self.resource_block_data(RESOURCE_URL_LIST)
+            .map(UrlListEntry::read_list)
+            .unwrap_or_default()
+    }
+
+    /// The URL of the document in a workflow, from the workflow URL resource (1035).
+    pub fn workflow_url(&self) -> Option<String> {
+        self.resource_block_data(RESOURCE_WORKFLOW_URL)
+            .and_then(read_unicode_string)
+            .map(|(url, _)| url)
+    }
+
+    /// The index of the layer that was targeted when the document was saved, from the layer
+    /// state resource (1024).
+    ///
+    /// The index counts every layer record from the bottom, including the hidden records that
+    /// open and close groups, see [`Psd::layer_count`].
+    pub fn target_layer_index(&self) -> Option<u16> {
+        self.resources().iter().find_map(|resource| match resource {
+            ImageResource::TargetLayer(index) => Some(*index),
+            _ => None,
+        })
+    }
+
+    /// The dragging group ID of every layer record from the bottom up, from the layer groups
+    /// resource (1026). Layers that Photoshop drags together share an ID, and 0 means no group.
+    pub fn layer_group_ids(&self) -> Option<&[u16]> {
+        self.resources().iter().find_map(|resource| match resource {
+            ImageResource::LayerGroups(ids) => Some(ids.as_slice()),
+            _ => None,
+        })
+    }
+
+    /// Whether the group of every layer record is enabled, from the bottom up, from the layer
+    /// groups enabled resource (1072).
+    pub fn layer_groups_enabled(&self) -> Option<&[u8]> {
+        self.resources().iter().find_map(|resource| match resource {
+            ImageResource::LayerGroupsEnabled(enabled) => Some(enabled.as_slice()),
+            _ => None,
+        })
+    }
+
+    /// The IDs of the layers that were selected when the document was saved, from the layer
+    /// selection IDs resource (1069). Empty if the PSD doesn't say.
+    ///
+    /// See [`Psd::selected_layers`] for the layers themselves.
+    pub fn selected_layer_ids(&self) -> &[u32] {
+        self.resources()
+            .iter()
+            .find_map(|resource| match resource {
+                ImageResource::LayerSelectionIds(ids) => Some(ids.as_slice()),
+                _ => None,
+            })
+            .unwrap_or(&[])
+    }
+
+    /// The layers that were selected when the document was saved, looked up by their
+    /// [`Psd::selected_layer_ids`]. Selected groups are left out since they aren't layers.
+    pub fn selected_layers(&self) -> Vec<&PsdLayer> {
+        self.selected_layer_ids()
+            .iter()
+            .filter_map(|id| self.layer_by_id(*id))
+            .collect()
+    }
+
+    /// The document's guides and grid settings, from the grid and guides resource (1032).
+    ///
+    /// Guides are moved along with the pixels by transforms such as [`Psd::crop`], so they always
+    /// line up with the canvas.
+    ///
+    /// # Example
+    ///
+    /// ```ignore
+    /// let guides = psd.guides().unwrap();
+    /// for guide in guides.guides_in(GuideDirection::Vertical) {
+    ///     println!("Column edge at x = {}", guide.position());
+    /// }
+    /// ```
+    pub fn guides(&self) -> Option<GridAndGuides> {
+        self.resource_block_data(RESOURCE_GRID_AND_GUIDES)
+            .and_then(GridAndGuides::read)
+    }
+
+    /// The data of the first resource block with the given ID
+    fn resource_block_data(&self, id: i16) -> Option<&[u8]> {
+        self.resource_blocks()
+            .iter()
+            .find(|block| block.id() == id)
+            .map(|block| block.data())
+    }
+
+    /// One grayscale plane of ink coverage per printing plate: cyan, magenta, yellow and black
+    /// for a CMYK image, followed by each spot color channel.
+    ///
+    /// The planes come from the PSD's final image, so every layer has already been flattened.
+    /// Alpha channels are skipped, as are spot channels in a PSD without display info (resource
+    /// 1077) since we can't tell them apart from alpha channels.
+    pub fn separations(&self) -> Vec<Separation> {
+        separations::separations(self)
+    }
+
+    /// The alpha and spot channels of the final image, beyond its color channels and merged
+    /// transparency, along with their names (resource 1045, or 1006) and display info (resource
+    /// 1077, or 1007).
+    pub fn extra_channels(&self) -> Vec<ExtraChannel> {
+        separations::extra_channels(self)
+    }
+
+    /// The first alpha or spot channel with the given name, such as the spot channel of a
+    /// `PANTONE 185 C` ink.
+    pub fn extra_channel_by_name(&self, name: &str) -> Option<ExtraChannel> {
+        self.extra_channels()
+            .into_iter()
+            .find(|channel| channel.name() == Some(name))
+    }
+
+    /// Crop every slice in the PSD's slices resource out of the final image, the same way that
+    /// Photoshop's "Save for Web" does.
+    ///
+    /// Each exported slice is named after the slice's name or URL. Slices that Photoshop
+    /// generated automatically don't have either, so they're named after the slices group
+    /// and their number (E.g. `my-design_01`).
+    pub fn export_slices(&self) -> Vec<ExportedSlice> {
+        let rgba = self.rgba();
+
+        let mut exported = vec![];
+        for resource in self.resources() {
+            if let ImageResource::Slices(slices) = resource {
+                exported.extend(slices::export_slices(
+                    &slices.base_name(),
+                    slices.slices(),
+                    &rgba,
+                    self.width(),
+                    self.height(),
+                ));
+            }
+        }
+
+        exported
+    }
 }
 
 impl IntoRgba for Psd {
@@ -309,7 +1671,67 @@ impl IntoRgba for Psd {
     }
 
     fn alpha(&self) -> Option<&ChannelBytes> {
-        self.image_data_section.alpha.as_ref()
+        match self.color_mode() {
+            // The fourth channel is black, so the fifth channel is used as alpha when there are
+            // exactly five channels
+            ColorMode::Cmyk => match self.image_data_section.channels().count() {
+                5 => self.image_data_section.channels().nth(4),
+                _ => None,
+            },
+            _ => self.image_data_section.alpha.as_ref(),
+        }
+    }
+
+    fn black(&self) -> Option<&ChannelBytes> {
+        match self.color_mode() {
+            ColorMode::Cmyk => self.image_data_section.channels().nth(3),
+            _ => None,
+        }
+    }
+
+    fn indexed_colors(&self) -> Option<[[u8; 4]; 256]> {
+        let palette = self.palette()?;
+
+        let mut colors = [[0; 4]; 256];
+        for (color, [red, green, blue]) in colors.iter_mut().zip(palette.iter()) {
+            *color = [*red, *green, *blue, 255];
+        }
+        if let Some(transparent) = self.transparency_index() {
+            if let Some(color) = colors.get_mut(transparent as usize) {
+                color[3] = 0;
+            }
+        }
+
+        Some(colors)
+    }
+
+    fn rle_rows(&self, channel: &ChannelBytes) -> Option<RleRows<'_>> {
+        let section = &self.image_data_section;
+        let channel_idx = section
+            .channels()
+            .position(|bytes| std::ptr::eq(bytes, channel))?;
+        let height = self.height() as usize;
+        let byte_counts = section
+            .scanline_byte_counts
+            .get(channel_idx * height..(channel_idx + 1) * height)?;
+
+        RleRows::new(byte_counts, self.width() as usize, self.depth())
+    }
+
+    /// Raw and ZIP compressed channels are mapped down to 8 bits when the image data is read,
+    /// while RLE compressed channels (and the copies of them that were decompressed in
+    /// parallel) hold samples at the PSD's depth.
+    fn channel_depth(&self, channel: &ChannelBytes) -> PsdDepth {
+        let is_mapped_down = matches!(channel, ChannelBytes::RawData(_))
+            && self
+                .image_data_section
+                .channels()
+                .any(|bytes| std::ptr::eq(bytes, channel));
+
+        match self.depth() {
+            PsdDepth::Sixteen if !is_mapped_down => PsdDepth::Sixteen,
+            _ => PsdDepth::Eight,
+        }
     }
 
     fn psd_width(&self) -> u32 {
@@ -339,4 +1761,54 @@ mod tests {
             PsdError::HeaderError(FileHeaderSectionError::InvalidSignature {})
         );
     }
+
+    /// Verify that a cloned Psd is equal to the original, so that parsed files can be cached
+    /// and compared in tests.
+    #[test]
+    fn clone_and_compare() {
+        let green = Psd::from_bytes(include_bytes!("../tests/fixtures/green-1x1.psd")).unwrap();
+        let slices =
+            Psd::from_bytes(include_bytes!("../tests/fixtures/slices-resource/1.psd")).unwrap();
+
+        assert_eq!(green.clone(), green);
+        assert_eq!(slices.clone(), slices);
+        assert_ne!(green, slices);
+
+        assert_eq!(slices.resources().clone(), *slices.resources());
+    }
+
+    /// Verify that a file that ends in the middle of a section is an error instead of a panic.
+    #[test]
+    fn truncated_file() {
+        let psd = include_bytes!("../tests/fixtures/green-1x1.psd");
+
+        for (len, section) in [(28, "color mode data"), (40, "image resources")].iter() {
+            assert_eq!(
+                Psd::from_bytes(&psd[..*len]).unwrap_err(),
+                PsdError::HeaderError(FileHeaderSectionError::TruncatedSection { section })
+            );
+        }
+    }
+
+    /// Verify that channel lengths that are too small or too large are errors instead of
+    /// underflowing or panicking.
+    #[test]
+    fn invalid_channel_length() {
+        // The length of the first channel of the only layer in green-1x1.psd
+        const FIRST_CHANNEL_LENGTH: usize = 22076;
+
+        for length in [1, u32::MAX].iter() {
+            let mut psd = include_bytes!("../tests/fixtures/green-1x1.psd").to_vec();
+            psd[FIRST_CHANNEL_LENGTH..FIRST_CHANNEL_LENGTH + 4]
+                .copy_from_slice(&length.to_be_bytes());
+
+            assert_eq!(
+                Psd::from_bytes(&psd).unwrap_err(),
+                PsdError::LayerError(PsdLayerError::InvalidChannelLength {
+                    channel: PsdChannelKind::TransparencyMask,
+                    length: *length as u64,
+                })
+            );
+        }
+    }
 }
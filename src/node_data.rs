@@ -0,0 +1,151 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt;
+use std::panic::{RefUnwindSafe, UnwindSafe};
+
+/// Identifies a group or layer that data can be attached to with [`Psd::node_data_mut`].
+///
+/// [`Psd::node_data_mut`]: crate::Psd::node_data_mut
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum NodeId {
+    /// The index of a layer in [`Psd::layers`]
+    ///
+    /// [`Psd::layers`]: crate::Psd::layers
+    Layer(usize),
+    /// The ID of a group, see [`PsdGroup::id`]
+    ///
+    /// [`PsdGroup::id`]: crate::PsdGroup::id
+    Group(u32),
+}
+
+/// Data that can be attached to a node. Implemented for every type that can be cloned, shared
+/// between threads and unwound through, so that attaching data doesn't stop a `Psd` from being
+/// `Clone`, `Send`, `Sync`, `UnwindSafe` or `RefUnwindSafe`.
+trait NodeDataValue: Any + Send + Sync + UnwindSafe + RefUnwindSafe {
+    fn clone_box(&self) -> Box<dyn NodeDataValue>;
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+    fn into_any(self: Box<Self>) -> Box<dyn Any>;
+}
+
+impl<T: Any + Clone + Send + Sync + UnwindSafe + RefUnwindSafe> NodeDataValue for T {
+    fn clone_box(&self) -> Box<dyn NodeDataValue> {
+        Box::new(self.clone())
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn into_any(self: Box<Self>) -> Box<dyn Any> {
+        self
+    }
+}
+
+/// Arbitrary typed data that callers have attached to groups and layers.
+///
+/// A node can have one value of each type attached to it.
+///
+/// The data belongs to the caller rather than the PSD file, so it is ignored when comparing
+/// two `Psd`s.
+#[derive(Default)]
+pub(crate) struct NodeDataMap {
+    data: HashMap<(NodeId, TypeId), Box<dyn NodeDataValue>>,
+}
+
+impl NodeDataMap {
+    pub(crate) fn get<T: Any>(&self, node: NodeId) -> Option<&T> {
+        self.data
+            .get(&(node, TypeId::of::<T>()))
+            .and_then(|value| (**value).as_any().downcast_ref())
+    }
+
+    /// Get the data of type `T`, inserting `T::default()` if there isn't any yet.
+    pub(crate) fn get_mut_or_default<
+        T: Any + Clone + Default + Send + Sync + UnwindSafe + RefUnwindSafe,
+    >(
+        &mut self,
+        node: NodeId,
+    ) -> &mut T {
+        let value = self
+            .data
+            .entry((node, TypeId::of::<T>()))
+            .or_insert_with(|| Box::new(T::default()));
+
+        (**value).as_any_mut().downcast_mut().unwrap()
+    }
+
+    pub(crate) fn insert<T: Any + Clone + Send + Sync + UnwindSafe + RefUnwindSafe>(
+        &mut self,
+        node: NodeId,
+        data: T,
+    ) -> Option<T> {
+        self.data
+            .insert((node, TypeId::of::<T>()), Box::new(data))
+            .map(|old| *old.into_any().downcast().unwrap())
+    }
+
+    pub(crate) fn remove<T: Any>(&mut self, node: NodeId) -> Option<T> {
+        self.data
+            .remove(&(node, TypeId::of::<T>()))
+            .map(|old| *old.into_any().downcast().unwrap())
+    }
+}
+
+impl Clone for NodeDataMap {
+    fn clone(&self) -> Self {
+        NodeDataMap {
+            data: self
+                .data
+                .iter()
+                .map(|(key, value)| (*key, (**value).clone_box()))
+                .collect(),
+        }
+    }
+}
+
+impl PartialEq for NodeDataMap {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl fmt::Debug for NodeDataMap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("NodeDataMap")
+            .field("len", &self.data.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Verify that each node can hold one value per type and that clones are independent.
+    #[test]
+    fn typed_values_per_node() {
+        let mut map = NodeDataMap::default();
+        let layer = NodeId::Layer(0);
+
+        *map.get_mut_or_default::<u32>(layer) += 5;
+        assert_eq!(map.insert(layer, String::from("selected")), None);
+
+        assert_eq!(map.get::<u32>(layer), Some(&5));
+        assert_eq!(map.get::<String>(layer).unwrap(), "selected");
+        assert_eq!(map.get::<u32>(NodeId::Group(0)), None);
+        assert_eq!(map.get::<u8>(layer), None);
+
+        let mut cloned = map.clone();
+        *cloned.get_mut_or_default::<u32>(layer) += 1;
+        assert_eq!(map.get::<u32>(layer), Some(&5));
+        assert_eq!(cloned.get::<u32>(layer), Some(&6));
+
+        assert_eq!(map.remove::<String>(layer).unwrap(), "selected");
+        assert_eq!(map.get::<String>(layer), None);
+    }
+}
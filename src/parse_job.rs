@@ -0,0 +1,260 @@
+//! Parsing a PSD a bounded amount of work at a time, so that a UI thread can stay responsive
+//! while it loads a large file.
+
+use crate::sections::file_header_section::FileHeaderSection;
+use crate::sections::image_data_section::ImageDataSection;
+use crate::sections::image_resources_section::ImageResourcesSection;
+use crate::sections::layer_and_mask_information_section::{
+    LayerAndMaskInformationSection, LayerSectionReader,
+};
+use crate::sections::MajorSections;
+use crate::{ColorMode, ImageResource, Psd, PsdDepth, PsdError, PsdLayer};
+
+/// The number of layer records, or of their channels, that a step reads by default
+const DEFAULT_LAYERS_PER_STEP: usize = 32;
+
+/// Parses a PSD a step at a time, such as once per animation frame, so that loading a large file
+/// doesn't block a UI thread for the whole parse.
+///
+/// Each step parses one part of the file, and what has been parsed so far is available right
+/// away: first the file header ([`ParseJob::dimensions`]), then the image resources
+/// ([`ParseJob::resources`]), then the layer records and their channels a few layers per step
+/// ([`ParseJob::layers`]), and finally the final image.
+///
+/// Channels are decompressed lazily, the same as with [`Psd::from_bytes`], so the final image
+/// takes a single step.
+///
+/// ```no_run
+/// # use psd::{ParseJob, ParseProgress};
+/// # let bytes: &[u8] = &[];
+/// let mut job = ParseJob::new(bytes).layers_per_step(16);
+///
+/// while !job.is_done() {
+///     match job.step().unwrap() {
+///         ParseProgress::LayerChannels { read, total } => println!("{}/{} layers", read, total),
+///         _ => {}
+///     }
+///     // Yield to the UI here
+/// }
+///
+/// let psd = job.finish().unwrap();
+/// ```
+///
+/// [`Psd::from_bytes`]: crate::Psd::from_bytes
+pub struct ParseJob<'a> {
+    bytes: &'a [u8],
+    layers_per_step: usize,
+    major_sections: Option<MajorSections<'a>>,
+    file_header_section: Option<FileHeaderSection>,
+    image_resources_section: Option<ImageResourcesSection>,
+    layer_reader: Option<LayerSectionReader<'a>>,
+    layer_and_mask_information_section: Option<LayerAndMaskInformationSection>,
+    psd: Option<Psd>,
+}
+
+/// What a call to [`ParseJob::step`] parsed.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum ParseProgress {
+    /// The file header, see [`ParseJob::dimensions`]
+    Header,
+    /// The image resources section, see [`ParseJob::resources`]
+    ImageResources,
+    /// Some of the layer records, which every layer's channels come after. The counts include
+    /// the records that open and close groups.
+    LayerRecords {
+        /// The number of layer records that have been read
+        read: usize,
+        /// The number of layer records in the file
+        total: usize,
+    },
+    /// The channels of some of the layer records. Once every record's channels have been read
+    /// the layers are available, see [`ParseJob::layers`].
+    LayerChannels {
+        /// The number of layer records whose channels have been read
+        read: usize,
+        /// The number of layer records in the file
+        total: usize,
+    },
+    /// The final image. The whole PSD has been parsed, see [`ParseJob::finish`].
+    Done,
+}
+
+impl<'a> ParseJob<'a> {
+    /// Start parsing the bytes of a PSD file. Nothing is parsed until the first step.
+    pub fn new(bytes: &'a [u8]) -> ParseJob<'a> {
+        ParseJob {
+            bytes,
+            layers_per_step: DEFAULT_LAYERS_PER_STEP,
+            major_sections: None,
+            file_header_section: None,
+            image_resources_section: None,
+            layer_reader: None,
+            layer_and_mask_information_section: None,
+            psd: None,
+        }
+    }
+
+    /// The number of layer records, or of their channels, to read in each step. Defaults to 32.
+    pub fn layers_per_step(mut self, layers_per_step: usize) -> ParseJob<'a> {
+        self.layers_per_step = layers_per_step.max(1);
+        self
+    }
+
+    /// Parse the next part of the file.
+    ///
+    /// Once the whole file has been parsed this keeps returning [`ParseProgress::Done`]. If a
+    /// step fails, calling it again returns the same error.
+    pub fn step(&mut self) -> Result<ParseProgress, PsdError> {
+        if self.psd.is_some() {
+            return Ok(ParseProgress::Done);
+        }
+
+        let major_sections = match self.major_sections {
+            Some(major_sections) => major_sections,
+            None => {
+                let major_sections =
+                    MajorSections::from_bytes(self.bytes).map_err(PsdError::HeaderError)?;
+                let file_header_section = FileHeaderSection::from_bytes(major_sections.file_header)
+                    .map_err(PsdError::HeaderError)?;
+
+                self.major_sections = Some(major_sections);
+                self.file_header_section = Some(file_header_section);
+                return Ok(ParseProgress::Header);
+            }
+        };
+        let header = self.file_header_section.as_ref().unwrap();
+
+        if self.image_resources_section.is_none() {
+            let image_resources_section =
+                ImageResourcesSection::from_bytes(major_sections.image_resources)
+                    .map_err(PsdError::ResourceError)?;
+
+            self.image_resources_section = Some(image_resources_section);
+            return Ok(ParseProgress::ImageResources);
+        }
+
+        if self.layer_and_mask_information_section.is_none() {
+            let (version, depth) = (header.version, header.depth);
+            let (width, height) = (header.width.0, header.height.0);
+            let reader = self.layer_reader.get_or_insert_with(|| {
+                LayerSectionReader::new(
+                    major_sections.layer_and_mask,
+                    version,
+                    depth,
+                    width,
+                    height,
+                )
+            });
+
+            let was_reading_records = reader.records_read() < reader.layer_count();
+            reader
+                .read(self.layers_per_step)
+                .map_err(PsdError::LayerError)?;
+
+            let total = reader.layer_count();
+            let progress = match was_reading_records {
+                true => ParseProgress::LayerRecords {
+                    read: reader.records_read(),
+                    total,
+                },
+                false => ParseProgress::LayerChannels {
+                    read: reader.channels_read(),
+                    total,
+                },
+            };
+
+            if reader.is_done() {
+                let reader = self.layer_reader.take().unwrap();
+                let section = reader.finish().map_err(PsdError::LayerError)?;
+                self.layer_and_mask_information_section = Some(section);
+            }
+
+            return Ok(progress);
+        }
+
+        let image_data_section = ImageDataSection::from_bytes(
+            major_sections.image_data,
+            header.depth,
+            header.version,
+            header.width.0,
+            header.height.0,
+            header.channel_count.count(),
+        )
+        .map_err(PsdError::ImageError)?;
+
+        self.psd = Some(Psd::from_sections(
+            self.file_header_section.take().unwrap(),
+            major_sections.color_mode_data,
+            self.image_resources_section.take().unwrap(),
+            self.layer_and_mask_information_section.take().unwrap(),
+            image_data_section,
+        ));
+
+        Ok(ParseProgress::Done)
+    }
+
+    /// Whether the whole file has been parsed
+    pub fn is_done(&self) -> bool {
+        self.psd.is_some()
+    }
+
+    /// Parse whatever is left of the file and return the PSD.
+    pub fn finish(mut self) -> Result<Psd, PsdError> {
+        while !self.is_done() {
+            self.step()?;
+        }
+
+        Ok(self.psd.unwrap())
+    }
+
+    /// The (width, height) of the PSD, once the file header has been parsed
+    pub fn dimensions(&self) -> Option<(u32, u32)> {
+        match (&self.psd, &self.file_header_section) {
+            (Some(psd), _) => Some((psd.width(), psd.height())),
+            (None, Some(header)) => Some((header.width.0, header.height.0)),
+            (None, None) => None,
+        }
+    }
+
+    /// The depth of the PSD, once the file header has been parsed
+    pub fn depth(&self) -> Option<PsdDepth> {
+        match (&self.psd, &self.file_header_section) {
+            (Some(psd), _) => Some(psd.depth()),
+            (None, Some(header)) => Some(header.depth),
+            (None, None) => None,
+        }
+    }
+
+    /// The color mode of the PSD, once the file header has been parsed
+    pub fn color_mode(&self) -> Option<ColorMode> {
+        match (&self.psd, &self.file_header_section) {
+            (Some(psd), _) => Some(psd.color_mode()),
+            (None, Some(header)) => Some(header.color_mode),
+            (None, None) => None,
+        }
+    }
+
+    /// The image resources, once the image resources section has been parsed. See
+    /// [`Psd::resources`].
+    ///
+    /// [`Psd::resources`]: crate::Psd::resources
+    pub fn resources(&self) -> Option<&[ImageResource]> {
+        match (&self.psd, &self.image_resources_section) {
+            (Some(psd), _) => Some(psd.resources()),
+            (None, Some(section)) => Some(&section.resources),
+            (None, None) => None,
+        }
+    }
+
+    /// The layers, once every layer record and its channels have been read. Their pixels are
+    /// decoded when you ask for them. See [`Psd::layers`].
+    ///
+    /// [`Psd::layers`]: crate::Psd::layers
+    pub fn layers(&self) -> Option<&[PsdLayer]> {
+        match (&self.psd, &self.layer_and_mask_information_section) {
+            (Some(psd), _) => Some(psd.layers().as_slice()),
+            (None, Some(section)) => Some(section.layers.as_slice()),
+            (None, None) => None,
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use std::time::{Duration, Instant};
+
+use crate::sections::image_data_section::ChannelBytes;
+
+/// Statistics about parsing a PSD file, returned by [`Psd::from_bytes_with_stats`].
+///
+/// Useful for tracking the performance of ingesting PSD files in production without reaching
+/// for an external profiler.
+///
+/// [`Psd::from_bytes_with_stats`]: crate::Psd::from_bytes_with_stats
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
+pub struct ParseStats {
+    pub(crate) total: Duration,
+    pub(crate) file_header_section: Duration,
+    pub(crate) image_resources_section: Duration,
+    pub(crate) layer_and_mask_information_section: Duration,
+    pub(crate) image_data_section: Duration,
+    pub(crate) compressed_bytes: usize,
+    pub(crate) layers_parsed: usize,
+}
+
+impl ParseStats {
+    /// The time spent parsing the entire file
+    pub fn total(&self) -> Duration {
+        self.total
+    }
+
+    /// The time spent parsing the file header section
+    pub fn file_header_section(&self) -> Duration {
+        self.file_header_section
+    }
+
+    /// The time spent parsing the image resources section
+    pub fn image_resources_section(&self) -> Duration {
+        self.image_resources_section
+    }
+
+    /// The time spent parsing the layer and mask information section
+    pub fn layer_and_mask_information_section(&self) -> Duration {
+        self.layer_and_mask_information_section
+    }
+
+    /// The time spent parsing the image data section
+    pub fn image_data_section(&self) -> Duration {
+        self.image_data_section
+    }
+
+    /// The number of RLE compressed channel bytes across the layers and the final image.
+    ///
+    /// Channels are decompressed lazily when you ask for their pixels (E.g. `Psd::rgba`), so
+    /// this is the amount of data that decompressing every channel would need to churn through.
+    pub fn compressed_bytes(&self) -> usize {
+        self.compressed_bytes
+    }
+
+    /// The number of layers that were parsed (not including groups)
+    pub fn layers_parsed(&self) -> usize {
+        self.layers_parsed
+    }
+}
+
+/// Run `f`, storing how long it took in `duration` if we are collecting stats.
+///
+/// We avoid calling `Instant::now` when stats weren't requested since it panics on some
+/// platforms, such as `wasm32-unknown-unknown`.
+pub(crate) fn timed<T>(duration: Option<&mut Duration>, f: impl FnOnce() -> T) -> T {
+    match duration {
+        Some(duration) => {
+            let start = Instant::now();
+            let result = f();
+            *duration = start.elapsed();
+            result
+        }
+        None => f(),
+    }
+}
+
+/// The number of bytes in a channel if it is RLE compressed
+pub(crate) fn compressed_len(channel: &ChannelBytes) -> usize {
+    match channel {
+        ChannelBytes::RawData(_) => 0,
+        ChannelBytes::RleCompressed(bytes) => bytes.len(),
+    }
+}
@@ -0,0 +1,19 @@
+//! The types that most code working with PSDs needs, so that they can be imported at once.
+//!
+//! ```
+//! use psd::prelude::*;
+//!
+//! fn visible_layer_names(psd: &Psd) -> Vec<&str> {
+//!     psd.layers()
+//!         .iter()
+//!         .filter(|layer| layer.visible())
+//!         .map(|layer| layer.name())
+//!         .collect()
+//! }
+//! ```
+
+pub use crate::{
+    BlendMode, ColorMode, ImageResource, LayerMask, LayerProperties, LayerRgbaCache, Psd,
+    PsdChannelCompression, PsdChannelKind, PsdDepth, PsdError, PsdGroup, PsdLayer, PsdNode,
+    RenderPipeline,
+};
@@ -1,5 +1,9 @@
+use crate::hdr;
 use crate::sections::image_data_section::ChannelBytes;
 use crate::sections::PsdCursor;
+use crate::{CropRect, PsdDepth};
+use std::borrow::Cow;
+use std::ops::Range;
 use thiserror::Error;
 
 pub trait IntoRgba {
@@ -27,6 +31,38 @@ pub trait IntoRgba {
     /// The fourth channel
     fn alpha(&self) -> Option<&ChannelBytes>;
 
+    /// The black channel of a CMYK image or layer, in which case the first three channels are
+    /// cyan, magenta and yellow rather than red, green and blue.
+    fn black(&self) -> Option<&ChannelBytes> {
+        None
+    }
+
+    /// The RGBA color of every index of an indexed color image, in which case the first channel
+    /// holds indices rather than red values.
+    fn indexed_colors(&self) -> Option<[[u8; 4]; 256]> {
+        None
+    }
+
+    /// The compressed length of each row of one of our RLE compressed channels, so that every
+    /// row can be decoded on its own, see `RleRows`.
+    ///
+    /// `None` decodes the channel as one stream, where a row that decodes to the wrong number
+    /// of samples shifts every row after it.
+    fn rle_rows(&self, _channel: &ChannelBytes) -> Option<RleRows<'_>> {
+        None
+    }
+
+    /// The row of the PSD that the first row of our channels lands on
+    fn top_in_psd(&self) -> i32 {
+        0
+    }
+
+    /// The number of bits in each sample of one of our channels. 16 and 32 bit samples are
+    /// mapped down to 8 bits as they're interleaved, see `samples_to_eight_bits`.
+    fn channel_depth(&self, _channel: &ChannelBytes) -> PsdDepth {
+        PsdDepth::Eight
+    }
+
     /// The width of the PSD
     fn psd_width(&self) -> u32;
 
@@ -34,12 +70,53 @@ pub trait IntoRgba {
     fn psd_height(&self) -> u32;
 
     fn generate_rgba(&self) -> Vec<u8> {
-        let rgba_len = (self.psd_width() * self.psd_height() * 4) as usize;
-
         let red = self.red();
         let green = self.green();
         let blue = self.blue();
         let alpha = self.alpha();
+        let black = self.black();
+
+        // Decompressing the channels is most of the work, so do it for every channel at once
+        #[cfg(feature = "rayon")]
+        let decompressed = rle_decompress_in_parallel(
+            [Some(red), green, blue, alpha, black]
+                .map(|channel| channel.map(|channel| (channel, self.rle_rows(channel)))),
+        );
+        #[cfg(feature = "rayon")]
+        let (red, green, blue, alpha, black) = (
+            decompressed[0].as_ref().unwrap_or(red),
+            decompressed[1].as_ref().or(green),
+            decompressed[2].as_ref().or(blue),
+            decompressed[3].as_ref().or(alpha),
+            decompressed[4].as_ref().or(black),
+        );
+
+        let psd = CropRect::new(0, 0, self.psd_width(), self.psd_height());
+        self.rgba_from_channels(psd, red, [green, blue, alpha, black])
+    }
+
+    /// [`IntoRgba::generate_rgba`] for a rectangle of the PSD that lies within the PSD, with one
+    /// row of the RGBA image per row of the rectangle.
+    ///
+    /// Only the rows of RLE compressed channels that overlap the rectangle are decompressed when
+    /// we know where each row starts, see `IntoRgba::rle_rows`.
+    fn generate_rgba_region(&self, region: CropRect) -> Vec<u8> {
+        self.rgba_from_channels(
+            region,
+            self.red(),
+            [self.green(), self.blue(), self.alpha(), self.black()],
+        )
+    }
+
+    /// Interleave the (decompressed or not) red channel and the green, blue, alpha and black
+    /// channels into the RGBA image of a rectangle of the PSD.
+    fn rgba_from_channels(
+        &self,
+        region: CropRect,
+        red: &ChannelBytes,
+        [green, blue, alpha, black]: [Option<&ChannelBytes>; 4],
+    ) -> Vec<u8> {
+        let rgba_len = region.width() as usize * region.height() as usize * 4;
 
         // TODO: We're assuming that if we only see two channels it is a 16 bit grayscale
         // PSD. Instead we should just check the Psd's color mode and depth to see if
@@ -51,28 +128,28 @@ pub trait IntoRgba {
 
         let mut rgba = vec![0; rgba_len];
 
-        use crate::psd_channel::PsdChannelKind::*;
+        use crate::psd_channel::RgbaChannel::*;
 
-        self.insert_channel_bytes(&mut rgba, Red, red);
+        self.insert_channel_bytes(&mut rgba, region, Red, red);
 
         // If there is a green channel we use it, otherwise we use the red channel since this is
         // a single channel grey image (such as a heightmap).
         if let Some(green) = green {
-            self.insert_channel_bytes(&mut rgba, Green, green);
+            self.insert_channel_bytes(&mut rgba, region, Green, green);
         } else {
-            self.insert_channel_bytes(&mut rgba, Green, red);
+            self.insert_channel_bytes(&mut rgba, region, Green, red);
         }
 
         // If there is a blue channel we use it, otherwise we use the red channel since this is
         // a single channel grey image (such as a heightmap).
         if let Some(blue) = blue {
-            self.insert_channel_bytes(&mut rgba, Blue, blue);
+            self.insert_channel_bytes(&mut rgba, region, Blue, blue);
         } else {
-            self.insert_channel_bytes(&mut rgba, Blue, red);
+            self.insert_channel_bytes(&mut rgba, region, Blue, red);
         }
 
         if let Some(alpha_channel) = alpha {
-            self.insert_channel_bytes(&mut rgba, TransparencyMask, alpha_channel);
+            self.insert_channel_bytes(&mut rgba, region, Alpha, alpha_channel);
         } else {
             // If there is no transparency data then the image is opaque
             for idx in 0..rgba_len / 4 {
@@ -80,6 +157,25 @@ pub trait IntoRgba {
             }
         }
 
+        if let Some(black) = black {
+            // Interleave the black channel the same way as the other channels so that each pixel's
+            // black value lines up with its cyan, magenta and yellow values
+            let mut black_rgba = vec![0; rgba_len];
+            self.insert_channel_bytes(&mut black_rgba, region, Red, black);
+
+            cmyk_to_rgb(&mut rgba, &black_rgba);
+        }
+
+        if let Some(colors) = self.indexed_colors() {
+            for pixel in rgba.chunks_exact_mut(4) {
+                let [red, green, blue, alpha] = colors[pixel[0] as usize];
+                pixel[0] = red;
+                pixel[1] = green;
+                pixel[2] = blue;
+                pixel[3] = ((pixel[3] as u16 * alpha as u16 + 127) / 255) as u8;
+            }
+        }
+
         rgba
     }
 
@@ -111,29 +207,45 @@ pub trait IntoRgba {
         }
     }
 
-    /// Given some vector of bytes, insert the bytes from the given channel into the vector.
+    /// Given some vector of bytes, insert the bytes from the given channel that land in the
+    /// `region` of the PSD into the vector.
     ///
     /// Doing it this way allows us to allocate for one vector and insert all 4 (RGBA) channels into
     /// it.
     fn insert_channel_bytes(
         &self,
         rgba: &mut Vec<u8>,
-        channel_kind: PsdChannelKind,
+        region: CropRect,
+        channel: RgbaChannel,
         channel_bytes: &ChannelBytes,
     ) {
+        let depth = self.channel_depth(channel_bytes);
+
         match channel_bytes {
-            ChannelBytes::RawData(channel_bytes) => {
-                let offset = channel_kind.rgba_offset().unwrap();
+            ChannelBytes::RawData(samples) => {
+                let offset = channel.offset();
+
+                let samples = match depth {
+                    PsdDepth::One | PsdDepth::Eight => Cow::Borrowed(&samples[..]),
+                    _ => Cow::Owned(samples_to_eight_bits(
+                        samples,
+                        depth,
+                        channel != RgbaChannel::Alpha,
+                    )),
+                };
 
-                for (idx, byte) in channel_bytes.iter().enumerate() {
-                    if let Some(rgba_idx) = self.rgba_idx(idx) {
-                        rgba[rgba_idx * 4 + offset] = *byte;
+                for (idx, byte) in samples.iter().enumerate() {
+                    if let Some(rgba_idx) = self.region_idx(region, idx) {
+                        if let Some(buffer) = rgba.get_mut(rgba_idx * 4 + offset) {
+                            *buffer = *byte;
+                        }
                     }
                 }
             }
             // https://en.wikipedia.org/wiki/PackBits
-            ChannelBytes::RleCompressed(channel_bytes) => {
-                self.insert_rle_channel(rgba, channel_kind, &channel_bytes);
+            ChannelBytes::RleCompressed(compressed) => {
+                let rows = self.rle_rows(channel_bytes);
+                self.insert_rle_channel(rgba, region, channel, compressed, rows, depth);
             }
         }
     }
@@ -147,57 +259,212 @@ pub trait IntoRgba {
     fn insert_rle_channel(
         &self,
         rgba: &mut Vec<u8>,
-        channel_kind: PsdChannelKind,
+        region: CropRect,
+        channel: RgbaChannel,
         channel_bytes: &[u8],
+        rows: Option<RleRows>,
+        depth: PsdDepth,
     ) {
-        let mut cursor = PsdCursor::new(&channel_bytes[..]);
+        let offset = channel.offset();
+
+        // The rows of the channel that land in the region
+        let top = region.top() as i64 - self.top_in_psd() as i64;
+        let bottom = top + region.height() as i64;
+        let rows_in_region = top.max(0) as usize..bottom.max(0) as usize;
 
-        let mut idx = 0;
-        let offset = channel_kind.rgba_offset().unwrap();
-        let len = cursor.get_ref().len() as u64;
+        // Samples wider than a byte are gathered and then mapped down to 8 bits
+        let bytes_per_sample = match depth {
+            PsdDepth::Sixteen => 2,
+            PsdDepth::ThirtyTwo => 4,
+            PsdDepth::One | PsdDepth::Eight => 1,
+        };
+        let is_color = channel != RgbaChannel::Alpha;
+        let mut sample = [0; 4];
 
-        while cursor.position() < len {
-            let header = cursor.read_i8() as i16;
+        for_each_rle_sample(channel_bytes, rows, rows_in_region, |byte_idx, byte| {
+            sample[byte_idx % bytes_per_sample] = byte;
+            if byte_idx % bytes_per_sample != bytes_per_sample - 1 {
+                return;
+            }
 
-            if header == -128 {
-                continue;
-            } else if header >= 0 {
-                let bytes_to_read = 1 + header;
-                if cursor.position() + bytes_to_read as u64 > len {
-                    break;
+            let idx = byte_idx / bytes_per_sample;
+            if let Some(rgba_idx) = self.region_idx(region, idx) {
+                if let Some(buffer) = rgba.get_mut(rgba_idx * 4 + offset) {
+                    *buffer = sample_to_eight_bits(&sample[..bytes_per_sample], is_color);
                 }
-                for byte in cursor.read(bytes_to_read as u32) {
-                    if let Some(rgba_idx) = self.rgba_idx(idx) {
-                        if let Some(buffer) = rgba.get_mut(rgba_idx * 4 + offset) {
-                            *buffer = *byte;
-                        }
-                    }
+            }
+        });
+    }
 
-                    idx += 1;
-                }
-            } else {
-                let repeat = 1 - header;
+    /// Given an index of a pixel in the current rectangle return the index of that pixel in the
+    /// RGBA image of the `region` of the PSD, see [`IntoRgba::rgba_idx`].
+    ///
+    /// `None` if the pixel doesn't land in the region.
+    fn region_idx(&self, region: CropRect, idx: usize) -> Option<usize> {
+        let psd_idx = self.rgba_idx(idx)?;
+        let psd_width = self.psd_width() as usize;
 
-                if cursor.position() + 1 > len {
-                    break;
-                }
-                let byte = cursor.read_1()[0];
-                for _ in 0..repeat {
-                    if let Some(rgba_idx) = self.rgba_idx(idx) {
-                        if let Some(buffer) = rgba.get_mut(rgba_idx * 4 + offset) {
-                            *buffer = byte;
-                        }
-                    }
+        // Regions that span the width of the PSD are a run of rows, so we skip the math per pixel
+        if region.left() == 0 && region.width() as usize == psd_width {
+            return psd_idx.checked_sub(region.top() as usize * psd_width);
+        }
 
-                    idx += 1;
-                }
-            };
+        let (left, top) = (psd_idx % psd_width, psd_idx / psd_width);
+        let left = left.checked_sub(region.left() as usize)?;
+        let top = top.checked_sub(region.top() as usize)?;
+        if left >= region.width() as usize || top >= region.height() as usize {
+            return None;
+        }
+
+        Some(top * region.width() as usize + left)
+    }
+}
+
+/// Where each row of an RLE compressed channel starts, see `IntoRgba::rle_rows`
+#[derive(Debug, Clone, Copy)]
+pub struct RleRows<'a> {
+    /// The compressed length of each row
+    pub(crate) byte_counts: &'a [u32],
+    /// The number of samples (bytes) that each row decodes to
+    pub(crate) row_len: usize,
+}
+
+impl<'a> RleRows<'a> {
+    /// The rows of a channel whose samples are `depth` bits, or `None` for depths that we don't
+    /// decode a row at a time.
+    pub(crate) fn new(byte_counts: &'a [u32], width: usize, depth: PsdDepth) -> Option<Self> {
+        let bytes_per_sample = match depth {
+            PsdDepth::Eight => 1,
+            PsdDepth::Sixteen => 2,
+            PsdDepth::ThirtyTwo => 4,
+            PsdDepth::One => return None,
+        };
+
+        Some(RleRows {
+            byte_counts,
+            row_len: width * bytes_per_sample,
+        })
+    }
+
+    /// Whether the rows add up to the length of the channel, without which we can't tell where
+    /// each row starts
+    fn fits(&self, channel_bytes: &[u8]) -> bool {
+        let total: u64 = self.byte_counts.iter().map(|count| *count as u64).sum();
+        total == channel_bytes.len() as u64
+    }
+}
+
+/// Rle decompress a channel, calling `f` with the index of each sample along with the sample.
+///
+/// When the channel's `rows` are known each row is decoded on its own, so a corrupt row only
+/// loses its own samples: a row that decodes to too many samples is cut short and one that
+/// decodes to too few leaves the rest of its samples alone. Rows outside of `rows_wanted` are
+/// skipped without being decoded. Otherwise, or when the rows don't add up to the channel's
+/// length, the whole channel is decoded as one stream.
+fn for_each_rle_sample(
+    channel_bytes: &[u8],
+    rows: Option<RleRows>,
+    rows_wanted: Range<usize>,
+    mut f: impl FnMut(usize, u8),
+) {
+    let rows = match rows.filter(|rows| rows.fits(channel_bytes)) {
+        Some(rows) => rows,
+        None => {
+            let mut idx = 0;
+            for_each_rle_byte(channel_bytes, |byte| {
+                f(idx, byte);
+                idx += 1;
+            });
+            return;
+        }
+    };
+
+    let mut row_start = 0;
+    for (row, byte_count) in rows.byte_counts.iter().enumerate() {
+        if row >= rows_wanted.end {
+            break;
         }
+        let row_end = row_start + *byte_count as usize;
+        if row < rows_wanted.start {
+            row_start = row_end;
+            continue;
+        }
+        let row_samples = row * rows.row_len..(row + 1) * rows.row_len;
+
+        let mut idx = row_samples.start;
+        for_each_rle_byte(&channel_bytes[row_start..row_end], |byte| {
+            if row_samples.contains(&idx) {
+                f(idx, byte);
+            }
+            idx += 1;
+        });
+
+        row_start = row_end;
+    }
+}
+
+/// Rle decompress a channel one byte at a time, stopping at the first run that is cut short.
+///
+/// https://en.wikipedia.org/wiki/PackBits - algorithm used for decompression
+fn for_each_rle_byte(channel_bytes: &[u8], mut f: impl FnMut(u8)) {
+    let mut cursor = PsdCursor::new(channel_bytes);
+    let len = cursor.get_ref().len() as u64;
+
+    while cursor.position() < len {
+        let header = cursor.read_i8() as i16;
+
+        if header == -128 {
+            continue;
+        } else if header >= 0 {
+            let bytes_to_read = 1 + header;
+            if cursor.position() + bytes_to_read as u64 > len {
+                break;
+            }
+            for byte in cursor.read(bytes_to_read as u32) {
+                f(*byte);
+            }
+        } else {
+            let repeat = 1 - header;
+
+            if cursor.position() + 1 > len {
+                break;
+            }
+            let byte = cursor.read_1()[0];
+            for _ in 0..repeat {
+                f(byte);
+            }
+        };
     }
 }
 
+/// Rle decompress the compressed channels on separate threads, returning `None` for the channels
+/// that are missing or that aren't compressed.
+#[cfg(feature = "rayon")]
+fn rle_decompress_in_parallel(
+    channels: [Option<(&ChannelBytes, Option<RleRows>)>; 5],
+) -> Vec<Option<ChannelBytes>> {
+    use rayon::prelude::*;
+
+    channels
+        .par_iter()
+        .map(|channel| match channel {
+            Some((ChannelBytes::RleCompressed(channel_bytes), rows)) => {
+                let mut decompressed = vec![];
+                for_each_rle_sample(channel_bytes, *rows, 0..usize::MAX, |idx, byte| {
+                    if decompressed.len() <= idx {
+                        decompressed.resize(idx + 1, 0);
+                    }
+                    decompressed[idx] = byte;
+                });
+                Some(ChannelBytes::RawData(decompressed.into()))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
 /// Rle decompress a channel
-fn rle_decompress(bytes: &[u8]) -> Vec<u8> {
+pub(crate) fn rle_decompress(bytes: &[u8]) -> Vec<u8> {
     let mut cursor = PsdCursor::new(&bytes[..]);
 
     let mut decompressed = vec![];
@@ -224,6 +491,231 @@ fn rle_decompress(bytes: &[u8]) -> Vec<u8> {
     decompressed
 }
 
+/// Rle compress each row of a channel separately, the way PSDs store them.
+///
+/// Returns the compressed rows one after another along with the compressed length of each row.
+pub(crate) fn rle_compress_rows(samples: &[u8], row_len: usize) -> (Vec<u8>, Vec<u32>) {
+    let mut compressed = vec![];
+    let mut byte_counts = vec![];
+
+    if row_len == 0 {
+        return (compressed, byte_counts);
+    }
+
+    for row in samples.chunks(row_len) {
+        let row_start = compressed.len();
+        rle_compress_row(row, &mut compressed);
+        byte_counts.push((compressed.len() - row_start) as u32);
+    }
+
+    (compressed, byte_counts)
+}
+
+/// PackBits a row: runs of two to 128 repeated bytes are stored as a negative header followed by
+/// the byte, and up to 128 other bytes are stored as a header of their count minus one followed
+/// by the bytes.
+fn rle_compress_row(row: &[u8], compressed: &mut Vec<u8>) {
+    let mut idx = 0;
+
+    while idx < row.len() {
+        let run = row[idx..]
+            .iter()
+            .take(128)
+            .take_while(|byte| **byte == row[idx])
+            .count();
+
+        if run >= 2 {
+            compressed.push((1 - run as i16) as u8);
+            compressed.push(row[idx]);
+            idx += run;
+            continue;
+        }
+
+        // Stop the literal bytes right before the next run
+        let start = idx;
+        while idx < row.len() && idx - start < 128 {
+            if idx + 1 < row.len() && row[idx] == row[idx + 1] {
+                break;
+            }
+            idx += 1;
+        }
+
+        compressed.push((idx - start - 1) as u8);
+        compressed.extend_from_slice(&row[start..idx]);
+    }
+}
+
+/// Decode a channel one row at a time, calling `f` with the index of each row and its bytes.
+///
+/// Only one row is ever decoded into memory at a time, so this can be used to stream a channel
+/// into some other format without first decoding the entire channel.
+///
+/// When the compressed length of each row of an RLE compressed channel is known, each row is
+/// decoded on its own the same way as in `for_each_rle_sample`, so a corrupt row only loses its
+/// own samples and every row is passed to `f`. Otherwise a trailing row that has fewer than
+/// `row_len` bytes (truncated channel data) is not passed to `f`.
+///
+/// Only the rows in `rows_wanted` are passed to `f`. With known row lengths the other rows
+/// aren't decoded at all.
+pub(crate) fn for_each_channel_row<F: FnMut(usize, &[u8])>(
+    channel_bytes: &ChannelBytes,
+    byte_counts: Option<&[u32]>,
+    row_len: usize,
+    rows_wanted: Range<usize>,
+    mut f: F,
+) {
+    if row_len == 0 || rows_wanted.is_empty() {
+        return;
+    }
+
+    match channel_bytes {
+        ChannelBytes::RawData(channel_bytes) => {
+            let rows = channel_bytes.chunks_exact(row_len).enumerate();
+            for (row_idx, row) in rows.skip(rows_wanted.start).take(rows_wanted.len()) {
+                f(row_idx, row);
+            }
+        }
+        ChannelBytes::RleCompressed(channel_bytes) => {
+            let rows = byte_counts
+                .map(|byte_counts| RleRows {
+                    byte_counts,
+                    row_len,
+                })
+                .filter(|rows| rows.fits(channel_bytes));
+
+            let mut row = vec![0; row_len];
+            // Rows before `rows_wanted` are only decoded when we can't skip over them
+            let mut row_idx = if rows.is_some() { rows_wanted.start } else { 0 };
+            // The samples of a row that decodes to too few of them are left at 0
+            let mut next_row = |row: &mut Vec<u8>, row_idx: &mut usize| {
+                if rows_wanted.contains(row_idx) {
+                    f(*row_idx, row);
+                }
+                row.iter_mut().for_each(|sample| *sample = 0);
+                *row_idx += 1;
+            };
+
+            let mut sample_count = 0;
+            for_each_rle_sample(channel_bytes, rows, rows_wanted.clone(), |idx, byte| {
+                if idx / row_len >= rows_wanted.end {
+                    return;
+                }
+                while idx / row_len > row_idx {
+                    next_row(&mut row, &mut row_idx);
+                }
+                row[idx % row_len] = byte;
+                sample_count = idx + 1;
+            });
+
+            let row_count = match rows {
+                Some(rows) => rows.byte_counts.len(),
+                None => sample_count / row_len,
+            };
+            while row_idx < row_count.min(rows_wanted.end) {
+                next_row(&mut row, &mut row_idx);
+            }
+        }
+    }
+}
+
+/// Decompress zlib compressed channel data into raw big endian samples.
+///
+/// With prediction each row stores the difference between each sample and the sample to its
+/// left. 32 bit rows are also split into planes of the first byte of every sample, then the
+/// second byte, etc, and the difference is taken between bytes across the whole row.
+///
+/// `None` if the zlib data is malformed.
+pub(crate) fn zip_decompress(
+    bytes: &[u8],
+    compression: PsdChannelCompression,
+    row_width: usize,
+    depth: PsdDepth,
+) -> Option<Vec<u8>> {
+    let mut decompressed = miniz_oxide::inflate::decompress_to_vec_zlib(bytes).ok()?;
+
+    if compression != PsdChannelCompression::ZipWithPrediction || row_width == 0 {
+        return Some(decompressed);
+    }
+
+    match depth {
+        PsdDepth::One => {}
+        PsdDepth::Eight => {
+            for row in decompressed.chunks_exact_mut(row_width) {
+                for idx in 1..row.len() {
+                    row[idx] = row[idx].wrapping_add(row[idx - 1]);
+                }
+            }
+        }
+        PsdDepth::Sixteen => {
+            for row in decompressed.chunks_exact_mut(row_width * 2) {
+                let mut previous = 0u16;
+                for sample in row.chunks_exact_mut(2) {
+                    previous = previous.wrapping_add(u16::from_be_bytes([sample[0], sample[1]]));
+                    sample.copy_from_slice(&previous.to_be_bytes());
+                }
+            }
+        }
+        PsdDepth::ThirtyTwo => {
+            let mut planes = vec![0; row_width * 4];
+            for row in decompressed.chunks_exact_mut(row_width * 4) {
+                planes.copy_from_slice(row);
+                for idx in 1..planes.len() {
+                    planes[idx] = planes[idx].wrapping_add(planes[idx - 1]);
+                }
+
+                for (idx, sample) in row.chunks_exact_mut(4).enumerate() {
+                    for (byte_idx, byte) in sample.iter_mut().enumerate() {
+                        *byte = planes[byte_idx * row_width + idx];
+                    }
+                }
+            }
+        }
+    }
+
+    Some(decompressed)
+}
+
+/// Map raw big endian 16 or 32 bit samples down to one byte per sample.
+///
+/// 32 bit color samples are linear, so they're tonemapped into sRGB. Other 32 bit samples,
+/// such as transparency and masks, are clamped to 0.0 - 1.0.
+pub(crate) fn samples_to_eight_bits(samples: &[u8], depth: PsdDepth, is_color: bool) -> Vec<u8> {
+    match depth {
+        PsdDepth::One | PsdDepth::Eight => samples.to_vec(),
+        PsdDepth::Sixteen => samples
+            .chunks_exact(2)
+            .map(|sample| sample_to_eight_bits(sample, is_color))
+            .collect(),
+        PsdDepth::ThirtyTwo => samples
+            .chunks_exact(4)
+            .map(|sample| sample_to_eight_bits(sample, is_color))
+            .collect(),
+    }
+}
+
+/// Map one raw big endian sample of 1, 2 or 4 bytes down to 8 bits, see `samples_to_eight_bits`
+fn sample_to_eight_bits(sample: &[u8], is_color: bool) -> u8 {
+    match *sample {
+        [high, _] => high,
+        [a, b, c, d] => {
+            let sample = f32::from_be_bytes([a, b, c, d]);
+            if is_color {
+                hdr::tonemap(sample)
+            } else {
+                hdr::unit_to_u8(sample)
+            }
+        }
+        _ => sample[0],
+    }
+}
+
+/// Read raw big endian 32 bit float samples
+pub(crate) fn f32_samples(samples: &[u8]) -> impl Iterator<Item = f32> + '_ {
+    samples
+        .chunks_exact(4)
+        .map(|sample| f32::from_be_bytes([sample[0], sample[1], sample[2], sample[3]]))
+}
+
 /// Take two 8 bit channels that together represent a 16 bit channel and convert them down
 /// into an 8 bit channel.
 ///
@@ -267,16 +759,17 @@ fn sixteen_to_eight_rgba(channel1: &[u8], channel2: &[u8]) -> Vec<u8> {
 }
 
 /// Indicates how a channe'sl data is compressed
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
 #[allow(missing_docs)]
 pub enum PsdChannelCompression {
     /// Not compressed
     RawData = 0,
     /// Compressed using [PackBits RLE compression](https://en.wikipedia.org/wiki/PackBits)
     RleCompressed = 1,
-    /// Currently unsupported
+    /// Compressed using zlib
     ZipWithoutPrediction = 2,
-    /// Currently unsupported
+    /// Compressed using zlib after storing each sample as the difference from the sample to
+    /// its left
     ZipWithPrediction = 3,
 }
 
@@ -293,6 +786,22 @@ impl PsdChannelCompression {
     }
 }
 
+/// Convert interleaved CMY pixels into RGB pixels, in place, given the black value of each pixel
+/// in the first byte of every four.
+///
+/// This is the naive conversion that ignores any embedded ICC profile, so colors won't exactly
+/// match Photoshop's. Photoshop stores ink channels inverted (255 is no ink), so each color is
+/// the inverted ink multiplied by the inverted black.
+fn cmyk_to_rgb(rgba: &mut [u8], black_rgba: &[u8]) {
+    for (pixel, black) in rgba.chunks_exact_mut(4).zip(black_rgba.chunks_exact(4)) {
+        let black = black[0] as u16;
+
+        for color in pixel[..3].iter_mut() {
+            *color = ((*color as u16 * black + 127) / 255) as u8;
+        }
+    }
+}
+
 /// The different kinds of channels in a layer (red, green, blue, ...).
 #[derive(Debug, Hash, Eq, PartialEq, Ord, PartialOrd, Copy, Clone)]
 #[allow(missing_docs)]
@@ -300,18 +809,34 @@ pub enum PsdChannelKind {
     Red = 0,
     Green = 1,
     Blue = 2,
+    /// The fourth color channel of a CMYK layer. In CMYK layers `Red`, `Green` and `Blue` hold
+    /// the cyan, magenta and yellow channels.
+    Black = 3,
     TransparencyMask = -1,
     UserSuppliedLayerMask = -2,
     RealUserSuppliedLayerMask = -3,
 }
 
 /// Represents an invalid channel
-#[derive(Debug, Error)]
+///
+/// New variants may be added in any release, see [`PsdChannelError::code`].
+#[derive(Debug, Clone, Eq, PartialEq, Error)]
+#[allow(missing_docs)]
+#[non_exhaustive]
 pub enum PsdChannelError {
     #[error("Channel {channel:#?} not present")]
     ChannelNotFound { channel: PsdChannelKind },
 }
 
+impl PsdChannelError {
+    /// A stable code that identifies the kind of error, see [`crate::PsdError::code`].
+    pub fn code(&self) -> &'static str {
+        match self {
+            PsdChannelError::ChannelNotFound { .. } => "channel.not_found",
+        }
+    }
+}
+
 impl PsdChannelKind {
     /// Create a new PsdLayerChannel
     pub fn new(channel_id: i16) -> Option<PsdChannelKind> {
@@ -319,6 +844,7 @@ impl PsdChannelKind {
             0 => Some(PsdChannelKind::Red),
             1 => Some(PsdChannelKind::Green),
             2 => Some(PsdChannelKind::Blue),
+            3 => Some(PsdChannelKind::Black),
             -1 => Some(PsdChannelKind::TransparencyMask),
             -2 => Some(PsdChannelKind::UserSuppliedLayerMask),
             -3 => Some(PsdChannelKind::RealUserSuppliedLayerMask),
@@ -330,13 +856,70 @@ impl PsdChannelKind {
     /// G -> 1
     /// B -> 2
     /// A -> 3
+    #[deprecated(note = "Use `PsdChannelKind::rgba_channel`, which can't fail for mask channels")]
     pub fn rgba_offset(self) -> Result<usize, String> {
+        self.rgba_channel()
+            .map(RgbaChannel::offset)
+            .ok_or_else(|| format!("{:#?} is not an RGBA channel", &self))
+    }
+
+    /// The RGBA channel that this channel is interleaved into, or `None` for the user supplied
+    /// layer masks since they aren't part of a layer's RGBA and for the black channel since it's
+    /// mixed into all three colors.
+    pub fn rgba_channel(self) -> Option<RgbaChannel> {
         match self {
-            PsdChannelKind::Red => Ok(0),
-            PsdChannelKind::Green => Ok(1),
-            PsdChannelKind::Blue => Ok(2),
-            PsdChannelKind::TransparencyMask => Ok(3),
-            _ => Err(format!("{:#?} is not an RGBA channel", &self)),
+            PsdChannelKind::Red => Some(RgbaChannel::Red),
+            PsdChannelKind::Green => Some(RgbaChannel::Green),
+            PsdChannelKind::Blue => Some(RgbaChannel::Blue),
+            PsdChannelKind::TransparencyMask => Some(RgbaChannel::Alpha),
+            PsdChannelKind::Black
+            | PsdChannelKind::UserSuppliedLayerMask
+            | PsdChannelKind::RealUserSuppliedLayerMask => None,
+        }
+    }
+
+    /// Whether this is one of the user supplied layer masks rather than a color or
+    /// transparency channel
+    pub fn is_layer_mask(self) -> bool {
+        matches!(
+            self,
+            PsdChannelKind::UserSuppliedLayerMask | PsdChannelKind::RealUserSuppliedLayerMask
+        )
+    }
+
+    /// Whether this channel holds color, rather than transparency or a layer mask
+    pub(crate) fn is_color(self) -> bool {
+        self != PsdChannelKind::TransparencyMask && !self.is_layer_mask()
+    }
+}
+
+/// One of the four channels in RGBA pixels.
+///
+/// Unlike [`PsdChannelKind`] this can't be a layer mask, so code that places channels into RGBA
+/// pixels can't be handed a mask by mistake.
+#[derive(Debug, Hash, Eq, PartialEq, Ord, PartialOrd, Copy, Clone)]
+#[allow(missing_docs)]
+pub enum RgbaChannel {
+    Red = 0,
+    Green = 1,
+    Blue = 2,
+    Alpha = 3,
+}
+
+impl RgbaChannel {
+    /// The index of this channel within each 4 byte RGBA pixel
+    pub fn offset(self) -> usize {
+        self as usize
+    }
+}
+
+impl From<RgbaChannel> for PsdChannelKind {
+    fn from(channel: RgbaChannel) -> Self {
+        match channel {
+            RgbaChannel::Red => PsdChannelKind::Red,
+            RgbaChannel::Green => PsdChannelKind::Green,
+            RgbaChannel::Blue => PsdChannelKind::Blue,
+            RgbaChannel::Alpha => PsdChannelKind::TransparencyMask,
         }
     }
 }
@@ -344,9 +927,9 @@ impl PsdChannelKind {
 #[cfg(test)]
 mod tests {
     use crate::sections::layer_and_mask_information_section::layer::{
-        BlendMode, LayerChannels, LayerProperties,
+        BlendMode, LayerChannels, LayerFlags, LayerProperties,
     };
-    use crate::PsdLayer;
+    use crate::{PsdDepth, PsdLayer};
 
     use super::*;
 
@@ -360,27 +943,186 @@ mod tests {
             layer_left: 0,
             layer_bottom: 0,
             layer_right: 0,
-            visible: true,
+            flags: LayerFlags::new(1 << 1),
             opacity: 0,
             clipping_mask: false,
             psd_width: 1,
             psd_height: 1,
             blend_mode: BlendMode::Normal,
             group_id: None,
+            layer_id: None,
+            layer_mask: None,
+            record_data: Default::default(),
         };
 
         let layer = PsdLayer {
             channels: LayerChannels::from([(
                 PsdChannelKind::Red,
-                ChannelBytes::RleCompressed(vec![0, 0, 0]),
+                ChannelBytes::RleCompressed(vec![0, 0, 0].into()),
             )]),
+            scanline_byte_counts: Default::default(),
+            zip_compressed_channels: Default::default(),
+            depth: PsdDepth::Eight,
             layer_properties,
+            text_layer_info: None,
+            placed_layer: None,
+            rgba_cache: Default::default(),
         };
 
         let mut rgba = vec![0; (layer.width() * layer.height() * 4) as usize];
 
-        layer.insert_channel_bytes(&mut rgba, PsdChannelKind::Red, layer.red());
+        let psd = CropRect::new(0, 0, 1, 1);
+        layer.insert_channel_bytes(&mut rgba, psd, RgbaChannel::Red, layer.red());
 
         assert_eq!(rgba, vec![0; 4]);
     }
+
+    /// Verify that each row of an RLE channel is decoded on its own, so that a row that decodes
+    /// to too many samples doesn't shift the rows below it.
+    #[test]
+    fn corrupt_rle_row_does_not_shift_rows_below() {
+        let layer_properties = LayerProperties {
+            name: "".into(),
+            layer_top: 0,
+            layer_left: 0,
+            layer_bottom: 3,
+            layer_right: 2,
+            flags: LayerFlags::new(1 << 1),
+            opacity: 255,
+            clipping_mask: false,
+            psd_width: 2,
+            psd_height: 3,
+            blend_mode: BlendMode::Normal,
+            group_id: None,
+            layer_id: None,
+            layer_mask: None,
+            record_data: Default::default(),
+        };
+
+        // The first row holds three literal bytes instead of two, the other rows repeat a byte
+        let rows = [vec![2, 10, 11, 12], vec![255, 20], vec![255, 30]];
+        let layer = PsdLayer {
+            channels: LayerChannels::from([(
+                PsdChannelKind::Red,
+                ChannelBytes::RleCompressed(rows.concat().into()),
+            )]),
+            scanline_byte_counts: [(PsdChannelKind::Red, vec![4, 2, 2])].into(),
+            zip_compressed_channels: Default::default(),
+            depth: PsdDepth::Eight,
+            layer_properties,
+            text_layer_info: None,
+            placed_layer: None,
+            rgba_cache: Default::default(),
+        };
+
+        let red: Vec<u8> = layer.rgba().chunks_exact(4).map(|pixel| pixel[0]).collect();
+        assert_eq!(red, [10, 11, 20, 20, 30, 30]);
+    }
+
+    /// Verify that the rows of an RLE channel outside of the rows that we want are skipped
+    /// without being decoded.
+    #[test]
+    fn skips_rle_rows_outside_of_region() {
+        // Three rows of two samples: a literal run, a corrupt row and a repeated run
+        let channel = [1, 10, 11, 5, 2, 255, 30];
+        let byte_counts = [3, 2, 2];
+        let rows = RleRows::new(&byte_counts, 2, PsdDepth::Eight);
+
+        let mut samples = vec![];
+        for_each_rle_sample(&channel, rows, 2..3, |idx, byte| samples.push((idx, byte)));
+
+        assert_eq!(samples, [(4, 30), (5, 30)]);
+    }
+
+    /// Verify that only the rows that we want are passed along when streaming a channel's rows,
+    /// with or without the compressed length of each row.
+    #[test]
+    fn streams_only_the_rows_wanted() {
+        let channel = ChannelBytes::RleCompressed(vec![1, 10, 11, 255, 20, 255, 30].into());
+
+        for byte_counts in [Some(&[3, 2, 2][..]), None].iter() {
+            let mut rows = vec![];
+            for_each_channel_row(&channel, *byte_counts, 2, 1..2, |row_idx, row| {
+                rows.push((row_idx, row.to_vec()))
+            });
+
+            assert_eq!(rows, [(1, vec![20, 20])], "{:?}", byte_counts);
+        }
+    }
+
+    /// Verify that every RGBA channel converts to and from a layer channel and that masks
+    /// aren't RGBA channels.
+    #[test]
+    fn rgba_channels() {
+        for channel in [
+            RgbaChannel::Red,
+            RgbaChannel::Green,
+            RgbaChannel::Blue,
+            RgbaChannel::Alpha,
+        ]
+        .iter()
+        {
+            assert_eq!(
+                PsdChannelKind::from(*channel).rgba_channel(),
+                Some(*channel)
+            );
+        }
+
+        assert_eq!(RgbaChannel::Alpha.offset(), 3);
+        assert!(PsdChannelKind::UserSuppliedLayerMask.is_layer_mask());
+        assert!(PsdChannelKind::RealUserSuppliedLayerMask.is_layer_mask());
+        assert!(!PsdChannelKind::TransparencyMask.is_layer_mask());
+        assert!(!PsdChannelKind::Black.is_layer_mask());
+        assert_eq!(PsdChannelKind::Black.rgba_channel(), None);
+    }
+
+    /// Verify that 16 bit prediction adds up whole samples rather than bytes, so that carries
+    /// from the low byte reach the high byte.
+    #[test]
+    fn zip_decompress_16_bit_prediction() {
+        // Two rows of two samples: [0x00FF, 0x0100] and [0xFFFF, 0x0000]
+        let predicted = [0x00, 0xFF, 0x00, 0x01, 0xFF, 0xFF, 0x00, 0x01];
+        let compressed = miniz_oxide::deflate::compress_to_vec_zlib(&predicted, 6);
+
+        let decompressed = zip_decompress(
+            &compressed,
+            PsdChannelCompression::ZipWithPrediction,
+            2,
+            PsdDepth::Sixteen,
+        )
+        .unwrap();
+
+        assert_eq!(
+            decompressed,
+            [0x00, 0xFF, 0x01, 0x00, 0xFF, 0xFF, 0x00, 0x00]
+        );
+    }
+
+    /// Verify that malformed zlib data is reported rather than panicking.
+    #[test]
+    fn zip_decompress_invalid_data() {
+        assert!(zip_decompress(
+            &[1, 2, 3],
+            PsdChannelCompression::ZipWithoutPrediction,
+            1,
+            PsdDepth::Eight,
+        )
+        .is_none());
+    }
+
+    /// Verify that rle compressed rows decompress back into the same bytes, including runs and
+    /// literals longer than a single PackBits header can hold.
+    #[test]
+    fn rle_compress_round_trip() {
+        let mut row = vec![1, 2, 3, 3, 3, 4, 5, 5, 6];
+        row.extend(std::iter::repeat_n(7, 300));
+        row.extend((0..=255).map(|byte: u8| byte.wrapping_mul(3)));
+        let samples = [row.clone(), row.clone()].concat();
+
+        let (compressed, byte_counts) = rle_compress_rows(&samples, row.len());
+
+        assert_eq!(byte_counts.len(), 2);
+        assert_eq!(byte_counts.iter().sum::<u32>() as usize, compressed.len());
+        assert_eq!(rle_decompress(&compressed), samples);
+    }
 }
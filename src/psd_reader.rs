@@ -0,0 +1,312 @@
+//! Reading the parts of a PSD that you ask for from a file, or from anything else that can be
+//! read and seeked, without holding the whole file in memory.
+
+use std::io::{self, Read, Seek, SeekFrom};
+
+use crate::sections::file_header_section::FileHeaderSection;
+use crate::sections::image_resources_section::ImageResourcesSection;
+use crate::sections::layer_and_mask_information_section::tagged_block::{
+    TaggedBlockContext, TaggedBlockHeader,
+};
+use crate::sections::layer_and_mask_information_section::{layer_names, KEYS_LAYER_INFO_BLOCK};
+use crate::sections::{PsdCursor, FILE_HEADER_SECTION_LEN};
+use crate::{
+    ColorMode, FileHeaderSectionError, ImageResource, Psd, PsdDepth, PsdError, PsdVersion,
+};
+
+/// The length of a layer record's rectangle and channel count
+const RECORD_START_LEN: u64 = 18;
+
+/// The length of a layer record's blend mode signature and key, opacity, clipping, flags and
+/// filler, along with the length of its extra data
+const RECORD_MIDDLE_LEN: u64 = 16;
+
+/// Reads a PSD a section at a time, see [`Psd::from_reader`].
+///
+/// Only the file header is read up front, along with the length markers that say where each
+/// section starts. The image resources and the layer records are read when you ask for them, and
+/// the layers' channels and the final image are only read by [`PsdReader::into_psd`].
+///
+/// ```no_run
+/// # use psd::Psd;
+/// let file = std::fs::File::open("large.psb").unwrap();
+/// let mut reader = Psd::from_reader(std::io::BufReader::new(file)).unwrap();
+///
+/// println!("{:?}", reader.dimensions());
+/// for name in reader.layer_names().unwrap() {
+///     println!("{}", name);
+/// }
+/// ```
+///
+/// [`Psd::from_reader`]: crate::Psd::from_reader
+pub struct PsdReader<R> {
+    reader: R,
+    file_header_section: FileHeaderSection,
+    /// The (start, end) of the image resources section, including its length marker
+    image_resources: (u64, u64),
+    /// The (start, end) of the layer and mask information section, including its length marker
+    layer_and_mask: (u64, u64),
+}
+
+impl<R: Read + Seek> PsdReader<R> {
+    /// Read the file header and find where each section starts.
+    pub(crate) fn new(mut reader: R) -> Result<PsdReader<R>, PsdError> {
+        let file_len = reader.seek(SeekFrom::End(0)).map_err(read_error)?;
+        if file_len < FILE_HEADER_SECTION_LEN as u64 {
+            return Err(PsdError::HeaderError(
+                FileHeaderSectionError::IncorrectLength {
+                    length: file_len as usize,
+                },
+            ));
+        }
+
+        let mut file_header = [0; FILE_HEADER_SECTION_LEN];
+        reader.seek(SeekFrom::Start(0)).map_err(read_error)?;
+        reader.read_exact(&mut file_header).map_err(read_error)?;
+        let file_header_section =
+            FileHeaderSection::from_bytes(&file_header).map_err(PsdError::HeaderError)?;
+
+        let mut psd_reader = PsdReader {
+            reader,
+            file_header_section,
+            image_resources: (0, 0),
+            layer_and_mask: (0, 0),
+        };
+
+        let version = psd_reader.file_header_section.version;
+        let color_mode_data = psd_reader.section(
+            FILE_HEADER_SECTION_LEN as u64,
+            PsdVersion::One,
+            file_len,
+            "color mode data",
+        )?;
+        psd_reader.image_resources = psd_reader.section(
+            color_mode_data.1,
+            PsdVersion::One,
+            file_len,
+            "image resources",
+        )?;
+        psd_reader.layer_and_mask = psd_reader.section(
+            psd_reader.image_resources.1,
+            version,
+            file_len,
+            "layer and mask information",
+        )?;
+
+        Ok(psd_reader)
+    }
+
+    /// The (width, height) of the PSD
+    pub fn dimensions(&self) -> (u32, u32) {
+        let header = &self.file_header_section;
+        (header.width.0, header.height.0)
+    }
+
+    /// The depth of the PSD
+    pub fn depth(&self) -> PsdDepth {
+        self.file_header_section.depth
+    }
+
+    /// The color mode of the PSD
+    pub fn color_mode(&self) -> ColorMode {
+        self.file_header_section.color_mode
+    }
+
+    /// Whether the file is a PSD ([`PsdVersion::One`]) or a PSB ([`PsdVersion::Two`])
+    pub fn version(&self) -> PsdVersion {
+        self.file_header_section.version
+    }
+
+    /// Read the image resources section. See [`Psd::resources`].
+    ///
+    /// [`Psd::resources`]: crate::Psd::resources
+    pub fn resources(&mut self) -> Result<Vec<ImageResource>, PsdError> {
+        let (start, end) = self.image_resources;
+        let bytes = self.read_at(start, end - start)?;
+
+        let section = ImageResourcesSection::from_bytes(&bytes).map_err(PsdError::ResourceError)?;
+        Ok(section.resources)
+    }
+
+    /// Read the names of the layers, in the same order as [`Psd::layers`], without reading any
+    /// of their channels.
+    ///
+    /// [`Psd::layers`]: crate::Psd::layers
+    pub fn layer_names(&mut self) -> Result<Vec<String>, PsdError> {
+        let version = self.version();
+        let (start, end) = self.layer_and_mask;
+        let length_marker_len = length_marker_len(version);
+        if end - start <= length_marker_len {
+            return Ok(vec![]);
+        }
+
+        self.seek(start + length_marker_len)?;
+        let layer_info_len = self.read_length(version)?;
+        let layer_info_start = start + length_marker_len * 2;
+
+        // 16 and 32 bit documents, and the documents of some other writers, store their layer
+        // info in a global tagged block, leaving the layer info at the start of the section empty
+        let records_end = if layer_info_len > 0 {
+            layer_info_start.saturating_add(layer_info_len).min(end)
+        } else {
+            match self.find_layer_info_block(layer_info_start, end)? {
+                Some((data_start, data_end)) => {
+                    self.seek(data_start)?;
+                    data_end
+                }
+                None => return Ok(vec![]),
+            }
+        };
+
+        let layer_count = i16::from_be_bytes(self.read_array(records_end)?).unsigned_abs() as usize;
+
+        // Every record is read into memory, but the channels that follow them aren't
+        let mut records = vec![];
+        for _ in 0..layer_count {
+            let start = self.read_vec(RECORD_START_LEN, records_end)?;
+            let channel_count = u16::from_be_bytes([start[16], start[17]]) as u64;
+            records.extend_from_slice(&start);
+
+            // Each channel's ID is followed by its length
+            let channel_info_len = channel_count * (2 + length_marker_len);
+            records.extend_from_slice(&self.read_vec(channel_info_len, records_end)?);
+
+            let middle = self.read_vec(RECORD_MIDDLE_LEN, records_end)?;
+            let extra_data_len =
+                u32::from_be_bytes([middle[12], middle[13], middle[14], middle[15]]);
+            records.extend_from_slice(&middle);
+            records.extend_from_slice(&self.read_vec(extra_data_len as u64, records_end)?);
+        }
+
+        layer_names(&records, layer_count, version).map_err(PsdError::LayerError)
+    }
+
+    /// Read the rest of the file and parse it the same way as [`Psd::from_bytes`].
+    ///
+    /// [`Psd::from_bytes`]: crate::Psd::from_bytes
+    pub fn into_psd(mut self) -> Result<Psd, PsdError> {
+        let mut bytes = vec![];
+        self.seek(0)?;
+        self.reader.read_to_end(&mut bytes).map_err(read_error)?;
+
+        Psd::from_bytes(&bytes)
+    }
+
+    /// The (start, end) of the major section at `start`, including its length marker
+    fn section(
+        &mut self,
+        start: u64,
+        version: PsdVersion,
+        file_len: u64,
+        section: &'static str,
+    ) -> Result<(u64, u64), PsdError> {
+        let truncated = PsdError::HeaderError(FileHeaderSectionError::TruncatedSection { section });
+        if start + length_marker_len(version) > file_len {
+            return Err(truncated);
+        }
+
+        self.seek(start)?;
+        let len = self.read_length(version)?;
+        let end = (start + length_marker_len(version))
+            .checked_add(len)
+            .filter(|end| *end <= file_len)
+            .ok_or(truncated)?;
+
+        Ok((start, end))
+    }
+
+    /// The (start, end) of the data of the `Lr16`, `Lr32` or `Layr` block among the global tagged blocks,
+    /// which come after the global layer mask info at `position`
+    fn find_layer_info_block(
+        &mut self,
+        position: u64,
+        end: u64,
+    ) -> Result<Option<(u64, u64)>, PsdError> {
+        if position + 4 > end {
+            return Ok(None);
+        }
+        self.seek(position)?;
+        let global_layer_mask_info_len = u32::from_be_bytes(self.read_array(end)?) as u64;
+        let mut position = position + 4 + global_layer_mask_info_len;
+
+        let version = self.version();
+        while position < end {
+            // The signature, the key and a length that is 8 bytes long for some keys in PSBs
+            let header_len = 16.min(end - position);
+            self.seek(position)?;
+            let header_bytes = self.read_vec(header_len, end)?;
+
+            let mut cursor = PsdCursor::new(&header_bytes);
+            let header = match TaggedBlockHeader::read(&mut cursor, version, end - position) {
+                Some(header) => header,
+                None => return Ok(None),
+            };
+
+            let data_start = position + header.data_start;
+            if KEYS_LAYER_INFO_BLOCK.contains(&&header.key) {
+                return Ok(Some((data_start, data_start + header.data_len)));
+            }
+
+            position += header.end(TaggedBlockContext::Document);
+        }
+
+        Ok(None)
+    }
+
+    fn seek(&mut self, position: u64) -> Result<(), PsdError> {
+        self.reader
+            .seek(SeekFrom::Start(position))
+            .map(|_| ())
+            .map_err(read_error)
+    }
+
+    fn read_at(&mut self, position: u64, len: u64) -> Result<Vec<u8>, PsdError> {
+        self.seek(position)?;
+        self.read_vec(len, position + len)
+    }
+
+    /// Read `len` bytes, making sure that they don't run past `end` so that a corrupt length
+    /// can't make us allocate more than the section holds.
+    fn read_vec(&mut self, len: u64, end: u64) -> Result<Vec<u8>, PsdError> {
+        let position = self.reader.stream_position().map_err(read_error)?;
+        if position.saturating_add(len) > end {
+            return Err(PsdError::HeaderError(
+                FileHeaderSectionError::TruncatedSection {
+                    section: "layer and mask information",
+                },
+            ));
+        }
+
+        let mut bytes = vec![0; len as usize];
+        self.reader.read_exact(&mut bytes).map_err(read_error)?;
+        Ok(bytes)
+    }
+
+    fn read_array<const N: usize>(&mut self, end: u64) -> Result<[u8; N], PsdError> {
+        let mut bytes = [0; N];
+        bytes.copy_from_slice(&self.read_vec(N as u64, end)?);
+        Ok(bytes)
+    }
+
+    /// Read a length marker, which is 8 bytes long in PSB files
+    fn read_length(&mut self, version: PsdVersion) -> Result<u64, PsdError> {
+        match version {
+            PsdVersion::One => Ok(u32::from_be_bytes(self.read_array(u64::MAX)?) as u64),
+            PsdVersion::Two => Ok(u64::from_be_bytes(self.read_array(u64::MAX)?)),
+        }
+    }
+}
+
+fn length_marker_len(version: PsdVersion) -> u64 {
+    match version {
+        PsdVersion::One => 4,
+        PsdVersion::Two => 8,
+    }
+}
+
+fn read_error(err: io::Error) -> PsdError {
+    PsdError::ReadError {
+        kind: err.kind(),
+        message: err.to_string(),
+    }
+}
@@ -1,62 +1,444 @@
+#[cfg(feature = "unstable")]
+use crate::adjustments::AdjustmentLayer;
 use crate::blend;
+use crate::color_stats::AlphaSummary;
+#[cfg(feature = "unstable")]
+use crate::effects;
+use crate::exif_orientation::ExifOrientation;
+#[cfg(not(feature = "unstable"))]
+use crate::fidelity::ADJUSTMENT_KEYS;
+#[cfg(feature = "unstable")]
+use crate::fills;
+use crate::layer_tree::{layer_tree, node_tree, LayerTreeNode, PsdNode};
+use crate::render_plan::{self, RenderPlan};
+use crate::rgba_layout::RgbaLayout;
+#[cfg(feature = "unstable")]
+use crate::sections::layer_and_mask_information_section::adjustment::Adjustment;
+use crate::sections::layer_and_mask_information_section::blending_ranges::BlendingRanges;
 use crate::sections::layer_and_mask_information_section::layer::BlendMode;
-use crate::PsdLayer;
-use std::cell::RefCell;
+use crate::ycbcr::rgb_to_ycbcr;
+use crate::{Psd, PsdDepth, PsdError, PsdGroup, PsdLayer};
+use std::cell::{Cell, Ref, RefCell};
 use std::iter::repeat_with;
+use std::rc::Rc;
+
+/// For every row of the PSD, a run of pixels `(left, right)`, inclusive, or None.
+type RowSpans = Vec<Option<(usize, usize)>>;
+
+/// The pixels and alpha summary of each layer, see `RenderJob::decode_layers`
+#[cfg(feature = "rayon")]
+type DecodedLayers = (Vec<Option<Rc<[u8]>>>, Vec<Option<AlphaSummary>>);
+
+/// The width and height of the squares of the checkerboard that
+/// `RenderPipeline::unsupported_placeholder` draws
+const PLACEHOLDER_SQUARE_LEN: usize = 8;
+
+/// A layer or group to composite, in the same order as the layers in the PSD (top to bottom).
+///
+/// Built by `render_nodes`.
+#[derive(Debug)]
+pub(crate) enum RenderNode {
+    /// The index of a layer in `Renderer::layers_to_flatten_top_down`
+    Layer(usize),
+    /// A group whose children get composited on their own and then blended with the pixels below
+    /// the group.
+    ///
+    /// Pass through groups instead composite their children directly onto the pixels below them
+    /// and then fade the result by their opacity. Pass through groups that are fully opaque are
+    /// the same as not having a group, so `render_nodes` replaces them with their children.
+    Group {
+        /// The group's ID, see `PsdGroup::id`
+        id: u32,
+        blend_mode: BlendMode,
+        opacity: u8,
+        children: Vec<RenderNode>,
+    },
+}
+
+/// A pixel that `Renderer::composite` has yet to blend with the pixels below it
+enum PendingBlend<'a> {
+    Pixel(blend::Pixel, BlendMode),
+    /// A layer's pixel that its blending ranges fade depending on the pixel below it, along with
+    /// the index of the layer in `Renderer::layers_to_flatten_top_down`
+    BlendIf(blend::Pixel, BlendMode, usize),
+    PassThrough {
+        opacity: u8,
+        children: &'a [RenderNode],
+    },
+    /// The index of an adjustment layer in `Renderer::layers_to_flatten_top_down`, whose
+    /// adjustment gets applied to the pixel below it
+    #[cfg(feature = "unstable")]
+    Adjustment(usize),
+}
 
 pub(crate) struct Renderer<'a> {
-    layers_to_flatten_top_down: &'a [&'a PsdLayer],
-    cached_layer_rgba: Vec<RefCell<Option<Vec<u8>>>>,
+    psd: &'a Psd,
+    /// The layers to flatten along with their index in `Psd::layers`
+    layers_to_flatten_top_down: &'a [(usize, &'a PsdLayer)],
+    /// The layers to flatten, nested in the groups that they get composited in
+    nodes: &'a [RenderNode],
+    layer_cache: &'a LayerRgbaCache,
+    /// The pixels of the layers that are rendered from their settings instead of being decoded,
+    /// which can reach anywhere on the PSD: fill layers and layers that are rendered with their
+    /// layer effects, see `RenderPipeline::layer_effects`
+    synthesized_layer_rgba: Vec<Option<&'a [u8]>>,
+    /// The pixels of the layers that were decoded before rendering, with their masks and opacity
+    /// applied, see `RenderJob::decode_layers`
+    decoded_layer_rgba: Vec<Option<&'a [u8]>>,
+    /// The adjustment layers that get applied to the pixels below them instead of being
+    /// blended, see `RenderPipeline::adjustment_layers`. Empty when none of them are applied.
+    #[cfg(feature = "unstable")]
+    adjustment_layers: &'a [Option<AdjustmentLayer>],
+    /// The "Blend If" sliders of the layers that hide some of their pixels, see
+    /// `RenderPipeline::blending_ranges`
+    blending_ranges: Vec<Option<BlendingRanges>>,
+    /// Whether each layer is drawn as a placeholder, which is always blended normally, see
+    /// `RenderPipeline::unsupported_placeholder`
+    placeholders: Vec<bool>,
+    cached_layer_rgba: Vec<RefCell<Option<Rc<[u8]>>>>,
+    /// The lowest and highest alpha of each layer, see `Renderer::decoded_alpha_summary`
+    cached_alpha_summaries: Vec<Cell<Option<AlphaSummary>>>,
+    /// For every row of the PSD, the longest run of pixels `(left, right)`, inclusive, that
+    /// a layer fully covers, see `Renderer::opaque_span`.
+    cached_opaque_spans: Vec<RefCell<Option<RowSpans>>>,
+    /// For every row of the PSD, whether or not each layer isn't hidden by an opaque layer above
+    /// it, see `Renderer::visible_layers_in_row`.
+    cached_row_layers: RefCell<Vec<Option<Vec<bool>>>>,
     width: usize,
-    pixel_cache: RefCell<Vec<(blend::Pixel, BlendMode)>>,
+    height: usize,
+    /// Decides how blend modes clamp, see `blend::blend_pixels_at_depth`
+    depth: PsdDepth,
+    pixel_cache: RefCell<Vec<PendingBlend<'a>>>,
 }
 
 impl<'a> Renderer<'a> {
     pub(crate) fn new(
-        layers_to_flatten_top_down: &'a [&'a PsdLayer],
-        width: usize,
+        psd: &'a Psd,
+        layers_to_flatten_top_down: &'a [(usize, &'a PsdLayer)],
+        nodes: &'a [RenderNode],
+        layer_cache: &'a LayerRgbaCache,
+        synthesized_layer_rgba: Vec<Option<&'a [u8]>>,
     ) -> Renderer<'a> {
+        let (width, height) = (psd.width() as usize, psd.height() as usize);
+
         Renderer {
+            psd,
             layers_to_flatten_top_down: layers_to_flatten_top_down,
+            nodes,
+            layer_cache,
+            synthesized_layer_rgba,
+            decoded_layer_rgba: vec![None; layers_to_flatten_top_down.len()],
+            #[cfg(feature = "unstable")]
+            adjustment_layers: &[],
+            blending_ranges: vec![None; layers_to_flatten_top_down.len()],
+            placeholders: vec![false; layers_to_flatten_top_down.len()],
             cached_layer_rgba: repeat_with(|| RefCell::new(None))
                 .take(layers_to_flatten_top_down.len())
                 .collect(),
+            cached_alpha_summaries: repeat_with(|| Cell::new(None))
+                .take(layers_to_flatten_top_down.len())
+                .collect(),
+            cached_opaque_spans: repeat_with(|| RefCell::new(None))
+                .take(layers_to_flatten_top_down.len())
+                .collect(),
+            cached_row_layers: RefCell::new(vec![None; height]),
             width: width,
+            height,
+            depth: psd.depth(),
             pixel_cache: RefCell::new(Vec::with_capacity(layers_to_flatten_top_down.len())),
         }
     }
 
+    /// Apply the adjustments of adjustment layers to the pixels below them, with one entry per
+    /// layer in `layers_to_flatten_top_down`.
+    #[cfg(feature = "unstable")]
+    pub(crate) fn with_adjustment_layers(
+        mut self,
+        adjustment_layers: &'a [Option<AdjustmentLayer>],
+    ) -> Renderer<'a> {
+        self.adjustment_layers = adjustment_layers;
+        self
+    }
+
+    /// Fade the pixels of layers by their blending ranges, with one entry per layer in
+    /// `layers_to_flatten_top_down`.
+    pub(crate) fn with_blending_ranges(
+        mut self,
+        blending_ranges: Vec<Option<BlendingRanges>>,
+    ) -> Renderer<'a> {
+        self.blending_ranges = blending_ranges;
+        self
+    }
+
+    /// Blend the layers that are drawn as placeholders normally, with one entry per layer in
+    /// `layers_to_flatten_top_down`.
+    pub(crate) fn with_placeholders(mut self, placeholders: Vec<bool>) -> Renderer<'a> {
+        self.placeholders = placeholders;
+        self
+    }
+
+    /// Look up the pixels of layers in `decoded_layer_rgba` instead of decoding them, with one
+    /// entry per layer in `layers_to_flatten_top_down`.
+    #[cfg(feature = "rayon")]
+    pub(crate) fn with_decoded_layer_rgba(
+        mut self,
+        decoded_layer_rgba: Vec<Option<&'a [u8]>>,
+    ) -> Renderer<'a> {
+        self.decoded_layer_rgba = decoded_layer_rgba;
+        self
+    }
+
+    /// Start from alpha summaries that were already computed, with one entry per layer in
+    /// `layers_to_flatten_top_down`.
+    #[cfg(feature = "rayon")]
+    pub(crate) fn with_alpha_summaries(
+        self,
+        alpha_summaries: &[Option<AlphaSummary>],
+    ) -> Renderer<'a> {
+        for (cached, summary) in self.cached_alpha_summaries.iter().zip(alpha_summaries) {
+            cached.set(*summary);
+        }
+        self
+    }
+
+    #[cfg(feature = "unstable")]
+    fn is_adjustment_layer(&self, flattened_layer_top_down_idx: usize) -> bool {
+        matches!(
+            self.adjustment_layers.get(flattened_layer_top_down_idx),
+            Some(Some(_))
+        )
+    }
+
+    #[cfg(not(feature = "unstable"))]
+    fn is_adjustment_layer(&self, _flattened_layer_top_down_idx: usize) -> bool {
+        false
+    }
+
+    /// The lowest and highest alpha of a layer's decoded pixels, before its masks and opacity are
+    /// applied. `None` for fill layers, layers that are rendered with their effects and
+    /// adjustment layers, whose pixels don't come from their channels.
+    fn decoded_alpha_summary(&self, flattened_layer_top_down_idx: usize) -> Option<AlphaSummary> {
+        if self.synthesized_layer_rgba[flattened_layer_top_down_idx].is_some()
+            || self.is_adjustment_layer(flattened_layer_top_down_idx)
+        {
+            return None;
+        }
+
+        let cached = &self.cached_alpha_summaries[flattened_layer_top_down_idx];
+        if cached.get().is_none() {
+            let (_, layer) = self.layers_to_flatten_top_down[flattened_layer_top_down_idx];
+            cached.set(Some(layer.alpha_summary()));
+        }
+
+        cached.get()
+    }
+
+    /// Call `f` with the pixels of a layer, decoding them the first time that they're needed.
+    fn with_layer_rgba<R>(
+        &self,
+        flattened_layer_top_down_idx: usize,
+        f: impl FnOnce(&[u8]) -> R,
+    ) -> R {
+        let known_pixels = self.synthesized_layer_rgba[flattened_layer_top_down_idx]
+            .or(self.decoded_layer_rgba[flattened_layer_top_down_idx]);
+        if let Some(pixels) = known_pixels {
+            return f(pixels);
+        }
+
+        let mut cached_layer_rgba =
+            self.cached_layer_rgba[flattened_layer_top_down_idx].borrow_mut();
+        if cached_layer_rgba.is_none() {
+            let (layer_idx, _) = self.layers_to_flatten_top_down[flattened_layer_top_down_idx];
+            *cached_layer_rgba = self.layer_cache.layer_rgba(self.psd, layer_idx);
+        }
+
+        f(cached_layer_rgba.as_deref().unwrap())
+    }
+
     fn pixel_rgba_for_layer(
         &'a self,
         flattened_layer_top_down_idx: usize,
         pixel_coord: (usize, usize),
     ) -> blend::Pixel {
-        let layer = self.layers_to_flatten_top_down[flattened_layer_top_down_idx];
+        let (pixel_left, pixel_top) = pixel_coord;
+        let pixel_idx = ((self.width * pixel_top) + pixel_left) * 4;
 
-        // If we haven't already calculated the RGBA for this layer, calculate and cache it
-        if self.cached_layer_rgba[flattened_layer_top_down_idx]
+        let (start, end) = (pixel_idx, pixel_idx + 4);
+
+        let mut copy = [0; 4];
+        self.with_layer_rgba(flattened_layer_top_down_idx, |layer_rgba| {
+            copy.copy_from_slice(&layer_rgba[start..end])
+        });
+        copy
+    }
+
+    /// The (top, left, bottom, right) of the layer, inclusive. Fill layers, layers that are
+    /// rendered with their effects and adjustment layers can cover the whole PSD.
+    fn layer_bounds(&self, flattened_layer_top_down_idx: usize) -> (i32, i32, i32, i32) {
+        if self.synthesized_layer_rgba[flattened_layer_top_down_idx].is_some()
+            || self.is_adjustment_layer(flattened_layer_top_down_idx)
+        {
+            return (0, 0, self.height as i32 - 1, self.width as i32 - 1);
+        }
+
+        let (_, layer) = self.layers_to_flatten_top_down[flattened_layer_top_down_idx];
+        let properties = &layer.layer_properties;
+
+        (
+            properties.layer_top,
+            properties.layer_left,
+            properties.layer_bottom,
+            properties.layer_right,
+        )
+    }
+
+    /// The columns of the layer that are within the PSD, (left, right) inclusive.
+    ///
+    /// None if the layer doesn't cover any pixels on this row.
+    fn layer_extent_in_row(
+        &self,
+        flattened_layer_top_down_idx: usize,
+        row: usize,
+    ) -> Option<(usize, usize)> {
+        let (top, left, bottom, right) = self.layer_bounds(flattened_layer_top_down_idx);
+        if (row as i32) < top || (row as i32) > bottom {
+            return None;
+        }
+
+        let left = left.max(0);
+        let right = right.min(self.width as i32 - 1);
+        if left > right {
+            return None;
+        }
+
+        Some((left as usize, right as usize))
+    }
+
+    /// The longest run of pixels in a row, (left, right) inclusive, where the layer hides
+    /// everything below it.
+    ///
+    /// Only fully opaque pixels of a fully opaque layer with the normal blend mode hide the
+    /// pixels below them, every other blend mode mixes with the pixels below.
+    fn opaque_span(
+        &self,
+        flattened_layer_top_down_idx: usize,
+        row: usize,
+    ) -> Option<(usize, usize)> {
+        let (_, layer) = self.layers_to_flatten_top_down[flattened_layer_top_down_idx];
+        if layer.opacity != 255
+            || layer.fill_opacity() != 255
+            || layer.blend_mode != BlendMode::Normal
+            || self.is_adjustment_layer(flattened_layer_top_down_idx)
+            || self.blending_ranges[flattened_layer_top_down_idx].is_some()
+        {
+            return None;
+        }
+
+        // A fully opaque layer without a mask covers every pixel that it has, so we don't need
+        // to decode it to find its spans
+        let has_mask = layer.mask().is_some_and(|mask| !mask.flags().disabled());
+        let is_opaque = self
+            .decoded_alpha_summary(flattened_layer_top_down_idx)
+            .is_some_and(|summary| summary.is_opaque());
+        if is_opaque && !has_mask {
+            return self.layer_extent_in_row(flattened_layer_top_down_idx, row);
+        }
+
+        if self.cached_opaque_spans[flattened_layer_top_down_idx]
             .borrow()
             .is_none()
         {
-            let pixels = layer.rgba();
+            let spans = self.with_layer_rgba(flattened_layer_top_down_idx, |layer_rgba| {
+                (0..self.height)
+                    .map(|row| {
+                        let (left, right) =
+                            self.layer_extent_in_row(flattened_layer_top_down_idx, row)?;
+                        longest_opaque_run(&layer_rgba[row * self.width * 4..], left, right)
+                    })
+                    .collect()
+            });
 
-            self.cached_layer_rgba[flattened_layer_top_down_idx].replace(Some(pixels));
+            self.cached_opaque_spans[flattened_layer_top_down_idx].replace(Some(spans));
         }
 
-        let cached_layer_rgba = self.cached_layer_rgba[flattened_layer_top_down_idx].borrow();
-        let layer_rgba = cached_layer_rgba.as_deref().unwrap();
+        self.cached_opaque_spans[flattened_layer_top_down_idx]
+            .borrow()
+            .as_ref()
+            .unwrap()[row]
+    }
 
-        let (pixel_left, pixel_top) = pixel_coord;
-        let pixel_idx = ((self.width * pixel_top) + pixel_left) * 4;
+    /// Whether or not each layer can be seen somewhere on a row.
+    ///
+    /// A layer is culled when an opaque layer above it covers every pixel that it has on
+    /// this row, so that we never look up (or even decode) the pixels that it can't show.
+    fn visible_layers_in_row(&self, row: usize) -> Ref<'_, [bool]> {
+        if self.cached_row_layers.borrow()[row].is_none() {
+            let mut visible = vec![false; self.layers_to_flatten_top_down.len()];
+            self.cull_row(self.nodes, row, &mut vec![], &mut visible);
 
-        let (start, end) = (pixel_idx, pixel_idx + 4);
+            self.cached_row_layers.borrow_mut()[row] = Some(visible);
+        }
 
-        let pixel = &layer_rgba[start..end];
-        let mut copy = [0; 4];
-        copy.copy_from_slice(pixel);
+        Ref::map(self.cached_row_layers.borrow(), |rows| {
+            rows[row].as_deref().unwrap()
+        })
+    }
 
-        blend::apply_opacity(&mut copy, layer.opacity);
-        copy
+    /// Mark the layers in `nodes` that aren't covered by any of the `covering_spans` (the opaque
+    /// spans above them) as visible, adding the opaque spans of the nodes that hide the nodes
+    /// below them.
+    fn cull_row(
+        &self,
+        nodes: &[RenderNode],
+        row: usize,
+        covering_spans: &mut Vec<(usize, usize)>,
+        visible: &mut [bool],
+    ) {
+        for node in nodes {
+            match node {
+                RenderNode::Layer(idx) => {
+                    let (left, right) = match self.layer_extent_in_row(*idx, row) {
+                        Some(extent) => extent,
+                        None => continue,
+                    };
+
+                    // Fully transparent layers don't change the pixels below them
+                    let is_transparent = self
+                        .decoded_alpha_summary(*idx)
+                        .is_some_and(|summary| summary.is_transparent());
+                    if is_transparent {
+                        continue;
+                    }
+
+                    let is_hidden = covering_spans
+                        .iter()
+                        .any(|(span_left, span_right)| *span_left <= left && right <= *span_right);
+                    if is_hidden {
+                        continue;
+                    }
+
+                    visible[*idx] = true;
+                    if let Some(span) = self.opaque_span(*idx, row) {
+                        covering_spans.push(span);
+                    }
+                }
+                RenderNode::Group {
+                    blend_mode,
+                    opacity,
+                    children,
+                    ..
+                } => {
+                    let spans_above = covering_spans.len();
+                    self.cull_row(children, row, covering_spans, visible);
+
+                    // The opaque children of a group only hide the pixels below the group when
+                    // the group's pixels replace the pixels below it
+                    if *blend_mode != BlendMode::Normal || *opacity != 255 {
+                        covering_spans.truncate(spans_above);
+                    }
+                }
+            }
+        }
     }
 
     /// Get the pixel at a coordinate within this image.
@@ -68,41 +450,1314 @@ impl<'a> Renderer<'a> {
         // (left, top)
         pixel_coord: (usize, usize),
     ) -> [u8; 4] {
+        let visible_layers = self.visible_layers_in_row(pixel_coord.1);
+
+        self.composite(self.nodes, pixel_coord, &visible_layers, None)
+            .unwrap_or([0; 4])
+    }
+
+    /// Blend the pixels of the nodes at a coordinate onto the `backdrop` pixel below them.
+    ///
+    /// Returns `None` if none of the nodes have a pixel at the coordinate and there is no
+    /// backdrop.
+    fn composite(
+        &'a self,
+        nodes: &'a [RenderNode],
+        pixel_coord: (usize, usize),
+        visible_layers: &[bool],
+        backdrop: Option<blend::Pixel>,
+    ) -> Option<blend::Pixel> {
         let (pixel_left, pixel_top) = pixel_coord;
-        let mut pixels = self.pixel_cache.borrow_mut();
-        pixels.clear();
-        for (idx, layer) in self.layers_to_flatten_top_down.iter().enumerate() {
-            // If this pixel is out of bounds of this layer we return the pixel below it.
-            // If there is no pixel below it we return a transparent pixel
-            if (pixel_left as i32) < layer.layer_properties.layer_left
-                || (pixel_left as i32) > layer.layer_properties.layer_right
-                || (pixel_top as i32) < layer.layer_properties.layer_top
-                || (pixel_top as i32) > layer.layer_properties.layer_bottom
-            {
-                continue;
-            }
 
-            let pixel = self.pixel_rgba_for_layer(idx, pixel_coord);
-            pixels.push((pixel, layer.blend_mode));
+        // The pending blends of every call to `composite` share the pixel cache, so we only
+        // look at the ones that we push
+        let first_pending = self.pixel_cache.borrow().len();
+        let mut hides_backdrop = false;
+
+        for node in nodes {
+            let (pixel, blend_mode) = match node {
+                RenderNode::Layer(idx) => {
+                    let (_, layer) = self.layers_to_flatten_top_down[*idx];
+                    let (top, left, bottom, right) = self.layer_bounds(*idx);
+
+                    // If this pixel is out of bounds of this layer we return the pixel below it.
+                    // If there is no pixel below it we return a transparent pixel
+                    if !visible_layers[*idx]
+                        || (pixel_left as i32) < left
+                        || (pixel_left as i32) > right
+                        || (pixel_top as i32) < top
+                        || (pixel_top as i32) > bottom
+                    {
+                        continue;
+                    }
+
+                    #[cfg(feature = "unstable")]
+                    {
+                        if self.is_adjustment_layer(*idx) {
+                            self.pixel_cache
+                                .borrow_mut()
+                                .push(PendingBlend::Adjustment(*idx));
+                            continue;
+                        }
+                    }
+
+                    let pixel = self.pixel_rgba_for_layer(*idx, pixel_coord);
+                    let blend_mode = match self.placeholders[*idx] {
+                        true => BlendMode::Normal,
+                        false => layer.blend_mode,
+                    };
+
+                    // Whether the pixel shows depends on the pixel below it, so it can't hide
+                    // the pixels below it
+                    if self.blending_ranges[*idx].is_some() {
+                        self.pixel_cache
+                            .borrow_mut()
+                            .push(PendingBlend::BlendIf(pixel, blend_mode, *idx));
+                        continue;
+                    }
+
+                    (pixel, blend_mode)
+                }
+                RenderNode::Group {
+                    blend_mode: BlendMode::PassThrough,
+                    opacity,
+                    children,
+                    ..
+                } => {
+                    self.pixel_cache
+                        .borrow_mut()
+                        .push(PendingBlend::PassThrough {
+                            opacity: *opacity,
+                            children,
+                        });
+                    continue;
+                }
+                RenderNode::Group {
+                    blend_mode,
+                    opacity,
+                    children,
+                    ..
+                } => {
+                    let mut pixel =
+                        match self.composite(children, pixel_coord, visible_layers, None) {
+                            Some(pixel) => pixel,
+                            None => continue,
+                        };
+                    blend::apply_opacity(&mut pixel, *opacity);
+
+                    (pixel, *blend_mode)
+                }
+            };
 
-            // This pixel is fully opaque, no point in going deeper
-            if pixel[3] == 255 && layer.opacity == 255 {
+            self.pixel_cache
+                .borrow_mut()
+                .push(PendingBlend::Pixel(pixel, blend_mode));
+
+            // This pixel is fully opaque and hides the pixels below it, no point in going deeper
+            if pixel[3] == 255 && blend_mode == BlendMode::Normal {
+                hides_backdrop = true;
                 break;
             }
         }
 
-        match pixels.pop() {
-            Some((bottom_pixel, _)) => {
-                pixels
-                    .iter()
-                    .rev()
-                    .fold(bottom_pixel, |mut pixel_below, (pixel, blend_mode)| {
-                        blend::blend_pixels(*pixel, pixel_below, *blend_mode, &mut pixel_below);
+        let mut pixel_below = if hides_backdrop { None } else { backdrop };
 
-                        pixel_below
-                    })
+        // Blend from the bottom up
+        while self.pixel_cache.borrow().len() > first_pending {
+            let pending = self.pixel_cache.borrow_mut().pop().unwrap();
+
+            pixel_below = match (pending, pixel_below) {
+                (PendingBlend::BlendIf(mut pixel, blend_mode, idx), pixel_below) => {
+                    let ranges = self.blending_ranges[idx].as_ref().unwrap();
+                    let opacity = ranges.opacity(pixel, pixel_below);
+                    blend::apply_opacity(&mut pixel, opacity);
+
+                    match pixel_below {
+                        Some(mut pixel_below) => {
+                            blend::blend_pixels_at_depth(
+                                pixel,
+                                pixel_below,
+                                blend_mode,
+                                self.depth,
+                                &mut pixel_below,
+                            );
+                            Some(pixel_below)
+                        }
+                        None => Some(pixel),
+                    }
+                }
+                (PendingBlend::Pixel(pixel, _), None) => Some(pixel),
+                (PendingBlend::Pixel(pixel, blend_mode), Some(mut pixel_below)) => {
+                    blend::blend_pixels_at_depth(
+                        pixel,
+                        pixel_below,
+                        blend_mode,
+                        self.depth,
+                        &mut pixel_below,
+                    );
+
+                    Some(pixel_below)
+                }
+                (PendingBlend::PassThrough { opacity, children }, pixel_below) => {
+                    let blended =
+                        self.composite(children, pixel_coord, visible_layers, pixel_below);
+
+                    match (pixel_below, blended) {
+                        (Some(pixel_below), Some(blended)) => {
+                            Some(blend::fade(pixel_below, blended, opacity))
+                        }
+                        (None, Some(mut blended)) => {
+                            blend::apply_opacity(&mut blended, opacity);
+                            Some(blended)
+                        }
+                        (pixel_below, None) => pixel_below,
+                    }
+                }
+                #[cfg(feature = "unstable")]
+                (PendingBlend::Adjustment(idx), pixel_below) => pixel_below.map(|pixel_below| {
+                    self.adjustment_layers[idx]
+                        .as_ref()
+                        .unwrap()
+                        .apply(pixel_below, pixel_coord)
+                }),
+            };
+        }
+
+        pixel_below
+    }
+}
+
+/// Nest the layers that pass the filter in the groups that they get composited in, top to
+/// bottom.
+///
+/// When a `group` is given only its contents are returned, without applying the group's own
+/// blend mode and opacity.
+fn render_nodes<'a>(
+    psd: &'a Psd,
+    group: Option<&PsdGroup>,
+    include_layer: &dyn Fn((usize, &PsdLayer)) -> bool,
+) -> (Vec<(usize, &'a PsdLayer)>, Vec<RenderNode>) {
+    let tree = node_tree(psd);
+    let nodes = match group {
+        Some(group) => find_group_children(&tree, group).unwrap_or(&[]),
+        None => &tree,
+    };
+
+    // The tree holds the layers in the same order as `Psd::layers`, so we count our way through
+    // them
+    let mut next_layer_idx = group.map_or(0, |group| group.contained_layers.start);
+    let mut layers_to_flatten = vec![];
+    let mut render_nodes = vec![];
+    push_render_nodes(
+        psd,
+        nodes,
+        include_layer,
+        &mut next_layer_idx,
+        &mut layers_to_flatten,
+        &mut render_nodes,
+    );
+
+    (layers_to_flatten, render_nodes)
+}
+
+fn push_render_nodes<'a>(
+    psd: &'a Psd,
+    nodes: &[PsdNode<'a>],
+    include_layer: &dyn Fn((usize, &PsdLayer)) -> bool,
+    next_layer_idx: &mut usize,
+    layers_to_flatten: &mut Vec<(usize, &'a PsdLayer)>,
+    render_nodes: &mut Vec<RenderNode>,
+) {
+    for node in nodes {
+        match node {
+            PsdNode::Layer(layer) => {
+                let layer_idx = *next_layer_idx;
+                *next_layer_idx += 1;
+                debug_assert!(std::ptr::eq(&psd.layers()[layer_idx], *layer));
+
+                if include_layer((layer_idx, layer)) {
+                    render_nodes.push(RenderNode::Layer(layers_to_flatten.len()));
+                    layers_to_flatten.push((layer_idx, *layer));
+                }
+            }
+            PsdNode::Group {
+                properties,
+                children,
+            } => {
+                let mut group_nodes = vec![];
+                push_render_nodes(
+                    psd,
+                    children,
+                    include_layer,
+                    next_layer_idx,
+                    layers_to_flatten,
+                    &mut group_nodes,
+                );
+
+                if group_nodes.is_empty() {
+                    continue;
+                }
+
+                let (blend_mode, opacity) = (properties.blend_mode(), properties.opacity());
+                if blend_mode == BlendMode::PassThrough && opacity == 255 {
+                    render_nodes.extend(group_nodes);
+                } else {
+                    render_nodes.push(RenderNode::Group {
+                        id: properties.id(),
+                        blend_mode,
+                        opacity,
+                        children: group_nodes,
+                    });
+                }
             }
-            None => [0; 4],
         }
     }
 }
+
+/// The children of a group somewhere in a tree of nodes
+fn find_group_children<'n, 'a>(
+    nodes: &'n [PsdNode<'a>],
+    group: &PsdGroup,
+) -> Option<&'n [PsdNode<'a>]> {
+    nodes.iter().find_map(|node| match node {
+        PsdNode::Group {
+            properties,
+            children,
+        } if std::ptr::eq(*properties, group) => Some(children.as_slice()),
+        PsdNode::Group { children, .. } => find_group_children(children, group),
+        PsdNode::Layer(_) => None,
+    })
+}
+
+/// The decoded RGBA pixels of a PSD's layers, shared between renders and anything else that
+/// needs a layer's pixels, such as layer previews in a UI.
+///
+/// Layers are decoded the first time that they're needed and kept until the cache is cleared.
+/// A cache must only ever be used with one PSD.
+///
+/// ```no_run
+/// # use psd::{LayerRgbaCache, Psd};
+/// # let psd = Psd::from_bytes(&[]).unwrap();
+/// let layer_cache = LayerRgbaCache::new();
+///
+/// let image = psd.render().layer_cache(&layer_cache).to_rgba().unwrap();
+/// // Doesn't decode the top layer again
+/// let top_layer_preview = layer_cache.layer_rgba(&psd, 0).unwrap();
+/// ```
+#[derive(Debug, Default)]
+pub struct LayerRgbaCache {
+    layers: RefCell<Vec<Option<Rc<[u8]>>>>,
+}
+
+impl LayerRgbaCache {
+    /// Create an empty cache
+    pub fn new() -> LayerRgbaCache {
+        LayerRgbaCache::default()
+    }
+
+    /// The RGBA pixels of the layer at `layer_idx` in [`Psd::layers`], with the layer's masks,
+    /// opacity and fill opacity applied. Like [`PsdLayer::rgba`] the pixels span the entire PSD, or are empty
+    /// for empty layers.
+    ///
+    /// Returns None if the PSD doesn't have a layer at that index.
+    ///
+    /// [`Psd::layers`]: crate::Psd::layers
+    pub fn layer_rgba(&self, psd: &Psd, layer_idx: usize) -> Option<Rc<[u8]>> {
+        let layer = psd.layers().get(layer_idx)?;
+
+        let mut layers = self.layers.borrow_mut();
+        if layers.len() < psd.layers().len() {
+            layers.resize(psd.layers().len(), None);
+        }
+
+        let pixels = layers[layer_idx].get_or_insert_with(|| decode_layer_rgba(layer).into());
+
+        Some(Rc::clone(pixels))
+    }
+
+    /// Store the pixels of a layer that were decoded somewhere else, such as on another thread.
+    #[cfg(feature = "rayon")]
+    pub(crate) fn insert(&self, psd: &Psd, layer_idx: usize, pixels: Vec<u8>) -> Rc<[u8]> {
+        let mut layers = self.layers.borrow_mut();
+        if layers.len() < psd.layers().len() {
+            layers.resize(psd.layers().len(), None);
+        }
+
+        Rc::clone(layers[layer_idx].insert(pixels.into()))
+    }
+
+    /// Whether or not the layer at `layer_idx` has already been decoded
+    pub fn contains(&self, layer_idx: usize) -> bool {
+        matches!(self.layers.borrow().get(layer_idx), Some(Some(_)))
+    }
+
+    /// Drop every decoded layer, such as after the PSD's layers were edited.
+    pub fn clear(&self) {
+        self.layers.borrow_mut().clear();
+    }
+}
+
+/// The pixels of a layer with its masks, opacity and fill opacity applied, see
+/// `LayerRgbaCache::layer_rgba`
+fn decode_layer_rgba(layer: &PsdLayer) -> Vec<u8> {
+    let mut pixels = layer.rgba();
+    layer.apply_masks(&mut pixels);
+    // Apply the opacity once for the whole layer instead of every time that we look up one of
+    // its pixels
+    blend::apply_layer_opacity_buffer(&mut pixels, layer.opacity, layer.fill_opacity());
+
+    pixels
+}
+
+/// A layer filter used by a [`RenderPipeline`]
+type LayerFilter<'a> = Box<dyn Fn((usize, &PsdLayer)) -> bool + 'a>;
+
+/// A chainable set of options for rendering a PSD's layers into RGBA pixels.
+///
+/// Created by [`Psd::render`]. Each option can be combined with any of the others, and nothing
+/// is rendered until you call [`RenderPipeline::to_rgba`].
+///
+/// ```no_run
+/// # use psd::Psd;
+/// # let psd = Psd::from_bytes(&[]).unwrap();
+/// let preview = psd
+///     .render()
+///     .visible_only()
+///     .group("UI/HUD")
+///     .background([255; 4])
+///     .scale(0.5)
+///     .to_rgba()
+///     .unwrap();
+/// ```
+///
+/// [`Psd::render`]: crate::Psd::render
+pub struct RenderPipeline<'a> {
+    psd: &'a Psd,
+    filters: Vec<LayerFilter<'a>>,
+    group_path: Option<String>,
+    background: Option<[u8; 4]>,
+    scale: f32,
+    orientation: Option<ExifOrientation>,
+    layer_cache: Option<&'a LayerRgbaCache>,
+    #[cfg(feature = "unstable")]
+    layer_effects: bool,
+    #[cfg(feature = "unstable")]
+    adjustment_layers: bool,
+    blending_ranges: bool,
+    placeholder: Option<[u8; 4]>,
+}
+
+impl<'a> RenderPipeline<'a> {
+    pub(crate) fn new(psd: &'a Psd) -> RenderPipeline<'a> {
+        RenderPipeline {
+            psd,
+            filters: vec![],
+            group_path: None,
+            background: None,
+            scale: 1.,
+            orientation: None,
+            layer_cache: None,
+            #[cfg(feature = "unstable")]
+            layer_effects: false,
+            #[cfg(feature = "unstable")]
+            adjustment_layers: false,
+            blending_ranges: false,
+            placeholder: None,
+        }
+    }
+
+    /// Only render layers that are visible and not fully transparent.
+    ///
+    /// This is the same set of layers that [`Psd::flatten_layers_rgba`] renders.
+    ///
+    /// [`Psd::flatten_layers_rgba`]: crate::Psd::flatten_layers_rgba
+    pub fn visible_only(self) -> RenderPipeline<'a> {
+        self.filter(|(_, layer)| (layer.opacity > 0 && layer.visible()) || layer.clipping_mask)
+    }
+
+    /// Only render layers that pass the filter.
+    ///
+    /// Calling this multiple times only renders layers that pass every filter.
+    pub fn filter<F>(mut self, filter: F) -> RenderPipeline<'a>
+    where
+        F: Fn((usize, &PsdLayer)) -> bool + 'a,
+    {
+        self.filters.push(Box::new(filter));
+        self
+    }
+
+    /// Only render layers that are inside of the group at the given path, such as `UI/HUD`.
+    ///
+    /// A group's path is the names of the groups that it is nested in followed by its own name,
+    /// separated by `/`. The group's contents are rendered without the group's own blend mode
+    /// and opacity, while the groups nested inside of it still use theirs.
+    ///
+    /// [`RenderPipeline::to_rgba`] returns [`PsdError::GroupNotFound`] if there is no group at
+    /// the path.
+    pub fn group(mut self, path: &str) -> RenderPipeline<'a> {
+        self.group_path = Some(path.to_string());
+        self
+    }
+
+    /// Composite the rendered layers on top of a solid color.
+    pub fn background(mut self, rgba: [u8; 4]) -> RenderPipeline<'a> {
+        self.background = Some(rgba);
+        self
+    }
+
+    /// Scale the rendered image, such as `0.5` to render a preview at half of the size.
+    ///
+    /// Only the pixels that end up in the scaled image are rendered (using nearest neighbor
+    /// sampling), so small previews of large documents are cheap.
+    ///
+    /// # Panics
+    ///
+    /// If the scale is not a positive, finite number.
+    pub fn scale(mut self, scale: f32) -> RenderPipeline<'a> {
+        assert!(
+            scale > 0. && scale.is_finite(),
+            "The scale must be a positive, finite number but was {}",
+            scale
+        );
+
+        self.scale = scale;
+        self
+    }
+
+    /// Rotate and flip the rendered image according to the PSD's [`Psd::exif_orientation`], so
+    /// that scanned or rotated documents come out the way that they should be viewed.
+    ///
+    /// Does nothing if the PSD doesn't have an EXIF orientation.
+    ///
+    /// [`Psd::exif_orientation`]: crate::Psd::exif_orientation
+    pub fn exif_orientation(mut self) -> RenderPipeline<'a> {
+        self.orientation = self.psd.exif_orientation();
+        self
+    }
+
+    /// Look up and store decoded layer pixels in a [`LayerRgbaCache`] that outlives this render,
+    /// so that later renders and layer previews don't decode the same layers again.
+    ///
+    /// The cache must only ever be used with this pipeline's PSD.
+    pub fn layer_cache(mut self, layer_cache: &'a LayerRgbaCache) -> RenderPipeline<'a> {
+        self.layer_cache = Some(layer_cache);
+        self
+    }
+
+    /// Render the drop shadows, color overlays and strokes of layers that have them, so that the
+    /// image looks closer to Photoshop's.
+    ///
+    /// The shadows and strokes are always blended normally, and the other effects aren't
+    /// rendered. Layers with effects are decoded before rendering, since their effects can reach
+    /// anywhere on the PSD, and they aren't stored in the [`RenderPipeline::layer_cache`].
+    #[cfg(feature = "unstable")]
+    pub fn layer_effects(mut self) -> RenderPipeline<'a> {
+        self.layer_effects = true;
+        self
+    }
+
+    /// Apply brightness/contrast, levels, curves, hue/saturation and exposure adjustment layers
+    /// to the pixels below them, faded by each adjustment layer's opacity and user mask.
+    ///
+    /// Without this adjustment layers don't change the image, since they don't have any pixels
+    /// of their own. The other kinds of adjustment layers are still ignored, adjustment layers
+    /// are always applied with the normal blend mode, and clipped adjustment layers apply to
+    /// everything below them rather than only to the layer that they are clipped to. Photoshop
+    /// doesn't document how it applies some of the adjustments, such as brightness/contrast, so
+    /// the colors are approximate.
+    #[cfg(feature = "unstable")]
+    pub fn adjustment_layers(mut self) -> RenderPipeline<'a> {
+        self.adjustment_layers = true;
+        self
+    }
+
+    /// Apply each layer's "Blend If" sliders, hiding the pixels of the layer whose values, or
+    /// the values of the pixels below them, are outside of the sliders' ranges.
+    ///
+    /// The gray sliders are compared with the luminosity of the pixels. Without this the sliders
+    /// are ignored, see [`FidelityIssueKind::BlendingRanges`].
+    ///
+    /// [`FidelityIssueKind::BlendingRanges`]: crate::FidelityIssueKind::BlendingRanges
+    pub fn blending_ranges(mut self) -> RenderPipeline<'a> {
+        self.blending_ranges = true;
+        self
+    }
+
+    /// Draw a checkerboard of `color`, such as magenta `[255, 0, 255, 255]`, in place of the
+    /// layers that can't be rendered, so that previews show where content is missing instead of
+    /// silently leaving it out.
+    ///
+    /// These are the adjustment layers that aren't applied, see
+    /// [`FidelityIssueKind::AdjustmentLayer`], and the layers whose blend mode can't be blended
+    /// yet, see [`FidelityIssueKind::UnsupportedBlendMode`], which would otherwise panic. The
+    /// checkerboard covers the layer, or the whole PSD for adjustment layers and other layers
+    /// without pixels of their own, and is blended normally after applying the layer's masks
+    /// and opacity.
+    ///
+    /// [`FidelityIssueKind::AdjustmentLayer`]: crate::FidelityIssueKind::AdjustmentLayer
+    /// [`FidelityIssueKind::UnsupportedBlendMode`]: crate::FidelityIssueKind::UnsupportedBlendMode
+    pub fn unsupported_placeholder(mut self, color: [u8; 4]) -> RenderPipeline<'a> {
+        self.placeholder = Some(color);
+        self
+    }
+
+    /// The (width, height) of the image that will be rendered, after scaling and orienting.
+    pub fn dimensions(&self) -> (u32, u32) {
+        let (width, height) = self.scaled_dimensions();
+
+        match self.orientation {
+            Some(orientation) => orientation.dimensions(width, height),
+            None => (width, height),
+        }
+    }
+
+    /// The (width, height) of the image that will be rendered, before orienting.
+    fn scaled_dimensions(&self) -> (u32, u32) {
+        let scaled = |size: u32| ((size as f32 * self.scale).round() as u32).max(1);
+
+        (scaled(self.psd.width()), scaled(self.psd.height()))
+    }
+
+    /// Render the layers into a vector of RGBA pixels that is [`RenderPipeline::dimensions`]
+    /// in size.
+    /// [ R,G,B,A, R,G,B,A, R,G,B,A, ...]
+    pub fn to_rgba(&self) -> Result<Vec<u8>, PsdError> {
+        let mut rgba = Vec::with_capacity(self.pixel_count() * 4);
+        self.for_each_row(|_, row| rgba.extend_from_slice(row))?;
+
+        Ok(rgba)
+    }
+
+    /// The layers that would be rendered, as a [`RenderPlan`] of draw commands with each
+    /// layer's pixels, bounds, blend mode, opacity, mask and clipping, for compositing them on
+    /// the GPU instead.
+    ///
+    /// The plan respects [`RenderPipeline::filter`], [`RenderPipeline::visible_only`],
+    /// [`RenderPipeline::group`] and [`RenderPipeline::background`]. Scaling, orienting, layer
+    /// effects, adjustment layers, blending ranges and placeholders are left to the engine.
+    ///
+    /// Returns [`PsdError::GroupNotFound`] if there is no group at the [`RenderPipeline::group`]
+    /// path.
+    pub fn to_plan(&self) -> Result<RenderPlan<'a>, PsdError> {
+        let (layers_to_flatten_top_down, nodes) = self.render_nodes()?;
+
+        Ok(render_plan::render_plan(
+            self.psd,
+            &layers_to_flatten_top_down,
+            &nodes,
+            self.background,
+        ))
+    }
+
+    /// Render the layers into a buffer that you own with the given layout, such as a mapped GPU
+    /// staging buffer whose rows are padded for alignment.
+    ///
+    /// Each row is written as it is rendered, so the buffer doesn't need to be repacked
+    /// afterwards. Bytes between the lines of pixels are left untouched.
+    pub fn to_rgba_into(&self, out: &mut [u8], layout: RgbaLayout) -> Result<(), PsdError> {
+        let (width, height) = self.dimensions();
+        layout.check(width, height, out.len())?;
+
+        self.for_each_row(|top, row| {
+            if !layout.is_column_major() {
+                let start = layout.offset(0, top);
+                out[start..start + row.len()].copy_from_slice(row);
+                return;
+            }
+
+            for (left, pixel) in row.chunks_exact(4).enumerate() {
+                let start = layout.offset(left, top);
+                out[start..start + 4].copy_from_slice(pixel);
+            }
+        })
+    }
+
+    /// Render the layers into separate red, green, blue and alpha planes, each of which has one
+    /// byte per pixel.
+    /// [ [R,R,R, ...], [G,G,G, ...], [B,B,B, ...], [A,A,A, ...] ]
+    ///
+    /// Video pipelines and SIMD code often want planar data, so this saves de-interleaving the
+    /// output of [`RenderPipeline::to_rgba`].
+    pub fn to_planar(&self) -> Result<[Vec<u8>; 4], PsdError> {
+        let pixel_count = self.pixel_count();
+        let mut planes = [
+            Vec::with_capacity(pixel_count),
+            Vec::with_capacity(pixel_count),
+            Vec::with_capacity(pixel_count),
+            Vec::with_capacity(pixel_count),
+        ];
+
+        self.for_each_pixel(|pixel| {
+            for (plane, value) in planes.iter_mut().zip(pixel.iter()) {
+                plane.push(*value);
+            }
+        })?;
+
+        Ok(planes)
+    }
+
+    /// Render the layers into 8-bit, video range BT.709 YCbCr, ready to feed into a video
+    /// encoder.
+    /// [ Y,Cb,Cr, Y,Cb,Cr, Y,Cb,Cr, ...]
+    ///
+    /// The pixels are converted as they are rendered, without an intermediate RGBA image.
+    /// YCbCr has no alpha channel, so you'll typically want to set a
+    /// [`RenderPipeline::background`] first.
+    pub fn to_ycbcr(&self) -> Result<Vec<u8>, PsdError> {
+        let mut ycbcr = Vec::with_capacity(self.pixel_count() * 3);
+        self.for_each_pixel(|pixel| ycbcr.extend_from_slice(&rgb_to_ycbcr(pixel)))?;
+
+        Ok(ycbcr)
+    }
+
+    /// Render the layers into separate, full resolution (4:4:4) Y, Cb and Cr planes.
+    /// [ [Y,Y,Y, ...], [Cb,Cb,Cb, ...], [Cr,Cr,Cr, ...] ]
+    ///
+    /// See [`RenderPipeline::to_ycbcr`].
+    pub fn to_ycbcr_planar(&self) -> Result<[Vec<u8>; 3], PsdError> {
+        let pixel_count = self.pixel_count();
+        let mut planes = [
+            Vec::with_capacity(pixel_count),
+            Vec::with_capacity(pixel_count),
+            Vec::with_capacity(pixel_count),
+        ];
+
+        self.for_each_pixel(|pixel| {
+            for (plane, value) in planes.iter_mut().zip(rgb_to_ycbcr(pixel).iter()) {
+                plane.push(*value);
+            }
+        })?;
+
+        Ok(planes)
+    }
+
+    /// Render the layers and crop the result to the smallest rectangle that contains every
+    /// pixel that isn't fully transparent, such as when generating sprites.
+    ///
+    /// The bounds are tracked while rendering, so this doesn't need an extra pass over the
+    /// image to find them.
+    pub fn to_trimmed_rgba(&self) -> Result<TrimmedRgba, PsdError> {
+        let (width, _) = self.dimensions();
+        let width = width as usize;
+
+        let mut rgba = Vec::with_capacity(self.pixel_count() * 4);
+        // (left, top, right, bottom), inclusive
+        let mut bounds: Option<(usize, usize, usize, usize)> = None;
+
+        self.for_each_pixel(|pixel| {
+            if pixel[3] > 0 {
+                let idx = rgba.len() / 4;
+                let (left, top) = (idx % width, idx / width);
+
+                bounds = Some(match bounds {
+                    Some((l, t, r, b)) => (l.min(left), t.min(top), r.max(left), b.max(top)),
+                    None => (left, top, left, top),
+                });
+            }
+
+            rgba.extend_from_slice(&pixel);
+        })?;
+
+        let (left, top, right, bottom) = match bounds {
+            Some(bounds) => bounds,
+            None => {
+                return Ok(TrimmedRgba {
+                    left: 0,
+                    top: 0,
+                    width: 0,
+                    height: 0,
+                    rgba: vec![],
+                })
+            }
+        };
+
+        let (trimmed_width, trimmed_height) = (right - left + 1, bottom - top + 1);
+        let mut trimmed = Vec::with_capacity(trimmed_width * trimmed_height * 4);
+        for row in top..=bottom {
+            let start = (row * width + left) * 4;
+            trimmed.extend_from_slice(&rgba[start..start + trimmed_width * 4]);
+        }
+
+        Ok(TrimmedRgba {
+            left: left as u32,
+            top: top as u32,
+            width: trimmed_width as u32,
+            height: trimmed_height as u32,
+            rgba: trimmed,
+        })
+    }
+
+    fn pixel_count(&self) -> usize {
+        let (width, height) = self.dimensions();
+        width as usize * height as usize
+    }
+
+    /// Render each pixel, from left to right and then top to bottom.
+    fn for_each_pixel<F: FnMut(blend::Pixel)>(&self, mut f: F) -> Result<(), PsdError> {
+        self.for_each_row(|_, row| {
+            for pixel in row.chunks_exact(4) {
+                f([pixel[0], pixel[1], pixel[2], pixel[3]]);
+            }
+        })
+    }
+
+    /// The layers that pass the filters, nested in the groups that they get composited in
+    #[allow(clippy::type_complexity)]
+    fn render_nodes(&self) -> Result<(Vec<(usize, &'a PsdLayer)>, Vec<RenderNode>), PsdError> {
+        let group = match &self.group_path {
+            Some(path) => Some(
+                group_by_path(self.psd, path)
+                    .ok_or_else(|| PsdError::GroupNotFound(path.clone()))?,
+            ),
+            None => None,
+        };
+
+        Ok(render_nodes(self.psd, group, &|(idx, layer)| {
+            self.filters.iter().all(|filter| filter((idx, layer)))
+        }))
+    }
+
+    /// Render each row of RGBA pixels from top to bottom, along with the index of the row, so
+    /// that callers can copy whole rows instead of one pixel at a time.
+    fn for_each_row<F: FnMut(usize, &[u8])>(&self, mut f: F) -> Result<(), PsdError> {
+        let psd = self.psd;
+
+        let (layers_to_flatten_top_down, nodes) = self.render_nodes()?;
+
+        // When you create a PSD but don't create any new layers the bottom layer might not
+        // show up in the layer and mask information section, so we fall back to the final image.
+        // See `Psd::flatten_layers_rgba`.
+        let final_image = if psd.layers().is_empty() {
+            Some(psd.rgba())
+        } else {
+            None
+        };
+        let own_layer_cache;
+        let layer_cache = match self.layer_cache {
+            Some(layer_cache) => layer_cache,
+            None => {
+                own_layer_cache = LayerRgbaCache::new();
+                &own_layer_cache
+            }
+        };
+        let placeholders = self.placeholders(&layers_to_flatten_top_down);
+        let synthesized_layer_rgba: Vec<Option<Vec<u8>>> = self
+            .synthesized_layer_rgba(&layers_to_flatten_top_down)
+            .into_iter()
+            .zip(placeholders.iter())
+            .map(|(pixels, placeholder)| placeholder.clone().or(pixels))
+            .collect();
+        #[cfg(feature = "unstable")]
+        let adjustment_layers: Vec<Option<AdjustmentLayer>> = match self.adjustment_layers {
+            true => layers_to_flatten_top_down
+                .iter()
+                .map(|(_, layer)| AdjustmentLayer::new(psd, layer))
+                .collect(),
+            false => vec![],
+        };
+
+        let (scaled_width, scaled_height) = self.scaled_dimensions();
+        let (width, height) = self.dimensions();
+        let job = RenderJob {
+            psd,
+            layers_to_flatten_top_down: &layers_to_flatten_top_down,
+            nodes: &nodes,
+            synthesized_layer_rgba: synthesized_layer_rgba
+                .iter()
+                .map(Option::as_deref)
+                .collect(),
+            #[cfg(feature = "rayon")]
+            decoded_layer_rgba: vec![None; layers_to_flatten_top_down.len()],
+            #[cfg(feature = "rayon")]
+            alpha_summaries: vec![None; layers_to_flatten_top_down.len()],
+            #[cfg(feature = "unstable")]
+            adjustment_layers: &adjustment_layers,
+            blending_ranges: match self.blending_ranges {
+                true => Some(
+                    layers_to_flatten_top_down
+                        .iter()
+                        .map(|(_, layer)| {
+                            layer
+                                .blending_ranges()
+                                .filter(|ranges| !ranges.is_default())
+                        })
+                        .collect(),
+                ),
+                false => None,
+            },
+            placeholders: placeholders.iter().map(Option::is_some).collect(),
+            final_image: final_image.as_deref(),
+            background: self.background,
+            scale: self.scale,
+            orientation: self.orientation,
+            scaled_dimensions: (scaled_width as usize, scaled_height as usize),
+            width: width as usize,
+            height: height as usize,
+        };
+
+        #[cfg(feature = "rayon")]
+        {
+            let (decoded_layer_rgba, alpha_summaries) = job.decode_layers(layer_cache);
+            let row_len = job.width * 4;
+            let rgba = job
+                .with_decoded_layers(&decoded_layer_rgba, alpha_summaries)
+                .render_in_parallel();
+            for (top, row) in rgba.chunks_exact(row_len.max(1)).enumerate() {
+                f(top, row);
+            }
+        }
+
+        #[cfg(not(feature = "rayon"))]
+        {
+            let renderer = job.renderer(layer_cache);
+            let mut row = vec![0; job.width * 4];
+            for top in 0..job.height {
+                for (left, pixel) in row.chunks_exact_mut(4).enumerate() {
+                    pixel.copy_from_slice(&job.pixel(&renderer, (left, top)));
+                }
+                f(top, &row);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Everything that rendering needs other than the layer cache, which the renderer of each band of
+/// rows shares when rendering in parallel.
+///
+/// Built by `RenderPipeline::for_each_row`.
+struct RenderJob<'a> {
+    psd: &'a Psd,
+    layers_to_flatten_top_down: &'a [(usize, &'a PsdLayer)],
+    nodes: &'a [RenderNode],
+    /// See `Renderer::synthesized_layer_rgba`
+    synthesized_layer_rgba: Vec<Option<&'a [u8]>>,
+    /// See `Renderer::decoded_layer_rgba`
+    #[cfg(feature = "rayon")]
+    decoded_layer_rgba: Vec<Option<&'a [u8]>>,
+    /// The alpha summaries of the decoded layers, see `Renderer::decoded_alpha_summary`
+    #[cfg(feature = "rayon")]
+    alpha_summaries: Vec<Option<AlphaSummary>>,
+    #[cfg(feature = "unstable")]
+    adjustment_layers: &'a [Option<AdjustmentLayer>],
+    blending_ranges: Option<Vec<Option<BlendingRanges>>>,
+    placeholders: Vec<bool>,
+    /// The final image, which is rendered instead of the layers when the PSD has no layers
+    final_image: Option<&'a [u8]>,
+    background: Option<[u8; 4]>,
+    scale: f32,
+    orientation: Option<ExifOrientation>,
+    /// The (width, height) of the scaled image before it gets oriented
+    scaled_dimensions: (usize, usize),
+    /// The width of the rendered image
+    width: usize,
+    /// The height of the rendered image
+    height: usize,
+}
+
+impl<'a> RenderJob<'a> {
+    fn renderer<'r>(&'r self, layer_cache: &'r LayerRgbaCache) -> Renderer<'r> {
+        let renderer = Renderer::new(
+            self.psd,
+            self.layers_to_flatten_top_down,
+            self.nodes,
+            layer_cache,
+            self.synthesized_layer_rgba.clone(),
+        );
+        #[cfg(feature = "unstable")]
+        let renderer = renderer.with_adjustment_layers(self.adjustment_layers);
+        #[cfg(feature = "rayon")]
+        let renderer = renderer
+            .with_decoded_layer_rgba(self.decoded_layer_rgba.clone())
+            .with_alpha_summaries(&self.alpha_summaries);
+
+        let renderer = match &self.blending_ranges {
+            Some(blending_ranges) => renderer.with_blending_ranges(blending_ranges.clone()),
+            None => renderer,
+        };
+        renderer.with_placeholders(self.placeholders.clone())
+    }
+
+    /// The pixel of the rendered image at (left, top)
+    fn pixel<'r>(&self, renderer: &'r Renderer<'r>, (left, top): (usize, usize)) -> blend::Pixel {
+        let (psd_width, psd_height) = (self.psd.width() as usize, self.psd.height() as usize);
+        let (scaled_left, scaled_top) = match self.orientation {
+            Some(orientation) => orientation.source_coord((left, top), self.scaled_dimensions),
+            None => (left, top),
+        };
+        let source_left = source_coord(scaled_left, self.scale, psd_width);
+        let source_top = source_coord(scaled_top, self.scale, psd_height);
+
+        let mut pixel = match self.final_image {
+            Some(rgba) => {
+                let idx = (source_top * psd_width + source_left) * 4;
+                let mut pixel = [0; 4];
+                pixel.copy_from_slice(&rgba[idx..idx + 4]);
+                pixel
+            }
+            None if self.layers_to_flatten_top_down.is_empty() => [0; 4],
+            None => renderer.flattened_pixel((source_left, source_top)),
+        };
+
+        if let Some(background) = self.background {
+            blend::blend_pixels(pixel, background, BlendMode::Normal, &mut pixel);
+        }
+
+        pixel
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<'a> RenderJob<'a> {
+    /// Decode the layers that might be seen on separate threads, along with their alpha
+    /// summaries, so that the renderers of every band of rows can share them. The layers that
+    /// are already in the layer cache aren't decoded again.
+    ///
+    /// Unlike rendering on one thread, layers that are hidden below opaque layers get decoded
+    /// as well, since we don't know which ones are hidden until we render each row.
+    fn decode_layers(&self, layer_cache: &LayerRgbaCache) -> DecodedLayers {
+        use rayon::prelude::*;
+
+        let layers = self.layers_to_flatten_top_down;
+        let mut decoded: Vec<Option<Rc<[u8]>>> = layers
+            .iter()
+            .map(|(layer_idx, _)| match layer_cache.contains(*layer_idx) {
+                true => layer_cache.layer_rgba(self.psd, *layer_idx),
+                false => None,
+            })
+            .collect();
+
+        let (psd_width, psd_height) = (self.psd.width() as i32, self.psd.height() as i32);
+        let is_decoded_layer = |idx: usize| {
+            #[cfg(feature = "unstable")]
+            {
+                if matches!(self.adjustment_layers.get(idx), Some(Some(_))) {
+                    return false;
+                }
+            }
+
+            let properties = &layers[idx].1.layer_properties;
+            self.synthesized_layer_rgba[idx].is_none()
+                && properties.layer_bottom >= 0.max(properties.layer_top)
+                && properties.layer_right >= 0.max(properties.layer_left)
+                && properties.layer_top < psd_height
+                && properties.layer_left < psd_width
+        };
+
+        let alpha_summaries: Vec<(usize, AlphaSummary)> = (0..layers.len())
+            .into_par_iter()
+            .filter(|idx| is_decoded_layer(*idx))
+            .map(|idx| (idx, layers[idx].1.alpha_summary()))
+            .collect();
+
+        // Fully transparent layers never get looked up
+        let to_decode: Vec<usize> = alpha_summaries
+            .iter()
+            .filter(|(idx, summary)| decoded[*idx].is_none() && !summary.is_transparent())
+            .map(|(idx, _)| *idx)
+            .collect();
+        let pixels: Vec<Vec<u8>> = to_decode
+            .par_iter()
+            .map(|idx| decode_layer_rgba(layers[*idx].1))
+            .collect();
+
+        for (idx, pixels) in to_decode.into_iter().zip(pixels) {
+            decoded[idx] = Some(layer_cache.insert(self.psd, layers[idx].0, pixels));
+        }
+        let mut summaries = vec![None; layers.len()];
+        for (idx, summary) in alpha_summaries {
+            summaries[idx] = Some(summary);
+        }
+
+        (decoded, summaries)
+    }
+
+    fn with_decoded_layers(
+        mut self,
+        decoded_layer_rgba: &'a [Option<Rc<[u8]>>],
+        alpha_summaries: Vec<Option<AlphaSummary>>,
+    ) -> RenderJob<'a> {
+        self.decoded_layer_rgba = decoded_layer_rgba.iter().map(Option::as_deref).collect();
+        self.alpha_summaries = alpha_summaries;
+        self
+    }
+
+    /// Render bands of rows on separate threads, each with its own renderer, into an RGBA
+    /// image.
+    fn render_in_parallel(&self) -> Vec<u8> {
+        use rayon::prelude::*;
+
+        let mut rgba = vec![0; self.width * self.height * 4];
+        let row_len = self.width * 4;
+        if row_len == 0 {
+            return rgba;
+        }
+
+        // A few bands per thread so that a thread that finishes early can take another one
+        let band_height = (self.height / (rayon::current_num_threads() * 4)).max(1);
+        rgba.par_chunks_mut(band_height * row_len)
+            .enumerate()
+            .for_each(|(band, band_rgba)| {
+                let layer_cache = LayerRgbaCache::new();
+                let renderer = self.renderer(&layer_cache);
+
+                for (idx, pixel) in band_rgba.chunks_exact_mut(4).enumerate() {
+                    let (left, top) = (idx % self.width, band * band_height + idx / self.width);
+                    pixel.copy_from_slice(&self.pixel(&renderer, (left, top)));
+                }
+            });
+
+        rgba
+    }
+}
+
+impl<'a> RenderPipeline<'a> {
+    /// The pixels of each fill layer and of each layer with its layer effects, or `None` for the
+    /// layers whose pixels get decoded
+    #[cfg(feature = "unstable")]
+    fn synthesized_layer_rgba(&self, layers: &[(usize, &PsdLayer)]) -> Vec<Option<Vec<u8>>> {
+        layers
+            .iter()
+            .map(|(_, layer)| {
+                let with_effects = if self.layer_effects {
+                    effects::layer_rgba_with_effects(self.psd, layer)
+                } else {
+                    None
+                };
+
+                with_effects.or_else(|| fills::layer_rgba_with_fill(self.psd, layer))
+            })
+            .collect()
+    }
+
+    #[cfg(not(feature = "unstable"))]
+    fn synthesized_layer_rgba(&self, layers: &[(usize, &PsdLayer)]) -> Vec<Option<Vec<u8>>> {
+        vec![None; layers.len()]
+    }
+
+    /// The placeholder pixels of each layer that can't be rendered, see
+    /// `RenderPipeline::unsupported_placeholder`
+    fn placeholders(&self, layers: &[(usize, &PsdLayer)]) -> Vec<Option<Vec<u8>>> {
+        layers
+            .iter()
+            .map(|(_, layer)| match self.placeholder {
+                Some(color)
+                    if !blend::is_supported(layer.blend_mode) || self.ignores_adjustment(layer) =>
+                {
+                    Some(placeholder_rgba(self.psd, layer, color))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Whether the layer is an adjustment layer whose adjustment isn't applied
+    #[cfg(feature = "unstable")]
+    fn ignores_adjustment(&self, layer: &PsdLayer) -> bool {
+        match layer.adjustment() {
+            Some(Adjustment::Other(_)) => true,
+            Some(_) => !self.adjustment_layers,
+            None => false,
+        }
+    }
+
+    #[cfg(not(feature = "unstable"))]
+    fn ignores_adjustment(&self, layer: &PsdLayer) -> bool {
+        layer
+            .layer_properties
+            .record_data
+            .tagged_blocks
+            .iter()
+            .any(|block| ADJUSTMENT_KEYS.contains(&block.key()))
+    }
+}
+
+/// A rendered image that was cropped to its pixels that aren't fully transparent.
+///
+/// Created by [`RenderPipeline::to_trimmed_rgba`] and [`Psd::flatten_trimmed_rgba`].
+///
+/// [`Psd::flatten_trimmed_rgba`]: crate::Psd::flatten_trimmed_rgba
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct TrimmedRgba {
+    left: u32,
+    top: u32,
+    width: u32,
+    height: u32,
+    rgba: Vec<u8>,
+}
+
+impl TrimmedRgba {
+    /// The distance from the left of the untrimmed image to the left of the trimmed image
+    pub fn left(&self) -> u32 {
+        self.left
+    }
+
+    /// The distance from the top of the untrimmed image to the top of the trimmed image
+    pub fn top(&self) -> u32 {
+        self.top
+    }
+
+    /// The width of the trimmed image. 0 if every pixel was transparent.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// The height of the trimmed image. 0 if every pixel was transparent.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The RGBA pixels of the trimmed image
+    /// [ R,G,B,A, R,G,B,A, R,G,B,A, ...]
+    pub fn rgba(&self) -> &[u8] {
+        &self.rgba
+    }
+
+    /// Take the RGBA pixels of the trimmed image
+    pub fn into_rgba(self) -> Vec<u8> {
+        self.rgba
+    }
+}
+
+/// A checkerboard of `color` and transparent squares the size of the PSD, covering the layer or
+/// the whole PSD if the layer doesn't have pixels of its own, faded by the layer's masks and
+/// opacity
+fn placeholder_rgba(psd: &Psd, layer: &PsdLayer, color: [u8; 4]) -> Vec<u8> {
+    let (width, height) = (psd.width() as usize, psd.height() as usize);
+    let mut rgba = vec![0; width * height * 4];
+
+    let properties = &layer.layer_properties;
+    let covers = |left: usize, top: usize| {
+        let (left, top) = (left as i32, top as i32);
+        layer.is_empty()
+            || (left >= properties.layer_left
+                && left <= properties.layer_right
+                && top >= properties.layer_top
+                && top <= properties.layer_bottom)
+    };
+
+    for (idx, pixel) in rgba.chunks_exact_mut(4).enumerate() {
+        let (left, top) = (idx % width, idx / width);
+        let square = left / PLACEHOLDER_SQUARE_LEN + top / PLACEHOLDER_SQUARE_LEN;
+        if square & 1 == 0 && covers(left, top) {
+            pixel.copy_from_slice(&color);
+        }
+    }
+
+    layer.apply_masks(&mut rgba);
+    blend::apply_layer_opacity_buffer(&mut rgba, layer.opacity, layer.fill_opacity());
+
+    rgba
+}
+
+/// The longest run of fully opaque pixels between `left` and `right`, inclusive, in a row of
+/// RGBA pixels.
+fn longest_opaque_run(row_rgba: &[u8], left: usize, right: usize) -> Option<(usize, usize)> {
+    let mut longest: Option<(usize, usize)> = None;
+    let mut run_start = None;
+
+    for column in left..=right {
+        if row_rgba[column * 4 + 3] != 255 {
+            run_start = None;
+            continue;
+        }
+
+        let start = *run_start.get_or_insert(column);
+        if longest.is_none_or(|(l, r)| column - start > r - l) {
+            longest = Some((start, column));
+        }
+    }
+
+    longest
+}
+
+/// The coordinate in the unscaled image that a coordinate in the scaled image samples.
+fn source_coord(coord: usize, scale: f32, size: usize) -> usize {
+    let source = ((coord as f32 + 0.5) / scale) as usize;
+    source.min(size - 1)
+}
+
+/// Find the group whose path (the names of its ancestor groups followed by its own name) is
+/// `path`.
+fn group_by_path<'a>(psd: &'a Psd, path: &str) -> Option<&'a PsdGroup> {
+    layer_tree(psd)
+        .into_iter()
+        .find_map(|entry| match entry.node {
+            LayerTreeNode::Group(group) if entry.path == path => Some(group),
+            _ => None,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The green layer is completely hidden by the opaque red layer above it, so it should
+    /// never be decoded.
+    #[test]
+    fn culls_layers_below_opaque_layers() {
+        let psd = include_bytes!("../tests/fixtures/two-layers-red-green-1x1.psd");
+        let psd = Psd::from_bytes(psd).unwrap();
+
+        let (layers, nodes) = render_nodes(&psd, None, &|_| true);
+        let layer_cache = LayerRgbaCache::new();
+        let renderer = Renderer::new(&psd, &layers, &nodes, &layer_cache, vec![None; 2]);
+
+        assert_eq!(renderer.flattened_pixel((0, 0)), [255, 0, 0, 255]);
+        assert_eq!(&*renderer.visible_layers_in_row(0), &[true, false]);
+        assert!(renderer.cached_layer_rgba[1].borrow().is_none());
+        assert!(!layer_cache.contains(1));
+    }
+
+    /// Rendering bands of rows on separate threads gives the same pixels as rendering every row
+    /// with one renderer.
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn parallel_render_matches_one_renderer() {
+        let fixtures: [&[u8]; 4] = [
+            include_bytes!("../tests/fixtures/rle-3-layer-8x8.psd"),
+            include_bytes!("../tests/fixtures/16x16-rle-partially-opaque.psd"),
+            include_bytes!("../tests/fixtures/negative-top-left-layer.psd"),
+            include_bytes!("../tests/fixtures/out-of-bounds-layer.psd"),
+        ];
+
+        for psd in fixtures.iter() {
+            let psd = Psd::from_bytes(psd).unwrap();
+
+            let (layers, nodes) = render_nodes(&psd, None, &|_| true);
+            let layer_cache = LayerRgbaCache::new();
+            let renderer = Renderer::new(
+                &psd,
+                &layers,
+                &nodes,
+                &layer_cache,
+                vec![None; layers.len()],
+            );
+            let mut expected = vec![];
+            for top in 0..psd.height() as usize {
+                for left in 0..psd.width() as usize {
+                    expected.extend_from_slice(&renderer.flattened_pixel((left, top)));
+                }
+            }
+
+            assert_eq!(psd.render().to_rgba().unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn longest_opaque_run_in_row() {
+        let row = [
+            [0, 0, 0, 255],
+            [0, 0, 0, 10],
+            [0, 0, 0, 255],
+            [0, 0, 0, 255],
+            [0, 0, 0, 0],
+        ]
+        .concat();
+
+        assert_eq!(longest_opaque_run(&row, 0, 4), Some((2, 3)));
+        assert_eq!(longest_opaque_run(&row, 0, 2), Some((0, 0)));
+        assert_eq!(longest_opaque_run(&row, 4, 4), None);
+    }
+}
@@ -0,0 +1,267 @@
+//! The inputs of the CPU renderer as a flat list of draw commands, for engines that composite
+//! the layers on the GPU themselves.
+
+use crate::render::RenderNode;
+use crate::sections::layer_and_mask_information_section::layer::BlendMode;
+use crate::{CropRect, LayerMask, Psd, PsdGroup, PsdLayer};
+
+/// What to draw, and in which order, to composite a PSD's layers.
+///
+/// Created by [`RenderPipeline::to_plan`]. The commands are in drawing order, from the bottom of
+/// the layer stack to the top. Groups that blend their contents on their own are wrapped in
+/// [`DrawCommand::BeginGroup`] and [`DrawCommand::EndGroup`], which is where a GPU renderer
+/// would render to and then blend an intermediate texture.
+///
+/// ```no_run
+/// # use psd::{DrawCommand, Psd};
+/// # let psd = Psd::from_bytes(&[]).unwrap();
+/// let plan = psd.render().visible_only().to_plan().unwrap();
+/// for command in plan.commands() {
+///     match command {
+///         DrawCommand::BeginGroup(group) => { /* Push a render target */ }
+///         DrawCommand::Layer(layer) => {
+///             if let Some(bounds) = layer.bounds() {
+///                 let texture = layer.rgba();
+///                 // Upload the texture and draw it at the bounds with the layer's blend mode,
+///                 // opacity and mask
+///             }
+///         }
+///         DrawCommand::EndGroup => { /* Blend the render target onto the one below it */ }
+///     }
+/// }
+/// ```
+///
+/// [`RenderPipeline::to_plan`]: crate::RenderPipeline::to_plan
+#[derive(Debug, Clone)]
+pub struct RenderPlan<'a> {
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) background: Option<[u8; 4]>,
+    pub(crate) commands: Vec<DrawCommand<'a>>,
+}
+
+/// One step of a [`RenderPlan`]
+#[derive(Debug, Clone)]
+pub enum DrawCommand<'a> {
+    /// Start compositing the commands up to the matching [`DrawCommand::EndGroup`] on their own
+    BeginGroup(GroupDraw<'a>),
+    /// Draw a layer onto the pixels below it
+    Layer(LayerDraw<'a>),
+    /// Blend the contents of the group that was begun last onto the pixels below it, with the
+    /// group's blend mode and opacity
+    EndGroup,
+}
+
+/// A group of a [`RenderPlan`] whose contents are composited on their own.
+///
+/// Groups that pass through with full opacity are the same as not having a group, so the plan
+/// leaves them out and only holds their layers.
+#[derive(Debug, Clone, Copy)]
+pub struct GroupDraw<'a> {
+    pub(crate) group: &'a PsdGroup,
+    pub(crate) blend_mode: BlendMode,
+    pub(crate) opacity: u8,
+}
+
+/// A layer of a [`RenderPlan`], along with everything that is needed to draw it.
+#[derive(Debug, Clone, Copy)]
+pub struct LayerDraw<'a> {
+    pub(crate) layer_idx: usize,
+    pub(crate) layer: &'a PsdLayer,
+    pub(crate) bounds: Option<CropRect>,
+}
+
+impl<'a> RenderPlan<'a> {
+    /// The width of the rendered image in pixels
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// The height of the rendered image in pixels
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The color to clear the image to before drawing, see [`RenderPipeline::background`]. The
+    /// image starts out transparent when this is `None`.
+    ///
+    /// [`RenderPipeline::background`]: crate::RenderPipeline::background
+    pub fn background(&self) -> Option<[u8; 4]> {
+        self.background
+    }
+
+    /// Every command, in drawing order
+    pub fn commands(&self) -> &[DrawCommand<'a>] {
+        &self.commands
+    }
+
+    /// The layers that get drawn, in drawing order, without the groups that they are in
+    pub fn layers(&self) -> impl Iterator<Item = &LayerDraw<'a>> {
+        self.commands.iter().filter_map(|command| match command {
+            DrawCommand::Layer(layer) => Some(layer),
+            _ => None,
+        })
+    }
+}
+
+impl<'a> GroupDraw<'a> {
+    /// The group
+    pub fn group(&self) -> &'a PsdGroup {
+        self.group
+    }
+
+    /// How the group's contents are blended onto the pixels below the group.
+    ///
+    /// [`BlendMode::PassThrough`] groups draw their contents straight onto the pixels below
+    /// them, and then fade the result back towards those pixels by the group's opacity.
+    pub fn blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+
+    /// The opacity of the group, from 0 to 255
+    pub fn opacity(&self) -> u8 {
+        self.opacity
+    }
+}
+
+impl<'a> LayerDraw<'a> {
+    /// The index of the layer in [`Psd::layers`]
+    ///
+    /// [`Psd::layers`]: crate::Psd::layers
+    pub fn layer_idx(&self) -> usize {
+        self.layer_idx
+    }
+
+    /// The layer
+    pub fn layer(&self) -> &'a PsdLayer {
+        self.layer
+    }
+
+    /// The part of the canvas that the layer covers, or `None` if the layer is empty or lies
+    /// entirely outside of the canvas. [`LayerDraw::rgba`] holds the pixels of this rectangle.
+    pub fn bounds(&self) -> Option<CropRect> {
+        self.bounds
+    }
+
+    /// The RGBA pixels of [`LayerDraw::bounds`], without the layer's masks or opacity applied,
+    /// or an empty vector if the layer doesn't have any bounds.
+    ///
+    /// Layers are decoded every time that this is called, so upload the pixels once and reuse
+    /// them.
+    pub fn rgba(&self) -> Vec<u8> {
+        match self.bounds {
+            Some(bounds) => self.layer.rgba_region(bounds).unwrap_or_default(),
+            None => vec![],
+        }
+    }
+
+    /// How the layer is blended onto the pixels below it
+    pub fn blend_mode(&self) -> BlendMode {
+        self.layer.blend_mode()
+    }
+
+    /// The opacity of the layer, from 0 to 255, which fades its pixels and its effects
+    pub fn opacity(&self) -> u8 {
+        self.layer.opacity()
+    }
+
+    /// The opacity of the layer's own pixels, from 0 to 255, which is multiplied with
+    /// [`LayerDraw::opacity`]
+    pub fn fill_opacity(&self) -> u8 {
+        self.layer.fill_opacity()
+    }
+
+    /// The layer's user and vector based masks, whose pixels are in
+    /// [`PsdLayer::mask_pixels`]
+    pub fn mask(&self) -> Option<&'a LayerMask> {
+        self.layer.mask()
+    }
+
+    /// Whether the layer is clipped to the nearest layer below it that isn't clipped, so that
+    /// it only shows where that layer does. The CPU renderer doesn't clip layers yet.
+    pub fn is_clipped(&self) -> bool {
+        !self.layer.is_clipping_mask()
+    }
+}
+
+/// Turn the nodes that the renderer would composite (top to bottom) into draw commands (bottom
+/// to top).
+pub(crate) fn render_plan<'a>(
+    psd: &'a Psd,
+    layers_to_flatten_top_down: &[(usize, &'a PsdLayer)],
+    nodes: &[RenderNode],
+    background: Option<[u8; 4]>,
+) -> RenderPlan<'a> {
+    let mut commands = vec![];
+    push_commands(psd, layers_to_flatten_top_down, nodes, &mut commands);
+
+    RenderPlan {
+        width: psd.width(),
+        height: psd.height(),
+        background,
+        commands,
+    }
+}
+
+fn push_commands<'a>(
+    psd: &'a Psd,
+    layers_to_flatten_top_down: &[(usize, &'a PsdLayer)],
+    nodes: &[RenderNode],
+    commands: &mut Vec<DrawCommand<'a>>,
+) {
+    for node in nodes.iter().rev() {
+        match node {
+            RenderNode::Layer(idx) => {
+                let (layer_idx, layer) = layers_to_flatten_top_down[*idx];
+                commands.push(DrawCommand::Layer(LayerDraw {
+                    layer_idx,
+                    layer,
+                    bounds: layer_bounds(psd, layer),
+                }));
+            }
+            RenderNode::Group {
+                id,
+                blend_mode,
+                opacity,
+                children,
+            } => {
+                commands.push(DrawCommand::BeginGroup(GroupDraw {
+                    group: &psd.groups()[id],
+                    blend_mode: *blend_mode,
+                    opacity: *opacity,
+                }));
+                push_commands(psd, layers_to_flatten_top_down, children, commands);
+                commands.push(DrawCommand::EndGroup);
+            }
+        }
+    }
+}
+
+/// The layer's rectangle clipped to the canvas
+fn layer_bounds(psd: &Psd, layer: &PsdLayer) -> Option<CropRect> {
+    if layer.is_empty() {
+        return None;
+    }
+
+    let left = layer.layer_left().max(0);
+    let top = layer.layer_top().max(0);
+    // The layer's right and bottom are inclusive
+    let right = layer
+        .layer_right()
+        .saturating_add(1)
+        .min(psd.width() as i32);
+    let bottom = layer
+        .layer_bottom()
+        .saturating_add(1)
+        .min(psd.height() as i32);
+    if right <= left || bottom <= top {
+        return None;
+    }
+
+    Some(CropRect::new(
+        left as u32,
+        top as u32,
+        (right - left) as u32,
+        (bottom - top) as u32,
+    ))
+}
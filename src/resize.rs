@@ -0,0 +1,233 @@
+//! Resampling the whole canvas to new dimensions.
+
+use crate::sections::layer_and_mask_information_section::layer_mask::MaskRect;
+
+/// How to work out the value of each pixel when resizing a PSD.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ResizeFilter {
+    /// Use the value of the nearest pixel, the same as Photoshop's "Nearest Neighbor". Keeps hard
+    /// edges, which suits pixel art.
+    NearestNeighbor,
+    /// Blend the values of the four nearest pixels, the same as Photoshop's "Bilinear". Halving
+    /// the size of an image averages each 2x2 block of pixels.
+    Bilinear,
+}
+
+/// Resampling a canvas from one width and height to another.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub(crate) struct Resize {
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) new_width: u32,
+    pub(crate) new_height: u32,
+    pub(crate) filter: ResizeFilter,
+}
+
+impl Resize {
+    fn scale_x(&self) -> f64 {
+        self.new_width as f64 / self.width as f64
+    }
+
+    fn scale_y(&self) -> f64 {
+        self.new_height as f64 / self.height as f64
+    }
+
+    /// Scale a length along the x axis, such as the width of an image
+    pub(crate) fn scale_width(&self, width: usize) -> usize {
+        (width as f64 * self.scale_x()).round() as usize
+    }
+
+    /// Scale a length along the y axis, such as the height of an image
+    pub(crate) fn scale_height(&self, height: usize) -> usize {
+        (height as f64 * self.scale_y()).round() as usize
+    }
+
+    /// Scale a rectangle of the canvas. Rectangles that had pixels keep at least one pixel.
+    pub(crate) fn rect(&self, rect: MaskRect) -> MaskRect {
+        let scale = |edge: i32, scale: f64| (edge as f64 * scale).round() as i32;
+
+        let top = scale(rect.top, self.scale_y());
+        let left = scale(rect.left, self.scale_x());
+
+        MaskRect {
+            top,
+            left,
+            bottom: scale(rect.bottom, self.scale_y()).max(top + 1),
+            right: scale(rect.right, self.scale_x()).max(left + 1),
+        }
+    }
+
+    /// Resample a plane of pixels that covers `rect` of the canvas into the scaled rectangle.
+    ///
+    /// Samples that are 1, 2 or 4 bytes are read as 8 bit, 16 bit and 32 bit float samples.
+    /// Other sizes always use the nearest pixel.
+    pub(crate) fn plane(&self, samples: &[u8], rect: MaskRect, bytes_per_pixel: usize) -> Vec<u8> {
+        let (width, height) = (rect.width() as usize, rect.height() as usize);
+        let scaled = self.rect(rect);
+        let (scaled_width, scaled_height) = (scaled.width() as usize, scaled.height() as usize);
+
+        // Where the center of each row and column of the scaled rectangle falls within the
+        // plane, in pixels
+        let source = |scaled_edge: i32, idx: usize, scale: f64, edge: i32| {
+            (scaled_edge as f64 + idx as f64 + 0.5) / scale - 0.5 - edge as f64
+        };
+        let xs: Vec<f64> = (0..scaled_width)
+            .map(|x| source(scaled.left, x, self.scale_x(), rect.left))
+            .collect();
+        let ys: Vec<f64> = (0..scaled_height)
+            .map(|y| source(scaled.top, y, self.scale_y(), rect.top))
+            .collect();
+
+        let is_numeric = [1, 2, 4].contains(&bytes_per_pixel);
+        let pixel = |x: usize, y: usize| {
+            let start = (y * width + x) * bytes_per_pixel;
+            &samples[start..start + bytes_per_pixel]
+        };
+
+        let mut resampled = Vec::with_capacity(scaled_width * scaled_height * bytes_per_pixel);
+        for y in ys.iter() {
+            for x in xs.iter() {
+                match self.filter {
+                    ResizeFilter::Bilinear if is_numeric => {
+                        let (x0, x1, x_weight) = neighbors(*x, width);
+                        let (y0, y1, y_weight) = neighbors(*y, height);
+
+                        let lerp = |a: f64, b: f64, weight: f64| a + (b - a) * weight;
+                        let top = lerp(
+                            read_sample(pixel(x0, y0)),
+                            read_sample(pixel(x1, y0)),
+                            x_weight,
+                        );
+                        let bottom = lerp(
+                            read_sample(pixel(x0, y1)),
+                            read_sample(pixel(x1, y1)),
+                            x_weight,
+                        );
+
+                        write_sample(&mut resampled, lerp(top, bottom, y_weight), bytes_per_pixel);
+                    }
+                    _ => {
+                        let nearest = |position: f64, len: usize| {
+                            ((position + 0.5).floor().max(0.) as usize).min(len - 1)
+                        };
+                        resampled.extend_from_slice(pixel(nearest(*x, width), nearest(*y, height)));
+                    }
+                }
+            }
+        }
+
+        resampled
+    }
+
+    /// Scale the position of a guide in 1/32nds of a pixel
+    pub(crate) fn guide(&self, position: i32, is_horizontal: bool) -> i32 {
+        let scale = if is_horizontal {
+            self.scale_y()
+        } else {
+            self.scale_x()
+        };
+
+        (position as f64 * scale).round() as i32
+    }
+}
+
+/// The two pixels on either side of a position along an axis of the given length, and how far the
+/// position is from the first of them. Positions past either end use the pixel at that end.
+fn neighbors(position: f64, len: usize) -> (usize, usize, f64) {
+    let position = position.max(0.).min((len - 1) as f64);
+    let first = position.floor() as usize;
+
+    (first, (first + 1).min(len - 1), position - first as f64)
+}
+
+fn read_sample(bytes: &[u8]) -> f64 {
+    match bytes.len() {
+        1 => bytes[0] as f64,
+        2 => u16::from_be_bytes([bytes[0], bytes[1]]) as f64,
+        _ => f32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64,
+    }
+}
+
+fn write_sample(samples: &mut Vec<u8>, value: f64, bytes_per_sample: usize) {
+    match bytes_per_sample {
+        1 => samples.push(value.round().clamp(0., u8::MAX as f64) as u8),
+        2 => samples
+            .extend_from_slice(&(value.round().clamp(0., u16::MAX as f64) as u16).to_be_bytes()),
+        _ => samples.extend_from_slice(&(value as f32).to_be_bytes()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resize(new_width: u32, new_height: u32, filter: ResizeFilter) -> Resize {
+        Resize {
+            width: 4,
+            height: 2,
+            new_width,
+            new_height,
+            filter,
+        }
+    }
+
+    fn rect(top: i32, left: i32, bottom: i32, right: i32) -> MaskRect {
+        MaskRect {
+            top,
+            left,
+            bottom,
+            right,
+        }
+    }
+
+    /// Verify that halving an image with the bilinear filter averages each 2x2 block, and that
+    /// the nearest neighbor filter picks one pixel from each block.
+    #[test]
+    fn halves_planes() {
+        // 0   10  20  30
+        // 40  50  60  70
+        let plane = [0, 10, 20, 30, 40, 50, 60, 70];
+
+        assert_eq!(
+            resize(2, 1, ResizeFilter::Bilinear).plane(&plane, rect(0, 0, 2, 4), 1),
+            vec![25, 45]
+        );
+        assert_eq!(
+            resize(2, 1, ResizeFilter::NearestNeighbor).plane(&plane, rect(0, 0, 2, 4), 1),
+            vec![50, 70]
+        );
+
+        // 16 bit samples
+        let plane: Vec<u8> = [0u16, 1000, 2000, 3000]
+            .iter()
+            .flat_map(|sample| sample.to_be_bytes().to_vec())
+            .collect();
+        assert_eq!(
+            resize(2, 2, ResizeFilter::Bilinear).plane(&plane, rect(0, 0, 1, 4), 2),
+            [500u16, 2500]
+                .iter()
+                .flat_map(|sample| sample.to_be_bytes().to_vec())
+                .collect::<Vec<u8>>()
+        );
+    }
+
+    /// Verify that doubling an image with the nearest neighbor filter repeats every pixel.
+    #[test]
+    fn doubles_planes() {
+        let plane = [1, 2, 3, 4];
+
+        assert_eq!(
+            resize(8, 4, ResizeFilter::NearestNeighbor).plane(&plane, rect(0, 0, 2, 2), 1),
+            vec![1, 1, 2, 2, 1, 1, 2, 2, 3, 3, 4, 4, 3, 3, 4, 4]
+        );
+    }
+
+    /// Verify that scaling rectangles keeps at least one pixel in rectangles that had pixels.
+    #[test]
+    fn scales_rects() {
+        let half = resize(2, 1, ResizeFilter::Bilinear);
+
+        assert_eq!(half.rect(rect(0, 2, 2, 4)), rect(0, 1, 1, 2));
+        assert_eq!(half.rect(rect(1, 1, 2, 2)), rect(1, 1, 2, 2));
+    }
+}
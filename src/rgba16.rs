@@ -0,0 +1,125 @@
+//! RGBA pixels with 16 bits per channel, so that the samples of 16 bit PSDs aren't mapped down to
+//! 8 bits.
+
+use crate::channel_integrity::decode_channel;
+use crate::{ColorMode, Psd, PsdChannelKind, PsdDepth, PsdLayer};
+
+/// The final image of the PSD, see [`Psd::rgba16`].
+pub(crate) fn psd_rgba16(psd: &Psd) -> Vec<u16> {
+    let color_mode = psd.color_mode();
+    if psd.depth() != PsdDepth::Sixteen
+        || !matches!(color_mode, ColorMode::Rgb | ColorMode::Grayscale)
+    {
+        return widen(&psd.rgba());
+    }
+
+    let image_data = &psd.image_data_section;
+    let channel_count = psd.file_header_section.channel_count.count();
+    let channels = image_data.samples(psd.width(), psd.depth(), channel_count);
+    let channel = |idx: usize| channels.get(idx).map(|channel| channel.as_slice());
+
+    let red = channel(0).unwrap_or(&[]);
+    let (green, blue) = match color_mode {
+        ColorMode::Grayscale => (red, red),
+        _ => (channel(1).unwrap_or(red), channel(2).unwrap_or(red)),
+    };
+    // The fourth channel is only used as alpha when there are exactly four channels
+    let alpha = match channel_count {
+        4 => channel(3),
+        _ => None,
+    };
+
+    let pixel_count = psd.width() as usize * psd.height() as usize;
+    let mut rgba = vec![0; pixel_count * 4];
+
+    for (idx, pixel) in rgba.chunks_exact_mut(4).enumerate() {
+        pixel[0] = sample(red, idx);
+        pixel[1] = sample(green, idx);
+        pixel[2] = sample(blue, idx);
+        pixel[3] = alpha.map(|alpha| sample(alpha, idx)).unwrap_or(u16::MAX);
+    }
+
+    rgba
+}
+
+/// The pixels of the layer, the size of the PSD, see [`PsdLayer::rgba16`].
+pub(crate) fn layer_rgba16(layer: &PsdLayer) -> Vec<u16> {
+    if layer.is_empty() {
+        return vec![];
+    }
+
+    let is_cmyk = layer.channels.contains_key(&PsdChannelKind::Black);
+    if layer.depth != PsdDepth::Sixteen || is_cmyk {
+        return widen(&layer.rgba());
+    }
+
+    let decode = |kind: PsdChannelKind| match layer.channels.get(&kind) {
+        Some(channel_bytes) => decode_channel(layer, kind, channel_bytes, layer.depth)
+            .map(Some)
+            .map_err(|_| ()),
+        None => Ok(None),
+    };
+    let channels = (
+        decode(PsdChannelKind::Red),
+        decode(PsdChannelKind::Green),
+        decode(PsdChannelKind::Blue),
+        decode(PsdChannelKind::TransparencyMask),
+    );
+    // Channels that can't be decoded are handled the same way that the 8 bit pixels handle them
+    let (red, green, blue, alpha) = match channels {
+        (Ok(Some(red)), Ok(green), Ok(blue), Ok(alpha)) => (red, green, blue, alpha),
+        _ => return widen(&layer.rgba()),
+    };
+
+    // A single channel is a grayscale layer
+    let green = green.as_deref().unwrap_or(&red);
+    let blue = blue.as_deref().unwrap_or(&red);
+
+    let (psd_width, psd_height) = (layer.psd_width as i32, layer.psd_height as i32);
+    let mut rgba = vec![0; psd_width as usize * psd_height as usize * 4];
+
+    let (width, height) = (layer.width() as usize, layer.height() as usize);
+    for idx in 0..width * height {
+        let left = layer.layer_left + (idx % width) as i32;
+        let top = layer.layer_top + (idx / width) as i32;
+        if left < 0 || left >= psd_width || top < 0 || top >= psd_height {
+            continue;
+        }
+
+        let pixel_idx = (top * psd_width + left) as usize * 4;
+        let pixel = &mut rgba[pixel_idx..pixel_idx + 4];
+        pixel[0] = sample(&red, idx);
+        pixel[1] = sample(green, idx);
+        pixel[2] = sample(blue, idx);
+        pixel[3] = alpha
+            .as_ref()
+            .map(|alpha| sample(alpha, idx))
+            .unwrap_or(u16::MAX);
+    }
+
+    rgba
+}
+
+/// The big endian 16 bit sample of a pixel, or 0 if the channel is too short
+fn sample(channel: &[u8], idx: usize) -> u16 {
+    match channel.get(idx * 2..idx * 2 + 2) {
+        Some(bytes) => u16::from_be_bytes([bytes[0], bytes[1]]),
+        None => 0,
+    }
+}
+
+/// Map 8 bit values onto the full 16 bit range, so that 255 becomes 65535.
+fn widen(rgba: &[u8]) -> Vec<u16> {
+    rgba.iter().map(|value| *value as u16 * 257).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 8 bit values are spread over the whole 16 bit range.
+    #[test]
+    fn widen_8_bit() {
+        assert_eq!(widen(&[0, 1, 128, 255]), vec![0, 257, 32896, 65535]);
+    }
+}
@@ -0,0 +1,180 @@
+//! Writing RGBA pixels into buffers that the caller owns, such as mapped GPU staging buffers whose
+//! rows are padded for alignment.
+
+use crate::PsdError;
+
+/// Where each pixel of an image goes in a buffer of RGBA pixels.
+///
+/// Pixels are written as lines of 4 byte pixels, either rows (left to right, with the rows from
+/// top to bottom) or columns (top to bottom, with the columns from left to right). The stride is
+/// the number of bytes from the start of one line to the start of the next, so it can be larger
+/// than the line itself when the buffer's lines need to be aligned. The padding between lines is
+/// left untouched.
+///
+/// ```
+/// # use psd::RgbaLayout;
+/// // A 3 pixel wide image whose rows are aligned to 256 bytes
+/// let layout = RgbaLayout::row_major(256);
+/// assert_eq!(layout.buffer_len(3, 2), 256 + 3 * 4);
+/// ```
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct RgbaLayout {
+    stride: usize,
+    column_major: bool,
+}
+
+impl RgbaLayout {
+    /// Rows of pixels, with `stride` bytes from the start of one row to the start of the next
+    pub fn row_major(stride: usize) -> RgbaLayout {
+        RgbaLayout {
+            stride,
+            column_major: false,
+        }
+    }
+
+    /// Columns of pixels, with `stride` bytes from the start of one column to the start of the
+    /// next. Useful when the image will be read a column at a time, such as when rotating it.
+    pub fn column_major(stride: usize) -> RgbaLayout {
+        RgbaLayout {
+            stride,
+            column_major: true,
+        }
+    }
+
+    /// Rows of pixels without any padding, the same as the vectors that methods such as
+    /// [`Psd::rgba`] return.
+    ///
+    /// [`Psd::rgba`]: crate::Psd::rgba
+    pub fn packed(width: u32) -> RgbaLayout {
+        RgbaLayout::row_major(width as usize * 4)
+    }
+
+    /// The number of bytes from the start of one line to the start of the next
+    pub fn stride(&self) -> usize {
+        self.stride
+    }
+
+    /// Whether the pixels are written in columns rather than rows
+    pub fn is_column_major(&self) -> bool {
+        self.column_major
+    }
+
+    /// The smallest number of bytes that a buffer needs to hold an image of the given size.
+    ///
+    /// The last line isn't padded, so this is less than `stride` times the number of lines when
+    /// the stride is larger than a line.
+    pub fn buffer_len(&self, width: u32, height: u32) -> usize {
+        let (line_count, line_len) = self.lines(width, height);
+        if line_count == 0 || line_len == 0 {
+            return 0;
+        }
+
+        self.stride * (line_count - 1) + line_len
+    }
+
+    /// Make sure that an image of the given size fits in a buffer of `len` bytes, with lines that
+    /// don't overlap.
+    pub(crate) fn check(&self, width: u32, height: u32, len: usize) -> Result<(), PsdError> {
+        let (line_count, line_len) = self.lines(width, height);
+        let overlaps = line_count > 1 && self.stride < line_len;
+
+        if overlaps || len < self.buffer_len(width, height) {
+            return Err(PsdError::RgbaBufferTooSmall {
+                width,
+                height,
+                stride: self.stride,
+                len,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// The index in the buffer of the first byte of the pixel
+    pub(crate) fn offset(&self, x: usize, y: usize) -> usize {
+        if self.column_major {
+            x * self.stride + y * 4
+        } else {
+            y * self.stride + x * 4
+        }
+    }
+
+    /// Copy tightly packed rows of RGBA pixels into the buffer.
+    pub(crate) fn write(
+        &self,
+        rgba: &[u8],
+        width: u32,
+        height: u32,
+        out: &mut [u8],
+    ) -> Result<(), PsdError> {
+        self.check(width, height, out.len())?;
+
+        let width = width as usize;
+        if !self.column_major {
+            for (y, row) in rgba.chunks_exact(width.max(1) * 4).enumerate() {
+                let start = self.offset(0, y);
+                out[start..start + row.len()].copy_from_slice(row);
+            }
+            return Ok(());
+        }
+
+        for (idx, pixel) in rgba.chunks_exact(4).enumerate() {
+            let start = self.offset(idx % width, idx / width);
+            out[start..start + 4].copy_from_slice(pixel);
+        }
+
+        Ok(())
+    }
+
+    /// The (number of lines, length of each line in bytes) of an image of the given size
+    fn lines(&self, width: u32, height: u32) -> (usize, usize) {
+        let (width, height) = (width as usize, height as usize);
+
+        if self.column_major {
+            (width, height * 4)
+        } else {
+            (height, width * 4)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 2x2 image of the pixels [1; 4], [2; 4], [3; 4] and [4; 4]
+    fn two_by_two() -> Vec<u8> {
+        [[1; 4], [2; 4], [3; 4], [4; 4]].concat()
+    }
+
+    /// Verify that padded rows leave the padding untouched.
+    #[test]
+    fn row_major_stride() {
+        let layout = RgbaLayout::row_major(12);
+        assert_eq!(layout.buffer_len(2, 2), 20);
+
+        let mut out = vec![9; 20];
+        layout.write(&two_by_two(), 2, 2, &mut out).unwrap();
+        assert_eq!(out, [[1; 4], [2; 4], [9; 4], [3; 4], [4; 4]].concat());
+    }
+
+    /// Verify that columns hold the pixels from top to bottom.
+    #[test]
+    fn column_major() {
+        let layout = RgbaLayout::column_major(8);
+
+        let mut out = vec![0; 16];
+        layout.write(&two_by_two(), 2, 2, &mut out).unwrap();
+        assert_eq!(out, [[1; 4], [3; 4], [2; 4], [4; 4]].concat());
+    }
+
+    /// Buffers that are too short, or whose stride would make rows overlap, are rejected.
+    #[test]
+    fn too_small() {
+        assert!(RgbaLayout::row_major(8).check(2, 2, 15).is_err());
+        assert!(RgbaLayout::row_major(4).check(2, 2, 64).is_err());
+        // A single row can't overlap with another
+        assert!(RgbaLayout::row_major(0).check(2, 1, 8).is_ok());
+        assert!(RgbaLayout::packed(2).check(2, 2, 16).is_ok());
+    }
+}
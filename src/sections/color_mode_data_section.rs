@@ -0,0 +1,52 @@
+/// The number of colors in an indexed color image's color table
+const PALETTE_LEN: usize = 256;
+
+/// Read the color table of an indexed color image from the color mode data section (without its
+/// length marker).
+///
+/// # [Adobe Docs](https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/)
+///
+/// Only indexed color and duotone (see the mode field in the File header section) have color
+/// mode data. For all other modes, this section is just the 4-byte length field, which is set to
+/// zero.
+///
+/// Indexed color images: length is 768; color data contains the color table for the image, in
+/// non-interleaved order.
+///
+/// Returns `None` if the data is too short to hold a color table.
+pub(crate) fn read_palette(color_mode_data: &[u8]) -> Option<[[u8; 3]; PALETTE_LEN]> {
+    if color_mode_data.len() < PALETTE_LEN * 3 {
+        return None;
+    }
+
+    // Every red, followed by every green, followed by every blue
+    let (reds, rest) = color_mode_data.split_at(PALETTE_LEN);
+    let (greens, blues) = rest.split_at(PALETTE_LEN);
+
+    let mut palette = [[0; 3]; PALETTE_LEN];
+    for (idx, color) in palette.iter_mut().enumerate() {
+        *color = [reds[idx], greens[idx], blues[idx]];
+    }
+
+    Some(palette)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Verify that we read the non-interleaved color table and reject truncated data.
+    #[test]
+    fn reads_palette() {
+        let mut data = vec![0; 768];
+        data[1] = 10;
+        data[256 + 1] = 20;
+        data[512 + 1] = 30;
+
+        let palette = read_palette(&data).unwrap();
+        assert_eq!(palette[0], [0, 0, 0]);
+        assert_eq!(palette[1], [10, 20, 30]);
+
+        assert_eq!(read_palette(&data[..767]), None);
+    }
+}
@@ -1,3 +1,4 @@
+use crate::sections::serialize::{PsdBuffer, PsdSerialize};
 use crate::sections::PsdCursor;
 use thiserror::Error;
 
@@ -5,6 +6,8 @@ use thiserror::Error;
 pub const EXPECTED_PSD_SIGNATURE: [u8; 4] = [56, 66, 80, 83];
 /// Bytes representing the number 1
 const EXPECTED_VERSION: [u8; 2] = [0, 1];
+/// Bytes representing the number 2, the version of a PSB (large document format) file
+const EXPECTED_PSB_VERSION: [u8; 2] = [0, 2];
 /// Bytes representing the Reserved section of the header
 const EXPECTED_RESERVED: [u8; 6] = [0; 6];
 
@@ -30,18 +33,22 @@ const EXPECTED_RESERVED: [u8; 6] = [0; 6];
 /// | 4      | The width of the image in pixels. Supported range is 1 to 30,000.<br> (**PSB** max of 300,000)                                                       |
 /// | 2      | Depth: the number of bits per channel. Supported values are 1, 8, 16 and 32.                                                                         |
 /// | 2      | The color mode of the file. Supported values are: Bitmap = 0; Grayscale = 1; Indexed = 2; RGB = 3; CMYK = 4; Multichannel = 7; Duotone = 8; Lab = 9. |
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct FileHeaderSection {
-    pub(in crate) version: PsdVersion,
-    pub(in crate) channel_count: ChannelCount,
-    pub(in crate) width: PsdWidth,
-    pub(in crate) height: PsdHeight,
-    pub(in crate) depth: PsdDepth,
-    pub(in crate) color_mode: ColorMode,
+    pub(crate) version: PsdVersion,
+    pub(crate) channel_count: ChannelCount,
+    pub(crate) width: PsdWidth,
+    pub(crate) height: PsdHeight,
+    pub(crate) depth: PsdDepth,
+    pub(crate) color_mode: ColorMode,
 }
 
 /// Represents an malformed file section header
-#[derive(Debug, PartialEq, Error)]
+///
+/// New variants may be added in any release, see [`FileHeaderSectionError::code`].
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[allow(missing_docs)]
+#[non_exhaustive]
 pub enum FileHeaderSectionError {
     #[error("A file section header is comprised of 26 bytes, you provided {length} bytes.")]
     IncorrectLength { length: usize },
@@ -51,21 +58,44 @@ pub enum FileHeaderSectionError {
     )]
     InvalidSignature {},
     #[error(
-        r#"Bytes 5 and 6 (indices 4-5) must always be [0, 1], Representing a PSD version of 1."#
+        r#"Bytes 5 and 6 (indices 4-5) must always be [0, 1] or [0, 2], Representing a PSD version of 1
+        or a PSB version of 2."#
     )]
     InvalidVersion {},
     #[error(r#"Bytes 7-12 (indices 6-11) must be zeroes"#)]
     InvalidReserved {},
     #[error("Invalid channel count: {channel_count}. Must be 1 <= channel count <= 56")]
     ChannelCountOutOfRange { channel_count: u8 },
-    #[error("Invalid width: {width}. Must be 1 <= width <= 30,000")]
+    #[error("Invalid width: {width}. Must be 1 <= width <= 30,000 (300,000 for PSB)")]
     WidthOutOfRange { width: u32 },
-    #[error("Invalid height: {height}. Must be 1 <= height <= 30,000")]
+    #[error("Invalid height: {height}. Must be 1 <= height <= 30,000 (300,000 for PSB)")]
     HeightOutOfRange { height: u32 },
     #[error("Depth {depth} is invalid. Must be 1, 8, 16 or 32")]
     InvalidDepth { depth: u8 },
     #[error("Invalid color mode {color_mode}. Must be 0, 1, 2, 3, 4, 7, 8 or 9")]
     InvalidColorMode { color_mode: u8 },
+    #[error("The file ends before the end of its {section} section")]
+    TruncatedSection { section: &'static str },
+}
+
+impl FileHeaderSectionError {
+    /// A stable code that identifies the kind of error, see [`crate::PsdError::code`].
+    pub fn code(&self) -> &'static str {
+        match self {
+            FileHeaderSectionError::IncorrectLength { .. } => "header.incorrect_length",
+            FileHeaderSectionError::InvalidSignature {} => "header.invalid_signature",
+            FileHeaderSectionError::InvalidVersion {} => "header.invalid_version",
+            FileHeaderSectionError::InvalidReserved {} => "header.invalid_reserved",
+            FileHeaderSectionError::ChannelCountOutOfRange { .. } => {
+                "header.channel_count_out_of_range"
+            }
+            FileHeaderSectionError::WidthOutOfRange { .. } => "header.width_out_of_range",
+            FileHeaderSectionError::HeightOutOfRange { .. } => "header.height_out_of_range",
+            FileHeaderSectionError::InvalidDepth { .. } => "header.invalid_depth",
+            FileHeaderSectionError::InvalidColorMode { .. } => "header.invalid_color_mode",
+            FileHeaderSectionError::TruncatedSection { .. } => "header.truncated_section",
+        }
+    }
 }
 
 impl FileHeaderSection {
@@ -81,8 +111,7 @@ impl FileHeaderSection {
         if bytes.len() != 26 {
             return Err(FileHeaderSectionError::IncorrectLength {
                 length: bytes.len(),
-            }
-            );
+            });
         }
 
         // First four bytes must be '8BPS'
@@ -92,10 +121,11 @@ impl FileHeaderSection {
         }
 
         // The next 2 bytes represent the version
-        let version = cursor.read_2();
-        if version != EXPECTED_VERSION {
-            return Err(FileHeaderSectionError::InvalidVersion {});
-        }
+        let version = match cursor.read_2() {
+            v if v == EXPECTED_VERSION => PsdVersion::One,
+            v if v == EXPECTED_PSB_VERSION => PsdVersion::Two,
+            _ => return Err(FileHeaderSectionError::InvalidVersion {}),
+        };
 
         // The next 6 bytes are reserved and should always be 0
         let reserved = cursor.read_6();
@@ -110,13 +140,13 @@ impl FileHeaderSection {
 
         // 4 bytes for the height
         let height = cursor.read_u32();
-        let height =
-            PsdHeight::new(height).ok_or(FileHeaderSectionError::HeightOutOfRange { height })?;
+        let height = PsdHeight::new(height, version)
+            .ok_or(FileHeaderSectionError::HeightOutOfRange { height })?;
 
         // 4 bytes for the width
         let width = cursor.read_u32();
-        let width =
-            PsdWidth::new(width).ok_or(FileHeaderSectionError::WidthOutOfRange { width })?;
+        let width = PsdWidth::new(width, version)
+            .ok_or(FileHeaderSectionError::WidthOutOfRange { width })?;
 
         // 2 bytes for depth
         let depth = cursor.read_2()[1];
@@ -128,7 +158,7 @@ impl FileHeaderSection {
             .ok_or(FileHeaderSectionError::InvalidColorMode { color_mode })?;
 
         let file_header_section = FileHeaderSection {
-            version: PsdVersion::One,
+            version,
             channel_count,
             width,
             height,
@@ -140,15 +170,67 @@ impl FileHeaderSection {
     }
 }
 
+impl PsdSerialize for FileHeaderSection {
+    /// The inverse of `FileHeaderSection::from_bytes`
+    fn write(&self, buffer: &mut PsdBuffer) {
+        buffer.write(&EXPECTED_PSD_SIGNATURE);
+        buffer.write(match self.version {
+            PsdVersion::One => &EXPECTED_VERSION,
+            PsdVersion::Two => &EXPECTED_PSB_VERSION,
+        });
+        buffer.write(&EXPECTED_RESERVED);
+        buffer.write_u16(self.channel_count.count() as u16);
+        buffer.write_u32(self.height.0);
+        buffer.write_u32(self.width.0);
+        buffer.write_u16(self.depth as u16);
+        buffer.write_u16(self.color_mode as u16);
+    }
+}
+
+/// The largest file that can be written as a PSD rather than a PSB, 2 GB
+const MAX_PSD_FILE_LEN: usize = i32::MAX as usize;
+
 /// # [Adobe Docs](https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/)
 ///
 /// Version: always equal to 1. Do not try to read the file if the version does not match this value. (**PSB** version is 2.)
 ///
 /// via: https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
 pub enum PsdVersion {
     /// Regular PSD (Not a PSB)
     One,
+    /// Large document format (PSB)
+    Two,
+}
+
+impl PsdVersion {
+    /// Whether or not this is a large document format (PSB) file.
+    ///
+    /// Many length markers are 8 bytes instead of 4 bytes in PSB files.
+    pub fn is_psb(self) -> bool {
+        self == PsdVersion::Two
+    }
+
+    /// The maximum width and height of a document of this version
+    fn max_dimension(self) -> u32 {
+        match self {
+            PsdVersion::One => 30_000,
+            PsdVersion::Two => 300_000,
+        }
+    }
+
+    /// Whether a document of this size, whose file is `file_len` bytes long, can be written as
+    /// this version. PSD files can't be larger than 2 GB.
+    pub(crate) fn fits(self, width: u32, height: u32, file_len: usize) -> bool {
+        match self {
+            PsdVersion::One => {
+                width <= self.max_dimension()
+                    && height <= self.max_dimension()
+                    && file_len <= MAX_PSD_FILE_LEN
+            }
+            PsdVersion::Two => true,
+        }
+    }
 }
 
 /// # [Adobe Docs](https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/)
@@ -156,7 +238,7 @@ pub enum PsdVersion {
 /// The number of channels in the image, including any alpha channels. Supported range is 1 to 56.
 ///
 /// via: https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
 pub struct ChannelCount(u8);
 
 impl ChannelCount {
@@ -181,13 +263,13 @@ impl ChannelCount {
 /// (**PSB** max of 300,000.)
 ///
 /// via: https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/
-#[derive(Debug)]
-pub struct PsdHeight(pub(in crate) u32);
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct PsdHeight(pub(crate) u32);
 
 impl PsdHeight {
     /// Create a new PsdHeight
-    pub fn new(height: u32) -> Option<PsdHeight> {
-        if height < 1 || height > 30000 {
+    pub fn new(height: u32, version: PsdVersion) -> Option<PsdHeight> {
+        if height < 1 || height > version.max_dimension() {
             return None;
         }
 
@@ -201,13 +283,13 @@ impl PsdHeight {
 /// (*PSB** max of 300,000)
 ///
 /// via: https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/
-#[derive(Debug, Clone, Copy)]
-pub struct PsdWidth(pub(in crate) u32);
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct PsdWidth(pub(crate) u32);
 
 impl PsdWidth {
     /// Create a new PsdWidth
-    pub fn new(width: u32) -> Option<PsdWidth> {
-        if width < 1 || width > 30000 {
+    pub fn new(width: u32, version: PsdVersion) -> Option<PsdWidth> {
+        if width < 1 || width > version.max_dimension() {
             return None;
         }
 
@@ -220,7 +302,7 @@ impl PsdWidth {
 /// Depth: the number of bits per channel. Supported values are 1, 8, 16 and 32.
 ///
 /// via: https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/
-#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
 #[allow(missing_docs)]
 pub enum PsdDepth {
     One = 1,
@@ -247,7 +329,7 @@ impl PsdDepth {
 /// The color mode of the file. Supported values are: Bitmap = 0; Grayscale = 1; Indexed = 2; RGB = 3; CMYK = 4; Multichannel = 7; Duotone = 8; Lab = 9.
 ///
 /// via: https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 #[allow(missing_docs)]
 pub enum ColorMode {
     Bitmap = 0,
@@ -336,6 +418,14 @@ mod tests {
         };
     }
 
+    // PSB files can be up to 300,000 pixels wide, PSD files only 30,000
+    #[test]
+    fn psb_dimensions() {
+        assert!(PsdWidth::new(30_001, PsdVersion::One).is_none());
+        assert!(PsdWidth::new(30_001, PsdVersion::Two).is_some());
+        assert!(PsdHeight::new(300_001, PsdVersion::Two).is_none());
+    }
+
     #[test]
     fn invalid_reserved_section() {
         let mut bytes = make_bytes();
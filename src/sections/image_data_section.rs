@@ -1,21 +1,38 @@
-use crate::psd_channel::PsdChannelCompression;
+use crate::psd_channel::{
+    for_each_channel_row, rle_decompress, samples_to_eight_bits, zip_decompress,
+    PsdChannelCompression,
+};
+use crate::sections::file_header_section::PsdVersion;
+use crate::sections::serialize::PsdBuffer;
 use crate::sections::PsdCursor;
+use crate::shared_bytes::SharedBytes;
 use crate::PsdDepth;
 use thiserror::Error;
 
 /// Represents an malformed image data
-#[derive(Debug, PartialEq, Error)]
+///
+/// New variants may be added in any release, see [`ImageDataSectionError::code`].
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[allow(missing_docs)]
+#[non_exhaustive]
 pub enum ImageDataSectionError {
-    #[error(
-        r#"Only 8 and 16 bit depths are supported at the moment.
-    If you'd like to see 1 and 32 bit depths supported - please open an issue."#
-    )]
-    UnsupportedDepth,
+    #[error("The ZIP compressed image data is not a valid zlib stream")]
+    InvalidZipData,
 
     #[error("{compression} is an invalid layer channel compression. Must be 0, 1, 2 or 3")]
     InvalidCompression { compression: u16 },
 }
 
+impl ImageDataSectionError {
+    /// A stable code that identifies the kind of error, see [`crate::PsdError::code`].
+    pub fn code(&self) -> &'static str {
+        match self {
+            ImageDataSectionError::InvalidZipData => "image_data.invalid_zip_data",
+            ImageDataSectionError::InvalidCompression { .. } => "image_data.invalid_compression",
+        }
+    }
+}
+
 /// The ImageDataSection comes from the final section in the PSD that contains the pixel data
 /// of the final PSD image (the one that comes from combining all of the layers).
 ///
@@ -29,7 +46,7 @@ pub enum ImageDataSectionError {
 /// |----------|--------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------------|
 /// | 2        | Compression method: <br> 0 = Raw image data <br> 1 = RLE compressed the image data starts with the byte counts for all the scan lines (rows * channels), with each count stored as a two-byte value. The RLE compressed data follows, with each scan line compressed separately. The RLE compression is the same compression algorithm used by the Macintosh ROM routine PackBits , and the TIFF standard. <br> 2 = ZIP without prediction <br> 3 = ZIP with prediction. |
 /// | Variable | The image data. Planar order = RRR GGG BBB, etc.                                                                                                                                                                                                                                                                                                                                                                                                                         |
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct ImageDataSection {
     /// The compression method for the image.
     pub(crate) compression: PsdChannelCompression,
@@ -42,6 +59,15 @@ pub struct ImageDataSection {
     /// the alpha channel of the final image.
     /// If there is no alpha channel then it is a fully opaque image.
     pub(crate) alpha: Option<ChannelBytes>,
+    /// The channels that we don't use when generating the final image, such as the fourth and
+    /// fifth channels of an image with five channels.
+    pub(crate) extra_channels: Vec<ChannelBytes>,
+    /// The compressed length of every scanline of every channel, if the image is RLE compressed.
+    pub(crate) scanline_byte_counts: Vec<u32>,
+    /// The image data as it was read, if we had to decode the channels up front because the
+    /// image is ZIP compressed, raw 16 bit or 1 bit. We need it when writing the image back out
+    /// and for the original 16 bit samples.
+    pub(crate) encoded_data: Option<Vec<u8>>,
 }
 
 impl ImageDataSection {
@@ -50,6 +76,8 @@ impl ImageDataSection {
     pub fn from_bytes(
         bytes: &[u8],
         depth: PsdDepth,
+        version: PsdVersion,
+        psd_width: u32,
         psd_height: u32,
         channel_count: u8,
     ) -> Result<ImageDataSection, ImageDataSectionError> {
@@ -60,61 +88,18 @@ impl ImageDataSection {
         let compression = PsdChannelCompression::new(compression)
             .ok_or(ImageDataSectionError::InvalidCompression { compression })?;
 
-        let (red, green, blue, alpha) = match compression {
+        let mut scanline_byte_counts = vec![];
+        let mut encoded_data = None;
+
+        let mut channels: Vec<ChannelBytes> = match compression {
+            // First 2 bytes were compression bytes
             PsdChannelCompression::RawData => {
-                // First 2 bytes were compression bytes
-                let channel_bytes = &bytes[2..];
-                let channel_byte_count = channel_bytes.len();
-
-                let bytes_per_channel = channel_byte_count / channel_count;
-
-                // First bytes are red
-                let mut red = channel_bytes[..bytes_per_channel].into();
-
-                // Next bytes are green
-                let green = if channel_count >= 2 {
-                    Some(ChannelBytes::RawData(
-                        channel_bytes[bytes_per_channel..2 * bytes_per_channel].into(),
-                    ))
-                } else {
-                    None
-                };
-
-                // Then comes blue
-                let blue = if channel_count >= 3 {
-                    Some(ChannelBytes::RawData(
-                        channel_bytes[2 * bytes_per_channel..3 * bytes_per_channel].into(),
-                    ))
-                } else {
-                    None
-                };
-
-                // And optionally alpha bytes
-                let alpha = if channel_count == 4 {
-                    Some(ChannelBytes::RawData(
-                        channel_bytes[3 * bytes_per_channel..4 * bytes_per_channel].to_vec(),
-                    ))
-                } else {
-                    None
-                };
-
-                match depth {
-                    PsdDepth::Eight => (ChannelBytes::RawData(red), green, blue, alpha),
-                    // If this is a 16bit image there will be two bytes per pixel. We
-                    // currently only support one byte per pixel so we convert the 2 bytes
-                    // back down into 1 byte by mapping 0-65535 down to 0-255
-                    PsdDepth::Sixteen => {
-                        for idx in 0..red.len() / 2 {
-                            let bytes = [red[2 * idx], red[2 * idx + 1]];
-                            let bits16 = u16::from_be_bytes(bytes);
-                            red[idx] = (bits16 / 256) as u8;
-                        }
-                        red.truncate(red.len() / 2);
-
-                        (ChannelBytes::RawData(red), green, blue, alpha)
-                    }
-                    _ => return Err(ImageDataSectionError::UnsupportedDepth),
+                // The channels are mapped down to 8 bits, so we hold on to the original samples
+                if depth == PsdDepth::Sixteen {
+                    encoded_data = Some(bytes[2..].to_vec());
                 }
+
+                split_raw_channels(&bytes[2..], depth, channel_count)
             }
             // # [Adobe Docs](https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/)
             //
@@ -124,103 +109,247 @@ impl ImageDataSection {
             // the same compression algorithm used by the Macintosh ROM routine PackBits,
             // and the TIFF standard.
             PsdChannelCompression::RleCompressed => {
-                let mut red_byte_count = 0;
-                let mut green_byte_count = if channel_count >= 2 { Some(0) } else { None };
-                let mut blue_byte_count = if channel_count >= 3 { Some(0) } else { None };
-                let mut alpha_byte_count = if channel_count == 4 { Some(0) } else { None };
-
-                for _ in 0..psd_height {
-                    red_byte_count += cursor.read_u16() as usize;
+                // 2 bytes (4 bytes for PSB) for each scanline of each channel. We hold on to
+                // them to decode each row on its own and to write the image back out.
+                for _ in 0..channel_count * psd_height as usize {
+                    scanline_byte_counts.push(cursor.read_rle_byte_count(version));
                 }
 
-                if let Some(ref mut green_byte_count) = green_byte_count {
-                    for _ in 0..psd_height {
-                        *green_byte_count += cursor.read_u16() as usize;
-                    }
-                }
+                let mut channel_start = cursor.position() as usize;
 
-                if let Some(ref mut blue_byte_count) = blue_byte_count {
-                    for _ in 0..psd_height {
-                        *blue_byte_count += cursor.read_u16() as usize;
-                    }
-                }
+                scanline_byte_counts
+                    .chunks(psd_height as usize)
+                    .map(|channel_byte_counts| {
+                        let byte_count: usize = channel_byte_counts
+                            .iter()
+                            .map(|count| *count as usize)
+                            .sum();
 
-                if let Some(ref mut alpha_byte_count) = alpha_byte_count {
-                    for _ in 0..psd_height {
-                        *alpha_byte_count += cursor.read_u16() as usize;
-                    }
-                }
+                        let channel_end = channel_start + byte_count;
+                        let channel =
+                            ChannelBytes::RleCompressed(bytes[channel_start..channel_end].into());
+                        channel_start = channel_end;
+
+                        channel
+                    })
+                    .collect()
+            }
+            // All of the channels are compressed together in a single zlib stream
+            PsdChannelCompression::ZipWithoutPrediction
+            | PsdChannelCompression::ZipWithPrediction => {
+                let compressed = &bytes[2..];
+                let decompressed =
+                    zip_decompress(compressed, compression, psd_width as usize, depth)
+                        .ok_or(ImageDataSectionError::InvalidZipData)?;
+                encoded_data = Some(compressed.to_vec());
 
-                // 2 bytes for compression level, then 2 bytes for each scanline of each channel
-                // We're skipping over the bytes that describe the length of each scanling since
-                // we don't currently use them. We might re-think this in the future when we
-                // implement serialization of a Psd back into bytes.. But not a concern at the
-                // moment.
-                let channel_data_start = 2 + (channel_count * psd_height as usize * 2);
-
-                let (red_start, red_end) =
-                    (channel_data_start, channel_data_start + red_byte_count);
-
-                let red = bytes[red_start..red_end].into();
-
-                let green = match green_byte_count {
-                    Some(green_byte_count) => {
-                        let green_start = red_end;
-                        let green_end = green_start + green_byte_count;
-                        Some(ChannelBytes::RleCompressed(
-                            bytes[green_start..green_end].into(),
-                        ))
-                    }
-                    None => None,
-                };
-
-                let blue = match blue_byte_count {
-                    Some(blue_byte_count) => {
-                        let blue_start = red_end + green_byte_count.unwrap();
-                        let blue_end = blue_start + blue_byte_count;
-                        Some(ChannelBytes::RleCompressed(
-                            bytes[blue_start..blue_end].into(),
-                        ))
-                    }
-                    None => None,
-                };
-
-                let alpha = match alpha_byte_count {
-                    Some(alpha_byte_count) => {
-                        let alpha_start =
-                            red_end + green_byte_count.unwrap() + blue_byte_count.unwrap();
-                        let alpha_end = alpha_start + alpha_byte_count;
-                        Some(ChannelBytes::RleCompressed(
-                            bytes[alpha_start..alpha_end].into(),
-                        ))
-                    }
-                    None => None,
-                };
-
-                (ChannelBytes::RleCompressed(red), green, blue, alpha)
+                split_raw_channels(&decompressed, depth, channel_count)
             }
-            PsdChannelCompression::ZipWithoutPrediction => unimplemented!(
-                r#"Zip without prediction compression is currently unsupported.
-                Please open an issue"#
-            ),
-            PsdChannelCompression::ZipWithPrediction => unimplemented!(
-                r#"Zip with prediction compression is currently unsupported.
-                Please open an issue"#
-            ),
         };
 
+        // Each bit is a pixel, so we expand them into bytes
+        if depth == PsdDepth::One {
+            channels = channels
+                .iter()
+                .map(|channel| {
+                    ChannelBytes::RawData(unpack_bits(channel, psd_width, psd_height).into())
+                })
+                .collect();
+            encoded_data = Some(bytes[2..].to_vec());
+        }
+
+        // The fourth channel is only used as alpha when there are exactly four channels
+        let used_channel_count = if channel_count == 4 {
+            4
+        } else {
+            channel_count.min(3)
+        };
+        let extra_channels = channels.split_off(used_channel_count.min(channels.len()));
+
+        let mut channels = channels.into_iter();
+        let red = channels
+            .next()
+            .unwrap_or(ChannelBytes::RawData(vec![].into()));
+        let green = channels.next();
+        let blue = channels.next();
+        let alpha = channels.next();
+
         Ok(ImageDataSection {
             compression,
             red,
             green,
             blue,
             alpha,
+            extra_channels,
+            scanline_byte_counts,
+            encoded_data,
         })
     }
+
+    /// Every channel of the final image in the order that they're stored in the file.
+    ///
+    /// Raw and ZIP compressed 16 bit data has already been mapped down to 8 bits, while RLE
+    /// compressed channels hold samples at the PSD's depth.
+    pub(crate) fn channels(&self) -> impl Iterator<Item = &ChannelBytes> {
+        std::iter::once(&self.red)
+            .chain(self.green.iter())
+            .chain(self.blue.iter())
+            .chain(self.alpha.iter())
+            .chain(self.extra_channels.iter())
+    }
+
+    /// Every channel of the final image at the PSD's depth, in the order that they're stored in
+    /// the file. 1 bit images have already been unpacked into one byte per pixel.
+    pub(crate) fn samples(
+        &self,
+        psd_width: u32,
+        depth: PsdDepth,
+        channel_count: u8,
+    ) -> Vec<Vec<u8>> {
+        let encoded_data = match &self.encoded_data {
+            Some(encoded_data) if depth != PsdDepth::One => encoded_data,
+            _ => {
+                return self
+                    .channels()
+                    .map(|channel| match channel {
+                        ChannelBytes::RawData(samples) => samples.to_vec(),
+                        ChannelBytes::RleCompressed(compressed) => rle_decompress(compressed),
+                    })
+                    .collect()
+            }
+        };
+
+        let samples = match self.compression {
+            PsdChannelCompression::RawData => encoded_data.clone(),
+            compression => zip_decompress(encoded_data, compression, psd_width as usize, depth)
+                .unwrap_or_default(),
+        };
+        let channel_len = samples.len() / (channel_count as usize).max(1);
+
+        samples
+            .chunks(channel_len.max(1))
+            .map(|channel| channel.to_vec())
+            .collect()
+    }
+
+    /// Write the section back into the bytes of a PSD file.
+    ///
+    /// The inverse of `ImageDataSection::from_bytes`. ZIP compressed, raw 16 bit and 1 bit data
+    /// is written as it was read, apart from the scanline byte counts of RLE compressed 1 bit
+    /// data.
+    pub(crate) fn write(&self, buffer: &mut PsdBuffer, version: PsdVersion) {
+        buffer.write_u16(self.compression as u16);
+
+        if let Some(encoded_data) = &self.encoded_data {
+            // RLE compressed 1 bit data starts with its scanline byte counts, which are written
+            // again in case the PSD is written as a different version than it was read as
+            if self.compression == PsdChannelCompression::RleCompressed {
+                let data_len: usize = self.scanline_byte_counts.iter().map(|c| *c as usize).sum();
+                let counts_len = encoded_data.len().saturating_sub(data_len);
+
+                for byte_count in self.scanline_byte_counts.iter() {
+                    buffer.write_rle_byte_count(version, *byte_count);
+                }
+                buffer.write(&encoded_data[counts_len..]);
+                return;
+            }
+
+            buffer.write(encoded_data);
+            return;
+        }
+
+        for byte_count in self.scanline_byte_counts.iter() {
+            buffer.write_rle_byte_count(version, *byte_count);
+        }
+
+        for channel in self.channels() {
+            buffer.write(channel.bytes());
+        }
+    }
+}
+
+/// Split uncompressed image data into its channels.
+fn split_raw_channels(
+    channel_bytes: &[u8],
+    depth: PsdDepth,
+    channel_count: usize,
+) -> Vec<ChannelBytes> {
+    let channel_byte_count = channel_bytes.len();
+
+    let bytes_per_channel = channel_byte_count / channel_count;
+
+    let mut channels: Vec<ChannelBytes> = (0..channel_count)
+        .map(|idx| {
+            let channel_start = idx * bytes_per_channel;
+            ChannelBytes::RawData(
+                channel_bytes[channel_start..channel_start + bytes_per_channel].into(),
+            )
+        })
+        .collect();
+
+    match depth {
+        PsdDepth::Eight => {}
+        // If this is a 16bit image there will be two bytes per pixel. We map every channel
+        // down to one byte per pixel, the original samples are kept in `encoded_data`
+        PsdDepth::Sixteen => {
+            for channel in channels.iter_mut() {
+                if let ChannelBytes::RawData(samples) = channel {
+                    *samples = samples_to_eight_bits(samples, depth, true).into();
+                }
+            }
+        }
+        // 32 bit samples are kept as floats, see `Psd::rgba_f32`.
+        // 1 bit samples are unpacked once the channels are split, see `unpack_bits`.
+        PsdDepth::ThirtyTwo | PsdDepth::One => {}
+    }
+
+    channels
+}
+
+/// Expand a channel of a 1 bit image into one grayscale byte per pixel.
+///
+/// Each row is padded to a whole number of bytes and the most significant bit is the leftmost
+/// pixel. A set bit is black.
+fn unpack_bits(channel: &ChannelBytes, psd_width: u32, psd_height: u32) -> Vec<u8> {
+    let width = psd_width as usize;
+    let mut grayscale = vec![255; width * psd_height as usize];
+
+    for_each_channel_row(
+        channel,
+        None,
+        width.div_ceil(8),
+        0..usize::MAX,
+        |row_idx, row| {
+            let pixels = match grayscale.get_mut(row_idx * width..(row_idx + 1) * width) {
+                Some(pixels) => pixels,
+                None => return,
+            };
+
+            for (idx, pixel) in pixels.iter_mut().enumerate() {
+                if row[idx / 8] & (0x80 >> (idx % 8)) != 0 {
+                    *pixel = 0;
+                }
+            }
+        },
+    );
+
+    grayscale
 }
 
-#[derive(Debug, Clone)]
+/// The bytes of a channel, which are a part of the file's bytes when the PSD was parsed with
+/// `Psd::from_shared_bytes`
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub enum ChannelBytes {
-    RawData(Vec<u8>),
-    RleCompressed(Vec<u8>),
+    RawData(SharedBytes),
+    RleCompressed(SharedBytes),
+}
+
+impl ChannelBytes {
+    /// The bytes of the channel, compressed or not
+    pub(crate) fn bytes(&self) -> &[u8] {
+        match self {
+            ChannelBytes::RawData(bytes) => bytes,
+            ChannelBytes::RleCompressed(bytes) => bytes,
+        }
+    }
 }
@@ -1,31 +1,136 @@
-use std::collections::HashMap;
-use std::ops::Range;
-
 use thiserror::Error;
 
+pub use crate::sections::image_resources_section::alpha_channels::{
+    ChannelDisplayInfo, ChannelDisplayKind,
+};
+pub use crate::sections::image_resources_section::descriptor_fields::DescriptorFields;
+pub use crate::sections::image_resources_section::guides::{GridAndGuides, Guide, GuideDirection};
+use crate::sections::image_resources_section::image_resource::trim_nul;
 pub use crate::sections::image_resources_section::image_resource::ImageResource;
-use crate::sections::image_resources_section::image_resource::SlicesImageResource;
-use crate::sections::PsdCursor;
+pub use crate::sections::image_resources_section::image_resource::Slice;
+pub use crate::sections::image_resources_section::image_resource::SlicesImageResource;
+#[cfg(feature = "unstable")]
+pub use crate::sections::image_resources_section::path::{
+    BezierKnot, BezierPath, PathPoint, SavedPath, Subpath,
+};
+pub use crate::sections::image_resources_section::resolution::{
+    DimensionUnit, ResolutionInfo, ResolutionUnit,
+};
+pub use crate::sections::image_resources_section::thumbnail::{Thumbnail, ThumbnailFormat};
+pub use crate::sections::image_resources_section::url_list::UrlListEntry;
+pub use crate::sections::image_resources_section::version_info::VersionInfo;
+use crate::sections::layer_and_mask_information_section::layer_mask::MaskRect;
+use crate::sections::serialize::{PsdBuffer, PsdSerialize};
+use crate::sections::{decode_pascal_string, PsdCursor};
 
 const EXPECTED_RESOURCE_BLOCK_SIGNATURE: [u8; 4] = [56, 66, 73, 77];
 const EXPECTED_DESCRIPTOR_VERSION: u32 = 16;
+/// The index of the target layer
+const RESOURCE_LAYER_STATE: i16 = 1024;
+/// The dragging group ID of each layer
+const RESOURCE_LAYER_GROUPS: i16 = 1026;
+/// The colors of an indexed color image, as RGB triples (Photoshop 2.0 only)
+pub(crate) const RESOURCE_COLOR_TABLE_OBSOLETE: i16 = 1003;
+/// The document's grid and guides
+pub(crate) const RESOURCE_GRID_AND_GUIDES: i16 = 1032;
+/// The horizontal and vertical resolution of the image and the units they're shown in
+const RESOURCE_RESOLUTION_INFO: i16 = 1005;
+/// The names of the alpha and spot channels, as Pascal strings
+pub(crate) const RESOURCE_ALPHA_NAMES: i16 = 1006;
+/// The color, solidity and kind of each alpha and spot channel (superseded by 1077)
+pub(crate) const RESOURCE_DISPLAY_INFO_OBSOLETE: i16 = 1007;
+/// The thumbnail of a file saved by Photoshop 4.0, with the red and blue channels swapped
+const RESOURCE_THUMBNAIL_BGR: i16 = 1033;
+/// The URL of the document in a workflow, as a Unicode string
+pub(crate) const RESOURCE_WORKFLOW_URL: i16 = 1035;
+/// The thumbnail of the document, usually a JPEG
+const RESOURCE_THUMBNAIL: i16 = 1036;
+/// The ICC color profile that the image's colors are in
+const RESOURCE_ICC_PROFILE: i16 = 1039;
+/// The angle of the global light that layer effects can use, in degrees
+pub(crate) const RESOURCE_GLOBAL_ANGLE: i16 = 1037;
+/// The names of the alpha and spot channels, as Unicode strings
+pub(crate) const RESOURCE_UNICODE_ALPHA_NAMES: i16 = 1045;
+/// The number of colors in the color table of an indexed color image
+pub(crate) const RESOURCE_INDEXED_COLOR_COUNT: i16 = 1046;
+/// The index of the transparent color in the color table of an indexed color image
+pub(crate) const RESOURCE_TRANSPARENCY_INDEX: i16 = 1047;
 const RESOURCE_SLICES_INFO: i16 = 1050;
-
+/// The URLs of the document's slices
+pub(crate) const RESOURCE_URL_LIST: i16 = 1054;
+/// The IDs of the selected layers
+const RESOURCE_LAYER_SELECTION_IDS: i16 = 1069;
+/// Whether the group of each layer is enabled
+const RESOURCE_LAYER_GROUPS_ENABLED: i16 = 1072;
+/// The application that wrote the file and whether the final image is a real composite
+const RESOURCE_VERSION_INFO: i16 = 1057;
+/// The EXIF data of the image
+pub(crate) const RESOURCE_EXIF_DATA: i16 = 1058;
+/// The color, solidity and kind of each alpha and spot channel
+pub(crate) const RESOURCE_DISPLAY_INFO: i16 = 1077;
+
+pub(crate) mod alpha_channels;
+mod descriptor_fields;
+pub(crate) mod guides;
 mod image_resource;
+mod layer_state;
+#[cfg(feature = "unstable")]
+pub(crate) mod path;
+mod resolution;
+mod thumbnail;
+pub(crate) mod url_list;
+mod version_info;
+
+/// A resource block from the image resources section, whether or not we parse it into an
+/// [`ImageResource`].
+///
+/// We hold on to the raw bytes of every block (even the ones that we parse) so that they can be
+/// written back out unchanged.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct ImageResourceBlock {
+    pub(crate) resource_id: i16,
+    /// The raw bytes of the Pascal string name, since they aren't necessarily UTF-8
+    pub(crate) name: Vec<u8>,
+    pub(crate) data: Vec<u8>,
+}
 
-struct ImageResourcesBlock {
-    resource_id: i16,
-    name: String,
-    data_range: Range<usize>,
+impl ImageResourceBlock {
+    /// The ID of the resource, such as 1050 for slices
+    pub fn id(&self) -> i16 {
+        self.resource_id
+    }
+
+    /// The name of the resource. Usually empty, but plugins use it to name their own resources.
+    ///
+    /// Names that aren't valid UTF-8 are decoded as Mac OS Roman. Files saved on systems with a
+    /// different legacy encoding (such as GB2312) can be decoded from [`Self::name_bytes`].
+    pub fn name(&self) -> String {
+        decode_pascal_string(&self.name)
+    }
+
+    /// The raw bytes of the name, as they were stored in the file
+    pub fn name_bytes(&self) -> &[u8] {
+        &self.name
+    }
+
+    /// The resource data
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct ImageResourcesSection {
     pub(crate) resources: Vec<ImageResource>,
+    pub(crate) blocks: Vec<ImageResourceBlock>,
 }
 
 /// Represents an malformed resource block
-#[derive(Debug, PartialEq, Error)]
+///
+/// New variants may be added in any release, see [`ImageResourcesSectionError::code`].
+#[derive(Debug, Clone, PartialEq, Error)]
+#[allow(missing_docs)]
+#[non_exhaustive]
 pub enum ImageResourcesSectionError {
     #[error(
         r#"The first four bytes (indices 0-3) must always equal [56, 66, 73, 77],
@@ -37,33 +142,95 @@ pub enum ImageResourcesSectionError {
     InvalidResource(ImageResourcesDescriptorError),
 }
 
+impl ImageResourcesSectionError {
+    /// A stable code that identifies the kind of error, see [`crate::PsdError::code`].
+    ///
+    /// Invalid resources return the code of their descriptor error.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ImageResourcesSectionError::InvalidSignature {} => "resources.invalid_signature",
+            ImageResourcesSectionError::InvalidResource(err) => err.code(),
+        }
+    }
+}
+
 impl ImageResourcesSection {
     pub fn from_bytes(bytes: &[u8]) -> Result<ImageResourcesSection, ImageResourcesSectionError> {
         let mut cursor = PsdCursor::new(bytes);
 
         let mut resources = vec![];
+        let mut blocks = vec![];
 
         let length = cursor.read_u32() as u64;
 
         while cursor.position() < length {
             let block = ImageResourcesSection::read_resource_block(&mut cursor)?;
 
-            let rid = block.resource_id;
-            match rid {
-                _ if rid == RESOURCE_SLICES_INFO => {
-                    let slices_image_resource = ImageResourcesSection::read_slice_block(
-                        &cursor.get_ref()[block.data_range],
-                    )
-                    .map_err(ImageResourcesSectionError::InvalidResource)?;
-                    resources.push(ImageResource::Slices(slices_image_resource));
-                }
-                _ => {}
+            if let Some(resource) = ImageResourcesSection::read_resource(&block)
+                .map_err(ImageResourcesSectionError::InvalidResource)?
+            {
+                resources.push(resource);
             }
+
+            blocks.push(block);
         }
 
         assert_eq!(cursor.position(), length + 4);
 
-        Ok(ImageResourcesSection { resources })
+        Ok(ImageResourcesSection { resources, blocks })
+    }
+
+    /// Parse a resource block into an [`ImageResource`], or `None` for the resources that we
+    /// don't parse.
+    fn read_resource(
+        block: &ImageResourceBlock,
+    ) -> Result<Option<ImageResource>, ImageResourcesDescriptorError> {
+        let rid = block.resource_id;
+        let resource = match rid {
+            _ if rid == RESOURCE_SLICES_INFO => Some(ImageResource::Slices(
+                ImageResourcesSection::read_slice_block(&block.data)?,
+            )),
+            _ if rid == RESOURCE_RESOLUTION_INFO => {
+                ResolutionInfo::read(&block.data).map(ImageResource::Resolution)
+            }
+            _ if rid == RESOURCE_ICC_PROFILE => Some(ImageResource::IccProfile(block.data.clone())),
+            _ if rid == RESOURCE_LAYER_STATE => {
+                layer_state::read_target_layer(&block.data).map(ImageResource::TargetLayer)
+            }
+            _ if rid == RESOURCE_LAYER_GROUPS => Some(ImageResource::LayerGroups(
+                layer_state::read_layer_groups(&block.data),
+            )),
+            _ if rid == RESOURCE_LAYER_SELECTION_IDS => Some(ImageResource::LayerSelectionIds(
+                layer_state::read_layer_selection_ids(&block.data),
+            )),
+            _ if rid == RESOURCE_LAYER_GROUPS_ENABLED => {
+                Some(ImageResource::LayerGroupsEnabled(block.data.clone()))
+            }
+            _ if rid == RESOURCE_THUMBNAIL => {
+                Thumbnail::read(&block.data, false).map(ImageResource::Thumbnail)
+            }
+            _ if rid == RESOURCE_THUMBNAIL_BGR => {
+                Thumbnail::read(&block.data, true).map(ImageResource::Thumbnail)
+            }
+            _ if rid == RESOURCE_ALPHA_NAMES => Some(ImageResource::AlphaNames(
+                alpha_channels::read_pascal_names(&block.data),
+            )),
+            _ if rid == RESOURCE_UNICODE_ALPHA_NAMES => Some(ImageResource::UnicodeAlphaNames(
+                alpha_channels::read_unicode_names(&block.data),
+            )),
+            _ if rid == RESOURCE_DISPLAY_INFO => Some(ImageResource::DisplayInfo(
+                alpha_channels::read_display_info(&block.data),
+            )),
+            _ if rid == RESOURCE_DISPLAY_INFO_OBSOLETE => Some(ImageResource::ObsoleteDisplayInfo(
+                alpha_channels::read_display_info_obsolete(&block.data),
+            )),
+            _ if rid == RESOURCE_VERSION_INFO => {
+                VersionInfo::read(&block.data).map(ImageResource::VersionInfo)
+            }
+            _ => None,
+        };
+
+        Ok(resource)
     }
 
     /// +----------+--------------------------------------------------------------------------------------------------------------------+
@@ -77,7 +244,7 @@ impl ImageResourcesSection {
     /// +----------+--------------------------------------------------------------------------------------------------------------------+
     fn read_resource_block(
         cursor: &mut PsdCursor,
-    ) -> Result<ImageResourcesBlock, ImageResourcesSectionError> {
+    ) -> Result<ImageResourceBlock, ImageResourcesSectionError> {
         // First four bytes must be '8BIM'
         let signature = cursor.read_4();
         if signature != EXPECTED_RESOURCE_BLOCK_SIGNATURE {
@@ -85,22 +252,17 @@ impl ImageResourcesSection {
         }
 
         let resource_id = cursor.read_i16();
-        let name = cursor.read_pascal_string();
+        let name = cursor.read_pascal_bytes().to_vec();
 
         let data_len = cursor.read_u32();
-        let pos = cursor.position() as usize;
+        let data = cursor.read(data_len).to_vec();
         // Note: data length is padded to even.
-        let data_len = data_len + data_len % 2;
-        let data_range = Range {
-            start: pos,
-            end: pos + data_len as usize,
-        };
-        cursor.read(data_len);
+        cursor.read(data_len % 2);
 
-        Ok(ImageResourcesBlock {
+        Ok(ImageResourceBlock {
             resource_id,
             name,
-            data_range,
+            data,
         })
     }
 
@@ -131,17 +293,20 @@ impl ImageResourcesSection {
             let number_of_slices = cursor.read_u32();
 
             let mut descriptors = Vec::new();
+            let mut slices = Vec::new();
 
             for _ in 0..number_of_slices {
-                match ImageResourcesSection::read_slice_body(&mut cursor)? {
-                    Some(v) => descriptors.push(v),
-                    None => {}
+                let (slice, descriptor, _) = ImageResourcesSection::read_slice_body(&mut cursor)?;
+                slices.push(slice);
+                if let Some(descriptor) = descriptor {
+                    descriptors.push(descriptor);
                 }
             }
 
             return Ok(SlicesImageResource {
                 name: group_of_slices_name,
                 descriptors,
+                slices,
             });
         }
         if version == 7 || version == 8 {
@@ -152,9 +317,21 @@ impl ImageResourcesSection {
                 );
             }
             let descriptor = DescriptorStructure::read_descriptor_structure(&mut cursor)?;
+            let slices = match descriptor.fields.get("slices") {
+                Some(DescriptorField::List(slices)) => slices
+                    .iter()
+                    .filter_map(|slice| match slice {
+                        DescriptorField::Descriptor(slice) => Some(Slice::from_descriptor(slice)),
+                        _ => None,
+                    })
+                    .collect(),
+                _ => vec![],
+            };
+
             return Ok(SlicesImageResource {
                 name: descriptor.name.clone(),
                 descriptors: vec![descriptor],
+                slices,
             });
         }
         unimplemented!("Slices resource format {version} is currently not supported");
@@ -189,10 +366,12 @@ impl ImageResourcesSection {
     /// | 4                                                    | Descriptor version ( = 16 for Photoshop 6.0). |
     /// | Variable                                             | Descriptor (see See Descriptor structure)     |
     /// +------------------------------------------------------+-----------------------------------------------+
+    ///
+    /// Also returns the position of the slice's left, top, right and bottom positions.
     fn read_slice_body(
         cursor: &mut PsdCursor,
-    ) -> Result<Option<DescriptorStructure>, ImageResourcesDescriptorError> {
-        let _slice_id = cursor.read_i32();
+    ) -> Result<(Slice, Option<DescriptorStructure>, u64), ImageResourcesDescriptorError> {
+        let slice_id = cursor.read_i32();
         let _group_id = cursor.read_i32();
         let origin = cursor.read_i32();
 
@@ -201,16 +380,17 @@ impl ImageResourcesSection {
             cursor.read_i32();
         }
 
-        let _name = cursor.read_unicode_string_padding(1);
+        let name = cursor.read_unicode_string_padding(1);
 
         let _type = cursor.read_i32();
 
-        let _top = cursor.read_i32();
-        let _left = cursor.read_i32();
-        let _bottom = cursor.read_i32();
-        let _right = cursor.read_i32();
+        let bounds_position = cursor.position();
+        let left = cursor.read_i32();
+        let top = cursor.read_i32();
+        let right = cursor.read_i32();
+        let bottom = cursor.read_i32();
 
-        let _url = cursor.read_unicode_string_padding(1);
+        let url = cursor.read_unicode_string_padding(1);
 
         let _target = cursor.read_unicode_string_padding(1);
 
@@ -228,7 +408,17 @@ impl ImageResourcesSection {
         let pos = cursor.position();
         let descriptor_version = cursor.peek_u32();
 
-        Ok(if descriptor_version == EXPECTED_DESCRIPTOR_VERSION {
+        let slice = Slice {
+            id: slice_id,
+            name: trim_nul(&name),
+            url: trim_nul(&url),
+            top,
+            left,
+            bottom,
+            right,
+        };
+
+        let descriptor = if descriptor_version == EXPECTED_DESCRIPTOR_VERSION {
             cursor.read_4();
 
             let descriptor = DescriptorStructure::read_descriptor_structure(cursor)?;
@@ -239,7 +429,152 @@ impl ImageResourcesSection {
             Some(descriptor)
         } else {
             None
-        })
+        };
+
+        Ok((slice, descriptor, bounds_position))
+    }
+
+    /// Move the bounds of every slice and of every group of slices, then parse the slices again
+    /// so that they match the resource blocks that we write.
+    pub(crate) fn map_slice_bounds(&mut self, map: impl Fn(MaskRect) -> MaskRect) {
+        for block in self.blocks.iter_mut() {
+            if block.resource_id != RESOURCE_SLICES_INFO || block.data.len() < 4 {
+                continue;
+            }
+
+            match PsdCursor::new(&block.data).read_i32() {
+                6 => map_slice_bounds_v6(&mut block.data, &map),
+                7 | 8 => map_slice_bounds_descriptor(&mut block.data, &map),
+                _ => {}
+            }
+        }
+
+        self.resources = self
+            .blocks
+            .iter()
+            .filter_map(|block| ImageResourcesSection::read_resource(block).ok().flatten())
+            .collect();
+    }
+}
+
+/// Move the bounds in a version 6 slices resource, which stores the bounds of the group of
+/// slices as top, left, bottom, right and the bounds of each slice as left, top, right, bottom.
+fn map_slice_bounds_v6(data: &mut [u8], map: &impl Fn(MaskRect) -> MaskRect) {
+    let mut slice_positions = vec![];
+    {
+        let mut cursor = PsdCursor::new(data);
+        cursor.read(20);
+        cursor.read_unicode_string_padding(1);
+
+        let number_of_slices = cursor.read_u32();
+        for _ in 0..number_of_slices {
+            match ImageResourcesSection::read_slice_body(&mut cursor) {
+                Ok((_, _, position)) => slice_positions.push(position as usize),
+                Err(_) => return,
+            }
+        }
+    }
+
+    let read = |data: &[u8], idx: usize| {
+        let start = idx * 4;
+        i32::from_be_bytes([
+            data[start],
+            data[start + 1],
+            data[start + 2],
+            data[start + 3],
+        ])
+    };
+    let write = |data: &mut [u8], idx: usize, value: i32| {
+        data[idx * 4..idx * 4 + 4].copy_from_slice(&value.to_be_bytes());
+    };
+
+    let bounds = &mut data[4..20];
+    let rect = map(MaskRect {
+        top: read(bounds, 0),
+        left: read(bounds, 1),
+        bottom: read(bounds, 2),
+        right: read(bounds, 3),
+    });
+    for (idx, value) in [rect.top, rect.left, rect.bottom, rect.right]
+        .iter()
+        .enumerate()
+    {
+        write(bounds, idx, *value);
+    }
+
+    for position in slice_positions {
+        let bounds = &mut data[position..position + 16];
+        let rect = map(MaskRect {
+            top: read(bounds, 1),
+            left: read(bounds, 0),
+            bottom: read(bounds, 3),
+            right: read(bounds, 2),
+        });
+        for (idx, value) in [rect.left, rect.top, rect.right, rect.bottom]
+            .iter()
+            .enumerate()
+        {
+            write(bounds, idx, *value);
+        }
+    }
+}
+
+/// Move the bounds in a version 7 or 8 slices resource, which stores the group of slices and
+/// each slice as descriptors with a "bounds" descriptor.
+fn map_slice_bounds_descriptor(data: &mut Vec<u8>, map: &impl Fn(MaskRect) -> MaskRect) {
+    let mut cursor = PsdCursor::new(data);
+    cursor.read(8);
+    let mut descriptor = match DescriptorStructure::read_descriptor_structure(&mut cursor) {
+        Ok(descriptor) => descriptor,
+        Err(_) => return,
+    };
+    let end = cursor.position() as usize;
+
+    map_bounds_field(&mut descriptor, map);
+    if let Some(DescriptorField::List(slices)) = descriptor.fields.get_mut("slices") {
+        for slice in slices.iter_mut() {
+            if let DescriptorField::Descriptor(slice) = slice {
+                map_bounds_field(slice, map);
+            }
+        }
+    }
+
+    let mut buffer = PsdBuffer::new();
+    buffer.write(&data[..8]);
+    descriptor.write(&mut buffer);
+    buffer.write(&data[end..]);
+    *data = buffer.into_inner();
+}
+
+/// Move the rectangle in a descriptor's "bounds" field, if it has one
+fn map_bounds_field(descriptor: &mut DescriptorStructure, map: &impl Fn(MaskRect) -> MaskRect) {
+    let bounds = match descriptor.fields.get_mut("bounds") {
+        Some(DescriptorField::Descriptor(bounds)) => bounds,
+        _ => return,
+    };
+
+    let keys = ["Top ", "Left", "Btom", "Rght"];
+    let mut edges = [0; 4];
+    for (key, edge) in keys.iter().zip(edges.iter_mut()) {
+        match bounds.fields.get(key) {
+            Some(DescriptorField::Integer(value)) => *edge = *value,
+            _ => return,
+        }
+    }
+
+    let rect = map(MaskRect {
+        top: edges[0],
+        left: edges[1],
+        bottom: edges[2],
+        right: edges[3],
+    });
+    for (key, value) in keys
+        .iter()
+        .zip([rect.top, rect.left, rect.bottom, rect.right].iter())
+    {
+        bounds
+            .fields
+            .insert(key.to_string(), DescriptorField::Integer(*value));
     }
 }
 
@@ -269,15 +604,16 @@ impl ImageResourcesSection {
 /// |                                                       | 'tdta' = Raw Data                                                                          |
 /// | Variable                                              | Item type: see the tables below for each possible type                                     |
 /// +-------------------------------------------------------+--------------------------------------------------------------------------------------------+
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
+#[allow(missing_docs)]
 pub struct DescriptorStructure {
     pub name: String,
-    pub fields: HashMap<String, DescriptorField>,
+    pub fields: DescriptorFields,
     pub class_id: Vec<u8>,
 }
 
 /// One of
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum DescriptorField {
     /// Descriptor as field
     Descriptor(DescriptorStructure),
@@ -330,7 +666,8 @@ pub enum DescriptorField {
 /// | Variable | classID: 4 bytes (length), followed either by string or (if length is zero) 4-byte classID |
 /// | Variable | KeyID: 4 bytes (length), followed either by string or (if length is zero) 4-byte keyID     |
 /// +----------+--------------------------------------------------------------------------------------------+
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
+#[allow(missing_docs)]
 pub struct PropertyStructure {
     pub name: String,
     pub class_id: Vec<u8>,
@@ -347,9 +684,11 @@ pub struct PropertyStructure {
 /// |                                    | '#Nne' = none: coerced.                                |
 /// |                                    | '#Prc'= percent: unit value                            |
 /// |                                    | '#Pxl' = pixels: tagged unit value                     |
+/// |                                    | '#Pnt' = points                                        |
+/// |                                    | '#Mlm' = millimeters                                   |
 /// | 8                                  | Actual value (double)                                  |
 /// +------------------------------------+--------------------------------------------------------+
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum UnitFloatStructure {
     /// Base degrees
     Angle(f64),
@@ -363,6 +702,56 @@ pub enum UnitFloatStructure {
     Percent(f64),
     /// Tagged unit value
     Pixels(f64),
+    /// Points (used by text layers)
+    Points(f64),
+    /// Millimeters
+    Millimeters(f64),
+}
+
+/// The resolution that Photoshop's distance and point units are based on
+const BASE_PPI: f64 = 72.;
+/// The number of millimeters in an inch
+const MILLIMETERS_PER_INCH: f64 = 25.4;
+
+impl UnitFloatStructure {
+    /// Convert a length into pixels, given the document's resolution in pixels per inch.
+    ///
+    /// Distances and points are based on 72ppi, so a distance of 72 is 1 inch, or 300 pixels
+    /// in a 300ppi document.
+    ///
+    /// Returns `None` if the value isn't a length.
+    pub fn to_pixels(&self, pixels_per_inch: f64) -> Option<f64> {
+        match self {
+            UnitFloatStructure::Pixels(pixels) => Some(*pixels),
+            UnitFloatStructure::Distance(value) | UnitFloatStructure::Points(value) => {
+                Some(value * pixels_per_inch / BASE_PPI)
+            }
+            UnitFloatStructure::Millimeters(millimeters) => {
+                Some(millimeters / MILLIMETERS_PER_INCH * pixels_per_inch)
+            }
+            _ => None,
+        }
+    }
+
+    /// Convert a percentage into a fraction, so 50% becomes 0.5.
+    ///
+    /// Returns `None` if the value isn't a percentage.
+    pub fn to_fraction(&self) -> Option<f64> {
+        match self {
+            UnitFloatStructure::Percent(percent) => Some(percent / 100.),
+            _ => None,
+        }
+    }
+
+    /// Convert an angle into radians.
+    ///
+    /// Returns `None` if the value isn't an angle.
+    pub fn to_radians(&self) -> Option<f64> {
+        match self {
+            UnitFloatStructure::Angle(degrees) => Some(degrees.to_radians()),
+            _ => None,
+        }
+    }
 }
 
 /// Unit float structure units keys
@@ -378,6 +767,10 @@ const UNIT_FLOAT_NONE: &[u8; 4] = b"#Nne";
 const UNIT_FLOAT_PERCENT: &[u8; 4] = b"#Prc";
 /// '#Pxl' = pixels: tagged unit value
 const UNIT_FLOAT_PIXELS: &[u8; 4] = b"#Pxl";
+/// '#Pnt' = points
+const UNIT_FLOAT_POINTS: &[u8; 4] = b"#Pnt";
+/// '#Mlm' = millimeters
+const UNIT_FLOAT_MILLIMETERS: &[u8; 4] = b"#Mlm";
 
 /// +----------+--------------------------------------------------------------------------------------------+
 /// |  Length  |                                        Description                                         |
@@ -385,7 +778,8 @@ const UNIT_FLOAT_PIXELS: &[u8; 4] = b"#Pxl";
 /// | Variable | Unicode string: name from classID                                                          |
 /// | Variable | ClassID: 4 bytes (length), followed either by string or (if length is zero) 4-byte classID |
 /// +----------+--------------------------------------------------------------------------------------------+
-#[derive(Debug)]
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+#[allow(missing_docs)]
 pub struct ClassStructure {
     pub name: String,
     pub class_id: Vec<u8>,
@@ -399,7 +793,8 @@ pub struct ClassStructure {
 /// | Variable | TypeID: 4 bytes (length), followed either by string or (if length is zero) 4-byte typeID   |
 /// | Variable | enum: 4 bytes (length), followed either by string or (if length is zero) 4-byte enum       |
 /// +----------+--------------------------------------------------------------------------------------------+
-#[derive(Debug)]
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+#[allow(missing_docs)]
 pub struct EnumeratedReference {
     pub name: String,
     pub class_id: Vec<u8>,
@@ -414,7 +809,8 @@ pub struct EnumeratedReference {
 /// | Variable | ClassID: 4 bytes (length), followed either by string or (if length is zero) 4-byte classID |
 /// | 4        | Value of the offset                                                                        |
 /// +----------+--------------------------------------------------------------------------------------------+
-#[derive(Debug)]
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+#[allow(missing_docs)]
 pub struct OffsetStructure {
     pub name: String,
     pub class_id: Vec<u8>,
@@ -427,7 +823,8 @@ pub struct OffsetStructure {
 /// | 4        | Length of data to follow                                                 |
 /// | Variable | FSSpec for Macintosh or a handle to a string to the full path on Windows |
 /// +----------+--------------------------------------------------------------------------+
-#[derive(Debug)]
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+#[allow(missing_docs)]
 pub struct AliasStructure {
     pub data: Vec<u8>,
 }
@@ -438,7 +835,8 @@ pub struct AliasStructure {
 /// | Variable | Type: 4 bytes (length), followed either by string or (if length is zero) 4-byte typeID |
 /// | Variable | Enum: 4 bytes (length), followed either by string or (if length is zero) 4-byte enum   |
 /// +----------+----------------------------------------------------------------------------------------+
-#[derive(Debug)]
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+#[allow(missing_docs)]
 pub struct EnumeratedDescriptor {
     pub type_field: Vec<u8>,
     pub enum_field: Vec<u8>,
@@ -454,7 +852,8 @@ pub struct EnumeratedDescriptor {
 /// | Variable | ClassID: 4 bytes (length), followed either by string or (if length is zero) 4-byte classID |
 /// | Variable | Unicode string: value                                                                      |
 /// +----------+--------------------------------------------------------------------------------------------+
-#[derive(Debug)]
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+#[allow(missing_docs)]
 pub struct NameStructure {
     pub name: String,
     pub class_id: Vec<u8>,
@@ -509,21 +908,60 @@ const OS_TYPE_INDEX: &[u8; 4] = b"indx";
 /// 'name' = Name
 const OS_TYPE_NAME: &[u8; 4] = b"name";
 
-#[derive(Debug, PartialEq, Error)]
+/// The maximum number of descriptors and lists that can be nested inside of each other.
+///
+/// Descriptors are read recursively, so without a limit a malicious file could nest descriptors
+/// deeply enough to overflow the stack.
+const MAX_DESCRIPTOR_DEPTH: usize = 64;
+
+/// Represents a malformed descriptor
+///
+/// New variants may be added in any release, see [`ImageResourcesDescriptorError::code`].
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[allow(missing_docs)]
+#[non_exhaustive]
 pub enum ImageResourcesDescriptorError {
     #[error(r#"Invalid TypeOS field."#)]
     InvalidTypeOS {},
     #[error(r#"Invalid unit name."#)]
     InvalidUnitName {},
+    #[error("Descriptors are nested more than {max_depth} levels deep.")]
+    MaxDepthExceeded { max_depth: usize },
+}
+
+impl ImageResourcesDescriptorError {
+    /// A stable code that identifies the kind of error, see [`crate::PsdError::code`].
+    pub fn code(&self) -> &'static str {
+        match self {
+            ImageResourcesDescriptorError::InvalidTypeOS {} => "descriptor.invalid_type_os",
+            ImageResourcesDescriptorError::InvalidUnitName {} => "descriptor.invalid_unit_name",
+            ImageResourcesDescriptorError::MaxDepthExceeded { .. } => {
+                "descriptor.max_depth_exceeded"
+            }
+        }
+    }
 }
 
 impl DescriptorStructure {
-    fn read_descriptor_structure(
+    pub(crate) fn read_descriptor_structure(
         cursor: &mut PsdCursor,
     ) -> Result<DescriptorStructure, ImageResourcesDescriptorError> {
+        DescriptorStructure::read_nested_descriptor_structure(cursor, 0)
+    }
+
+    fn read_nested_descriptor_structure(
+        cursor: &mut PsdCursor,
+        depth: usize,
+    ) -> Result<DescriptorStructure, ImageResourcesDescriptorError> {
+        if depth >= MAX_DESCRIPTOR_DEPTH {
+            return Err(ImageResourcesDescriptorError::MaxDepthExceeded {
+                max_depth: MAX_DESCRIPTOR_DEPTH,
+            });
+        }
+
         let name = cursor.read_unicode_string_padding(1);
         let class_id = DescriptorStructure::read_key_length(cursor).to_vec();
-        let fields = DescriptorStructure::read_fields(cursor, false)?;
+        let fields = DescriptorStructure::read_fields(cursor, depth)?;
 
         Ok(DescriptorStructure {
             name,
@@ -534,16 +972,19 @@ impl DescriptorStructure {
 
     fn read_fields(
         cursor: &mut PsdCursor,
-        sub_list: bool,
-    ) -> Result<HashMap<String, DescriptorField>, ImageResourcesDescriptorError> {
+        depth: usize,
+    ) -> Result<DescriptorFields, ImageResourcesDescriptorError> {
         let count = cursor.read_u32();
-        let mut m = HashMap::with_capacity(count as usize);
+        let mut m = DescriptorFields::with_capacity(preallocation(count, cursor, MIN_FIELD_LEN));
 
         for n in 0..count {
             let key = DescriptorStructure::read_key_length(cursor);
             let key = String::from_utf8_lossy(key).into_owned();
 
-            m.insert(key, DescriptorStructure::read_descriptor_field(cursor)?);
+            m.insert(
+                key,
+                DescriptorStructure::read_descriptor_field(cursor, depth)?,
+            );
         }
 
         Ok(m)
@@ -551,13 +992,19 @@ impl DescriptorStructure {
 
     fn read_list(
         cursor: &mut PsdCursor,
-        sub_list: bool,
+        depth: usize,
     ) -> Result<Vec<DescriptorField>, ImageResourcesDescriptorError> {
+        if depth >= MAX_DESCRIPTOR_DEPTH {
+            return Err(ImageResourcesDescriptorError::MaxDepthExceeded {
+                max_depth: MAX_DESCRIPTOR_DEPTH,
+            });
+        }
+
         let count = cursor.read_u32();
-        let mut vec = Vec::with_capacity(count as usize);
+        let mut vec = Vec::with_capacity(preallocation(count, cursor, MIN_LIST_ITEM_LEN));
 
         for n in 0..count {
-            let field = DescriptorStructure::read_descriptor_field(cursor)?;
+            let field = DescriptorStructure::read_descriptor_field(cursor, depth)?;
             vec.push(field);
         }
 
@@ -566,6 +1013,7 @@ impl DescriptorStructure {
 
     fn read_descriptor_field(
         cursor: &mut PsdCursor,
+        depth: usize,
     ) -> Result<DescriptorField, ImageResourcesDescriptorError> {
         let mut os_type = [0; 4];
         os_type.copy_from_slice(cursor.read_4());
@@ -574,11 +1022,11 @@ impl DescriptorStructure {
             OS_TYPE_REFERENCE => {
                 DescriptorField::Reference(DescriptorStructure::read_reference_structure(cursor)?)
             }
-            OS_TYPE_DESCRIPTOR => {
-                DescriptorField::Descriptor(DescriptorStructure::read_descriptor_structure(cursor)?)
-            }
+            OS_TYPE_DESCRIPTOR => DescriptorField::Descriptor(
+                DescriptorStructure::read_nested_descriptor_structure(cursor, depth + 1)?,
+            ),
             OS_TYPE_LIST => {
-                DescriptorField::List(DescriptorStructure::read_list_structure(cursor)?)
+                DescriptorField::List(DescriptorStructure::read_list_structure(cursor, depth + 1)?)
             }
             OS_TYPE_DOUBLE => DescriptorField::Double(cursor.read_f64()),
             OS_TYPE_UNIT_FLOAT => {
@@ -591,9 +1039,9 @@ impl DescriptorStructure {
             OS_TYPE_LARGE_INTEGER => DescriptorField::LargeInteger(cursor.read_i64()),
             OS_TYPE_INTEGER => DescriptorField::Integer(cursor.read_i32()),
             OS_TYPE_BOOL => DescriptorField::Boolean(cursor.read_u8() > 0),
-            OS_TYPE_GLOBAL_OBJECT => {
-                DescriptorField::Descriptor(DescriptorStructure::read_descriptor_structure(cursor)?)
-            }
+            OS_TYPE_GLOBAL_OBJECT => DescriptorField::Descriptor(
+                DescriptorStructure::read_nested_descriptor_structure(cursor, depth + 1)?,
+            ),
             OS_TYPE_CLASS => {
                 DescriptorField::Class(DescriptorStructure::read_class_structure(cursor))
             }
@@ -631,7 +1079,7 @@ impl DescriptorStructure {
         cursor: &mut PsdCursor,
     ) -> Result<Vec<DescriptorField>, ImageResourcesDescriptorError> {
         let count = cursor.read_u32();
-        let mut vec = Vec::with_capacity(count as usize);
+        let mut vec = Vec::with_capacity(preallocation(count, cursor, MIN_REFERENCE_ITEM_LEN));
 
         for n in 0..count {
             DescriptorStructure::read_key_length(cursor);
@@ -683,9 +1131,15 @@ impl DescriptorStructure {
             UNIT_FLOAT_ANGLE => UnitFloatStructure::Angle(cursor.read_f64()),
             UNIT_FLOAT_DENSITY => UnitFloatStructure::Density(cursor.read_f64()),
             UNIT_FLOAT_DISTANCE => UnitFloatStructure::Distance(cursor.read_f64()),
-            UNIT_FLOAT_NONE => UnitFloatStructure::None,
+            UNIT_FLOAT_NONE => {
+                // The value is still present for coerced units, we just don't use it
+                cursor.read_f64();
+                UnitFloatStructure::None
+            }
             UNIT_FLOAT_PERCENT => UnitFloatStructure::Percent(cursor.read_f64()),
             UNIT_FLOAT_PIXELS => UnitFloatStructure::Pixels(cursor.read_f64()),
+            UNIT_FLOAT_POINTS => UnitFloatStructure::Points(cursor.read_f64()),
+            UNIT_FLOAT_MILLIMETERS => UnitFloatStructure::Millimeters(cursor.read_f64()),
             _ => return Err(ImageResourcesDescriptorError::InvalidUnitName {}),
         })
     }
@@ -732,8 +1186,9 @@ impl DescriptorStructure {
 
     fn read_list_structure(
         cursor: &mut PsdCursor,
+        depth: usize,
     ) -> Result<Vec<DescriptorField>, ImageResourcesDescriptorError> {
-        DescriptorStructure::read_list(cursor, true)
+        DescriptorStructure::read_list(cursor, depth)
     }
 
     fn read_enumerated_descriptor(cursor: &mut PsdCursor) -> EnumeratedDescriptor {
@@ -771,3 +1226,465 @@ impl DescriptorStructure {
         cursor.read(length)
     }
 }
+
+/// The fewest bytes that a descriptor field can take up: a key of at least one byte after its
+/// 4 byte length, a 4 byte OSType and a 1 byte boolean
+const MIN_FIELD_LEN: u64 = 10;
+/// The fewest bytes that an item of a list can take up: a 4 byte OSType and a 1 byte boolean
+const MIN_LIST_ITEM_LEN: u64 = 5;
+/// The fewest bytes that an item of a reference can take up: a key of at least one byte after
+/// its 4 byte length, a 4 byte OSType and a 4 byte identifier
+const MIN_REFERENCE_ITEM_LEN: u64 = 13;
+
+/// How many entries to allocate room for up front, for a count that was read from the file.
+///
+/// A corrupt count could ask for more memory than there is, which aborts rather than panics,
+/// so we never allocate for more entries than could fit in the bytes that are left.
+fn preallocation(count: u32, cursor: &PsdCursor, min_entry_len: u64) -> usize {
+    (count as u64).min(cursor.remaining() / min_entry_len) as usize
+}
+
+impl PsdSerialize for ImageResourcesSection {
+    fn write(&self, buffer: &mut PsdBuffer) {
+        let length = buffer.start_length();
+
+        for block in self.blocks.iter() {
+            block.write(buffer);
+        }
+
+        buffer.end_length(length);
+    }
+}
+
+impl PsdSerialize for ImageResourceBlock {
+    /// The inverse of `ImageResourcesSection::read_resource_block`
+    fn write(&self, buffer: &mut PsdBuffer) {
+        buffer.write(&EXPECTED_RESOURCE_BLOCK_SIGNATURE);
+        buffer.write_i16(self.resource_id);
+        buffer.write_pascal_bytes(&self.name);
+
+        buffer.write_u32(self.data.len() as u32);
+        buffer.write(&self.data);
+        buffer.write_padding(self.data.len(), 2);
+    }
+}
+
+impl PsdSerialize for DescriptorStructure {
+    /// The inverse of `DescriptorStructure::read_descriptor_structure`
+    fn write(&self, buffer: &mut PsdBuffer) {
+        buffer.write_unicode_string_padding(&self.name, 1);
+        write_key_length(buffer, &self.class_id);
+
+        buffer.write_u32(self.fields.len() as u32);
+        for (key, field) in self.fields.iter() {
+            write_key_length(buffer, key.as_bytes());
+            field.write(buffer);
+        }
+    }
+}
+
+impl PsdSerialize for DescriptorField {
+    /// The inverse of `DescriptorStructure::read_descriptor_field`
+    fn write(&self, buffer: &mut PsdBuffer) {
+        buffer.write(self.os_type());
+        self.write_value(buffer);
+    }
+}
+
+impl DescriptorField {
+    /// The OSType key that is written before this field's value
+    fn os_type(&self) -> &'static [u8; 4] {
+        match self {
+            DescriptorField::Descriptor(_) => OS_TYPE_DESCRIPTOR,
+            DescriptorField::Reference(_) => OS_TYPE_REFERENCE,
+            DescriptorField::UnitFloat(_) => OS_TYPE_UNIT_FLOAT,
+            DescriptorField::Double(_) => OS_TYPE_DOUBLE,
+            DescriptorField::Class(_) => OS_TYPE_CLASS,
+            DescriptorField::String(_) => OS_TYPE_TEXT,
+            DescriptorField::EnumeratedReference(_) => OS_TYPE_ENUMERATED_REFERENCE,
+            DescriptorField::Offset(_) => OS_TYPE_OFFSET,
+            DescriptorField::Boolean(_) => OS_TYPE_BOOL,
+            DescriptorField::Alias(_) => OS_TYPE_ALIAS,
+            DescriptorField::List(_) => OS_TYPE_LIST,
+            DescriptorField::LargeInteger(_) => OS_TYPE_LARGE_INTEGER,
+            DescriptorField::Integer(_) => OS_TYPE_INTEGER,
+            DescriptorField::EnumeratedDescriptor(_) => OS_TYPE_ENUMERATED,
+            DescriptorField::RawData(_) => OS_TYPE_RAW_DATA,
+            DescriptorField::Property(_) => OS_TYPE_PROPERTY,
+            DescriptorField::Identifier(_) => OS_TYPE_IDENTIFIER,
+            DescriptorField::Index(_) => OS_TYPE_INDEX,
+            DescriptorField::Name(_) => OS_TYPE_NAME,
+        }
+    }
+
+    fn write_value(&self, buffer: &mut PsdBuffer) {
+        match self {
+            DescriptorField::Descriptor(descriptor) => descriptor.write(buffer),
+            DescriptorField::Reference(items) => {
+                buffer.write_u32(items.len() as u32);
+
+                for item in items.iter() {
+                    // Classes use a different OSType inside of references
+                    let os_type = match item {
+                        DescriptorField::Class(_) => OS_TYPE_CLASS3,
+                        _ => item.os_type(),
+                    };
+
+                    write_key_length(buffer, os_type);
+                    buffer.write(os_type);
+                    item.write_value(buffer);
+                }
+            }
+            DescriptorField::UnitFloat(unit_float) => {
+                let (unit, value) = match unit_float {
+                    UnitFloatStructure::Angle(value) => (UNIT_FLOAT_ANGLE, *value),
+                    UnitFloatStructure::Density(value) => (UNIT_FLOAT_DENSITY, *value),
+                    UnitFloatStructure::Distance(value) => (UNIT_FLOAT_DISTANCE, *value),
+                    UnitFloatStructure::None => (UNIT_FLOAT_NONE, 0.),
+                    UnitFloatStructure::Percent(value) => (UNIT_FLOAT_PERCENT, *value),
+                    UnitFloatStructure::Pixels(value) => (UNIT_FLOAT_PIXELS, *value),
+                    UnitFloatStructure::Points(value) => (UNIT_FLOAT_POINTS, *value),
+                    UnitFloatStructure::Millimeters(value) => (UNIT_FLOAT_MILLIMETERS, *value),
+                };
+
+                buffer.write(unit);
+                buffer.write_f64(value);
+            }
+            DescriptorField::Double(value) => buffer.write_f64(*value),
+            DescriptorField::Class(class) => {
+                buffer.write_unicode_string_padding(&class.name, 4);
+                write_key_length(buffer, &class.class_id);
+            }
+            DescriptorField::String(string) => buffer.write_unicode_string_padding(string, 1),
+            DescriptorField::EnumeratedReference(reference) => {
+                buffer.write_unicode_string_padding(&reference.name, 4);
+                write_key_length(buffer, &reference.class_id);
+                write_key_length(buffer, &reference.key_id);
+                write_key_length(buffer, &reference.enum_field);
+            }
+            DescriptorField::Offset(offset) => {
+                buffer.write_unicode_string_padding(&offset.name, 4);
+                write_key_length(buffer, &offset.class_id);
+                buffer.write_u32(offset.offset);
+            }
+            DescriptorField::Boolean(value) => buffer.write_u8(*value as u8),
+            DescriptorField::Alias(alias) => {
+                buffer.write_u32(alias.data.len() as u32);
+                buffer.write(&alias.data);
+            }
+            DescriptorField::List(fields) => {
+                buffer.write_u32(fields.len() as u32);
+                for field in fields.iter() {
+                    field.write(buffer);
+                }
+            }
+            DescriptorField::LargeInteger(value) => buffer.write_i64(*value),
+            DescriptorField::Integer(value) => buffer.write_i32(*value),
+            DescriptorField::EnumeratedDescriptor(descriptor) => {
+                write_key_length(buffer, &descriptor.type_field);
+                write_key_length(buffer, &descriptor.enum_field);
+            }
+            DescriptorField::RawData(data) => {
+                buffer.write_u32(data.len() as u32);
+                buffer.write(data);
+            }
+            DescriptorField::Property(property) => {
+                buffer.write_unicode_string_padding(&property.name, 4);
+                write_key_length(buffer, &property.class_id);
+                write_key_length(buffer, &property.key_id);
+            }
+            DescriptorField::Identifier(value) => buffer.write_i32(*value),
+            DescriptorField::Index(value) => buffer.write_i32(*value),
+            DescriptorField::Name(name) => {
+                buffer.write_unicode_string_padding(&name.name, 4);
+                write_key_length(buffer, &name.class_id);
+                buffer.write_unicode_string_padding(&name.value, 4);
+            }
+        }
+    }
+}
+
+/// The inverse of `DescriptorStructure::read_key_length`.
+///
+/// 4 byte keys are written with a length of zero.
+fn write_key_length(buffer: &mut PsdBuffer, key: &[u8]) {
+    if key.len() == 4 {
+        buffer.write_u32(0);
+    } else {
+        buffer.write_u32(key.len() as u32);
+    }
+
+    buffer.write(key);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sections::MajorSections;
+
+    /// Verify that lengths, percentages and angles are converted using Photoshop's base units.
+    /// Verify that a corrupt count of fields or list items doesn't allocate room for all of
+    /// them up front, which would abort instead of returning an error.
+    #[test]
+    fn corrupt_descriptor_counts() {
+        let mut fields = vec![];
+        fields.extend_from_slice(&0u32.to_be_bytes());
+        fields.extend_from_slice(&0u32.to_be_bytes());
+        fields.extend_from_slice(b"null");
+        fields.extend_from_slice(&u32::MAX.to_be_bytes());
+        fields.extend_from_slice(&0u32.to_be_bytes());
+        fields.extend_from_slice(b"Key ");
+        fields.extend_from_slice(b"????");
+
+        let mut list = fields[..fields.len() - 4].to_vec();
+        list[12..16].copy_from_slice(&1u32.to_be_bytes());
+        list.extend_from_slice(OS_TYPE_LIST);
+        list.extend_from_slice(&u32::MAX.to_be_bytes());
+        list.extend_from_slice(b"????");
+
+        for bytes in [fields, list].iter() {
+            let mut cursor = PsdCursor::new(bytes);
+            assert!(matches!(
+                DescriptorStructure::read_descriptor_structure(&mut cursor),
+                Err(ImageResourcesDescriptorError::InvalidTypeOS {})
+            ));
+        }
+    }
+
+    #[test]
+    fn unit_float_conversions() {
+        let ppi = 300.;
+
+        assert_eq!(UnitFloatStructure::Pixels(10.).to_pixels(ppi), Some(10.));
+        assert_eq!(UnitFloatStructure::Distance(72.).to_pixels(ppi), Some(300.));
+        assert_eq!(UnitFloatStructure::Points(36.).to_pixels(ppi), Some(150.));
+        assert_eq!(
+            UnitFloatStructure::Millimeters(25.4).to_pixels(ppi),
+            Some(300.)
+        );
+        assert_eq!(UnitFloatStructure::Percent(50.).to_pixels(ppi), None);
+
+        assert_eq!(UnitFloatStructure::Percent(50.).to_fraction(), Some(0.5));
+        assert_eq!(UnitFloatStructure::Pixels(50.).to_fraction(), None);
+
+        assert_eq!(
+            UnitFloatStructure::Angle(180.).to_radians(),
+            Some(std::f64::consts::PI)
+        );
+        assert_eq!(UnitFloatStructure::None.to_radians(), None);
+    }
+
+    /// Verify that we move the bounds of version 6 and version 8 slices, and that the parsed
+    /// slices match the moved bounds.
+    #[test]
+    fn map_slice_bounds() {
+        let fixtures: [&[u8]; 2] = [
+            include_bytes!("../../tests/fixtures/slices-resource/1234.psd"),
+            include_bytes!("../../tests/fixtures/slices-v8.psd"),
+        ];
+
+        for psd in fixtures.iter() {
+            let major_sections = MajorSections::from_bytes(psd).unwrap();
+            let mut section =
+                ImageResourcesSection::from_bytes(major_sections.image_resources).unwrap();
+
+            section.map_slice_bounds(|rect| MaskRect {
+                top: rect.top + 1,
+                left: rect.left + 2,
+                bottom: rect.bottom + 3,
+                right: rect.right + 4,
+            });
+
+            let mut buffer = PsdBuffer::new();
+            section.write(&mut buffer);
+            let written = buffer.into_inner();
+            let section = ImageResourcesSection::from_bytes(&written).unwrap();
+
+            let slices = section
+                .resources
+                .iter()
+                .find_map(|resource| match resource {
+                    ImageResource::Slices(slices) => Some(slices),
+                    _ => None,
+                })
+                .unwrap();
+            let slice = &slices.slices()[0];
+            assert_eq!(
+                (slice.top(), slice.left(), slice.bottom(), slice.right()),
+                (1, 2, 4, 5)
+            );
+        }
+    }
+
+    /// Verify that writing an image resources section gives us back the exact bytes that we read.
+    #[test]
+    fn image_resources_section_round_trip() {
+        let fixtures: [&[u8]; 3] = [
+            include_bytes!("../../tests/fixtures/green-1x1.psd"),
+            include_bytes!("../../tests/fixtures/slices-v8.psd"),
+            include_bytes!("../../tests/fixtures/slices-resource/1234.psd"),
+        ];
+
+        for psd in fixtures.iter() {
+            let major_sections = MajorSections::from_bytes(psd).unwrap();
+            let section =
+                ImageResourcesSection::from_bytes(major_sections.image_resources).unwrap();
+
+            let mut buffer = PsdBuffer::new();
+            section.write(&mut buffer);
+
+            assert_eq!(buffer.into_inner(), major_sections.image_resources);
+        }
+    }
+
+    /// Verify that a resource with an odd length name and odd length data is padded to even.
+    #[test]
+    fn resource_block_padding() {
+        let block = ImageResourceBlock {
+            resource_id: 1000,
+            name: b"ab".to_vec(),
+            data: vec![1, 2, 3],
+        };
+
+        let mut buffer = PsdBuffer::new();
+        block.write(&mut buffer);
+        let bytes = buffer.into_inner();
+
+        assert_eq!(
+            bytes,
+            [56, 66, 73, 77, 3, 232, 2, 97, 98, 0, 0, 0, 0, 3, 1, 2, 3, 0]
+        );
+
+        let mut cursor = PsdCursor::new(&bytes);
+        let read = ImageResourcesSection::read_resource_block(&mut cursor).unwrap();
+        assert_eq!(read.resource_id, 1000);
+        assert_eq!(read.name(), "ab");
+        assert_eq!(read.data, vec![1, 2, 3]);
+        assert_eq!(cursor.position(), bytes.len() as u64);
+    }
+
+    /// Verify that names that aren't UTF-8 are decoded as Mac OS Roman and written back
+    /// unchanged.
+    #[test]
+    fn international_resource_names() {
+        assert_eq!(decode_pascal_string(b"plugin"), "plugin");
+        assert_eq!(decode_pascal_string("Ébauche".as_bytes()), "Ébauche");
+        assert_eq!(decode_pascal_string(&[0x83, 0x62, 0x61, 0x75]), "Ébau");
+        assert_eq!(decode_pascal_string(&[0xD2, 0xD3, 0xDB, 0xFF]), "“”€ˇ");
+
+        let block = ImageResourceBlock {
+            resource_id: 4000,
+            name: vec![0x8E, 0x74, 0xE9],
+            data: vec![],
+        };
+        assert_eq!(block.name(), "étÈ");
+
+        let mut buffer = PsdBuffer::new();
+        block.write(&mut buffer);
+        let bytes = buffer.into_inner();
+
+        let read = ImageResourcesSection::read_resource_block(&mut PsdCursor::new(&bytes)).unwrap();
+        assert_eq!(read, block);
+    }
+
+    /// Verify that a descriptor that we write can be read back, and that it's written with its
+    /// fields in the order that they were read.
+    #[test]
+    fn descriptor_round_trip() {
+        let psd = include_bytes!("../../tests/fixtures/slices-v8.psd");
+        let major_sections = MajorSections::from_bytes(psd).unwrap();
+        let section = ImageResourcesSection::from_bytes(major_sections.image_resources).unwrap();
+
+        let descriptor = section
+            .resources
+            .iter()
+            .find_map(|resource| match resource {
+                ImageResource::Slices(slices) => Some(&slices.descriptors()[0]),
+                _ => None,
+            })
+            .unwrap();
+
+        let mut buffer = PsdBuffer::new();
+        descriptor.write(&mut buffer);
+        let bytes = buffer.into_inner();
+
+        // The descriptor follows the resource's version and the descriptor's version
+        let block = section
+            .blocks
+            .iter()
+            .find(|block| block.resource_id == RESOURCE_SLICES_INFO)
+            .unwrap();
+        assert_eq!(&block.data[8..8 + bytes.len()], &bytes[..]);
+
+        let mut cursor = PsdCursor::new(&bytes);
+        let read = DescriptorStructure::read_descriptor_structure(&mut cursor).unwrap();
+
+        assert_eq!(cursor.position(), bytes.len() as u64);
+        assert_eq!(read.name, descriptor.name);
+        assert_eq!(read.class_id, descriptor.class_id);
+        assert_eq!(read.fields.len(), descriptor.fields.len());
+
+        match (read.fields.get("slices"), descriptor.fields.get("slices")) {
+            (Some(DescriptorField::List(read)), Some(DescriptorField::List(original))) => {
+                assert_eq!(slices_from_list(read), slices_from_list(original));
+            }
+            _ => panic!("expected a list of slices"),
+        }
+    }
+
+    /// Verify that we return an error instead of overflowing the stack when descriptors are
+    /// nested too deeply.
+    #[test]
+    fn descriptor_depth_limit() {
+        for os_type in [OS_TYPE_DESCRIPTOR, OS_TYPE_LIST].iter() {
+            let bytes = nested_descriptor(os_type, MAX_DESCRIPTOR_DEPTH - 1);
+            let mut cursor = PsdCursor::new(&bytes);
+            assert!(DescriptorStructure::read_descriptor_structure(&mut cursor).is_ok());
+
+            let bytes = nested_descriptor(os_type, 100_000);
+            let mut cursor = PsdCursor::new(&bytes);
+            assert_eq!(
+                DescriptorStructure::read_descriptor_structure(&mut cursor).unwrap_err(),
+                ImageResourcesDescriptorError::MaxDepthExceeded {
+                    max_depth: MAX_DESCRIPTOR_DEPTH
+                }
+            );
+        }
+    }
+
+    /// A descriptor with `depth` descriptors (or lists) nested inside of it.
+    fn nested_descriptor(os_type: &[u8; 4], depth: usize) -> Vec<u8> {
+        let mut buffer = PsdBuffer::new();
+
+        let write_descriptor_header = |buffer: &mut PsdBuffer, field_count: u32| {
+            buffer.write_unicode_string_padding("", 1);
+            write_key_length(buffer, b"null");
+            buffer.write_u32(field_count);
+        };
+
+        write_descriptor_header(&mut buffer, 1);
+        write_key_length(&mut buffer, b"next");
+        for _ in 0..depth {
+            buffer.write(os_type);
+            if os_type == OS_TYPE_DESCRIPTOR {
+                write_descriptor_header(&mut buffer, 1);
+                write_key_length(&mut buffer, b"next");
+            } else {
+                buffer.write_u32(1);
+            }
+        }
+        buffer.write(OS_TYPE_INTEGER);
+        buffer.write_i32(0);
+
+        buffer.into_inner()
+    }
+
+    fn slices_from_list(list: &[DescriptorField]) -> Vec<Slice> {
+        list.iter()
+            .map(|slice| match slice {
+                DescriptorField::Descriptor(slice) => Slice::from_descriptor(slice),
+                _ => panic!("expected a slice descriptor"),
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,209 @@
+//! The resources that name the alpha and spot channels of the final image and say how they are
+//! shown.
+//!
+//! # [Adobe Docs](https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/)
+//!
+//! | ID   | Description                                                                                    |
+//! |------|------------------------------------------------------------------------------------------------|
+//! | 1006 | Names of the alpha channels as a series of Pascal strings.                                     |
+//! | 1007 | (Obsolete) Display information. 14 bytes per channel.                                          |
+//! | 1045 | Unicode Alpha Names. Unicode string.                                                           |
+//! | 1077 | DisplayInfo structure to support floating point colors. 4 bytes version, 13 bytes per channel. |
+//!
+//! Each channel's display info is a 2 byte color space followed by four 2 byte color components,
+//! a 2 byte opacity from 0 to 100 and a 1 byte kind. The obsolete resource pads each channel
+//! with one more byte.
+
+use crate::sections::decode_pascal_string;
+
+/// How an alpha or spot channel is shown, from the display info resource (1077, or 1007 for
+/// older files).
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct ChannelDisplayInfo {
+    pub(crate) color_space: u16,
+    pub(crate) color: [u16; 4],
+    pub(crate) opacity: u16,
+    pub(crate) kind: ChannelDisplayKind,
+}
+
+/// What an alpha or spot channel is for, see [`ChannelDisplayInfo::kind`]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum ChannelDisplayKind {
+    /// An alpha channel whose color shows the selected areas (0)
+    SelectedAreas,
+    /// An alpha channel whose color shows the masked areas (1)
+    MaskedAreas,
+    /// A spot color channel, printed with its own ink (2)
+    Spot,
+    /// A kind that Photoshop doesn't document
+    Unknown(u8),
+}
+
+impl ChannelDisplayInfo {
+    /// The color space that [`ChannelDisplayInfo::color`] is in, such as 0 for RGB, 2 for CMYK
+    /// or 7 for Lab
+    pub fn color_space(&self) -> u16 {
+        self.color_space
+    }
+
+    /// The color that the channel is shown (or printed) in, as the four components of its color
+    /// space. Color spaces with fewer components leave the rest as 0.
+    pub fn color(&self) -> [u16; 4] {
+        self.color
+    }
+
+    /// The opacity of the channel's overlay, or the solidity of a spot color's ink, from 0 to
+    /// 100
+    pub fn opacity(&self) -> u16 {
+        self.opacity
+    }
+
+    /// Whether the channel is an alpha channel or a spot color
+    pub fn kind(&self) -> ChannelDisplayKind {
+        self.kind
+    }
+
+    /// Whether the channel is a spot color, printed with its own ink
+    pub fn is_spot(&self) -> bool {
+        self.kind == ChannelDisplayKind::Spot
+    }
+
+    fn read(info: &[u8]) -> ChannelDisplayInfo {
+        let u16_at = |idx: usize| u16::from_be_bytes([info[idx], info[idx + 1]]);
+
+        ChannelDisplayInfo {
+            color_space: u16_at(0),
+            color: [u16_at(2), u16_at(4), u16_at(6), u16_at(8)],
+            opacity: u16_at(10),
+            kind: match info[12] {
+                0 => ChannelDisplayKind::SelectedAreas,
+                1 => ChannelDisplayKind::MaskedAreas,
+                2 => ChannelDisplayKind::Spot,
+                kind => ChannelDisplayKind::Unknown(kind),
+            },
+        }
+    }
+}
+
+/// Read the display info of every alpha and spot channel (1077), which starts with a 4 byte
+/// version
+pub(crate) fn read_display_info(data: &[u8]) -> Vec<ChannelDisplayInfo> {
+    data.get(4..)
+        .unwrap_or(&[])
+        .chunks_exact(13)
+        .map(ChannelDisplayInfo::read)
+        .collect()
+}
+
+/// Read the obsolete display info of every alpha and spot channel (1007)
+pub(crate) fn read_display_info_obsolete(data: &[u8]) -> Vec<ChannelDisplayInfo> {
+    data.chunks_exact(14)
+        .map(ChannelDisplayInfo::read)
+        .collect()
+}
+
+/// A series of Unicode strings, each a 4 byte count of UTF-16 code units followed by the code
+/// units. Photoshop usually includes a trailing null.
+pub(crate) fn read_unicode_names(mut data: &[u8]) -> Vec<String> {
+    let mut names = vec![];
+
+    while data.len() >= 4 {
+        let len = u32::from_be_bytes([data[0], data[1], data[2], data[3]]) as usize;
+        let units = match data.get(4..4 + len * 2) {
+            Some(units) => units,
+            None => break,
+        };
+
+        let units: Vec<u16> = units
+            .chunks_exact(2)
+            .map(|unit| u16::from_be_bytes([unit[0], unit[1]]))
+            .collect();
+        names.push(
+            String::from_utf16_lossy(&units)
+                .trim_end_matches('\0')
+                .to_string(),
+        );
+
+        data = &data[4 + len * 2..];
+    }
+
+    names
+}
+
+/// A series of Pascal strings without any padding between them
+pub(crate) fn read_pascal_names(mut data: &[u8]) -> Vec<String> {
+    let mut names = vec![];
+
+    while let Some((len, rest)) = data.split_first() {
+        let name = match rest.get(..*len as usize) {
+            Some(name) => name,
+            None => break,
+        };
+
+        names.push(decode_pascal_string(name));
+        data = &rest[*len as usize..];
+    }
+
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Verify that we read each Unicode alpha channel name and drop the trailing null.
+    #[test]
+    fn unicode_names() {
+        let mut data = vec![];
+        for name in ["PANTONE 185 C\0", "Alpha 1"].iter() {
+            let units: Vec<u16> = name.encode_utf16().collect();
+            data.extend_from_slice(&(units.len() as u32).to_be_bytes());
+            for unit in units {
+                data.extend_from_slice(&unit.to_be_bytes());
+            }
+        }
+
+        assert_eq!(
+            read_unicode_names(&data),
+            vec!["PANTONE 185 C".to_string(), "Alpha 1".to_string()]
+        );
+    }
+
+    /// Verify that we read unpadded Pascal alpha channel names and stop at truncated data.
+    #[test]
+    fn pascal_names() {
+        let data = b"\x04Gold\x05Alpha\x09Trunc";
+
+        assert_eq!(
+            read_pascal_names(data),
+            vec!["Gold".to_string(), "Alpha".to_string()]
+        );
+    }
+
+    /// Verify that both versions of the display info are read, ignoring a trailing partial
+    /// channel.
+    #[test]
+    fn display_info() {
+        let spot = [0, 2, 0, 10, 0, 20, 0, 30, 0, 40, 0, 50, 2];
+        let alpha = [0, 0, 255, 255, 0, 0, 0, 0, 0, 0, 0, 100, 1];
+
+        let mut data = vec![0, 0, 0, 1];
+        data.extend_from_slice(&spot);
+        data.extend_from_slice(&alpha);
+        data.extend_from_slice(&[0; 5]);
+
+        let infos = read_display_info(&data);
+        assert_eq!(infos.len(), 2);
+        assert_eq!(infos[0].color_space(), 2);
+        assert_eq!(infos[0].color(), [10, 20, 30, 40]);
+        assert_eq!(infos[0].opacity(), 50);
+        assert!(infos[0].is_spot());
+        assert_eq!(infos[1].kind(), ChannelDisplayKind::MaskedAreas);
+
+        let mut obsolete = spot.to_vec();
+        obsolete.push(0);
+        obsolete.extend_from_slice(&alpha);
+        obsolete.push(0);
+        assert_eq!(read_display_info_obsolete(&obsolete), infos);
+    }
+}
@@ -0,0 +1,77 @@
+use crate::sections::image_resources_section::DescriptorField;
+use std::collections::HashMap;
+use std::ops::Index;
+
+/// The fields of a descriptor in the order that they were read, along with lookups by key.
+///
+/// Descriptors are written back out with their fields in this order.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct DescriptorFields {
+    fields: HashMap<String, DescriptorField>,
+    keys_in_order: Vec<String>,
+}
+
+impl DescriptorFields {
+    pub(crate) fn with_capacity(capacity: usize) -> Self {
+        DescriptorFields {
+            fields: HashMap::with_capacity(capacity),
+            keys_in_order: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Set a field, making it last in the order if there wasn't already a field with the key.
+    ///
+    /// Returns the field's previous value, which keeps its place in the order.
+    pub fn insert(&mut self, key: String, field: DescriptorField) -> Option<DescriptorField> {
+        if !self.fields.contains_key(&key) {
+            self.keys_in_order.push(key.clone());
+        }
+
+        self.fields.insert(key, field)
+    }
+
+    /// The number of fields
+    pub fn len(&self) -> usize {
+        self.keys_in_order.len()
+    }
+
+    /// Whether there aren't any fields
+    pub fn is_empty(&self) -> bool {
+        self.keys_in_order.is_empty()
+    }
+
+    /// Get a field by its key
+    pub fn get(&self, key: &str) -> Option<&DescriptorField> {
+        self.fields.get(key)
+    }
+
+    /// Get a field by its key, mutably
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut DescriptorField> {
+        self.fields.get_mut(key)
+    }
+
+    /// Whether there is a field with the given key
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.fields.contains_key(key)
+    }
+
+    /// The keys of the fields, in order
+    pub fn keys(&self) -> &[String] {
+        &self.keys_in_order
+    }
+
+    /// Iterate over the keys and fields, in order
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &DescriptorField)> {
+        self.keys_in_order
+            .iter()
+            .map(move |key| (key, &self.fields[key]))
+    }
+}
+
+impl Index<&str> for DescriptorFields {
+    type Output = DescriptorField;
+
+    fn index(&self, key: &str) -> &DescriptorField {
+        &self.fields[key]
+    }
+}
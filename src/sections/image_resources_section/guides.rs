@@ -0,0 +1,168 @@
+use crate::sections::PsdCursor;
+
+/// Guide positions are stored in 1/32nds of a pixel
+pub(crate) const GUIDE_UNITS_PER_PIXEL: i32 = 32;
+
+/// The length of the header that comes before the guides
+const GRID_AND_GUIDES_HEADER_LEN: usize = 16;
+
+/// The grid settings and guides of the document, from the grid and guides resource (1032).
+///
+/// # [Adobe Docs](https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/)
+///
+/// | Length | Description                                                                                                                                  |
+/// |--------|----------------------------------------------------------------------------------------------------------------------------------------------|
+/// | 4      | Version ( = 1)                                                                                                                               |
+/// | 8      | Future implementation of document-specific grids (4 bytes horizontal, 4 bytes vertical).                                                     |
+/// | 4      | Number of guide resource blocks (can be 0).                                                                                                  |
+/// | 5 * n  | Guide resource blocks: 4 bytes location of guide in document coordinates, 1 byte direction of guide: VHSelect (0 = vertical, 1 = horizontal) |
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct GridAndGuides {
+    pub(crate) version: u32,
+    pub(crate) grid_horizontal: u32,
+    pub(crate) grid_vertical: u32,
+    pub(crate) guides: Vec<Guide>,
+}
+
+/// A guide line across the whole document
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct Guide {
+    pub(crate) location: i32,
+    pub(crate) direction: GuideDirection,
+}
+
+/// Which way a [`Guide`] runs
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum GuideDirection {
+    /// A vertical line at an x coordinate (0)
+    Vertical,
+    /// A horizontal line at a y coordinate (1)
+    Horizontal,
+}
+
+impl GridAndGuides {
+    /// Read a grid and guides resource, or `None` if it's too short to hold the header. Reading
+    /// stops at the first guide that runs past the end of the data, keeping the guides before it.
+    pub(crate) fn read(data: &[u8]) -> Option<GridAndGuides> {
+        if data.len() < GRID_AND_GUIDES_HEADER_LEN {
+            return None;
+        }
+        let mut cursor = PsdCursor::new(data);
+
+        let version = cursor.read_u32();
+        let grid_horizontal = cursor.read_u32();
+        let grid_vertical = cursor.read_u32();
+        let guide_count = cursor.read_u32() as usize;
+
+        let guides = data[GRID_AND_GUIDES_HEADER_LEN..]
+            .chunks_exact(5)
+            .take(guide_count)
+            .map(|guide| Guide {
+                location: i32::from_be_bytes([guide[0], guide[1], guide[2], guide[3]]),
+                direction: match guide[4] {
+                    1 => GuideDirection::Horizontal,
+                    _ => GuideDirection::Vertical,
+                },
+            })
+            .collect();
+
+        Some(GridAndGuides {
+            version,
+            grid_horizontal,
+            grid_vertical,
+            guides,
+        })
+    }
+
+    /// The version of the resource, which is always 1
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// The horizontal grid setting as it is stored. Photoshop documents it as being reserved for
+    /// document specific grids, and writes 576 (18 pixels in guide units).
+    pub fn grid_horizontal(&self) -> u32 {
+        self.grid_horizontal
+    }
+
+    /// The vertical grid setting as it is stored, see [`GridAndGuides::grid_horizontal`]
+    pub fn grid_vertical(&self) -> u32 {
+        self.grid_vertical
+    }
+
+    /// Every guide, in the order that they are stored
+    pub fn guides(&self) -> &[Guide] {
+        &self.guides
+    }
+
+    /// The guides that run in one direction
+    pub fn guides_in(&self, direction: GuideDirection) -> impl Iterator<Item = &Guide> {
+        self.guides
+            .iter()
+            .filter(move |guide| guide.direction == direction)
+    }
+}
+
+impl Guide {
+    /// Whether the guide is a vertical or a horizontal line
+    pub fn direction(&self) -> GuideDirection {
+        self.direction
+    }
+
+    /// Where the guide is, in the 1/32nds of a pixel that it is stored in. Guides can be off the
+    /// canvas, so this can be negative or past the edge of the document.
+    pub fn location(&self) -> i32 {
+        self.location
+    }
+
+    /// Where the guide is in pixels, from the left of the document for vertical guides and from
+    /// the top for horizontal guides
+    pub fn position(&self) -> f64 {
+        self.location as f64 / GUIDE_UNITS_PER_PIXEL as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Verify that the grid settings and every guide are read, and that a count that runs past
+    /// the end of the data keeps the guides that are there.
+    #[test]
+    fn read_grid_and_guides() {
+        let mut data = vec![];
+        for value in [1u32, 576, 576, 3].iter() {
+            data.extend_from_slice(&value.to_be_bytes());
+        }
+        data.extend_from_slice(&[0, 0, 0, 48, 0]);
+        data.extend_from_slice(&[255, 255, 255, 224, 1]);
+
+        let grid_and_guides = GridAndGuides::read(&data).unwrap();
+
+        assert_eq!(grid_and_guides.version(), 1);
+        assert_eq!(
+            (
+                grid_and_guides.grid_horizontal(),
+                grid_and_guides.grid_vertical()
+            ),
+            (576, 576)
+        );
+        assert_eq!(
+            grid_and_guides.guides(),
+            &[
+                Guide {
+                    location: 48,
+                    direction: GuideDirection::Vertical
+                },
+                Guide {
+                    location: -32,
+                    direction: GuideDirection::Horizontal
+                },
+            ]
+        );
+        assert_eq!(grid_and_guides.guides()[0].position(), 1.5);
+        assert_eq!(grid_and_guides.guides()[1].position(), -1.);
+
+        assert_eq!(GridAndGuides::read(&data[..15]), None);
+    }
+}
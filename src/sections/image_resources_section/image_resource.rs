@@ -1,17 +1,44 @@
-use crate::sections::image_resources_section::DescriptorStructure;
+use crate::sections::image_resources_section::alpha_channels::ChannelDisplayInfo;
+use crate::sections::image_resources_section::resolution::ResolutionInfo;
+use crate::sections::image_resources_section::thumbnail::Thumbnail;
+use crate::sections::image_resources_section::version_info::VersionInfo;
+use crate::sections::image_resources_section::{DescriptorField, DescriptorStructure};
 
 /// An image resource from the image resources section
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 #[allow(missing_docs)]
 pub enum ImageResource {
     Slices(SlicesImageResource),
+    Resolution(ResolutionInfo),
+    /// The raw bytes of the embedded ICC color profile
+    IccProfile(Vec<u8>),
+    Thumbnail(Thumbnail),
+    /// The index of the target layer, counting layer records from the bottom (1024)
+    TargetLayer(u16),
+    /// The dragging group ID of each layer record, from the bottom record up (1026)
+    LayerGroups(Vec<u16>),
+    /// The IDs of the selected layers (1069)
+    LayerSelectionIds(Vec<u32>),
+    /// Whether the group of each layer record is enabled, from the bottom record up (1072)
+    LayerGroupsEnabled(Vec<u8>),
+    /// The application that wrote the file and whether the final image is real (1057)
+    VersionInfo(VersionInfo),
+    /// The names of the alpha and spot channels (1006)
+    AlphaNames(Vec<String>),
+    /// The names of the alpha and spot channels, which take the place of `AlphaNames` (1045)
+    UnicodeAlphaNames(Vec<String>),
+    /// How each alpha and spot channel is shown (1077)
+    DisplayInfo(Vec<ChannelDisplayInfo>),
+    /// How each alpha and spot channel is shown, from files saved before 1077 (1007)
+    ObsoleteDisplayInfo(Vec<ChannelDisplayInfo>),
 }
 
 /// Comes from a slices resource block
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct SlicesImageResource {
     pub(crate) name: String,
     pub(crate) descriptors: Vec<DescriptorStructure>,
+    pub(crate) slices: Vec<Slice>,
 }
 
 #[allow(missing_docs)]
@@ -23,4 +50,114 @@ impl SlicesImageResource {
     pub fn descriptors(&self) -> &Vec<DescriptorStructure> {
         &self.descriptors
     }
+
+    pub fn slices(&self) -> &Vec<Slice> {
+        &self.slices
+    }
+
+    /// The name used for slices that don't have a name of their own.
+    ///
+    /// Version 7 and 8 slices resources store this as the `baseName` of their descriptor.
+    pub(crate) fn base_name(&self) -> String {
+        let name = trim_nul(&self.name);
+        if !name.is_empty() {
+            return name;
+        }
+
+        match self
+            .descriptors
+            .first()
+            .and_then(|descriptor| descriptor.fields.get("baseName"))
+        {
+            Some(DescriptorField::String(base_name)) => trim_nul(base_name),
+            _ => name,
+        }
+    }
+}
+
+/// A single slice from a slices resource block.
+///
+/// The bounds are in pixels relative to the top left corner of the PSD. The bottom and right
+/// are exclusive, so a slice with a left of 0 and a right of 4 is 4 pixels wide.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Slice {
+    pub(crate) id: i32,
+    pub(crate) name: String,
+    pub(crate) url: String,
+    pub(crate) top: i32,
+    pub(crate) left: i32,
+    pub(crate) bottom: i32,
+    pub(crate) right: i32,
+}
+
+#[allow(missing_docs)]
+impl Slice {
+    pub fn id(&self) -> i32 {
+        self.id
+    }
+
+    /// The name of the slice. Empty for slices that Photoshop generated automatically.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn top(&self) -> i32 {
+        self.top
+    }
+
+    pub fn left(&self) -> i32 {
+        self.left
+    }
+
+    pub fn bottom(&self) -> i32 {
+        self.bottom
+    }
+
+    pub fn right(&self) -> i32 {
+        self.right
+    }
+
+    /// Read a slice out of one of the descriptors in the `slices` list of a version 7 or 8
+    /// slices resource.
+    pub(crate) fn from_descriptor(descriptor: &DescriptorStructure) -> Slice {
+        let integer = |descriptor: &DescriptorStructure, key: &str| match descriptor.fields.get(key)
+        {
+            Some(DescriptorField::Integer(value)) => *value,
+            _ => 0,
+        };
+        let string = |key: &str| match descriptor.fields.get(key) {
+            Some(DescriptorField::String(value)) => trim_nul(value),
+            _ => String::new(),
+        };
+
+        let (top, left, bottom, right) = match descriptor.fields.get("bounds") {
+            Some(DescriptorField::Descriptor(bounds)) => (
+                integer(bounds, "Top "),
+                integer(bounds, "Left"),
+                integer(bounds, "Btom"),
+                integer(bounds, "Rght"),
+            ),
+            _ => (0, 0, 0, 0),
+        };
+
+        Slice {
+            id: integer(descriptor, "sliceID"),
+            name: string("Nm  "),
+            url: string("url"),
+            top,
+            left,
+            bottom,
+            right,
+        }
+    }
+}
+
+/// Unicode strings in slice resources are often terminated by a null character that we don't
+/// want to expose.
+pub(crate) fn trim_nul(string: &str) -> String {
+    string.trim_end_matches('\u{0}').to_string()
 }
@@ -0,0 +1,62 @@
+//! The resources that hold Photoshop's layer selection and layer group state.
+//!
+//! # [Adobe Docs](https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/)
+//!
+//! | ID   | Description                                                                                                       |
+//! |------|-------------------------------------------------------------------------------------------------------------------|
+//! | 1024 | Layer state information. 2 bytes containing the index of target layer (0 = bottom layer).                         |
+//! | 1026 | Layers group information. 2 bytes per layer containing a group ID for the dragging groups.                        |
+//! | 1069 | Layer Selection ID(s). 2 bytes count, following is repeated for each count: 4 bytes layer ID                      |
+//! | 1072 | Layer Group(s) Enabled ID. 1 byte for each layer in the document, repeated by length of the resource.             |
+//!
+//! The per layer resources have an entry for every layer record, including the hidden records
+//! that open and close groups, from the bottom record up.
+
+/// Read the index of the target layer (1024), or `None` if the data is too short
+pub(crate) fn read_target_layer(data: &[u8]) -> Option<u16> {
+    let index = data.get(..2)?;
+    Some(u16::from_be_bytes([index[0], index[1]]))
+}
+
+/// Read the dragging group ID of every layer record (1026)
+pub(crate) fn read_layer_groups(data: &[u8]) -> Vec<u16> {
+    data.chunks_exact(2)
+        .map(|id| u16::from_be_bytes([id[0], id[1]]))
+        .collect()
+}
+
+/// Read the IDs of the selected layers (1069). Reading stops at the first ID that runs past the
+/// end of the data, keeping the IDs before it.
+pub(crate) fn read_layer_selection_ids(data: &[u8]) -> Vec<u32> {
+    let count = match data.get(..2) {
+        Some(count) => u16::from_be_bytes([count[0], count[1]]) as usize,
+        None => return vec![],
+    };
+
+    data[2..]
+        .chunks_exact(4)
+        .take(count)
+        .map(|id| u32::from_be_bytes([id[0], id[1], id[2], id[3]]))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Verify that each resource is read, and that truncated data keeps what is there.
+    #[test]
+    fn read_layer_state() {
+        assert_eq!(read_target_layer(&[0, 5]), Some(5));
+        assert_eq!(read_target_layer(&[0]), None);
+
+        assert_eq!(read_layer_groups(&[0, 0, 0, 2, 1, 0]), vec![0, 2, 256]);
+
+        assert_eq!(
+            read_layer_selection_ids(&[0, 2, 0, 0, 0, 7, 0, 0, 1, 0]),
+            vec![7, 256]
+        );
+        assert_eq!(read_layer_selection_ids(&[0, 3, 0, 0, 0, 7, 0, 0]), vec![7]);
+        assert_eq!(read_layer_selection_ids(&[]), Vec::<u32>::new());
+    }
+}
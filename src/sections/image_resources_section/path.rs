@@ -0,0 +1,303 @@
+use std::fmt::Write;
+
+use crate::sections::PsdCursor;
+
+/// Every path record is 26 bytes long
+const PATH_RECORD_LEN: usize = 26;
+/// The first ID of the resources that hold the paths saved in the paths panel
+pub(crate) const RESOURCE_FIRST_SAVED_PATH: i16 = 2000;
+/// The last ID of the resources that hold the paths saved in the paths panel
+pub(crate) const RESOURCE_LAST_SAVED_PATH: i16 = 2997;
+
+/// A path made of Bezier curves, such as a saved path or a layer's vector mask.
+///
+/// # [Adobe Docs](https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/)
+///
+/// A path is a series of 26 byte records that each start with a 2 byte selector:
+///
+/// | Selector | Description                            |
+/// |----------|----------------------------------------|
+/// | 0        | Closed subpath length record           |
+/// | 1        | Closed subpath Bezier knot, linked     |
+/// | 2        | Closed subpath Bezier knot, unlinked   |
+/// | 3        | Open subpath length record             |
+/// | 4        | Open subpath Bezier knot, linked       |
+/// | 5        | Open subpath Bezier knot, unlinked     |
+/// | 6        | Path fill rule record                  |
+/// | 7        | Clipboard record                       |
+/// | 8        | Initial fill rule record               |
+///
+/// The points of a knot are stored as vertical then horizontal signed 8.24 fixed point numbers,
+/// relative to the size of the document.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BezierPath {
+    pub(crate) subpaths: Vec<Subpath>,
+    pub(crate) starts_with_all_pixels: bool,
+}
+
+/// A series of Bezier knots, connected from one to the next.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Subpath {
+    pub(crate) closed: bool,
+    pub(crate) knots: Vec<BezierKnot>,
+}
+
+/// A point on a path along with the control points of the curves on either side of it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BezierKnot {
+    pub(crate) linked: bool,
+    pub(crate) preceding: PathPoint,
+    pub(crate) anchor: PathPoint,
+    pub(crate) leaving: PathPoint,
+}
+
+/// A point of a path, as fractions of the document's width and height.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PathPoint {
+    pub(crate) x: f64,
+    pub(crate) y: f64,
+}
+
+/// A path that was saved in the paths panel, from one of the path resources (2000 - 2997).
+#[derive(Debug, Clone, PartialEq)]
+pub struct SavedPath {
+    pub(crate) id: i16,
+    pub(crate) name: String,
+    pub(crate) path: BezierPath,
+}
+
+impl BezierPath {
+    /// Read path records until the end of the data. Records that we don't recognize are skipped,
+    /// as are knots that don't belong to a subpath.
+    pub(crate) fn read(data: &[u8]) -> BezierPath {
+        let mut path = BezierPath {
+            subpaths: vec![],
+            starts_with_all_pixels: false,
+        };
+        // The number of knots that the last subpath is still waiting for
+        let mut knots_left = 0;
+
+        for record in data.chunks_exact(PATH_RECORD_LEN) {
+            let mut cursor = PsdCursor::new(record);
+
+            match cursor.read_u16() {
+                selector @ 0 | selector @ 3 => {
+                    knots_left = cursor.read_u16() as usize;
+                    path.subpaths.push(Subpath {
+                        closed: selector == 0,
+                        knots: Vec::with_capacity(knots_left),
+                    });
+                }
+                selector @ 1 | selector @ 2 | selector @ 4 | selector @ 5 => {
+                    let subpath = match path.subpaths.last_mut() {
+                        Some(subpath) if knots_left > 0 => subpath,
+                        _ => continue,
+                    };
+                    knots_left -= 1;
+
+                    subpath.knots.push(BezierKnot {
+                        linked: selector == 1 || selector == 4,
+                        preceding: PathPoint::read(&mut cursor),
+                        anchor: PathPoint::read(&mut cursor),
+                        leaving: PathPoint::read(&mut cursor),
+                    });
+                }
+                8 => path.starts_with_all_pixels = cursor.read_u16() == 1,
+                _ => {}
+            }
+        }
+
+        path
+    }
+
+    /// The subpaths, in the order that they are stored
+    pub fn subpaths(&self) -> &[Subpath] {
+        &self.subpaths
+    }
+
+    /// Whether the path's fill starts with all pixels, rather than with none of them
+    pub fn starts_with_all_pixels(&self) -> bool {
+        self.starts_with_all_pixels
+    }
+
+    /// The path as the data of an SVG `<path>` element's `d` attribute, for a document of the
+    /// given size in pixels.
+    ///
+    /// Photoshop combines the subpaths with an even-odd fill rule, so the element should have
+    /// `fill-rule="evenodd"`.
+    pub fn to_svg_path_data(&self, width: u32, height: u32) -> String {
+        let (width, height) = (width as f64, height as f64);
+        let mut data = String::new();
+
+        let write_point = |data: &mut String, point: PathPoint| {
+            let _ = write!(data, " {} {}", point.x * width, point.y * height);
+        };
+
+        for subpath in self.subpaths.iter() {
+            let first = match subpath.knots.first() {
+                Some(first) => first,
+                None => continue,
+            };
+
+            if !data.is_empty() {
+                data.push(' ');
+            }
+            data.push('M');
+            write_point(&mut data, first.anchor);
+
+            let curve_to = |data: &mut String, from: &BezierKnot, to: &BezierKnot| {
+                data.push_str(" C");
+                write_point(data, from.leaving);
+                write_point(data, to.preceding);
+                write_point(data, to.anchor);
+            };
+
+            for pair in subpath.knots.windows(2) {
+                curve_to(&mut data, &pair[0], &pair[1]);
+            }
+            if subpath.closed {
+                curve_to(&mut data, subpath.knots.last().unwrap(), first);
+                data.push_str(" Z");
+            }
+        }
+
+        data
+    }
+}
+
+impl Subpath {
+    /// Whether the subpath's last knot connects back to its first
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    /// The knots of the subpath, in order
+    pub fn knots(&self) -> &[BezierKnot] {
+        &self.knots
+    }
+}
+
+impl BezierKnot {
+    /// Whether the control points are linked, so that moving one moves the other
+    pub fn is_linked(&self) -> bool {
+        self.linked
+    }
+
+    /// The control point of the curve from the previous knot
+    pub fn preceding(&self) -> PathPoint {
+        self.preceding
+    }
+
+    /// The point that the path goes through
+    pub fn anchor(&self) -> PathPoint {
+        self.anchor
+    }
+
+    /// The control point of the curve to the next knot
+    pub fn leaving(&self) -> PathPoint {
+        self.leaving
+    }
+}
+
+impl PathPoint {
+    fn read(cursor: &mut PsdCursor) -> PathPoint {
+        let y = read_fixed_point(cursor);
+        let x = read_fixed_point(cursor);
+
+        PathPoint { x, y }
+    }
+
+    /// The horizontal position, as a fraction of the document's width
+    pub fn x(&self) -> f64 {
+        self.x
+    }
+
+    /// The vertical position, as a fraction of the document's height
+    pub fn y(&self) -> f64 {
+        self.y
+    }
+}
+
+impl SavedPath {
+    /// The ID of the resource that the path was read from, from 2000 to 2997
+    pub fn id(&self) -> i16 {
+        self.id
+    }
+
+    /// The name of the path in the paths panel
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The path itself
+    pub fn path(&self) -> &BezierPath {
+        &self.path
+    }
+}
+
+/// A signed 8.24 fixed point number
+fn read_fixed_point(cursor: &mut PsdCursor) -> f64 {
+    cursor.read_i32() as f64 / (1 << 24) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(selector: u16, values: &[i32]) -> Vec<u8> {
+        let mut record = selector.to_be_bytes().to_vec();
+        for value in values {
+            record.extend_from_slice(&value.to_be_bytes());
+        }
+        record.resize(PATH_RECORD_LEN, 0);
+        record
+    }
+
+    /// A fixed point coordinate, as a fraction of the document
+    fn fixed(fraction: f64) -> i32 {
+        (fraction * (1 << 24) as f64) as i32
+    }
+
+    /// A knot without any curves into or out of it
+    fn corner(selector: u16, x: f64, y: f64) -> Vec<u8> {
+        let point = [fixed(y), fixed(x)];
+        record(selector, &[point, point, point].concat())
+    }
+
+    /// A closed triangle followed by an open line, with the fill and clipboard records that
+    /// Photoshop writes first.
+    #[test]
+    fn read_subpaths() {
+        let mut data = record(6, &[]);
+        data.extend(record(8, &[1 << 16]));
+        data.extend(record(0, &[3 << 16]));
+        data.extend(corner(2, 0., 0.));
+        data.extend(corner(2, 1., 0.));
+        data.extend(corner(1, 0.5, 1.));
+        data.extend(record(3, &[2 << 16]));
+        data.extend(corner(5, 0., 0.5));
+        data.extend(corner(5, 1., 0.5));
+        // A knot that doesn't belong to any subpath
+        data.extend(corner(5, 0.25, 0.25));
+
+        let path = BezierPath::read(&data);
+        assert!(path.starts_with_all_pixels());
+        assert_eq!(path.subpaths().len(), 2);
+
+        let triangle = &path.subpaths()[0];
+        assert!(triangle.is_closed());
+        assert_eq!(triangle.knots().len(), 3);
+        assert!(!triangle.knots()[0].is_linked());
+        assert!(triangle.knots()[2].is_linked());
+        assert_eq!(triangle.knots()[2].anchor(), PathPoint { x: 0.5, y: 1. });
+
+        let line = &path.subpaths()[1];
+        assert!(!line.is_closed());
+        assert_eq!(line.knots().len(), 2);
+
+        assert_eq!(
+            path.to_svg_path_data(4, 2),
+            "M 0 0 C 0 0 4 0 4 0 C 4 0 2 2 2 2 C 2 2 0 0 0 0 Z M 0 1 C 0 1 4 1 4 1"
+        );
+    }
+}
@@ -0,0 +1,153 @@
+use crate::sections::PsdCursor;
+
+/// The resolution info resource is always 16 bytes long
+const RESOLUTION_INFO_LEN: usize = 16;
+
+/// The physical resolution of the document, from the resolution info resource (1005).
+///
+/// # [Adobe Docs](https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/)
+///
+/// | Length | Description                                                 |
+/// |--------|-------------------------------------------------------------|
+/// | 4      | Horizontal resolution in pixels per inch, 16.16 fixed point |
+/// | 2      | Horizontal resolution unit: 1 = PPI, 2 = PPCM               |
+/// | 2      | Width unit: 1 = in, 2 = cm, 3 = pt, 4 = pica, 5 = column    |
+/// | 4      | Vertical resolution in pixels per inch, 16.16 fixed point   |
+/// | 2      | Vertical resolution unit: 1 = PPI, 2 = PPCM                 |
+/// | 2      | Height unit: 1 = in, 2 = cm, 3 = pt, 4 = pica, 5 = column   |
+///
+/// The resolutions are always stored in pixels per inch, whichever unit they're displayed in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ResolutionInfo {
+    pub(crate) horizontal_dpi: f64,
+    pub(crate) horizontal_unit: ResolutionUnit,
+    pub(crate) width_unit: DimensionUnit,
+    pub(crate) vertical_dpi: f64,
+    pub(crate) vertical_unit: ResolutionUnit,
+    pub(crate) height_unit: DimensionUnit,
+}
+
+/// The unit that Photoshop shows a resolution in.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum ResolutionUnit {
+    /// Pixels per inch
+    PixelsPerInch,
+    /// Pixels per centimeter
+    PixelsPerCentimeter,
+}
+
+/// The unit that Photoshop shows the width or height of the document in.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[allow(missing_docs)]
+pub enum DimensionUnit {
+    Inches,
+    Centimeters,
+    Points,
+    Picas,
+    Columns,
+}
+
+impl ResolutionInfo {
+    /// Read a resolution info resource, or `None` if it's too short. Units that we don't
+    /// recognize are read as inches.
+    pub(crate) fn read(data: &[u8]) -> Option<ResolutionInfo> {
+        if data.len() < RESOLUTION_INFO_LEN {
+            return None;
+        }
+        let mut cursor = PsdCursor::new(data);
+
+        Some(ResolutionInfo {
+            horizontal_dpi: read_fixed_point(&mut cursor),
+            horizontal_unit: ResolutionUnit::new(cursor.read_i16()),
+            width_unit: DimensionUnit::new(cursor.read_i16()),
+            vertical_dpi: read_fixed_point(&mut cursor),
+            vertical_unit: ResolutionUnit::new(cursor.read_i16()),
+            height_unit: DimensionUnit::new(cursor.read_i16()),
+        })
+    }
+
+    /// The number of pixels per inch across the document
+    pub fn horizontal_dpi(&self) -> f64 {
+        self.horizontal_dpi
+    }
+
+    /// The number of pixels per inch down the document
+    pub fn vertical_dpi(&self) -> f64 {
+        self.vertical_dpi
+    }
+
+    /// The unit that Photoshop shows the horizontal resolution in
+    pub fn horizontal_unit(&self) -> ResolutionUnit {
+        self.horizontal_unit
+    }
+
+    /// The unit that Photoshop shows the vertical resolution in
+    pub fn vertical_unit(&self) -> ResolutionUnit {
+        self.vertical_unit
+    }
+
+    /// The unit that Photoshop shows the width of the document in
+    pub fn width_unit(&self) -> DimensionUnit {
+        self.width_unit
+    }
+
+    /// The unit that Photoshop shows the height of the document in
+    pub fn height_unit(&self) -> DimensionUnit {
+        self.height_unit
+    }
+}
+
+impl ResolutionUnit {
+    fn new(unit: i16) -> ResolutionUnit {
+        match unit {
+            2 => ResolutionUnit::PixelsPerCentimeter,
+            _ => ResolutionUnit::PixelsPerInch,
+        }
+    }
+}
+
+impl DimensionUnit {
+    fn new(unit: i16) -> DimensionUnit {
+        match unit {
+            2 => DimensionUnit::Centimeters,
+            3 => DimensionUnit::Points,
+            4 => DimensionUnit::Picas,
+            5 => DimensionUnit::Columns,
+            _ => DimensionUnit::Inches,
+        }
+    }
+}
+
+/// An unsigned 16.16 fixed point number
+fn read_fixed_point(cursor: &mut PsdCursor) -> f64 {
+    cursor.read_u32() as f64 / (1 << 16) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Verify that the resolutions are read as 16.16 fixed point numbers along with their units.
+    #[test]
+    fn read_resolution_info() {
+        let mut data = vec![];
+        data.extend_from_slice(&((300u32 << 16) | (1 << 15)).to_be_bytes());
+        data.extend_from_slice(&[0, 2, 0, 3]);
+        data.extend_from_slice(&(72u32 << 16).to_be_bytes());
+        data.extend_from_slice(&[0, 1, 0, 9]);
+
+        let resolution = ResolutionInfo::read(&data).unwrap();
+
+        assert_eq!(resolution.horizontal_dpi(), 300.5);
+        assert_eq!(
+            resolution.horizontal_unit(),
+            ResolutionUnit::PixelsPerCentimeter
+        );
+        assert_eq!(resolution.width_unit(), DimensionUnit::Points);
+        assert_eq!(resolution.vertical_dpi(), 72.);
+        assert_eq!(resolution.vertical_unit(), ResolutionUnit::PixelsPerInch);
+        assert_eq!(resolution.height_unit(), DimensionUnit::Inches);
+
+        assert_eq!(ResolutionInfo::read(&data[..15]), None);
+    }
+}
@@ -0,0 +1,129 @@
+use crate::sections::PsdCursor;
+
+/// The header that comes before the thumbnail's pixels is always 28 bytes long
+const THUMBNAIL_HEADER_LEN: usize = 28;
+
+/// A small preview of the document, from the thumbnail resource (1036, or 1033 for files saved
+/// by Photoshop 4.0).
+///
+/// # [Adobe Docs](https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/)
+///
+/// | Length   | Description                                                |
+/// |----------|------------------------------------------------------------|
+/// | 4        | Format: 1 = JPEG RGB, 0 = raw RGB                          |
+/// | 4        | Width of the thumbnail in pixels                           |
+/// | 4        | Height of the thumbnail in pixels                          |
+/// | 4        | Width bytes: padded row bytes = (width * 24 + 31) / 32 * 4 |
+/// | 4        | Total size = width bytes * height * planes                 |
+/// | 4        | Size after compression, used for consistency check         |
+/// | 2        | Bits per pixel = 24                                        |
+/// | 2        | Number of planes = 1                                       |
+/// | Variable | JFIF data in RGB format (BGR for 1033)                     |
+#[derive(Debug, Clone, PartialEq)]
+pub struct Thumbnail {
+    pub(crate) format: ThumbnailFormat,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) row_bytes: u32,
+    pub(crate) is_bgr: bool,
+    pub(crate) data: Vec<u8>,
+}
+
+/// How the pixels of a [`Thumbnail`] are stored.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum ThumbnailFormat {
+    /// A JPEG (JFIF) file
+    Jpeg,
+    /// Uncompressed rows of 24 bit pixels, each padded to [`Thumbnail::row_bytes`]
+    Raw,
+}
+
+impl Thumbnail {
+    /// Read a thumbnail resource, or `None` if it's too short to hold the header. `is_bgr` is
+    /// set for the Photoshop 4.0 resource (1033), which stores the red and blue channels swapped.
+    pub(crate) fn read(data: &[u8], is_bgr: bool) -> Option<Thumbnail> {
+        if data.len() < THUMBNAIL_HEADER_LEN {
+            return None;
+        }
+        let mut cursor = PsdCursor::new(data);
+
+        let format = match cursor.read_u32() {
+            0 => ThumbnailFormat::Raw,
+            _ => ThumbnailFormat::Jpeg,
+        };
+        let width = cursor.read_u32();
+        let height = cursor.read_u32();
+        let row_bytes = cursor.read_u32();
+        let _total_size = cursor.read_u32();
+        let _compressed_size = cursor.read_u32();
+        let _bits_per_pixel = cursor.read_u16();
+        let _planes = cursor.read_u16();
+
+        Some(Thumbnail {
+            format,
+            width,
+            height,
+            row_bytes,
+            is_bgr,
+            data: data[THUMBNAIL_HEADER_LEN..].to_vec(),
+        })
+    }
+
+    /// How the thumbnail's pixels are stored. Photoshop always writes JPEGs.
+    pub fn format(&self) -> ThumbnailFormat {
+        self.format
+    }
+
+    /// The width of the thumbnail in pixels
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// The height of the thumbnail in pixels
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The number of bytes in each row of a raw thumbnail, including the padding that aligns
+    /// rows to 4 bytes
+    pub fn row_bytes(&self) -> u32 {
+        self.row_bytes
+    }
+
+    /// Whether the red and blue channels are swapped, which is the case for thumbnails saved by
+    /// Photoshop 4.0 (1033). Swap them back after decoding.
+    pub fn is_bgr(&self) -> bool {
+        self.is_bgr
+    }
+
+    /// The bytes of the JPEG file, or the raw rows of pixels, see [`Thumbnail::format`]
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Verify that the header is read and the pixels are everything after it.
+    #[test]
+    fn read_thumbnail() {
+        let mut data = vec![];
+        for value in [0u32, 3, 2, 12, 24, 24].iter() {
+            data.extend_from_slice(&value.to_be_bytes());
+        }
+        data.extend_from_slice(&[0, 24, 0, 1]);
+        data.extend_from_slice(&[7; 24]);
+
+        let thumbnail = Thumbnail::read(&data, true).unwrap();
+
+        assert_eq!(thumbnail.format(), ThumbnailFormat::Raw);
+        assert_eq!((thumbnail.width(), thumbnail.height()), (3, 2));
+        assert_eq!(thumbnail.row_bytes(), 12);
+        assert!(thumbnail.is_bgr());
+        assert_eq!(thumbnail.data(), &[7; 24][..]);
+
+        assert_eq!(Thumbnail::read(&data[..27], false), None);
+    }
+}
@@ -0,0 +1,140 @@
+/// A URL from the URL list resource (1054), such as the link of a slice.
+///
+/// # [Adobe Docs](https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/)
+///
+/// The resource is a 4 byte count followed by that many URLs, each of which is:
+///
+/// | Length   | Description                |
+/// |----------|----------------------------|
+/// | 4        | Long, usually `url `       |
+/// | 4        | ID                         |
+/// | Variable | Unicode string, the URL    |
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct UrlListEntry {
+    pub(crate) key: [u8; 4],
+    pub(crate) id: i32,
+    pub(crate) url: String,
+}
+
+impl UrlListEntry {
+    /// The four bytes that come before the ID. Photoshop doesn't document them, but they tend to
+    /// be the key of the descriptor field that the URL was set through.
+    pub fn key(&self) -> [u8; 4] {
+        self.key
+    }
+
+    /// The ID of the URL, which matches the ID of the slice that links to it
+    pub fn id(&self) -> i32 {
+        self.id
+    }
+
+    /// The URL itself
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Read the entries of a URL list resource. Reading stops at the first entry that runs past
+    /// the end of the data, keeping the entries before it.
+    pub(crate) fn read_list(data: &[u8]) -> Vec<UrlListEntry> {
+        let count = match read_u32(data) {
+            Some(count) => count,
+            None => return vec![],
+        };
+        let mut data = &data[4..];
+
+        let mut entries = vec![];
+        for _ in 0..count {
+            let (key, id) = match (data.get(..4), data.get(4..8)) {
+                (Some(key), Some(id)) => (
+                    [key[0], key[1], key[2], key[3]],
+                    i32::from_be_bytes([id[0], id[1], id[2], id[3]]),
+                ),
+                _ => break,
+            };
+            let (url, rest) = match read_unicode_string(&data[8..]) {
+                Some(read) => read,
+                None => break,
+            };
+
+            entries.push(UrlListEntry { key, id, url });
+            data = rest;
+        }
+
+        entries
+    }
+}
+
+/// Read a Unicode string (a 4 byte count of UTF-16 code units followed by the units) from the
+/// start of the data, dropping any trailing nul. Returns the data after the string.
+pub(crate) fn read_unicode_string(data: &[u8]) -> Option<(String, &[u8])> {
+    let len = read_u32(data)? as usize;
+    let end = len.checked_mul(2)?.checked_add(4)?;
+    let units: Vec<u16> = data
+        .get(4..end)?
+        .chunks_exact(2)
+        .map(|unit| u16::from_be_bytes([unit[0], unit[1]]))
+        .collect();
+
+    let string = String::from_utf16_lossy(&units)
+        .trim_end_matches('\0')
+        .to_string();
+    Some((string, &data[end..]))
+}
+
+fn read_u32(data: &[u8]) -> Option<u32> {
+    data.get(..4)
+        .map(|bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unicode_string(string: &str) -> Vec<u8> {
+        let units: Vec<u16> = string.encode_utf16().collect();
+        let mut bytes = (units.len() as u32).to_be_bytes().to_vec();
+        for unit in units {
+            bytes.extend_from_slice(&unit.to_be_bytes());
+        }
+        bytes
+    }
+
+    /// Verify that every URL in the list is read along with its key and ID.
+    #[test]
+    fn read_url_list() {
+        let mut data = 2u32.to_be_bytes().to_vec();
+        for (id, url) in [(1i32, "https://example.com\0"), (7, "mailto:a@b.c")].iter() {
+            data.extend_from_slice(b"url ");
+            data.extend_from_slice(&id.to_be_bytes());
+            data.extend(unicode_string(url));
+        }
+
+        let entries = UrlListEntry::read_list(&data);
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].key(), *b"url ");
+        assert_eq!(entries[0].id(), 1);
+        assert_eq!(entries[0].url(), "https://example.com");
+        assert_eq!(entries[1].id(), 7);
+        assert_eq!(entries[1].url(), "mailto:a@b.c");
+    }
+
+    /// Verify that an entry that runs past the end of the data is dropped without losing the
+    /// entries before it.
+    #[test]
+    fn truncated_url_list() {
+        let mut data = 2u32.to_be_bytes().to_vec();
+        data.extend_from_slice(b"url ");
+        data.extend_from_slice(&3u32.to_be_bytes());
+        data.extend(unicode_string("a"));
+        data.extend_from_slice(b"url ");
+        data.extend_from_slice(&4u32.to_be_bytes());
+        data.extend_from_slice(&100u32.to_be_bytes());
+
+        let entries = UrlListEntry::read_list(&data);
+
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].url(), "a");
+        assert!(read_unicode_string(&[0, 0]).is_none());
+    }
+}
@@ -0,0 +1,117 @@
+use std::convert::TryInto;
+
+use crate::sections::PsdCursor;
+
+/// Which application wrote the document and whether it saved a real final image, from the
+/// version info resource (1057).
+///
+/// # [Adobe Docs](https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/)
+///
+/// | Length   | Description                  |
+/// |----------|------------------------------|
+/// | 4        | Version                      |
+/// | 1        | hasRealMergedData            |
+/// | Variable | Unicode string: writer name  |
+/// | Variable | Unicode string: reader name  |
+/// | 4        | File version                 |
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct VersionInfo {
+    pub(crate) version: u32,
+    pub(crate) has_real_merged_data: bool,
+    pub(crate) writer_name: String,
+    pub(crate) reader_name: String,
+    pub(crate) file_version: u32,
+}
+
+impl VersionInfo {
+    /// Read a version info resource, or `None` if it ends before the file version.
+    pub(crate) fn read(data: &[u8]) -> Option<VersionInfo> {
+        let mut cursor = PsdCursor::new(data);
+
+        let version = u32::from_be_bytes(cursor.read_checked(4)?.try_into().ok()?);
+        let has_real_merged_data = cursor.read_checked(1)?[0] != 0;
+        let writer_name = read_unicode_string(&mut cursor)?;
+        let reader_name = read_unicode_string(&mut cursor)?;
+        let file_version = u32::from_be_bytes(cursor.read_checked(4)?.try_into().ok()?);
+
+        Some(VersionInfo {
+            version,
+            has_real_merged_data,
+            writer_name,
+            reader_name,
+            file_version,
+        })
+    }
+
+    /// The version of the resource, which is 1
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Whether the final image in the image data section is a real composite of the layers.
+    ///
+    /// Photoshop writes a blank final image when "Maximize PSD and PSB File Compatibility" is
+    /// turned off, in which case this is false.
+    pub fn has_real_merged_data(&self) -> bool {
+        self.has_real_merged_data
+    }
+
+    /// The name of the application that wrote the file, such as "Adobe Photoshop"
+    pub fn writer_name(&self) -> &str {
+        &self.writer_name
+    }
+
+    /// The name of the application that the file is meant to be read by, such as
+    /// "Adobe Photoshop CC"
+    pub fn reader_name(&self) -> &str {
+        &self.reader_name
+    }
+
+    /// The version of the file, which is 1
+    pub fn file_version(&self) -> u32 {
+        self.file_version
+    }
+}
+
+/// A 4 byte count of UTF-16 code units followed by the code units, without the padding that
+/// `PsdCursor::read_unicode_string` expects. Photoshop ends the names with a NUL, which is
+/// trimmed.
+fn read_unicode_string(cursor: &mut PsdCursor) -> Option<String> {
+    let len = u32::from_be_bytes(cursor.read_checked(4)?.try_into().ok()?);
+    let units: Vec<u16> = cursor
+        .read_checked(len as u64 * 2)?
+        .chunks_exact(2)
+        .map(|unit| u16::from_be_bytes([unit[0], unit[1]]))
+        .collect();
+
+    let string = String::from_utf16_lossy(&units);
+    Some(string.trim_end_matches('\0').to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Verify that every field is read, and that data that ends early isn't read.
+    #[test]
+    fn read_version_info() {
+        let mut data = vec![0, 0, 0, 1, 0];
+        for name in ["Adobe Photoshop", "Adobe Photoshop CC"].iter() {
+            data.extend_from_slice(&(name.len() as u32 + 1).to_be_bytes());
+            for unit in name.encode_utf16().chain(Some(0)) {
+                data.extend_from_slice(&unit.to_be_bytes());
+            }
+        }
+        data.extend_from_slice(&[0, 0, 0, 1]);
+
+        let version_info = VersionInfo::read(&data).unwrap();
+
+        assert_eq!(version_info.version(), 1);
+        assert!(!version_info.has_real_merged_data());
+        assert_eq!(version_info.writer_name(), "Adobe Photoshop");
+        assert_eq!(version_info.reader_name(), "Adobe Photoshop CC");
+        assert_eq!(version_info.file_version(), 1);
+
+        assert_eq!(VersionInfo::read(&data[..data.len() - 1]), None);
+    }
+}
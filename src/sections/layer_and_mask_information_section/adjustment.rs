@@ -0,0 +1,511 @@
+use crate::sections::PsdCursor;
+
+/// Key of `Brightness and Contrast`, "brit"
+const KEY_BRIGHTNESS_CONTRAST: &[u8; 4] = b"brit";
+/// Key of `Levels`, "levl"
+const KEY_LEVELS: &[u8; 4] = b"levl";
+/// Key of `Curves`, "curv"
+const KEY_CURVES: &[u8; 4] = b"curv";
+/// Key of `Hue/Saturation` (Photoshop 5.0 and later), "hue2"
+const KEY_HUE_SATURATION: &[u8; 4] = b"hue2";
+/// Key of `Exposure`, "expA"
+const KEY_EXPOSURE: &[u8; 4] = b"expA";
+
+/// The number of level records in a levels block
+const LEVELS_RECORD_COUNT: usize = 29;
+
+/// The adjustment that an adjustment layer applies to the layers below it, read from its
+/// adjustment layer tagged block.
+///
+/// Adjustment layers don't have any pixels of their own. Their adjustments are only applied when
+/// rendering with [`RenderPipeline::adjustment_layers`].
+///
+/// [`RenderPipeline::adjustment_layers`]: crate::RenderPipeline::adjustment_layers
+#[derive(Debug, Clone, PartialEq)]
+pub enum Adjustment {
+    /// Brightness/Contrast ('brit')
+    BrightnessContrast(BrightnessContrast),
+    /// Levels ('levl')
+    Levels(Levels),
+    /// Curves ('curv')
+    Curves(Curves),
+    /// Hue/Saturation ('hue2')
+    HueSaturation(HueSaturation),
+    /// Exposure ('expA')
+    Exposure(Exposure),
+    /// An adjustment that we don't parse, or that we couldn't read, identified by the key of its
+    /// tagged block, such as `selc` for selective color.
+    Other([u8; 4]),
+}
+
+impl Adjustment {
+    /// Read the data of an adjustment layer tagged block
+    pub(crate) fn read(key: &[u8; 4], data: &[u8]) -> Adjustment {
+        let adjustment = match key {
+            KEY_BRIGHTNESS_CONTRAST => {
+                BrightnessContrast::read(data).map(Adjustment::BrightnessContrast)
+            }
+            KEY_LEVELS => Levels::read(data).map(Adjustment::Levels),
+            KEY_CURVES => Curves::read(data).map(Adjustment::Curves),
+            KEY_HUE_SATURATION => HueSaturation::read(data).map(Adjustment::HueSaturation),
+            KEY_EXPOSURE => Exposure::read(data).map(Adjustment::Exposure),
+            _ => None,
+        };
+
+        adjustment.unwrap_or(Adjustment::Other(*key))
+    }
+}
+
+/// The settings of a brightness/contrast adjustment layer, read from its "brit" block.
+///
+/// # [Adobe Docs](https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/)
+///
+/// | Length | Description                             |
+/// |--------|-----------------------------------------|
+/// | 2      | Brightness                              |
+/// | 2      | Contrast                                |
+/// | 2      | Mean value for brightness and contrast  |
+/// | 1      | Lab color only                          |
+#[derive(Debug, Clone, PartialEq)]
+pub struct BrightnessContrast {
+    pub(crate) brightness: i16,
+    pub(crate) contrast: i16,
+    pub(crate) mean: i16,
+    pub(crate) lab_only: bool,
+}
+
+impl BrightnessContrast {
+    fn read(data: &[u8]) -> Option<BrightnessContrast> {
+        if data.len() < 7 {
+            return None;
+        }
+
+        let mut cursor = PsdCursor::new(data);
+
+        Some(BrightnessContrast {
+            brightness: cursor.read_i16(),
+            contrast: cursor.read_i16(),
+            mean: cursor.read_i16(),
+            lab_only: cursor.read_u8() != 0,
+        })
+    }
+
+    /// The brightness, from -150 to 150 (-100 to 100 for legacy adjustments)
+    pub fn brightness(&self) -> i16 {
+        self.brightness
+    }
+
+    /// The contrast, from -50 to 100 (-100 to 100 for legacy adjustments)
+    pub fn contrast(&self) -> i16 {
+        self.contrast
+    }
+
+    /// The mean value that the brightness and contrast are applied around
+    pub fn mean(&self) -> i16 {
+        self.mean
+    }
+
+    /// Whether the adjustment only applies to the lightness of Lab images
+    pub fn lab_only(&self) -> bool {
+        self.lab_only
+    }
+}
+
+/// The settings of a levels adjustment layer, read from its "levl" block.
+///
+/// # [Adobe Docs](https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/)
+///
+/// | Length  | Description                                                            |
+/// |---------|------------------------------------------------------------------------|
+/// | 2       | Version ( = 2)                                                         |
+/// | 29 * 10 | Level records. The first is the composite, followed by each channel    |
+///
+/// Each level record:
+///
+/// | Length | Description                              |
+/// |--------|------------------------------------------|
+/// | 2      | Input floor (0...253)                    |
+/// | 2      | Input ceiling (2...255)                  |
+/// | 2      | Output floor (0...255)                   |
+/// | 2      | Output ceiling (0...255)                 |
+/// | 2      | Gamma. Short integer from 10...999 representing 0.1...9.99 |
+#[derive(Debug, Clone, PartialEq)]
+pub struct Levels {
+    pub(crate) records: Vec<LevelsRecord>,
+}
+
+impl Levels {
+    fn read(data: &[u8]) -> Option<Levels> {
+        if data.len() < 2 + LEVELS_RECORD_COUNT * 10 {
+            return None;
+        }
+
+        let mut cursor = PsdCursor::new(data);
+        let _version = cursor.read_u16();
+
+        let records = (0..LEVELS_RECORD_COUNT)
+            .map(|_| LevelsRecord {
+                input_floor: cursor.read_u16(),
+                input_ceiling: cursor.read_u16(),
+                output_floor: cursor.read_u16(),
+                output_ceiling: cursor.read_u16(),
+                gamma: cursor.read_u16() as f64 / 100.,
+            })
+            .collect();
+
+        Some(Levels { records })
+    }
+
+    /// The levels of every channel, starting with the composite of all of the channels followed
+    /// by each channel in order, such as red, green and blue. Unused records are left at their
+    /// defaults.
+    pub fn records(&self) -> &[LevelsRecord] {
+        &self.records
+    }
+
+    /// The levels that apply to all of the channels
+    pub fn composite(&self) -> &LevelsRecord {
+        &self.records[0]
+    }
+}
+
+/// The levels of one channel
+#[derive(Debug, Clone, PartialEq)]
+pub struct LevelsRecord {
+    pub(crate) input_floor: u16,
+    pub(crate) input_ceiling: u16,
+    pub(crate) output_floor: u16,
+    pub(crate) output_ceiling: u16,
+    pub(crate) gamma: f64,
+}
+
+impl LevelsRecord {
+    /// The input value that becomes the output floor
+    pub fn input_floor(&self) -> u16 {
+        self.input_floor
+    }
+
+    /// The input value that becomes the output ceiling
+    pub fn input_ceiling(&self) -> u16 {
+        self.input_ceiling
+    }
+
+    /// The darkest output value
+    pub fn output_floor(&self) -> u16 {
+        self.output_floor
+    }
+
+    /// The brightest output value
+    pub fn output_ceiling(&self) -> u16 {
+        self.output_ceiling
+    }
+
+    /// The gamma of the midtones, from 0.1 to 9.99
+    pub fn gamma(&self) -> f64 {
+        self.gamma
+    }
+}
+
+/// The settings of a curves adjustment layer, read from its "curv" block.
+///
+/// # [Adobe Docs](https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/)
+///
+/// | Length   | Description                                                             |
+/// |----------|-------------------------------------------------------------------------|
+/// | 1        | Is map ( = 0 for curves made of points)                                 |
+/// | 2        | Version ( = 1 or 4)                                                     |
+/// | 4        | Bitmap of the curves in the file. The composite is bit 0, then each channel |
+/// | Variable | Each curve: count of points (2) followed by each point's output and input value (2 each) |
+#[derive(Debug, Clone, PartialEq)]
+pub struct Curves {
+    pub(crate) curves: Vec<Curve>,
+}
+
+impl Curves {
+    fn read(data: &[u8]) -> Option<Curves> {
+        if data.len() < 7 {
+            return None;
+        }
+
+        let mut cursor = PsdCursor::new(data);
+        let is_map = cursor.read_u8() != 0;
+        let _version = cursor.read_u16();
+        let channels = cursor.read_u32();
+
+        // Curves that map each of the 256 input values straight to an output value aren't
+        // made of points
+        if is_map {
+            return None;
+        }
+
+        let mut curves = vec![];
+        for channel in 0..32 {
+            if channels & (1 << channel) == 0 {
+                continue;
+            }
+
+            let point_count = cursor.read_checked(2)?;
+            let point_count = u16::from_be_bytes([point_count[0], point_count[1]]) as usize;
+            let points = cursor.read_checked(point_count as u64 * 4)?;
+
+            curves.push(Curve {
+                channel,
+                points: points
+                    .chunks_exact(4)
+                    .map(|point| {
+                        let output = u16::from_be_bytes([point[0], point[1]]);
+                        let input = u16::from_be_bytes([point[2], point[3]]);
+                        (input, output)
+                    })
+                    .collect(),
+            });
+        }
+
+        Some(Curves { curves })
+    }
+
+    /// Every curve that was changed from a straight line, in channel order
+    pub fn curves(&self) -> &[Curve] {
+        &self.curves
+    }
+}
+
+/// The curve of one channel
+#[derive(Debug, Clone, PartialEq)]
+pub struct Curve {
+    pub(crate) channel: u32,
+    pub(crate) points: Vec<(u16, u16)>,
+}
+
+impl Curve {
+    /// The channel that the curve applies to: 0 for the composite of all of the channels,
+    /// followed by each channel in order, such as 1 for red.
+    pub fn channel(&self) -> u32 {
+        self.channel
+    }
+
+    /// The points of the curve as (input, output) values from 0 to 255, from left to right
+    pub fn points(&self) -> &[(u16, u16)] {
+        &self.points
+    }
+}
+
+/// The settings of a hue/saturation adjustment layer, read from its "hue2" block.
+///
+/// # [Adobe Docs](https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/)
+///
+/// | Length | Description                                                         |
+/// |--------|---------------------------------------------------------------------|
+/// | 2      | Version ( = 2)                                                      |
+/// | 1      | 0 = Use settings for hue-adjustment; 1 = Use settings for colorization |
+/// | 1      | Padding byte                                                        |
+/// | 6      | Colorization: hue (-180...180), saturation (0...100), lightness (-100...100) |
+/// | 6      | Master hue, saturation and lightness values                         |
+/// | 6 * 14 | For each of the six color ranges: 4 range values followed by the hue, saturation and lightness |
+#[derive(Debug, Clone, PartialEq)]
+pub struct HueSaturation {
+    pub(crate) colorize: bool,
+    pub(crate) colorization: HueSaturationLightness,
+    pub(crate) master: HueSaturationLightness,
+    pub(crate) ranges: Vec<HueRange>,
+}
+
+impl HueSaturation {
+    fn read(data: &[u8]) -> Option<HueSaturation> {
+        if data.len() < 2 + 2 + 6 + 6 + 6 * 14 {
+            return None;
+        }
+
+        let mut cursor = PsdCursor::new(data);
+        let _version = cursor.read_u16();
+        let colorize = cursor.read_u8() != 0;
+        let _padding = cursor.read_u8();
+
+        let colorization = HueSaturationLightness::read(&mut cursor);
+        let master = HueSaturationLightness::read(&mut cursor);
+        let ranges = (0..6)
+            .map(|_| HueRange {
+                range: [
+                    cursor.read_i16(),
+                    cursor.read_i16(),
+                    cursor.read_i16(),
+                    cursor.read_i16(),
+                ],
+                settings: HueSaturationLightness::read(&mut cursor),
+            })
+            .collect();
+
+        Some(HueSaturation {
+            colorize,
+            colorization,
+            master,
+            ranges,
+        })
+    }
+
+    /// Whether the layers below are colorized with [`HueSaturation::colorization`] instead of
+    /// having their hue, saturation and lightness adjusted
+    pub fn colorize(&self) -> bool {
+        self.colorize
+    }
+
+    /// The color that the layers below are colorized with
+    pub fn colorization(&self) -> &HueSaturationLightness {
+        &self.colorization
+    }
+
+    /// The adjustment that applies to every color
+    pub fn master(&self) -> &HueSaturationLightness {
+        &self.master
+    }
+
+    /// The adjustments of the reds, yellows, greens, cyans, blues and magentas
+    pub fn ranges(&self) -> &[HueRange] {
+        &self.ranges
+    }
+}
+
+/// A hue from -180 to 180, a saturation from -100 to 100 (0 to 100 when colorizing) and a
+/// lightness from -100 to 100
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct HueSaturationLightness {
+    pub(crate) hue: i16,
+    pub(crate) saturation: i16,
+    pub(crate) lightness: i16,
+}
+
+impl HueSaturationLightness {
+    fn read(cursor: &mut PsdCursor) -> HueSaturationLightness {
+        HueSaturationLightness {
+            hue: cursor.read_i16(),
+            saturation: cursor.read_i16(),
+            lightness: cursor.read_i16(),
+        }
+    }
+
+    /// The hue
+    pub fn hue(&self) -> i16 {
+        self.hue
+    }
+
+    /// The saturation
+    pub fn saturation(&self) -> i16 {
+        self.saturation
+    }
+
+    /// The lightness
+    pub fn lightness(&self) -> i16 {
+        self.lightness
+    }
+}
+
+/// The adjustment of one range of colors
+#[derive(Debug, Clone, PartialEq)]
+pub struct HueRange {
+    pub(crate) range: [i16; 4],
+    pub(crate) settings: HueSaturationLightness,
+}
+
+impl HueRange {
+    /// The hues, in degrees, where the range starts fading in, is fully applied, stops being
+    /// fully applied and is faded out
+    pub fn range(&self) -> [i16; 4] {
+        self.range
+    }
+
+    /// The adjustment of the colors in the range
+    pub fn settings(&self) -> &HueSaturationLightness {
+        &self.settings
+    }
+}
+
+/// The settings of an exposure adjustment layer, read from its "expA" block.
+///
+/// # [Adobe Docs](https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/)
+///
+/// | Length | Description      |
+/// |--------|------------------|
+/// | 2      | Version ( = 1)   |
+/// | 4      | Exposure         |
+/// | 4      | Offset           |
+/// | 4      | Gamma            |
+#[derive(Debug, Clone, PartialEq)]
+pub struct Exposure {
+    pub(crate) exposure: f32,
+    pub(crate) offset: f32,
+    pub(crate) gamma: f32,
+}
+
+impl Exposure {
+    fn read(data: &[u8]) -> Option<Exposure> {
+        if data.len() < 14 {
+            return None;
+        }
+
+        let mut cursor = PsdCursor::new(data);
+        let _version = cursor.read_u16();
+        let mut read_f32 = || {
+            let bytes = cursor.read_4();
+            f32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+        };
+
+        Some(Exposure {
+            exposure: read_f32(),
+            offset: read_f32(),
+            gamma: read_f32(),
+        })
+    }
+
+    /// The exposure, in stops
+    pub fn exposure(&self) -> f32 {
+        self.exposure
+    }
+
+    /// How much the shadows and midtones are darkened or lightened
+    pub fn offset(&self) -> f32 {
+        self.offset
+    }
+
+    /// The gamma correction
+    pub fn gamma(&self) -> f32 {
+        self.gamma
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Verify that we read the composite curve and the curve of the green channel.
+    #[test]
+    fn reads_curves() {
+        let mut data = vec![0, 0, 1, 0, 0, 0, 0b101];
+        data.extend_from_slice(&[0, 2, 0, 0, 0, 0, 0, 200, 0, 255]);
+        data.extend_from_slice(&[0, 3, 0, 0, 0, 0, 0, 64, 0, 128, 0, 255, 0, 255]);
+
+        let curves = match Adjustment::read(b"curv", &data) {
+            Adjustment::Curves(curves) => curves,
+            other => panic!("Expected curves, got {:?}", other),
+        };
+
+        assert_eq!(curves.curves().len(), 2);
+        assert_eq!(curves.curves()[0].channel(), 0);
+        assert_eq!(curves.curves()[0].points(), &[(0, 0), (255, 200)]);
+        assert_eq!(curves.curves()[1].channel(), 2);
+        assert_eq!(curves.curves()[1].points()[1], (128, 64));
+    }
+
+    /// Truncated blocks and blocks that we don't parse are reported by their key.
+    #[test]
+    fn other_adjustments() {
+        assert_eq!(
+            Adjustment::read(b"curv", &[0, 0, 1, 0, 0, 0, 1, 0, 5]),
+            Adjustment::Other(*b"curv")
+        );
+        assert_eq!(
+            Adjustment::read(b"levl", &[0, 2]),
+            Adjustment::Other(*b"levl")
+        );
+        assert_eq!(Adjustment::read(b"selc", &[]), Adjustment::Other(*b"selc"));
+    }
+}
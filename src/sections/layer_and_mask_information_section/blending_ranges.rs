@@ -0,0 +1,200 @@
+use crate::blend::{self, Pixel};
+
+/// The "Blend If" sliders of a layer, which hide the layer's pixels depending on their own
+/// values and the values of the pixels below them.
+///
+/// # [Adobe Docs](https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/)
+///
+/// | Length   | Description                                                                 |
+/// |----------|-----------------------------------------------------------------------------|
+/// | 4        | Composite gray blend source. Contains 2 black values followed by 2 white values. Present but irrelevant for Lab & Grayscale. |
+/// | 4        | Composite gray blend destination range                                      |
+/// | 4        | First channel source range                                                  |
+/// | 4        | First channel destination range                                             |
+/// | ...      | The source and destination ranges of the rest of the channels               |
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlendingRanges {
+    pub(crate) gray: BlendingRange,
+    pub(crate) channels: Vec<BlendingRange>,
+}
+
+/// The "This Layer" (source) and "Underlying Layer" (destination) sliders of a gray or color
+/// channel.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct BlendingRange {
+    pub(crate) source: BlendIfRange,
+    pub(crate) destination: BlendIfRange,
+}
+
+/// The values of a channel that a layer's pixels show at.
+///
+/// Each end of the range can be split in two, so that the pixels fade in between the two halves
+/// instead of being cut off.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct BlendIfRange {
+    pub(crate) black: (u8, u8),
+    pub(crate) white: (u8, u8),
+}
+
+impl BlendingRanges {
+    /// Read the blending ranges of a layer record. Returns `None` if the record doesn't have the
+    /// gray ranges, which every other range follows.
+    pub(crate) fn read(data: &[u8]) -> Option<BlendingRanges> {
+        let mut ranges = data.chunks_exact(8).map(|range| BlendingRange {
+            source: BlendIfRange::new(&range[..4]),
+            destination: BlendIfRange::new(&range[4..]),
+        });
+
+        Some(BlendingRanges {
+            gray: ranges.next()?,
+            channels: ranges.collect(),
+        })
+    }
+
+    /// The ranges of the composite gray of the pixels
+    pub fn gray(&self) -> BlendingRange {
+        self.gray
+    }
+
+    /// The ranges of each of the layer's color channels, in the order of the PSD's color mode,
+    /// such as red, green and blue.
+    pub fn channels(&self) -> &[BlendingRange] {
+        &self.channels
+    }
+
+    /// Whether every range shows every pixel, in which case the ranges don't change anything
+    pub fn is_default(&self) -> bool {
+        self.gray.is_default() && self.channels.iter().all(|range| range.is_default())
+    }
+
+    /// How much of the layer's pixel the ranges show, from 0 to 255, given the pixel below it.
+    ///
+    /// The gray ranges are compared with the luminosity of the pixels and the first three
+    /// channel ranges with their red, green and blue. Without a pixel below, the destination
+    /// ranges don't hide anything.
+    pub(crate) fn opacity(&self, source: Pixel, destination: Option<Pixel>) -> u8 {
+        let mut opacity = self.gray.opacity(gray(source), destination.map(gray));
+
+        for (channel, range) in self.channels.iter().take(3).enumerate() {
+            let channel_opacity =
+                range.opacity(source[channel], destination.map(|pixel| pixel[channel]));
+            opacity = blend::multiply_alpha(opacity, channel_opacity);
+        }
+
+        opacity
+    }
+}
+
+impl BlendingRange {
+    /// The range of the layer's own pixels
+    pub fn source(&self) -> BlendIfRange {
+        self.source
+    }
+
+    /// The range of the pixels below the layer
+    pub fn destination(&self) -> BlendIfRange {
+        self.destination
+    }
+
+    /// Whether both ranges show every pixel
+    pub fn is_default(&self) -> bool {
+        self.source.is_default() && self.destination.is_default()
+    }
+
+    fn opacity(&self, source: u8, destination: Option<u8>) -> u8 {
+        let opacity = self.source.opacity(source);
+
+        match destination {
+            Some(destination) => {
+                blend::multiply_alpha(opacity, self.destination.opacity(destination))
+            }
+            None => opacity,
+        }
+    }
+}
+
+impl BlendIfRange {
+    fn new(data: &[u8]) -> BlendIfRange {
+        BlendIfRange {
+            black: (data[0], data[1]),
+            white: (data[2], data[3]),
+        }
+    }
+
+    /// The (start, end) of the black end of the range. Values below the start are hidden, and
+    /// values between the start and the end fade in.
+    pub fn black(&self) -> (u8, u8) {
+        self.black
+    }
+
+    /// The (start, end) of the white end of the range. Values between the start and the end fade
+    /// out, and values above the end are hidden.
+    pub fn white(&self) -> (u8, u8) {
+        self.white
+    }
+
+    /// Whether the range shows every value, from 0 to 255
+    pub fn is_default(&self) -> bool {
+        self.black == (0, 0) && self.white == (255, 255)
+    }
+
+    /// How much a value shows, from 0 to 255
+    fn opacity(&self, value: u8) -> u8 {
+        let (black_start, black_end) = self.black;
+        let (white_start, white_end) = self.white;
+
+        if value < black_start || value > white_end {
+            0
+        } else if value < black_end {
+            fraction(value - black_start, black_end - black_start)
+        } else if value > white_start {
+            fraction(white_end - value, white_end - white_start)
+        } else {
+            255
+        }
+    }
+}
+
+/// `numerator / denominator`, from 0 to 255
+fn fraction(numerator: u8, denominator: u8) -> u8 {
+    ((numerator as u32 * 255 + denominator as u32 / 2) / denominator as u32) as u8
+}
+
+/// The luminosity of a pixel, which the gray ranges are compared with
+fn gray([r, g, b, _]: Pixel) -> u8 {
+    ((r as u32 * 30 + g as u32 * 59 + b as u32 * 11 + 50) / 100) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Verify that values fade in and out between the halves of split sliders.
+    #[test]
+    fn split_sliders() {
+        let range = BlendIfRange {
+            black: (50, 100),
+            white: (200, 250),
+        };
+
+        assert_eq!(range.opacity(49), 0);
+        assert_eq!(range.opacity(75), 128);
+        assert_eq!(range.opacity(150), 255);
+        assert_eq!(range.opacity(225), 128);
+        assert_eq!(range.opacity(251), 0);
+    }
+
+    /// The gray destination range hides the layer over light pixels, but not where there is
+    /// nothing below the layer.
+    #[test]
+    fn destination_range() {
+        let ranges = BlendingRanges::read(&[0, 0, 255, 255, 0, 0, 128, 128]).unwrap();
+        assert!(!ranges.is_default());
+        assert!(ranges.channels().is_empty());
+
+        let red = [255, 0, 0, 255];
+        assert_eq!(ranges.opacity(red, Some([0, 0, 0, 255])), 255);
+        assert_eq!(ranges.opacity(red, Some([255; 4])), 0);
+        assert_eq!(ranges.opacity(red, None), 255);
+    }
+}
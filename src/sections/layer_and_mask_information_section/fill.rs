@@ -0,0 +1,357 @@
+use crate::sections::image_resources_section::{DescriptorField, DescriptorStructure};
+use crate::sections::layer_and_mask_information_section::layer_effects::Fields;
+use crate::sections::PsdCursor;
+
+/// Key of `Solid color sheet setting (Photoshop 6.0)`, "SoCo"
+pub(crate) const KEY_SOLID_COLOR: &[u8; 4] = b"SoCo";
+/// Key of `Gradient fill setting (Photoshop 6.0)`, "GdFl"
+pub(crate) const KEY_GRADIENT: &[u8; 4] = b"GdFl";
+/// Key of `Pattern fill setting (Photoshop 6.0)`, "PtFl"
+pub(crate) const KEY_PATTERN: &[u8; 4] = b"PtFl";
+
+/// The locations of gradient stops go from 0 to this
+const GRADIENT_LOCATION_SCALE: f64 = 4096.;
+
+/// The content of a fill layer, read from its solid color ("SoCo"), gradient ("GdFl") or
+/// pattern ("PtFl") fill setting block.
+///
+/// Fill layers often don't have any pixels of their own, so fills are rendered from these
+/// settings. Pattern fills repeat one of the PSD's patterns, see [`Psd::patterns`].
+///
+/// [`Psd::patterns`]: crate::Psd::patterns
+///
+/// # [Adobe Docs](https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/)
+///
+/// | Length   | Description                                 |
+/// |----------|---------------------------------------------|
+/// | 4        | Version ( = 16 for Photoshop 6.0)           |
+/// | Variable | Descriptor (see See Descriptor structure)   |
+#[derive(Debug, Clone, PartialEq)]
+pub enum Fill {
+    /// A solid color fill ('SoCo')
+    SolidColor(SolidColorFill),
+    /// A gradient fill ('GdFl')
+    Gradient(GradientFill),
+    /// A pattern fill ('PtFl')
+    Pattern(PatternFill),
+}
+
+impl Fill {
+    /// Read the data of a fill setting block. Returns `None` if the key isn't a fill setting, or
+    /// if the data is too short to hold a descriptor or the descriptor is invalid.
+    pub(crate) fn read(key: &[u8; 4], data: &[u8]) -> Option<Fill> {
+        if data.len() < 4 {
+            return None;
+        }
+
+        let mut cursor = PsdCursor::new(data);
+        let _version = cursor.read_u32();
+        let descriptor = DescriptorStructure::read_descriptor_structure(&mut cursor).ok()?;
+
+        match key {
+            KEY_SOLID_COLOR => Some(Fill::SolidColor(SolidColorFill::new(descriptor))),
+            KEY_GRADIENT => Some(Fill::Gradient(GradientFill::new(descriptor))),
+            KEY_PATTERN => Some(Fill::Pattern(PatternFill::new(descriptor))),
+            _ => None,
+        }
+    }
+
+    /// The whole fill descriptor, including the settings that we don't parse
+    pub fn descriptor(&self) -> &DescriptorStructure {
+        match self {
+            Fill::SolidColor(fill) => &fill.descriptor,
+            Fill::Gradient(fill) => &fill.descriptor,
+            Fill::Pattern(fill) => &fill.descriptor,
+        }
+    }
+}
+
+/// A fill with a single color
+#[derive(Debug, Clone, PartialEq)]
+pub struct SolidColorFill {
+    pub(crate) color: [u8; 3],
+    pub(crate) descriptor: DescriptorStructure,
+}
+
+impl SolidColorFill {
+    fn new(descriptor: DescriptorStructure) -> SolidColorFill {
+        SolidColorFill {
+            color: Fields(&descriptor).color(),
+            descriptor,
+        }
+    }
+
+    /// The color as RGB
+    pub fn color(&self) -> [u8; 3] {
+        self.color
+    }
+}
+
+/// The shape that a gradient's colors follow
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum GradientKind {
+    /// Along a straight line ('Lnr ')
+    Linear,
+    /// Out from the center in circles ('Rdl ')
+    Radial,
+    /// Around the center, counterclockwise ('Angl')
+    Angle,
+    /// Along a straight line that is mirrored at the center ('Rflc')
+    Reflected,
+    /// Out from the center in diamonds ('Dmnd')
+    Diamond,
+}
+
+/// A fill that blends between colors
+#[derive(Debug, Clone, PartialEq)]
+pub struct GradientFill {
+    pub(crate) kind: GradientKind,
+    pub(crate) angle: f64,
+    pub(crate) scale: f64,
+    pub(crate) reverse: bool,
+    pub(crate) dither: bool,
+    pub(crate) align_with_layer: bool,
+    pub(crate) offset: (f64, f64),
+    pub(crate) color_stops: Vec<ColorStop>,
+    pub(crate) transparency_stops: Vec<TransparencyStop>,
+    pub(crate) descriptor: DescriptorStructure,
+}
+
+impl GradientFill {
+    fn new(descriptor: DescriptorStructure) -> GradientFill {
+        let fields = Fields(&descriptor);
+
+        let kind = match fields.enumerated("Type") {
+            Some(b"Rdl ") => GradientKind::Radial,
+            Some(b"Angl") => GradientKind::Angle,
+            Some(b"Rflc") => GradientKind::Reflected,
+            Some(b"Dmnd") => GradientKind::Diamond,
+            _ => GradientKind::Linear,
+        };
+        let offset = fields
+            .descriptor("Ofst")
+            .map(|offset| {
+                (
+                    offset.unit_float("Hrzn").unwrap_or(0.),
+                    offset.unit_float("Vrtc").unwrap_or(0.),
+                )
+            })
+            .unwrap_or((0., 0.));
+
+        // Noise gradients ("ClNs") are generated from a seed instead of from stops
+        let (color_stops, transparency_stops) = match fields.descriptor("Grad") {
+            Some(gradient) if gradient.enumerated("GrdF") != Some(b"ClNs") => (
+                stops(gradient.list("Clrs"))
+                    .map(|(stop, location, midpoint)| ColorStop {
+                        location,
+                        midpoint,
+                        color: stop.color(),
+                    })
+                    .collect(),
+                stops(gradient.list("Trns"))
+                    .map(|(stop, location, midpoint)| TransparencyStop {
+                        location,
+                        midpoint,
+                        opacity: stop.opacity(),
+                    })
+                    .collect(),
+            ),
+            _ => (vec![], vec![]),
+        };
+
+        GradientFill {
+            kind,
+            angle: fields.unit_float("Angl").unwrap_or(0.),
+            scale: fields.unit_float("Scl ").unwrap_or(100.),
+            reverse: fields.boolean("Rvrs").unwrap_or(false),
+            dither: fields.boolean("Dthr").unwrap_or(false),
+            align_with_layer: fields.boolean("Algn").unwrap_or(true),
+            offset,
+            color_stops,
+            transparency_stops,
+            descriptor,
+        }
+    }
+
+    /// The shape of the gradient
+    pub fn kind(&self) -> GradientKind {
+        self.kind
+    }
+
+    /// The direction of the gradient in degrees, counterclockwise from pointing right
+    pub fn angle(&self) -> f64 {
+        self.angle
+    }
+
+    /// The percentage that the gradient is scaled by
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    /// Whether the gradient goes from its last stop to its first
+    pub fn reverse(&self) -> bool {
+        self.reverse
+    }
+
+    /// Whether the gradient is dithered
+    pub fn dither(&self) -> bool {
+        self.dither
+    }
+
+    /// Whether the gradient spans the layer's bounds rather than the PSD's
+    pub fn align_with_layer(&self) -> bool {
+        self.align_with_layer
+    }
+
+    /// How far the center of the gradient is moved (horizontally, vertically), as percentages of
+    /// the width and height
+    pub fn offset(&self) -> (f64, f64) {
+        self.offset
+    }
+
+    /// The colors of the gradient, from its start to its end. Empty for noise gradients.
+    pub fn color_stops(&self) -> &[ColorStop] {
+        &self.color_stops
+    }
+
+    /// The opacity of the gradient, from its start to its end. Empty for noise gradients.
+    pub fn transparency_stops(&self) -> &[TransparencyStop] {
+        &self.transparency_stops
+    }
+}
+
+/// The descriptor of each stop of a gradient with its location and midpoint, sorted by location.
+fn stops(items: &[DescriptorField]) -> impl Iterator<Item = (Fields<'_>, f64, f64)> {
+    let mut stops: Vec<_> = items
+        .iter()
+        .filter_map(|item| match item {
+            DescriptorField::Descriptor(stop) => {
+                let stop = Fields(stop);
+                let location = stop.integer("Lctn").unwrap_or(0) as f64 / GRADIENT_LOCATION_SCALE;
+                let midpoint = stop.integer("Mdpn").unwrap_or(50) as f64 / 100.;
+
+                Some((stop, location.clamp(0., 1.), midpoint.clamp(0., 1.)))
+            }
+            _ => None,
+        })
+        .collect();
+    stops.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+
+    stops.into_iter()
+}
+
+/// A color at a location along a gradient
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColorStop {
+    pub(crate) location: f64,
+    pub(crate) midpoint: f64,
+    pub(crate) color: [u8; 3],
+}
+
+impl ColorStop {
+    /// Where the stop is along the gradient, from 0 to 1
+    pub fn location(&self) -> f64 {
+        self.location
+    }
+
+    /// Where the color is halfway between the previous stop and this one, as a fraction of the
+    /// distance between them
+    pub fn midpoint(&self) -> f64 {
+        self.midpoint
+    }
+
+    /// The color as RGB. Stops that use the foreground or background color are black, since
+    /// those colors aren't saved with the gradient.
+    pub fn color(&self) -> [u8; 3] {
+        self.color
+    }
+}
+
+/// An opacity at a location along a gradient
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TransparencyStop {
+    pub(crate) location: f64,
+    pub(crate) midpoint: f64,
+    pub(crate) opacity: u8,
+}
+
+impl TransparencyStop {
+    /// Where the stop is along the gradient, from 0 to 1
+    pub fn location(&self) -> f64 {
+        self.location
+    }
+
+    /// Where the opacity is halfway between the previous stop and this one, as a fraction of the
+    /// distance between them
+    pub fn midpoint(&self) -> f64 {
+        self.midpoint
+    }
+
+    /// The opacity, from 0 to 255
+    pub fn opacity(&self) -> u8 {
+        self.opacity
+    }
+}
+
+/// A fill that repeats a pattern
+#[derive(Debug, Clone, PartialEq)]
+pub struct PatternFill {
+    pub(crate) name: String,
+    pub(crate) id: String,
+    pub(crate) scale: f64,
+    pub(crate) phase: (f64, f64),
+    pub(crate) descriptor: DescriptorStructure,
+}
+
+impl PatternFill {
+    fn new(descriptor: DescriptorStructure) -> PatternFill {
+        let fields = Fields(&descriptor);
+        let pattern = fields.descriptor("Ptrn");
+        let pattern_string = |key: &str| {
+            pattern
+                .and_then(|pattern| pattern.string(key))
+                .unwrap_or("")
+                .trim_end_matches('\0')
+                .to_string()
+        };
+
+        PatternFill {
+            name: pattern_string("Nm  "),
+            id: pattern_string("Idnt"),
+            scale: fields.unit_float("Scl ").unwrap_or(100.),
+            phase: fields
+                .descriptor("phase")
+                .map(|phase| {
+                    (
+                        phase.unit_float("Hrzn").unwrap_or(0.),
+                        phase.unit_float("Vrtc").unwrap_or(0.),
+                    )
+                })
+                .unwrap_or((0., 0.)),
+            descriptor,
+        }
+    }
+
+    /// The name of the pattern
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The ID of the pattern in the PSD's patterns, see [`Pattern::id`]
+    ///
+    /// [`Pattern::id`]: crate::Pattern::id
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// The percentage that the pattern is scaled by
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    /// Where the first tile of the pattern starts (horizontally, vertically), in pixels from the
+    /// top left of the PSD
+    pub fn phase(&self) -> (f64, f64) {
+        self.phase
+    }
+}
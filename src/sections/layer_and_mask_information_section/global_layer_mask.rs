@@ -0,0 +1,111 @@
+use crate::sections::PsdCursor;
+
+/// The global layer mask info at the end of the layer and mask information section, which
+/// describes how Photoshop shows masks that are being edited as an overlay.
+///
+/// # [Adobe Docs](https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/)
+///
+/// | Length   | Description                                                                     |
+/// |----------|---------------------------------------------------------------------------------|
+/// | 4        | Length of global layer mask info section.                                       |
+/// | 2        | Overlay color space (undocumented).                                             |
+/// | 8        | 4 * 2 byte color components                                                     |
+/// | 2        | Opacity. 0 = transparent, 100 = opaque.                                         |
+/// | 1        | Kind. 0 = Color selected--i.e. inverted; 1 = Color protected; 128 = use value stored per layer. This value is preferred. The others are for backward compatibility with beta versions. |
+/// | Variable | Filler: zeros                                                                   |
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct GlobalLayerMask {
+    pub(crate) color_space: u16,
+    pub(crate) color: [u16; 4],
+    pub(crate) opacity: u16,
+    pub(crate) kind: GlobalLayerMaskKind,
+}
+
+/// What the overlay color of a mask marks
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum GlobalLayerMaskKind {
+    /// The color marks the selected parts of the mask, so the mask is inverted (0)
+    ColorSelected,
+    /// The color marks the protected parts of the mask (1)
+    ColorProtected,
+    /// Each layer's mask says what the color marks (128)
+    PerLayer,
+    /// A kind that isn't documented
+    Other(u8),
+}
+
+/// The length of the global layer mask info without its filler
+const GLOBAL_LAYER_MASK_LEN: usize = 13;
+
+impl GlobalLayerMask {
+    /// Read the global layer mask info, without its length marker. Returns `None` if the info is
+    /// too short, such as when the PSD doesn't have any.
+    pub(crate) fn read(data: &[u8]) -> Option<GlobalLayerMask> {
+        if data.len() < GLOBAL_LAYER_MASK_LEN {
+            return None;
+        }
+
+        let mut cursor = PsdCursor::new(data);
+        let color_space = cursor.read_u16();
+        let color = [
+            cursor.read_u16(),
+            cursor.read_u16(),
+            cursor.read_u16(),
+            cursor.read_u16(),
+        ];
+        let opacity = cursor.read_u16();
+        let kind = match cursor.read_u8() {
+            0 => GlobalLayerMaskKind::ColorSelected,
+            1 => GlobalLayerMaskKind::ColorProtected,
+            128 => GlobalLayerMaskKind::PerLayer,
+            kind => GlobalLayerMaskKind::Other(kind),
+        };
+
+        Some(GlobalLayerMask {
+            color_space,
+            color,
+            opacity,
+            kind,
+        })
+    }
+
+    /// The color space of the overlay color, which Adobe doesn't document
+    pub fn color_space(&self) -> u16 {
+        self.color_space
+    }
+
+    /// The four components of the overlay color, in the overlay color space
+    pub fn color(&self) -> [u16; 4] {
+        self.color
+    }
+
+    /// The opacity of the overlay, from 0 (transparent) to 100 (opaque)
+    pub fn opacity(&self) -> u16 {
+        self.opacity
+    }
+
+    /// What the overlay color marks
+    pub fn kind(&self) -> GlobalLayerMaskKind {
+        self.kind
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Verify that the filler is skipped and that info that is too short is ignored.
+    #[test]
+    fn read_global_layer_mask() {
+        let data = [0, 0, 255, 255, 0, 0, 0, 0, 0, 0, 0, 50, 128, 0, 0, 0];
+
+        let mask = GlobalLayerMask::read(&data).unwrap();
+        assert_eq!(mask.color_space(), 0);
+        assert_eq!(mask.color(), [65535, 0, 0, 0]);
+        assert_eq!(mask.opacity(), 50);
+        assert_eq!(mask.kind(), GlobalLayerMaskKind::PerLayer);
+
+        assert_eq!(GlobalLayerMask::read(&data[..12]), None);
+        assert_eq!(GlobalLayerMask::read(&[]), None);
+    }
+}
@@ -1,15 +1,21 @@
 use crate::PsdGroup;
 use std::collections::HashMap;
-use std::ops::Deref;
+use std::ops::Index;
 
-#[derive(Debug)]
-pub(crate) struct Groups {
+/// The groups of a PSD in the order that they are closed, from the top of the layers panel down
+/// with each group after the groups inside of it, along with lookups by ID and name.
+///
+/// See [`Psd::groups_in_order`] for the groups in the order of the layers panel.
+///
+/// [`Psd::groups_in_order`]: crate::Psd::groups_in_order
+#[derive(Debug, Clone, PartialEq)]
+pub struct Groups {
     groups: HashMap<u32, PsdGroup>,
     group_ids_in_order: Vec<u32>,
 }
 
 impl Groups {
-    pub fn with_capacity(capacity: usize) -> Self {
+    pub(crate) fn with_capacity(capacity: usize) -> Self {
         Groups {
             groups: HashMap::with_capacity(capacity),
             group_ids_in_order: Vec::with_capacity(capacity),
@@ -17,22 +23,111 @@ impl Groups {
     }
 
     /// Add a group to the list of groups, making it last in the order.
-    pub fn push(&mut self, group: PsdGroup) {
+    pub(crate) fn push(&mut self, group: PsdGroup) {
         self.group_ids_in_order.push(group.id);
 
         self.groups.insert(group.id, group);
     }
 
-    /// Get the group ID's in order (from bottom to top in a PSD file).
-    pub fn group_ids_in_order(&self) -> &Vec<u32> {
+    /// The number of groups
+    pub fn len(&self) -> usize {
+        self.group_ids_in_order.len()
+    }
+
+    /// Whether there aren't any groups
+    pub fn is_empty(&self) -> bool {
+        self.group_ids_in_order.is_empty()
+    }
+
+    /// Get a group by its ID, see [`PsdGroup::id`]
+    pub fn get(&self, id: &u32) -> Option<&PsdGroup> {
+        self.groups.get(id)
+    }
+
+    /// Whether there is a group with the given ID
+    pub fn contains(&self, id: &u32) -> bool {
+        self.groups.contains_key(id)
+    }
+
+    /// Get the last group with the given name, in the same order as [`Groups::iter`]
+    pub fn by_name(&self, name: &str) -> Option<&PsdGroup> {
+        self.iter().rev().find(|group| group.name() == name)
+    }
+
+    /// Iterate over the groups in the order that they are closed, see [`Groups`].
+    pub fn iter(&self) -> GroupsIter<'_> {
+        GroupsIter {
+            ids: self.group_ids_in_order.iter(),
+            groups: &self.groups,
+        }
+    }
+
+    /// Get the group ID's in the order that the groups are closed, see [`Groups`].
+    pub fn ids(&self) -> &[u32] {
         &self.group_ids_in_order
     }
+
+    /// Get the group ID's in the order that the groups are closed, see [`Groups`].
+    pub(crate) fn group_ids_in_order(&self) -> &Vec<u32> {
+        &self.group_ids_in_order
+    }
+
+    /// Iterate over the groups in stacking order, from the top of the layers panel to the
+    /// bottom, where each group comes before the groups inside of it.
+    pub(crate) fn iter_top_down(&self) -> impl Iterator<Item = &PsdGroup> {
+        // IDs are given out as the groups are opened, from the top of the layers panel down
+        let mut ids = self.group_ids_in_order.clone();
+        ids.sort_unstable();
+
+        ids.into_iter().map(move |id| &self.groups[&id])
+    }
+
+    /// Iterate over the groups mutably, in no particular order.
+    pub(crate) fn values_mut(&mut self) -> impl Iterator<Item = &mut PsdGroup> {
+        self.groups.values_mut()
+    }
+}
+
+impl Index<&u32> for Groups {
+    type Output = PsdGroup;
+
+    fn index(&self, id: &u32) -> &PsdGroup {
+        &self.groups[id]
+    }
+}
+
+impl<'a> IntoIterator for &'a Groups {
+    type Item = &'a PsdGroup;
+    type IntoIter = GroupsIter<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// An iterator over the groups of a PSD in the order that they are closed, see [`Groups::iter`]
+#[derive(Debug, Clone)]
+pub struct GroupsIter<'a> {
+    ids: std::slice::Iter<'a, u32>,
+    groups: &'a HashMap<u32, PsdGroup>,
 }
 
-impl Deref for Groups {
-    type Target = HashMap<u32, PsdGroup>;
+impl<'a> Iterator for GroupsIter<'a> {
+    type Item = &'a PsdGroup;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.ids.next().map(|id| &self.groups[id])
+    }
 
-    fn deref(&self) -> &Self::Target {
-        &self.groups
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.ids.size_hint()
     }
 }
+
+impl<'a> DoubleEndedIterator for GroupsIter<'a> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.ids.next_back().map(|id| &self.groups[id])
+    }
+}
+
+impl<'a> ExactSizeIterator for GroupsIter<'a> {}
@@ -1,20 +1,60 @@
 use std::collections::HashMap;
+use std::fmt;
 use std::ops::{Deref, Range};
+use std::sync::OnceLock;
 
 use thiserror::Error;
 
+use crate::blend::Normalized;
+use crate::color_stats::{AlphaSummary, ColorHistogram, LayerColorStats};
+#[cfg(feature = "unstable")]
+use crate::fidelity::ADJUSTMENT_KEYS;
+use crate::psd_channel::for_each_channel_row;
+use crate::psd_channel::samples_to_eight_bits;
 use crate::psd_channel::IntoRgba;
 use crate::psd_channel::PsdChannelCompression;
 use crate::psd_channel::PsdChannelError;
 use crate::psd_channel::PsdChannelKind;
+use crate::psd_channel::RleRows;
+use crate::rgba16;
+use crate::rgba_layout::RgbaLayout;
 use crate::sections::image_data_section::ChannelBytes;
+#[cfg(feature = "unstable")]
+use crate::sections::layer_and_mask_information_section::adjustment::Adjustment;
+use crate::sections::layer_and_mask_information_section::blending_ranges::BlendingRanges;
+#[cfg(feature = "unstable")]
+use crate::sections::layer_and_mask_information_section::fill::{
+    Fill, KEY_GRADIENT, KEY_PATTERN, KEY_SOLID_COLOR,
+};
+#[cfg(feature = "unstable")]
+use crate::sections::layer_and_mask_information_section::layer_effects::{
+    LayerEffects, KEY_OBJECT_BASED_EFFECTS,
+};
+use crate::sections::layer_and_mask_information_section::layer_mask::{
+    LayerMask, LayerMaskFlags, MaskRect,
+};
+use crate::sections::layer_and_mask_information_section::smart_object::{PlacedLayer, SmartFilter};
+use crate::sections::layer_and_mask_information_section::tagged_block::TaggedBlock;
+use crate::sections::layer_and_mask_information_section::text_layer::TextLayerInfo;
+#[cfg(feature = "unstable")]
+use crate::sections::layer_and_mask_information_section::vector_mask::{
+    VectorMask, KEY_VECTOR_MASK,
+};
+use crate::{CropRect, PsdDepth, PsdError};
+
+/// Key of the tagged block that holds the fill opacity
+const KEY_FILL_OPACITY: &[u8; 4] = b"iOpa";
+/// Key of the tagged block that says whether clipped layers are blended as a group
+const KEY_BLEND_CLIPPED_ELEMENTS: &[u8; 4] = b"clbl";
+/// Key of the tagged block that says whether interior effects are blended as a group
+const KEY_BLEND_INTERIOR_ELEMENTS: &[u8; 4] = b"infx";
 
 /// Information about a layer in a PSD file.
 ///
 /// TODO: I set all of these pub during a late evening of getting to get things working.
 /// Replace with private and accessor methods so that this crate is as locked down as possible
 /// (to allow us to be strict).
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct LayerProperties {
     /// The name of this layer
     pub(crate) name: String,
@@ -22,12 +62,12 @@ pub struct LayerProperties {
     pub(crate) layer_top: i32,
     /// The position of the left of the layer
     pub(crate) layer_left: i32,
-    /// The position of the bottom of the layer
+    /// The position of the bottom of the layer, inclusive. Above the top for empty layers.
     pub(crate) layer_bottom: i32,
-    /// The position of the right of the layer
+    /// The position of the right of the layer, inclusive. Left of the left for empty layers.
     pub(crate) layer_right: i32,
-    /// If true, the layer is marked as visible
-    pub(crate) visible: bool,
+    /// The flags of the layer record (visibility, transparency protection, ...)
+    pub(crate) flags: LayerFlags,
     /// The opacity of the layer
     pub(crate) opacity: u8,
     /// If true, the layer is clipping mask
@@ -40,10 +80,16 @@ pub struct LayerProperties {
     pub(crate) blend_mode: BlendMode,
     /// If layer is nested, contains parent group ID, otherwise `None`
     pub(crate) group_id: Option<u32>,
+    /// The ID from the layer's `lyid` block, if present
+    pub(crate) layer_id: Option<u32>,
+    /// The layer's mask, from the layer mask / adjustment layer data
+    pub(crate) layer_mask: Option<LayerMask>,
+    /// The parts of the layer record that we hold on to in order to write the record back out
+    pub(crate) record_data: LayerRecordData,
 }
 
 impl LayerProperties {
-    pub fn from_layer_record(
+    pub(crate) fn from_layer_record(
         name: String,
         layer_record: &LayerRecord,
         psd_width: u32,
@@ -58,11 +104,14 @@ impl LayerProperties {
             layer_right: layer_record.right,
             opacity: layer_record.opacity,
             clipping_mask: layer_record.clipping_base,
-            visible: layer_record.visible,
+            flags: layer_record.flags,
             blend_mode: layer_record.blend_mode,
             psd_width,
             psd_height,
             group_id,
+            layer_id: layer_record.layer_id,
+            layer_mask: LayerMask::read(&layer_record.record_data.mask_data),
+            record_data: layer_record.record_data.clone(),
         }
     }
 
@@ -71,21 +120,42 @@ impl LayerProperties {
         &self.name
     }
 
-    /// The width of the layer
+    /// The width of the layer, zero for empty layers
     pub fn width(&self) -> u16 {
         // If left is at 0 and right is at 4, the width is 5
-        (self.layer_right - self.layer_left) as u16 + 1
+        (self.layer_right - self.layer_left + 1).max(0) as u16
     }
 
-    /// The height of the layer
+    /// The height of the layer, zero for empty layers
     pub fn height(&self) -> u16 {
         // If top is at 0 and bottom is at 3, the height is 4
-        (self.layer_bottom - self.layer_top) as u16 + 1
+        (self.layer_bottom - self.layer_top + 1).max(0) as u16
+    }
+
+    /// Whether the layer's rectangle doesn't cover any pixels, such as for fully transparent
+    /// layers and adjustment layers.
+    ///
+    /// Empty layers have a width and height of zero. Their top and left are wherever the layer
+    /// record put them, which is usually (0, 0).
+    pub fn is_empty(&self) -> bool {
+        self.layer_bottom < self.layer_top || self.layer_right < self.layer_left
     }
 
     /// If true, the layer is marked as visible
     pub fn visible(&self) -> bool {
-        self.visible
+        self.flags.visible()
+    }
+
+    /// The flags of the layer record
+    pub fn flags(&self) -> LayerFlags {
+        self.flags
+    }
+
+    /// The unique ID that Photoshop assigned to the layer, if the file contains one.
+    ///
+    /// This ID stays the same when layers are moved or renamed.
+    pub fn layer_id(&self) -> Option<u32> {
+        self.layer_id
     }
 
     /// The position of the top of the layer
@@ -113,6 +183,45 @@ impl LayerProperties {
         self.opacity
     }
 
+    /// The opacity of the layer from 0.0 to 100.0, the way that Photoshop shows it. See
+    /// [`Psd::set_opacity_percent`].
+    ///
+    /// [`Psd::set_opacity_percent`]: crate::Psd::set_opacity_percent
+    pub fn opacity_percent(&self) -> f32 {
+        Normalized::from_channel(self.opacity).0 * 100.
+    }
+
+    /// The opacity of the layer's own pixels, from the "iOpa" block. Unlike [`Self::opacity`]
+    /// it doesn't fade the layer's effects. 255 if the layer doesn't have a fill opacity.
+    pub fn fill_opacity(&self) -> u8 {
+        self.raw_tagged_block(KEY_FILL_OPACITY)
+            .and_then(|data| data.first().copied())
+            .unwrap_or(255)
+    }
+
+    /// Whether the layers clipped to this one are blended with it before it's blended with the
+    /// layers below, from the "clbl" block. True if the layer doesn't say.
+    pub fn blend_clipped_elements(&self) -> bool {
+        self.raw_tagged_block(KEY_BLEND_CLIPPED_ELEMENTS)
+            .and_then(|data| data.first())
+            .map(|blend| *blend != 0)
+            .unwrap_or(true)
+    }
+
+    /// Whether the layer's interior effects, such as its color overlay, are faded by its fill
+    /// opacity along with its pixels, from the "infx" block. False if the layer doesn't say.
+    pub fn blend_interior_elements(&self) -> bool {
+        self.raw_tagged_block(KEY_BLEND_INTERIOR_ELEMENTS)
+            .and_then(|data| data.first())
+            .map(|blend| *blend != 0)
+            .unwrap_or(false)
+    }
+
+    /// The layer's "Blend If" sliders, or `None` if the layer record doesn't have any
+    pub fn blending_ranges(&self) -> Option<BlendingRanges> {
+        BlendingRanges::read(&self.record_data.blending_ranges)
+    }
+
     /// If true, the layer is clipping mask
     pub fn is_clipping_mask(&self) -> bool {
         self.clipping_mask
@@ -127,10 +236,73 @@ impl LayerProperties {
     pub fn parent_id(&self) -> Option<u32> {
         self.group_id
     }
+
+    /// The layer's user and vector masks, if it has any
+    pub fn mask(&self) -> Option<&LayerMask> {
+        self.layer_mask.as_ref()
+    }
+
+    /// Whether the layer's user mask is linked to the layer, so that moving the layer should also
+    /// move the mask, like in Photoshop. `None` if the layer doesn't have a mask.
+    pub fn is_mask_linked(&self) -> Option<bool> {
+        let mask = self.layer_mask.as_ref()?;
+
+        Some(match mask.real_user_mask() {
+            Some(real) => real.is_linked(),
+            None => mask.is_linked(),
+        })
+    }
+
+    /// The data of the first tagged block in the layer record with the key, such as `lspf` for
+    /// the layer's lock settings, including the blocks that we don't parse.
+    ///
+    /// See [`Psd::set_raw_tagged_block`] for patching a block before writing the PSD.
+    ///
+    /// [`Psd::set_raw_tagged_block`]: crate::Psd::set_raw_tagged_block
+    pub fn raw_tagged_block(&self, key: &[u8; 4]) -> Option<&[u8]> {
+        self.record_data
+            .tagged_blocks
+            .iter()
+            .find(|block| block.key() == key)
+            .map(|block| block.data())
+    }
+
+    /// Replace the data of the first tagged block with the key, or add a block to the end of the
+    /// record if there isn't one.
+    pub(crate) fn set_raw_tagged_block(&mut self, key: [u8; 4], data: Vec<u8>) {
+        let tagged_blocks = &mut self.record_data.tagged_blocks;
+
+        match tagged_blocks.iter_mut().find(|block| block.key == key) {
+            Some(block) => block.data = data,
+            None => tagged_blocks.push(TaggedBlock { key, data }),
+        }
+    }
+
+    /// Remove the first tagged block with the key, returning its data.
+    pub(crate) fn remove_raw_tagged_block(&mut self, key: &[u8; 4]) -> Option<Vec<u8>> {
+        let tagged_blocks = &mut self.record_data.tagged_blocks;
+        let idx = tagged_blocks.iter().position(|block| &block.key == key)?;
+
+        Some(tagged_blocks.remove(idx).data)
+    }
+}
+
+/// The parts of a layer record that we don't otherwise expose, kept so that the record can be
+/// written back into a PSD file.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub(crate) struct LayerRecordData {
+    /// The layer mask / adjustment layer data
+    pub(crate) mask_data: Vec<u8>,
+    /// The layer blending ranges data
+    pub(crate) blending_ranges: Vec<u8>,
+    /// The Pascal string name, which a `luni` block might have replaced as the layer's name
+    pub(crate) pascal_name: Vec<u8>,
+    /// Every additional layer information block in the record, including the ones that we parse
+    pub(crate) tagged_blocks: Vec<TaggedBlock>,
 }
 
 /// PsdGroup represents a group of layers
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct PsdGroup {
     /// Group unique identifier
     pub(crate) id: u32,
@@ -138,26 +310,52 @@ pub struct PsdGroup {
     pub(crate) contained_layers: Range<usize>,
     /// Common layer properties
     pub(crate) layer_properties: LayerProperties,
+    /// The properties of the record that opens the group (the one that holds the group's name),
+    /// whereas `layer_properties` come from the hidden record that closes the group.
+    pub(crate) folder_properties: LayerProperties,
+    /// The blend mode that the group's contents are blended with, see `PsdGroup::blend_mode`
+    pub(crate) blend_mode: BlendMode,
 }
 
 impl PsdGroup {
     /// Create a new photoshop group layer
+    ///
+    /// The group is named after the `folder_record`, which opens the group, while its properties
+    /// come from the `layer_record` that closes the group.
     pub fn new(
-        name: String,
         id: u32,
         contained_layers: Range<usize>,
         layer_record: &LayerRecord,
+        folder_record: &LayerRecord,
         psd_width: u32,
         psd_height: u32,
         group_id: Option<u32>,
     ) -> Self {
-        let layer_properties =
-            LayerProperties::from_layer_record(name, layer_record, psd_width, psd_height, group_id);
+        let name = folder_record.name.clone();
+        let blend_mode = folder_record
+            .divider_blend_mode
+            .unwrap_or(folder_record.blend_mode);
+        let layer_properties = LayerProperties::from_layer_record(
+            name.clone(),
+            layer_record,
+            psd_width,
+            psd_height,
+            group_id,
+        );
+        let folder_properties = LayerProperties::from_layer_record(
+            name,
+            folder_record,
+            psd_width,
+            psd_height,
+            group_id,
+        );
 
         PsdGroup {
             id,
             contained_layers,
             layer_properties,
+            folder_properties,
+            blend_mode,
         }
     }
 
@@ -165,6 +363,27 @@ impl PsdGroup {
     pub fn id(&self) -> u32 {
         self.id
     }
+
+    /// The blend mode that the group's contents are blended with.
+    ///
+    /// Read from the record that opens the group, where Photoshop stores the pass through mode
+    /// in the section divider setting.
+    pub fn blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+
+    /// The opacity that the group's contents are blended with, read from the record that opens
+    /// the group.
+    pub fn opacity(&self) -> u8 {
+        self.folder_properties.opacity
+    }
+
+    /// The data of the first tagged block with the key in the record that opens the group, which
+    /// holds the group's settings such as its `lspf` lock settings. See
+    /// [`LayerProperties::raw_tagged_block`].
+    pub fn raw_tagged_block(&self, key: &[u8; 4]) -> Option<&[u8]> {
+        self.folder_properties.raw_tagged_block(key)
+    }
 }
 
 impl Deref for PsdGroup {
@@ -178,8 +397,40 @@ impl Deref for PsdGroup {
 /// Channels represents channels of the layer, stored separately.
 pub type LayerChannels = HashMap<PsdChannelKind, ChannelBytes>;
 
+/// The compressed length of each scanline of a layer's RLE compressed channels.
+pub(crate) type ScanlineByteCounts = HashMap<PsdChannelKind, Vec<u32>>;
+
+/// The zlib streams that a layer's ZIP compressed channels were decompressed from, along with
+/// whether they used prediction.
+pub(crate) type ZipCompressedChannels = HashMap<PsdChannelKind, (PsdChannelCompression, Vec<u8>)>;
+
+/// The RGBA of a layer, kept after the first time that it's decoded by layers that were parsed
+/// with `Psd::from_shared_bytes`. `None` for layers that decode their pixels every time.
+///
+/// Layers compare equal whether or not they keep their pixels.
+#[derive(Clone, Default)]
+pub(crate) struct RgbaCache(Option<OnceLock<Vec<u8>>>);
+
+impl PartialEq for RgbaCache {
+    fn eq(&self, _other: &RgbaCache) -> bool {
+        true
+    }
+}
+
+impl fmt::Debug for RgbaCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RgbaCache")
+            .field("enabled", &self.0.is_some())
+            .field(
+                "decoded",
+                &self.0.as_ref().is_some_and(|rgba| rgba.get().is_some()),
+            )
+            .finish()
+    }
+}
+
 /// PsdLayer represents a pixel layer
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct PsdLayer {
     /// The channels of the layer, stored separately.
     ///
@@ -189,12 +440,29 @@ pub struct PsdLayer {
     ///
     /// Storing the channels separately allows for this flexability.
     pub(crate) channels: LayerChannels,
+    /// The length of each scanline of the channels that are RLE compressed
+    pub(crate) scanline_byte_counts: ScanlineByteCounts,
+    /// The compressed bytes of the channels that are ZIP compressed. The channels themselves
+    /// are decompressed when reading, these are only needed to write the channels back out.
+    pub(crate) zip_compressed_channels: ZipCompressedChannels,
+    /// The bit depth of the PSD, which the samples of the channels are stored at
+    pub(crate) depth: PsdDepth,
     /// Common layer properties
     pub(crate) layer_properties: LayerProperties,
+    /// Text information, if this is a text layer
+    pub(crate) text_layer_info: Option<TextLayerInfo>,
+    /// Where the layer places its smart object, if this is a smart object layer
+    pub(crate) placed_layer: Option<PlacedLayer>,
+    /// The decoded RGBA of layers that were parsed with `Psd::from_shared_bytes`
+    pub(crate) rgba_cache: RgbaCache,
 }
 
 /// An error when working with a PsdLayer
-#[derive(Debug, PartialEq, Error)]
+///
+/// New variants may be added in any release, see [`PsdLayerError::code`].
+#[derive(Debug, Clone, PartialEq, Error)]
+#[allow(missing_docs)]
+#[non_exhaustive]
 pub enum PsdLayerError {
     #[error(
         r#"Could not combine Red, Green, Blue and Alpha.
@@ -207,16 +475,44 @@ pub enum PsdLayerError {
     UnknownBlendingMode { mode: [u8; 4] },
     #[error("{compression} is an invalid layer channel compression. Must be 0, 1, 2 or 3")]
     InvalidCompression { compression: u16 },
+    #[error("The {channel:?} channel of layer {layer:?} has ZIP compressed data that is not a valid zlib stream")]
+    InvalidZipData {
+        layer: String,
+        channel: PsdChannelKind,
+    },
+    #[error("The {channel:?} channel's length of {length} bytes doesn't fit in the layer and mask information section")]
+    InvalidChannelLength {
+        channel: PsdChannelKind,
+        length: u64,
+    },
+}
+
+impl PsdLayerError {
+    /// A stable code that identifies the kind of error, see [`crate::PsdError::code`].
+    pub fn code(&self) -> &'static str {
+        match self {
+            PsdLayerError::MissingChannels { .. } => "layer.missing_channels",
+            PsdLayerError::InvalidChannel { .. } => "layer.invalid_channel",
+            PsdLayerError::UnknownBlendingMode { .. } => "layer.unknown_blending_mode",
+            PsdLayerError::InvalidCompression { .. } => "layer.invalid_compression",
+            PsdLayerError::InvalidZipData { .. } => "layer.invalid_zip_data",
+            PsdLayerError::InvalidChannelLength { .. } => "layer.invalid_channel_length",
+        }
+    }
 }
 
 impl PsdLayer {
     /// Create a new photoshop layer
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         layer_record: &LayerRecord,
         psd_width: u32,
         psd_height: u32,
+        depth: PsdDepth,
         group_id: Option<u32>,
         channels: LayerChannels,
+        scanline_byte_counts: ScanlineByteCounts,
+        zip_compressed_channels: ZipCompressedChannels,
     ) -> PsdLayer {
         PsdLayer {
             layer_properties: LayerProperties::from_layer_record(
@@ -227,6 +523,12 @@ impl PsdLayer {
                 group_id,
             ),
             channels,
+            scanline_byte_counts,
+            zip_compressed_channels,
+            depth,
+            text_layer_info: layer_record.text_layer_info.clone(),
+            placed_layer: layer_record.placed_layer.clone(),
+            rgba_cache: RgbaCache::default(),
         }
     }
 
@@ -235,6 +537,10 @@ impl PsdLayer {
         &self,
         channel: PsdChannelKind,
     ) -> Result<PsdChannelCompression, PsdChannelError> {
+        if let Some((compression, _)) = self.zip_compressed_channels.get(&channel) {
+            return Ok(*compression);
+        }
+
         match self.channels.get(&channel) {
             Some(channel) => match channel {
                 ChannelBytes::RawData(_) => Ok(PsdChannelCompression::RawData),
@@ -247,16 +553,454 @@ impl PsdLayer {
     /// Create a vector that interleaves the red, green, blue and alpha channels in this PSD
     ///
     /// vec![R, G, B, A, R, G, B, A, ...]
+    ///
+    /// Returns an empty vector for empty layers, see [`LayerProperties::is_empty`].
     pub fn rgba(&self) -> Vec<u8> {
-        self.generate_rgba()
+        if self.is_empty() {
+            return vec![];
+        }
+
+        match self.rgba_cache.0.as_ref() {
+            Some(rgba) => rgba.get_or_init(|| self.generate_rgba()).clone(),
+            None => self.generate_rgba(),
+        }
+    }
+
+    /// Keep the layer's RGBA after the first time that it's decoded
+    pub(crate) fn cache_rgba(&mut self) {
+        self.rgba_cache = RgbaCache(Some(OnceLock::new()));
+    }
+
+    /// Forget the layer's decoded RGBA after its pixels have changed
+    pub(crate) fn clear_rgba_cache(&mut self) {
+        if self.rgba_cache.0.is_some() {
+            self.cache_rgba();
+        }
+    }
+
+    /// The RGBA pixels of a rectangle of the PSD, the same as cropping [`PsdLayer::rgba`] to the
+    /// rectangle.
+    ///
+    /// Only the rows of the layer's channels that overlap the rectangle are decompressed, so
+    /// tiles of a large layer can be decoded one at a time.
+    ///
+    /// Returns an error if the rectangle is empty or doesn't lie within the canvas, and an empty
+    /// vector for empty layers, see [`LayerProperties::is_empty`].
+    pub fn rgba_region(&self, rect: CropRect) -> Result<Vec<u8>, PsdError> {
+        if !rect.fits(self.psd_width, self.psd_height) {
+            return Err(PsdError::InvalidCropRect(rect));
+        }
+        if self.is_empty() {
+            return Ok(vec![]);
+        }
+
+        Ok(self.generate_rgba_region(rect))
+    }
+
+    /// [`PsdLayer::rgba`] with 16 bits per channel
+    ///
+    /// vec![R, G, B, A, R, G, B, A, ...]
+    ///
+    /// The samples of a 16 bit layer are returned as they were stored, without being mapped down
+    /// to 8 bits. Layers of other depths, CMYK layers and layers with channels that can't be
+    /// decoded have the pixels of [`PsdLayer::rgba`] mapped onto the 16 bit range, so 255
+    /// becomes 65535.
+    ///
+    /// Returns an empty vector for empty layers, see [`LayerProperties::is_empty`].
+    pub fn rgba16(&self) -> Vec<u16> {
+        rgba16::layer_rgba16(self)
+    }
+
+    /// [`PsdLayer::rgba`], written into a buffer that you own with the given layout, such as a
+    /// mapped GPU staging buffer whose rows are padded for alignment.
+    ///
+    /// Like [`PsdLayer::rgba`] the image is the size of the PSD. Nothing is written for empty
+    /// layers.
+    pub fn rgba_into(&self, out: &mut [u8], layout: RgbaLayout) -> Result<(), PsdError> {
+        let (width, height) = (self.psd_width, self.psd_height);
+        layout.check(width, height, out.len())?;
+        if self.is_empty() {
+            return Ok(());
+        }
+
+        layout.write(&self.generate_rgba(), width, height, out)
+    }
+
+    /// Decode one of this layer's channels one row at a time, calling `f` with the index of each
+    /// row (top to bottom) and the row's bytes.
+    ///
+    /// Every row is `width()` bytes long, with one byte per pixel at every depth: 16 bit samples
+    /// are mapped down to their high byte and 32 bit samples are mapped down the same way as in
+    /// [`PsdLayer::rgba`]. Only a single row is decoded into memory at a time, so this is useful
+    /// when converting a layer directly into some other format (such as a GPU texture) without
+    /// first decoding the full channel.
+    pub fn for_each_decoded_row<F: FnMut(usize, &[u8])>(
+        &self,
+        channel: PsdChannelKind,
+        f: F,
+    ) -> Result<(), PsdChannelError> {
+        if self.get_channel(channel).is_none() {
+            return Err(PsdChannelError::ChannelNotFound { channel });
+        }
+
+        self.for_each_eight_bit_row(channel, self.width() as usize, f);
+
+        Ok(())
+    }
+
+    /// The average color of the layer's pixels, weighted by their alpha so that barely visible
+    /// pixels barely affect the result.
+    ///
+    /// Returns `None` if every pixel is fully transparent.
+    pub fn average_color(&self) -> Option<[u8; 3]> {
+        LayerColorStats::new(self).average_color()
+    }
+
+    /// The number of pixels with each red, green, blue and alpha value.
+    ///
+    /// Fully transparent pixels are only counted in the alpha histogram. Channels are decoded
+    /// one row at a time instead of generating the layer's RGBA.
+    pub fn histogram(&self) -> ColorHistogram {
+        LayerColorStats::new(self).histogram
+    }
+
+    /// The lowest and highest alpha of the layer's pixels, such as to skip exporting layers that
+    /// are fully transparent.
+    ///
+    /// Only the transparency channel is decoded, one row at a time. The layer's masks, opacity
+    /// and fill opacity aren't taken into account.
+    pub fn alpha_summary(&self) -> AlphaSummary {
+        AlphaSummary::new(self)
+    }
+
+    /// The alpha of the pixel at a coordinate in the PSD (not in the layer), or `None` if the
+    /// coordinate is outside of the layer.
+    ///
+    /// Layers without a transparency channel are fully opaque, while pixels that are missing
+    /// from the transparency channel (such as in empty layers) are fully transparent. Only the
+    /// coordinate's row of the transparency channel is decoded, so this doesn't allocate the
+    /// layer's full RGBA.
+    pub fn alpha_at(&self, left: i32, top: i32) -> Option<u8> {
+        let column = left - self.layer_left;
+        let row = top - self.layer_top;
+        if column < 0 || row < 0 || column >= self.width() as i32 || row >= self.height() as i32 {
+            return None;
+        }
+
+        if self.get_channel(PsdChannelKind::TransparencyMask).is_none() {
+            return Some(255);
+        }
+
+        let mut alpha = None;
+        let width = self.width() as usize;
+        let row = row as usize;
+        self.for_each_eight_bit_row_in(
+            PsdChannelKind::TransparencyMask,
+            width,
+            row..row + 1,
+            |_, bytes| alpha = Some(bytes[column as usize]),
+        );
+
+        Some(alpha.unwrap_or(0))
+    }
+
+    /// Decode the layer's user mask into a grayscale buffer the size of the PSD, with one byte
+    /// per pixel. 255 lets the layer show through and 0 hides it.
+    ///
+    /// Pixels outside of the mask's rectangle get the mask's default color, and masks that are
+    /// positioned relative to the layer are moved to the layer's position. When the layer has
+    /// both a user mask and a vector mask this decodes the user mask's
+    /// `RealUserSuppliedLayerMask` channel, otherwise its `UserSuppliedLayerMask` channel.
+    ///
+    /// Returns `None` if the layer doesn't have a user mask. The pixels are returned even if the
+    /// mask is disabled, see [`LayerMaskFlags::disabled`].
+    ///
+    /// [`LayerMaskFlags::disabled`]: crate::LayerMaskFlags::disabled
+    pub fn mask_pixels(&self) -> Option<Vec<u8>> {
+        let mask = self.mask()?;
+
+        if let Some(real) = mask.real_user_mask() {
+            if let Some(pixels) = self.decode_mask_channel(
+                PsdChannelKind::RealUserSuppliedLayerMask,
+                real.rect(),
+                real.background(),
+                real.flags(),
+            ) {
+                return Some(pixels);
+            }
+        }
+
+        self.decode_mask_channel(
+            PsdChannelKind::UserSuppliedLayerMask,
+            mask.rect(),
+            mask.default_color(),
+            mask.flags(),
+        )
+    }
+
+    /// The layer's user mask as a grayscale buffer the size of the PSD, with one byte per pixel.
+    ///
+    /// The same as [`PsdLayer::mask_pixels`], named to pair with [`PsdLayer::mask_rgba`].
+    pub fn mask_gray(&self) -> Option<Vec<u8>> {
+        self.mask_pixels()
+    }
+
+    /// The layer's user mask as opaque RGBA pixels the size of the PSD, with the mask's gray in
+    /// the red, green and blue channels, so that it can be saved like the layer's own pixels.
+    ///
+    /// Returns `None` if the layer doesn't have a user mask, see [`PsdLayer::mask_pixels`].
+    pub fn mask_rgba(&self) -> Option<Vec<u8>> {
+        let gray = self.mask_pixels()?;
+
+        Some(
+            gray.iter()
+                .flat_map(|gray| [*gray, *gray, *gray, 255])
+                .collect(),
+        )
+    }
+
+    /// Multiply the alpha of the layer's RGBA pixels (the size of the PSD) by its enabled masks,
+    /// the same way that Photoshop does when it merges the layer.
+    ///
+    /// When a layer has both a vector mask and a user mask the `UserSuppliedLayerMask` channel
+    /// holds the rendered vector mask and the `RealUserSuppliedLayerMask` channel holds the user
+    /// mask, so both of them are applied. Feathering is not applied.
+    pub(crate) fn apply_masks(&self, rgba: &mut [u8]) {
+        let mask = match self.mask() {
+            Some(mask) if !mask.flags().disabled() => mask,
+            _ => return,
+        };
+
+        let real = mask
+            .real_user_mask()
+            .filter(|real| !real.flags().disabled());
+        let density = match real {
+            Some(_) => mask.vector_mask_density(),
+            None => mask.user_mask_density(),
+        };
+        let pixels = self.decode_mask_channel(
+            PsdChannelKind::UserSuppliedLayerMask,
+            mask.rect(),
+            mask.default_color(),
+            mask.flags(),
+        );
+        if let Some(pixels) = pixels {
+            apply_mask_pixels(rgba, &pixels, density);
+        }
+
+        if let Some(real) = real {
+            let pixels = self.decode_mask_channel(
+                PsdChannelKind::RealUserSuppliedLayerMask,
+                real.rect(),
+                real.background(),
+                real.flags(),
+            );
+            if let Some(pixels) = pixels {
+                apply_mask_pixels(rgba, &pixels, mask.user_mask_density());
+            }
+        }
+    }
+
+    /// Decode one of the layer's mask channels into a grayscale buffer the size of the PSD,
+    /// filling the pixels outside of the mask's rectangle with its default color.
+    ///
+    /// The rectangle is moved by the layer's top left when the mask is positioned relative to
+    /// the layer.
+    fn decode_mask_channel(
+        &self,
+        channel: PsdChannelKind,
+        rect: MaskRect,
+        default_color: u8,
+        flags: LayerMaskFlags,
+    ) -> Option<Vec<u8>> {
+        self.get_channel(channel)?;
+
+        let rect = if flags.position_relative_to_layer() {
+            rect.translate(self.layer_top, self.layer_left)
+        } else {
+            rect
+        };
+
+        let (psd_width, psd_height) = (self.psd_width as i32, self.psd_height as i32);
+        let mut pixels = vec![default_color; (psd_width * psd_height) as usize];
+
+        self.for_each_eight_bit_row(channel, rect.width() as usize, |row_idx, bytes| {
+            let top = rect.top() + row_idx as i32;
+            if top < 0 || top >= psd_height {
+                return;
+            }
+
+            for (column, byte) in bytes.iter().enumerate() {
+                let left = rect.left() + column as i32;
+                if left < 0 || left >= psd_width {
+                    continue;
+                }
+
+                pixels[(top * psd_width + left) as usize] = *byte;
+            }
+        });
+
+        Some(pixels)
+    }
+
+    /// The depth of the samples that we keep for one of the layer's channels. ZIP compressed
+    /// channels are mapped down to 8 bits when they're decompressed, other channels are kept at
+    /// the PSD's depth.
+    pub(crate) fn sample_depth(&self, channel: PsdChannelKind) -> PsdDepth {
+        if self.zip_compressed_channels.contains_key(&channel) {
+            PsdDepth::Eight
+        } else {
+            self.depth
+        }
+    }
+
+    /// Decode one of the layer's channels one row of `width` samples at a time, like
+    /// `for_each_channel_row`, with the samples mapped down to 8 bits so that every row is
+    /// `width` bytes at any depth.
+    ///
+    /// Does nothing if the layer doesn't have the channel.
+    pub(crate) fn for_each_eight_bit_row<F: FnMut(usize, &[u8])>(
+        &self,
+        channel: PsdChannelKind,
+        width: usize,
+        f: F,
+    ) {
+        self.for_each_eight_bit_row_in(channel, width, 0..usize::MAX, f);
+    }
+
+    /// Like `for_each_eight_bit_row`, but only the rows in `rows_wanted` are passed to `f`. The
+    /// rows of an RLE compressed channel are skipped over using its scanline byte counts.
+    pub(crate) fn for_each_eight_bit_row_in<F: FnMut(usize, &[u8])>(
+        &self,
+        channel: PsdChannelKind,
+        width: usize,
+        rows_wanted: Range<usize>,
+        mut f: F,
+    ) {
+        let channel_bytes = match self.get_channel(channel) {
+            Some(channel_bytes) => channel_bytes,
+            None => return,
+        };
+
+        let depth = self.sample_depth(channel);
+        let bytes_per_sample = match depth {
+            PsdDepth::Sixteen => 2,
+            PsdDepth::ThirtyTwo => 4,
+            PsdDepth::One | PsdDepth::Eight => 1,
+        };
+        // Each RLE compressed row is decoded on its own, so a corrupt row can't shift the rows
+        // after it
+        let byte_counts = self.scanline_byte_counts.get(&channel).map(Vec::as_slice);
+        if bytes_per_sample == 1 {
+            for_each_channel_row(channel_bytes, byte_counts, width, rows_wanted, f);
+            return;
+        }
+
+        for_each_channel_row(
+            channel_bytes,
+            byte_counts,
+            width * bytes_per_sample,
+            rows_wanted,
+            |row_idx, row| {
+                f(
+                    row_idx,
+                    &samples_to_eight_bits(row, depth, channel.is_color()),
+                )
+            },
+        );
     }
 
     // Get one of the PsdLayerChannels of this PsdLayer
-    fn get_channel(&self, channel: PsdChannelKind) -> Option<&ChannelBytes> {
+    pub(crate) fn get_channel(&self, channel: PsdChannelKind) -> Option<&ChannelBytes> {
         self.channels.get(&channel)
     }
 }
 
+// Accessors for subsystems that are still experimental
+#[cfg(feature = "unstable")]
+impl PsdLayer {
+    /// Information about the text in this layer, if it is a text layer.
+    pub fn text_layer_info(&self) -> Option<&TextLayerInfo> {
+        self.text_layer_info.as_ref()
+    }
+
+    /// Where the layer places its smart object, if this is a smart object layer. See
+    /// [`Psd::smart_objects`] for the files that smart objects place.
+    ///
+    /// [`Psd::smart_objects`]: crate::Psd::smart_objects
+    pub fn placed_layer(&self) -> Option<&PlacedLayer> {
+        self.placed_layer.as_ref()
+    }
+
+    /// The smart filters that are applied to the layer, if this is a smart object layer. The
+    /// layer's pixels already have the filters applied, so they can't be reproduced after
+    /// changing the smart object.
+    pub fn smart_filters(&self) -> &[SmartFilter] {
+        match &self.placed_layer {
+            Some(placed_layer) => placed_layer.smart_filters(),
+            None => &[],
+        }
+    }
+
+    /// The layer's effects, such as its drop shadow and stroke, if it has an object-based
+    /// effects block that we can read. Older PSDs that only have the legacy "lrFX" block report
+    /// `None`.
+    pub fn effects(&self) -> Option<LayerEffects> {
+        self.layer_properties
+            .record_data
+            .tagged_blocks
+            .iter()
+            .find(|block| block.key() == KEY_OBJECT_BASED_EFFECTS)
+            .and_then(|block| LayerEffects::read(block.data()))
+    }
+
+    /// The adjustment that the layer applies to the layers below it, if this is an adjustment
+    /// layer such as levels or curves.
+    pub fn adjustment(&self) -> Option<Adjustment> {
+        self.layer_properties
+            .record_data
+            .tagged_blocks
+            .iter()
+            .find(|block| ADJUSTMENT_KEYS.contains(&block.key()))
+            .map(|block| Adjustment::read(block.key(), block.data()))
+    }
+
+    /// The solid color, gradient or pattern that the layer is filled with, if this is a fill
+    /// layer.
+    pub fn fill(&self) -> Option<Fill> {
+        self.layer_properties
+            .record_data
+            .tagged_blocks
+            .iter()
+            .filter(|block| [KEY_SOLID_COLOR, KEY_GRADIENT, KEY_PATTERN].contains(&block.key()))
+            .find_map(|block| Fill::read(block.key(), block.data()))
+    }
+
+    /// The layer's vector mask, such as the outline of a shape layer, if it has one.
+    pub fn vector_mask(&self) -> Option<VectorMask> {
+        self.layer_properties
+            .record_data
+            .tagged_blocks
+            .iter()
+            .filter(|block| KEY_VECTOR_MASK.contains(&block.key()))
+            .find_map(|block| VectorMask::read(block.data()))
+    }
+}
+
+/// Multiply the alpha of every RGBA pixel by the mask pixel at the same index.
+///
+/// A density below 255 lightens the mask, so that a density of 0 doesn't hide anything.
+fn apply_mask_pixels(rgba: &mut [u8], mask_pixels: &[u8], density: Option<u8>) {
+    let density = density.unwrap_or(255) as u32;
+
+    for (pixel, mask) in rgba.chunks_exact_mut(4).zip(mask_pixels) {
+        let hidden = (255 - *mask as u32) * density / 255;
+        let mask = 255 - hidden;
+
+        pixel[3] = ((pixel[3] as u32 * mask + 127) / 255) as u8;
+    }
+}
+
 impl Deref for PsdLayer {
     type Target = LayerProperties;
 
@@ -265,9 +1009,65 @@ impl Deref for PsdLayer {
     }
 }
 
+/// The flags of a layer record.
+///
+/// # [Adobe Docs](https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/)
+///
+/// Flags:
+///  - bit 0 = transparency protected;
+///  - bit 1 = visible;
+///  - bit 2 = obsolete;
+///  - bit 3 = 1 for Photoshop 5.0 and later, tells if bit 4 has useful information;
+///  - bit 4 = pixel data irrelevant to appearance of document
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub struct LayerFlags(u8);
+
+impl LayerFlags {
+    /// Create LayerFlags from the flags byte of a layer record
+    pub fn new(bits: u8) -> LayerFlags {
+        LayerFlags(bits)
+    }
+
+    /// The raw flags byte
+    pub fn bits(&self) -> u8 {
+        self.0
+    }
+
+    /// Bit 0 - the layer's transparent pixels are locked
+    pub fn transparency_protected(&self) -> bool {
+        self.bit(0)
+    }
+
+    /// Bit 1 - the layer is marked as visible
+    pub fn visible(&self) -> bool {
+        self.bit(1)
+    }
+
+    /// Bit 2 - obsolete
+    pub fn obsolete(&self) -> bool {
+        self.bit(2)
+    }
+
+    /// Bit 3 - set by Photoshop 5.0 and later when bit 4 has useful information
+    pub fn bit4_valid(&self) -> bool {
+        self.bit(3)
+    }
+
+    /// Bit 4 - the layer's pixel data is irrelevant to the appearance of the document.
+    ///
+    /// Always false if bit 4 isn't marked as valid by bit 3.
+    pub fn pixel_data_irrelevant(&self) -> bool {
+        self.bit4_valid() && self.bit(4)
+    }
+
+    fn bit(&self, bit: u8) -> bool {
+        self.0 & (1 << bit) != 0
+    }
+}
+
 /// GroupDivider represents tag type of Section divider.
 #[derive(Debug, Clone)]
-pub(super) enum GroupDivider {
+pub(crate) enum GroupDivider {
     /// 0 = any other type of layer
     Other = 0,
     /// 1 = open "folder"
@@ -291,7 +1091,7 @@ impl GroupDivider {
 }
 
 /// Describes how to blend a layer with the layer below it
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
 #[allow(missing_docs)]
 pub enum BlendMode {
     PassThrough = 0,
@@ -324,39 +1124,53 @@ pub enum BlendMode {
     Luminosity = 27,
 }
 
+/// The key that each blend mode is stored as in a layer record
+const BLEND_MODE_KEYS: [(&[u8; 4], BlendMode); 28] = [
+    (b"pass", BlendMode::PassThrough),
+    (b"norm", BlendMode::Normal),
+    (b"diss", BlendMode::Dissolve),
+    (b"dark", BlendMode::Darken),
+    (b"mul ", BlendMode::Multiply),
+    (b"idiv", BlendMode::ColorBurn),
+    (b"lbrn", BlendMode::LinearBurn),
+    (b"dkCl", BlendMode::DarkerColor),
+    (b"lite", BlendMode::Lighten),
+    (b"scrn", BlendMode::Screen),
+    (b"div ", BlendMode::ColorDodge),
+    (b"lddg", BlendMode::LinearDodge),
+    (b"lgCl", BlendMode::LighterColor),
+    (b"over", BlendMode::Overlay),
+    (b"sLit", BlendMode::SoftLight),
+    (b"hLit", BlendMode::HardLight),
+    (b"vLit", BlendMode::VividLight),
+    (b"lLit", BlendMode::LinearLight),
+    (b"pLit", BlendMode::PinLight),
+    (b"hMix", BlendMode::HardMix),
+    (b"diff", BlendMode::Difference),
+    (b"smud", BlendMode::Exclusion),
+    (b"fsub", BlendMode::Subtract),
+    (b"fdiv", BlendMode::Divide),
+    (b"hue ", BlendMode::Hue),
+    (b"sat ", BlendMode::Saturation),
+    (b"colr", BlendMode::Color),
+    (b"lum ", BlendMode::Luminosity),
+];
+
 impl BlendMode {
     pub(super) fn match_mode(mode: [u8; 4]) -> Option<BlendMode> {
-        match &mode {
-            b"pass" => Some(BlendMode::PassThrough),
-            b"norm" => Some(BlendMode::Normal),
-            b"diss" => Some(BlendMode::Dissolve),
-            b"dark" => Some(BlendMode::Darken),
-            b"mul " => Some(BlendMode::Multiply),
-            b"idiv" => Some(BlendMode::ColorBurn),
-            b"lbrn" => Some(BlendMode::LinearBurn),
-            b"dkCl" => Some(BlendMode::DarkerColor),
-            b"lite" => Some(BlendMode::Lighten),
-            b"scrn" => Some(BlendMode::Screen),
-            b"div " => Some(BlendMode::ColorDodge),
-            b"lddg" => Some(BlendMode::LinearDodge),
-            b"lgCl" => Some(BlendMode::LighterColor),
-            b"over" => Some(BlendMode::Overlay),
-            b"sLit" => Some(BlendMode::SoftLight),
-            b"hLit" => Some(BlendMode::HardLight),
-            b"vLit" => Some(BlendMode::VividLight),
-            b"lLit" => Some(BlendMode::LinearLight),
-            b"pLit" => Some(BlendMode::PinLight),
-            b"hMix" => Some(BlendMode::HardMix),
-            b"diff" => Some(BlendMode::Difference),
-            b"smud" => Some(BlendMode::Exclusion),
-            b"fsub" => Some(BlendMode::Subtract),
-            b"fdiv" => Some(BlendMode::Divide),
-            b"hue " => Some(BlendMode::Hue),
-            b"sat " => Some(BlendMode::Saturation),
-            b"colr" => Some(BlendMode::Color),
-            b"lum " => Some(BlendMode::Luminosity),
-            _ => None,
-        }
+        BLEND_MODE_KEYS
+            .iter()
+            .find(|(key, _)| **key == mode)
+            .map(|(_, blend_mode)| *blend_mode)
+    }
+
+    /// The key that the blend mode is stored as in a layer record, such as `norm`
+    pub(crate) fn key(self) -> [u8; 4] {
+        let (key, _) = BLEND_MODE_KEYS
+            .iter()
+            .find(|(_, blend_mode)| *blend_mode == self)
+            .unwrap();
+        **key
     }
 }
 
@@ -375,7 +1189,7 @@ pub struct LayerRecord {
     /// So a 1x1 image would have 1 byte per channel.
     ///
     /// A 2x2 image would have 4 bytes per channel.
-    pub(super) channel_data_lengths: Vec<(PsdChannelKind, u32)>,
+    pub(super) channel_data_lengths: Vec<(PsdChannelKind, u64)>,
     /// The position of the top of the image
     pub(super) top: i32,
     /// The position of the left of the image
@@ -384,8 +1198,8 @@ pub struct LayerRecord {
     pub(super) bottom: i32,
     /// The position of the right of the image
     pub(super) right: i32,
-    /// If true, the layer is marked as visible
-    pub(super) visible: bool,
+    /// The flags of the layer record
+    pub(super) flags: LayerFlags,
     /// The opacity of the layer
     pub(super) opacity: u8,
     /// If true, the layer is clipping mask
@@ -394,12 +1208,27 @@ pub struct LayerRecord {
     pub(super) blend_mode: BlendMode,
     /// Group divider tag
     pub(super) divider_type: Option<GroupDivider>,
+    /// The blend mode in the section divider setting, which can differ from `blend_mode`
+    pub(super) divider_blend_mode: Option<BlendMode>,
+    /// Text information from the type tool object setting
+    pub(super) text_layer_info: Option<TextLayerInfo>,
+    /// The layer's ID from the `lyid` block
+    pub(super) layer_id: Option<u32>,
+    /// Smart object placement from the `SoLd`, `SoLE` or `PlLd` block
+    pub(super) placed_layer: Option<PlacedLayer>,
+    /// The data that we hold on to in order to write the record back out
+    pub(super) record_data: LayerRecordData,
 }
 
 impl LayerRecord {
-    /// The height of this layer record
+    /// The height of this layer record, zero for empty layers
     pub fn height(&self) -> i32 {
-        (self.bottom - self.top) + 1
+        ((self.bottom - self.top) + 1).max(0)
+    }
+
+    /// The width of this layer record, zero for empty layers
+    pub fn width(&self) -> i32 {
+        ((self.right - self.left) + 1).max(0)
     }
 }
 
@@ -429,23 +1258,22 @@ impl IntoRgba for PsdLayer {
     ///
     /// So we transform the pixel's index based on the layer's left and top
     /// position within the PSD.
+    ///
+    /// Pixels that hang off the left, top or right of the PSD are `None` rather than wrapping
+    /// around onto the rows next to them.
     fn rgba_idx(&self, idx: usize) -> Option<usize> {
         let left_in_layer = idx % self.width() as usize;
-        let left_in_psd = self.layer_properties.layer_left + left_in_layer as i32;
+        let left_in_psd = self.layer_properties.layer_left as i64 + left_in_layer as i64;
 
         let top_in_layer = idx / self.width() as usize;
-        let top_in_psd = self.layer_properties.layer_top + top_in_layer as i32;
+        let top_in_psd = self.layer_properties.layer_top as i64 + top_in_layer as i64;
 
-        let idx = top_in_psd
-            .checked_mul(self.layer_properties.psd_width as i32)
-            .unwrap()
-            + left_in_psd;
-
-        if idx < 0 {
-            None
-        } else {
-            Some(idx as usize)
+        let psd_width = self.layer_properties.psd_width as i64;
+        if left_in_psd < 0 || left_in_psd >= psd_width || top_in_psd < 0 {
+            return None;
         }
+
+        Some((top_in_psd * psd_width + left_in_psd) as usize)
     }
 
     fn red(&self) -> &ChannelBytes {
@@ -464,6 +1292,38 @@ impl IntoRgba for PsdLayer {
         self.get_channel(PsdChannelKind::TransparencyMask)
     }
 
+    fn black(&self) -> Option<&ChannelBytes> {
+        self.get_channel(PsdChannelKind::Black)
+    }
+
+    fn rle_rows(&self, channel: &ChannelBytes) -> Option<RleRows<'_>> {
+        let (kind, _) = self
+            .channels
+            .iter()
+            .find(|(_, bytes)| std::ptr::eq(*bytes, channel))?;
+        let byte_counts = self.scanline_byte_counts.get(kind)?;
+
+        RleRows::new(byte_counts, self.width() as usize, self.depth)
+    }
+
+    fn top_in_psd(&self) -> i32 {
+        self.layer_properties.layer_top
+    }
+
+    fn channel_depth(&self, channel: &ChannelBytes) -> PsdDepth {
+        let kind = self
+            .channels
+            .iter()
+            .find(|(_, bytes)| std::ptr::eq(*bytes, channel))
+            .map(|(kind, _)| *kind);
+
+        match kind {
+            Some(kind) => self.sample_depth(kind),
+            // Channels that were decompressed in parallel are RLE channels at the layer's depth
+            None => self.depth,
+        }
+    }
+
     fn psd_width(&self) -> u32 {
         self.layer_properties.psd_width
     }
@@ -0,0 +1,43 @@
+/// The layer count that the layer info declares, along with the number of layer records that
+/// were actually parsed.
+///
+/// # [Adobe Docs](https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/)
+///
+/// Layer count. If it is a negative number, its absolute value is the number of layers and the
+/// first alpha channel contains the transparency data for the merged result.
+///
+/// The count includes the hidden records that open and close groups, so a document with one
+/// group holding one layer declares three records.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub struct LayerCount {
+    pub(crate) declared: i16,
+    pub(crate) records_parsed: usize,
+}
+
+impl LayerCount {
+    /// The layer count as it was stored, including its sign
+    pub fn declared(&self) -> i16 {
+        self.declared
+    }
+
+    /// The number of layer records that the layer count declares
+    pub fn declared_records(&self) -> usize {
+        self.declared.unsigned_abs() as usize
+    }
+
+    /// Whether the layer count was negative, meaning that the first alpha channel of the final
+    /// image holds the transparency of the merged result
+    pub fn is_negative(&self) -> bool {
+        self.declared < 0
+    }
+
+    /// The number of layer records that were parsed
+    pub fn records_parsed(&self) -> usize {
+        self.records_parsed
+    }
+
+    /// Whether the layer info ended before every declared layer record was parsed
+    pub fn is_truncated(&self) -> bool {
+        self.records_parsed < self.declared_records()
+    }
+}
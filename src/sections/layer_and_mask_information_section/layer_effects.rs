@@ -0,0 +1,576 @@
+use crate::sections::image_resources_section::{
+    DescriptorField, DescriptorStructure, UnitFloatStructure,
+};
+use crate::sections::layer_and_mask_information_section::layer::BlendMode;
+use crate::sections::PsdCursor;
+
+/// Key of `Object-based effects layer info (Photoshop 6.0)`, "lfx2"
+pub(crate) const KEY_OBJECT_BASED_EFFECTS: &[u8; 4] = b"lfx2";
+
+/// The blend mode that each value of a descriptor's blend mode ("BlnM") enum stands for
+const DESCRIPTOR_BLEND_MODES: [(&[u8], BlendMode); 27] = [
+    (b"Nrml", BlendMode::Normal),
+    (b"Dslv", BlendMode::Dissolve),
+    (b"Drkn", BlendMode::Darken),
+    (b"Mltp", BlendMode::Multiply),
+    (b"CBrn", BlendMode::ColorBurn),
+    (b"linearBurn", BlendMode::LinearBurn),
+    (b"darkerColor", BlendMode::DarkerColor),
+    (b"Lghn", BlendMode::Lighten),
+    (b"Scrn", BlendMode::Screen),
+    (b"CDdg", BlendMode::ColorDodge),
+    (b"linearDodge", BlendMode::LinearDodge),
+    (b"lighterColor", BlendMode::LighterColor),
+    (b"Ovrl", BlendMode::Overlay),
+    (b"SftL", BlendMode::SoftLight),
+    (b"HrdL", BlendMode::HardLight),
+    (b"vividLight", BlendMode::VividLight),
+    (b"linearLight", BlendMode::LinearLight),
+    (b"pinLight", BlendMode::PinLight),
+    (b"hardMix", BlendMode::HardMix),
+    (b"Dfrn", BlendMode::Difference),
+    (b"Xclu", BlendMode::Exclusion),
+    (b"blendSubtraction", BlendMode::Subtract),
+    (b"blendDivide", BlendMode::Divide),
+    (b"H   ", BlendMode::Hue),
+    (b"Strt", BlendMode::Saturation),
+    (b"Clr ", BlendMode::Color),
+    (b"Lmns", BlendMode::Luminosity),
+];
+
+/// A layer's effects, read from its object-based effects ("lfx2") additional layer information
+/// block.
+///
+/// We don't render effects, but exposing them lets you recreate them elsewhere or check them
+/// against a design system. Bevels, satins, gradient overlays and pattern overlays are only
+/// available through [`LayerEffects::descriptor`].
+///
+/// # [Adobe Docs](https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/)
+///
+/// | Length   | Description                                                 |
+/// |----------|-------------------------------------------------------------|
+/// | 4        | Object effects version: 0                                   |
+/// | 4        | Descriptor version ( = 16 for Photoshop 6.0)                |
+/// | Variable | Descriptor (see See Descriptor structure)                   |
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayerEffects {
+    pub(crate) enabled: bool,
+    pub(crate) scale: f64,
+    pub(crate) drop_shadow: Option<Shadow>,
+    pub(crate) inner_shadow: Option<Shadow>,
+    pub(crate) outer_glow: Option<Glow>,
+    pub(crate) inner_glow: Option<Glow>,
+    pub(crate) color_overlay: Option<ColorOverlay>,
+    pub(crate) stroke: Option<Stroke>,
+    pub(crate) descriptor: DescriptorStructure,
+}
+
+impl LayerEffects {
+    /// Read the data of an `lfx2` block. Returns `None` if the data is too short to hold a
+    /// descriptor or the descriptor is invalid.
+    pub(crate) fn read(data: &[u8]) -> Option<LayerEffects> {
+        if data.len() < 8 {
+            return None;
+        }
+
+        let mut cursor = PsdCursor::new(data);
+        let _version = cursor.read_u32();
+        let _descriptor_version = cursor.read_u32();
+        let descriptor = DescriptorStructure::read_descriptor_structure(&mut cursor).ok()?;
+
+        let fields = Fields(&descriptor);
+        let effect = |key: &str| match descriptor.fields.get(key) {
+            Some(DescriptorField::Descriptor(effect)) => Some(Fields(effect)),
+            _ => None,
+        };
+
+        Some(LayerEffects {
+            enabled: fields.boolean("masterFXSwitch").unwrap_or(true),
+            scale: fields.unit_float("Scl ").unwrap_or(100.),
+            drop_shadow: effect("DrSh").map(Shadow::from_fields),
+            inner_shadow: effect("IrSh").map(Shadow::from_fields),
+            outer_glow: effect("OrGl").map(Glow::from_fields),
+            inner_glow: effect("IrGl").map(Glow::from_fields),
+            color_overlay: effect("SoFi").map(ColorOverlay::from_fields),
+            stroke: effect("FrFX").map(Stroke::from_fields),
+            descriptor,
+        })
+    }
+
+    /// Whether the layer's effects are shown. Each effect can also be turned off on its own.
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// The percentage that the sizes and distances of the effects are scaled by
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    /// The drop shadow, if the layer has one
+    pub fn drop_shadow(&self) -> Option<&Shadow> {
+        self.drop_shadow.as_ref()
+    }
+
+    /// The inner shadow, if the layer has one
+    pub fn inner_shadow(&self) -> Option<&Shadow> {
+        self.inner_shadow.as_ref()
+    }
+
+    /// The outer glow, if the layer has one
+    pub fn outer_glow(&self) -> Option<&Glow> {
+        self.outer_glow.as_ref()
+    }
+
+    /// The inner glow, if the layer has one
+    pub fn inner_glow(&self) -> Option<&Glow> {
+        self.inner_glow.as_ref()
+    }
+
+    /// The color overlay, if the layer has one
+    pub fn color_overlay(&self) -> Option<&ColorOverlay> {
+        self.color_overlay.as_ref()
+    }
+
+    /// The stroke, if the layer has one
+    pub fn stroke(&self) -> Option<&Stroke> {
+        self.stroke.as_ref()
+    }
+
+    /// The whole effects descriptor, including the effects that we don't parse
+    pub fn descriptor(&self) -> &DescriptorStructure {
+        &self.descriptor
+    }
+}
+
+/// A drop shadow or an inner shadow
+#[derive(Debug, Clone, PartialEq)]
+pub struct Shadow {
+    pub(crate) enabled: bool,
+    pub(crate) blend_mode: BlendMode,
+    pub(crate) color: [u8; 3],
+    pub(crate) opacity: u8,
+    pub(crate) angle: f64,
+    pub(crate) use_global_light: bool,
+    pub(crate) distance: f64,
+    pub(crate) spread: f64,
+    pub(crate) size: f64,
+    pub(crate) noise: f64,
+}
+
+impl Shadow {
+    fn from_fields(fields: Fields) -> Shadow {
+        Shadow {
+            enabled: fields.boolean("enab").unwrap_or(true),
+            blend_mode: fields.blend_mode(),
+            color: fields.color(),
+            opacity: fields.opacity(),
+            angle: fields.unit_float("lagl").unwrap_or(0.),
+            use_global_light: fields.boolean("uglg").unwrap_or(false),
+            distance: fields.unit_float("Dstn").unwrap_or(0.),
+            spread: fields.unit_float("Ckmt").unwrap_or(0.),
+            size: fields.unit_float("blur").unwrap_or(0.),
+            noise: fields.unit_float("Nose").unwrap_or(0.),
+        }
+    }
+
+    /// Whether the shadow is shown
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// How the shadow is blended with the pixels below it
+    pub fn blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+
+    /// The color of the shadow as RGB
+    pub fn color(&self) -> [u8; 3] {
+        self.color
+    }
+
+    /// The opacity of the shadow, from 0 to 255
+    pub fn opacity(&self) -> u8 {
+        self.opacity
+    }
+
+    /// The direction that the light comes from, in degrees
+    pub fn angle(&self) -> f64 {
+        self.angle
+    }
+
+    /// Whether the shadow uses the document's global light angle instead of its own angle
+    pub fn use_global_light(&self) -> bool {
+        self.use_global_light
+    }
+
+    /// How far the shadow is offset from the layer, in pixels
+    pub fn distance(&self) -> f64 {
+        self.distance
+    }
+
+    /// The spread of a drop shadow or the choke of an inner shadow, in percent
+    pub fn spread(&self) -> f64 {
+        self.spread
+    }
+
+    /// The size of the shadow's blur, in pixels
+    pub fn size(&self) -> f64 {
+        self.size
+    }
+
+    /// The amount of noise in the shadow, in percent
+    pub fn noise(&self) -> f64 {
+        self.noise
+    }
+}
+
+/// Where an inner glow starts from
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum GlowSource {
+    /// The glow starts from the edges of the layer's content
+    Edge,
+    /// The glow starts from the center of the layer's content
+    Center,
+}
+
+/// An outer glow or an inner glow
+#[derive(Debug, Clone, PartialEq)]
+pub struct Glow {
+    pub(crate) enabled: bool,
+    pub(crate) blend_mode: BlendMode,
+    pub(crate) color: [u8; 3],
+    pub(crate) opacity: u8,
+    pub(crate) spread: f64,
+    pub(crate) size: f64,
+    pub(crate) noise: f64,
+    pub(crate) source: GlowSource,
+}
+
+impl Glow {
+    fn from_fields(fields: Fields) -> Glow {
+        let source = match fields.enumerated("glwS") {
+            Some(b"SrcC") => GlowSource::Center,
+            _ => GlowSource::Edge,
+        };
+
+        Glow {
+            enabled: fields.boolean("enab").unwrap_or(true),
+            blend_mode: fields.blend_mode(),
+            color: fields.color(),
+            opacity: fields.opacity(),
+            spread: fields.unit_float("Ckmt").unwrap_or(0.),
+            size: fields.unit_float("blur").unwrap_or(0.),
+            noise: fields.unit_float("Nose").unwrap_or(0.),
+            source,
+        }
+    }
+
+    /// Whether the glow is shown
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// How the glow is blended with the pixels below it
+    pub fn blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+
+    /// The color of the glow as RGB. Glows that use a gradient report black.
+    pub fn color(&self) -> [u8; 3] {
+        self.color
+    }
+
+    /// The opacity of the glow, from 0 to 255
+    pub fn opacity(&self) -> u8 {
+        self.opacity
+    }
+
+    /// The spread of an outer glow or the choke of an inner glow, in percent
+    pub fn spread(&self) -> f64 {
+        self.spread
+    }
+
+    /// The size of the glow, in pixels
+    pub fn size(&self) -> f64 {
+        self.size
+    }
+
+    /// The amount of noise in the glow, in percent
+    pub fn noise(&self) -> f64 {
+        self.noise
+    }
+
+    /// Where the glow starts from. Outer glows always start from the edges.
+    pub fn source(&self) -> GlowSource {
+        self.source
+    }
+}
+
+/// A color overlay, which fills the layer's content with a color
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColorOverlay {
+    pub(crate) enabled: bool,
+    pub(crate) blend_mode: BlendMode,
+    pub(crate) color: [u8; 3],
+    pub(crate) opacity: u8,
+}
+
+impl ColorOverlay {
+    fn from_fields(fields: Fields) -> ColorOverlay {
+        ColorOverlay {
+            enabled: fields.boolean("enab").unwrap_or(true),
+            blend_mode: fields.blend_mode(),
+            color: fields.color(),
+            opacity: fields.opacity(),
+        }
+    }
+
+    /// Whether the overlay is shown
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// How the overlay is blended with the layer's content
+    pub fn blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+
+    /// The color of the overlay as RGB
+    pub fn color(&self) -> [u8; 3] {
+        self.color
+    }
+
+    /// The opacity of the overlay, from 0 to 255
+    pub fn opacity(&self) -> u8 {
+        self.opacity
+    }
+}
+
+/// Where a stroke is drawn relative to the edges of the layer's content
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum StrokePosition {
+    /// Outside of the edges
+    Outside,
+    /// Inside of the edges
+    Inside,
+    /// Centered on the edges
+    Center,
+}
+
+/// A stroke around the edges of the layer's content
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stroke {
+    pub(crate) enabled: bool,
+    pub(crate) blend_mode: BlendMode,
+    pub(crate) color: [u8; 3],
+    pub(crate) opacity: u8,
+    pub(crate) size: f64,
+    pub(crate) position: StrokePosition,
+}
+
+impl Stroke {
+    fn from_fields(fields: Fields) -> Stroke {
+        let position = match fields.enumerated("Styl") {
+            Some(b"InsF") => StrokePosition::Inside,
+            Some(b"CtrF") => StrokePosition::Center,
+            _ => StrokePosition::Outside,
+        };
+
+        Stroke {
+            enabled: fields.boolean("enab").unwrap_or(true),
+            blend_mode: fields.blend_mode(),
+            color: fields.color(),
+            opacity: fields.opacity(),
+            size: fields.unit_float("Sz  ").unwrap_or(0.),
+            position,
+        }
+    }
+
+    /// Whether the stroke is shown
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// How the stroke is blended with the pixels below it
+    pub fn blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+
+    /// The color of the stroke as RGB. Strokes that are filled with a gradient or a pattern
+    /// report black.
+    pub fn color(&self) -> [u8; 3] {
+        self.color
+    }
+
+    /// The opacity of the stroke, from 0 to 255
+    pub fn opacity(&self) -> u8 {
+        self.opacity
+    }
+
+    /// The width of the stroke, in pixels
+    pub fn size(&self) -> f64 {
+        self.size
+    }
+
+    /// Where the stroke is drawn relative to the edges of the layer's content
+    pub fn position(&self) -> StrokePosition {
+        self.position
+    }
+}
+
+/// The fields of an effect's descriptor, or of another descriptor that describes colors the same
+/// way, such as a fill layer's
+#[derive(Clone, Copy)]
+pub(crate) struct Fields<'a>(pub(crate) &'a DescriptorStructure);
+
+impl<'a> Fields<'a> {
+    fn get(&self, key: &str) -> Option<&'a DescriptorField> {
+        self.0.fields.get(key)
+    }
+
+    /// The fields of a nested descriptor
+    pub(crate) fn descriptor(&self, key: &str) -> Option<Fields<'a>> {
+        match self.get(key) {
+            Some(DescriptorField::Descriptor(descriptor)) => Some(Fields(descriptor)),
+            _ => None,
+        }
+    }
+
+    /// The items of a list, or no items if there isn't a list
+    pub(crate) fn list(&self, key: &str) -> &'a [DescriptorField] {
+        match self.get(key) {
+            Some(DescriptorField::List(items)) => items,
+            _ => &[],
+        }
+    }
+
+    pub(crate) fn string(&self, key: &str) -> Option<&'a str> {
+        match self.get(key) {
+            Some(DescriptorField::String(value)) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn integer(&self, key: &str) -> Option<i32> {
+        match self.get(key) {
+            Some(DescriptorField::Integer(value)) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn boolean(&self, key: &str) -> Option<bool> {
+        match self.get(key) {
+            Some(DescriptorField::Boolean(value)) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// The value of a unit float, whatever its unit, or of a double
+    pub(crate) fn unit_float(&self, key: &str) -> Option<f64> {
+        match self.get(key) {
+            Some(DescriptorField::UnitFloat(unit_float)) => match unit_float {
+                UnitFloatStructure::Angle(value)
+                | UnitFloatStructure::Density(value)
+                | UnitFloatStructure::Distance(value)
+                | UnitFloatStructure::Percent(value)
+                | UnitFloatStructure::Pixels(value)
+                | UnitFloatStructure::Points(value)
+                | UnitFloatStructure::Millimeters(value) => Some(*value),
+                UnitFloatStructure::None => None,
+            },
+            Some(DescriptorField::Double(value)) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn enumerated(&self, key: &str) -> Option<&'a [u8]> {
+        match self.get(key) {
+            Some(DescriptorField::EnumeratedDescriptor(value)) => Some(&value.enum_field),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn blend_mode(&self) -> BlendMode {
+        self.enumerated("Md  ")
+            .and_then(|mode| {
+                DESCRIPTOR_BLEND_MODES
+                    .iter()
+                    .find(|(key, _)| *key == mode)
+                    .map(|(_, blend_mode)| *blend_mode)
+            })
+            .unwrap_or(BlendMode::Normal)
+    }
+
+    /// The opacity percentage, scaled to 0 to 255
+    pub(crate) fn opacity(&self) -> u8 {
+        let percent = self.unit_float("Opct").unwrap_or(100.);
+        (percent * 255. / 100.).round().clamp(0., 255.) as u8
+    }
+
+    /// The "Clr " color as RGB. RGB, grayscale and HSB colors are supported, other colors are
+    /// black.
+    pub(crate) fn color(&self) -> [u8; 3] {
+        let color = match self.get("Clr ") {
+            Some(DescriptorField::Descriptor(color)) => Fields(color),
+            _ => return [0; 3],
+        };
+        let channel = |value: f64| value.round().clamp(0., 255.) as u8;
+
+        match color.0.class_id.as_slice() {
+            b"RGBC" => [
+                channel(color.unit_float("Rd  ").unwrap_or(0.)),
+                channel(color.unit_float("Grn ").unwrap_or(0.)),
+                channel(color.unit_float("Bl  ").unwrap_or(0.)),
+            ],
+            b"Grsc" => {
+                // The percentage of black
+                let gray = channel(255. - color.unit_float("Gry ").unwrap_or(0.) * 255. / 100.);
+                [gray; 3]
+            }
+            b"HSBC" => {
+                let hue = color.unit_float("H   ").unwrap_or(0.);
+                let saturation = color.unit_float("Strt").unwrap_or(0.) / 100.;
+                let brightness = color.unit_float("Brgh").unwrap_or(0.) / 100.;
+
+                let [r, g, b] = hsb_to_rgb(hue, saturation, brightness);
+                [channel(r * 255.), channel(g * 255.), channel(b * 255.)]
+            }
+            _ => [0; 3],
+        }
+    }
+}
+
+/// Convert a hue in degrees and a saturation and brightness from 0 to 1 into RGB from 0 to 1
+fn hsb_to_rgb(hue: f64, saturation: f64, brightness: f64) -> [f64; 3] {
+    let hue = hue.rem_euclid(360.) / 60.;
+    let chroma = brightness * saturation;
+    let x = chroma * (1. - (hue % 2. - 1.).abs());
+
+    let [r, g, b] = match hue as u8 {
+        0 => [chroma, x, 0.],
+        1 => [x, chroma, 0.],
+        2 => [0., chroma, x],
+        3 => [0., x, chroma],
+        4 => [x, 0., chroma],
+        _ => [chroma, 0., x],
+    };
+    let m = brightness - chroma;
+
+    [r + m, g + m, b + m]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Verify that we convert the primary and secondary hues and gray.
+    #[test]
+    fn hsb_to_rgb_conversion() {
+        assert_eq!(hsb_to_rgb(0., 1., 1.), [1., 0., 0.]);
+        assert_eq!(hsb_to_rgb(120., 1., 1.), [0., 1., 0.]);
+        assert_eq!(hsb_to_rgb(240., 1., 1.), [0., 0., 1.]);
+        assert_eq!(hsb_to_rgb(300., 1., 1.), [1., 0., 1.]);
+        assert_eq!(hsb_to_rgb(90., 0., 0.5), [0.5, 0.5, 0.5]);
+    }
+}
@@ -0,0 +1,495 @@
+use crate::sections::PsdCursor;
+
+/// The layer mask / adjustment layer data of a layer record, describing the layer's user
+/// supplied mask and, when the layer also has a vector mask, its "real" user mask.
+///
+/// The mask's pixels are stored in the layer's `UserSuppliedLayerMask` channel (or its
+/// `RealUserSuppliedLayerMask` channel when there is also a vector mask), see
+/// [`PsdLayer::mask_pixels`].
+///
+/// # [Adobe Docs](https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/)
+///
+/// | Length   | Description                                                           |
+/// |----------|-----------------------------------------------------------------------|
+/// | 4        | Size of the data: Check the size and flags to determine what is present. If zero, the following fields are not present |
+/// | 4 * 4    | Rectangle enclosing layer mask: Top, left, bottom, right              |
+/// | 1        | Default color. 0 or 255                                               |
+/// | 1        | Flags                                                                 |
+/// | 1        | Mask Parameters. Only present if bit 4 of Flags set above.            |
+/// | Variable | Mask Parameters bit flags present as follows: user mask density (1), user mask feather (8), vector mask density (1), vector mask feather (8) |
+/// | 2        | Padding. Only present if size = 20. Otherwise the following is present |
+/// | 1        | Real Flags. Same as Flags information above.                          |
+/// | 1        | Real user mask background. 0 or 255.                                  |
+/// | 4 * 4    | Rectangle enclosing layer mask: Top, left, bottom, right.             |
+///
+/// Photoshop writes the real flags, background and rectangle before the mask parameters, so
+/// that is the order that we read them in.
+///
+/// [`PsdLayer::mask_pixels`]: crate::PsdLayer::mask_pixels
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayerMask {
+    pub(crate) rect: MaskRect,
+    pub(crate) default_color: u8,
+    pub(crate) flags: LayerMaskFlags,
+    pub(crate) real: Option<RealUserMask>,
+    pub(crate) user_mask_density: Option<u8>,
+    pub(crate) user_mask_feather: Option<f64>,
+    pub(crate) vector_mask_density: Option<u8>,
+    pub(crate) vector_mask_feather: Option<f64>,
+}
+
+/// The rectangle that encloses a mask's pixels, in PSD coordinates.
+///
+/// `bottom` and `right` are exclusive, so a mask at the top left of the PSD covering a single
+/// pixel is (0, 0, 1, 1).
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub struct MaskRect {
+    pub(crate) top: i32,
+    pub(crate) left: i32,
+    pub(crate) bottom: i32,
+    pub(crate) right: i32,
+}
+
+/// The user mask of a layer that has both a user mask and a vector mask.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct RealUserMask {
+    pub(crate) flags: LayerMaskFlags,
+    pub(crate) background: u8,
+    pub(crate) rect: MaskRect,
+}
+
+/// The flags of a layer mask.
+///
+/// # [Adobe Docs](https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/)
+///
+/// Flags:
+///  - bit 0 = position relative to layer
+///  - bit 1 = layer mask disabled
+///  - bit 2 = invert layer mask when blending (Obsolete)
+///  - bit 3 = indicates that the user mask actually came from rendering other data
+///  - bit 4 = indicates that the user and/or vector masks have parameters applied to them
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub struct LayerMaskFlags(u8);
+
+impl LayerMask {
+    /// Read the layer mask data of a layer record, not including its length.
+    ///
+    /// Returns `None` if the layer doesn't have a mask, or if the data is too short to hold
+    /// one.
+    pub(crate) fn read(mask_data: &[u8]) -> Option<LayerMask> {
+        // The rectangle, the default color and the flags
+        if mask_data.len() < 18 {
+            return None;
+        }
+
+        let mut cursor = PsdCursor::new(mask_data);
+
+        let rect = MaskRect::read(&mut cursor);
+        let default_color = cursor.read_u8();
+        let flags = LayerMaskFlags::new(cursor.read_u8());
+
+        // The real flags, the real background and the real rectangle
+        let real = if cursor.remaining() >= 18 {
+            Some(RealUserMask {
+                flags: LayerMaskFlags::new(cursor.read_u8()),
+                background: cursor.read_u8(),
+                rect: MaskRect::read(&mut cursor),
+            })
+        } else {
+            None
+        };
+
+        let mut layer_mask = LayerMask {
+            rect,
+            default_color,
+            flags,
+            real,
+            user_mask_density: None,
+            user_mask_feather: None,
+            vector_mask_density: None,
+            vector_mask_feather: None,
+        };
+
+        if flags.parameters_applied() && cursor.remaining() >= 1 {
+            let parameters = cursor.read_u8();
+            let has_parameter = |bit: u8| parameters & (1 << bit) != 0;
+
+            if has_parameter(0) && cursor.remaining() >= 1 {
+                layer_mask.user_mask_density = Some(cursor.read_u8());
+            }
+            if has_parameter(1) && cursor.remaining() >= 8 {
+                layer_mask.user_mask_feather = Some(cursor.read_f64());
+            }
+            if has_parameter(2) && cursor.remaining() >= 1 {
+                layer_mask.vector_mask_density = Some(cursor.read_u8());
+            }
+            if has_parameter(3) && cursor.remaining() >= 8 {
+                layer_mask.vector_mask_feather = Some(cursor.read_f64());
+            }
+        }
+
+        Some(layer_mask)
+    }
+
+    /// Overwrite the rectangles in the layer mask data that this mask was read from with the
+    /// mask's rectangles, since the layer record is written from that data.
+    pub(crate) fn write_rects(&self, mask_data: &mut [u8]) {
+        if let Some(rect_bytes) = mask_data.get_mut(0..16) {
+            self.rect.write(rect_bytes);
+        }
+
+        // After the rectangle, the default color, the flags, the real flags and the real
+        // background
+        if let (Some(real), Some(rect_bytes)) = (&self.real, mask_data.get_mut(20..36)) {
+            real.rect.write(rect_bytes);
+        }
+    }
+
+    /// The rectangle enclosing the mask
+    pub fn rect(&self) -> MaskRect {
+        self.rect
+    }
+
+    /// The color (0 or 255) of the mask outside of its rectangle
+    pub fn default_color(&self) -> u8 {
+        self.default_color
+    }
+
+    /// The flags of the mask
+    pub fn flags(&self) -> LayerMaskFlags {
+        self.flags
+    }
+
+    /// Whether the mask is linked to the layer, so that moving the layer also moves the mask.
+    ///
+    /// See [`LayerMaskFlags::position_relative_to_layer`].
+    pub fn is_linked(&self) -> bool {
+        self.flags.position_relative_to_layer()
+    }
+
+    /// The user mask, when the layer has both a user mask and a vector mask.
+    ///
+    /// In that case the rest of this `LayerMask` describes the vector mask.
+    pub fn real_user_mask(&self) -> Option<&RealUserMask> {
+        self.real.as_ref()
+    }
+
+    /// The density (0 to 255) of the user mask, if it isn't the default of 255
+    pub fn user_mask_density(&self) -> Option<u8> {
+        self.user_mask_density
+    }
+
+    /// The feather, in pixels, of the user mask, if it has one
+    pub fn user_mask_feather(&self) -> Option<f64> {
+        self.user_mask_feather
+    }
+
+    /// The density (0 to 255) of the vector mask, if it isn't the default of 255
+    pub fn vector_mask_density(&self) -> Option<u8> {
+        self.vector_mask_density
+    }
+
+    /// The feather, in pixels, of the vector mask, if it has one
+    pub fn vector_mask_feather(&self) -> Option<f64> {
+        self.vector_mask_feather
+    }
+}
+
+impl MaskRect {
+    fn read(cursor: &mut PsdCursor) -> MaskRect {
+        MaskRect {
+            top: cursor.read_i32(),
+            left: cursor.read_i32(),
+            bottom: cursor.read_i32(),
+            right: cursor.read_i32(),
+        }
+    }
+
+    /// The inverse of `MaskRect::read`, into the 16 bytes that the rectangle was read from
+    fn write(&self, bytes: &mut [u8]) {
+        for (edge, value) in bytes
+            .chunks_exact_mut(4)
+            .zip([self.top, self.left, self.bottom, self.right].iter())
+        {
+            edge.copy_from_slice(&value.to_be_bytes());
+        }
+    }
+
+    /// Move the rectangle down by `top` and right by `left`
+    pub(crate) fn translate(&self, top: i32, left: i32) -> MaskRect {
+        MaskRect {
+            top: self.top + top,
+            left: self.left + left,
+            bottom: self.bottom + top,
+            right: self.right + left,
+        }
+    }
+
+    /// The position of the top of the mask
+    pub fn top(&self) -> i32 {
+        self.top
+    }
+
+    /// The position of the left of the mask
+    pub fn left(&self) -> i32 {
+        self.left
+    }
+
+    /// The position right below the bottom of the mask
+    pub fn bottom(&self) -> i32 {
+        self.bottom
+    }
+
+    /// The position right after the right of the mask
+    pub fn right(&self) -> i32 {
+        self.right
+    }
+
+    /// The width of the mask, 0 if the rectangle is empty
+    pub fn width(&self) -> u32 {
+        (self.right - self.left).max(0) as u32
+    }
+
+    /// The height of the mask, 0 if the rectangle is empty
+    pub fn height(&self) -> u32 {
+        (self.bottom - self.top).max(0) as u32
+    }
+}
+
+impl RealUserMask {
+    /// The flags of the user mask
+    pub fn flags(&self) -> LayerMaskFlags {
+        self.flags
+    }
+
+    /// The color (0 or 255) of the user mask outside of its rectangle
+    pub fn background(&self) -> u8 {
+        self.background
+    }
+
+    /// The rectangle enclosing the user mask
+    pub fn rect(&self) -> MaskRect {
+        self.rect
+    }
+
+    /// Whether the user mask is linked to the layer, so that moving the layer also moves the
+    /// user mask.
+    pub fn is_linked(&self) -> bool {
+        self.flags.position_relative_to_layer()
+    }
+}
+
+impl LayerMaskFlags {
+    /// Create LayerMaskFlags from the flags byte of a layer mask
+    pub fn new(bits: u8) -> LayerMaskFlags {
+        LayerMaskFlags(bits)
+    }
+
+    /// The raw flags byte
+    pub fn bits(&self) -> u8 {
+        self.0
+    }
+
+    /// Bit 0 - the mask's position is relative to the layer.
+    ///
+    /// Such a mask is linked to the layer, so moving the layer also moves the mask.
+    pub fn position_relative_to_layer(&self) -> bool {
+        self.bit(0)
+    }
+
+    /// Bit 1 - the mask is disabled
+    pub fn disabled(&self) -> bool {
+        self.bit(1)
+    }
+
+    /// Bit 2 - invert the mask when blending (obsolete)
+    pub fn invert(&self) -> bool {
+        self.bit(2)
+    }
+
+    /// Bit 3 - the user mask actually came from rendering other data
+    pub fn from_rendered_data(&self) -> bool {
+        self.bit(3)
+    }
+
+    /// Bit 4 - the user and/or vector masks have parameters applied to them
+    pub fn parameters_applied(&self) -> bool {
+        self.bit(4)
+    }
+
+    fn bit(&self, bit: u8) -> bool {
+        self.0 & (1 << bit) != 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::psd_channel::PsdChannelKind;
+    use crate::sections::image_data_section::ChannelBytes;
+    use crate::sections::layer_and_mask_information_section::layer::{
+        BlendMode, LayerChannels, LayerFlags, LayerProperties,
+    };
+    use crate::{PsdDepth, PsdLayer};
+
+    fn rect_bytes(top: i32, left: i32, bottom: i32, right: i32) -> Vec<u8> {
+        [top, left, bottom, right]
+            .iter()
+            .flat_map(|value| value.to_be_bytes().to_vec())
+            .collect()
+    }
+
+    /// A user mask without a vector mask is 20 bytes, two of which are padding.
+    #[test]
+    fn read_user_mask() {
+        let mut bytes = rect_bytes(1, 2, 3, 4);
+        bytes.extend_from_slice(&[255, 0b10, 0, 0]);
+
+        let mask = LayerMask::read(&bytes).unwrap();
+        assert_eq!(
+            mask.rect(),
+            MaskRect {
+                top: 1,
+                left: 2,
+                bottom: 3,
+                right: 4
+            }
+        );
+        assert_eq!(mask.default_color(), 255);
+        assert!(mask.flags().disabled());
+        assert!(mask.real_user_mask().is_none());
+        assert_eq!(mask.user_mask_density(), None);
+    }
+
+    /// A layer with both a user mask and a vector mask holds the user mask in the real fields,
+    /// followed by the mask parameters.
+    #[test]
+    fn read_real_user_mask_and_parameters() {
+        let mut bytes = rect_bytes(0, 0, 10, 10);
+        bytes.extend_from_slice(&[0, 0b1_0000]);
+        bytes.extend_from_slice(&[0b1, 255]);
+        bytes.extend(rect_bytes(2, 3, 4, 5));
+        bytes.push(0b1010);
+        bytes.extend_from_slice(&2.5f64.to_be_bytes());
+        bytes.extend_from_slice(&1.5f64.to_be_bytes());
+
+        let mask = LayerMask::read(&bytes).unwrap();
+        let real = mask.real_user_mask().unwrap();
+        assert!(real.flags().position_relative_to_layer());
+        assert_eq!(real.background(), 255);
+        assert_eq!((real.rect().width(), real.rect().height()), (2, 2));
+
+        assert_eq!(mask.user_mask_density(), None);
+        assert_eq!(mask.user_mask_feather(), Some(2.5));
+        assert_eq!(mask.vector_mask_density(), None);
+        assert_eq!(mask.vector_mask_feather(), Some(1.5));
+    }
+
+    /// Bit 0 of the flags links the mask to the layer, separately for the user mask when there is
+    /// also a vector mask.
+    #[test]
+    fn linked_masks() {
+        let mut bytes = rect_bytes(0, 0, 1, 1);
+        bytes.extend_from_slice(&[0, 0b1, 0, 0]);
+        assert!(LayerMask::read(&bytes).unwrap().is_linked());
+
+        let mut bytes = rect_bytes(0, 0, 1, 1);
+        bytes.extend_from_slice(&[0, 0, 0b1, 0]);
+        bytes.extend(rect_bytes(0, 0, 1, 1));
+        let mask = LayerMask::read(&bytes).unwrap();
+        assert!(!mask.is_linked());
+        assert!(mask.real_user_mask().unwrap().is_linked());
+    }
+
+    #[test]
+    fn no_mask() {
+        assert_eq!(LayerMask::read(&[]), None);
+    }
+
+    /// A 3x3 layer in a 3x3 PSD with a user mask that hides the two pixels at (1, 1) and (2, 1).
+    fn layer_with_mask(flags: u8, density: Option<u8>) -> PsdLayer {
+        let mut mask_data = rect_bytes(1, 1, 2, 3);
+        match density {
+            Some(density) => mask_data.extend_from_slice(&[255, flags | 0b1_0000, 0b1, density]),
+            None => mask_data.extend_from_slice(&[255, flags, 0, 0]),
+        }
+
+        let layer_properties = LayerProperties {
+            name: "".into(),
+            layer_top: 0,
+            layer_left: 0,
+            layer_bottom: 2,
+            layer_right: 2,
+            flags: LayerFlags::new(1 << 1),
+            opacity: 255,
+            clipping_mask: false,
+            psd_width: 3,
+            psd_height: 3,
+            blend_mode: BlendMode::Normal,
+            group_id: None,
+            layer_id: None,
+            layer_mask: LayerMask::read(&mask_data),
+            record_data: Default::default(),
+        };
+
+        PsdLayer {
+            channels: LayerChannels::from([
+                (
+                    PsdChannelKind::Red,
+                    ChannelBytes::RawData(vec![200; 9].into()),
+                ),
+                (
+                    PsdChannelKind::UserSuppliedLayerMask,
+                    // Two repeats of 0
+                    ChannelBytes::RleCompressed(vec![255, 0].into()),
+                ),
+            ]),
+            scanline_byte_counts: Default::default(),
+            zip_compressed_channels: Default::default(),
+            depth: PsdDepth::Eight,
+            layer_properties,
+            text_layer_info: None,
+            placed_layer: None,
+            rgba_cache: Default::default(),
+        }
+    }
+
+    fn alphas(rgba: &[u8]) -> Vec<u8> {
+        rgba.chunks_exact(4).map(|pixel| pixel[3]).collect()
+    }
+
+    /// The mask's pixels are placed at its rectangle, with the default color everywhere else.
+    #[test]
+    fn mask_pixels() {
+        let layer = layer_with_mask(0, None);
+
+        assert_eq!(
+            layer.mask_pixels().unwrap(),
+            vec![255, 255, 255, 255, 0, 0, 255, 255, 255]
+        );
+    }
+
+    /// Masks hide the pixels of the layer, unless they are disabled.
+    #[test]
+    fn apply_masks() {
+        let layer = layer_with_mask(0, None);
+        let mut rgba = layer.rgba();
+        layer.apply_masks(&mut rgba);
+        assert_eq!(alphas(&rgba), vec![255, 255, 255, 255, 0, 0, 255, 255, 255]);
+
+        let disabled = layer_with_mask(0b10, None);
+        let mut rgba = disabled.rgba();
+        disabled.apply_masks(&mut rgba);
+        assert_eq!(alphas(&rgba), vec![255; 9]);
+    }
+
+    /// A mask with a lower density hides less of the layer.
+    #[test]
+    fn apply_masks_with_density() {
+        let layer = layer_with_mask(0, Some(51));
+        let mut rgba = layer.rgba();
+        layer.apply_masks(&mut rgba);
+
+        assert_eq!(alphas(&rgba)[4..6], [204, 204]);
+    }
+}
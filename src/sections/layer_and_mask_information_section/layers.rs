@@ -1,54 +1,166 @@
 use crate::PsdLayer;
 use std::collections::HashMap;
-use std::ops::{Deref, Range};
+use std::ops::Index;
+use std::slice::SliceIndex;
 
-/// `NamedItems` is immutable container for storing items with order-preservation
-/// and indexing by id and name
-#[derive(Debug)]
-pub(crate) struct Layers {
+/// The separator between group names and the layer name in a layer's path.
+///
+/// A layer named "Eyes" inside of a group named "Face" that is inside of a group named
+/// "Character" has the path "Character/Face/Eyes".
+pub(crate) const LAYER_PATH_SEPARATOR: &str = "/";
+
+/// The layers of a PSD in order, from the top of the layers panel to the bottom, along with
+/// lookups by name, ID and path.
+///
+/// The lookup tables are built as layers are pushed so that lookups don't need to scan every
+/// layer, which adds up for documents with thousands of layers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Layers {
     items: Vec<PsdLayer>,
-    // TODO: This is incorrect since layers can have the same name. Perhaps a Vec<u32> instead of
-    //  usize. Add a failing test with a PSD with two layers with the same name.
-    //  Take inspiration from the `Groups` type.
-    item_indices: HashMap<String, usize>,
+    /// Layer ID (from the layer's `lyid` block) -> index
+    id_indices: HashMap<u32, usize>,
+    /// Layers can have the same name, so a name maps to every index that has that name
+    /// (in the same order as the items).
+    name_indices: HashMap<String, Vec<usize>>,
+    /// Layer path -> index
+    path_indices: HashMap<String, usize>,
 }
 
 impl Layers {
-    /// Creates a new `NamedItems`
-    pub fn new() -> Self {
-        Layers {
-            items: vec![],
-            item_indices: HashMap::new(),
-        }
+    /// Creates an empty list of layers
+    pub(crate) fn new() -> Self {
+        Layers::with_capacity(0)
     }
 
-    /// Creates a new `NamedItems` with the specified capacity
-    pub fn with_capacity(capacity: usize) -> Self {
+    /// Creates an empty list of layers with room for `capacity` layers
+    pub(crate) fn with_capacity(capacity: usize) -> Self {
         Layers {
             items: Vec::with_capacity(capacity),
-            item_indices: HashMap::with_capacity(capacity),
+            id_indices: HashMap::with_capacity(capacity),
+            name_indices: HashMap::with_capacity(capacity),
+            path_indices: HashMap::with_capacity(capacity),
+        }
+    }
+
+    /// The number of layers
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Whether there aren't any layers
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Get a layer by its index, where index 0 is the top layer
+    pub fn get(&self, idx: usize) -> Option<&PsdLayer> {
+        self.items.get(idx)
+    }
+
+    /// Iterate over the layers from the top layer to the bottom layer
+    pub fn iter(&self) -> std::slice::Iter<'_, PsdLayer> {
+        self.items.iter()
+    }
+
+    /// Get the last layer with the given name.
+    pub fn by_name(&self, name: &str) -> Option<&PsdLayer> {
+        self.item_by_name(name)
+    }
+
+    /// Get every layer with the given name, from the top layer to the bottom layer.
+    pub fn all_by_name<'a>(&'a self, name: &str) -> impl Iterator<Item = &'a PsdLayer> + 'a {
+        self.item_indices_by_name(name)
+            .iter()
+            .map(move |idx| &self.items[*idx])
+    }
+
+    /// Get a layer by the unique ID that Photoshop assigned to it, see `PsdLayer::layer_id`.
+    pub fn by_id(&self, id: u32) -> Option<&PsdLayer> {
+        self.item_by_id(id)
+    }
+
+    /// Get a layer by its path, which is the names of the groups that it is nested in followed
+    /// by its own name, separated by `/`.
+    pub fn by_path(&self, path: &str) -> Option<&PsdLayer> {
+        self.item_by_path(path)
+    }
+
+    /// The layers as a slice, from the top layer to the bottom layer
+    pub fn as_slice(&self) -> &[PsdLayer] {
+        &self.items
+    }
+
+    /// Get the last layer with the given name.
+    pub(crate) fn item_by_name(&self, name: &str) -> Option<&PsdLayer> {
+        match self.name_indices.get(name) {
+            Some(item_indices) => self.items.get(*item_indices.last()?),
+            None => None,
+        }
+    }
+
+    /// Get the indices of every layer with the given name, in order.
+    pub(crate) fn item_indices_by_name(&self, name: &str) -> &[usize] {
+        match self.name_indices.get(name) {
+            Some(item_indices) => item_indices,
+            None => &[],
+        }
+    }
+
+    /// Get a layer by the ID in its `lyid` block.
+    pub(crate) fn item_by_id(&self, id: u32) -> Option<&PsdLayer> {
+        match self.id_indices.get(&id) {
+            Some(item_idx) => self.items.get(*item_idx),
+            None => None,
         }
     }
 
-    #[allow(missing_docs)]
-    pub fn item_by_name(&self, name: &str) -> Option<&PsdLayer> {
-        match self.item_indices.get(name) {
+    /// Get the last layer with the given path.
+    pub(crate) fn item_by_path(&self, path: &str) -> Option<&PsdLayer> {
+        match self.path_indices.get(path) {
             Some(item_idx) => self.items.get(*item_idx),
             None => None,
         }
     }
 
-    #[allow(missing_docs)]
-    pub(in crate) fn push(&mut self, name: String, item: PsdLayer) {
+    /// Add a layer, making it last in the order.
+    ///
+    /// `path` is the names of the groups that the layer is nested in followed by the name of
+    /// the layer, separated by `LAYER_PATH_SEPARATOR`.
+    pub(crate) fn push(&mut self, path: String, item: PsdLayer) {
+        let idx = self.items.len();
+
+        if let Some(id) = item.layer_id() {
+            self.id_indices.insert(id, idx);
+        }
+        self.name_indices
+            .entry(item.name().to_string())
+            .or_default()
+            .push(idx);
+        self.path_indices.insert(path, idx);
+
         self.items.push(item);
-        self.item_indices.insert(name, self.items.len() - 1);
+    }
+
+    /// Iterate over the layers mutably. Their names, IDs and paths can't change, since we index
+    /// the layers by them.
+    pub(crate) fn iter_mut(&mut self) -> impl Iterator<Item = &mut PsdLayer> {
+        self.items.iter_mut()
     }
 }
 
-impl Deref for Layers {
-    type Target = Vec<PsdLayer>;
+impl<I: SliceIndex<[PsdLayer]>> Index<I> for Layers {
+    type Output = I::Output;
 
-    fn deref(&self) -> &Self::Target {
-        &self.items
+    fn index(&self, idx: I) -> &I::Output {
+        &self.items[idx]
+    }
+}
+
+impl<'a> IntoIterator for &'a Layers {
+    type Item = &'a PsdLayer;
+    type IntoIter = std::slice::Iter<'a, PsdLayer>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.items.iter()
     }
 }
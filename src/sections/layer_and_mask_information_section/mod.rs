@@ -1,30 +1,81 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::convert::TryFrom;
 use std::ops::Range;
 
 use crate::psd_channel::PsdChannelCompression;
 use crate::psd_channel::PsdChannelKind;
+use crate::psd_channel::{samples_to_eight_bits, zip_decompress};
+use crate::sections::file_header_section::PsdVersion;
 use crate::sections::image_data_section::ChannelBytes;
 use crate::sections::layer_and_mask_information_section::groups::Groups;
 use crate::sections::layer_and_mask_information_section::layer::{
-    BlendMode, GroupDivider, LayerChannels, LayerRecord, PsdGroup, PsdLayer, PsdLayerError,
+    BlendMode, GroupDivider, LayerChannels, LayerFlags, LayerProperties, LayerRecord,
+    LayerRecordData, PsdGroup, PsdLayer, PsdLayerError, ScanlineByteCounts, ZipCompressedChannels,
 };
-use crate::sections::layer_and_mask_information_section::layers::Layers;
+use crate::sections::layer_and_mask_information_section::layer_count::LayerCount;
+use crate::sections::layer_and_mask_information_section::layer_mask::LayerMask;
+use crate::sections::layer_and_mask_information_section::layers::{Layers, LAYER_PATH_SEPARATOR};
+use crate::sections::layer_and_mask_information_section::pattern::Pattern;
+use crate::sections::layer_and_mask_information_section::smart_object::{LinkedFile, PlacedLayer};
+use crate::sections::layer_and_mask_information_section::tagged_block::{
+    TaggedBlock, TaggedBlockContext, TaggedBlockHeader,
+};
+use crate::sections::layer_and_mask_information_section::text_layer::TextLayerInfo;
+use crate::sections::serialize::PsdBuffer;
 use crate::sections::PsdCursor;
-
-/// One of the possible additional layer block signatures
-const SIGNATURE_EIGHT_BIM: [u8; 4] = [56, 66, 73, 77];
-/// One of the possible additional layer block signatures
-const SIGNATURE_EIGHT_B64: [u8; 4] = [56, 66, 54, 52];
+use crate::shared_bytes::SharedBytes;
+use crate::PsdDepth;
 
 /// Additional Layer Information constants.
 /// Key of `Unicode layer name (Photoshop 5.0)`, "luni"
 const KEY_UNICODE_LAYER_NAME: &[u8; 4] = b"luni";
 /// Key of `Section divider setting (Photoshop 6.0)`, "lsct"
-const KEY_SECTION_DIVIDER_SETTING: &[u8; 4] = b"lsct";
-
+pub(crate) const KEY_SECTION_DIVIDER_SETTING: &[u8; 4] = b"lsct";
+/// Key of `Type tool object setting (Photoshop 6.0)`, "TySh"
+const KEY_TYPE_TOOL_OBJECT_SETTING: &[u8; 4] = b"TySh";
+/// Key of `Layer ID (Photoshop 5.0)`, "lyid"
+const KEY_LAYER_ID: &[u8; 4] = b"lyid";
+/// Key of `Placed Layer Data (Photoshop CS3)`, "SoLd"
+const KEY_PLACED_LAYER_DATA: &[u8; 4] = b"SoLd";
+/// Key of the placed layer data of a smart object that links to an external file, "SoLE"
+const KEY_LINKED_PLACED_LAYER_DATA: &[u8; 4] = b"SoLE";
+/// Key of `Placed Layer (replaced by SoLd in Photoshop CS3)`, "PlLd"
+const KEY_PLACED_LAYER: &[u8; 4] = b"PlLd";
+/// Keys of the global tagged blocks that hold the layer info of 16 and 32 bit documents,
+/// "Lr16" and "Lr32", along with "Layr", which some writers use instead of the start of the
+/// section
+pub(crate) const KEYS_LAYER_INFO_BLOCK: [&[u8; 4]; 3] = [b"Lr16", b"Lr32", b"Layr"];
+
+/// The order that we write a layer's channels in, which is the order that Photoshop uses
+const CHANNEL_WRITE_ORDER: [PsdChannelKind; 7] = [
+    PsdChannelKind::TransparencyMask,
+    PsdChannelKind::Red,
+    PsdChannelKind::Green,
+    PsdChannelKind::Blue,
+    PsdChannelKind::Black,
+    PsdChannelKind::UserSuppliedLayerMask,
+    PsdChannelKind::RealUserSuppliedLayerMask,
+];
+
+#[cfg(feature = "unstable")]
+pub mod adjustment;
+pub mod blending_ranges;
+#[cfg(feature = "unstable")]
+pub mod fill;
+pub mod global_layer_mask;
 pub mod groups;
 pub mod layer;
+pub mod layer_count;
+#[cfg(feature = "unstable")]
+pub mod layer_effects;
+pub mod layer_mask;
 pub mod layers;
+pub mod pattern;
+pub mod smart_object;
+pub mod tagged_block;
+pub mod text_layer;
+#[cfg(feature = "unstable")]
+pub mod vector_mask;
 
 /// The LayerAndMaskInformationSection comes from the bytes in the fourth section of the PSD.
 ///
@@ -53,10 +104,53 @@ pub mod layers;
 /// | Variable | Layer info (see See Layer info for details).                                                                                                                                               |
 /// | Variable | Global layer mask info (see See Global layer mask info for details).                                                                                                                       |
 /// | Variable | (Photoshop 4.0 and later) <br> Series of tagged blocks containing various types of data. See See Additional Layer Information for the list of the types of data that can be included here. |
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct LayerAndMaskInformationSection {
     pub(crate) layers: Layers,
     pub(crate) groups: Groups,
+    pub(crate) global_tagged_blocks: Vec<TaggedBlock>,
+    /// The files of the document's smart objects, read from its linked layer tagged blocks
+    pub(crate) linked_files: Vec<LinkedFile>,
+    /// The patterns of the document, read from its pattern tagged blocks
+    pub(crate) patterns: Vec<Pattern>,
+    /// The global layer mask info, see `GlobalLayerMask`
+    pub(crate) global_layer_mask_info: Vec<u8>,
+    /// Whether the layer count was negative, meaning that the first alpha channel of the image
+    /// data holds the transparency of the merged result
+    pub(crate) merged_transparency: bool,
+    /// The declared layer count and the number of layer records that were parsed
+    pub(crate) layer_count: LayerCount,
+    /// The key of the `Lr16`, `Lr32` or `Layr` global tagged block that the layer info was
+    /// stored in, along with where the block was among the global tagged blocks. `None` when the
+    /// layer info was stored at the start of the section.
+    pub(crate) layer_info_block: Option<(usize, [u8; 4])>,
+}
+
+/// A layer record along with the data of its channels
+type RecordWithChannels = (
+    LayerRecord,
+    LayerChannels,
+    ScanlineByteCounts,
+    ZipCompressedChannels,
+);
+
+/// A layer record that is about to be written
+#[derive(Debug)]
+enum RecordToWrite<'a> {
+    Layer(&'a PsdLayer),
+    /// The record that opens a group, which holds the group's name
+    GroupFolder(&'a PsdGroup),
+    /// The hidden record that closes a group
+    GroupBounding(&'a PsdGroup),
+}
+
+/// The data of one of a layer record's channels
+#[derive(Debug)]
+pub(crate) struct ChannelToWrite<'a> {
+    pub(crate) kind: PsdChannelKind,
+    pub(crate) compression: PsdChannelCompression,
+    pub(crate) scanline_byte_counts: &'a [u32],
+    pub(crate) data: &'a [u8],
 }
 
 /// Frame represents a group stack frame
@@ -66,66 +160,70 @@ struct Frame {
     name: String,
     group_id: u32,
     parent_group_id: u32,
+    /// The record that opened the group, `None` for the root
+    folder_record: Option<LayerRecord>,
 }
 
 impl LayerAndMaskInformationSection {
     /// Create a LayerAndMaskInformationSection from the bytes in the corresponding section in a
     /// PSD file (including the length marker).
+    ///
+    /// Raw and RLE compressed channels point into `shared` when the bytes are a part of it.
     pub fn from_bytes(
         bytes: &[u8],
+        version: PsdVersion,
+        depth: PsdDepth,
         psd_width: u32,
         psd_height: u32,
+        shared: Option<&SharedBytes>,
     ) -> Result<LayerAndMaskInformationSection, PsdLayerError> {
-        let mut cursor = PsdCursor::new(bytes);
-
-        // The first four bytes of the section is the length marker for the layer and mask
-        // information section.
-        //
-        // We do not currently use it since the number of bytes passed into this function was
-        // the exact number of bytes in the layer and information mask section of the PSD file,
-        // so there's no way for us to accidentally read too many bytes. If we did the program
-        // would panic.
-        let len = cursor.read_u32();
+        LayerSectionReader::new(bytes, version, depth, psd_width, psd_height)
+            .with_shared_bytes(shared.cloned())
+            .finish()
+    }
 
-        if len == 0 {
-            return Ok(LayerAndMaskInformationSection {
-                layers: Layers::new(),
-                groups: Groups::with_capacity(0),
-            });
+    /// Read the global layer mask info and the tagged blocks that follow it.
+    ///
+    /// Not every writer agrees on the layout of the end of this section, so if anything looks
+    /// off we stop reading instead of returning an error.
+    ///
+    /// # [Adobe Docs](https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/)
+    ///
+    /// Global layer mask info
+    ///
+    /// | Length   | Description                                                   |
+    /// |----------|---------------------------------------------------------------|
+    /// | 4        | Length of global layer mask info section.                     |
+    /// | Variable | Global layer mask data                                        |
+    fn read_global_info(
+        cursor: &mut PsdCursor,
+        version: PsdVersion,
+        layer_info_section_end: u64,
+    ) -> (Vec<u8>, Vec<TaggedBlock>) {
+        let section_end = cursor.get_ref().len() as u64;
+        if layer_info_section_end > section_end.saturating_sub(4) {
+            return (vec![], vec![]);
         }
 
-        // Read the next four bytes to get the length of the layer info section.
-        let _layer_info_section_len = cursor.read_u32();
+        cursor.seek(layer_info_section_end);
+        let global_layer_mask_info_len = cursor.read_u32() as u64;
 
-        // Next 2 bytes is the layer count
-        //
-        // NOTE: Appears to be -1 when we create a new PSD and don't create any new layers but
-        // instead only manipulate the default background layer.
-        //
-        // # [Adobe Docs](https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/)
-        //
-        // Layer count. If it is a negative number, its absolute value is the number of layers and
-        // the first alpha channel contains the transparency data for the merged result.
-        let layer_count = cursor.read_i16();
+        let global_layer_mask_info = match cursor.read_checked(global_layer_mask_info_len) {
+            Some(global_layer_mask_info) => global_layer_mask_info.to_vec(),
+            None => return (vec![], vec![]),
+        };
 
-        // TODO: If the layer count was negative we were supposed to treat the first alpha
-        // channel as transparency data for the merged result.. So add a new test with a transparent
-        // PSD and make sure that we're handling this case properly.
-        let layer_count: u16 = layer_count.abs() as u16;
-        let (group_count, layer_records) =
-            LayerAndMaskInformationSection::read_layer_records(&mut cursor, layer_count)?;
+        let global_tagged_blocks =
+            TaggedBlock::read_all(cursor, version, TaggedBlockContext::Document, section_end);
 
-        LayerAndMaskInformationSection::decode_layers(
-            layer_records,
-            group_count,
-            (psd_width, psd_height),
-        )
+        (global_layer_mask_info, global_tagged_blocks)
     }
 
     fn decode_layers(
-        layer_records: Vec<(LayerRecord, LayerChannels)>,
+        layer_records: Vec<RecordWithChannels>,
         group_count: usize,
         psd_size: (u32, u32),
+        depth: PsdDepth,
     ) -> Result<LayerAndMaskInformationSection, PsdLayerError> {
         let mut layers = Layers::with_capacity(layer_records.len());
         let mut groups = Groups::with_capacity(group_count);
@@ -136,13 +234,16 @@ impl LayerAndMaskInformationSection {
             name: String::from("root"),
             group_id: 0,
             parent_group_id: 0,
+            folder_record: None,
         }];
 
         // Viewed group counter
         let mut already_viewed = 0;
 
         // Read each layer's channel image data
-        for (layer_record, channels) in layer_records.into_iter() {
+        for (layer_record, channels, scanline_byte_counts, zip_compressed_channels) in
+            layer_records.into_iter()
+        {
             // get current group from stack
             let current_group_id = stack.last().unwrap().group_id;
 
@@ -153,9 +254,10 @@ impl LayerAndMaskInformationSection {
 
                     let frame = Frame {
                         start_idx: layers.len(),
-                        name: layer_record.name,
+                        name: layer_record.name.clone(),
                         group_id: already_viewed,
                         parent_group_id: current_group_id,
+                        folder_record: Some(layer_record),
                     };
 
                     stack.push(frame);
@@ -171,10 +273,10 @@ impl LayerAndMaskInformationSection {
                     };
 
                     groups.push(PsdGroup::new(
-                        frame.name,
                         frame.group_id,
                         range,
                         &layer_record,
+                        frame.folder_record.as_ref().unwrap(),
                         psd_size.0,
                         psd_size.1,
                         if frame.parent_group_id > 0 {
@@ -190,78 +292,536 @@ impl LayerAndMaskInformationSection {
                         &layer_record,
                         current_group_id,
                         psd_size,
+                        depth,
                         channels,
+                        scanline_byte_counts,
+                        zip_compressed_channels,
                     )?;
 
-                    layers.push(psd_layer.name.clone(), psd_layer);
+                    let path = stack
+                        .iter()
+                        .skip(1)
+                        .map(|frame| frame.name.as_str())
+                        .chain(std::iter::once(psd_layer.name()))
+                        .collect::<Vec<&str>>()
+                        .join(LAYER_PATH_SEPARATOR);
+
+                    layers.push(path, psd_layer);
                 }
             };
         }
 
-        Ok(LayerAndMaskInformationSection { layers, groups })
+        Ok(LayerAndMaskInformationSection {
+            layers,
+            groups,
+            global_tagged_blocks: vec![],
+            linked_files: vec![],
+            patterns: vec![],
+            global_layer_mask_info: vec![],
+            merged_transparency: false,
+            layer_count: LayerCount::default(),
+            layer_info_block: None,
+        })
     }
 
-    fn read_layer_records(
-        cursor: &mut PsdCursor,
-        layer_count: u16,
-    ) -> Result<(usize, Vec<(LayerRecord, LayerChannels)>), PsdLayerError> {
-        let mut groups_count = 0;
+    fn read_layer(
+        layer_record: &LayerRecord,
+        parent_id: u32,
+        psd_size: (u32, u32),
+        depth: PsdDepth,
+        channels: LayerChannels,
+        scanline_byte_counts: ScanlineByteCounts,
+        zip_compressed_channels: ZipCompressedChannels,
+    ) -> Result<PsdLayer, PsdLayerError> {
+        Ok(PsdLayer::new(
+            &layer_record,
+            psd_size.0,
+            psd_size.1,
+            depth,
+            if parent_id > 0 { Some(parent_id) } else { None },
+            channels,
+            scanline_byte_counts,
+            zip_compressed_channels,
+        ))
+    }
 
-        let mut layer_records = vec![];
-        // Read each layer record
-        for _layer_num in 0..layer_count {
-            let layer_record = read_layer_record(cursor)?;
+    /// Write the section back into the bytes of a PSD file (including the length marker).
+    ///
+    /// The inverse of `LayerAndMaskInformationSection::from_bytes`.
+    pub(crate) fn write(&self, buffer: &mut PsdBuffer, version: PsdVersion) {
+        let section_length = buffer.start_length_for(version);
+
+        let records = self.records_in_file_order();
+        if records.is_empty()
+            && self.global_layer_mask_info.is_empty()
+            && self.global_tagged_blocks.is_empty()
+        {
+            buffer.end_length_for(section_length, version);
+            return;
+        }
 
-            match layer_record.divider_type {
-                Some(GroupDivider::BoundingSection) => {
-                    groups_count = groups_count + 1;
+        let layer_info_length = buffer.start_length_for(version);
+        if self.layer_info_block.is_none() {
+            self.write_layer_info(buffer, version, &records);
+        }
+        buffer.end_length_for(layer_info_length, version);
+
+        buffer.write_u32(self.global_layer_mask_info.len() as u32);
+        buffer.write(&self.global_layer_mask_info);
+
+        for (idx, block) in self.global_tagged_blocks.iter().enumerate() {
+            match self.layer_info_block {
+                Some((block_idx, key)) if block_idx == idx => {
+                    self.write_layer_info_block(buffer, version, &records, key);
                 }
                 _ => {}
             }
+            block.write(buffer, version, TaggedBlockContext::Document);
+        }
+        match self.layer_info_block {
+            Some((block_idx, key)) if block_idx >= self.global_tagged_blocks.len() => {
+                self.write_layer_info_block(buffer, version, &records, key);
+            }
+            _ => {}
+        }
+
+        buffer.end_length_for(section_length, version);
+    }
+
+    /// Write the layer count, the layer records and their channels, without a length marker.
+    fn write_layer_info(
+        &self,
+        buffer: &mut PsdBuffer,
+        version: PsdVersion,
+        records: &[RecordToWrite],
+    ) {
+        let records: Vec<(&LayerProperties, Vec<ChannelToWrite>)> = records
+            .iter()
+            .map(|record| (record.properties(), record.channels()))
+            .collect();
+
+        write_layer_records(buffer, version, self.merged_transparency, &records);
+    }
+
+    /// Write the layer info into an `Lr16`, `Lr32` or `Layr` global tagged block.
+    fn write_layer_info_block(
+        &self,
+        buffer: &mut PsdBuffer,
+        version: PsdVersion,
+        records: &[RecordToWrite],
+        key: [u8; 4],
+    ) {
+        let mut layer_info = PsdBuffer::new();
+        self.write_layer_info(&mut layer_info, version, records);
+
+        let block = TaggedBlock {
+            key,
+            data: layer_info.into_inner(),
+        };
+        block.write(buffer, version, TaggedBlockContext::Document);
+    }
+
+    /// The layer records from the bottom of the PSD to the top, with a record at the top and
+    /// bottom of every group.
+    ///
+    /// The inverse of `LayerAndMaskInformationSection::decode_layers`.
+    fn records_in_file_order<'a>(&'a self) -> Vec<RecordToWrite<'a>> {
+        // Group IDs are handed out in the order that the groups are opened, from the top of the
+        // PSD to the bottom
+        let mut groups: Vec<&PsdGroup> = self.groups.iter().collect();
+        groups.sort_by_key(|group| group.id);
+
+        let mut records = vec![];
+        let mut open_groups: Vec<&PsdGroup> = vec![];
+        let mut next_layer = 0;
+
+        let mut push_layers = |records: &mut Vec<RecordToWrite<'a>>, end: usize| {
+            let end = end.max(next_layer);
+            records.extend(
+                self.layers[next_layer..end]
+                    .iter()
+                    .map(RecordToWrite::Layer),
+            );
+            next_layer = end;
+        };
+
+        for group in groups {
+            // Close the groups that this group isn't nested in
+            while let Some(open_group) =
+                open_groups.pop_if(|open_group| Some(open_group.id) != group.parent_id())
+            {
+                push_layers(&mut records, open_group.contained_layers.end);
+                records.push(RecordToWrite::GroupBounding(open_group));
+            }
+
+            push_layers(&mut records, group.contained_layers.start);
+            records.push(RecordToWrite::GroupFolder(group));
+            open_groups.push(group);
+        }
+
+        while let Some(open_group) = open_groups.pop() {
+            push_layers(&mut records, open_group.contained_layers.end);
+            records.push(RecordToWrite::GroupBounding(open_group));
+        }
+        push_layers(&mut records, self.layers.len());
+
+        // Photoshop stores layers in reverse order
+        records.reverse();
+        records
+    }
+}
+
+/// The names of the layers in a run of layer records, in the same order as `Psd::layers`. Used
+/// to list the layers of a file without reading their channels, see `PsdReader`.
+pub(crate) fn layer_names(
+    records: &[u8],
+    layer_count: usize,
+    version: PsdVersion,
+) -> Result<Vec<String>, PsdLayerError> {
+    let mut cursor = PsdCursor::new(records);
+    let mut names = Vec::with_capacity(layer_count);
+
+    for _ in 0..layer_count {
+        let layer_record = read_layer_record(&mut cursor, version)?;
+
+        match layer_record.divider_type {
+            Some(GroupDivider::OpenFolder)
+            | Some(GroupDivider::CloseFolder)
+            | Some(GroupDivider::BoundingSection) => {}
+            _ => names.push(layer_record.name),
+        }
+    }
+
+    // Photoshop stores layers in reverse order
+    names.reverse();
+    Ok(names)
+}
+
+/// Reads the layer and mask information section a bounded number of layer records at a time, so
+/// that parsing a large file can be spread across many calls, see `ParseJob`.
+///
+/// Every layer record comes before the channels of the first layer, so the records are all read
+/// before any of their channels.
+pub(crate) struct LayerSectionReader<'a> {
+    /// The bytes of the whole section
+    bytes: &'a [u8],
+    /// The cursor that the layer info is read from, which is limited to the `Lr16`, `Lr32` or
+    /// `Layr` block when the layer info is stored in one
+    cursor: PsdCursor<'a>,
+    version: PsdVersion,
+    depth: PsdDepth,
+    psd_size: (u32, u32),
+    /// Whether the section only holds its length marker, set to zero
+    is_empty: bool,
+    /// Where the layer info ends and the global layer mask info starts
+    layer_info_section_end: u64,
+    /// Where the layer records and channels end in the cursor, which is the end of the block
+    /// when the layer info is stored in one
+    layer_info_end: u64,
+    /// The layer count as it was stored, including its sign
+    declared_layer_count: i16,
+    /// The number of layer records to read, which is less than the declared count if the layer
+    /// info ends early
+    layer_count: usize,
+    /// Whether the layer count was negative, see `LayerAndMaskInformationSection`
+    merged_transparency: bool,
+    /// See `LayerAndMaskInformationSection`
+    layer_info_block: Option<(usize, [u8; 4])>,
+    group_count: usize,
+    /// The records whose channels haven't been read yet, in the order that they are stored
+    records: VecDeque<LayerRecord>,
+    /// The records whose channels have been read, in the order that they are stored
+    records_with_channels: Vec<RecordWithChannels>,
+    /// The file that `bytes` are a part of, which raw and RLE compressed channels point into
+    /// instead of being copied out of it, see `Psd::from_shared_bytes`
+    shared: Option<SharedBytes>,
+}
+
+impl<'a> LayerSectionReader<'a> {
+    /// Start reading the bytes of the section (including the length marker), reading the lengths
+    /// and the layer count.
+    pub(crate) fn new(
+        bytes: &'a [u8],
+        version: PsdVersion,
+        depth: PsdDepth,
+        psd_width: u32,
+        psd_height: u32,
+    ) -> LayerSectionReader<'a> {
+        let mut cursor = PsdCursor::new(bytes);
+
+        // The first four bytes of the section is the length marker for the layer and mask
+        // information section.
+        //
+        // We do not currently use it since the number of bytes passed into this function was
+        // the exact number of bytes in the layer and information mask section of the PSD file,
+        // so there's no way for us to accidentally read too many bytes. If we did the program
+        // would panic.
+        let len = cursor.read_length(version);
+
+        let mut reader = LayerSectionReader {
+            bytes,
+            cursor,
+            version,
+            depth,
+            psd_size: (psd_width, psd_height),
+            is_empty: len == 0,
+            layer_info_section_end: 0,
+            layer_info_end: 0,
+            declared_layer_count: 0,
+            layer_count: 0,
+            merged_transparency: false,
+            layer_info_block: None,
+            group_count: 0,
+            records: VecDeque::new(),
+            records_with_channels: vec![],
+            shared: None,
+        };
+        if reader.is_empty {
+            return reader;
+        }
+
+        // Read the next four bytes to get the length of the layer info section.
+        let layer_info_section_len = reader.cursor.read_length(version);
+        // A corrupt 8 byte length could overflow, in which case there are no global tagged
+        // blocks to read
+        reader.layer_info_section_end = reader
+            .cursor
+            .position()
+            .saturating_add(layer_info_section_len);
+        reader.layer_info_end = reader.layer_info_section_end.min(bytes.len() as u64);
+
+        // 16 and 32 bit documents, and the documents of some other writers, store their layer
+        // info in a global tagged block, leaving the layer info at the start of the section empty
+        if layer_info_section_len == 0 {
+            match find_layer_info_block(bytes, version, reader.layer_info_section_end) {
+                Some((idx, header)) => {
+                    // The 8 byte length of a PSB's block might not fit in a usize
+                    let data = usize::try_from(header.data_len)
+                        .ok()
+                        .and_then(|data_len| bytes[header.data_start as usize..].get(..data_len));
+                    let data = match data {
+                        Some(data) => data,
+                        None => return reader,
+                    };
+                    reader.cursor = PsdCursor::new(data);
+                    reader.layer_info_end = header.data_len;
+                    reader.layer_info_block = Some((idx, header.key));
+                }
+                None => return reader,
+            }
+        }
+
+        // Next 2 bytes is the layer count
+        //
+        // NOTE: Appears to be -1 when we create a new PSD and don't create any new layers but
+        // instead only manipulate the default background layer.
+        //
+        // # [Adobe Docs](https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/)
+        //
+        // Layer count. If it is a negative number, its absolute value is the number of layers and
+        // the first alpha channel contains the transparency data for the merged result.
+        let layer_count = reader.cursor.read_i16();
+        reader.declared_layer_count = layer_count;
+        reader.merged_transparency = layer_count < 0;
+
+        // TODO: If the layer count was negative we were supposed to treat the first alpha
+        // channel as transparency data for the merged result.. So add a new test with a transparent
+        // PSD and make sure that we're handling this case properly.
+        reader.layer_count = layer_count.unsigned_abs() as usize;
+
+        reader
+    }
+
+    /// Point the channels into the file that the section's bytes are a part of, instead of
+    /// copying them.
+    pub(crate) fn with_shared_bytes(mut self, shared: Option<SharedBytes>) -> Self {
+        self.shared = shared;
+        self
+    }
+
+    /// The number of layer records in the section, including the records that open and close
+    /// groups
+    pub(crate) fn layer_count(&self) -> usize {
+        self.layer_count
+    }
+
+    /// The number of layer records that have been read
+    pub(crate) fn records_read(&self) -> usize {
+        self.records.len() + self.records_with_channels.len()
+    }
+
+    /// The number of layer records whose channels have been read
+    pub(crate) fn channels_read(&self) -> usize {
+        self.records_with_channels.len()
+    }
+
+    /// Whether every layer record and its channels have been read
+    pub(crate) fn is_done(&self) -> bool {
+        self.channels_read() == self.layer_count
+    }
+
+    /// Read up to `max_records` more layer records, or once every record has been read, the
+    /// channels of up to `max_records` more records.
+    pub(crate) fn read(&mut self, max_records: usize) -> Result<(), PsdLayerError> {
+        let mut budget = max_records;
+
+        if self.records_read() < self.layer_count {
+            while budget > 0 && self.records_read() < self.layer_count {
+                // A layer info that ends before its declared count is truncated, so we keep the
+                // records that are there, see `LayerCount::is_truncated`
+                if self.cursor.position() >= self.layer_info_end {
+                    self.layer_count = self.records_read();
+                    break;
+                }
+
+                let layer_record = read_layer_record(&mut self.cursor, self.version)?;
+
+                if let Some(GroupDivider::BoundingSection) = layer_record.divider_type {
+                    self.group_count += 1;
+                }
+
+                self.records.push_back(layer_record);
+                budget -= 1;
+            }
 
-            layer_records.push(layer_record);
+            return Ok(());
         }
 
-        let mut result = vec![];
-        for layer_record in layer_records {
-            let channels = read_layer_channels(
-                cursor,
-                &layer_record.channel_data_lengths,
-                layer_record.height() as usize,
+        while budget > 0 {
+            let layer_record = match self.records.pop_front() {
+                Some(layer_record) => layer_record,
+                None => break,
+            };
+            let (channels, scanline_byte_counts, zip_compressed_channels) = read_layer_channels(
+                &mut self.cursor,
+                self.version,
+                self.depth,
+                &layer_record,
+                self.shared.as_ref(),
             )?;
 
-            result.push((layer_record, channels));
+            self.records_with_channels.push((
+                layer_record,
+                channels,
+                scanline_byte_counts,
+                zip_compressed_channels,
+            ));
+            budget -= 1;
+        }
+
+        Ok(())
+    }
+
+    /// Read whatever is left of the layers, followed by the global layer mask info and the
+    /// global tagged blocks.
+    pub(crate) fn finish(mut self) -> Result<LayerAndMaskInformationSection, PsdLayerError> {
+        if self.is_empty {
+            return Ok(LayerAndMaskInformationSection {
+                layers: Layers::new(),
+                groups: Groups::with_capacity(0),
+                global_tagged_blocks: vec![],
+                linked_files: vec![],
+                patterns: vec![],
+                global_layer_mask_info: vec![],
+                merged_transparency: false,
+                layer_count: LayerCount::default(),
+                layer_info_block: None,
+            });
+        }
+
+        while !self.is_done() {
+            self.read(usize::MAX)?;
+        }
+
+        let (global_layer_mask_info, mut global_tagged_blocks) =
+            LayerAndMaskInformationSection::read_global_info(
+                &mut PsdCursor::new(self.bytes),
+                self.version,
+                self.layer_info_section_end,
+            );
+        // The layers are written back into the block, so it isn't kept with the other blocks
+        if let Some((idx, _)) = self.layer_info_block {
+            global_tagged_blocks.remove(idx);
         }
 
         // Photoshop stores layers in reverse order
-        result.reverse();
-        Ok((groups_count, result))
+        let mut layer_records = self.records_with_channels;
+        layer_records.reverse();
+
+        let mut section = LayerAndMaskInformationSection::decode_layers(
+            layer_records,
+            self.group_count,
+            self.psd_size,
+            self.depth,
+        )?;
+        if self.shared.is_some() {
+            for layer in section.layers.iter_mut() {
+                layer.cache_rgba();
+            }
+        }
+        section.linked_files = LinkedFile::read_all(&global_tagged_blocks);
+        section.patterns = Pattern::read_all(&global_tagged_blocks);
+        section.global_tagged_blocks = global_tagged_blocks;
+        section.global_layer_mask_info = global_layer_mask_info;
+        section.merged_transparency = self.merged_transparency;
+        section.layer_count = LayerCount {
+            declared: self.declared_layer_count,
+            records_parsed: self.layer_count,
+        };
+        section.layer_info_block = self.layer_info_block;
+
+        Ok(section)
     }
+}
 
-    fn read_layer(
-        layer_record: &LayerRecord,
-        parent_id: u32,
-        psd_size: (u32, u32),
-        channels: LayerChannels,
-    ) -> Result<PsdLayer, PsdLayerError> {
-        Ok(PsdLayer::new(
-            &layer_record,
-            psd_size.0,
-            psd_size.1,
-            if parent_id > 0 { Some(parent_id) } else { None },
-            channels,
-        ))
+/// Find the `Lr16`, `Lr32` or `Layr` global tagged block, returning where the block is among the global
+/// tagged blocks along with its header.
+fn find_layer_info_block(
+    bytes: &[u8],
+    version: PsdVersion,
+    layer_info_section_end: u64,
+) -> Option<(usize, TaggedBlockHeader)> {
+    let section_end = bytes.len() as u64;
+    if layer_info_section_end > section_end.saturating_sub(4) {
+        return None;
     }
+
+    let mut cursor = PsdCursor::new(bytes);
+    cursor.seek(layer_info_section_end);
+    let global_layer_mask_info_len = cursor.read_u32() as u64;
+    cursor.read_checked(global_layer_mask_info_len)?;
+
+    let mut idx = 0;
+    while let Some(header) = TaggedBlockHeader::read(&mut cursor, version, section_end) {
+        if KEYS_LAYER_INFO_BLOCK.contains(&&header.key) {
+            return Some((idx, header));
+        }
+
+        cursor.seek(header.end(TaggedBlockContext::Document).min(section_end));
+        idx += 1;
+    }
+
+    None
 }
 
 /// Reads layer channels
+///
+/// ZIP compressed channels are decompressed and mapped down to 8 bits per sample. The other
+/// channels point into `shared` when their bytes are a part of it, and are copied otherwise.
 fn read_layer_channels(
     cursor: &mut PsdCursor,
-    channel_data_lengths: &Vec<(PsdChannelKind, u32)>,
-    scanlines: usize,
-) -> Result<LayerChannels, PsdLayerError> {
+    version: PsdVersion,
+    depth: PsdDepth,
+    layer_record: &LayerRecord,
+    shared: Option<&SharedBytes>,
+) -> Result<(LayerChannels, ScanlineByteCounts, ZipCompressedChannels), PsdLayerError> {
+    let channel_data_lengths = &layer_record.channel_data_lengths;
+    let scanlines = layer_record.height() as usize;
+
     let capacity = channel_data_lengths.len();
     let mut channels = HashMap::with_capacity(capacity);
+    let mut scanline_byte_counts = HashMap::new();
+    let mut zip_compressed_channels = HashMap::new();
 
     for (channel_kind, channel_length) in channel_data_lengths.iter() {
         let compression = cursor.read_u16();
@@ -274,26 +834,68 @@ fn read_layer_channels(
             PsdChannelCompression::RawData
         };
 
-        let channel_data = cursor.read(*channel_length);
+        // Report the length from the layer record, which includes the compression
+        let invalid_length = PsdLayerError::InvalidChannelLength {
+            channel: *channel_kind,
+            length: *channel_length + 2,
+        };
+
+        let channel_data = cursor
+            .read_checked(*channel_length)
+            .ok_or(invalid_length.clone())?;
         let channel_bytes = match compression {
-            PsdChannelCompression::RawData => ChannelBytes::RawData(channel_data.into()),
+            PsdChannelCompression::RawData => {
+                ChannelBytes::RawData(SharedBytes::share(shared, channel_data))
+            }
             PsdChannelCompression::RleCompressed => {
-                // We're skipping over the bytes that describe the length of each scanline since
-                // we don't currently use them. We might re-think this in the future when we
-                // implement serialization of a Psd back into bytes.. But not a concern at the
-                // moment.
                 // Compressed bytes per scanline are encoded at the beginning as 2 bytes
-                // per scanline
-                let channel_data = &channel_data[2 * scanlines..];
-                ChannelBytes::RleCompressed(channel_data.into())
+                // (4 bytes for PSB) per scanline. We use them to decode each row on its own and
+                // to write the channel back out.
+                let byte_count_len = if version.is_psb() { 4 } else { 2 };
+                let byte_counts_len = byte_count_len * scanlines;
+                if channel_data.len() < byte_counts_len {
+                    return Err(invalid_length);
+                }
+                let (byte_counts, channel_data) = channel_data.split_at(byte_counts_len);
+
+                let mut byte_counts_cursor = PsdCursor::new(byte_counts);
+                let byte_counts = (0..scanlines)
+                    .map(|_| byte_counts_cursor.read_rle_byte_count(version))
+                    .collect();
+                scanline_byte_counts.insert(*channel_kind, byte_counts);
+
+                ChannelBytes::RleCompressed(SharedBytes::share(shared, channel_data))
+            }
+            PsdChannelCompression::ZipWithoutPrediction
+            | PsdChannelCompression::ZipWithPrediction => {
+                // Masks have their own rectangle
+                let mask = LayerMask::read(&layer_record.record_data.mask_data);
+                let row_width = match (channel_kind, &mask) {
+                    (PsdChannelKind::UserSuppliedLayerMask, Some(mask)) => mask.rect().width(),
+                    (PsdChannelKind::RealUserSuppliedLayerMask, Some(mask)) => mask
+                        .real_user_mask()
+                        .map(|real| real.rect().width())
+                        .unwrap_or(0),
+                    _ => layer_record.width().max(0) as u32,
+                };
+
+                let samples = zip_decompress(channel_data, compression, row_width as usize, depth)
+                    .ok_or(PsdLayerError::InvalidZipData {
+                        layer: layer_record.name.clone(),
+                        channel: *channel_kind,
+                    })?;
+                zip_compressed_channels.insert(*channel_kind, (compression, channel_data.into()));
+
+                ChannelBytes::RawData(
+                    samples_to_eight_bits(&samples, depth, channel_kind.is_color()).into(),
+                )
             }
-            _ => unimplemented!("Zip compression currently unsupported"),
         };
 
         channels.insert(*channel_kind, channel_bytes);
     }
 
-    Ok(channels)
+    Ok((channels, scanline_byte_counts, zip_compressed_channels))
 }
 
 /// Read bytes, starting from the cursor, until we've processed all of the data for a layer in
@@ -322,7 +924,10 @@ fn read_layer_channels(
 /// | Variable               | Layer mask data: See See Layer mask / adjustment layer data for structure. Can be 40 bytes, 24 bytes, or 4 bytes if no layer mask.                                                                                                                                                                                                                                                                                                                                                                                                                                                                |
 /// | Variable               | Layer blending ranges: See See Layer blending ranges data.                                                                                                                                                                                                                                                                                                                                                                                                                                                                                                                                        |
 /// | Variable               | Layer name: Pascal string, padded to a multiple of 4 bytes.                                                                                                                                                                                                                                                                                                                                                                                                                                                                                                                                       |
-fn read_layer_record(cursor: &mut PsdCursor) -> Result<LayerRecord, PsdLayerError> {
+fn read_layer_record(
+    cursor: &mut PsdCursor,
+    version: PsdVersion,
+) -> Result<LayerRecord, PsdLayerError> {
     let mut channel_data_lengths = vec![];
 
     // FIXME:
@@ -337,15 +942,14 @@ fn read_layer_record(cursor: &mut PsdCursor) -> Result<LayerRecord, PsdLayerErro
 
     let left = cursor.read_i32();
 
-    // Subtract one in order to zero index. If a layer is fully transparent it's bottom will
-    // already be 0 so we don't subtract
-    let bottom = cursor.read_i32();
-    let bottom = if bottom == 0 { 0 } else { bottom - 1 };
+    // Subtract one in order to zero index. A layer without any pixels (such as a fully
+    // transparent layer) has a bottom at or above its top, which leaves its bottom above its top
+    // so that it has a height of zero instead of a height of one.
+    let bottom = cursor.read_i32().saturating_sub(1);
 
-    // Subtract one in order to zero index. If a layer is fully transparent it's right will
-    // already be zero so we don't subtract.
-    let right = cursor.read_i32();
-    let right = if right == 0 { 0 } else { right - 1 };
+    // Subtract one in order to zero index. A layer without any pixels ends up with its right to
+    // the left of its left, see the bottom.
+    let right = cursor.read_i32().saturating_sub(1);
 
     // Get the number of channels in the layer
     let channel_count = cursor.read_u16();
@@ -356,10 +960,16 @@ fn read_layer_record(cursor: &mut PsdCursor) -> Result<LayerRecord, PsdLayerErro
         let channel_id =
             PsdChannelKind::new(channel_id).ok_or(PsdLayerError::InvalidChannel { channel_id })?;
 
-        let channel_length = cursor.read_u32();
+        let channel_length = cursor.read_length(version);
         // The first two bytes encode the compression, the rest of the bytes
         // are the channel data.
-        let channel_data_length = channel_length - 2;
+        let channel_data_length =
+            channel_length
+                .checked_sub(2)
+                .ok_or(PsdLayerError::InvalidChannelLength {
+                    channel: channel_id,
+                    length: channel_length,
+                })?;
 
         channel_data_lengths.push((channel_id, channel_data_length));
     }
@@ -379,34 +989,29 @@ fn read_layer_record(cursor: &mut PsdCursor) -> Result<LayerRecord, PsdLayerErro
     let clipping_base = cursor.read_u8();
     let clipping_base = clipping_base == 0;
 
-    // We do not currently parse all flags, only visible
-    // Flags:
-    //  - bit 0 = transparency protected;
-    //  - bit 1 = visible;
-    //  - bit 2 = obsolete;
-    //  - bit 3 = 1 for Photoshop 5.0 and later, tells if bit 4 has useful information;
-    //  - bit 4 = pixel data irrelevant to appearance of document
-    let visible = cursor.read_u8() & (1 << 1) != 0; // here we get second bit - visible
+    // See `LayerFlags` for the meaning of each bit
+    let flags = LayerFlags::new(cursor.read_u8());
 
     // We do not currently parse the filler, skip it
     cursor.read_1();
 
-    // We do not currently use the length of the extra data field, skip it
-    cursor.read_4();
+    // Length of the extra data field ( = the total length of the next five fields).
+    let extra_data_len = cursor.read_u32();
+    let extra_data_end = cursor.position() + extra_data_len as u64;
 
-    // We do not currently use the layer mask data, skip it
+    // We do not currently use the layer mask data, but we hold on to it so that it can be
+    // written back out
     let layer_mask_data_len = cursor.read_u32();
-    cursor.read(layer_mask_data_len);
+    let mask_data = cursor.read(layer_mask_data_len).to_vec();
 
-    // We do not currently use the layer blending range, skip it
+    // The blending ranges are parsed when they're needed, see `LayerProperties::blending_ranges`
     let layer_blending_range_data_len = cursor.read_u32();
-    cursor.read(layer_blending_range_data_len);
+    let blending_ranges = cursor.read(layer_blending_range_data_len).to_vec();
 
     // Read the layer name
     let name_len = cursor.read_u8();
-    let name = cursor.read(name_len as u32);
-    let name = String::from_utf8_lossy(name);
-    let mut name = name.to_string();
+    let pascal_name = cursor.read(name_len as u32).to_vec();
+    let mut name = String::from_utf8_lossy(&pascal_name).to_string();
 
     // Layer name is padded to the next multiple of 4 bytes.
     // So if the name length is 9, there will be three throwaway bytes
@@ -418,40 +1023,80 @@ fn read_layer_record(cursor: &mut PsdCursor) -> Result<LayerRecord, PsdLayerErro
     cursor.read(padding as u32);
 
     let mut divider_type = None;
+    let mut divider_blend_mode = None;
+    let mut text_layer_info = None;
+    let mut layer_id = None;
+    let mut placed_layer = None;
+    let mut tagged_blocks = vec![];
     // There can be multiple additional layer information sections so we'll loop
     // until we stop seeing them.
-    while cursor.peek_4() == SIGNATURE_EIGHT_BIM || cursor.peek_4() == SIGNATURE_EIGHT_B64 {
-        let _signature = cursor.read_4();
-        let mut key = [0; 4];
-        key.copy_from_slice(cursor.read_4());
-        let additional_layer_info_len = cursor.read_u32();
-
-        match &key {
+    while let Some(block) = TaggedBlockHeader::read(cursor, version, extra_data_end) {
+        match &block.key {
             KEY_UNICODE_LAYER_NAME => {
-                let pos = cursor.position();
                 name = cursor.read_unicode_string_padding(1);
-                cursor.seek(pos + additional_layer_info_len as u64);
             }
             KEY_SECTION_DIVIDER_SETTING => {
                 divider_type = GroupDivider::match_divider(cursor.read_i32());
 
                 // data present only if length >= 12
-                if additional_layer_info_len >= 12 {
+                if block.data_len >= 12 {
                     let _signature = cursor.read_4();
-                    let _key = cursor.read_4();
+
+                    // The blend mode of a group, which is where Photoshop stores pass through
+                    let mut key = [0; 4];
+                    key.copy_from_slice(cursor.read_4());
+                    divider_blend_mode = BlendMode::match_mode(key);
                 }
 
                 // data present only if length >= 16
-                if additional_layer_info_len >= 16 {
+                if block.data_len >= 16 {
                     cursor.read_4();
                 }
             }
+            // Text layers are still drawn from their pixels, so a descriptor that we can't read
+            // (such as one with an OSType that we don't support) only loses the text information
+            KEY_TYPE_TOOL_OBJECT_SETTING => {
+                text_layer_info = TextLayerInfo::read(cursor).ok();
+            }
 
-            // TODO: Skipping other keys until we implement parsing for them
-            _ => {
-                cursor.read(additional_layer_info_len);
+            KEY_LAYER_ID => {
+                layer_id = Some(cursor.read_u32());
+            }
+            // A descriptor that we can't read (such as a warp mesh with an OSType that we don't
+            // support) leaves the layer without its placed layer rather than failing the parse
+            KEY_PLACED_LAYER_DATA | KEY_LINKED_PLACED_LAYER_DATA => {
+                placed_layer = PlacedLayer::read_placed_layer_data(cursor).ok();
             }
+            // Newer PSDs have both blocks, in which case we prefer the placed layer data
+            KEY_PLACED_LAYER if placed_layer.is_none() => {
+                placed_layer = Some(PlacedLayer::read_placed_layer(cursor));
+            }
+
+            // TODO: Skipping other keys until we implement parsing for them
+            _ => {}
         }
+
+        cursor.seek(block.data_start);
+        let data = match cursor.read_checked(block.data_len) {
+            Some(data) => data.to_vec(),
+            None => break,
+        };
+        tagged_blocks.push(TaggedBlock {
+            key: block.key,
+            data,
+        });
+
+        cursor.seek(block.end(TaggedBlockContext::LayerRecord));
+    }
+
+    // A block that runs past the end of the extra data can't be read, so we skip the rest of the
+    // extra data instead of reading the next record from the middle of this one.
+    //
+    // NOTE: Otherwise we intentionally don't seek to `extra_data_end`. Some writers report an
+    // extra data length that is larger than the data that they actually wrote (see `luni.psd`),
+    // so we continue from the end of the last tagged block instead.
+    if cursor.position() < extra_data_end && TaggedBlockHeader::has_signature(cursor) {
+        cursor.seek(extra_data_end.min(cursor.get_ref().len() as u64));
     }
 
     Ok(LayerRecord {
@@ -461,10 +1106,174 @@ fn read_layer_record(cursor: &mut PsdCursor) -> Result<LayerRecord, PsdLayerErro
         left,
         bottom,
         right,
-        visible,
+        flags,
         opacity,
         clipping_base,
         blend_mode,
         divider_type,
+        divider_blend_mode,
+        text_layer_info,
+        layer_id,
+        placed_layer,
+        record_data: LayerRecordData {
+            mask_data,
+            blending_ranges,
+            pascal_name,
+            tagged_blocks,
+        },
     })
 }
+
+impl<'a> RecordToWrite<'a> {
+    fn properties(&self) -> &'a LayerProperties {
+        match self {
+            RecordToWrite::Layer(layer) => &layer.layer_properties,
+            RecordToWrite::GroupFolder(group) => &group.folder_properties,
+            RecordToWrite::GroupBounding(group) => &group.layer_properties,
+        }
+    }
+
+    fn channels(&self) -> Vec<ChannelToWrite<'a>> {
+        match self {
+            RecordToWrite::Layer(layer) => CHANNEL_WRITE_ORDER
+                .iter()
+                .filter_map(|kind| {
+                    let channel = layer.channels.get(kind)?;
+
+                    if let Some((compression, zip_data)) = layer.zip_compressed_channels.get(kind) {
+                        return Some(ChannelToWrite {
+                            kind: *kind,
+                            compression: *compression,
+                            scanline_byte_counts: &[],
+                            data: zip_data,
+                        });
+                    }
+
+                    let (compression, scanline_byte_counts) = match channel {
+                        ChannelBytes::RawData(_) => (PsdChannelCompression::RawData, &[][..]),
+                        ChannelBytes::RleCompressed(_) => (
+                            PsdChannelCompression::RleCompressed,
+                            layer
+                                .scanline_byte_counts
+                                .get(kind)
+                                .map(|counts| counts.as_slice())
+                                .unwrap_or(&[]),
+                        ),
+                    };
+
+                    Some(ChannelToWrite {
+                        kind: *kind,
+                        compression,
+                        scanline_byte_counts,
+                        data: channel.bytes(),
+                    })
+                })
+                .collect(),
+            // Groups don't have any pixels, but Photoshop still gives them empty color and
+            // transparency channels
+            RecordToWrite::GroupFolder(_) | RecordToWrite::GroupBounding(_) => CHANNEL_WRITE_ORDER
+                [..4]
+                .iter()
+                .map(|kind| ChannelToWrite {
+                    kind: *kind,
+                    compression: PsdChannelCompression::RawData,
+                    scanline_byte_counts: &[],
+                    data: &[],
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Write the layer count, the layer records (from the bottom of the PSD to the top) and their
+/// channels, without a length marker. Nothing is written when there aren't any records.
+pub(crate) fn write_layer_records(
+    buffer: &mut PsdBuffer,
+    version: PsdVersion,
+    merged_transparency: bool,
+    records: &[(&LayerProperties, Vec<ChannelToWrite>)],
+) {
+    if records.is_empty() {
+        return;
+    }
+    let layer_info_start = buffer.len();
+
+    let layer_count = records.len() as i16;
+    buffer.write_i16(if merged_transparency {
+        -layer_count
+    } else {
+        layer_count
+    });
+
+    for (properties, channels) in records.iter() {
+        write_layer_record(buffer, version, properties, channels);
+    }
+
+    for (_, channels) in records.iter() {
+        for channel in channels.iter() {
+            buffer.write_u16(channel.compression as u16);
+            for byte_count in channel.scanline_byte_counts.iter() {
+                buffer.write_rle_byte_count(version, *byte_count);
+            }
+            buffer.write(channel.data);
+        }
+    }
+
+    // The docs say that the layer info is padded to a multiple of 2 bytes, but Photoshop pads it
+    // to a multiple of 4 bytes
+    buffer.write_padding(buffer.len() - layer_info_start, 4);
+}
+
+/// Write a layer record.
+///
+/// The inverse of `read_layer_record`.
+fn write_layer_record(
+    buffer: &mut PsdBuffer,
+    version: PsdVersion,
+    properties: &LayerProperties,
+    channels: &[ChannelToWrite],
+) {
+    // The bottom and right are written exclusive, so empty layers are written with their bottom
+    // and right at or above their top and left, the same way that we read them.
+    buffer.write_i32(properties.layer_top);
+    buffer.write_i32(properties.layer_left);
+    buffer.write_i32(properties.layer_bottom.saturating_add(1));
+    buffer.write_i32(properties.layer_right.saturating_add(1));
+
+    buffer.write_u16(channels.len() as u16);
+    for channel in channels.iter() {
+        let byte_count_len = if version.is_psb() { 4 } else { 2 };
+        let length = 2 + channel.scanline_byte_counts.len() * byte_count_len + channel.data.len();
+
+        buffer.write_i16(channel.kind as i16);
+        buffer.write_length(version, length as u64);
+    }
+
+    buffer.write(b"8BIM");
+    buffer.write(&properties.blend_mode.key());
+    buffer.write_u8(properties.opacity);
+    buffer.write_u8(if properties.clipping_mask { 0 } else { 1 });
+    buffer.write_u8(properties.flags.bits());
+    buffer.write_u8(0);
+
+    let record_data = &properties.record_data;
+    let extra_data_length = buffer.start_length();
+
+    buffer.write_u32(record_data.mask_data.len() as u32);
+    buffer.write(&record_data.mask_data);
+
+    buffer.write_u32(record_data.blending_ranges.len() as u32);
+    buffer.write(&record_data.blending_ranges);
+
+    // The layer name is padded to a multiple of 4 bytes
+    let pascal_name = &record_data.pascal_name[..record_data.pascal_name.len().min(255)];
+    buffer.write_u8(pascal_name.len() as u8);
+    buffer.write(pascal_name);
+    buffer.write_padding(1 + pascal_name.len(), 4);
+
+    for block in record_data.tagged_blocks.iter() {
+        block.write(buffer, version, TaggedBlockContext::LayerRecord);
+    }
+
+    buffer.end_length(extra_data_length);
+}
@@ -0,0 +1,341 @@
+use std::convert::TryFrom;
+
+use crate::psd_channel::{rle_decompress, zip_decompress, PsdChannelCompression};
+use crate::sections::layer_and_mask_information_section::tagged_block::TaggedBlock;
+use crate::sections::PsdCursor;
+use crate::{ColorMode, PsdDepth};
+
+/// Keys of the global tagged blocks that hold the document's patterns, "Patt", "Pat2" and "Pat3"
+const KEYS_PATTERNS: [&[u8; 4]; 3] = [b"Patt", b"Pat2", b"Pat3"];
+
+/// The length of the version, image mode and size that every pattern starts with
+const PATTERN_HEADER_LEN: u64 = 12;
+
+/// The length of the version, length, rectangle and channel count of a virtual memory array list
+const ARRAY_LIST_HEADER_LEN: u64 = 28;
+
+/// The length of the depth, rectangle, depth again and compression that come before the data of
+/// a virtual memory array
+const ARRAY_HEADER_LEN: u64 = 23;
+
+/// A pattern that pattern fill layers repeat, read from the document's `Patt`, `Pat2` or `Pat3`
+/// tagged block.
+///
+/// # [Adobe Docs](https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/)
+///
+/// | Length   | Description                                                           |
+/// |----------|-----------------------------------------------------------------------|
+/// | 4        | Length of this pattern                                                |
+/// | 4        | Version ( =1)                                                         |
+/// | 4        | The image mode of the file. Supported values are: Bitmap = 0; Grayscale = 1; Indexed = 2; RGB = 3; CMYK = 4; Multichannel = 7; Duotone = 8; Lab = 9. |
+/// | 4        | Point: vertical, 2 bytes and horizontal, 2 bytes                      |
+/// | Variable | Name: Unicode string                                                  |
+/// | Variable | Unique ID for this pattern: Pascal string                             |
+/// | Variable | Index color table (256 * 3 RGB values): only present when image mode is indexed color |
+/// | Variable | Pattern data as Virtual Memory Array List                             |
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pattern {
+    pub(crate) name: String,
+    pub(crate) id: String,
+    pub(crate) color_mode: ColorMode,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    /// The RGB colors of an indexed color pattern
+    pub(crate) color_table: Option<Vec<u8>>,
+    /// The channels that were written, in order. The channel after the color channels, if any,
+    /// is the pattern's transparency.
+    pub(crate) channels: Vec<PatternChannel>,
+}
+
+/// One channel of a pattern's virtual memory array list.
+///
+/// | Length   | Description                                                           |
+/// |----------|-----------------------------------------------------------------------|
+/// | 4        | Boolean indicating whether array is written, skip following data if 0 |
+/// | 4        | Length, skip following data if 0                                      |
+/// | 4        | Pixel depth: 1, 8, 16 or 32                                           |
+/// | 16       | Rectangle: top, left, bottom, right                                   |
+/// | 2        | Pixel depth: 1, 8, 16 or 32                                           |
+/// | 1        | Compression mode of data to follow. 1 is zip.                         |
+/// | Variable | Actual data based on parameters and compression                       |
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct PatternChannel {
+    depth: PsdDepth,
+    compression: PsdChannelCompression,
+    data: Vec<u8>,
+}
+
+impl Pattern {
+    /// Read the patterns in every pattern block of the document. Patterns that we can't read are
+    /// skipped.
+    pub(crate) fn read_all(global_tagged_blocks: &[TaggedBlock]) -> Vec<Pattern> {
+        let mut patterns = vec![];
+
+        for block in global_tagged_blocks.iter() {
+            if !KEYS_PATTERNS.contains(&&block.key) {
+                continue;
+            }
+
+            let mut cursor = PsdCursor::new(&block.data);
+            while cursor.remaining() >= 4 {
+                let len = cursor.read_u32() as u64;
+                let data = match cursor.read_checked(len) {
+                    Some(data) => data,
+                    None => break,
+                };
+                if let Some(pattern) = Pattern::read(data) {
+                    patterns.push(pattern);
+                }
+
+                let padding = (4 - len % 4) % 4;
+                if cursor.read_checked(padding).is_none() {
+                    break;
+                }
+            }
+        }
+
+        patterns
+    }
+
+    fn read(bytes: &[u8]) -> Option<Pattern> {
+        let mut cursor = PsdCursor::new(bytes);
+        if cursor.remaining() < PATTERN_HEADER_LEN {
+            return None;
+        }
+
+        let _version = cursor.read_u32();
+        let color_mode = ColorMode::new(u8::try_from(cursor.read_u32()).ok()?)?;
+        let _height = cursor.read_u16();
+        let _width = cursor.read_u16();
+
+        let name_len = cursor.read_checked(4)?;
+        let name_len = u32::from_be_bytes([name_len[0], name_len[1], name_len[2], name_len[3]]);
+        let name: Vec<u16> = cursor
+            .read_checked(name_len as u64 * 2)?
+            .chunks_exact(2)
+            .map(|unit| u16::from_be_bytes([unit[0], unit[1]]))
+            .collect();
+        let name = String::from_utf16_lossy(&name)
+            .trim_end_matches('\0')
+            .to_string();
+
+        let id_len = cursor.read_checked(1)?[0];
+        let id = String::from_utf8_lossy(cursor.read_checked(id_len as u64)?).to_string();
+
+        let color_table = match color_mode {
+            ColorMode::Indexed => Some(cursor.read_checked(256 * 3)?.to_vec()),
+            _ => None,
+        };
+
+        // The virtual memory array list that holds the pixels
+        if cursor.remaining() < ARRAY_LIST_HEADER_LEN {
+            return None;
+        }
+        let _version = cursor.read_u32();
+        let _len = cursor.read_u32();
+        let top = cursor.read_u32();
+        let left = cursor.read_u32();
+        let bottom = cursor.read_u32();
+        let right = cursor.read_u32();
+        let channel_count = cursor.read_u32();
+
+        // The color channels are followed by a user mask and a sheet mask
+        let mut channels = vec![];
+        for _ in 0..channel_count as u64 + 2 {
+            if cursor.remaining() < 4 || cursor.read_u32() == 0 {
+                continue;
+            }
+            if cursor.remaining() < 4 {
+                break;
+            }
+            let len = cursor.read_u32() as u64;
+            if len < ARRAY_HEADER_LEN {
+                cursor.read_checked(len)?;
+                continue;
+            }
+
+            let header = cursor.read_checked(ARRAY_HEADER_LEN)?;
+            let depth = match header[21] {
+                8 => PsdDepth::Eight,
+                16 => PsdDepth::Sixteen,
+                _ => PsdDepth::One,
+            };
+            // Adobe documents 1 as ZIP, but Photoshop uses the same compression numbers as it
+            // does for the channels of layers
+            let compression = match header[22] {
+                1 => PsdChannelCompression::RleCompressed,
+                2 => PsdChannelCompression::ZipWithoutPrediction,
+                3 => PsdChannelCompression::ZipWithPrediction,
+                _ => PsdChannelCompression::RawData,
+            };
+            let data = cursor.read_checked(len - ARRAY_HEADER_LEN)?.to_vec();
+
+            channels.push(PatternChannel {
+                depth,
+                compression,
+                data,
+            });
+        }
+
+        Some(Pattern {
+            name,
+            id,
+            color_mode,
+            width: right.saturating_sub(left),
+            height: bottom.saturating_sub(top),
+            color_table,
+            channels,
+        })
+    }
+
+    /// The name of the pattern
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The ID that pattern fills refer to the pattern by, see [`PatternFill::id`]
+    ///
+    /// [`PatternFill::id`]: crate::PatternFill::id
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// The color mode of the pattern's pixels
+    pub fn color_mode(&self) -> ColorMode {
+        self.color_mode
+    }
+
+    /// The width of the pattern in pixels
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// The height of the pattern in pixels
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The RGBA pixels of the pattern
+    /// [ R,G,B,A, R,G,B,A, R,G,B,A, ...]
+    ///
+    /// RGB, grayscale and indexed color patterns with 8 or 16 bit channels are supported. Returns
+    /// `None` for other patterns and for patterns whose channels can't be decoded.
+    pub fn rgba(&self) -> Option<Vec<u8>> {
+        let color_channel_count = match self.color_mode {
+            ColorMode::Rgb => 3,
+            ColorMode::Grayscale | ColorMode::Indexed => 1,
+            _ => return None,
+        };
+
+        let pixel_count = self.width as usize * self.height as usize;
+        let channels = self
+            .channels
+            .iter()
+            .take(color_channel_count + 1)
+            .map(|channel| channel.samples(self.width as usize, self.height as usize))
+            .collect::<Option<Vec<Vec<u8>>>>()?;
+        if channels.len() < color_channel_count
+            || channels.iter().any(|channel| channel.len() < pixel_count)
+        {
+            return None;
+        }
+
+        let mut rgba = Vec::with_capacity(pixel_count * 4);
+        for idx in 0..pixel_count {
+            let color = match (&self.color_table, color_channel_count) {
+                (Some(color_table), _) => {
+                    let color = channels[0][idx] as usize * 3;
+                    [
+                        color_table[color],
+                        color_table[color + 1],
+                        color_table[color + 2],
+                    ]
+                }
+                (None, 1) => [channels[0][idx]; 3],
+                (None, _) => [channels[0][idx], channels[1][idx], channels[2][idx]],
+            };
+            let alpha = channels
+                .get(color_channel_count)
+                .map(|alpha| alpha[idx])
+                .unwrap_or(255);
+
+            rgba.extend_from_slice(&[color[0], color[1], color[2], alpha]);
+        }
+
+        Some(rgba)
+    }
+}
+
+impl PatternChannel {
+    /// Decompress the channel into one byte per pixel. 16 bit samples are mapped down to 8 bits.
+    fn samples(&self, width: usize, height: usize) -> Option<Vec<u8>> {
+        let samples = match self.compression {
+            PsdChannelCompression::RawData => self.data.clone(),
+            // The compressed rows start with the length of each row
+            PsdChannelCompression::RleCompressed => rle_decompress(self.data.get(height * 2..)?),
+            compression => zip_decompress(&self.data, compression, width, self.depth)?,
+        };
+
+        match self.depth {
+            PsdDepth::Eight => Some(samples),
+            PsdDepth::Sixteen => Some(samples.chunks_exact(2).map(|sample| sample[0]).collect()),
+            PsdDepth::One | PsdDepth::ThirtyTwo => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 2x1 RGB pattern named "P" with the ID "id", whose red channel is raw, green channel is RLE
+    /// compressed and blue channel is missing.
+    fn pattern_bytes(blue_is_written: bool) -> Vec<u8> {
+        let mut bytes = vec![0, 0, 0, 1, 0, 0, 0, 3, 0, 1, 0, 2];
+        bytes.extend_from_slice(&[0, 0, 0, 2, 0, b'P', 0, 0]);
+        bytes.extend_from_slice(&[2, b'i', b'd']);
+
+        let channel = |compression: u8, data: &[u8]| {
+            let mut channel = vec![0, 0, 0, 1];
+            channel.extend_from_slice(&(23 + data.len() as u32).to_be_bytes());
+            channel.extend_from_slice(&[0, 0, 0, 8]);
+            channel.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 2]);
+            channel.extend_from_slice(&[0, 8, compression]);
+            channel.extend_from_slice(data);
+            channel
+        };
+        let channels = [
+            channel(0, &[10, 20]),
+            // One row of 2 bytes, which repeats 30 twice
+            channel(1, &[0, 2, 255, 30]),
+        ]
+        .concat();
+
+        bytes.extend_from_slice(&[0, 0, 0, 3]);
+        bytes.extend_from_slice(&(channels.len() as u32 + 24).to_be_bytes());
+        bytes.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 3]);
+        bytes.extend_from_slice(&channels);
+        if blue_is_written {
+            bytes.extend_from_slice(&channel(0, &[40, 50]));
+        }
+
+        bytes
+    }
+
+    /// Verify that channels are decompressed, and that patterns missing a color channel aren't
+    /// rendered.
+    #[test]
+    fn read_pattern() {
+        let pattern = Pattern::read(&pattern_bytes(true)).unwrap();
+        assert_eq!(pattern.name(), "P");
+        assert_eq!(pattern.id(), "id");
+        assert_eq!((pattern.width(), pattern.height()), (2, 1));
+        assert_eq!(
+            pattern.rgba().unwrap(),
+            vec![10, 30, 40, 255, 20, 30, 50, 255]
+        );
+
+        let pattern = Pattern::read(&pattern_bytes(false)).unwrap();
+        assert_eq!(pattern.rgba(), None);
+    }
+}
@@ -0,0 +1,420 @@
+use crate::sections::image_resources_section::{
+    DescriptorField, DescriptorStructure, ImageResourcesDescriptorError,
+};
+use crate::sections::layer_and_mask_information_section::layer::PsdLayer;
+use crate::sections::layer_and_mask_information_section::tagged_block::TaggedBlock;
+use crate::sections::PsdCursor;
+
+/// The keys of the document level blocks that hold the files of smart objects
+const KEYS_LINKED_LAYER: [&[u8; 4]; 3] = [b"lnk2", b"lnkD", b"lnk3"];
+
+/// The smart object that a layer places, read from its placed layer data ("SoLd" or "SoLE") or
+/// its older placed layer ("PlLd") additional layer information block.
+///
+/// # [Adobe Docs](https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/)
+///
+/// Placed layer data:
+///
+/// | Length   | Description                                                 |
+/// |----------|-------------------------------------------------------------|
+/// | 4        | Identifier ( = 'soLD' )                                     |
+/// | 4        | Version ( = 4 )                                             |
+/// | 4        | Descriptor version ( = 16)                                  |
+/// | Variable | Descriptor of placed layer information                      |
+///
+/// Placed layer:
+///
+/// | Length   | Description                                                 |
+/// |----------|-------------------------------------------------------------|
+/// | 4        | Type ( = 'plcL' )                                           |
+/// | 4        | Version ( = 3 )                                             |
+/// | Variable | Unique ID as a pascal string                                |
+/// | 4        | Page number                                                 |
+/// | 4        | Total pages                                                 |
+/// | 4        | Anti alias policy                                           |
+/// | 4        | Placed layer type: 0 = unknown, 1 = vector, 2 = raster, 3 = image stack |
+/// | 4 * 8    | Transformation: x,y of upper left, upper right, lower right, lower left |
+/// | 4        | Warp version ( = 0 )                                        |
+/// | 4        | Warp descriptor version ( = 16 )                            |
+/// | Variable | Descriptor for warping information                          |
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlacedLayer {
+    pub(crate) unique_id: String,
+    pub(crate) transform: [f64; 8],
+    pub(crate) smart_filters: Vec<SmartFilter>,
+    pub(crate) descriptor: Option<DescriptorStructure>,
+}
+
+impl PlacedLayer {
+    /// Read a placed layer data block ("SoLd" or "SoLE") starting at the cursor's current
+    /// position.
+    pub(crate) fn read_placed_layer_data(
+        cursor: &mut PsdCursor,
+    ) -> Result<PlacedLayer, ImageResourcesDescriptorError> {
+        let _identifier = cursor.read_4();
+        let _version = cursor.read_u32();
+        let _descriptor_version = cursor.read_u32();
+        let descriptor = DescriptorStructure::read_descriptor_structure(cursor)?;
+
+        let unique_id = match descriptor.fields.get("Idnt") {
+            Some(DescriptorField::String(unique_id)) => {
+                unique_id.trim_end_matches('\0').to_string()
+            }
+            _ => String::new(),
+        };
+
+        let mut transform = [0.; 8];
+        if let Some(DescriptorField::List(values)) = descriptor.fields.get("Trnf") {
+            for (value, field) in transform.iter_mut().zip(values.iter()) {
+                if let DescriptorField::Double(field) = field {
+                    *value = *field;
+                }
+            }
+        }
+
+        let smart_filters = match descriptor.fields.get("filterFX") {
+            Some(DescriptorField::Descriptor(filter_fx)) => SmartFilter::read_all(filter_fx),
+            _ => vec![],
+        };
+
+        Ok(PlacedLayer {
+            unique_id,
+            transform,
+            smart_filters,
+            descriptor: Some(descriptor),
+        })
+    }
+
+    /// Read a placed layer block ("PlLd") starting at the cursor's current position. We don't
+    /// read the warp that follows the transformation.
+    pub(crate) fn read_placed_layer(cursor: &mut PsdCursor) -> PlacedLayer {
+        let _kind = cursor.read_4();
+        let _version = cursor.read_u32();
+
+        let len = cursor.read_u8();
+        let unique_id = String::from_utf8_lossy(cursor.read(len as u32)).to_string();
+
+        let _page_number = cursor.read_u32();
+        let _total_pages = cursor.read_u32();
+        let _anti_alias_policy = cursor.read_u32();
+        let _placed_layer_type = cursor.read_u32();
+
+        let mut transform = [0.; 8];
+        for value in transform.iter_mut() {
+            *value = cursor.read_f64();
+        }
+
+        PlacedLayer {
+            unique_id,
+            transform,
+            smart_filters: vec![],
+            descriptor: None,
+        }
+    }
+
+    /// The ID that the smart object's file is stored under in the document's linked files
+    pub fn unique_id(&self) -> &str {
+        &self.unique_id
+    }
+
+    /// Where the corners of the smart object's content are placed on the canvas: the x and y of
+    /// the upper left, upper right, lower right and lower left corners respectively.
+    pub fn transform(&self) -> [f64; 8] {
+        self.transform
+    }
+
+    /// The smart filters that are applied to the smart object, from the first one applied to the
+    /// last. Layers that only have the older "PlLd" block don't report any.
+    pub fn smart_filters(&self) -> &[SmartFilter] {
+        &self.smart_filters
+    }
+
+    /// The placed layer information descriptor, which holds the smart object's size, warp and
+    /// crop among other things. `None` for layers that only have the older "PlLd" block.
+    pub fn descriptor(&self) -> Option<&DescriptorStructure> {
+        self.descriptor.as_ref()
+    }
+}
+
+/// A filter that is applied to a smart object, such as a Gaussian blur, read from the "filterFX"
+/// field of the placed layer information descriptor.
+///
+/// The layer's pixels already have its smart filters applied, but we can't apply the filters
+/// again after changing the smart object.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SmartFilter {
+    pub(crate) name: String,
+    pub(crate) enabled: bool,
+    pub(crate) descriptor: DescriptorStructure,
+}
+
+impl SmartFilter {
+    /// Read every filter of a "filterFX" descriptor
+    fn read_all(filter_fx: &DescriptorStructure) -> Vec<SmartFilter> {
+        let all_enabled = !matches!(
+            filter_fx.fields.get("enab"),
+            Some(DescriptorField::Boolean(false))
+        );
+
+        let filters = match filter_fx.fields.get("filterFXList") {
+            Some(DescriptorField::List(filters)) => filters,
+            _ => return vec![],
+        };
+
+        filters
+            .iter()
+            .filter_map(|filter| match filter {
+                DescriptorField::Descriptor(filter) => Some(filter),
+                _ => None,
+            })
+            .map(|filter| {
+                let name = match filter.fields.get("Nm  ") {
+                    Some(DescriptorField::String(name)) => name.trim_end_matches('\0').to_string(),
+                    _ => String::new(),
+                };
+                let enabled = !matches!(
+                    filter.fields.get("enab"),
+                    Some(DescriptorField::Boolean(false))
+                );
+
+                SmartFilter {
+                    name,
+                    enabled: all_enabled && enabled,
+                    descriptor: filter.clone(),
+                }
+            })
+            .collect()
+    }
+
+    /// The name of the filter as shown in Photoshop's layers panel, such as `Gaussian Blur...`
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Whether the filter is applied. Turning off all of a layer's smart filters at once turns
+    /// off each of them.
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// The filter's parameters, such as the radius of a Gaussian blur. Its class ID identifies
+    /// the filter, such as `GsnB` for a Gaussian blur. `None` for filters without any options.
+    pub fn parameters(&self) -> Option<&DescriptorStructure> {
+        match self.descriptor.fields.get("Fltr") {
+            Some(DescriptorField::Descriptor(parameters)) => Some(parameters),
+            _ => None,
+        }
+    }
+
+    /// The whole descriptor of the smart filter, which also holds its blending options
+    pub fn descriptor(&self) -> &DescriptorStructure {
+        &self.descriptor
+    }
+}
+
+/// Where the file of a smart object lives
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum LinkedFileKind {
+    /// The file is embedded in the PSD ('liFD')
+    Embedded,
+    /// The PSD links to a file on disk ('liFE')
+    External,
+    /// The PSD links to a file through an alias ('liFA')
+    Alias,
+}
+
+/// The file of a smart object, read from the document's linked layer ("lnk2", "lnkD" or
+/// "lnk3") tagged blocks.
+///
+/// # [Adobe Docs](https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/)
+///
+/// Each linked file is preceded by its length (8 bytes) and padded to a multiple of 4 bytes.
+///
+/// | Length   | Description                                                           |
+/// |----------|-----------------------------------------------------------------------|
+/// | 4        | Type ( = 'liFD' linked file data, 'liFE' external, 'liFA' alias )     |
+/// | 4        | Version ( = 1 to 7 )                                                  |
+/// | Variable | Pascal string. Unique ID.                                             |
+/// | Variable | Unicode string of the original file name                              |
+/// | 4        | File Type                                                             |
+/// | 4        | File Creator                                                          |
+/// | 8        | Length of the data to follow                                          |
+/// | 1        | File open descriptor                                                  |
+/// | Variable | Descriptor of open parameters. Only present when above is true.       |
+/// | Variable | If the type is 'liFE', descriptor of the linked file, its date (version > 3) and its size |
+/// | Variable | Raw bytes of the file, for 'liFD' and for 'liFE' version 3 and above  |
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkedFile {
+    pub(crate) kind: LinkedFileKind,
+    pub(crate) unique_id: String,
+    pub(crate) file_name: String,
+    pub(crate) file_type: [u8; 4],
+    pub(crate) data: Option<Vec<u8>>,
+}
+
+impl LinkedFile {
+    /// Read the linked files in every linked layer block of the document. Files that we can't
+    /// read are skipped.
+    pub(crate) fn read_all(global_tagged_blocks: &[TaggedBlock]) -> Vec<LinkedFile> {
+        let mut files = vec![];
+
+        for block in global_tagged_blocks.iter() {
+            if !KEYS_LINKED_LAYER.contains(&&block.key) {
+                continue;
+            }
+
+            let mut cursor = PsdCursor::new(&block.data);
+            while let Some(len) = cursor.read_checked(8).map(|len| {
+                let mut bytes = [0; 8];
+                bytes.copy_from_slice(len);
+                u64::from_be_bytes(bytes)
+            }) {
+                let data = match cursor.read_checked(len) {
+                    Some(data) => data,
+                    None => break,
+                };
+                if let Some(file) = LinkedFile::read(data) {
+                    files.push(file);
+                }
+
+                let padding = (4 - len % 4) % 4;
+                if cursor.read_checked(padding).is_none() {
+                    break;
+                }
+            }
+        }
+
+        files
+    }
+
+    /// Read one linked file, without its length and padding
+    fn read(bytes: &[u8]) -> Option<LinkedFile> {
+        let mut cursor = PsdCursor::new(bytes);
+
+        let kind = match cursor.read_checked(4)? {
+            b"liFD" => LinkedFileKind::Embedded,
+            b"liFE" => LinkedFileKind::External,
+            b"liFA" => LinkedFileKind::Alias,
+            _ => return None,
+        };
+        let version = cursor.read_u32();
+
+        let len = cursor.read_u8();
+        let unique_id = String::from_utf8_lossy(cursor.read_checked(len as u64)?).to_string();
+        let file_name = cursor
+            .read_unicode_string_padding(1)
+            .trim_end_matches('\0')
+            .to_string();
+
+        let mut file_type = [0; 4];
+        file_type.copy_from_slice(cursor.read_4());
+        let _file_creator = cursor.read_4();
+        let data_len = cursor.read_u64();
+
+        let has_open_descriptor = cursor.read_u8() != 0;
+        if has_open_descriptor {
+            let _descriptor_version = cursor.read_u32();
+            DescriptorStructure::read_descriptor_structure(&mut cursor).ok()?;
+        }
+
+        let data = match kind {
+            LinkedFileKind::Embedded => Some(cursor.read_checked(data_len)?.to_vec()),
+            LinkedFileKind::External => {
+                let _descriptor_version = cursor.read_u32();
+                DescriptorStructure::read_descriptor_structure(&mut cursor).ok()?;
+                if version > 3 {
+                    // Year, month, day, hour, minute and seconds
+                    cursor.read_checked(4 + 4 + 8)?;
+                }
+                let _file_size = cursor.read_u64();
+
+                if version > 2 {
+                    cursor.read_checked(data_len).map(|data| data.to_vec())
+                } else {
+                    None
+                }
+            }
+            LinkedFileKind::Alias => None,
+        };
+
+        Some(LinkedFile {
+            kind,
+            unique_id,
+            file_name,
+            file_type,
+            data,
+        })
+    }
+
+    /// Whether the file is embedded in the PSD or linked to
+    pub fn kind(&self) -> LinkedFileKind {
+        self.kind
+    }
+
+    /// The ID that placed layers refer to the file by
+    pub fn unique_id(&self) -> &str {
+        &self.unique_id
+    }
+
+    /// The name of the file that was placed, such as `logo.psb`
+    pub fn file_name(&self) -> &str {
+        &self.file_name
+    }
+
+    /// The file's type code, such as `8BPB` for a PSB file. Often four spaces.
+    pub fn file_type(&self) -> &[u8; 4] {
+        &self.file_type
+    }
+
+    /// The raw bytes of the file, when the PSD holds them. Embedded files always have their
+    /// bytes, and newer PSDs keep a copy of the bytes of external files too.
+    pub fn data(&self) -> Option<&[u8]> {
+        self.data.as_deref()
+    }
+}
+
+/// A smart object layer along with the file that it places.
+///
+/// Created by [`Psd::smart_objects`].
+///
+/// [`Psd::smart_objects`]: crate::Psd::smart_objects
+#[derive(Debug, Clone, Copy)]
+pub struct SmartObject<'a> {
+    pub(crate) layer: &'a PsdLayer,
+    pub(crate) placed_layer: &'a PlacedLayer,
+    pub(crate) file: Option<&'a LinkedFile>,
+}
+
+impl<'a> SmartObject<'a> {
+    /// The smart object layer
+    pub fn layer(&self) -> &'a PsdLayer {
+        self.layer
+    }
+
+    /// Where the layer places the smart object
+    pub fn placed_layer(&self) -> &'a PlacedLayer {
+        self.placed_layer
+    }
+
+    /// The file that the layer places. `None` if the PSD doesn't have a linked file with the
+    /// placed layer's unique ID.
+    pub fn file(&self) -> Option<&'a LinkedFile> {
+        self.file
+    }
+
+    /// The name of the file that the layer places, if we found the file
+    pub fn file_name(&self) -> Option<&'a str> {
+        self.file.map(|file| file.file_name())
+    }
+
+    /// The raw bytes of the file that the layer places, when the PSD holds them
+    pub fn data(&self) -> Option<&'a [u8]> {
+        self.file.and_then(|file| file.data())
+    }
+
+    /// Where the corners of the smart object's content are placed on the canvas. See
+    /// [`PlacedLayer::transform`].
+    pub fn transform(&self) -> [f64; 8] {
+        self.placed_layer.transform
+    }
+}
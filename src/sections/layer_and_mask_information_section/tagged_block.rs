@@ -0,0 +1,296 @@
+use crate::sections::file_header_section::PsdVersion;
+use crate::sections::serialize::PsdBuffer;
+use crate::sections::PsdCursor;
+
+/// One of the possible additional layer block signatures
+const SIGNATURE_EIGHT_BIM: [u8; 4] = [56, 66, 73, 77];
+/// One of the possible additional layer block signatures
+const SIGNATURE_EIGHT_B64: [u8; 4] = [56, 66, 54, 52];
+
+/// Keys of the additional layer information blocks whose length marker is 8 bytes long
+/// (instead of 4 bytes) in PSB files.
+const KEYS_WITH_PSB_EIGHT_BYTE_LENGTH: [&[u8; 4]; 13] = [
+    b"LMsk", b"Lr16", b"Lr32", b"Layr", b"Mt16", b"Mt32", b"Mtrn", b"Alph", b"FMsk", b"lnk2",
+    b"FEid", b"FXid", b"PxSD",
+];
+
+/// Where in the layer and mask information section a series of tagged blocks lives.
+///
+/// The data of a tagged block is padded differently depending on where the block lives.
+/// Blocks that are a part of a layer record are padded to a multiple of 2 bytes, while the
+/// blocks at the end of the layer and mask information section are padded to a multiple
+/// of 4 bytes.
+///
+/// The length marker of a block does not include the padding.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub(crate) enum TaggedBlockContext {
+    /// Additional layer information inside of a layer record
+    LayerRecord,
+    /// The tagged blocks that follow the global layer mask info
+    Document,
+}
+
+impl TaggedBlockContext {
+    /// The multiple that the data of a block in this context is padded to
+    fn padding(self) -> u64 {
+        match self {
+            TaggedBlockContext::LayerRecord => 2,
+            TaggedBlockContext::Document => 4,
+        }
+    }
+}
+
+/// The signature, key and length marker that precede the data of a tagged block.
+///
+/// # [Adobe Docs](https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/)
+///
+/// | Length   | Description                                                                         |
+/// |----------|-------------------------------------------------------------------------------------|
+/// | 4        | Signature: '8BIM' or '8B64'                                                         |
+/// | 4        | Key: a 4-character code (See individual sections)                                   |
+/// | 4        | Length data below, rounded up to an even byte count.<br> (**PSB**, the following keys have a length count of 8 bytes: LMsk, Lr16, Lr32, Layr, Mt16, Mt32, Mtrn, Alph, FMsk, lnk2, FEid, FXid, PxSD. |
+/// | Variable | Data (see individual sections)                                                      |
+#[derive(Debug)]
+pub(crate) struct TaggedBlockHeader {
+    pub(crate) key: [u8; 4],
+    pub(crate) data_start: u64,
+    pub(crate) data_len: u64,
+}
+
+impl TaggedBlockHeader {
+    /// Read the header of the tagged block at the cursor's current position.
+    ///
+    /// Returns `None` (without moving the cursor) if there is no tagged block that fits
+    /// before `end`.
+    pub(crate) fn read(
+        cursor: &mut PsdCursor,
+        version: PsdVersion,
+        end: u64,
+    ) -> Option<TaggedBlockHeader> {
+        let start = cursor.position();
+        if start.saturating_add(12) > end {
+            return None;
+        }
+
+        let signature = cursor.peek_4();
+        if signature != SIGNATURE_EIGHT_BIM && signature != SIGNATURE_EIGHT_B64 {
+            return None;
+        }
+        cursor.read_4();
+
+        let mut key = [0; 4];
+        key.copy_from_slice(cursor.read_4());
+
+        let data_len = if version.is_psb() && KEYS_WITH_PSB_EIGHT_BYTE_LENGTH.contains(&&key) {
+            cursor.read_u64()
+        } else {
+            cursor.read_u32() as u64
+        };
+
+        let data_start = cursor.position();
+        if data_start
+            .checked_add(data_len)
+            .is_none_or(|data_end| data_end > end)
+        {
+            cursor.seek(start);
+            return None;
+        }
+
+        Some(TaggedBlockHeader {
+            key,
+            data_start,
+            data_len,
+        })
+    }
+
+    /// Whether the bytes at the cursor's current position start with a tagged block signature,
+    /// such as when [`TaggedBlockHeader::read`] returns `None` for a block that doesn't fit.
+    pub(crate) fn has_signature(cursor: &PsdCursor) -> bool {
+        let start = cursor.position() as usize;
+
+        match cursor.get_ref().get(start..start.saturating_add(4)) {
+            Some(signature) => signature == SIGNATURE_EIGHT_BIM || signature == SIGNATURE_EIGHT_B64,
+            None => false,
+        }
+    }
+
+    /// The position right after this block's data and padding.
+    pub(crate) fn end(&self, context: TaggedBlockContext) -> u64 {
+        let padding = context.padding();
+
+        self.data_start + self.data_len.div_ceil(padding) * padding
+    }
+}
+
+/// A tagged block that we do not parse, such as the tagged blocks found at the end of the
+/// layer and mask information section.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct TaggedBlock {
+    pub(crate) key: [u8; 4],
+    pub(crate) data: Vec<u8>,
+}
+
+impl TaggedBlock {
+    /// Read all of the tagged blocks between the cursor's current position and `end`.
+    ///
+    /// We stop as soon as we run into something that isn't a tagged block.
+    pub(crate) fn read_all(
+        cursor: &mut PsdCursor,
+        version: PsdVersion,
+        context: TaggedBlockContext,
+        end: u64,
+    ) -> Vec<TaggedBlock> {
+        let mut blocks = vec![];
+
+        while let Some(header) = TaggedBlockHeader::read(cursor, version, end) {
+            // The header made sure that the data fits before `end`
+            let data = match cursor.read_checked(header.data_len) {
+                Some(data) => data.to_vec(),
+                None => break,
+            };
+            blocks.push(TaggedBlock {
+                key: header.key,
+                data,
+            });
+
+            cursor.seek(header.end(context).min(end));
+        }
+
+        blocks
+    }
+
+    /// Write the block, padding its data based on where the block lives.
+    ///
+    /// The inverse of `TaggedBlock::read_all`.
+    pub(crate) fn write(
+        &self,
+        buffer: &mut PsdBuffer,
+        version: PsdVersion,
+        context: TaggedBlockContext,
+    ) {
+        buffer.write(&SIGNATURE_EIGHT_BIM);
+        buffer.write(&self.key);
+
+        if version.is_psb() && KEYS_WITH_PSB_EIGHT_BYTE_LENGTH.contains(&&self.key) {
+            buffer.write_u64(self.data.len() as u64);
+        } else {
+            buffer.write_u32(self.data.len() as u32);
+        }
+
+        buffer.write(&self.data);
+        buffer.write_padding(self.data.len(), context.padding() as usize);
+    }
+
+    /// The 4 character key of the block, such as `Patt` or `Lr16`
+    pub fn key(&self) -> &[u8; 4] {
+        &self.key
+    }
+
+    /// The block's data, without any padding
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Verify that layer record blocks are padded to 2 bytes and document blocks to 4 bytes.
+    #[test]
+    fn pads_block_data_based_on_context() {
+        let mut bytes = vec![];
+        push_block(&mut bytes, b"aaaa", &[1, 2, 3, 4, 5]);
+        push_block(&mut bytes, b"bbbb", &[6]);
+
+        let mut padded_to_2 = bytes.clone();
+        padded_to_2.insert(17, 0);
+        padded_to_2.push(0);
+
+        let mut padded_to_4 = bytes.clone();
+        padded_to_4.splice(17..17, vec![0; 3]);
+        padded_to_4.extend_from_slice(&[0; 3]);
+
+        for (bytes, context) in [
+            (padded_to_2, TaggedBlockContext::LayerRecord),
+            (padded_to_4, TaggedBlockContext::Document),
+        ]
+        .iter()
+        {
+            let mut cursor = PsdCursor::new(bytes);
+            let blocks =
+                TaggedBlock::read_all(&mut cursor, PsdVersion::One, *context, bytes.len() as u64);
+
+            assert_eq!(blocks.len(), 2);
+            assert_eq!(blocks[0].key(), b"aaaa");
+            assert_eq!(blocks[0].data(), &[1, 2, 3, 4, 5]);
+            assert_eq!(blocks[1].key(), b"bbbb");
+            assert_eq!(blocks[1].data(), &[6]);
+            assert_eq!(cursor.position(), bytes.len() as u64);
+        }
+    }
+
+    /// Verify that we stop reading when a length marker points past the end of the blocks.
+    #[test]
+    fn stops_at_out_of_bounds_block() {
+        let mut bytes = vec![];
+        push_block(&mut bytes, b"aaaa", &[1, 2]);
+        bytes.extend_from_slice(b"8BIMbbbb");
+        bytes.extend_from_slice(&100u32.to_be_bytes());
+
+        let mut cursor = PsdCursor::new(&bytes);
+        let blocks = TaggedBlock::read_all(
+            &mut cursor,
+            PsdVersion::One,
+            TaggedBlockContext::Document,
+            bytes.len() as u64,
+        );
+
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(cursor.position(), 16);
+    }
+
+    /// Verify that the blocks that we write can be read back.
+    #[test]
+    fn write_round_trip() {
+        let blocks = vec![
+            TaggedBlock {
+                key: *b"aaaa",
+                data: vec![1, 2, 3],
+            },
+            TaggedBlock {
+                key: *b"Lr16",
+                data: vec![4],
+            },
+        ];
+
+        for version in [PsdVersion::One, PsdVersion::Two].iter() {
+            for context in [
+                TaggedBlockContext::LayerRecord,
+                TaggedBlockContext::Document,
+            ]
+            .iter()
+            {
+                let mut buffer = PsdBuffer::new();
+                for block in blocks.iter() {
+                    block.write(&mut buffer, *version, *context);
+                }
+                let bytes = buffer.into_inner();
+
+                let mut cursor = PsdCursor::new(&bytes);
+                let read =
+                    TaggedBlock::read_all(&mut cursor, *version, *context, bytes.len() as u64);
+
+                assert_eq!(read, blocks);
+                assert_eq!(cursor.position(), bytes.len() as u64);
+            }
+        }
+    }
+
+    fn push_block(bytes: &mut Vec<u8>, key: &[u8; 4], data: &[u8]) {
+        bytes.extend_from_slice(b"8BIM");
+        bytes.extend_from_slice(key);
+        bytes.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(data);
+    }
+}
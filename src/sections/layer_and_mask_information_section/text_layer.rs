@@ -0,0 +1,409 @@
+use crate::sections::image_resources_section::{
+    DescriptorField, DescriptorStructure, ImageResourcesDescriptorError,
+};
+use crate::sections::PsdCursor;
+
+/// Information about a text layer, read from the `Type tool object setting` ("TySh")
+/// additional layer information block.
+///
+/// We don't rasterize text, but exposing this information lets you detect text layers (and
+/// whether or not they are warped) so that you can decide how to handle them.
+///
+/// # [Adobe Docs](https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/)
+///
+/// | Length   | Description                                                 |
+/// |----------|-------------------------------------------------------------|
+/// | 2        | Version ( = 1 for Photoshop 6.0)                            |
+/// | 6 * 8    | Transform: xx, xy, yx, yy, tx, and ty respectively.         |
+/// | 2        | Text version ( = 50 for Photoshop 6.0)                      |
+/// | 4        | Descriptor version ( = 16 for Photoshop 6.0)                |
+/// | Variable | Text data (see See Descriptor structure)                    |
+/// | 2        | Warp version ( = 1 for Photoshop 6.0)                       |
+/// | 4        | Descriptor version ( = 16 for Photoshop 6.0)                |
+/// | Variable | Warp data (see See Descriptor structure)                    |
+/// | 4 * 8    | Left, top, right, bottom respectively.                      |
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextLayerInfo {
+    pub(crate) transform: [f64; 6],
+    pub(crate) text_data: DescriptorStructure,
+    pub(crate) warp: TextWarp,
+}
+
+impl TextLayerInfo {
+    /// Read the `TySh` block starting at the cursor's current position.
+    pub(crate) fn read(
+        cursor: &mut PsdCursor,
+    ) -> Result<TextLayerInfo, ImageResourcesDescriptorError> {
+        let _version = cursor.read_u16();
+
+        let mut transform = [0.; 6];
+        for value in transform.iter_mut() {
+            *value = cursor.read_f64();
+        }
+
+        let _text_version = cursor.read_u16();
+        let _descriptor_version = cursor.read_u32();
+        let text_data = DescriptorStructure::read_descriptor_structure(cursor)?;
+
+        let _warp_version = cursor.read_u16();
+        let _descriptor_version = cursor.read_u32();
+        let warp_data = DescriptorStructure::read_descriptor_structure(cursor)?;
+
+        Ok(TextLayerInfo {
+            transform,
+            text_data,
+            warp: TextWarp::from_descriptor(&warp_data),
+        })
+    }
+
+    /// The transform of the text: xx, xy, yx, yy, tx, and ty respectively.
+    pub fn transform(&self) -> [f64; 6] {
+        self.transform
+    }
+
+    /// The text that the layer contains, if present.
+    pub fn text(&self) -> Option<&str> {
+        match self.text_data.fields.get("Txt ") {
+            Some(DescriptorField::String(text)) => Some(text.as_str()),
+            _ => None,
+        }
+    }
+
+    /// The PostScript names of the fonts that the text's style runs can refer to, such as
+    /// `ArialMT`, in the order that they're listed in the font set of the text's engine data.
+    ///
+    /// We don't rasterize text, but these let you check that the fonts a PSD needs are
+    /// installed before handing it to something that does. Photoshop usually lists
+    /// `AdobeInvisFont`, which it uses to draw invisible characters.
+    pub fn font_names(&self) -> Vec<String> {
+        match self.text_data.fields.get("EngineData") {
+            Some(DescriptorField::RawData(engine_data)) => read_font_names(engine_data),
+            _ => vec![],
+        }
+    }
+
+    /// The raw text data descriptor.
+    pub fn text_data(&self) -> &DescriptorStructure {
+        &self.text_data
+    }
+
+    /// The warp that is applied to the text.
+    pub fn warp(&self) -> &TextWarp {
+        &self.warp
+    }
+}
+
+/// The warp applied to a text layer (arc, flag, wave, ...).
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextWarp {
+    pub(crate) style: WarpStyle,
+    pub(crate) value: f64,
+    pub(crate) perspective: f64,
+    pub(crate) perspective_other: f64,
+    pub(crate) rotation: WarpRotation,
+}
+
+impl TextWarp {
+    fn from_descriptor(descriptor: &DescriptorStructure) -> TextWarp {
+        let double = |key: &str| match descriptor.fields.get(key) {
+            Some(DescriptorField::Double(value)) => *value,
+            _ => 0.,
+        };
+        let enum_field = |key: &str| match descriptor.fields.get(key) {
+            Some(DescriptorField::EnumeratedDescriptor(e)) => Some(e.enum_field.as_slice()),
+            _ => None,
+        };
+
+        let style = match enum_field("warpStyle") {
+            Some(style) => WarpStyle::match_style(style),
+            None => WarpStyle::None,
+        };
+        let rotation = match enum_field("warpRotate") {
+            Some(b"Vrtc") => WarpRotation::Vertical,
+            _ => WarpRotation::Horizontal,
+        };
+
+        TextWarp {
+            style,
+            value: double("warpValue"),
+            perspective: double("warpPerspective"),
+            perspective_other: double("warpPerspectiveOther"),
+            rotation,
+        }
+    }
+
+    /// The style of the warp
+    pub fn style(&self) -> WarpStyle {
+        self.style
+    }
+
+    /// The bend of the warp, in percent (-100 to 100)
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+
+    /// The horizontal distortion of the warp, in percent (-100 to 100)
+    pub fn perspective(&self) -> f64 {
+        self.perspective
+    }
+
+    /// The vertical distortion of the warp, in percent (-100 to 100)
+    pub fn perspective_other(&self) -> f64 {
+        self.perspective_other
+    }
+
+    /// The orientation of the warp
+    pub fn rotation(&self) -> WarpRotation {
+        self.rotation
+    }
+
+    /// Whether or not any warp is applied to the text
+    pub fn is_warped(&self) -> bool {
+        self.style != WarpStyle::None
+    }
+}
+
+/// The style of a text warp
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[allow(missing_docs)]
+pub enum WarpStyle {
+    None,
+    Arc,
+    ArcLower,
+    ArcUpper,
+    Arch,
+    Bulge,
+    ShellLower,
+    ShellUpper,
+    Flag,
+    Wave,
+    Fish,
+    Rise,
+    FishEye,
+    Inflate,
+    Squeeze,
+    Twist,
+    Custom,
+    /// A warp style that we do not know about
+    Unknown,
+}
+
+impl WarpStyle {
+    fn match_style(style: &[u8]) -> WarpStyle {
+        match style {
+            b"warpNone" => WarpStyle::None,
+            b"warpArc" => WarpStyle::Arc,
+            b"warpArcLower" => WarpStyle::ArcLower,
+            b"warpArcUpper" => WarpStyle::ArcUpper,
+            b"warpArch" => WarpStyle::Arch,
+            b"warpBulge" => WarpStyle::Bulge,
+            b"warpShellLower" => WarpStyle::ShellLower,
+            b"warpShellUpper" => WarpStyle::ShellUpper,
+            b"warpFlag" => WarpStyle::Flag,
+            b"warpWave" => WarpStyle::Wave,
+            b"warpFish" => WarpStyle::Fish,
+            b"warpRise" => WarpStyle::Rise,
+            b"warpFisheye" => WarpStyle::FishEye,
+            b"warpInflate" => WarpStyle::Inflate,
+            b"warpSqueeze" => WarpStyle::Squeeze,
+            b"warpTwist" => WarpStyle::Twist,
+            b"warpCustom" => WarpStyle::Custom,
+            _ => WarpStyle::Unknown,
+        }
+    }
+}
+
+/// Read the `/Name` of every font in the first `/FontSet` array of the text's engine data, which
+/// is a PostScript-like dictionary such as:
+///
+/// ```text
+/// /ResourceDict << /FontSet [ << /Name (\xFE\xFF\0A\0r\0i\0a\0l\0M\0T) /Script 0 >> ] >>
+/// ```
+fn read_font_names(engine_data: &[u8]) -> Vec<String> {
+    let mut names = vec![];
+
+    let start = match find(engine_data, b"/FontSet") {
+        Some(start) => start + b"/FontSet".len(),
+        None => return names,
+    };
+
+    let mut idx = start;
+    let mut depth = 0;
+    let mut is_name = false;
+    while idx < engine_data.len() {
+        match engine_data[idx] {
+            b'[' => depth += 1,
+            b']' => {
+                depth -= 1;
+                if depth == 0 {
+                    break;
+                }
+            }
+            b'/' => {
+                let key_len = engine_data[idx + 1..]
+                    .iter()
+                    .take_while(|byte| byte.is_ascii_alphanumeric())
+                    .count();
+                is_name = &engine_data[idx + 1..idx + 1 + key_len] == b"Name";
+                idx += key_len;
+            }
+            b'(' => {
+                let (string, len) = read_string(&engine_data[idx + 1..]);
+                if is_name && depth > 0 {
+                    names.push(string);
+                }
+                is_name = false;
+                idx += len;
+            }
+            _ => {}
+        }
+
+        idx += 1;
+    }
+
+    names
+}
+
+/// Read a string that started with a `(`, returning it and the number of bytes up to and
+/// including its closing `)`.
+///
+/// Strings are UTF-16 after a byte order mark and escape `(`, `)` and `\` with a `\`.
+fn read_string(bytes: &[u8]) -> (String, usize) {
+    let mut string = vec![];
+
+    let mut idx = 0;
+    while idx < bytes.len() {
+        match bytes[idx] {
+            b'\\' if idx + 1 < bytes.len() => {
+                idx += 1;
+                string.push(bytes[idx]);
+            }
+            b')' => {
+                idx += 1;
+                break;
+            }
+            byte => string.push(byte),
+        }
+
+        idx += 1;
+    }
+
+    let string = match string.strip_prefix(&[0xFE, 0xFF]) {
+        Some(utf16) => {
+            let units: Vec<u16> = utf16
+                .chunks_exact(2)
+                .map(|unit| u16::from_be_bytes([unit[0], unit[1]]))
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        None => String::from_utf8_lossy(&string).into_owned(),
+    };
+
+    (string, idx)
+}
+
+/// The position of the first occurrence of `needle`
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// The orientation of a text warp
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[allow(missing_docs)]
+pub enum WarpRotation {
+    Horizontal,
+    Vertical,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Verify that we read the warp style and bend out of a TySh block.
+    #[test]
+    fn reads_warp_parameters() {
+        let mut bytes = vec![];
+        bytes.extend_from_slice(&1u16.to_be_bytes());
+        for value in [1., 0., 0., 1., 10., 20.].iter() {
+            bytes.extend_from_slice(&f64::to_be_bytes(*value));
+        }
+
+        bytes.extend_from_slice(&50u16.to_be_bytes());
+        bytes.extend_from_slice(&16u32.to_be_bytes());
+        push_descriptor_header(&mut bytes, b"TxLr", 1);
+        push_key(&mut bytes, b"Txt ");
+        bytes.extend_from_slice(b"TEXT");
+        push_unicode_string(&mut bytes, "Hello");
+
+        bytes.extend_from_slice(&1u16.to_be_bytes());
+        bytes.extend_from_slice(&16u32.to_be_bytes());
+        push_descriptor_header(&mut bytes, b"warp", 3);
+        push_key(&mut bytes, b"warpStyle");
+        bytes.extend_from_slice(b"enum");
+        push_key(&mut bytes, b"warpStyle");
+        push_key(&mut bytes, b"warpArc");
+        push_key(&mut bytes, b"warpValue");
+        bytes.extend_from_slice(b"doub");
+        bytes.extend_from_slice(&f64::to_be_bytes(50.));
+        push_key(&mut bytes, b"warpRotate");
+        bytes.extend_from_slice(b"enum");
+        push_key(&mut bytes, b"Ornt");
+        push_key(&mut bytes, b"Vrtc");
+
+        let mut cursor = PsdCursor::new(&bytes);
+        let text = TextLayerInfo::read(&mut cursor).unwrap();
+
+        assert_eq!(text.transform(), [1., 0., 0., 1., 10., 20.]);
+        assert_eq!(text.text(), Some("Hello"));
+        assert_eq!(text.warp().style(), WarpStyle::Arc);
+        assert_eq!(text.warp().value(), 50.);
+        assert_eq!(text.warp().rotation(), WarpRotation::Vertical);
+        assert!(text.warp().is_warped());
+    }
+
+    /// Verify that we read the names in the font set and skip the other strings in the engine
+    /// data, including escaped parentheses.
+    #[test]
+    fn reads_font_names() {
+        let mut engine_data =
+            b"<< /Editor << /Text (\xFE\xFF\0H\0i) >> /ResourceDict << /FontSet [ ".to_vec();
+        for name in ["ArialMT", "Font)Name"].iter() {
+            engine_data.extend_from_slice(b"<< /Name (\xFE\xFF");
+            for byte in name.encode_utf16().flat_map(|unit| unit.to_be_bytes()) {
+                if byte == b'(' || byte == b')' || byte == b'\\' {
+                    engine_data.push(b'\\');
+                }
+                engine_data.push(byte);
+            }
+            engine_data.extend_from_slice(b") /Script 0 /FontType 0 >> ");
+        }
+        engine_data.extend_from_slice(b"] /StyleSheetSet [ << /Name (Normal) >> ] >> >>");
+
+        assert_eq!(
+            read_font_names(&engine_data),
+            vec!["ArialMT".to_string(), "Font)Name".to_string()]
+        );
+    }
+
+    fn push_descriptor_header(bytes: &mut Vec<u8>, class_id: &[u8], field_count: u32) {
+        push_unicode_string(bytes, "");
+        push_key(bytes, class_id);
+        bytes.extend_from_slice(&field_count.to_be_bytes());
+    }
+
+    fn push_key(bytes: &mut Vec<u8>, key: &[u8]) {
+        let len = if key.len() == 4 { 0 } else { key.len() as u32 };
+        bytes.extend_from_slice(&len.to_be_bytes());
+        bytes.extend_from_slice(key);
+    }
+
+    fn push_unicode_string(bytes: &mut Vec<u8>, string: &str) {
+        let utf16: Vec<u16> = string.encode_utf16().collect();
+        bytes.extend_from_slice(&(utf16.len() as u32).to_be_bytes());
+        for unit in utf16 {
+            bytes.extend_from_slice(&unit.to_be_bytes());
+        }
+    }
+}
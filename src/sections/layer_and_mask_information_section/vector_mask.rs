@@ -0,0 +1,60 @@
+use crate::sections::image_resources_section::BezierPath;
+use crate::sections::PsdCursor;
+
+/// Keys of the tagged blocks that hold a vector mask, "vmsk" or "vsms" (Photoshop CS6)
+pub(crate) const KEY_VECTOR_MASK: [&[u8; 4]; 2] = [b"vmsk", b"vsms"];
+
+/// A layer's vector mask, such as the outline of a shape layer.
+///
+/// # [Adobe Docs](https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/)
+///
+/// | Length   | Description                                                        |
+/// |----------|--------------------------------------------------------------------|
+/// | 4        | Version ( = 3 for Photoshop 6.0)                                   |
+/// | 4        | Flags. bit 1 = invert, bit 2 = not link, bit 3 = disable           |
+/// | Variable | Path records, see [`BezierPath`]                                   |
+#[derive(Debug, Clone, PartialEq)]
+pub struct VectorMask {
+    pub(crate) flags: u32,
+    pub(crate) path: BezierPath,
+}
+
+impl VectorMask {
+    /// Read the data of a vector mask block. Returns `None` if the data is too short to hold the
+    /// version and the flags.
+    pub(crate) fn read(data: &[u8]) -> Option<VectorMask> {
+        if data.len() < 8 {
+            return None;
+        }
+
+        let mut cursor = PsdCursor::new(data);
+        let _version = cursor.read_u32();
+        let flags = cursor.read_u32();
+
+        Some(VectorMask {
+            flags,
+            path: BezierPath::read(&data[8..]),
+        })
+    }
+
+    /// The outline of the mask
+    pub fn path(&self) -> &BezierPath {
+        &self.path
+    }
+
+    /// Bit 1 - the mask is inverted
+    pub fn is_inverted(&self) -> bool {
+        self.flags & (1 << 1) != 0
+    }
+
+    /// Bit 2 - whether the mask is linked to the layer, so that moving the layer also moves the
+    /// mask
+    pub fn is_linked(&self) -> bool {
+        self.flags & (1 << 2) == 0
+    }
+
+    /// Bit 3 - the mask is disabled
+    pub fn is_disabled(&self) -> bool {
+        self.flags & (1 << 3) != 0
+    }
+}
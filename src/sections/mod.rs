@@ -1,17 +1,41 @@
+use std::convert::TryFrom;
 use std::io::Cursor;
 
-use self::file_header_section::{FileHeaderSectionError, EXPECTED_PSD_SIGNATURE};
+use self::file_header_section::{FileHeaderSectionError, PsdVersion, EXPECTED_PSD_SIGNATURE};
 
 /// The length of the entire file header section
-const FILE_HEADER_SECTION_LEN: usize = 26;
+pub(crate) const FILE_HEADER_SECTION_LEN: usize = 26;
 
+pub(crate) mod color_mode_data_section;
 pub mod file_header_section;
 pub mod image_data_section;
 pub mod image_resources_section;
 pub mod layer_and_mask_information_section;
+pub(crate) mod serialize;
 
-/// References to the different major sections of a PSD file
-#[derive(Debug)]
+/// References to the different major sections of a PSD file.
+///
+/// This splits a PSD file into its five sections without parsing them, so that sections can be
+/// inspected, removed or replaced without the cost of parsing the whole file. Every section
+/// except for the file header starts with its length marker, so the sections can be written back
+/// to back to get a PSD file again.
+///
+/// ```no_run
+/// use psd::MajorSections;
+///
+/// // Remove the image resources from a PSD file
+/// let bytes = std::fs::read("image.psd").unwrap();
+/// let sections = MajorSections::from_bytes(&bytes).unwrap();
+///
+/// let mut stripped = vec![];
+/// stripped.extend_from_slice(sections.file_header());
+/// stripped.extend_from_slice(sections.color_mode_data());
+/// // An empty image resources section is only its length marker
+/// stripped.extend_from_slice(&[0, 0, 0, 0]);
+/// stripped.extend_from_slice(sections.layer_and_mask());
+/// stripped.extend_from_slice(sections.image_data());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct MajorSections<'a> {
     pub(crate) file_header: &'a [u8],
     pub(crate) color_mode_data: &'a [u8],
@@ -56,7 +80,10 @@ impl<'a> MajorSections<'a> {
     /// A 4-byte length field, representing the number of characters in the string (not bytes).
     ///
     /// The string of Unicode values, two bytes per character.
-    pub fn from_bytes(bytes: &[u8]) -> Result<MajorSections, FileHeaderSectionError> {
+    ///
+    /// Only the signature, the version and the length markers are read, so the sections
+    /// themselves might still fail to parse.
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<MajorSections<'a>, FileHeaderSectionError> {
         // File header section must be 26 bytes long.
         if bytes.len() < FILE_HEADER_SECTION_LEN {
             return Err(FileHeaderSectionError::IncorrectLength {
@@ -76,9 +103,20 @@ impl<'a> MajorSections<'a> {
         let file_header = &bytes[0..FILE_HEADER_SECTION_LEN];
         cursor.read(FILE_HEADER_SECTION_LEN as u32);
 
-        let (color_start, color_end) = read_major_section_start_end(&mut cursor);
-        let (img_res_start, img_res_end) = read_major_section_start_end(&mut cursor);
-        let (layer_mask_start, layer_mask_end) = read_major_section_start_end(&mut cursor);
+        // The version gets validated when we parse the file header section, here we only need
+        // to know whether or not the layer and mask section has an 8 byte length marker.
+        let version = if file_header[4..6] == [0, 2] {
+            PsdVersion::Two
+        } else {
+            PsdVersion::One
+        };
+
+        let (color_start, color_end) =
+            read_major_section_start_end(&mut cursor, PsdVersion::One, "color mode data")?;
+        let (img_res_start, img_res_end) =
+            read_major_section_start_end(&mut cursor, PsdVersion::One, "image resources")?;
+        let (layer_mask_start, layer_mask_end) =
+            read_major_section_start_end(&mut cursor, version, "layer and mask information")?;
 
         // The remaining bytes are the image data section.
         let image_data = &bytes[cursor.position() as usize..];
@@ -91,16 +129,55 @@ impl<'a> MajorSections<'a> {
             image_data,
         })
     }
+
+    /// The 26 bytes of the file header section
+    pub fn file_header(&self) -> &'a [u8] {
+        self.file_header
+    }
+
+    /// The color mode data section, starting with its 4 byte length marker
+    pub fn color_mode_data(&self) -> &'a [u8] {
+        self.color_mode_data
+    }
+
+    /// The image resources section, starting with its 4 byte length marker
+    pub fn image_resources(&self) -> &'a [u8] {
+        self.image_resources
+    }
+
+    /// The layer and mask information section, starting with its length marker, which is 8
+    /// bytes long in PSB files and 4 bytes long otherwise
+    pub fn layer_and_mask(&self) -> &'a [u8] {
+        self.layer_and_mask
+    }
+
+    /// The image data section, which runs until the end of the file
+    pub fn image_data(&self) -> &'a [u8] {
+        self.image_data
+    }
 }
 
 /// Get the start and end indices of a major section
-fn read_major_section_start_end(cursor: &mut PsdCursor) -> (usize, usize) {
+///
+/// The length marker is 8 bytes long for sections that are larger in PSB files.
+fn read_major_section_start_end(
+    cursor: &mut PsdCursor,
+    version: PsdVersion,
+    section: &'static str,
+) -> Result<(usize, usize), FileHeaderSectionError> {
+    let truncated = FileHeaderSectionError::TruncatedSection { section };
+
     let start = cursor.position() as usize;
-    let data_len = cursor.read_u32();
-    cursor.read(data_len);
+    let length_marker_len = if version.is_psb() { 8 } else { 4 };
+    if cursor.remaining() < length_marker_len {
+        return Err(truncated);
+    }
+
+    let data_len = cursor.read_length(version);
+    cursor.read_checked(data_len).ok_or(truncated)?;
     let end = cursor.position() as usize;
 
-    (start, end)
+    Ok((start, end))
 }
 
 /// A Cursor wrapping bytes from a PSD file.
@@ -123,6 +200,11 @@ impl<'a> PsdCursor<'a> {
         self.cursor.position()
     }
 
+    /// The number of bytes after the cursor's position, or 0 if it was moved past the end
+    pub fn remaining(&self) -> u64 {
+        (self.get_ref().len() as u64).saturating_sub(self.position())
+    }
+
     pub fn seek(&mut self, pos: u64) {
         self.cursor.set_position(pos);
     }
@@ -133,13 +215,38 @@ impl<'a> PsdCursor<'a> {
     }
 
     /// Advance the cursor by count bytes and return those bytes
+    ///
+    /// # Panics
+    ///
+    /// If there are fewer than count bytes left. Use `read_checked` for lengths that might be
+    /// corrupt.
     pub fn read(&mut self, count: u32) -> &[u8] {
+        let (position, len) = (self.position(), self.get_ref().len());
+
+        match self.read_checked(count as u64) {
+            Some(bytes) => bytes,
+            None => panic!(
+                "Tried to read {} bytes at position {} of {} bytes",
+                count, position, len
+            ),
+        }
+    }
+
+    /// Advance the cursor by a length that was read from the file and return those bytes.
+    ///
+    /// Returns `None` without moving the cursor if the bytes run past the end of the data. We
+    /// do the math in `u64` and check the conversion into `usize` so that the 8 byte lengths in
+    /// PSB files can't overflow or get truncated on 32-bit targets such as wasm32.
+    pub fn read_checked(&mut self, count: u64) -> Option<&[u8]> {
+        let bytes = *self.cursor.get_ref();
+
+        // The position never exceeds the length of the bytes, so it always fits in a usize
         let start = self.cursor.position() as usize;
-        let end = start + count as usize;
-        let bytes = &self.cursor.get_ref()[start..end];
+        let end = start.checked_add(usize::try_from(count).ok()?)?;
+        let bytes = bytes.get(start..end)?;
 
         self.cursor.set_position(end as u64);
-        bytes
+        Some(bytes)
     }
 
     pub fn peek_u32(&self) -> u32 {
@@ -245,6 +352,35 @@ impl<'a> PsdCursor<'a> {
         f64::from_be_bytes(array)
     }
 
+    /// Read 8 bytes as a u64
+    pub fn read_u64(&mut self) -> u64 {
+        let bytes = self.read_8();
+
+        let mut array = [0; 8];
+        array.copy_from_slice(bytes);
+
+        u64::from_be_bytes(array)
+    }
+
+    /// Read a length marker, which is 4 bytes in PSD files and 8 bytes in PSB files.
+    pub fn read_length(&mut self, version: PsdVersion) -> u64 {
+        if version.is_psb() {
+            self.read_u64()
+        } else {
+            self.read_u32() as u64
+        }
+    }
+
+    /// Read the byte count of an RLE compressed scanline, which is 2 bytes in PSD files and 4
+    /// bytes in PSB files.
+    pub fn read_rle_byte_count(&mut self, version: PsdVersion) -> u32 {
+        if version.is_psb() {
+            self.read_u32()
+        } else {
+            self.read_u16() as u32
+        }
+    }
+
     /// Read 8 bytes as a i64
     pub fn read_i64(&mut self) -> i64 {
         let bytes = self.read_8();
@@ -270,14 +406,18 @@ impl<'a> PsdCursor<'a> {
     /// A 4-byte length field, representing the number of UTF-16 code units in the string (not bytes).
     /// The string of Unicode values, two bytes per character and a two byte null for the end of the string.
     pub fn read_unicode_string_padding(&mut self, padding: usize) -> String {
-        let length = self.read_u32() as usize;
-        // UTF-16 encoding - two bytes per character
+        let length = self.read_u32() as u64;
+        // UTF-16 encoding - two bytes per character. This can overflow a u32 (or a 32-bit
+        // usize) so we do the math in u64.
         let length_bytes = length * 2;
 
-        let data = self.read(length_bytes as u32);
-        let result = String::from_utf16(&u8_slice_to_u16(data).as_slice()[..length]).unwrap();
+        let data = self
+            .read_checked(length_bytes)
+            .expect("Unicode string runs past the end of the data");
+        let result = String::from_utf16(&u8_slice_to_u16(data)).unwrap();
 
-        self.read_padding(4 + length_bytes, padding);
+        // The string fit in the data, so its length fits in a usize
+        self.read_padding(4 + length_bytes as usize, padding);
 
         result
     }
@@ -292,21 +432,42 @@ impl<'a> PsdCursor<'a> {
         }
     }
 
-    /// Reads 'Pascal string'
-    ///
-    /// Pascal string is UTF-8 string, padded to make the size even
+    /// Reads the bytes of a 'Pascal string', padded to make the size even
     /// (a null name consists of two bytes of 0)
-    pub fn read_pascal_string(&mut self) -> String {
+    ///
+    /// See `decode_pascal_string` for turning the bytes into a `String`.
+    pub fn read_pascal_bytes(&mut self) -> &[u8] {
         let len = self.read_u8();
-        let data = self.read(len as u32);
-        let result = String::from_utf8_lossy(data).into_owned();
 
         if len % 2 == 0 {
             // If the total length is odd, read an extra null byte
-            self.read_u8();
+            let data = self.read(len as u32 + 1);
+            &data[..len as usize]
+        } else {
+            self.read(len as u32)
         }
+    }
+}
 
-        result
+/// The characters of the Mac OS Roman encoding from 0x80 to 0xFF
+const MAC_ROMAN_HIGH: &str = "ÄÅÇÉÑÖÜáàâäãåçéèêëíìîïñóòôöõúùûü†°¢£§•¶ß®©™´¨≠ÆØ∞±≤≥¥µ∂∑∏π∫ªºΩæø\
+                              ¿¡¬√ƒ≈∆«»…\u{a0}ÀÃÕŒœ–—“”‘’÷◊ÿŸ⁄€‹›ﬁﬂ‡·‚„‰ÂÊÁËÈÍÎÏÌÓÔ\u{f8ff}ÒÚÛÙıˆ˜¯˘˙˚¸˝˛ˇ";
+
+/// Decode the bytes of a Pascal string.
+///
+/// Photoshop writes Pascal strings in the system's legacy encoding (Mac OS Roman on macOS) and
+/// plugins may write UTF-8. We can't tell which legacy encoding was used, so we use UTF-8 when
+/// the bytes are valid UTF-8 and otherwise fall back to Mac OS Roman.
+pub(crate) fn decode_pascal_string(bytes: &[u8]) -> String {
+    match std::str::from_utf8(bytes) {
+        Ok(string) => string.to_string(),
+        Err(_) => bytes
+            .iter()
+            .map(|byte| match byte {
+                0..=0x7F => *byte as char,
+                _ => MAC_ROMAN_HIGH.chars().nth(*byte as usize - 0x80).unwrap(),
+            })
+            .collect(),
     }
 }
 
@@ -324,3 +485,22 @@ fn u32_from_be_bytes(bytes: &[u8]) -> u32 {
 
     u32::from_be_bytes(array)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Verify that lengths past the end of the data (or past the end of a usize) don't move the
+    /// cursor.
+    #[test]
+    fn read_checked() {
+        let mut cursor = PsdCursor::new(&[1, 2, 3]);
+
+        assert_eq!(cursor.read_checked(1), Some(&[1][..]));
+        assert_eq!(cursor.read_checked(3), None);
+        assert_eq!(cursor.read_checked(u64::MAX), None);
+        assert_eq!(cursor.remaining(), 2);
+        assert_eq!(cursor.read_checked(2), Some(&[2, 3][..]));
+        assert_eq!(cursor.read_checked(0), Some(&[][..]));
+    }
+}
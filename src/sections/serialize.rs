@@ -0,0 +1,202 @@
+use crate::sections::file_header_section::PsdVersion;
+
+/// Write a structure back into the bytes of a PSD file.
+///
+/// This is the inverse of reading the structure with a [`PsdCursor`].
+///
+/// [`PsdCursor`]: crate::sections::PsdCursor
+pub(crate) trait PsdSerialize {
+    /// Append the bytes of this structure to the buffer.
+    fn write(&self, buffer: &mut PsdBuffer);
+}
+
+/// A growable buffer that PSD bytes are written into.
+///
+/// Provides methods that abstract common ways of writing PSD bytes, mirroring the read methods
+/// of [`PsdCursor`].
+///
+/// [`PsdCursor`]: crate::sections::PsdCursor
+#[derive(Debug, Default)]
+pub(crate) struct PsdBuffer {
+    bytes: Vec<u8>,
+}
+
+impl PsdBuffer {
+    /// Create a new, empty PsdBuffer
+    pub fn new() -> PsdBuffer {
+        PsdBuffer::default()
+    }
+
+    /// The number of bytes that have been written so far
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// Get the bytes that have been written
+    pub fn into_inner(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    /// Write raw bytes
+    pub fn write(&mut self, bytes: &[u8]) {
+        self.bytes.extend_from_slice(bytes);
+    }
+
+    /// Write a u8
+    pub fn write_u8(&mut self, value: u8) {
+        self.bytes.push(value);
+    }
+
+    /// Write a big endian u16
+    pub fn write_u16(&mut self, value: u16) {
+        self.write(&value.to_be_bytes());
+    }
+
+    /// Write a big endian i16
+    pub fn write_i16(&mut self, value: i16) {
+        self.write(&value.to_be_bytes());
+    }
+
+    /// Write a big endian u32
+    pub fn write_u32(&mut self, value: u32) {
+        self.write(&value.to_be_bytes());
+    }
+
+    /// Write a big endian i32
+    pub fn write_i32(&mut self, value: i32) {
+        self.write(&value.to_be_bytes());
+    }
+
+    /// Write a big endian u64
+    pub fn write_u64(&mut self, value: u64) {
+        self.write(&value.to_be_bytes());
+    }
+
+    /// Write a big endian i64
+    pub fn write_i64(&mut self, value: i64) {
+        self.write(&value.to_be_bytes());
+    }
+
+    /// Write a big endian f64
+    pub fn write_f64(&mut self, value: f64) {
+        self.write(&value.to_be_bytes());
+    }
+
+    /// Write a u32 length marker that will be filled in later with [`PsdBuffer::end_length`].
+    ///
+    /// Returns the position of the length marker.
+    pub fn start_length(&mut self) -> usize {
+        self.start_length_for(PsdVersion::One)
+    }
+
+    /// Fill in a length marker that was started with [`PsdBuffer::start_length`] with the number
+    /// of bytes that have been written since.
+    pub fn end_length(&mut self, pos: usize) {
+        self.end_length_for(pos, PsdVersion::One)
+    }
+
+    /// Write a length marker that is 8 bytes long in PSB files and 4 bytes long otherwise, to be
+    /// filled in later with [`PsdBuffer::end_length_for`].
+    ///
+    /// Returns the position of the length marker.
+    pub fn start_length_for(&mut self, version: PsdVersion) -> usize {
+        let pos = self.len();
+        self.write_length(version, 0);
+        pos
+    }
+
+    /// Fill in a length marker that was started with [`PsdBuffer::start_length_for`] with the
+    /// number of bytes that have been written since.
+    pub fn end_length_for(&mut self, pos: usize, version: PsdVersion) {
+        if version.is_psb() {
+            let len = (self.len() - pos - 8) as u64;
+            self.bytes[pos..pos + 8].copy_from_slice(&len.to_be_bytes());
+        } else {
+            let len = (self.len() - pos - 4) as u32;
+            self.bytes[pos..pos + 4].copy_from_slice(&len.to_be_bytes());
+        }
+    }
+
+    /// Write a length marker, which is 8 bytes long in PSB files and 4 bytes long otherwise.
+    ///
+    /// The inverse of `PsdCursor::read_length`.
+    pub fn write_length(&mut self, version: PsdVersion, length: u64) {
+        if version.is_psb() {
+            self.write_u64(length);
+        } else {
+            self.write_u32(length as u32);
+        }
+    }
+
+    /// Write the byte count of an RLE compressed scanline, which is 2 bytes in PSD files and 4
+    /// bytes in PSB files.
+    ///
+    /// The inverse of `PsdCursor::read_rle_byte_count`.
+    pub fn write_rle_byte_count(&mut self, version: PsdVersion, byte_count: u32) {
+        if version.is_psb() {
+            self.write_u32(byte_count);
+        } else {
+            self.write_u16(byte_count as u16);
+        }
+    }
+
+    /// Writes 'Unicode string' using specified padding
+    ///
+    /// The inverse of `PsdCursor::read_unicode_string_padding`.
+    pub fn write_unicode_string_padding(&mut self, string: &str, padding: usize) {
+        let utf16: Vec<u16> = string.encode_utf16().collect();
+
+        self.write_u32(utf16.len() as u32);
+        for unit in utf16.iter() {
+            self.write(&unit.to_be_bytes());
+        }
+
+        self.write_padding(4 + utf16.len() * 2, padding);
+    }
+
+    /// Writes the bytes of a 'Pascal string', padded to make the size even
+    ///
+    /// The inverse of `PsdCursor::read_pascal_bytes`.
+    pub fn write_pascal_bytes(&mut self, bytes: &[u8]) {
+        let bytes = &bytes[..bytes.len().min(255)];
+
+        self.write_u8(bytes.len() as u8);
+        self.write(bytes);
+
+        self.write_padding(1 + bytes.len(), 2);
+    }
+
+    /// Write enough zeros to pad `size` to a multiple of `divisor`
+    pub fn write_padding(&mut self, size: usize, divisor: usize) {
+        let remainder = size % divisor;
+        if remainder > 0 {
+            for _ in 0..divisor - remainder {
+                self.write_u8(0);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sections::PsdCursor;
+
+    /// Verify that the strings that we write can be read back.
+    #[test]
+    fn strings_round_trip() {
+        for name in ["", "a", "ab", "abc"].iter() {
+            let mut buffer = PsdBuffer::new();
+            buffer.write_pascal_bytes(name.as_bytes());
+            buffer.write_unicode_string_padding(name, 4);
+            buffer.write_u8(42);
+
+            let bytes = buffer.into_inner();
+            let mut cursor = PsdCursor::new(&bytes);
+
+            assert_eq!(cursor.read_pascal_bytes(), name.as_bytes());
+            assert_eq!(cursor.read_unicode_string(), *name);
+            assert_eq!(cursor.read_u8(), 42);
+        }
+    }
+}
@@ -0,0 +1,229 @@
+use crate::psd_channel::for_each_channel_row;
+use crate::sections::image_data_section::ChannelBytes;
+use crate::{ChannelDisplayInfo, ColorMode, ImageResource, Psd, PsdDepth};
+
+/// The names of the process inks of a CMYK image, in the order that they're stored
+const CMYK_INKS: [&str; 4] = ["Cyan", "Magenta", "Yellow", "Black"];
+
+/// The coverage of a single ink across the PSD's final image.
+///
+/// Created by [`Psd::separations`].
+///
+/// [`Psd::separations`]: crate::Psd::separations
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Separation {
+    pub(crate) name: String,
+    pub(crate) kind: SeparationKind,
+    pub(crate) pixels: Vec<u8>,
+}
+
+/// Whether a [`Separation`] is one of the four process inks or a spot color.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum SeparationKind {
+    /// Cyan, magenta, yellow or black
+    Process,
+    /// A spot color channel, printed with its own ink
+    Spot,
+}
+
+impl Separation {
+    /// The name of the ink, such as `Cyan` or `PANTONE 185 C`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Whether this is a process ink or a spot color
+    pub fn kind(&self) -> SeparationKind {
+        self.kind
+    }
+
+    /// One byte per pixel of the PSD's final image, where 0 is no ink and 255 is full coverage.
+    ///
+    /// Photoshop stores ink channels inverted (255 is no ink), so these are flipped to match how
+    /// the plates are printed.
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+}
+
+/// One plane per ink: the process inks of a CMYK image followed by every spot channel.
+pub(crate) fn separations(psd: &Psd) -> Vec<Separation> {
+    let process_inks: &[&str] = match psd.color_mode() {
+        ColorMode::Cmyk => &CMYK_INKS,
+        _ => &[],
+    };
+
+    let mut separations = vec![];
+
+    for (name, channel) in process_inks.iter().zip(psd.image_data_section.channels()) {
+        separations.push(Separation {
+            name: name.to_string(),
+            kind: SeparationKind::Process,
+            pixels: ink_coverage(channel, psd.width(), psd.height(), psd.depth()),
+        });
+    }
+
+    for (idx, channel) in extra_channels(psd).into_iter().enumerate() {
+        if !channel.is_spot() {
+            continue;
+        }
+
+        separations.push(Separation {
+            name: channel.name.unwrap_or_else(|| format!("Spot {}", idx + 1)),
+            kind: SeparationKind::Spot,
+            pixels: channel.pixels.iter().map(|sample| 255 - sample).collect(),
+        });
+    }
+
+    separations
+}
+
+/// An alpha or spot channel of the PSD's final image, along with its name and display info.
+///
+/// Created by [`Psd::extra_channels`].
+///
+/// [`Psd::extra_channels`]: crate::Psd::extra_channels
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct ExtraChannel {
+    pub(crate) name: Option<String>,
+    pub(crate) display_info: Option<ChannelDisplayInfo>,
+    pub(crate) pixels: Vec<u8>,
+}
+
+impl ExtraChannel {
+    /// The name of the channel, such as `Alpha 1` or `PANTONE 185 C`, or `None` if the PSD
+    /// doesn't name its channels
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// The color, opacity and kind of the channel, or `None` if the PSD doesn't have a display
+    /// info resource
+    pub fn display_info(&self) -> Option<&ChannelDisplayInfo> {
+        self.display_info.as_ref()
+    }
+
+    /// Whether the channel is a spot color, printed with its own ink
+    pub fn is_spot(&self) -> bool {
+        self.display_info.is_some_and(|info| info.is_spot())
+    }
+
+    /// One byte per pixel of the PSD's final image, as the channel is stored and shown in
+    /// Photoshop's channels panel. 16 bit channels are mapped down to 8 bits.
+    ///
+    /// Spot channels are stored inverted, so 0 is full ink coverage. See [`Separation::pixels`]
+    /// for the coverage the way that it's printed.
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+}
+
+/// Every alpha and spot channel of the final image, in the order that they're stored.
+pub(crate) fn extra_channels(psd: &Psd) -> Vec<ExtraChannel> {
+    let color_channel_count = match psd.color_mode() {
+        ColorMode::Rgb | ColorMode::Lab => 3,
+        ColorMode::Cmyk => 4,
+        ColorMode::Multichannel => 0,
+        ColorMode::Bitmap | ColorMode::Grayscale | ColorMode::Indexed | ColorMode::Duotone => 1,
+    };
+
+    let channels: Vec<&ChannelBytes> = psd.image_data_section.channels().collect();
+    let channels = channels.get(color_channel_count..).unwrap_or(&[]);
+
+    let names = psd
+        .resources()
+        .iter()
+        .find_map(|resource| match resource {
+            ImageResource::UnicodeAlphaNames(names) => Some(names),
+            _ => None,
+        })
+        .or_else(|| {
+            psd.resources().iter().find_map(|resource| match resource {
+                ImageResource::AlphaNames(names) => Some(names),
+                _ => None,
+            })
+        });
+    let display_infos = psd
+        .resources()
+        .iter()
+        .find_map(|resource| match resource {
+            ImageResource::DisplayInfo(infos) => Some(infos),
+            _ => None,
+        })
+        .or_else(|| {
+            psd.resources().iter().find_map(|resource| match resource {
+                ImageResource::ObsoleteDisplayInfo(infos) => Some(infos),
+                _ => None,
+            })
+        });
+
+    let described = names
+        .map_or(0, |names| names.len())
+        .max(display_infos.map_or(0, |infos| infos.len()));
+
+    // A layered image can store its merged transparency before the alpha and spot channels.
+    // It doesn't have a name or display info.
+    let channels = if channels.len() == described + 1 {
+        &channels[1..]
+    } else {
+        channels
+    };
+
+    channels
+        .iter()
+        .enumerate()
+        .map(|(idx, channel)| ExtraChannel {
+            name: names.and_then(|names| names.get(idx).cloned()),
+            display_info: display_infos.and_then(|infos| infos.get(idx).copied()),
+            pixels: channel_samples(channel, psd.width(), psd.height(), psd.depth()),
+        })
+        .collect()
+}
+
+/// Decode a channel of the final image into one byte of ink coverage per pixel.
+fn ink_coverage(channel: &ChannelBytes, width: u32, height: u32, depth: PsdDepth) -> Vec<u8> {
+    let mut coverage = channel_samples(channel, width, height, depth);
+    for ink in coverage.iter_mut() {
+        *ink = 255 - *ink;
+    }
+    coverage
+}
+
+/// Decode a channel of the final image into one 8 bit sample per pixel. Pixels that can't be
+/// decoded are left at 255, which is no ink.
+fn channel_samples(channel: &ChannelBytes, width: u32, height: u32, depth: PsdDepth) -> Vec<u8> {
+    let pixel_count = width as usize * height as usize;
+    let mut samples = vec![255; pixel_count];
+
+    let bytes_per_sample = match (channel, depth) {
+        (_, PsdDepth::Eight) => 1,
+        // We map raw 16 bit red data down to 8 bits while parsing
+        (ChannelBytes::RawData(bytes), PsdDepth::Sixteen) if bytes.len() == pixel_count => 1,
+        (_, PsdDepth::Sixteen) => 2,
+        _ => return samples,
+    };
+
+    let width = width as usize;
+    for_each_channel_row(
+        channel,
+        None,
+        width * bytes_per_sample,
+        0..usize::MAX,
+        |row_idx, row| {
+            let samples_row = match samples.get_mut(row_idx * width..(row_idx + 1) * width) {
+                Some(samples_row) => samples_row,
+                None => return,
+            };
+
+            // The most significant byte comes first, which is the 8 bit value that we want
+            for (sample, stored) in samples_row
+                .iter_mut()
+                .zip(row.chunks_exact(bytes_per_sample))
+            {
+                *sample = stored[0];
+            }
+        },
+    );
+
+    samples
+}
@@ -0,0 +1,103 @@
+//! Bytes that point into a buffer which other parts of the PSD share, so that parsing doesn't
+//! need to copy them out of the file, see `Psd::from_shared_bytes`.
+
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::{Deref, Range};
+use std::sync::Arc;
+
+/// A range of a shared buffer. Compares and hashes as the bytes in the range, regardless of
+/// which buffer they are in.
+#[derive(Clone)]
+pub struct SharedBytes {
+    buffer: Arc<[u8]>,
+    range: Range<usize>,
+}
+
+impl SharedBytes {
+    /// All of the bytes of the buffer
+    pub(crate) fn new(buffer: Arc<[u8]>) -> SharedBytes {
+        let range = 0..buffer.len();
+        SharedBytes { buffer, range }
+    }
+
+    /// The bytes of `bytes`, sharing our buffer when they are a part of it and copying them
+    /// otherwise.
+    pub(crate) fn share(shared: Option<&SharedBytes>, bytes: &[u8]) -> SharedBytes {
+        let shared = match shared {
+            Some(shared) => shared,
+            None => return SharedBytes::from(bytes),
+        };
+
+        let buffer_start = shared.buffer.as_ptr() as usize;
+        let start = bytes.as_ptr() as usize;
+        if start < buffer_start || start + bytes.len() > buffer_start + shared.buffer.len() {
+            return SharedBytes::from(bytes);
+        }
+
+        let start = start - buffer_start;
+        SharedBytes {
+            buffer: Arc::clone(&shared.buffer),
+            range: start..start + bytes.len(),
+        }
+    }
+}
+
+impl Deref for SharedBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.buffer[self.range.clone()]
+    }
+}
+
+impl From<Vec<u8>> for SharedBytes {
+    fn from(bytes: Vec<u8>) -> SharedBytes {
+        SharedBytes::new(Arc::from(bytes))
+    }
+}
+
+impl From<&[u8]> for SharedBytes {
+    fn from(bytes: &[u8]) -> SharedBytes {
+        SharedBytes::new(Arc::from(bytes))
+    }
+}
+
+impl PartialEq for SharedBytes {
+    fn eq(&self, other: &SharedBytes) -> bool {
+        **self == **other
+    }
+}
+
+impl Eq for SharedBytes {}
+
+impl Hash for SharedBytes {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (**self).hash(state)
+    }
+}
+
+impl fmt::Debug for SharedBytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Bytes inside of the buffer point into it, and bytes outside of it are copied.
+    #[test]
+    fn share_bytes_of_buffer() {
+        let buffer = SharedBytes::from(vec![1, 2, 3, 4]);
+
+        let shared = SharedBytes::share(Some(&buffer), &buffer[1..3]);
+        assert_eq!(&*shared, &[2, 3]);
+        assert!(Arc::ptr_eq(&shared.buffer, &buffer.buffer));
+
+        let copied = SharedBytes::share(Some(&buffer), &[2, 3]);
+        assert_eq!(copied, shared);
+        assert!(!Arc::ptr_eq(&copied.buffer, &buffer.buffer));
+    }
+}
@@ -0,0 +1,144 @@
+use crate::sections::image_resources_section::Slice;
+
+/// The pixels of a single slice, cropped out of the PSD's final image.
+///
+/// Created by [`Psd::export_slices`].
+///
+/// [`Psd::export_slices`]: crate::Psd::export_slices
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct ExportedSlice {
+    pub(crate) file_name: String,
+    pub(crate) slice: Slice,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) rgba: Vec<u8>,
+}
+
+impl ExportedSlice {
+    /// The name that Photoshop would use when saving this slice for the web (without an
+    /// extension).
+    ///
+    /// This is the slice's name if it has one, otherwise its URL, otherwise the name of the
+    /// slices group followed by the slice's (one indexed) number, such as `my-design_03`.
+    pub fn file_name(&self) -> &str {
+        &self.file_name
+    }
+
+    /// The slice that these pixels were cropped from
+    pub fn slice(&self) -> &Slice {
+        &self.slice
+    }
+
+    /// The width of the cropped pixels
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// The height of the cropped pixels
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The cropped pixels
+    /// [ R,G,B,A, R,G,B,A, R,G,B,A, ...]
+    pub fn rgba(&self) -> &[u8] {
+        &self.rgba
+    }
+}
+
+/// Crop each slice out of the PSD's final image.
+///
+/// Slices are clamped to the PSD's bounds. Slices that are entirely outside of the PSD are
+/// skipped.
+pub(crate) fn export_slices(
+    group_name: &str,
+    slices: &[Slice],
+    rgba: &[u8],
+    psd_width: u32,
+    psd_height: u32,
+) -> Vec<ExportedSlice> {
+    let mut exported = Vec::with_capacity(slices.len());
+
+    for (idx, slice) in slices.iter().enumerate() {
+        let left = slice.left.max(0).min(psd_width as i32) as u32;
+        let right = slice.right.max(0).min(psd_width as i32) as u32;
+        let top = slice.top.max(0).min(psd_height as i32) as u32;
+        let bottom = slice.bottom.max(0).min(psd_height as i32) as u32;
+
+        if right <= left || bottom <= top {
+            continue;
+        }
+
+        let width = right - left;
+        let height = bottom - top;
+
+        let mut cropped = Vec::with_capacity((width * height * 4) as usize);
+        for row in top..bottom {
+            let row_start = ((row * psd_width + left) * 4) as usize;
+            let row_end = row_start + (width * 4) as usize;
+            cropped.extend_from_slice(&rgba[row_start..row_end]);
+        }
+
+        let file_name = if !slice.name.is_empty() {
+            slice.name.clone()
+        } else if !slice.url.is_empty() {
+            slice.url.clone()
+        } else {
+            format!("{}_{:02}", group_name, idx + 1)
+        };
+
+        exported.push(ExportedSlice {
+            file_name,
+            slice: slice.clone(),
+            width,
+            height,
+            rgba: cropped,
+        });
+    }
+
+    exported
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Verify that we crop slices out of the final image and clamp them to the PSD's bounds.
+    #[test]
+    fn crops_and_names_slices() {
+        // 3x2 image where each pixel's red value is its index
+        let rgba: Vec<u8> = (0..6).flat_map(|idx| vec![idx, 0, 0, 255]).collect();
+
+        let slices = vec![
+            slice("", "", (0, 1, 2, 3)),
+            slice("header", "", (-5, -5, 1, 1)),
+            slice("", "https://example.com", (10, 10, 20, 20)),
+        ];
+
+        let exported = export_slices("design", &slices, &rgba, 3, 2);
+
+        assert_eq!(exported.len(), 2);
+
+        assert_eq!(exported[0].file_name(), "design_01");
+        assert_eq!((exported[0].width(), exported[0].height()), (2, 2));
+        assert_eq!(
+            exported[0].rgba(),
+            &[1, 0, 0, 255, 2, 0, 0, 255, 4, 0, 0, 255, 5, 0, 0, 255]
+        );
+
+        assert_eq!(exported[1].file_name(), "header");
+        assert_eq!(exported[1].rgba(), &[0, 0, 0, 255]);
+    }
+
+    fn slice(name: &str, url: &str, (top, left, bottom, right): (i32, i32, i32, i32)) -> Slice {
+        Slice {
+            id: 0,
+            name: name.to_string(),
+            url: url.to_string(),
+            top,
+            left,
+            bottom,
+            right,
+        }
+    }
+}
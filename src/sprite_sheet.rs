@@ -0,0 +1,246 @@
+use std::fmt::Write;
+
+use crate::geometry::json_string;
+use crate::{Psd, PsdError};
+
+/// Chainable options for packing groups into a sprite sheet.
+///
+/// Created by [`Psd::sprite_sheet`].
+///
+/// [`Psd::sprite_sheet`]: crate::Psd::sprite_sheet
+#[derive(Debug, Clone)]
+pub struct SpriteSheetBuilder<'a> {
+    psd: &'a Psd,
+    group_paths: Option<Vec<String>>,
+    max_width: u32,
+    padding: u32,
+}
+
+impl<'a> SpriteSheetBuilder<'a> {
+    pub(crate) fn new(psd: &'a Psd) -> SpriteSheetBuilder<'a> {
+        SpriteSheetBuilder {
+            psd,
+            group_paths: None,
+            max_width: 2048,
+            padding: 1,
+        }
+    }
+
+    /// Pack these groups instead of every top level group. Paths are the names of the groups
+    /// that the group is nested in followed by its own name, separated by `/`.
+    pub fn groups<S: AsRef<str>>(mut self, group_paths: &[S]) -> SpriteSheetBuilder<'a> {
+        self.group_paths = Some(
+            group_paths
+                .iter()
+                .map(|path| path.as_ref().to_string())
+                .collect(),
+        );
+        self
+    }
+
+    /// Start a new row of sprites once a row would be wider than this. Defaults to 2048.
+    ///
+    /// A sprite that is wider than this gets a row to itself.
+    pub fn max_width(mut self, max_width: u32) -> SpriteSheetBuilder<'a> {
+        self.max_width = max_width;
+        self
+    }
+
+    /// The number of transparent pixels between sprites, which prevents neighboring sprites
+    /// from bleeding into each other when sampled. Defaults to 1.
+    pub fn padding(mut self, padding: u32) -> SpriteSheetBuilder<'a> {
+        self.padding = padding;
+        self
+    }
+
+    /// Render each group, trim its transparent borders and pack it into the sheet.
+    ///
+    /// Groups that are fully transparent are left out of the sheet.
+    pub fn pack(&self) -> Result<SpriteSheet, PsdError> {
+        let psd = self.psd;
+
+        let group_paths = match &self.group_paths {
+            Some(group_paths) => group_paths.clone(),
+            None => psd
+                .groups_in_order()
+                .filter(|group| group.parent_id().is_none())
+                .map(|group| group.name().to_string())
+                .collect(),
+        };
+
+        let mut sprites = vec![];
+        for path in group_paths {
+            let trimmed = psd.render().visible_only().group(&path).to_trimmed_rgba()?;
+            if trimmed.width() > 0 {
+                sprites.push((path, trimmed));
+            }
+        }
+
+        // Shelf packing: place the tallest sprites first, left to right, starting a new shelf
+        // when the current one is full.
+        let mut order: Vec<usize> = (0..sprites.len()).collect();
+        order.sort_by_key(|idx| std::cmp::Reverse(sprites[*idx].1.height()));
+
+        let mut positions = vec![(0, 0); sprites.len()];
+        let (mut x, mut y, mut shelf_height) = (0, 0, 0);
+        let (mut width, mut height) = (0, 0);
+
+        for idx in order {
+            let sprite = &sprites[idx].1;
+
+            if x > 0 && x + sprite.width() > self.max_width {
+                x = 0;
+                y += shelf_height + self.padding;
+                shelf_height = 0;
+            }
+
+            positions[idx] = (x, y);
+
+            width = width.max(x + sprite.width());
+            height = height.max(y + sprite.height());
+            shelf_height = shelf_height.max(sprite.height());
+            x += sprite.width() + self.padding;
+        }
+
+        let mut rgba = vec![0; width as usize * height as usize * 4];
+        let mut frames = Vec::with_capacity(sprites.len());
+
+        for ((name, sprite), (x, y)) in sprites.into_iter().zip(positions) {
+            let row_len = sprite.width() as usize * 4;
+
+            for (row_idx, row) in sprite.rgba().chunks_exact(row_len).enumerate() {
+                let start = ((y as usize + row_idx) * width as usize + x as usize) * 4;
+                rgba[start..start + row_len].copy_from_slice(row);
+            }
+
+            frames.push(SpriteFrame {
+                name,
+                x,
+                y,
+                width: sprite.width(),
+                height: sprite.height(),
+                offset: (sprite.left(), sprite.top()),
+            });
+        }
+
+        Ok(SpriteSheet {
+            width,
+            height,
+            rgba,
+            frames,
+        })
+    }
+}
+
+/// Groups that were rendered and packed into a single image, along with where each group
+/// ended up.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct SpriteSheet {
+    width: u32,
+    height: u32,
+    rgba: Vec<u8>,
+    frames: Vec<SpriteFrame>,
+}
+
+impl SpriteSheet {
+    /// The width of the sheet
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// The height of the sheet
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The RGBA pixels of the sheet
+    /// [ R,G,B,A, R,G,B,A, R,G,B,A, ...]
+    pub fn rgba(&self) -> &[u8] {
+        &self.rgba
+    }
+
+    /// Where each group was placed in the sheet, in the order that the groups were requested
+    pub fn frames(&self) -> &[SpriteFrame] {
+        &self.frames
+    }
+
+    /// An atlas describing the sheet for game engines, such as
+    ///
+    /// ```text
+    /// {"width":64,"height":32,"frames":[{"name":"Idle","x":0,"y":0,"width":32,"height":32,"offset":[4,0]}]}
+    /// ```
+    pub fn atlas_json(&self) -> String {
+        let mut json = String::new();
+        write!(
+            json,
+            r#"{{"width":{},"height":{},"frames":["#,
+            self.width, self.height
+        )
+        .unwrap();
+
+        for (idx, frame) in self.frames.iter().enumerate() {
+            if idx > 0 {
+                json.push(',');
+            }
+
+            write!(
+                json,
+                r#"{{"name":{},"x":{},"y":{},"width":{},"height":{},"offset":[{},{}]}}"#,
+                json_string(&frame.name),
+                frame.x,
+                frame.y,
+                frame.width,
+                frame.height,
+                frame.offset.0,
+                frame.offset.1
+            )
+            .unwrap();
+        }
+
+        json.push_str("]}");
+        json
+    }
+}
+
+/// Where a rendered group was placed in a [`SpriteSheet`].
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct SpriteFrame {
+    name: String,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    offset: (u32, u32),
+}
+
+impl SpriteFrame {
+    /// The path of the group that was rendered
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The left of the sprite in the sheet
+    pub fn x(&self) -> u32 {
+        self.x
+    }
+
+    /// The top of the sprite in the sheet
+    pub fn y(&self) -> u32 {
+        self.y
+    }
+
+    /// The width of the sprite
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// The height of the sprite
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The (left, top) of the sprite in the PSD, since transparent borders were trimmed
+    pub fn offset(&self) -> (u32, u32) {
+        self.offset
+    }
+}
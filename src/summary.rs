@@ -0,0 +1,147 @@
+use std::fmt;
+
+use crate::layer_tree::{layer_tree, LayerTreeNode};
+use crate::sections::layer_and_mask_information_section::layer::{BlendMode, LayerProperties};
+use crate::{Psd, PsdGroup, PsdLayer};
+
+/// The number of spaces that each level of the tree is indented by
+const INDENT: usize = 2;
+
+/// A compact, human readable tree of a PSD document's groups and layers.
+///
+/// Created by [`Psd::summary`]. `Psd`'s `Display` implementation writes the summary with no
+/// depth limit.
+///
+/// ```text
+/// 1x1 Rgb Eight, 1 layer, 1 group
+/// group "group" PassThrough 100% visible
+///   layer "First Layer" 1x1 at (0, 0) Normal 100% visible
+/// ```
+///
+/// [`Psd::summary`]: crate::Psd::summary
+#[derive(Debug, Clone, Copy)]
+pub struct PsdSummary<'a> {
+    psd: &'a Psd,
+    max_depth: Option<usize>,
+}
+
+impl<'a> PsdSummary<'a> {
+    pub(crate) fn new(psd: &'a Psd) -> PsdSummary<'a> {
+        PsdSummary {
+            psd,
+            max_depth: None,
+        }
+    }
+
+    /// Only show groups and layers that are nested at most `max_depth` groups deep.
+    ///
+    /// A depth of 0 only shows the top level of the document. Groups whose children are hidden
+    /// are followed by a `...` line.
+    pub fn max_depth(mut self, max_depth: usize) -> PsdSummary<'a> {
+        self.max_depth = Some(max_depth);
+        self
+    }
+}
+
+fn write_group(f: &mut fmt::Formatter, group: &PsdGroup, depth: usize) -> fmt::Result {
+    write!(
+        f,
+        "{:indent$}group \"{}\"",
+        "",
+        group.name(),
+        indent = depth * INDENT
+    )?;
+    write_properties(f, group, group.blend_mode(), group.opacity())
+}
+
+fn write_layer(f: &mut fmt::Formatter, layer: &PsdLayer, depth: usize) -> fmt::Result {
+    write!(
+        f,
+        "{:indent$}layer \"{}\" {}x{} at ({}, {})",
+        "",
+        layer.name(),
+        layer.width(),
+        layer.height(),
+        layer.layer_left(),
+        layer.layer_top(),
+        indent = depth * INDENT
+    )?;
+    write_properties(f, layer, layer.blend_mode(), layer.opacity())
+}
+
+/// Write the properties that groups and layers share, ending the line.
+///
+/// Groups read their blend mode and opacity from a different record than the rest of their
+/// properties, so those are passed in separately.
+fn write_properties(
+    f: &mut fmt::Formatter,
+    properties: &LayerProperties,
+    blend_mode: BlendMode,
+    opacity: u8,
+) -> fmt::Result {
+    let opacity = (opacity as f32 / 255. * 100.).round();
+    let visibility = if properties.visible() {
+        "visible"
+    } else {
+        "hidden"
+    };
+
+    writeln!(f, " {:?} {}% {}", blend_mode, opacity, visibility)
+}
+
+/// `"1 layer"`, `"2 layers"`
+fn count(count: usize, noun: &str) -> String {
+    if count == 1 {
+        format!("{} {}", count, noun)
+    } else {
+        format!("{} {}s", count, noun)
+    }
+}
+
+impl fmt::Display for PsdSummary<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let psd = self.psd;
+
+        writeln!(
+            f,
+            "{}x{} {:?} {:?}, {}, {}",
+            psd.width(),
+            psd.height(),
+            psd.color_mode(),
+            psd.depth(),
+            count(psd.layers().len(), "layer"),
+            count(psd.groups().len(), "group"),
+        )?;
+
+        for entry in layer_tree(psd) {
+            if self
+                .max_depth
+                .is_some_and(|max_depth| entry.depth > max_depth)
+            {
+                continue;
+            }
+
+            match entry.node {
+                LayerTreeNode::Group(group) => {
+                    write_group(f, group, entry.depth)?;
+
+                    let children_hidden = self
+                        .max_depth
+                        .is_some_and(|max_depth| entry.depth == max_depth);
+                    if children_hidden && !group.contained_layers.is_empty() {
+                        writeln!(f, "{:indent$}...", "", indent = (entry.depth + 1) * INDENT)?;
+                    }
+                }
+                LayerTreeNode::Layer(layer) => write_layer(f, layer, entry.depth)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Psd {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        PsdSummary::new(self).fmt(f)
+    }
+}
@@ -0,0 +1,720 @@
+//! Rotating, flipping, cropping and resizing the whole canvas: the final image, every layer and
+//! mask, the guides and the slices.
+
+use crate::psd_channel::{rle_compress_rows, rle_decompress, zip_decompress};
+use crate::resize::Resize;
+use crate::sections::file_header_section::{PsdDepth, PsdHeight, PsdVersion, PsdWidth};
+use crate::sections::image_data_section::{ChannelBytes, ImageDataSection};
+use crate::sections::image_resources_section::guides::GUIDE_UNITS_PER_PIXEL;
+use crate::sections::image_resources_section::RESOURCE_GRID_AND_GUIDES;
+use crate::sections::layer_and_mask_information_section::layer::{LayerProperties, PsdLayer};
+use crate::sections::layer_and_mask_information_section::layer_mask::MaskRect;
+use crate::sections::serialize::PsdBuffer;
+use crate::sections::PsdCursor;
+use crate::{Psd, PsdChannelCompression, PsdChannelKind};
+
+/// The zlib compression level that we re-compress ZIP compressed channels with
+const ZIP_COMPRESSION_LEVEL: u8 = 6;
+
+/// A rectangle of a PSD's canvas to crop it to.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct CropRect {
+    pub(crate) left: u32,
+    pub(crate) top: u32,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+}
+
+impl CropRect {
+    /// Create a rectangle whose top left corner is `left` pixels from the left of the canvas and
+    /// `top` pixels from the top.
+    pub fn new(left: u32, top: u32, width: u32, height: u32) -> CropRect {
+        CropRect {
+            left,
+            top,
+            width,
+            height,
+        }
+    }
+
+    /// The distance from the left of the canvas to the left of the rectangle
+    pub fn left(&self) -> u32 {
+        self.left
+    }
+
+    /// The distance from the top of the canvas to the top of the rectangle
+    pub fn top(&self) -> u32 {
+        self.top
+    }
+
+    /// The width of the rectangle
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// The height of the rectangle
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Whether the rectangle has pixels and lies within a canvas of the given width and height
+    pub(crate) fn fits(&self, width: u32, height: u32) -> bool {
+        self.width > 0
+            && self.height > 0
+            && self.left as u64 + self.width as u64 <= width as u64
+            && self.top as u64 + self.height as u64 <= height as u64
+    }
+
+    /// Copy the pixels inside the rectangle out of the RGBA pixels of a canvas of the given width
+    pub(crate) fn crop_rgba(&self, rgba: &[u8], width: u32) -> Vec<u8> {
+        let mut cropped = Vec::with_capacity(self.width as usize * self.height as usize * 4);
+        for top in self.top..self.top + self.height {
+            let start = (top as usize * width as usize + self.left as usize) * 4;
+            cropped.extend_from_slice(&rgba[start..start + self.width as usize * 4]);
+        }
+
+        cropped
+    }
+
+    fn mask_rect(&self) -> MaskRect {
+        MaskRect {
+            top: self.top as i32,
+            left: self.left as i32,
+            bottom: (self.top + self.height) as i32,
+            right: (self.left + self.width) as i32,
+        }
+    }
+}
+
+/// A way to rotate, flip, crop or resize the whole canvas.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub(crate) enum CanvasTransform {
+    /// Rotate 90° clockwise
+    Rotate90,
+    /// Rotate 180°
+    Rotate180,
+    /// Mirror from left to right
+    FlipHorizontal,
+    /// Keep only the pixels within a rectangle of the canvas
+    Crop(CropRect),
+    /// Resample the canvas to new dimensions
+    Resize(Resize),
+}
+
+impl CanvasTransform {
+    /// The width and height of an image with the given width and height once transformed
+    fn dimensions(self, width: usize, height: usize) -> (usize, usize) {
+        match self {
+            CanvasTransform::Rotate90 => (height, width),
+            CanvasTransform::Rotate180 | CanvasTransform::FlipHorizontal => (width, height),
+            CanvasTransform::Crop(crop) => (crop.width as usize, crop.height as usize),
+            CanvasTransform::Resize(resize) => {
+                (resize.scale_width(width), resize.scale_height(height))
+            }
+        }
+    }
+
+    /// Where the pixel at `x` and `y` ends up. Cropping and resizing don't move pixels within a
+    /// plane.
+    fn pixel(self, x: usize, y: usize, width: usize, height: usize) -> (usize, usize) {
+        match self {
+            CanvasTransform::Rotate90 => (height - 1 - y, x),
+            CanvasTransform::Rotate180 => (width - 1 - x, height - 1 - y),
+            CanvasTransform::FlipHorizontal => (width - 1 - x, y),
+            CanvasTransform::Crop(_) | CanvasTransform::Resize(_) => (x, y),
+        }
+    }
+
+    /// Move every sample of a plane of pixels that covers `rect` of the canvas to where its pixel
+    /// ends up, dropping the pixels that are cropped away and resampling resized planes.
+    ///
+    /// Pixels can be any number of bytes, which we work out from the length of the plane. A
+    /// plane whose length isn't a whole number of pixels is returned as is.
+    fn plane(self, samples: &[u8], rect: MaskRect) -> Vec<u8> {
+        let (width, height) = (rect.width() as usize, rect.height() as usize);
+        let pixel_count = width * height;
+        if pixel_count == 0 || !samples.len().is_multiple_of(pixel_count) {
+            return samples.to_vec();
+        }
+
+        let bytes_per_pixel = samples.len() / pixel_count;
+
+        if let CanvasTransform::Resize(resize) = self {
+            return resize.plane(samples, rect, bytes_per_pixel);
+        }
+
+        if let CanvasTransform::Crop(crop) = self {
+            let clipped = match intersection(rect, crop.mask_rect()) {
+                Some(clipped) => clipped,
+                None => return vec![],
+            };
+
+            let row_len = width * bytes_per_pixel;
+            let start = (clipped.left - rect.left) as usize * bytes_per_pixel;
+            let len = clipped.width() as usize * bytes_per_pixel;
+
+            return (clipped.top..clipped.bottom)
+                .flat_map(|y| {
+                    let row = (y - rect.top) as usize * row_len;
+                    samples[row + start..row + start + len].iter().copied()
+                })
+                .collect();
+        }
+
+        let (transformed_width, _) = self.dimensions(width, height);
+
+        let mut transformed = vec![0; samples.len()];
+        for (idx, pixel) in samples.chunks_exact(bytes_per_pixel).enumerate() {
+            let (x, y) = self.pixel(idx % width, idx / width, width, height);
+
+            let start = (y * transformed_width + x) * bytes_per_pixel;
+            transformed[start..start + bytes_per_pixel].copy_from_slice(pixel);
+        }
+
+        transformed
+    }
+
+    /// Where a rectangle within a canvas of the given width and height ends up. `bottom` and
+    /// `right` are exclusive.
+    fn rect(self, rect: MaskRect, width: i32, height: i32) -> MaskRect {
+        let MaskRect {
+            top,
+            left,
+            bottom,
+            right,
+        } = rect;
+
+        match self {
+            CanvasTransform::Rotate90 => MaskRect {
+                top: left,
+                left: height - bottom,
+                bottom: right,
+                right: height - top,
+            },
+            CanvasTransform::Rotate180 => MaskRect {
+                top: height - bottom,
+                left: width - right,
+                bottom: height - top,
+                right: width - left,
+            },
+            CanvasTransform::FlipHorizontal => MaskRect {
+                top,
+                left: width - right,
+                bottom,
+                right: width - left,
+            },
+            CanvasTransform::Crop(crop) => match intersection(rect, crop.mask_rect()) {
+                Some(clipped) => MaskRect {
+                    top: clipped.top - crop.top as i32,
+                    left: clipped.left - crop.left as i32,
+                    bottom: clipped.bottom - crop.top as i32,
+                    right: clipped.right - crop.left as i32,
+                },
+                None => MaskRect {
+                    top: 0,
+                    left: 0,
+                    bottom: 0,
+                    right: 0,
+                },
+            },
+            CanvasTransform::Resize(resize) => resize.rect(rect),
+        }
+    }
+
+    /// Where a guide ends up, given its position in 1/32nds of a pixel and whether it is
+    /// horizontal. Returns the new position and whether the guide is now horizontal.
+    ///
+    /// Cropping moves guides along with the pixels, even when they end up off the canvas.
+    fn guide(self, position: i32, is_horizontal: bool, width: i32, height: i32) -> (i32, bool) {
+        let width = width * GUIDE_UNITS_PER_PIXEL;
+        let height = height * GUIDE_UNITS_PER_PIXEL;
+        let (crop_left, crop_top) = match self {
+            CanvasTransform::Crop(crop) => (
+                crop.left as i32 * GUIDE_UNITS_PER_PIXEL,
+                crop.top as i32 * GUIDE_UNITS_PER_PIXEL,
+            ),
+            _ => (0, 0),
+        };
+
+        match (self, is_horizontal) {
+            (CanvasTransform::Rotate90, false) => (position, true),
+            (CanvasTransform::Rotate90, true) => (height - position, false),
+            (CanvasTransform::Rotate180, false) => (width - position, false),
+            (CanvasTransform::Rotate180, true) => (height - position, true),
+            (CanvasTransform::FlipHorizontal, false) => (width - position, false),
+            (CanvasTransform::FlipHorizontal, true) => (position, true),
+            (CanvasTransform::Crop(_), false) => (position - crop_left, false),
+            (CanvasTransform::Crop(_), true) => (position - crop_top, true),
+            (CanvasTransform::Resize(resize), _) => {
+                (resize.guide(position, is_horizontal), is_horizontal)
+            }
+        }
+    }
+}
+
+/// The part of two rectangles that they both cover, if any
+fn intersection(a: MaskRect, b: MaskRect) -> Option<MaskRect> {
+    let rect = MaskRect {
+        top: a.top.max(b.top),
+        left: a.left.max(b.left),
+        bottom: a.bottom.min(b.bottom),
+        right: a.right.min(b.right),
+    };
+
+    if rect.bottom > rect.top && rect.right > rect.left {
+        Some(rect)
+    } else {
+        None
+    }
+}
+
+/// Rotate, flip, crop or resize the final image, every layer and mask, the guides and the slices.
+pub(crate) fn transform_psd(psd: &mut Psd, transform: CanvasTransform) {
+    let width = psd.width() as usize;
+    let height = psd.height() as usize;
+    let (transformed_width, transformed_height) = transform.dimensions(width, height);
+    let header = &psd.file_header_section;
+    let (depth, version, channel_count) =
+        (header.depth, header.version, header.channel_count.count());
+
+    let canvas = Canvas {
+        transform,
+        width: width as i32,
+        height: height as i32,
+        transformed_width: transformed_width as u32,
+        transformed_height: transformed_height as u32,
+    };
+
+    let layer_and_mask = &mut psd.layer_and_mask_information_section;
+    for layer in layer_and_mask.layers.iter_mut() {
+        transform_layer(layer, &canvas, depth);
+    }
+    for group in layer_and_mask.groups.values_mut() {
+        // Groups don't have any pixels, so only their masks move
+        canvas.properties(&mut group.layer_properties, false);
+        canvas.properties(&mut group.folder_properties, false);
+    }
+
+    psd.image_data_section = transform_image_data(
+        &psd.image_data_section,
+        transform,
+        (width, height),
+        depth,
+        version,
+        channel_count,
+    );
+
+    for block in psd.image_resources_section.blocks.iter_mut() {
+        if block.resource_id == RESOURCE_GRID_AND_GUIDES {
+            transform_guides(&mut block.data, &canvas);
+        }
+    }
+
+    psd.image_resources_section
+        .map_slice_bounds(|rect| canvas.rect(rect));
+
+    psd.file_header_section.width = PsdWidth(transformed_width as u32);
+    psd.file_header_section.height = PsdHeight(transformed_height as u32);
+}
+
+/// The canvas that is being transformed
+struct Canvas {
+    transform: CanvasTransform,
+    width: i32,
+    height: i32,
+    transformed_width: u32,
+    transformed_height: u32,
+}
+
+impl Canvas {
+    /// Move the rectangles of a layer or group and of its masks
+    fn properties(&self, properties: &mut LayerProperties, has_pixels: bool) {
+        if has_pixels {
+            // The layer's bottom and right are inclusive
+            let rect = self.rect(layer_rect(properties));
+
+            properties.layer_top = rect.top;
+            properties.layer_left = rect.left;
+            properties.layer_bottom = rect.bottom - 1;
+            properties.layer_right = rect.right - 1;
+        }
+
+        if let Some(layer_mask) = properties.layer_mask.as_mut() {
+            layer_mask.rect = self.rect(layer_mask.rect);
+            if let Some(real) = layer_mask.real.as_mut() {
+                real.rect = self.rect(real.rect);
+            }
+
+            layer_mask.write_rects(&mut properties.record_data.mask_data);
+        }
+
+        properties.psd_width = self.transformed_width;
+        properties.psd_height = self.transformed_height;
+    }
+
+    /// Move a rectangle, leaving empty rectangles where they are
+    fn rect(&self, rect: MaskRect) -> MaskRect {
+        if rect.width() == 0 || rect.height() == 0 {
+            return rect;
+        }
+
+        self.transform.rect(rect, self.width, self.height)
+    }
+}
+
+/// The rectangle of the canvas that a layer's pixels cover. The layer's bottom and right are
+/// inclusive.
+fn layer_rect(properties: &LayerProperties) -> MaskRect {
+    MaskRect {
+        top: properties.layer_top,
+        left: properties.layer_left,
+        bottom: properties.layer_bottom + 1,
+        right: properties.layer_right + 1,
+    }
+}
+
+/// Move a layer's rectangle and the pixels of each of its channels.
+///
+/// Channels are compressed the same way that they were, except that ZIP compressed channels are
+/// compressed without prediction. Channels that are cropped away entirely become empty raw
+/// channels.
+fn transform_layer(layer: &mut PsdLayer, canvas: &Canvas, depth: PsdDepth) {
+    layer.clear_rgba_cache();
+
+    let PsdLayer {
+        channels,
+        scanline_byte_counts,
+        zip_compressed_channels,
+        layer_properties,
+        ..
+    } = layer;
+
+    let has_pixels = channels.values().any(|channel| !channel.bytes().is_empty());
+
+    for (kind, channel) in channels.iter_mut() {
+        // Masks have their own rectangle
+        let mask = layer_properties.layer_mask.as_ref();
+        let rect = match kind {
+            PsdChannelKind::UserSuppliedLayerMask => mask.map(|mask| mask.rect()),
+            PsdChannelKind::RealUserSuppliedLayerMask => mask
+                .and_then(|mask| mask.real_user_mask())
+                .map(|real| real.rect()),
+            _ if has_pixels => Some(layer_rect(layer_properties)),
+            _ => None,
+        };
+        let rect = match rect {
+            Some(rect) if rect.width() > 0 && rect.height() > 0 => rect,
+            _ => continue,
+        };
+        let transform = canvas.transform;
+        let transformed_height = canvas.rect(rect).height() as usize;
+
+        if let Some((compression, zip_data)) = zip_compressed_channels.get_mut(kind) {
+            if let Some(samples) =
+                zip_decompress(zip_data, *compression, rect.width() as usize, depth)
+            {
+                let samples = transform.plane(&samples, rect);
+
+                *zip_data =
+                    miniz_oxide::deflate::compress_to_vec_zlib(&samples, ZIP_COMPRESSION_LEVEL);
+                *compression = PsdChannelCompression::ZipWithoutPrediction;
+            }
+        }
+
+        *channel = match channel {
+            ChannelBytes::RawData(samples) => {
+                ChannelBytes::RawData(transform.plane(samples, rect).into())
+            }
+            ChannelBytes::RleCompressed(compressed) => {
+                let samples = transform.plane(&rle_decompress(compressed), rect);
+
+                let (compressed, byte_counts) =
+                    rle_compress_rows(&samples, samples.len() / transformed_height.max(1));
+                scanline_byte_counts.insert(*kind, byte_counts);
+
+                ChannelBytes::RleCompressed(compressed.into())
+            }
+        };
+
+        if channel.bytes().is_empty() {
+            zip_compressed_channels.remove(kind);
+            scanline_byte_counts.remove(kind);
+            *channel = ChannelBytes::RawData(vec![].into());
+        }
+    }
+
+    canvas.properties(layer_properties, has_pixels);
+}
+
+/// Move the pixels of every channel of the final image.
+///
+/// The image is compressed the same way that it was, except that a ZIP compressed image is
+/// compressed without prediction. The new image data is parsed the same way that it would be
+/// when reading a PSD.
+fn transform_image_data(
+    image_data: &ImageDataSection,
+    transform: CanvasTransform,
+    (width, height): (usize, usize),
+    depth: PsdDepth,
+    version: PsdVersion,
+    channel_count: u8,
+) -> ImageDataSection {
+    let channels = image_data.samples(width as u32, depth, channel_count);
+
+    let (transformed_width, transformed_height) = transform.dimensions(width, height);
+    let channels: Vec<Vec<u8>> = channels
+        .iter()
+        .map(|channel| {
+            let channel = transform.plane(
+                channel,
+                MaskRect {
+                    top: 0,
+                    left: 0,
+                    bottom: height as i32,
+                    right: width as i32,
+                },
+            );
+
+            match depth {
+                PsdDepth::One => pack_bits(&channel, transformed_width),
+                _ => channel,
+            }
+        })
+        .collect();
+
+    let compression = match image_data.compression {
+        PsdChannelCompression::ZipWithPrediction => PsdChannelCompression::ZipWithoutPrediction,
+        compression => compression,
+    };
+
+    let mut buffer = PsdBuffer::new();
+    buffer.write_u16(compression as u16);
+    match compression {
+        PsdChannelCompression::RawData => {
+            for channel in channels.iter() {
+                buffer.write(channel);
+            }
+        }
+        PsdChannelCompression::RleCompressed => {
+            let compressed: Vec<(Vec<u8>, Vec<u32>)> = channels
+                .iter()
+                .map(|channel| {
+                    rle_compress_rows(channel, channel.len() / transformed_height.max(1))
+                })
+                .collect();
+
+            for (_, byte_counts) in compressed.iter() {
+                for byte_count in byte_counts.iter() {
+                    buffer.write_rle_byte_count(version, *byte_count);
+                }
+            }
+            for (channel, _) in compressed.iter() {
+                buffer.write(channel);
+            }
+        }
+        PsdChannelCompression::ZipWithoutPrediction | PsdChannelCompression::ZipWithPrediction => {
+            buffer.write(&miniz_oxide::deflate::compress_to_vec_zlib(
+                &channels.concat(),
+                ZIP_COMPRESSION_LEVEL,
+            ));
+        }
+    }
+
+    ImageDataSection::from_bytes(
+        &buffer.into_inner(),
+        depth,
+        version,
+        transformed_width as u32,
+        transformed_height as u32,
+        channel_count,
+    )
+    .expect("Image data that we encoded ourselves is valid")
+}
+
+/// Pack one byte per pixel back into one bit per pixel, the inverse of unpacking a 1 bit image.
+/// Each row is padded to a whole number of bytes and black pixels are set bits. Resampling can
+/// leave gray pixels, which become black when they are darker than middle gray.
+fn pack_bits(pixels: &[u8], width: usize) -> Vec<u8> {
+    if width == 0 {
+        return vec![];
+    }
+
+    pixels
+        .chunks(width)
+        .flat_map(|row| {
+            let mut packed = vec![0; width.div_ceil(8)];
+            for (idx, pixel) in row.iter().enumerate() {
+                if *pixel < 128 {
+                    packed[idx / 8] |= 0x80 >> (idx % 8);
+                }
+            }
+
+            packed
+        })
+        .collect()
+}
+
+/// Move every guide in the grid and guides resource.
+///
+/// # [Adobe Docs](https://www.adobe.com/devnet-apps/photoshop/fileformatashtml/)
+///
+/// | Length | Description                                                       |
+/// |--------|-------------------------------------------------------------------|
+/// | 4      | Version ( = 1)                                                    |
+/// | 8      | Future implementation of document-specific grids (4 bytes horizontal, 4 bytes vertical). |
+/// | 4      | Number of guide resource blocks (can be 0).                       |
+/// | 5 * n  | Guide resource blocks: 4 bytes location of guide in document coordinates, 1 byte direction of guide: VHSelect (0 = vertical, 1 = horizontal) |
+fn transform_guides(data: &mut [u8], canvas: &Canvas) {
+    if data.len() < 16 {
+        return;
+    }
+
+    let guide_count = PsdCursor::new(&data[12..16]).read_u32() as usize;
+
+    for guide in data[16..].chunks_exact_mut(5).take(guide_count) {
+        let position = i32::from_be_bytes([guide[0], guide[1], guide[2], guide[3]]);
+        let is_horizontal = guide[4] == 1;
+
+        let (position, is_horizontal) =
+            canvas
+                .transform
+                .guide(position, is_horizontal, canvas.width, canvas.height);
+
+        guide[0..4].copy_from_slice(&position.to_be_bytes());
+        guide[4] = is_horizontal as u8;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Verify where each transform moves the pixels of a 3x2 image.
+    #[test]
+    fn transforms_planes() {
+        // 1 2 3
+        // 4 5 6
+        let plane = [1, 2, 3, 4, 5, 6];
+
+        assert_eq!(
+            CanvasTransform::Rotate90.plane(&plane, rect(0, 0, 2, 3)),
+            vec![4, 1, 5, 2, 6, 3]
+        );
+        assert_eq!(
+            CanvasTransform::Rotate180.plane(&plane, rect(0, 0, 2, 3)),
+            vec![6, 5, 4, 3, 2, 1]
+        );
+        assert_eq!(
+            CanvasTransform::FlipHorizontal.plane(&plane, rect(0, 0, 2, 3)),
+            vec![3, 2, 1, 6, 5, 4]
+        );
+
+        // Two bytes per pixel
+        assert_eq!(
+            CanvasTransform::FlipHorizontal.plane(&[1, 2, 3, 4], rect(0, 0, 1, 2)),
+            vec![3, 4, 1, 2]
+        );
+    }
+
+    /// Verify that cropping keeps only the part of a plane within the crop rectangle, wherever
+    /// the plane is on the canvas.
+    #[test]
+    fn crops_planes() {
+        // 1 2 3
+        // 4 5 6
+        let plane = [1, 2, 3, 4, 5, 6];
+        let crop = CanvasTransform::Crop(CropRect::new(2, 1, 2, 2));
+
+        // The plane covers x = 1..4 and y = 1..3, so the crop keeps its two right columns
+        assert_eq!(crop.plane(&plane, rect(1, 1, 3, 4)), vec![2, 3, 5, 6]);
+        assert_eq!(crop.rect(rect(1, 1, 3, 4), 10, 10), rect(0, 0, 2, 2));
+
+        // Two bytes per pixel
+        assert_eq!(
+            crop.plane(&[1, 2, 3, 4, 5, 6, 7, 8], rect(1, 0, 2, 4)),
+            vec![5, 6, 7, 8]
+        );
+
+        // A plane outside of the crop rectangle is dropped entirely
+        assert_eq!(crop.plane(&plane, rect(5, 5, 7, 8)), Vec::<u8>::new());
+        assert_eq!(crop.rect(rect(5, 5, 7, 8), 10, 10), rect(0, 0, 0, 0));
+    }
+
+    fn rect(top: i32, left: i32, bottom: i32, right: i32) -> MaskRect {
+        MaskRect {
+            top,
+            left,
+            bottom,
+            right,
+        }
+    }
+
+    /// Verify that rotating a rectangle by 90° four times gives back the same rectangle.
+    #[test]
+    fn rotates_rects() {
+        let rect = MaskRect {
+            top: 1,
+            left: 2,
+            bottom: 3,
+            right: 5,
+        };
+
+        let rotated = CanvasTransform::Rotate90.rect(rect, 10, 4);
+        assert_eq!(
+            rotated,
+            MaskRect {
+                top: 2,
+                left: 1,
+                bottom: 5,
+                right: 3,
+            }
+        );
+
+        let mut rect_back = rect;
+        let (mut width, mut height) = (10, 4);
+        for _ in 0..4 {
+            rect_back = CanvasTransform::Rotate90.rect(rect_back, width, height);
+            std::mem::swap(&mut width, &mut height);
+        }
+        assert_eq!(rect_back, rect);
+    }
+
+    /// Verify that vertical guides become horizontal guides when rotating, and that flipping
+    /// mirrors vertical guides.
+    #[test]
+    fn transforms_guides() {
+        let mut data = vec![];
+        data.extend_from_slice(&1u32.to_be_bytes());
+        data.extend_from_slice(&[0; 8]);
+        data.extend_from_slice(&2u32.to_be_bytes());
+        // A vertical guide at x = 1 and a horizontal guide at y = 3
+        data.extend_from_slice(&32i32.to_be_bytes());
+        data.push(0);
+        data.extend_from_slice(&96i32.to_be_bytes());
+        data.push(1);
+
+        let canvas = |transform| Canvas {
+            transform,
+            width: 10,
+            height: 4,
+            transformed_width: 0,
+            transformed_height: 0,
+        };
+
+        let mut rotated = data.clone();
+        transform_guides(&mut rotated, &canvas(CanvasTransform::Rotate90));
+        assert_eq!(&rotated[16..21], &[0, 0, 0, 32, 1]);
+        assert_eq!(&rotated[21..26], &[0, 0, 0, 32, 0]);
+
+        let mut flipped = data.clone();
+        transform_guides(&mut flipped, &canvas(CanvasTransform::FlipHorizontal));
+        assert_eq!(&flipped[16..21], &[0, 0, 1, 32, 0]);
+        assert_eq!(&flipped[21..26], &[0, 0, 0, 96, 1]);
+
+        let mut cropped = data.clone();
+        let crop = CropRect::new(2, 1, 4, 2);
+        transform_guides(&mut cropped, &canvas(CanvasTransform::Crop(crop)));
+        assert_eq!(&cropped[16..21], &[255, 255, 255, 224, 0]);
+        assert_eq!(&cropped[21..26], &[0, 0, 0, 64, 1]);
+    }
+}
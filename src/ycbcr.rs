@@ -0,0 +1,51 @@
+use crate::blend::Pixel;
+
+/// The BT.709 luma coefficient of red
+const KR: f32 = 0.2126;
+/// The BT.709 luma coefficient of blue
+const KB: f32 = 0.0722;
+
+/// In video range, Y is stored in [16, 235]
+const Y_OFFSET: f32 = 16.;
+const Y_RANGE: f32 = 219.;
+/// In video range, Cb and Cr are stored in [16, 240] centered around 128
+const C_OFFSET: f32 = 128.;
+const C_RANGE: f32 = 224.;
+
+/// Convert an RGB pixel into 8-bit, video range BT.709 [Y, Cb, Cr].
+///
+/// The alpha channel is ignored.
+pub(crate) fn rgb_to_ycbcr(pixel: Pixel) -> [u8; 3] {
+    let r = pixel[0] as f32 / 255.;
+    let g = pixel[1] as f32 / 255.;
+    let b = pixel[2] as f32 / 255.;
+
+    let y = KR * r + (1. - KR - KB) * g + KB * b;
+    let cb = (b - y) / (2. * (1. - KB));
+    let cr = (r - y) / (2. * (1. - KR));
+
+    [
+        quantize(Y_OFFSET + Y_RANGE * y),
+        quantize(C_OFFSET + C_RANGE * cb),
+        quantize(C_OFFSET + C_RANGE * cr),
+    ]
+}
+
+fn quantize(value: f32) -> u8 {
+    value.round().clamp(0., 255.) as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Verify that we match the BT.709 video range values of common colors.
+    #[test]
+    fn bt709_video_range() {
+        assert_eq!(rgb_to_ycbcr([0, 0, 0, 255]), [16, 128, 128]);
+        assert_eq!(rgb_to_ycbcr([255, 255, 255, 255]), [235, 128, 128]);
+        assert_eq!(rgb_to_ycbcr([255, 0, 0, 255]), [63, 102, 240]);
+        assert_eq!(rgb_to_ycbcr([0, 255, 0, 255]), [173, 42, 26]);
+        assert_eq!(rgb_to_ycbcr([0, 0, 255, 255]), [32, 240, 118]);
+    }
+}
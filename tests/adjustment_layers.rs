@@ -0,0 +1,128 @@
+use anyhow::Result;
+use psd::{Adjustment, Psd};
+
+const ADJUSTMENTS: &[u8] = include_bytes!("./fixtures/adjustment-layers/adjustments-2x2.psd");
+
+/// Verify that we read the settings of each adjustment layer that we parse.
+///
+/// cargo test --test adjustment_layers adjustment_layers -- --exact
+#[test]
+fn adjustment_layers() -> Result<()> {
+    let psd = Psd::from_bytes(ADJUSTMENTS)?;
+    let adjustment = |name: &str| psd.layer_by_name(name).unwrap().adjustment().unwrap();
+
+    match adjustment("Brightness") {
+        Adjustment::BrightnessContrast(brightness_contrast) => {
+            assert_eq!(brightness_contrast.brightness(), 30);
+            assert_eq!(brightness_contrast.contrast(), -20);
+            assert_eq!(brightness_contrast.mean(), 127);
+            assert!(!brightness_contrast.lab_only());
+        }
+        other => panic!("Expected brightness/contrast, got {:?}", other),
+    };
+
+    match adjustment("Levels") {
+        Adjustment::Levels(levels) => {
+            assert_eq!(levels.records().len(), 29);
+
+            let composite = levels.composite();
+            assert_eq!(composite.input_floor(), 10);
+            assert_eq!(composite.input_ceiling(), 240);
+            assert_eq!(composite.output_floor(), 5);
+            assert_eq!(composite.output_ceiling(), 250);
+            assert_eq!(composite.gamma(), 1.5);
+
+            assert_eq!(levels.records()[1].input_ceiling(), 200);
+        }
+        other => panic!("Expected levels, got {:?}", other),
+    };
+
+    match adjustment("Curves") {
+        Adjustment::Curves(curves) => {
+            let curves = curves.curves();
+            assert_eq!(curves.len(), 2);
+            assert_eq!(curves[0].channel(), 0);
+            assert_eq!(curves[0].points(), &[(0, 0), (128, 140), (255, 255)]);
+            assert_eq!(curves[1].channel(), 3);
+            assert_eq!(curves[1].points(), &[(0, 20), (255, 255)]);
+        }
+        other => panic!("Expected curves, got {:?}", other),
+    };
+
+    match adjustment("Hue") {
+        Adjustment::HueSaturation(hue_saturation) => {
+            assert!(!hue_saturation.colorize());
+            assert_eq!(hue_saturation.colorization().hue(), 25);
+            assert_eq!(hue_saturation.master().hue(), -30);
+            assert_eq!(hue_saturation.master().saturation(), 40);
+            assert_eq!(hue_saturation.master().lightness(), 10);
+            assert_eq!(hue_saturation.ranges().len(), 6);
+            assert_eq!(hue_saturation.ranges()[1].range(), [15, 45, 75, 105]);
+            assert_eq!(hue_saturation.ranges()[5].settings().hue(), 5);
+        }
+        other => panic!("Expected hue/saturation, got {:?}", other),
+    };
+
+    match adjustment("Exposure") {
+        Adjustment::Exposure(exposure) => {
+            assert_eq!(exposure.exposure(), 1.5);
+            assert_eq!(exposure.offset(), -0.25);
+            assert_eq!(exposure.gamma(), 0.8);
+        }
+        other => panic!("Expected exposure, got {:?}", other),
+    };
+
+    assert_eq!(adjustment("Invert"), Adjustment::Other(*b"nvrt"));
+
+    Ok(())
+}
+
+/// Layers with pixels aren't adjustment layers.
+///
+/// cargo test --test adjustment_layers no_adjustment -- --exact
+#[test]
+fn no_adjustment() -> Result<()> {
+    let psd = Psd::from_bytes(include_bytes!("./fixtures/green-1x1.psd"))?;
+    assert!(psd.layers()[0].adjustment().is_none());
+
+    Ok(())
+}
+
+/// Render the `Base` layer with one of the adjustment layers above it.
+fn render_adjusted(psd: &Psd, adjustment: &'static str) -> Result<Vec<u8>> {
+    Ok(psd
+        .render()
+        .filter(move |(_, layer)| layer.name() == "Base" || layer.name() == adjustment)
+        .adjustment_layers()
+        .to_rgba()?)
+}
+
+/// Verify that adjustment layers are applied to the pixels below them when rendering with
+/// `RenderPipeline::adjustment_layers`, and only where their masks show them.
+///
+/// cargo test --test adjustment_layers render_adjustment_layers -- --exact
+#[test]
+fn render_adjustment_layers() -> Result<()> {
+    let psd = include_bytes!("./fixtures/adjustment-layers/render-adjustments-2x1.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let base = vec![200, 100, 50, 255, 50, 150, 250, 255];
+    assert_eq!(psd.render().to_rgba()?, base);
+    assert_eq!(render_adjusted(&psd, "Brightness")?, base);
+
+    assert_eq!(
+        render_adjusted(&psd, "Levels")?,
+        vec![100, 50, 25, 255, 25, 75, 125, 255]
+    );
+    // The curves layer's mask hides it from the right pixel
+    assert_eq!(
+        render_adjusted(&psd, "Curves")?,
+        vec![55, 155, 205, 255, 50, 150, 250, 255]
+    );
+    assert_eq!(
+        render_adjusted(&psd, "Hue")?,
+        vec![50, 150, 200, 255, 250, 150, 50, 255]
+    );
+
+    Ok(())
+}
@@ -0,0 +1,34 @@
+use anyhow::Result;
+use psd::{ColorMode, Psd, PsdDepth};
+
+/// The grey value of each pixel in the bitmap mode fixtures
+const PIXELS: [u8; 20] = [
+    0, 255, 0, 255, 0, 255, 0, 255, 0, 255, //
+    0, 0, 0, 0, 0, 0, 0, 0, 255, 255,
+];
+
+/// Each bit of a 1 bit image is expanded into an opaque grey pixel.
+///
+/// cargo test --test bitmap_mode unpack_bits -- --exact
+#[test]
+fn unpack_bits() -> Result<()> {
+    for psd in [
+        &include_bytes!("./fixtures/bitmap-mode/bitmap-10x2-raw.psd")[..],
+        &include_bytes!("./fixtures/bitmap-mode/bitmap-10x2-rle.psd")[..],
+    ]
+    .iter()
+    {
+        let psd = Psd::from_bytes(psd)?;
+
+        assert_eq!(psd.color_mode(), ColorMode::Bitmap);
+        assert_eq!(psd.depth(), PsdDepth::One);
+
+        let expected: Vec<u8> = PIXELS
+            .iter()
+            .flat_map(|grey| [*grey, *grey, *grey, 255])
+            .collect();
+        assert_eq!(psd.rgba(), expected);
+    }
+
+    Ok(())
+}
@@ -2,7 +2,7 @@
 //! (PathBuf, [f32; 4])
 
 use anyhow::Result;
-use psd::Psd;
+use psd::{BlendMode, Psd};
 
 const BLEND_NORMAL_BLUE_RED_PIXEL: [u8; 4] = [85, 0, 170, 192];
 const BLEND_MULTIPLY_BLUE_RED_PIXEL: [u8; 4] = [85, 0, 85, 192];
@@ -222,3 +222,65 @@ fn exclusion() -> Result<()> {
 
     Ok(())
 }
+
+/// cargo test --test blend group_pass_through -- --exact
+#[test]
+fn group_pass_through() -> Result<()> {
+    let psd = include_bytes!("./fixtures/group-blending/multiply-child-in-pass-through-group.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let image = psd.flatten_layers_rgba(&|_| true)?;
+    assert_eq!(image[0..4], [0, 0, 0, 255]);
+
+    Ok(())
+}
+
+/// A group that doesn't pass through composites its children on their own, so the multiply layer
+/// inside of it doesn't multiply with the blue layer below the group.
+///
+/// cargo test --test blend group_isolates_children -- --exact
+#[test]
+fn group_isolates_children() -> Result<()> {
+    let psd = include_bytes!("./fixtures/group-blending/multiply-child-in-normal-group.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let image = psd.flatten_layers_rgba(&|_| true)?;
+    assert_eq!(image[0..4], [255, 0, 0, 255]);
+
+    Ok(())
+}
+
+/// cargo test --test blend group_opacity -- --exact
+#[test]
+fn group_opacity() -> Result<()> {
+    for psd in [
+        &include_bytes!("./fixtures/group-blending/normal-group-opacity-50.psd")[..],
+        &include_bytes!("./fixtures/group-blending/pass-through-group-opacity-50.psd")[..],
+    ]
+    .iter()
+    {
+        let psd = Psd::from_bytes(psd)?;
+
+        let image = psd.flatten_layers_rgba(&|_| true)?;
+        assert_eq!(image[0..4], [128, 0, 127, 255]);
+    }
+
+    Ok(())
+}
+
+/// cargo test --test blend group_blend_mode -- --exact
+#[test]
+fn group_blend_mode() -> Result<()> {
+    let psd = include_bytes!("./fixtures/group-blending/multiply-group.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    assert_eq!(
+        psd.groups_in_order().next().unwrap().blend_mode(),
+        BlendMode::Multiply
+    );
+
+    let image = psd.flatten_layers_rgba(&|_| true)?;
+    assert_eq!(image[0..4], [0, 0, 0, 255]);
+
+    Ok(())
+}
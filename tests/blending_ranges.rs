@@ -0,0 +1,55 @@
+use anyhow::Result;
+use psd::{FidelityIssueKind, Psd};
+
+const BLEND_IF: &[u8] = include_bytes!("./fixtures/blending-ranges/blend-if-2x1.psd");
+
+/// Verify that we read the gray and per channel "Blend If" ranges of a layer.
+///
+/// cargo test --test blending_ranges read_blending_ranges -- --exact
+#[test]
+fn read_blending_ranges() -> Result<()> {
+    let psd = Psd::from_bytes(BLEND_IF)?;
+
+    assert_eq!(psd.layer_by_name("Base").unwrap().blending_ranges(), None);
+
+    let ranges = psd.layer_by_name("Red").unwrap().blending_ranges().unwrap();
+    assert!(!ranges.is_default());
+
+    let gray = ranges.gray();
+    assert!(gray.source().is_default());
+    assert_eq!(gray.destination().black(), (0, 0));
+    assert_eq!(gray.destination().white(), (128, 128));
+
+    assert_eq!(ranges.channels().len(), 3);
+    assert!(ranges.channels()[0].is_default());
+    let green = ranges.channels()[1].source();
+    assert_eq!(green.black(), (0, 0));
+    assert_eq!(green.white(), (200, 255));
+
+    Ok(())
+}
+
+/// The red layer is hidden where the pixel below it is lighter than 128, but only when rendering
+/// with the blending ranges.
+///
+/// cargo test --test blending_ranges render_blending_ranges -- --exact
+#[test]
+fn render_blending_ranges() -> Result<()> {
+    let psd = Psd::from_bytes(BLEND_IF)?;
+
+    let rgba = psd.render().blending_ranges().to_rgba()?;
+    assert_eq!(rgba, [[255, 0, 0, 255], [255; 4]].concat());
+
+    let rgba = psd.render().to_rgba()?;
+    assert_eq!(rgba, [255, 0, 0, 255].repeat(2));
+
+    let kinds: Vec<_> = psd
+        .render_fidelity_report()
+        .issues()
+        .iter()
+        .map(|issue| issue.kind())
+        .collect();
+    assert_eq!(kinds, vec![FidelityIssueKind::BlendingRanges]);
+
+    Ok(())
+}
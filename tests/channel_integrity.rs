@@ -0,0 +1,88 @@
+use anyhow::Result;
+use psd::{ChannelCorruption, Psd, PsdChannelCompression, PsdChannelKind, PsdError, PsdLayerError};
+
+const RLE_ROW: &[u8] = include_bytes!("./fixtures/corrupt-channels/rle-row-2x2.psd");
+
+/// Verify that a row of an RLE compressed channel that decodes to the wrong length is reported
+/// along with its layer and channel.
+///
+/// cargo test --test channel_integrity corrupt_rle_row -- --exact
+#[test]
+fn corrupt_rle_row() -> Result<()> {
+    let psd = Psd::from_bytes(RLE_ROW)?;
+
+    let report = psd.verify_layer_channels();
+    assert!(!report.is_ok());
+    assert!(report.checksums().is_empty());
+
+    let corrupt = report.corrupt_channels();
+    assert_eq!(corrupt.len(), 1);
+    assert_eq!(corrupt[0].layer_idx(), 0);
+    assert_eq!(corrupt[0].layer_name(), "Corrupt");
+    assert_eq!(corrupt[0].channel(), PsdChannelKind::Green);
+    assert_eq!(
+        corrupt[0].compression(),
+        PsdChannelCompression::RleCompressed
+    );
+    assert_eq!(
+        corrupt[0].corruption(),
+        ChannelCorruption::RowLength {
+            row: 1,
+            expected: 2,
+            actual: 1
+        }
+    );
+
+    Ok(())
+}
+
+/// Verify that every channel that decodes is checksummed, and that the checksums don't change
+/// when the PSD is written back out.
+///
+/// cargo test --test channel_integrity checksums -- --exact
+#[test]
+fn checksums() -> Result<()> {
+    let psd = Psd::from_bytes(RLE_ROW)?;
+
+    let report = psd.verify_layer_channels_with_checksums();
+    assert_eq!(report.corrupt_channels().len(), 1);
+    // Four channels of the intact layer and three of the corrupt one
+    assert_eq!(report.checksums().len(), 7);
+
+    let red = report
+        .checksums()
+        .iter()
+        .find(|checksum| {
+            checksum.layer_name() == "Intact" && checksum.channel() == PsdChannelKind::Red
+        })
+        .unwrap();
+    assert_eq!(red.layer_idx(), 1);
+    assert_eq!(red.len(), 4);
+    // The CRC-32 of [1, 2, 3, 4]
+    assert_eq!(red.crc32(), 0xB63C_FBCD);
+
+    let written = Psd::from_bytes(&psd.to_bytes())?;
+    assert_eq!(written.verify_layer_channels_with_checksums(), report);
+
+    let green = Psd::from_bytes(include_bytes!("./fixtures/green-1x1.psd"))?;
+    assert!(green.verify_layer_channels().is_ok());
+
+    Ok(())
+}
+
+/// ZIP compressed channels are decompressed while parsing, so a corrupt zlib stream fails the
+/// parse with an error that names its layer and channel.
+///
+/// cargo test --test channel_integrity corrupt_zip_stream -- --exact
+#[test]
+fn corrupt_zip_stream() {
+    let psd = include_bytes!("./fixtures/corrupt-channels/zip-1x1.psd");
+
+    assert_eq!(
+        Psd::from_bytes(psd).unwrap_err(),
+        PsdError::LayerError(PsdLayerError::InvalidZipData {
+            layer: "Zipped".to_string(),
+            channel: PsdChannelKind::Red,
+        })
+    );
+}
@@ -1,6 +1,7 @@
 use anyhow::Result;
 use psd::ColorMode;
 use psd::Psd;
+use psd::PsdChannelKind;
 use psd::PsdDepth;
 
 /// cargo test --test channels one_channel_grayscale_raw_data -- --exact
@@ -60,3 +61,120 @@ fn two_channel_grayscale_raw_data() -> Result<()> {
 
     Ok(())
 }
+
+/// Verify that we can stream an RLE compressed layer channel one row at a time.
+///
+/// cargo test --test channels for_each_decoded_row_rle -- --exact
+#[test]
+fn for_each_decoded_row_rle() -> Result<()> {
+    let psd = include_bytes!("./fixtures/16x16-rle-partially-opaque.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let layer = psd.layer_by_name("OpaqueCenter").unwrap();
+
+    let mut red_rows = vec![];
+    layer.for_each_decoded_row(PsdChannelKind::Red, |row_idx, row| {
+        red_rows.push((row_idx, row.to_vec()));
+    })?;
+
+    let expected: Vec<(usize, Vec<u8>)> = (0..9).map(|row_idx| (row_idx, vec![255; 9])).collect();
+    assert_eq!(red_rows, expected);
+
+    let mut green_row_count = 0;
+    layer.for_each_decoded_row(PsdChannelKind::Green, |_, row| {
+        assert_eq!(row, &[0; 9]);
+        green_row_count += 1;
+    })?;
+    assert_eq!(green_row_count, 9);
+
+    Ok(())
+}
+
+/// Verify that a corrupt RLE row doesn't shift the rows after it when streaming a layer channel.
+///
+/// cargo test --test channels for_each_decoded_row_rle_corrupt_row -- --exact
+#[test]
+fn for_each_decoded_row_rle_corrupt_row() -> Result<()> {
+    let mut psd = include_bytes!("./fixtures/16x16-rle-partially-opaque.psd").to_vec();
+
+    // OpaqueCenter's transparency and red channels are both nine rows of `[-8, 255]`, with red
+    // stored second. Turn row 4's header into a two byte literal, which reads past the end of the
+    // row without changing its byte count.
+    let red_rows = [0xf8, 0xff].repeat(9);
+    let red_channel = psd
+        .windows(red_rows.len())
+        .rposition(|bytes| bytes == red_rows.as_slice())
+        .unwrap();
+    psd[red_channel + 4 * 2] = 0x01;
+
+    let psd = Psd::from_bytes(&psd)?;
+    let layer = psd.layer_by_name("OpaqueCenter").unwrap();
+
+    let mut red_rows = vec![];
+    layer.for_each_decoded_row(PsdChannelKind::Red, |row_idx, row| {
+        red_rows.push((row_idx, row.to_vec()));
+    })?;
+
+    assert_eq!(red_rows.len(), 9);
+    for (row_idx, row) in red_rows {
+        if row_idx != 4 {
+            assert_eq!(row, vec![255; 9], "row {}", row_idx);
+        }
+    }
+
+    Ok(())
+}
+
+/// Verify that we can stream a raw data layer channel one row at a time.
+///
+/// cargo test --test channels for_each_decoded_row_raw_data -- --exact
+#[test]
+fn for_each_decoded_row_raw_data() -> Result<()> {
+    let psd = include_bytes!("./fixtures/green-1x1.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let layer = psd.layer_by_name("First Layer").unwrap();
+
+    let mut rows = vec![];
+    layer.for_each_decoded_row(PsdChannelKind::Green, |row_idx, row| {
+        rows.push((row_idx, row.to_vec()));
+    })?;
+    assert_eq!(rows, vec![(0, vec![255])]);
+
+    assert!(layer
+        .for_each_decoded_row(PsdChannelKind::UserSuppliedLayerMask, |_, _| {})
+        .is_err());
+
+    Ok(())
+}
+
+/// Verify that we can get a layer's average color and histogram without generating its RGBA.
+///
+/// cargo test --test channels layer_color_stats -- --exact
+#[test]
+fn layer_color_stats() -> Result<()> {
+    let psd = include_bytes!("./fixtures/rle-3-layer-8x8.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let red = psd.layer_by_name("Red Layer").unwrap();
+    assert_eq!(red.average_color(), Some([255, 0, 0]));
+
+    let histogram = red.histogram();
+    assert_eq!(histogram.red()[255], 64);
+    assert_eq!(histogram.green()[0], 64);
+    assert_eq!(histogram.alpha()[255], 64);
+
+    // Only the opaque pixels contribute to the average
+    let psd = include_bytes!("./fixtures/3x3-opaque-center.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let layer = psd.layer_by_name("OpaqueCenter").unwrap();
+    let histogram = layer.histogram();
+    let visible: u32 = histogram.red().iter().sum();
+    let total: u32 = histogram.alpha().iter().sum();
+    assert_eq!(total, 4);
+    assert_eq!(visible, total - histogram.alpha()[0]);
+    assert!(layer.average_color().is_some());
+
+    Ok(())
+}
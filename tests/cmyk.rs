@@ -0,0 +1,47 @@
+use anyhow::Result;
+use psd::{Psd, PsdChannelCompression, PsdChannelKind};
+
+const CMYK: &[u8] = include_bytes!("./fixtures/cmyk/cmyk-2x1.psd");
+
+/// The final image of a CMYK PSD is converted to RGB, using the fifth channel as alpha.
+///
+/// cargo test --test cmyk cmyk_final_image -- --exact
+#[test]
+fn cmyk_final_image() -> Result<()> {
+    let psd = Psd::from_bytes(CMYK)?;
+
+    assert_eq!(psd.rgba(), vec![0, 255, 255, 255, 127, 0, 127, 128]);
+
+    Ok(())
+}
+
+/// A CMYK layer's black channel is mixed into its colors.
+///
+/// cargo test --test cmyk cmyk_layer -- --exact
+#[test]
+fn cmyk_layer() -> Result<()> {
+    let psd = Psd::from_bytes(CMYK)?;
+    let layer = psd.layer_by_name("CMYK").unwrap();
+
+    assert_eq!(
+        layer.compression(PsdChannelKind::Black)?,
+        PsdChannelCompression::RawData
+    );
+    assert_eq!(layer.rgba(), vec![0, 255, 255, 255, 127, 0, 127, 128]);
+
+    Ok(())
+}
+
+/// The final image of a CMYK PSD without a fifth channel is opaque, since its fourth channel is
+/// black rather than alpha.
+///
+/// cargo test --test cmyk cmyk_without_alpha -- --exact
+#[test]
+fn cmyk_without_alpha() -> Result<()> {
+    let psd = include_bytes!("./fixtures/separations/cmyk-spot-and-alpha.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    assert_eq!(psd.rgba(), vec![0, 255, 255, 255, 255, 255, 64, 255]);
+
+    Ok(())
+}
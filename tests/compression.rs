@@ -1,5 +1,5 @@
 use anyhow::Result;
-use psd::{Psd, PsdChannelCompression};
+use psd::{Psd, PsdChannelCompression, PsdChannelKind};
 
 const RED_PIXEL: [u8; 4] = [255, 0, 0, 255];
 const GREEN_PIXEL: [u8; 4] = [0, 255, 0, 255];
@@ -45,6 +45,39 @@ fn test_rle_layer(psd: &Psd, layer_name: &str, expected_pixels: &[u8]) {
     assert_eq!(&layer.rgba().as_slice(), &expected_pixels);
 }
 
+/// ZIP compressed final images and layers are decompressed, undoing the prediction when there
+/// is one.
+///
+/// cargo test --test compression zip_decompress -- --exact
+#[test]
+fn zip_decompress() -> Result<()> {
+    let expected = [RED_PIXEL, GREEN_PIXEL, [10, 20, 30, 255]].concat();
+
+    for (psd, compression) in [
+        (
+            &include_bytes!("./fixtures/zip-compression/rgb-3x1-zip-with-prediction.psd")[..],
+            PsdChannelCompression::ZipWithPrediction,
+        ),
+        (
+            &include_bytes!("./fixtures/zip-compression/rgb-3x1-zip-without-prediction.psd")[..],
+            PsdChannelCompression::ZipWithoutPrediction,
+        ),
+    ]
+    .iter()
+    {
+        let psd = Psd::from_bytes(psd)?;
+
+        assert_eq!(psd.compression(), compression);
+        assert_eq!(psd.rgba(), expected);
+
+        let layer = psd.layer_by_name("HDR").unwrap();
+        assert_eq!(layer.compression(PsdChannelKind::Red)?, *compression);
+        assert_eq!(layer.rgba(), expected);
+    }
+
+    Ok(())
+}
+
 // Below are methods to make different expected final pixels so that we can text our generated
 // pixels against these expected pixels below.
 
@@ -0,0 +1,157 @@
+use anyhow::Result;
+use psd::{CropRect, Psd, PsdError};
+
+const ASYMMETRIC: &[u8] = include_bytes!("./fixtures/transform/asymmetric-4x3.psd");
+
+/// Fixtures with different compressions, depths and color modes.
+const FIXTURES: [&[u8]; 10] = [
+    ASYMMETRIC,
+    include_bytes!("./fixtures/rle-3-layer-8x8.psd"),
+    include_bytes!("./fixtures/transparent-top-layer-2x1.psd"),
+    include_bytes!("./fixtures/layer-masks/red-3x3-mask-default-color-0.psd"),
+    include_bytes!("./fixtures/two-channel-8x8.psd"),
+    include_bytes!("./fixtures/zip-compression/rgb-3x1-zip-with-prediction.psd"),
+    include_bytes!("./fixtures/zip-compression/hdr-2x1-zip-with-prediction.psd"),
+    include_bytes!("./fixtures/bitmap-mode/bitmap-10x2-rle.psd"),
+    include_bytes!("./fixtures/cmyk/cmyk-2x1.psd"),
+    include_bytes!("./fixtures/indexed/indexed-3x1.psd"),
+];
+
+/// Cropping keeps the pixels of the final image and of every layer that are within the crop
+/// rectangle, before and after writing the cropped PSD.
+///
+/// cargo test --test crop crop_pixels -- --exact
+#[test]
+fn crop_pixels() -> Result<()> {
+    for bytes in FIXTURES.iter() {
+        let original = Psd::from_bytes(bytes)?;
+        let (width, height) = (original.width(), original.height());
+        let rect = CropRect::new(
+            width / 2,
+            height / 2,
+            width - width / 2,
+            height - height / 2,
+        );
+
+        let psd = original.crop(rect)?;
+        assert_eq!((psd.width(), psd.height()), (rect.width(), rect.height()));
+
+        let cropped = |pixels: &[u8]| -> Vec<u8> {
+            pixels
+                .chunks(width as usize * 4)
+                .skip(rect.top() as usize)
+                .take(rect.height() as usize)
+                .flat_map(|row| {
+                    let start = rect.left() as usize * 4;
+                    row[start..start + rect.width() as usize * 4].to_vec()
+                })
+                .collect()
+        };
+
+        let written = Psd::from_bytes(&psd.to_bytes())?;
+        for psd in [&psd, &written].iter() {
+            assert_eq!(psd.rgba(), cropped(&original.rgba()));
+
+            for (layer, original_layer) in psd.layers().iter().zip(original.layers()) {
+                assert_eq!(layer.rgba(), cropped(&original_layer.rgba()));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Cropping clips and moves the layer's rectangle, its mask's rectangle and pixels, and the
+/// guides.
+///
+/// cargo test --test crop crop_rects_and_guides -- --exact
+#[test]
+fn crop_rects_and_guides() -> Result<()> {
+    let psd = Psd::from_bytes(ASYMMETRIC)?.crop(CropRect::new(1, 1, 3, 2))?;
+    let psd = Psd::from_bytes(&psd.to_bytes())?;
+
+    let layer = psd.layer_by_name("Offset").unwrap();
+    assert_eq!(
+        (
+            layer.layer_top(),
+            layer.layer_left(),
+            layer.layer_bottom(),
+            layer.layer_right()
+        ),
+        (0, 1, 0, 2)
+    );
+
+    let mask_rect = layer.mask().unwrap().rect();
+    assert_eq!(
+        (
+            mask_rect.top(),
+            mask_rect.left(),
+            mask_rect.bottom(),
+            mask_rect.right()
+        ),
+        (0, 0, 2, 1)
+    );
+    // The right column of the 2x2 mask is the left column of the canvas, and the rest of the
+    // canvas is the mask's default color
+    assert_eq!(layer.mask_pixels(), Some(vec![255, 255, 255, 64, 255, 255]));
+
+    // The vertical guide at x = 1 is now at x = 0, and the horizontal guide at y = 2 is now at
+    // y = 1
+    let guides = psd
+        .resource_blocks()
+        .iter()
+        .find(|block| block.id() == 1032)
+        .unwrap()
+        .data();
+    assert_eq!(&guides[16..], &[0, 0, 0, 0, 0, 0, 0, 0, 32, 1]);
+
+    Ok(())
+}
+
+/// A layer that is entirely outside of the crop rectangle ends up without any pixels.
+///
+/// cargo test --test crop crop_away_layer -- --exact
+#[test]
+fn crop_away_layer() -> Result<()> {
+    let psd = Psd::from_bytes(ASYMMETRIC)?.crop(CropRect::new(0, 2, 2, 1))?;
+    let psd = Psd::from_bytes(&psd.to_bytes())?;
+
+    let layer = psd.layer_by_name("Offset").unwrap();
+    assert_eq!(
+        (
+            layer.layer_top(),
+            layer.layer_left(),
+            layer.layer_bottom(),
+            layer.layer_right()
+        ),
+        (0, 0, -1, -1)
+    );
+    assert!(layer.is_empty());
+    assert_eq!((layer.width(), layer.height()), (0, 0));
+    assert!(layer.rgba().is_empty());
+
+    Ok(())
+}
+
+/// Cropping to an empty rectangle or to one that doesn't lie within the canvas is an error.
+///
+/// cargo test --test crop invalid_crop_rect -- --exact
+#[test]
+fn invalid_crop_rect() -> Result<()> {
+    let psd = Psd::from_bytes(ASYMMETRIC)?;
+
+    for rect in [
+        CropRect::new(0, 0, 0, 3),
+        CropRect::new(1, 0, 4, 3),
+        CropRect::new(0, 3, 4, 1),
+    ]
+    .iter()
+    {
+        match psd.crop(*rect) {
+            Err(PsdError::InvalidCropRect(invalid)) => assert_eq!(invalid, *rect),
+            _ => panic!("Expected an invalid crop rectangle error"),
+        }
+    }
+
+    Ok(())
+}
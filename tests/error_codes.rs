@@ -0,0 +1,41 @@
+use psd::{Psd, PsdError, ResizeFilter};
+
+const GROUPS: &[u8] =
+    include_bytes!("./fixtures/groups/green-1x1-one-group-one-layer-inside-one-outside.psd");
+
+/// Section errors are reported with the code of the section's error.
+///
+/// cargo test --test error_codes section_error_codes -- --exact
+#[test]
+fn section_error_codes() {
+    let mut bad_signature = GROUPS.to_vec();
+    bad_signature[0] = b'X';
+
+    assert_eq!(
+        Psd::from_bytes(&bad_signature).unwrap_err().code(),
+        "header.invalid_signature"
+    );
+    assert_eq!(
+        Psd::from_bytes(&GROUPS[..10]).unwrap_err().code(),
+        "header.incorrect_length"
+    );
+}
+
+/// Errors that aren't from a section have codes of their own.
+///
+/// cargo test --test error_codes psd_error_codes -- --exact
+#[test]
+fn psd_error_codes() {
+    let psd = Psd::from_bytes(GROUPS).unwrap();
+
+    assert_eq!(
+        psd.resize(0, 1, ResizeFilter::NearestNeighbor)
+            .unwrap_err()
+            .code(),
+        "resize.invalid_dimensions"
+    );
+    assert_eq!(
+        PsdError::GroupNotFound("missing".to_string()).code(),
+        "render.group_not_found"
+    );
+}
@@ -0,0 +1,142 @@
+use anyhow::Result;
+use psd::{ColorMode, Fill, GradientKind, Psd};
+
+const FILLS: &[u8] = include_bytes!("./fixtures/fill-layers/fills-4x1.psd");
+const PATTERNS: &[u8] = include_bytes!("./fixtures/fill-layers/patterns-4x2.psd");
+
+/// Verify that we read the settings of solid color, gradient and pattern fill layers.
+///
+/// cargo test --test fill_layers fill_settings -- --exact
+#[test]
+fn fill_settings() -> Result<()> {
+    let psd = Psd::from_bytes(FILLS)?;
+    let fill = |name: &str| psd.layer_by_name(name).unwrap().fill().unwrap();
+
+    match fill("Solid") {
+        Fill::SolidColor(solid) => assert_eq!(solid.color(), [255, 0, 0]),
+        other => panic!("Expected a solid color, got {:?}", other),
+    };
+
+    match fill("Gradient") {
+        Fill::Gradient(gradient) => {
+            assert_eq!(gradient.kind(), GradientKind::Linear);
+            assert_eq!(gradient.angle(), 0.);
+            assert_eq!(gradient.scale(), 100.);
+            assert!(!gradient.reverse());
+            assert!(gradient.align_with_layer());
+            assert_eq!(gradient.offset(), (0., 0.));
+
+            // The stops are sorted by their location
+            let colors: Vec<_> = gradient
+                .color_stops()
+                .iter()
+                .map(|stop| (stop.location(), stop.midpoint(), stop.color()))
+                .collect();
+            assert_eq!(colors, vec![(0., 0.5, [0; 3]), (1., 0.5, [255; 3])]);
+
+            let opacities: Vec<_> = gradient
+                .transparency_stops()
+                .iter()
+                .map(|stop| stop.opacity())
+                .collect();
+            assert_eq!(opacities, vec![255, 255]);
+        }
+        other => panic!("Expected a gradient, got {:?}", other),
+    };
+
+    match fill("Pattern") {
+        Fill::Pattern(pattern) => {
+            assert_eq!(pattern.name(), "Bubbles");
+            assert_eq!(pattern.id(), "b7334da0-122f-11d4-8bb5-e27e45023b5f");
+            assert_eq!(pattern.scale(), 50.);
+        }
+        other => panic!("Expected a pattern, got {:?}", other),
+    };
+
+    Ok(())
+}
+
+/// Fill layers without any pixels of their own are rendered from their settings, with their
+/// masks applied. Pattern fills whose pattern isn't in the PSD aren't rendered.
+///
+/// cargo test --test fill_layers render_fill_layers -- --exact
+#[test]
+fn render_fill_layers() -> Result<()> {
+    let psd = Psd::from_bytes(FILLS)?;
+    assert!(psd.layer_by_name("Solid").unwrap().is_empty());
+
+    let gradient = psd.flatten_layers_rgba(&|(_, layer)| layer.name() == "Gradient")?;
+    assert_eq!(
+        gradient,
+        vec![32, 32, 32, 255, 96, 96, 96, 255, 159, 159, 159, 255, 223, 223, 223, 255]
+    );
+
+    let pattern = psd.flatten_layers_rgba(&|(_, layer)| layer.name() == "Pattern")?;
+    assert_eq!(pattern, vec![0; 16]);
+
+    // The solid color's mask only shows its left half
+    let flattened = psd.flatten_layers_rgba(&|_| true)?;
+    assert_eq!(
+        flattened,
+        vec![255, 0, 0, 255, 255, 0, 0, 255, 159, 159, 159, 255, 223, 223, 223, 255]
+    );
+
+    Ok(())
+}
+
+/// Verify that we read the patterns in the document's `Patt` block.
+///
+/// cargo test --test fill_layers read_patterns -- --exact
+#[test]
+fn read_patterns() -> Result<()> {
+    let psd = Psd::from_bytes(PATTERNS)?;
+
+    let patterns: Vec<_> = psd
+        .patterns()
+        .iter()
+        .map(|pattern| {
+            (
+                pattern.name(),
+                pattern.id(),
+                pattern.width(),
+                pattern.height(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        patterns,
+        vec![("Stripes", "stripes-id", 2, 1), ("Gray", "gray-id", 2, 1)]
+    );
+
+    let stripes = &psd.patterns()[0];
+    assert_eq!(stripes.color_mode(), ColorMode::Rgb);
+    assert_eq!(
+        stripes.rgba().unwrap(),
+        vec![255, 0, 0, 255, 0, 0, 255, 128]
+    );
+
+    Ok(())
+}
+
+/// Pattern fills repeat their pattern from their phase, scaled by the nearest pixel.
+///
+/// cargo test --test fill_layers render_pattern_fills -- --exact
+#[test]
+fn render_pattern_fills() -> Result<()> {
+    let psd = Psd::from_bytes(PATTERNS)?;
+    let flatten = |name: &'static str| psd.flatten_layers_rgba(&|(_, layer)| layer.name() == name);
+
+    // The phase moves the first red pixel one pixel to the right
+    let red = [255, 0, 0, 255];
+    let blue = [0, 0, 255, 128];
+    let row = [blue, red, blue, red].concat();
+    assert_eq!(flatten("Stripes")?, [row.clone(), row].concat());
+
+    // At 200% each pixel of the pattern covers two pixels
+    let row = [[0, 0, 0, 255], [0, 0, 0, 255], [255; 4], [255; 4]].concat();
+    assert_eq!(flatten("Scaled")?, [row.clone(), row].concat());
+
+    assert_eq!(flatten("Missing")?, vec![0; 32]);
+
+    Ok(())
+}
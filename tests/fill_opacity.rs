@@ -0,0 +1,48 @@
+use anyhow::Result;
+use psd::Psd;
+
+const FILL_OPACITY: &[u8] = include_bytes!("./fixtures/fill-opacity/fill-opacity-4x1.psd");
+
+/// Verify that we read each layer's fill opacity and advanced blending flags, and fall back to
+/// Photoshop's defaults for layers without them.
+///
+/// cargo test --test fill_opacity fill_opacity_blocks -- --exact
+#[test]
+fn fill_opacity_blocks() -> Result<()> {
+    let psd = Psd::from_bytes(FILL_OPACITY)?;
+
+    let half_fill = psd.layer_by_name("Half fill").unwrap();
+    assert_eq!(half_fill.fill_opacity(), 128);
+    assert_eq!(half_fill.opacity(), 255);
+    assert!(!half_fill.blend_clipped_elements());
+    assert!(half_fill.blend_interior_elements());
+
+    let half_both = psd.layer_by_name("Half both").unwrap();
+    assert_eq!(half_both.fill_opacity(), 128);
+    assert_eq!(half_both.opacity(), 128);
+    assert!(half_both.blend_clipped_elements());
+    assert!(!half_both.blend_interior_elements());
+
+    assert_eq!(psd.layer_by_name("Stroke").unwrap().fill_opacity(), 0);
+
+    Ok(())
+}
+
+/// The fill opacity fades a layer's pixels along with its opacity, but not its stroke.
+///
+/// cargo test --test fill_opacity render_fill_opacity -- --exact
+#[test]
+fn render_fill_opacity() -> Result<()> {
+    let psd = Psd::from_bytes(FILL_OPACITY)?;
+
+    let rgba = psd.render().to_rgba()?;
+    assert_eq!(&rgba[0..4], &[255, 0, 0, 128]);
+    assert_eq!(&rgba[4..8], &[255, 0, 0, 64]);
+    assert_eq!(rgba[15], 0);
+
+    let rgba = psd.render().layer_effects().to_rgba()?;
+    assert_eq!(&rgba[8..12], &[0, 0, 255, 255]);
+    assert_eq!(rgba[15], 0);
+
+    Ok(())
+}
@@ -0,0 +1,264 @@
+use anyhow::Result;
+use psd::fixture::{FixtureLayer, PsdFixture};
+use psd::{BlendMode, CropRect, PsdChannelCompression, PsdChannelKind, PsdDepth, PsdVersion};
+
+const COMPRESSIONS: [PsdChannelCompression; 4] = [
+    PsdChannelCompression::RawData,
+    PsdChannelCompression::RleCompressed,
+    PsdChannelCompression::ZipWithoutPrediction,
+    PsdChannelCompression::ZipWithPrediction,
+];
+
+/// The pixels of a 3x2 layer whose pixels are all different.
+fn gradient() -> Vec<u8> {
+    (0..6u8)
+        .flat_map(|idx| vec![idx * 40, 255 - idx * 40, idx * 7, 100 + idx * 30])
+        .collect()
+}
+
+/// Verify that the pixels of layers and of the final image survive being written at every
+/// depth and with every compression.
+///
+/// cargo test --test fixture_builder depths_and_compressions -- --exact
+#[test]
+fn depths_and_compressions() -> Result<()> {
+    let final_rgba: Vec<u8> = (0..16u8)
+        .flat_map(|idx| vec![idx, idx * 2, 255, 255])
+        .collect();
+    let region = CropRect::new(1, 1, 3, 2);
+
+    for depth in [PsdDepth::Eight, PsdDepth::Sixteen].iter() {
+        for compression in COMPRESSIONS.iter() {
+            for version in [PsdVersion::One, PsdVersion::Two].iter() {
+                let psd = PsdFixture::new(4, 4)
+                    .version(*version)
+                    .depth(*depth)
+                    .compression(*compression)
+                    .layer(FixtureLayer::new("Gradient", 1, 1, 3, gradient()))
+                    .final_rgba(final_rgba.clone())
+                    .build()?;
+
+                assert_eq!(psd.depth(), *depth);
+                assert_eq!(psd.version(), *version);
+                let layer = psd.layer_by_name("Gradient").unwrap();
+                assert_eq!(layer.compression(PsdChannelKind::Red)?, *compression);
+
+                match depth {
+                    PsdDepth::Sixteen => {
+                        let to_sixteen = |rgba: Vec<u8>| -> Vec<u16> {
+                            rgba.iter().map(|sample| *sample as u16 * 257).collect()
+                        };
+                        assert_eq!(psd.rgba16(), to_sixteen(final_rgba.clone()));
+                        assert_eq!(psd.rgba(), final_rgba);
+                        assert_eq!(layer.rgba_region(region)?, gradient());
+
+                        let layer_rgba = layer.rgba16();
+                        for (idx, pixel) in to_sixteen(gradient()).chunks_exact(4).enumerate() {
+                            let (left, top) = (1 + idx % 3, 1 + idx / 3);
+                            let start = (top * 4 + left) * 4;
+                            assert_eq!(&layer_rgba[start..start + 4], pixel);
+                        }
+                    }
+                    _ => {
+                        assert_eq!(psd.rgba(), final_rgba);
+                        assert_eq!(layer.rgba_region(region)?, gradient());
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Verify that groups, blend modes, opacity, visibility, clipping and masks are written.
+///
+/// cargo test --test fixture_builder groups_and_layer_properties -- --exact
+#[test]
+fn groups_and_layer_properties() -> Result<()> {
+    let psd = PsdFixture::new(4, 4)
+        .layer(FixtureLayer::solid(
+            "Background",
+            0,
+            0,
+            4,
+            4,
+            [0, 0, 255, 255],
+        ))
+        .group("Outer", |outer| {
+            outer
+                .layer(
+                    FixtureLayer::solid("Multiply", 0, 0, 2, 2, [255, 0, 0, 255])
+                        .blend_mode(BlendMode::Multiply)
+                        .opacity(128),
+                )
+                .group_with_blend_mode("Inner", BlendMode::Screen, |inner| {
+                    inner.layer(
+                        FixtureLayer::solid("Clipped", 1, 1, 2, 2, [0, 255, 0, 255])
+                            .clipped()
+                            .hidden()
+                            .mask(1, 1, 2, vec![255, 0, 0, 255], 0),
+                    )
+                })
+        })
+        .build()?;
+
+    assert_eq!(psd.layers().len(), 3);
+    assert_eq!(psd.groups().len(), 2);
+
+    let group = |name| psd.groups().by_name(name).unwrap();
+    let (outer, inner) = (group("Outer"), group("Inner"));
+    assert_eq!(outer.blend_mode(), BlendMode::PassThrough);
+    assert_eq!(inner.blend_mode(), BlendMode::Screen);
+    assert_eq!(inner.parent_id(), Some(outer.id()));
+
+    let background = psd.layer_by_name("Background").unwrap();
+    assert_eq!(background.parent_id(), None);
+    assert!(background.visible());
+
+    let multiply = psd.layer_by_name("Multiply").unwrap();
+    assert_eq!(multiply.parent_id(), Some(outer.id()));
+    assert_eq!(multiply.blend_mode(), BlendMode::Multiply);
+    assert_eq!(multiply.opacity(), 128);
+
+    let clipped = psd.layer_by_name("Clipped").unwrap();
+    assert_eq!(clipped.parent_id(), Some(inner.id()));
+    assert!(!clipped.visible());
+    assert!(!clipped.is_clipping_mask());
+    let mask = clipped.mask().unwrap();
+    assert_eq!((mask.rect().left(), mask.rect().width()), (1, 2));
+    assert_eq!(mask.default_color(), 0);
+
+    Ok(())
+}
+
+/// Verify that the 8 bit pixels of 16 bit layers that are larger than a pixel are their 16 bit
+/// samples mapped down, rather than each byte of a sample being read as its own pixel.
+///
+/// cargo test --test fixture_builder sixteen_bit_layer_rgba -- --exact
+#[test]
+fn sixteen_bit_layer_rgba() -> Result<()> {
+    let transparent = [0, 0, 0, 0];
+    let red = [255, 0, 0, 255];
+    let rgba: Vec<u8> = [transparent, transparent, red, red].concat();
+
+    for compression in COMPRESSIONS.iter() {
+        let psd = PsdFixture::new(2, 2)
+            .depth(PsdDepth::Sixteen)
+            .compression(*compression)
+            .layer(FixtureLayer::new("Red Row", 0, 0, 2, rgba.clone()))
+            .build()?;
+
+        let layer = psd.layer_by_name("Red Row").unwrap();
+        assert_eq!(layer.rgba(), rgba, "{:?}", compression);
+        assert_eq!(
+            layer.rgba_region(CropRect::new(0, 1, 2, 1))?,
+            [red, red].concat(),
+            "{:?}",
+            compression
+        );
+    }
+
+    Ok(())
+}
+
+/// Verify that the alpha summary and histogram of 16 bit layers come from their samples, since
+/// the renderer skips layers that the summary says are transparent and hides the layers below
+/// the ones that it says are opaque.
+///
+/// cargo test --test fixture_builder sixteen_bit_alpha_summary_and_culling -- --exact
+#[test]
+fn sixteen_bit_alpha_summary_and_culling() -> Result<()> {
+    let transparent = [0, 0, 0, 0];
+    let red = [255, 0, 0, 255];
+    let blue = [0, 0, 255, 255];
+    let red_row: Vec<u8> = [transparent, transparent, red, red].concat();
+
+    for compression in COMPRESSIONS.iter() {
+        let psd = PsdFixture::new(2, 2)
+            .depth(PsdDepth::Sixteen)
+            .compression(*compression)
+            .layer(FixtureLayer::solid("Blue", 0, 0, 2, 2, blue))
+            .layer(FixtureLayer::new("Red Row", 0, 0, 2, red_row.clone()))
+            .build()?;
+
+        let layer = psd.layer_by_name("Red Row").unwrap();
+        let summary = layer.alpha_summary();
+        assert_eq!(
+            (summary.min(), summary.max()),
+            (0, 255),
+            "{:?}",
+            compression
+        );
+        assert_eq!(layer.histogram().alpha()[0], 2, "{:?}", compression);
+        assert_eq!(layer.histogram().red()[255], 2, "{:?}", compression);
+        assert!(psd
+            .layer_by_name("Blue")
+            .unwrap()
+            .alpha_summary()
+            .is_opaque());
+
+        assert_eq!(
+            psd.flatten_layers_rgba(&|_| true)?,
+            [blue, blue, red, red].concat(),
+            "{:?}",
+            compression
+        );
+        assert_eq!(
+            psd.flatten_layers_rgba(&|(_, layer)| layer.name() == "Red Row")?,
+            red_row,
+            "{:?}",
+            compression
+        );
+    }
+
+    Ok(())
+}
+
+/// Verify that decoded rows, the alpha at a pixel and mask pixels of 16 bit layers have one
+/// byte per pixel, like their 8 bit RGBA.
+///
+/// cargo test --test fixture_builder sixteen_bit_rows_and_masks -- --exact
+#[test]
+fn sixteen_bit_rows_and_masks() -> Result<()> {
+    let transparent = [0, 0, 0, 0];
+    let red = [255, 0, 0, 255];
+    let rgba: Vec<u8> = [transparent, transparent, red, red].concat();
+
+    for compression in COMPRESSIONS.iter() {
+        let psd = PsdFixture::new(2, 2)
+            .depth(PsdDepth::Sixteen)
+            .compression(*compression)
+            .layer(FixtureLayer::new("Red Row", 0, 0, 2, rgba.clone()).mask(
+                0,
+                0,
+                2,
+                vec![255, 0, 128, 255],
+                0,
+            ))
+            .build()?;
+        let layer = psd.layer_by_name("Red Row").unwrap();
+
+        let mut rows = vec![];
+        layer.for_each_decoded_row(PsdChannelKind::TransparencyMask, |row_idx, row| {
+            rows.push((row_idx, row.to_vec()))
+        })?;
+        assert_eq!(
+            rows,
+            vec![(0, vec![0, 0]), (1, vec![255, 255])],
+            "{:?}",
+            compression
+        );
+
+        assert_eq!(layer.alpha_at(1, 0), Some(0), "{:?}", compression);
+        assert_eq!(layer.alpha_at(1, 1), Some(255), "{:?}", compression);
+        assert_eq!(
+            layer.mask_pixels(),
+            Some(vec![255, 0, 128, 255]),
+            "{:?}",
+            compression
+        );
+    }
+
+    Ok(())
+}
@@ -38,3 +38,65 @@ fn no_matching_layers() -> Result<()> {
 
     Ok(())
 }
+
+/// The planar output contains the same pixels as the interleaved output.
+///
+/// cargo test --test flatten_layers flatten_planar_matches_rgba -- --exact
+#[test]
+fn flatten_planar_matches_rgba() -> Result<()> {
+    let psd = include_bytes!("./fixtures/transparent-top-layer-2x1.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let rgba = psd.flatten_layers_rgba(&|_| true)?;
+    let planes = psd.flatten_layers_planar(&|_| true)?;
+
+    for (channel, plane) in planes.iter().enumerate() {
+        assert_eq!(plane.len(), 2);
+
+        let interleaved: Vec<u8> = rgba.iter().skip(channel).step_by(4).copied().collect();
+        assert_eq!(plane, &interleaved);
+    }
+
+    assert_eq!(planes[2], vec![0, 255]);
+
+    Ok(())
+}
+
+/// The flattened image is cropped to the pixels that aren't fully transparent.
+///
+/// cargo test --test flatten_layers flatten_trimmed -- --exact
+#[test]
+fn flatten_trimmed() -> Result<()> {
+    let psd = include_bytes!("./fixtures/16x16-rle-partially-opaque.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let trimmed = psd.flatten_trimmed_rgba(&|_| true)?;
+    assert_eq!((trimmed.left(), trimmed.top()), (1, 1));
+    assert_eq!((trimmed.width(), trimmed.height()), (9, 9));
+    assert_eq!(trimmed.rgba().len(), 9 * 9 * 4);
+    assert_eq!(&trimmed.rgba()[0..4], &RED_PIXEL);
+
+    let empty = psd.flatten_trimmed_rgba(&|_| false)?;
+    assert_eq!((empty.width(), empty.height()), (0, 0));
+    assert!(empty.rgba().is_empty());
+
+    Ok(())
+}
+
+/// Transparent pixels inside of the bounding box are kept.
+///
+/// cargo test --test flatten_layers flatten_trimmed_keeps_inner_transparency -- --exact
+#[test]
+fn flatten_trimmed_keeps_inner_transparency() -> Result<()> {
+    let psd = include_bytes!("./fixtures/3x3-opaque-center.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let trimmed = psd.flatten_trimmed_rgba(&|_| true)?;
+    assert_eq!((trimmed.left(), trimmed.top()), (1, 0));
+    assert_eq!((trimmed.width(), trimmed.height()), (2, 2));
+
+    let alpha: Vec<u8> = trimmed.rgba().chunks(4).map(|pixel| pixel[3]).collect();
+    assert_eq!(alpha, vec![0, 255, 255, 0]);
+
+    Ok(())
+}
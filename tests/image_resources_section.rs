@@ -1,4 +1,19 @@
-use psd::{DescriptorField, ImageResource, Psd};
+use psd::fixture::PsdFixture;
+use psd::{
+    CropRect, DescriptorField, DimensionUnit, GuideDirection, ImageResource, Psd, ResolutionUnit,
+    SlicesImageResource, ThumbnailFormat,
+};
+
+/// The first slices resource of the PSD.
+fn slices_resource(psd: &Psd) -> &SlicesImageResource {
+    psd.resources()
+        .iter()
+        .find_map(|resource| match resource {
+            ImageResource::Slices(slices) => Some(slices),
+            _ => None,
+        })
+        .unwrap()
+}
 
 /// In this test we check that root descriptor's `bounds` field is equal to 1
 /// So, then fields parsed correctly
@@ -10,9 +25,7 @@ fn image_check_1x1p_bound_field() {
 
     let psd = Psd::from_bytes(psd).unwrap();
 
-    let descriptors = match &psd.resources()[0] {
-        ImageResource::Slices(s) => s.descriptors(),
-    };
+    let descriptors = slices_resource(&psd).descriptors();
     let descriptor = descriptors.get(0).unwrap();
     let bounds = descriptor.fields.get("bounds").unwrap();
 
@@ -41,9 +54,7 @@ fn image_check_16x16p_bound_field() {
 
     let psd = Psd::from_bytes(psd).unwrap();
 
-    let descriptors = match &psd.resources()[0] {
-        ImageResource::Slices(s) => s.descriptors(),
-    };
+    let descriptors = slices_resource(&psd).descriptors();
     let descriptor = descriptors.get(0).unwrap();
     let bounds = descriptor.fields.get("bounds").unwrap();
 
@@ -71,6 +82,17 @@ fn image_non_utf8_pascal_string() {
     let psd = Psd::from_bytes(psd).unwrap();
 
     assert!(psd.layers().is_empty());
+
+    // "双轴对称 1" encoded as GB2312, which we can only hand back as raw bytes
+    let block = psd
+        .resource_blocks()
+        .iter()
+        .find(|block| block.id() == 2000)
+        .unwrap();
+    assert_eq!(
+        block.name_bytes(),
+        &[0xCB, 0xAB, 0xD6, 0xE1, 0xB6, 0xD4, 0xB3, 0xC6, 0x20, 0x31]
+    );
 }
 
 /// The image contains a Pascal string of odd length in its image resource block.
@@ -82,4 +104,172 @@ fn image_odd_length_pascal_string() {
     let psd = Psd::from_bytes(psd).unwrap();
 
     assert!(psd.layers().is_empty());
+
+    let names: Vec<(i16, String)> = psd
+        .resource_blocks()
+        .iter()
+        .filter(|block| !block.name_bytes().is_empty())
+        .map(|block| (block.id(), block.name()))
+        .collect();
+    assert_eq!(names, vec![(3000, "OriginDataIRB".to_string())]);
+}
+
+/// Verify that the resolution info resource (1005) is parsed into a typed resource.
+///
+/// cargo test --test image_resources_section resolution_info -- --exact
+#[test]
+fn resolution_info() {
+    let psd = include_bytes!("./fixtures/two-layers-red-green-1x1.psd");
+    let psd = Psd::from_bytes(psd).unwrap();
+
+    let resolution = psd.resolution().unwrap();
+    assert_eq!(resolution.horizontal_dpi(), 144.);
+    assert_eq!(resolution.vertical_dpi(), 144.);
+    assert_eq!(resolution.horizontal_unit(), ResolutionUnit::PixelsPerInch);
+    assert_eq!(resolution.width_unit(), DimensionUnit::Inches);
+
+    assert!(psd
+        .resources()
+        .contains(&ImageResource::Resolution(*resolution)));
+}
+
+/// Verify that the ICC profile resource (1039) is exposed as the raw bytes of the profile.
+///
+/// cargo test --test image_resources_section icc_profile -- --exact
+#[test]
+fn icc_profile() {
+    let psd = Psd::from_bytes(include_bytes!("./fixtures/green-1x1.psd")).unwrap();
+
+    let profile = psd.icc_profile().unwrap();
+    // Every ICC profile starts with its own size and has the `acsp` signature at byte 36
+    assert_eq!(profile.len(), 3996);
+    assert_eq!(
+        u32::from_be_bytes([profile[0], profile[1], profile[2], profile[3]]),
+        3996
+    );
+    assert_eq!(&profile[36..40], b"acsp");
+    assert!(psd
+        .resources()
+        .contains(&ImageResource::IccProfile(profile.to_vec())));
+
+    let untagged = PsdFixture::new(1, 1).build().unwrap();
+    assert_eq!(untagged.icc_profile(), None);
+}
+
+/// Verify that the thumbnail resource (1036) is exposed as the JPEG along with its size.
+///
+/// cargo test --test image_resources_section thumbnail -- --exact
+#[test]
+fn thumbnail() {
+    let psd = Psd::from_bytes(include_bytes!("./fixtures/green-clipping-10x10.psd")).unwrap();
+
+    let thumbnail = psd.thumbnail().unwrap();
+    assert_eq!(thumbnail.format(), ThumbnailFormat::Jpeg);
+    assert_eq!((thumbnail.width(), thumbnail.height()), (5, 5));
+    assert!(!thumbnail.is_bgr());
+    // JPEGs start with a start of image marker and end with an end of image marker
+    assert_eq!(thumbnail.data().len(), 586);
+    assert_eq!(&thumbnail.data()[..2], &[0xFF, 0xD8]);
+    assert_eq!(&thumbnail.data()[584..], &[0xFF, 0xD9]);
+
+    let no_thumbnail = PsdFixture::new(1, 1).build().unwrap();
+    assert_eq!(no_thumbnail.thumbnail(), None);
+}
+
+/// Verify that the grid and guides resource (1032) is read into guides, which follow the canvas
+/// when it is cropped.
+///
+/// cargo test --test image_resources_section guides -- --exact
+#[test]
+fn guides() {
+    let psd = Psd::from_bytes(include_bytes!("./fixtures/transform/asymmetric-4x3.psd")).unwrap();
+
+    let grid_and_guides = psd.guides().unwrap();
+    assert_eq!(grid_and_guides.version(), 1);
+    let positions = |direction| {
+        grid_and_guides
+            .guides_in(direction)
+            .map(|guide| guide.position())
+            .collect::<Vec<f64>>()
+    };
+    assert_eq!(positions(GuideDirection::Vertical), vec![1.]);
+    assert_eq!(positions(GuideDirection::Horizontal), vec![2.]);
+
+    let cropped = psd
+        .crop(CropRect::new(1, 1, 3, 2))
+        .unwrap()
+        .guides()
+        .unwrap();
+    let locations: Vec<(i32, GuideDirection)> = cropped
+        .guides()
+        .iter()
+        .map(|guide| (guide.location(), guide.direction()))
+        .collect();
+    assert_eq!(
+        locations,
+        vec![
+            (0, GuideDirection::Vertical),
+            (32, GuideDirection::Horizontal)
+        ]
+    );
+
+    let no_guides = PsdFixture::new(1, 1).build().unwrap();
+    assert_eq!(no_guides.guides(), None);
+}
+
+/// Verify that the layer state (1024), layer groups (1026), layer selection IDs (1069) and layer
+/// groups enabled (1072) resources are parsed, and that the selected IDs lead to the layers.
+///
+/// cargo test --test image_resources_section layer_state -- --exact
+#[test]
+fn layer_state() {
+    let psd =
+        include_bytes!("./fixtures/groups/green-1x1-one-group-one-layer-inside-one-outside.psd");
+    let psd = Psd::from_bytes(psd).unwrap();
+
+    assert_eq!(psd.target_layer_index(), Some(0));
+    assert_eq!(psd.layer_group_ids(), Some(&[0, 0, 0, 0][..]));
+    assert_eq!(psd.layer_groups_enabled(), Some(&[1, 1, 1, 1][..]));
+    assert_eq!(psd.selected_layer_ids(), &[4]);
+
+    let selected: Vec<&str> = psd
+        .selected_layers()
+        .iter()
+        .map(|layer| layer.name())
+        .collect();
+    assert_eq!(selected, vec!["Second Layer"]);
+    assert!(psd
+        .resources()
+        .contains(&ImageResource::LayerSelectionIds(vec![4])));
+
+    let no_state = PsdFixture::new(1, 1).build().unwrap();
+    assert_eq!(no_state.target_layer_index(), None);
+    assert!(no_state.selected_layers().is_empty());
+}
+
+/// Verify that the version info resource (1057) is parsed, and that a PSD without a real final
+/// image returns its flattened layers from `Psd::rgba`.
+///
+/// cargo test --test image_resources_section version_info -- --exact
+#[test]
+fn version_info() {
+    let psd = Psd::from_bytes(include_bytes!("./fixtures/slices-v8.psd")).unwrap();
+
+    let version_info = psd.version_info().unwrap();
+    assert_eq!(version_info.version(), 1);
+    assert_eq!(version_info.writer_name(), "Adobe Photoshop");
+    assert_eq!(version_info.reader_name(), "Adobe Photoshop 2022");
+    assert_eq!(version_info.file_version(), 1);
+    assert!(!psd.has_real_merged_data());
+
+    let flattened = psd.flatten_layers_rgba(&|_| true).unwrap();
+    assert_eq!(psd.rgba(), flattened);
+
+    let psd = include_bytes!("./fixtures/green-clipping-10x10.psd");
+    let psd = Psd::from_bytes(psd).unwrap();
+    assert!(psd.has_real_merged_data());
+
+    let no_version_info = PsdFixture::new(1, 1).build().unwrap();
+    assert_eq!(no_version_info.version_info(), None);
+    assert!(no_version_info.has_real_merged_data());
 }
@@ -0,0 +1,69 @@
+use anyhow::Result;
+use psd::Psd;
+
+const INDEXED: &[u8] = include_bytes!("./fixtures/indexed/indexed-3x1.psd");
+
+/// The legacy resources that describe an indexed color image's color table are exposed.
+///
+/// cargo test --test indexed_color legacy_color_table_resources -- --exact
+#[test]
+fn legacy_color_table_resources() -> Result<()> {
+    let psd = Psd::from_bytes(INDEXED)?;
+
+    assert_eq!(psd.indexed_color_count(), Some(3));
+    assert_eq!(psd.transparency_index(), Some(2));
+    assert_eq!(
+        psd.obsolete_color_table(),
+        Some(vec![[255, 0, 0], [0, 255, 0], [0, 0, 255]])
+    );
+
+    Ok(())
+}
+
+/// PSDs without the legacy resources don't have them.
+///
+/// cargo test --test indexed_color without_legacy_resources -- --exact
+#[test]
+fn without_legacy_resources() -> Result<()> {
+    let psd = Psd::from_bytes(include_bytes!("./fixtures/green-1x1.psd"))?;
+
+    assert_eq!(psd.indexed_color_count(), None);
+    assert_eq!(psd.transparency_index(), None);
+    assert_eq!(psd.obsolete_color_table(), None);
+
+    Ok(())
+}
+
+/// The final image of an indexed color PSD is looked up in its color table, with the color at the
+/// transparency index being transparent.
+///
+/// cargo test --test indexed_color indexed_final_image -- --exact
+#[test]
+fn indexed_final_image() -> Result<()> {
+    let psd = Psd::from_bytes(INDEXED)?;
+
+    let palette = psd.palette().unwrap();
+    assert_eq!(
+        palette[..4],
+        [[255, 0, 0], [0, 255, 0], [0, 0, 255], [0, 0, 0]]
+    );
+
+    assert_eq!(
+        psd.rgba(),
+        vec![255, 0, 0, 255, 0, 255, 0, 255, 0, 0, 255, 0]
+    );
+
+    Ok(())
+}
+
+/// Only indexed color PSDs have a color table.
+///
+/// cargo test --test indexed_color rgb_without_palette -- --exact
+#[test]
+fn rgb_without_palette() -> Result<()> {
+    let psd = Psd::from_bytes(include_bytes!("./fixtures/green-1x1.psd"))?;
+
+    assert_eq!(psd.palette(), None);
+
+    Ok(())
+}
@@ -1,4 +1,5 @@
-use psd::{Psd, PsdGroup};
+use psd::fixture::{FixtureLayer, PsdFixture};
+use psd::{GlobalLayerMaskKind, MajorSections, Psd, PsdGroup};
 
 const GREEN_PIXEL: [u8; 4] = [0, 255, 0, 255];
 
@@ -243,11 +244,7 @@ fn one_group_with_two_subgroups() {
 }
 
 fn group_by_name<'a>(psd: &'a Psd, name: &str) -> &'a PsdGroup {
-    psd.groups()
-        .iter()
-        .find(|group| group.1.name() == name)
-        .unwrap()
-        .1
+    psd.groups().by_name(name).unwrap()
 }
 
 /// cargo test --test layer_and_mask_information_section layer_larger_than_psd -- --exact
@@ -269,3 +266,244 @@ fn out_of_bounds_layer() {
     let larger_layer = psd.layer_by_name("out-of-bounds").unwrap();
     larger_layer.rgba();
 }
+
+/// Additional layer information blocks inside of a layer record are padded to an even length,
+/// so we need to skip the padding after the odd length block in order to find the `luni` block.
+///
+/// cargo test --test layer_and_mask_information_section layer_record_tagged_block_padding -- --exact
+#[test]
+fn layer_record_tagged_block_padding() {
+    let psd = include_bytes!("fixtures/odd-length-tagged-block.psd");
+    let psd = Psd::from_bytes(psd).unwrap();
+
+    assert_eq!(psd.layers().len(), 1);
+    let layer = psd.layer_by_name("First Layer").unwrap();
+    assert_eq!(&layer.rgba()[..], &GREEN_PIXEL);
+
+    let keys: Vec<&[u8; 4]> = psd.global_tagged_blocks().iter().map(|b| b.key()).collect();
+    assert_eq!(keys, vec![b"Patt", b"FMsk"]);
+}
+
+/// The tagged blocks at the end of the layer and mask information section are padded to a
+/// multiple of 4 bytes, so the 14 byte `LMsk` block is followed by two bytes of padding.
+///
+/// cargo test --test layer_and_mask_information_section document_tagged_block_padding -- --exact
+#[test]
+fn document_tagged_block_padding() {
+    let psd = include_bytes!("fixtures/one-channel-1x1.psd");
+    let psd = Psd::from_bytes(psd).unwrap();
+
+    let blocks = psd.global_tagged_blocks();
+    let keys: Vec<&[u8; 4]> = blocks.iter().map(|b| b.key()).collect();
+    // The layers of the `Lr16` block that comes first are parsed into the PSD's layers
+    assert_eq!(keys, vec![b"LMsk", b"Pat2", b"FMsk"]);
+    assert_eq!(psd.layers().len(), 1);
+
+    assert_eq!(blocks[0].data().len(), 14);
+    assert_eq!(blocks[2].data().len(), 12);
+
+    // Written back in the same place
+    let written = Psd::from_bytes(&psd.to_bytes()).unwrap();
+    assert_eq!(written.layers(), psd.layers());
+    assert_eq!(written.global_tagged_blocks(), blocks);
+}
+
+/// The global layer mask info comes right after the layer info, before the document's tagged
+/// blocks.
+///
+/// cargo test --test layer_and_mask_information_section global_layer_mask -- --exact
+#[test]
+fn global_layer_mask() {
+    let psd = include_bytes!("fixtures/global-layer-mask-1x1.psd");
+    let psd = Psd::from_bytes(psd).unwrap();
+
+    let mask = psd.global_layer_mask().unwrap();
+    assert_eq!(mask.color_space(), 0);
+    assert_eq!(mask.color(), [65535, 0, 0, 0]);
+    assert_eq!(mask.opacity(), 50);
+    assert_eq!(mask.kind(), GlobalLayerMaskKind::PerLayer);
+
+    let keys: Vec<&[u8; 4]> = psd.global_tagged_blocks().iter().map(|b| b.key()).collect();
+    assert_eq!(keys, vec![b"Patt"]);
+    assert_eq!(
+        &psd.layer_by_name("Green").unwrap().rgba()[..],
+        &GREEN_PIXEL
+    );
+
+    // Photoshop doesn't write any global layer mask info for most documents
+    let psd = Psd::from_bytes(include_bytes!("fixtures/green-1x1.psd")).unwrap();
+    assert_eq!(psd.global_layer_mask(), None);
+}
+
+/// A layer record whose bottom and right are at its top and left doesn't have any pixels, rather
+/// than having one pixel at the top left of the PSD.
+///
+/// cargo test --test layer_and_mask_information_section empty_layer -- --exact
+#[test]
+fn empty_layer() {
+    let psd = include_bytes!("./fixtures/adjustment-layers/adjustments-2x2.psd");
+    let psd = Psd::from_bytes(psd).unwrap();
+
+    let layer = psd.layer_by_name("Levels").unwrap();
+    assert!(layer.is_empty());
+    assert_eq!((layer.width(), layer.height()), (0, 0));
+    assert_eq!(layer.rgba(), vec![]);
+    assert_eq!(layer.alpha_at(0, 0), None);
+
+    // Written back out as an empty rectangle
+    let psd = Psd::from_bytes(&psd.to_bytes()).unwrap();
+    assert!(psd.layer_by_name("Levels").unwrap().is_empty());
+}
+
+/// 16 and 32 bit documents store their layer info in an `Lr16` or `Lr32` global tagged block,
+/// which is written back where it was among the other global tagged blocks.
+///
+/// cargo test --test layer_and_mask_information_section layer_info_block -- --exact
+#[test]
+fn layer_info_block() {
+    let bytes = include_bytes!("./fixtures/high-bit-depth/lr16-2x1.psd");
+    let psd = Psd::from_bytes(bytes).unwrap();
+
+    let names: Vec<&str> = psd.layers().iter().map(|layer| layer.name()).collect();
+    assert_eq!(names, vec!["Green", "Red"]);
+    assert_eq!(
+        psd.layer_by_name("Red").unwrap().rgba()[..8],
+        [255, 0, 0, 255, 0, 0, 0, 0]
+    );
+    assert_eq!(
+        psd.flatten_layers_rgba(&|_| true).unwrap(),
+        [255, 0, 0, 255, 0, 255, 0, 255]
+    );
+
+    // The layers aren't kept as a tagged block as well
+    let keys: Vec<&[u8; 4]> = psd.global_tagged_blocks().iter().map(|b| b.key()).collect();
+    assert_eq!(keys, vec![b"Patt"]);
+    assert_eq!(psd.to_bytes(), &bytes[..]);
+
+    let bytes = include_bytes!("./fixtures/high-bit-depth/lr32-1x1.psd");
+    let psd = Psd::from_bytes(bytes).unwrap();
+    assert_eq!(psd.layers().len(), 1);
+    assert_eq!(psd.layer_by_name("White").unwrap().rgba(), psd.rgba());
+    assert_eq!(psd.to_bytes(), &bytes[..]);
+}
+
+/// Some writers store the layer info in a `Layr` global tagged block instead of at the start of
+/// the section, which is read the same way as an `Lr16` block and written back under its key.
+///
+/// cargo test --test layer_and_mask_information_section layr_layer_info_block -- --exact
+#[test]
+fn layr_layer_info_block() {
+    let lr16 = include_bytes!("./fixtures/high-bit-depth/lr16-2x1.psd");
+    let key_start = lr16.windows(4).position(|key| key == b"Lr16").unwrap();
+    let mut bytes = lr16.to_vec();
+    bytes[key_start..key_start + 4].copy_from_slice(b"Layr");
+
+    let psd = Psd::from_bytes(&bytes).unwrap();
+    let names: Vec<&str> = psd.layers().iter().map(|layer| layer.name()).collect();
+    assert_eq!(names, vec!["Green", "Red"]);
+    assert_eq!(
+        psd.flatten_layers_rgba(&|_| true).unwrap(),
+        [255, 0, 0, 255, 0, 255, 0, 255]
+    );
+
+    let keys: Vec<&[u8; 4]> = psd.global_tagged_blocks().iter().map(|b| b.key()).collect();
+    assert_eq!(keys, vec![b"Patt"]);
+    assert_eq!(psd.to_bytes(), bytes);
+
+    let mut reader = Psd::from_reader(std::io::Cursor::new(&bytes)).unwrap();
+    assert_eq!(reader.layer_names().unwrap(), vec!["Green", "Red"]);
+}
+
+/// The declared layer count keeps its sign, and layer info that ends before the declared number
+/// of records is reported as truncated.
+///
+/// cargo test --test layer_and_mask_information_section layer_count -- --exact
+#[test]
+fn layer_count() {
+    let psd = Psd::from_bytes(include_bytes!("./fixtures/green-clipping-10x10.psd")).unwrap();
+    let count = psd.layer_count();
+    assert_eq!(count.declared(), -3);
+    assert!(count.is_negative());
+    assert_eq!((count.declared_records(), count.records_parsed()), (3, 3));
+    assert!(!count.is_truncated());
+
+    // A layer info that declares two layers but ends right after the count
+    let bytes = include_bytes!("./fixtures/green-1x1.psd");
+    let sections = MajorSections::from_bytes(bytes).unwrap();
+    let mut truncated = vec![];
+    truncated.extend_from_slice(sections.file_header());
+    truncated.extend_from_slice(sections.color_mode_data());
+    truncated.extend_from_slice(sections.image_resources());
+    truncated.extend_from_slice(&10u32.to_be_bytes());
+    truncated.extend_from_slice(&2u32.to_be_bytes());
+    truncated.extend_from_slice(&2i16.to_be_bytes());
+    truncated.extend_from_slice(&0u32.to_be_bytes());
+    truncated.extend_from_slice(sections.image_data());
+
+    let psd = Psd::from_bytes(&truncated).unwrap();
+    let count = psd.layer_count();
+    assert_eq!(count.declared(), 2);
+    assert_eq!(count.records_parsed(), 0);
+    assert!(count.is_truncated());
+    assert_eq!(psd.layers().len(), 0);
+}
+
+/// Verify that a text layer whose type tool descriptor has an OSType that we don't support is
+/// still parsed, only without its text information.
+///
+/// cargo test --test layer_and_mask_information_section unreadable_text_descriptor -- --exact
+#[test]
+fn unreadable_text_descriptor() {
+    let mut type_tool = vec![0, 1];
+    for value in [1., 0., 0., 1., 0., 0.].iter() {
+        type_tool.extend_from_slice(&f64::to_be_bytes(*value));
+    }
+    type_tool.extend_from_slice(&50u16.to_be_bytes());
+    type_tool.extend_from_slice(&16u32.to_be_bytes());
+    // A descriptor without a name, of class "TxLr", with one "Txt " field of type "UnFl"
+    type_tool.extend_from_slice(&0u32.to_be_bytes());
+    type_tool.extend_from_slice(&0u32.to_be_bytes());
+    type_tool.extend_from_slice(b"TxLr");
+    type_tool.extend_from_slice(&1u32.to_be_bytes());
+    type_tool.extend_from_slice(&0u32.to_be_bytes());
+    type_tool.extend_from_slice(b"Txt ");
+    type_tool.extend_from_slice(b"UnFl");
+    type_tool.extend_from_slice(&[0; 16]);
+
+    let psd = PsdFixture::new(1, 1)
+        .layer(
+            FixtureLayer::solid("Text", 0, 0, 1, 1, GREEN_PIXEL).tagged_block(*b"TySh", type_tool),
+        )
+        .build()
+        .unwrap();
+
+    let layer = psd.layer_by_name("Text").unwrap();
+    assert!(layer.text_layer_info().is_none());
+    assert_eq!(&layer.rgba()[..], &GREEN_PIXEL);
+}
+
+/// A tagged block whose length runs past the end of its layer record is skipped along with the
+/// rest of the record's extra data, so the next record is still read from where it starts.
+///
+/// cargo test --test layer_and_mask_information_section tagged_block_overrunning_record -- --exact
+#[test]
+fn tagged_block_overrunning_record() {
+    let mut bytes = PsdFixture::new(1, 1)
+        // The bottom layer's record is the first one in the file
+        .layer(
+            FixtureLayer::solid("Bottom", 0, 0, 1, 1, GREEN_PIXEL)
+                .tagged_block(*b"abcd", vec![0; 4]),
+        )
+        .layer(FixtureLayer::solid("Top", 0, 0, 1, 1, GREEN_PIXEL))
+        .to_bytes();
+
+    let block = bytes
+        .windows(8)
+        .position(|window| window == b"8BIMabcd")
+        .unwrap();
+    bytes[block + 8..block + 12].copy_from_slice(&64u32.to_be_bytes());
+
+    let psd = Psd::from_bytes(&bytes).unwrap();
+    let names: Vec<&str> = psd.layers().iter().map(|layer| layer.name()).collect();
+    assert_eq!(names, ["Top", "Bottom"]);
+}
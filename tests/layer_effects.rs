@@ -0,0 +1,110 @@
+use anyhow::Result;
+use psd::{BlendMode, GlowSource, Psd, StrokePosition};
+
+const EFFECTS: &[u8] = include_bytes!("./fixtures/layer-effects/effects-2x2.psd");
+
+/// Verify that we read every effect that we support from the object-based effects block.
+///
+/// cargo test --test layer_effects layer_effects -- --exact
+#[test]
+fn layer_effects() -> Result<()> {
+    let psd = Psd::from_bytes(EFFECTS)?;
+
+    let effects = psd.layer_by_name("Effects").unwrap().effects().unwrap();
+    assert!(effects.enabled());
+    assert_eq!(effects.scale(), 100.);
+
+    let drop_shadow = effects.drop_shadow().unwrap();
+    assert!(drop_shadow.enabled());
+    assert_eq!(drop_shadow.blend_mode(), BlendMode::Multiply);
+    assert_eq!(drop_shadow.color(), [10, 20, 30]);
+    assert_eq!(drop_shadow.opacity(), 191);
+    assert_eq!(drop_shadow.angle(), 120.);
+    assert!(!drop_shadow.use_global_light());
+    assert_eq!(drop_shadow.distance(), 5.);
+    assert_eq!(drop_shadow.spread(), 10.);
+    assert_eq!(drop_shadow.size(), 7.);
+    assert_eq!(drop_shadow.noise(), 0.);
+
+    let inner_shadow = effects.inner_shadow().unwrap();
+    assert!(!inner_shadow.enabled());
+    assert_eq!(inner_shadow.blend_mode(), BlendMode::LinearBurn);
+    assert!(inner_shadow.use_global_light());
+    assert_eq!(inner_shadow.noise(), 25.);
+
+    let outer_glow = effects.outer_glow().unwrap();
+    assert_eq!(outer_glow.blend_mode(), BlendMode::Screen);
+    assert_eq!(outer_glow.color(), [0, 255, 0]);
+    assert_eq!(outer_glow.opacity(), 128);
+    assert_eq!(outer_glow.size(), 3.);
+    assert_eq!(outer_glow.source(), GlowSource::Edge);
+
+    let inner_glow = effects.inner_glow().unwrap();
+    assert_eq!(inner_glow.color(), [255, 255, 255]);
+    assert_eq!(inner_glow.spread(), 20.);
+    assert_eq!(inner_glow.source(), GlowSource::Center);
+
+    let color_overlay = effects.color_overlay().unwrap();
+    assert_eq!(color_overlay.blend_mode(), BlendMode::Normal);
+    assert_eq!(color_overlay.color(), [255, 0, 0]);
+    assert_eq!(color_overlay.opacity(), 255);
+
+    let stroke = effects.stroke().unwrap();
+    assert_eq!(stroke.color(), [0, 0, 255]);
+    assert_eq!(stroke.size(), 3.);
+    assert_eq!(stroke.position(), StrokePosition::Inside);
+
+    assert!(effects.descriptor().fields.contains_key("FrFX"));
+
+    Ok(())
+}
+
+/// Layers without an effects block don't have effects, and effects blocks that are too short to
+/// hold a descriptor are ignored.
+///
+/// cargo test --test layer_effects no_layer_effects -- --exact
+#[test]
+fn no_layer_effects() -> Result<()> {
+    let psd = Psd::from_bytes(EFFECTS)?;
+    assert!(psd.layer_by_name("Plain").unwrap().effects().is_none());
+
+    let psd = Psd::from_bytes(include_bytes!(
+        "./fixtures/render-fidelity/unsupported-features.psd"
+    ))?;
+    assert!(psd.layer_by_name("Effects").unwrap().effects().is_none());
+
+    Ok(())
+}
+
+/// Verify that rendering with layer effects draws the color overlay, the stroke outside of the
+/// layer's content and the drop shadow below it.
+///
+/// cargo test --test layer_effects render_layer_effects -- --exact
+#[test]
+fn render_layer_effects() -> Result<()> {
+    let psd = Psd::from_bytes(include_bytes!(
+        "./fixtures/layer-effects/render-effects-8x8.psd"
+    ))?;
+    let pixel = |rgba: &[u8], x: usize, y: usize| {
+        let idx = (y * 8 + x) * 4;
+        [rgba[idx], rgba[idx + 1], rgba[idx + 2], rgba[idx + 3]]
+    };
+
+    let rgba = psd.render().layer_effects().to_rgba()?;
+    // The color overlay
+    assert_eq!(pixel(&rgba, 3, 3), [0, 255, 0, 255]);
+    // The stroke
+    assert_eq!(pixel(&rgba, 2, 3), [0, 0, 255, 255]);
+    assert_eq!(pixel(&rgba, 2, 2), [0, 0, 0, 0]);
+    // The drop shadow, two pixels below the content and its stroke
+    assert_eq!(pixel(&rgba, 3, 6), [0, 0, 0, 255]);
+    assert_eq!(pixel(&rgba, 3, 7), [0, 0, 0, 255]);
+    assert_eq!(pixel(&rgba, 1, 6), [0, 0, 0, 0]);
+
+    let rgba = psd.render().to_rgba()?;
+    assert_eq!(pixel(&rgba, 3, 3), [255, 0, 0, 255]);
+    assert_eq!(pixel(&rgba, 2, 3), [0, 0, 0, 0]);
+    assert_eq!(pixel(&rgba, 3, 6), [0, 0, 0, 0]);
+
+    Ok(())
+}
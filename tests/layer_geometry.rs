@@ -0,0 +1,72 @@
+use anyhow::Result;
+use psd::{LayerNodeKind, Psd};
+
+/// Groups come right before their children and their bounds cover the layers inside of them.
+///
+/// cargo test --test layer_geometry layer_geometry_tree -- --exact
+#[test]
+fn layer_geometry_tree() -> Result<()> {
+    let psd =
+        include_bytes!("./fixtures/groups/green-1x1-one-group-one-layer-inside-one-outside.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let geometry = psd.layer_geometry();
+    let nodes: Vec<(&str, LayerNodeKind)> = geometry
+        .iter()
+        .map(|node| (node.path(), node.kind()))
+        .collect();
+
+    assert_eq!(
+        nodes,
+        vec![
+            ("group", LayerNodeKind::Group),
+            ("group/First Layer", LayerNodeKind::Layer),
+            ("Second Layer", LayerNodeKind::Layer),
+        ]
+    );
+
+    let group = &geometry[0];
+    assert_eq!((group.left(), group.top()), (0, 0));
+    assert_eq!((group.width(), group.height()), (1, 1));
+    assert_eq!(group.anchor(), (0.5, 0.5));
+
+    Ok(())
+}
+
+/// cargo test --test layer_geometry layer_geometry_json_and_csv -- --exact
+#[test]
+fn layer_geometry_json_and_csv() -> Result<()> {
+    let psd = include_bytes!("./fixtures/transparent-top-layer-2x1.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let json = psd.layer_geometry_json();
+    assert!(json.starts_with(
+        r#"[{"path":"Blue Layer","kind":"layer","left":1,"top":0,"width":1,"height":1,"anchor":[0.75,0.5]},"#
+    ));
+
+    let csv = psd.layer_geometry_csv();
+    let mut lines = csv.lines();
+    assert_eq!(
+        lines.next(),
+        Some("path,kind,left,top,width,height,anchor_x,anchor_y")
+    );
+    assert_eq!(lines.next(), Some("Blue Layer,layer,1,0,1,1,0.75,0.5"));
+    assert_eq!(lines.count(), 2);
+
+    Ok(())
+}
+
+/// Layers that hang off of the top left of the document have negative positions.
+///
+/// cargo test --test layer_geometry layer_geometry_negative_position -- --exact
+#[test]
+fn layer_geometry_negative_position() -> Result<()> {
+    let psd = include_bytes!("./fixtures/negative-top-left-layer.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let geometry = psd.layer_geometry();
+    assert_eq!((geometry[0].left(), geometry[0].top()), (-4, -4));
+    assert_eq!((geometry[0].width(), geometry[0].height()), (9, 9));
+
+    Ok(())
+}
@@ -1,4 +1,4 @@
-use psd::{Psd, PsdGroup};
+use psd::{Psd, PsdGroup, PsdNode};
 const TOP_LEVEL_ID: u32 = 1;
 
 /// Verify that we can get a group by it's ID.
@@ -124,6 +124,48 @@ fn one_group_with_two_subgroups() {
     assert_eq!(layer.parent_id().unwrap(), outside_group.id());
 }
 
+/// Verify that the tree nests each group's children inside of it in stacking order.
+///
+/// cargo test --test layer_groups tree -- --exact
+#[test]
+fn tree() {
+    let psd = include_bytes!("fixtures/groups/green-1x1-one-group-with-two-subgroups.psd");
+    let psd = Psd::from_bytes(psd).unwrap();
+
+    let mut lines = vec![];
+    push_lines(&psd.tree(), 0, &mut lines);
+
+    assert_eq!(
+        lines,
+        vec![
+            "outside group",
+            "  first group inside",
+            "    First Layer",
+            "  second group inside",
+            "    sub sub group",
+            "      Second Layer",
+            "    Third Layer",
+            "  third group inside",
+            "  Fourth Layer",
+            "Firth Layer",
+            "outside group 2",
+            "  Sixth Layer",
+        ]
+    );
+
+    match &psd.tree()[0] {
+        PsdNode::Group { properties, .. } => assert_eq!(properties.id(), 1),
+        PsdNode::Layer(_) => panic!("expected a group"),
+    }
+}
+
+fn push_lines(nodes: &[PsdNode], depth: usize, lines: &mut Vec<String>) {
+    for node in nodes {
+        lines.push(format!("{:indent$}{}", "", node.name(), indent = depth * 2));
+        push_lines(node.children(), depth + 1, lines);
+    }
+}
+
 /// Verify that we can properly load an RLEcompressed empty channel (caused by a group from GIMP)
 #[test]
 fn rle_compressed_empty_channel() {
@@ -133,9 +175,110 @@ fn rle_compressed_empty_channel() {
 }
 
 fn group_by_name<'a>(psd: &'a Psd, name: &str) -> &'a PsdGroup {
-    psd.groups()
-        .iter()
-        .find(|group| group.1.name() == name)
-        .unwrap()
-        .1
+    psd.groups().by_name(name).unwrap()
+}
+
+/// Photoshop marks the pixel data of groups as irrelevant to the appearance of the document.
+///
+/// cargo test --test layer_groups group_and_layer_flags -- --exact
+#[test]
+fn group_and_layer_flags() {
+    let psd = include_bytes!("fixtures/groups/green-1x1-one-group-one-layer-inside.psd");
+    let psd = Psd::from_bytes(psd).unwrap();
+
+    let group = psd.groups().get(&1).unwrap();
+    assert!(group.flags().bit4_valid());
+    assert!(group.flags().pixel_data_irrelevant());
+    assert!(!group.flags().transparency_protected());
+
+    let layer = psd.layer_by_idx(0);
+    assert!(layer.flags().bit4_valid());
+    assert!(!layer.flags().pixel_data_irrelevant());
+    assert_eq!(layer.flags().bits(), 0b1000);
+}
+
+/// Verify that we can look up layers by their ID, name and path.
+///
+/// cargo test --test layer_groups layer_lookups -- --exact
+#[test]
+fn layer_lookups() {
+    let psd = include_bytes!("fixtures/groups/green-1x1-one-group-with-two-subgroups.psd");
+    let psd = Psd::from_bytes(psd).unwrap();
+
+    let layer = psd.layer_by_id(4).unwrap();
+    assert_eq!(layer.name(), "Second Layer");
+    assert_eq!(layer.layer_id(), Some(4));
+    assert!(psd.layer_by_id(1000).is_none());
+
+    let layer = psd
+        .layer_by_path("outside group/second group inside/sub sub group/Second Layer")
+        .unwrap();
+    assert_eq!(layer.layer_id(), Some(4));
+
+    let layer = psd.layer_by_path("Firth Layer").unwrap();
+    assert_eq!(layer.layer_id(), Some(37));
+    assert!(psd.layer_by_path("Second Layer").is_none());
+
+    let layers = psd.layers_by_name("Sixth Layer");
+    assert_eq!(layers.len(), 1);
+    assert_eq!(layers[0].layer_id(), Some(36));
+    assert!(psd.layers_by_name("Missing Layer").is_empty());
+}
+
+/// Verify that groups are iterated in the order of the layers panel, from the top down with
+/// each group before the groups inside of it.
+///
+/// cargo test --test layer_groups groups_in_order -- --exact
+#[test]
+fn groups_in_order() {
+    let psd = include_bytes!("fixtures/groups/green-1x1-one-group-with-two-subgroups.psd");
+    let psd = Psd::from_bytes(psd).unwrap();
+
+    let ids: Vec<u32> = psd.groups_in_order().map(|group| group.id()).collect();
+    assert_eq!(ids, [1, 2, 3, 4, 5, 6]);
+
+    let names: Vec<&str> = psd.groups_in_order().map(|group| group.name()).collect();
+    assert_eq!(
+        names,
+        [
+            "outside group",
+            "first group inside",
+            "second group inside",
+            "sub sub group",
+            "third group inside",
+            "outside group 2",
+        ]
+    );
+}
+
+/// Verify that the layer and group collections can be counted, iterated and looked up.
+///
+/// cargo test --test layer_groups layer_and_group_collections -- --exact
+#[test]
+fn layer_and_group_collections() {
+    let psd = include_bytes!("fixtures/groups/green-1x1-one-group-with-two-subgroups.psd");
+    let psd = Psd::from_bytes(psd).unwrap();
+
+    let layers = psd.layers();
+    assert_eq!(layers.iter().count(), layers.len());
+    assert!(!layers.is_empty());
+    assert_eq!(
+        layers.get(0).map(|layer| layer.name()),
+        Some(layers[0].name())
+    );
+    assert!(layers.get(layers.len()).is_none());
+    assert_eq!(layers.by_id(4).unwrap().name(), "Second Layer");
+    assert_eq!(layers.by_name("Sixth Layer").unwrap().layer_id(), Some(36));
+
+    let groups = psd.groups();
+    assert_eq!(groups.len(), 6);
+    assert_eq!(groups.ids(), psd.group_ids_in_order());
+    let ids: Vec<u32> = groups.iter().map(|group| group.id()).collect();
+    assert_eq!(&ids, groups.ids());
+    assert!(groups.contains(&ids[0]));
+    assert_eq!(groups.get(&ids[0]).unwrap().id(), ids[0]);
+    assert!(groups.get(&1000).is_none());
+
+    let name = groups[&ids[1]].name();
+    assert_eq!(groups.by_name(name).unwrap().id(), ids[1]);
 }
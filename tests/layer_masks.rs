@@ -0,0 +1,96 @@
+use anyhow::Result;
+use psd::Psd;
+
+/// The alpha of each pixel in a rendered image
+fn alphas(rgba: &[u8]) -> Vec<u8> {
+    rgba.chunks_exact(4).map(|pixel| pixel[3]).collect()
+}
+
+/// Pixels outside of the mask's rectangle are hidden when its default color is black.
+///
+/// cargo test --test layer_masks default_color_0 -- --exact
+#[test]
+fn default_color_0() -> Result<()> {
+    let psd = include_bytes!("./fixtures/layer-masks/red-3x3-mask-default-color-0.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let mask = psd.layers()[0].mask().unwrap();
+    assert_eq!(mask.default_color(), 0);
+
+    let flattened = psd.flatten_layers_rgba(&|_| true)?;
+    assert_eq!(alphas(&flattened), vec![0, 0, 0, 0, 255, 0, 0, 0, 0]);
+    assert_eq!(&flattened[16..20], &[255, 0, 0, 255]);
+
+    Ok(())
+}
+
+/// Pixels outside of the mask's rectangle are shown when its default color is white.
+///
+/// cargo test --test layer_masks default_color_255 -- --exact
+#[test]
+fn default_color_255() -> Result<()> {
+    let psd = include_bytes!("./fixtures/layer-masks/red-3x3-mask-default-color-255.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let layer = &psd.layers()[0];
+    assert_eq!(layer.mask().unwrap().default_color(), 255);
+    assert_eq!(
+        layer.mask_pixels().unwrap(),
+        vec![255, 255, 255, 255, 0, 255, 255, 255, 255]
+    );
+
+    let flattened = psd.flatten_layers_rgba(&|_| true)?;
+    assert_eq!(
+        alphas(&flattened),
+        vec![255, 255, 255, 255, 0, 255, 255, 255, 255]
+    );
+
+    Ok(())
+}
+
+/// A mask that is positioned relative to its layer is moved to the layer's position.
+///
+/// cargo test --test layer_masks position_relative_to_layer -- --exact
+#[test]
+fn position_relative_to_layer() -> Result<()> {
+    let psd = include_bytes!("./fixtures/layer-masks/red-3x3-mask-relative-to-layer.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let mask = psd.layers()[0].mask().unwrap();
+    assert!(mask.flags().position_relative_to_layer());
+    assert!(mask.is_linked());
+    assert_eq!(psd.layers()[0].is_mask_linked(), Some(true));
+
+    let flattened = psd.flatten_layers_rgba(&|_| true)?;
+    assert_eq!(alphas(&flattened), vec![0, 0, 0, 0, 0, 255, 0, 255, 255]);
+
+    Ok(())
+}
+
+/// A layer's mask can be exported as grayscale or RGBA pixels, and written as PNGs along with
+/// its layer.
+///
+/// cargo test --test layer_masks export_mask -- --exact
+#[test]
+fn export_mask() -> Result<()> {
+    let psd = include_bytes!("./fixtures/layer-masks/red-3x3-mask-default-color-255.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let layer = &psd.layers()[0];
+    assert_eq!(layer.mask_gray(), layer.mask_pixels());
+
+    let rgba = layer.mask_rgba().unwrap();
+    assert_eq!(rgba.len(), 9 * 4);
+    assert_eq!(&rgba[12..20], &[255, 255, 255, 255, 0, 0, 0, 255]);
+
+    let dir = std::env::temp_dir().join(format!("psd-export-mask-{}", std::process::id()));
+    let written = psd.write_layer_pngs(&dir)?;
+    assert_eq!(written.len(), 2);
+    assert!(written[1].to_str().unwrap().ends_with("-mask.png"));
+    for path in written.iter() {
+        assert_eq!(&std::fs::read(path)?[..8], b"\x89PNG\r\n\x1a\n");
+    }
+    std::fs::remove_dir_all(&dir)?;
+
+    Ok(())
+}
@@ -0,0 +1,59 @@
+use anyhow::Result;
+use psd::{MajorSections, Psd};
+
+const PSD: &[u8] = include_bytes!("./fixtures/two-layers-red-green-1x1.psd");
+
+/// The five major sections cover the whole file, back to back.
+///
+/// cargo test --test major_sections split_sections -- --exact
+#[test]
+fn split_sections() -> Result<()> {
+    let sections = MajorSections::from_bytes(PSD)?;
+
+    assert_eq!(sections.file_header().len(), 26);
+    assert_eq!(&sections.file_header()[0..4], b"8BPS");
+
+    let joined: Vec<u8> = [
+        sections.file_header(),
+        sections.color_mode_data(),
+        sections.image_resources(),
+        sections.layer_and_mask(),
+        sections.image_data(),
+    ]
+    .concat();
+    assert_eq!(joined, PSD);
+
+    Ok(())
+}
+
+/// Sections can be replaced without parsing the rest of the file.
+///
+/// cargo test --test major_sections strip_image_resources -- --exact
+#[test]
+fn strip_image_resources() -> Result<()> {
+    let sections = MajorSections::from_bytes(PSD)?;
+    assert!(sections.image_resources().len() > 4);
+
+    let stripped: Vec<u8> = [
+        sections.file_header(),
+        sections.color_mode_data(),
+        &[0, 0, 0, 0],
+        sections.layer_and_mask(),
+        sections.image_data(),
+    ]
+    .concat();
+
+    let original = Psd::from_bytes(PSD)?;
+    let psd = Psd::from_bytes(&stripped)?;
+    assert!(psd.resources().is_empty());
+    assert_eq!(psd.layers().len(), original.layers().len());
+    assert_eq!(psd.rgba(), original.rgba());
+
+    Ok(())
+}
+
+/// cargo test --test major_sections truncated -- --exact
+#[test]
+fn truncated() {
+    assert!(MajorSections::from_bytes(&PSD[..30]).is_err());
+}
@@ -0,0 +1,58 @@
+use std::panic::{RefUnwindSafe, UnwindSafe};
+
+use anyhow::Result;
+use psd::{NodeId, Psd};
+
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Selected(bool);
+
+/// Verify that data attached to groups and layers can be read back.
+///
+/// cargo test --test node_data attach_node_data -- --exact
+#[test]
+fn attach_node_data() -> Result<()> {
+    let psd = include_bytes!("./fixtures/groups/green-1x1-one-group-one-layer-inside.psd");
+    let mut psd = Psd::from_bytes(psd)?;
+    let original = psd.clone();
+
+    let layer = NodeId::Layer(0);
+    let group = NodeId::Group(1);
+
+    assert_eq!(psd.node_data::<Selected>(layer), None);
+    psd.node_data_mut::<Selected>(layer).0 = true;
+    assert_eq!(psd.node_data(layer), Some(&Selected(true)));
+
+    assert_eq!(psd.insert_node_data(group, String::from("HUD")), None);
+    assert_eq!(psd.node_data::<String>(group).unwrap(), "HUD");
+    assert_eq!(psd.node_data::<Selected>(group), None);
+
+    // Attached data belongs to the caller, not the file
+    assert_eq!(psd, original);
+
+    assert_eq!(psd.remove_node_data(layer), Some(Selected(true)));
+    assert_eq!(psd.node_data::<Selected>(layer), None);
+
+    Ok(())
+}
+
+/// Attaching data must not stop a Psd from being shared across threads.
+///
+/// cargo test --test node_data psd_is_send_and_sync -- --exact
+#[test]
+fn psd_is_send_and_sync() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Psd>();
+}
+
+/// Attaching data must not stop a Psd from being used inside of `std::panic::catch_unwind`.
+///
+/// cargo test --test node_data psd_is_unwind_safe -- --exact
+#[test]
+fn psd_is_unwind_safe() {
+    fn assert_unwind_safe<T: UnwindSafe + RefUnwindSafe>() {}
+    assert_unwind_safe::<Psd>();
+    assert_unwind_safe::<&Psd>();
+
+    let psd = Psd::from_bytes(include_bytes!("./fixtures/green-1x1.psd")).unwrap();
+    assert!(std::panic::catch_unwind(|| psd.rgba()).is_ok());
+}
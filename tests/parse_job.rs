@@ -0,0 +1,59 @@
+use anyhow::Result;
+use psd::{ParseJob, ParseProgress, Psd};
+
+const GROUPS: &[u8] =
+    include_bytes!("./fixtures/groups/green-1x1-one-group-one-layer-inside-one-outside.psd");
+
+/// Verify that a job parses the header, the resources and then a few layer records and their
+/// channels per step, making each part available as soon as it has been parsed.
+///
+/// cargo test --test parse_job parse_in_steps -- --exact
+#[test]
+fn parse_in_steps() -> Result<()> {
+    let expected = Psd::from_bytes(GROUPS)?;
+    // One layer inside of a group and one outside of it, plus the records that open and close
+    // the group
+    let total = 4;
+
+    let mut job = ParseJob::new(GROUPS).layers_per_step(3);
+    assert_eq!(job.dimensions(), None);
+
+    assert_eq!(job.step()?, ParseProgress::Header);
+    assert_eq!(job.dimensions(), Some((1, 1)));
+    assert_eq!(job.color_mode(), Some(expected.color_mode()));
+    assert_eq!(job.resources(), None);
+
+    assert_eq!(job.step()?, ParseProgress::ImageResources);
+    assert_eq!(job.resources(), Some(&expected.resources()[..]));
+
+    assert_eq!(job.step()?, ParseProgress::LayerRecords { read: 3, total });
+    assert_eq!(job.step()?, ParseProgress::LayerRecords { read: 4, total });
+    assert!(job.layers().is_none());
+
+    // The channels come after every record
+    assert_eq!(job.step()?, ParseProgress::LayerChannels { read: 3, total });
+    assert_eq!(job.step()?, ParseProgress::LayerChannels { read: 4, total });
+    assert_eq!(job.layers(), Some(&expected.layers()[..]));
+    assert!(!job.is_done());
+
+    assert_eq!(job.step()?, ParseProgress::Done);
+    assert!(job.is_done());
+    assert_eq!(job.step()?, ParseProgress::Done);
+
+    assert_eq!(job.finish()?, expected);
+
+    Ok(())
+}
+
+/// Finishing a job that hasn't taken any steps parses the whole file.
+///
+/// cargo test --test parse_job finish_without_steps -- --exact
+#[test]
+fn finish_without_steps() -> Result<()> {
+    let psd = include_bytes!("./fixtures/two-layers-red-green-1x1.psd");
+
+    assert_eq!(ParseJob::new(psd).finish()?, Psd::from_bytes(psd)?);
+    assert!(ParseJob::new(&[0; 4]).finish().is_err());
+
+    Ok(())
+}
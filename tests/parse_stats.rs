@@ -0,0 +1,35 @@
+use anyhow::Result;
+use psd::{Psd, PsdChannelCompression};
+
+/// cargo test --test parse_stats parse_stats -- --exact
+#[test]
+fn parse_stats() -> Result<()> {
+    let psd = include_bytes!("./fixtures/rle-3-layer-8x8.psd");
+
+    let (psd, stats) = Psd::from_bytes_with_stats(psd)?;
+
+    assert_eq!(psd.compression(), &PsdChannelCompression::RleCompressed);
+    assert_eq!(stats.layers_parsed(), 3);
+    assert!(stats.compressed_bytes() > 0);
+
+    let sections = stats.file_header_section()
+        + stats.image_resources_section()
+        + stats.layer_and_mask_information_section()
+        + stats.image_data_section();
+    assert!(stats.total() >= sections);
+
+    Ok(())
+}
+
+/// cargo test --test parse_stats parse_stats_raw_data -- --exact
+#[test]
+fn parse_stats_raw_data() -> Result<()> {
+    let psd = include_bytes!("./fixtures/green-1x1.psd");
+
+    let (psd, stats) = Psd::from_bytes_with_stats(psd)?;
+
+    assert_eq!(psd.compression(), &PsdChannelCompression::RawData);
+    assert_eq!(stats.layers_parsed(), 1);
+
+    Ok(())
+}
@@ -0,0 +1,122 @@
+use std::io::{Cursor, Read, Seek, SeekFrom};
+
+use anyhow::Result;
+use psd::{FileHeaderSectionError, MajorSections, Psd, PsdError, PsdVersion};
+
+const GROUPS: &[u8] =
+    include_bytes!("./fixtures/groups/green-1x1-one-group-one-layer-inside-one-outside.psd");
+const LR16: &[u8] = include_bytes!("./fixtures/high-bit-depth/lr16-2x1.psd");
+const SLICES: &[u8] = include_bytes!("./fixtures/slices-v8.psd");
+
+/// The header, layer names and resources match the ones parsed from the whole file.
+///
+/// cargo test --test psd_reader read_sections -- --exact
+#[test]
+fn read_sections() -> Result<()> {
+    for bytes in [GROUPS, LR16, SLICES].iter() {
+        let psd = Psd::from_bytes(bytes)?;
+        let mut reader = Psd::from_reader(Cursor::new(bytes))?;
+
+        assert_eq!(reader.dimensions(), (psd.width(), psd.height()));
+        assert_eq!(reader.depth(), psd.depth());
+        assert_eq!(reader.color_mode(), psd.color_mode());
+        assert_eq!(reader.version(), PsdVersion::One);
+        assert_eq!(&reader.resources()?, psd.resources());
+        assert_eq!(reader.layer_names()?, layer_names(&psd));
+
+        assert_eq!(reader.into_psd()?, psd);
+    }
+
+    Ok(())
+}
+
+/// PSB files have 8 byte length markers for their layer and mask information and channels.
+///
+/// cargo test --test psd_reader read_psb -- --exact
+#[test]
+fn read_psb() -> Result<()> {
+    for bytes in [GROUPS, LR16].iter() {
+        let psd = Psd::from_bytes(bytes)?;
+        let psb = psd.to_bytes_as(PsdVersion::Two)?;
+        let mut reader = Psd::from_reader(Cursor::new(&psb))?;
+
+        assert_eq!(reader.version(), PsdVersion::Two);
+        assert_eq!(reader.layer_names()?, layer_names(&psd));
+    }
+
+    Ok(())
+}
+
+/// Reading the layer names doesn't read the channels or the final image.
+///
+/// cargo test --test psd_reader layer_names_skip_channels -- --exact
+#[test]
+fn layer_names_skip_channels() -> Result<()> {
+    let image_data_len = MajorSections::from_bytes(GROUPS)?.image_data().len();
+
+    let counting_reader = CountingReader::new(GROUPS);
+    let read = counting_reader.read.clone();
+    let mut reader = Psd::from_reader(counting_reader)?;
+    reader.layer_names()?;
+    reader.resources()?;
+
+    // The channels come after the records at the end of the layer and mask information
+    let read = read.get();
+    assert!(read < GROUPS.len() - image_data_len, "Read {} bytes", read);
+
+    Ok(())
+}
+
+/// A file that ends before the end of one of its sections is reported as truncated.
+///
+/// cargo test --test psd_reader truncated_file -- --exact
+#[test]
+fn truncated_file() {
+    let truncated = &GROUPS[..40];
+
+    match Psd::from_reader(Cursor::new(truncated)) {
+        Err(PsdError::HeaderError(FileHeaderSectionError::TruncatedSection { .. })) => {}
+        other => panic!("Should have been truncated, got {:?}", other.err()),
+    };
+
+    match Psd::from_reader(Cursor::new(&GROUPS[..10])) {
+        Err(PsdError::HeaderError(FileHeaderSectionError::IncorrectLength { length: 10 })) => {}
+        other => panic!("Should have been too short, got {:?}", other.err()),
+    };
+}
+
+fn layer_names(psd: &Psd) -> Vec<String> {
+    psd.layers()
+        .iter()
+        .map(|layer| layer.name().to_string())
+        .collect()
+}
+
+/// Counts the bytes that are read through it.
+struct CountingReader<'a> {
+    cursor: Cursor<&'a [u8]>,
+    read: std::rc::Rc<std::cell::Cell<usize>>,
+}
+
+impl<'a> CountingReader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        CountingReader {
+            cursor: Cursor::new(bytes),
+            read: Default::default(),
+        }
+    }
+}
+
+impl Read for CountingReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.cursor.read(buf)?;
+        self.read.set(self.read.get() + read);
+        Ok(read)
+    }
+}
+
+impl Seek for CountingReader<'_> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.cursor.seek(pos)
+    }
+}
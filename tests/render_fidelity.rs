@@ -0,0 +1,92 @@
+use anyhow::Result;
+use psd::{BlendMode, FidelityIssueKind, NodeId, Psd};
+
+/// Every layer in the fixture has one thing that rendering ignores.
+///
+/// cargo test --test render_fidelity unsupported_features -- --exact
+#[test]
+fn unsupported_features() -> Result<()> {
+    let psd = include_bytes!("./fixtures/render-fidelity/unsupported-features.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let report = psd.render_fidelity_report();
+    assert!(!report.is_faithful());
+
+    let issues: Vec<(Option<NodeId>, &str, FidelityIssueKind)> = report
+        .issues()
+        .iter()
+        .map(|issue| (issue.node(), issue.name(), issue.kind()))
+        .collect();
+    assert_eq!(
+        issues,
+        vec![
+            (
+                Some(NodeId::Layer(0)),
+                "Hue",
+                FidelityIssueKind::UnsupportedBlendMode(BlendMode::Hue)
+            ),
+            (
+                Some(NodeId::Layer(1)),
+                "Blend if",
+                FidelityIssueKind::BlendingRanges
+            ),
+            (
+                Some(NodeId::Layer(2)),
+                "Vector mask",
+                FidelityIssueKind::VectorMask
+            ),
+            (
+                Some(NodeId::Layer(3)),
+                "Levels",
+                FidelityIssueKind::AdjustmentLayer(*b"levl")
+            ),
+            (
+                Some(NodeId::Layer(5)),
+                "Effects",
+                FidelityIssueKind::LayerEffects
+            ),
+        ]
+    );
+
+    assert_eq!(
+        report.to_string().lines().next(),
+        Some("Layer 'Hue': unsupported blend mode Hue")
+    );
+
+    Ok(())
+}
+
+/// Clipped layers are rendered without being clipped.
+///
+/// cargo test --test render_fidelity clipping_masks -- --exact
+#[test]
+fn clipping_masks() -> Result<()> {
+    let psd = include_bytes!("./fixtures/green-clipping-10x10.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let report = psd.render_fidelity_report();
+    let clipped: Vec<&str> = report
+        .issues()
+        .iter()
+        .filter(|issue| issue.kind() == FidelityIssueKind::ClippingMask)
+        .map(|issue| issue.name())
+        .collect();
+    assert_eq!(clipped, vec!["Second clipped layer", "First clipped layer"]);
+
+    Ok(())
+}
+
+/// A plain RGB PSD renders the same as it does in Photoshop.
+///
+/// cargo test --test render_fidelity faithful -- --exact
+#[test]
+fn faithful() -> Result<()> {
+    let psd = include_bytes!("./fixtures/two-layers-red-green-1x1.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let report = psd.render_fidelity_report();
+    assert!(report.is_faithful());
+    assert_eq!(report.to_string(), "");
+
+    Ok(())
+}
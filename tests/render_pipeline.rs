@@ -0,0 +1,283 @@
+use anyhow::Result;
+use psd::{
+    BlendMode, CropRect, DrawCommand, ExifOrientation, LayerRgbaCache, Psd, PsdError, PsdLayer,
+};
+use std::cell::RefCell;
+
+const WHITE_PIXEL: [u8; 4] = [255, 255, 255, 255];
+const BLUE_PIXEL: [u8; 4] = [0, 0, 255, 255];
+
+/// Rendering the visible layers is the same as flattening every layer.
+///
+/// cargo test --test render_pipeline render_matches_flatten_layers -- --exact
+#[test]
+fn render_matches_flatten_layers() -> Result<()> {
+    let psd = include_bytes!("./fixtures/transparent-top-layer-2x1.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    assert_eq!(
+        psd.render().visible_only().to_rgba()?,
+        psd.flatten_layers_rgba(&|_| true)?
+    );
+
+    Ok(())
+}
+
+/// The background shows through the transparent pixels of the rendered layers.
+///
+/// cargo test --test render_pipeline render_background -- --exact
+#[test]
+fn render_background() -> Result<()> {
+    let psd = include_bytes!("./fixtures/transparent-top-layer-2x1.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let rgba = psd
+        .render()
+        .filter(|(_, layer)| layer.name() == "Blue Layer")
+        .background(WHITE_PIXEL)
+        .to_rgba()?;
+
+    assert_eq!(&rgba[0..4], &WHITE_PIXEL);
+    assert_eq!(&rgba[4..8], &BLUE_PIXEL);
+
+    Ok(())
+}
+
+/// Scaling the render only renders the sampled pixels.
+///
+/// cargo test --test render_pipeline render_scaled -- --exact
+#[test]
+fn render_scaled() -> Result<()> {
+    let psd = include_bytes!("./fixtures/rle-3-layer-8x8.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let pipeline = psd.render().visible_only().scale(0.5);
+    assert_eq!(pipeline.dimensions(), (4, 4));
+
+    let rgba = pipeline.to_rgba()?;
+    assert_eq!(rgba.len(), 4 * 4 * 4);
+    assert!(rgba.chunks(4).all(|pixel| pixel == BLUE_PIXEL));
+
+    let pipeline = psd.render().scale(2.);
+    assert_eq!(pipeline.dimensions(), (16, 16));
+    assert_eq!(pipeline.to_rgba()?.len(), 16 * 16 * 4);
+
+    Ok(())
+}
+
+/// Only the layers inside of the group are rendered.
+///
+/// cargo test --test render_pipeline render_group -- --exact
+#[test]
+fn render_group() -> Result<()> {
+    let psd = include_bytes!("./fixtures/groups/green-1x1-one-group-with-two-subgroups.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let rendered = RefCell::new(vec![]);
+    psd.render()
+        .group("outside group/second group inside")
+        .filter(|(_, layer)| {
+            rendered.borrow_mut().push(layer.name().to_string());
+            true
+        })
+        .to_rgba()?;
+
+    assert_eq!(*rendered.borrow(), vec!["Second Layer", "Third Layer"]);
+
+    assert_eq!(
+        psd.render().group("second group inside").to_rgba(),
+        Err(PsdError::GroupNotFound("second group inside".to_string()))
+    );
+
+    Ok(())
+}
+
+/// Rendered pixels are converted into video range BT.709 YCbCr.
+///
+/// cargo test --test render_pipeline render_ycbcr -- --exact
+#[test]
+fn render_ycbcr() -> Result<()> {
+    let psd = include_bytes!("./fixtures/transparent-top-layer-2x1.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let pipeline = psd
+        .render()
+        .filter(|(_, layer)| layer.name() == "Blue Layer")
+        .background(WHITE_PIXEL);
+
+    // White, then blue
+    assert_eq!(pipeline.to_ycbcr()?, vec![235, 128, 128, 32, 240, 118]);
+    assert_eq!(
+        pipeline.to_ycbcr_planar()?,
+        [vec![235, 32], vec![128, 240], vec![128, 118]]
+    );
+
+    Ok(())
+}
+
+/// The rendered image is rotated according to the EXIF orientation only when asked to.
+///
+/// cargo test --test render_pipeline render_exif_orientation -- --exact
+#[test]
+fn render_exif_orientation() -> Result<()> {
+    let psd = include_bytes!("./fixtures/transparent-top-layer-2x1-exif-rotate-270.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    assert_eq!(psd.exif_orientation(), Some(ExifOrientation::Rotate270));
+
+    let pipeline = psd
+        .render()
+        .filter(|(_, layer)| layer.name() == "Blue Layer")
+        .background(WHITE_PIXEL);
+
+    assert_eq!(pipeline.dimensions(), (2, 1));
+    assert_eq!(pipeline.to_rgba()?, [WHITE_PIXEL, BLUE_PIXEL].concat());
+
+    // Rotating 270 degrees clockwise moves the right pixel to the top
+    let pipeline = pipeline.exif_orientation();
+    assert_eq!(pipeline.dimensions(), (1, 2));
+    assert_eq!(pipeline.to_rgba()?, [BLUE_PIXEL, WHITE_PIXEL].concat());
+
+    let orientation = psd.exif_orientation().unwrap();
+    assert_eq!(
+        orientation.apply_to_rgba(&psd.rgba(), psd.width(), psd.height()),
+        [&psd.rgba()[4..8], &psd.rgba()[0..4]].concat()
+    );
+
+    let psd = include_bytes!("./fixtures/transparent-top-layer-2x1.psd");
+    assert_eq!(
+        Psd::from_bytes(psd)?.exif_orientation(),
+        Some(ExifOrientation::Normal)
+    );
+
+    Ok(())
+}
+
+/// Renders that share a layer cache decode each layer once and render the same pixels as
+/// renders that don't, and the cached layers can be reused for previews.
+///
+/// cargo test --test render_pipeline render_with_layer_cache -- --exact
+#[test]
+fn render_with_layer_cache() -> Result<()> {
+    let psd = include_bytes!("./fixtures/rle-3-layer-8x8.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let layer_cache = LayerRgbaCache::new();
+    assert!(!layer_cache.contains(0));
+
+    let rgba = psd.render().layer_cache(&layer_cache).to_rgba()?;
+    assert_eq!(rgba, psd.render().to_rgba()?);
+    assert!(layer_cache.contains(0));
+
+    let top_layer = layer_cache.layer_rgba(&psd, 0).unwrap();
+    assert_eq!(&top_layer[..], &psd.layers()[0].rgba()[..]);
+    assert!(layer_cache.layer_rgba(&psd, psd.layers().len()).is_none());
+
+    layer_cache.clear();
+    assert!(!layer_cache.contains(0));
+
+    Ok(())
+}
+
+/// Layers that can't be rendered are drawn as a placeholder when asked to, while the layers that
+/// can be rendered stay the same.
+///
+/// cargo test --test render_pipeline unsupported_placeholder -- --exact
+#[test]
+fn unsupported_placeholder() -> Result<()> {
+    const MAGENTA: [u8; 4] = [255, 0, 255, 255];
+    let render_only = |psd: &Psd, name: &'static str| {
+        psd.render()
+            .filter(move |(_, layer)| layer.name() == name)
+            .unsupported_placeholder(MAGENTA)
+            .to_rgba()
+    };
+
+    let psd = include_bytes!("./fixtures/render-fidelity/unsupported-features.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    // The hue blend mode would panic without the placeholder
+    assert_eq!(render_only(&psd, "Hue")?, MAGENTA);
+    assert_eq!(render_only(&psd, "Levels")?, MAGENTA);
+    assert_eq!(render_only(&psd, "Vector mask")?, [255, 0, 0, 255]);
+
+    // The curves layer's mask only shows its placeholder on the left pixel
+    let psd = include_bytes!("./fixtures/adjustment-layers/render-adjustments-2x1.psd");
+    let psd = Psd::from_bytes(psd)?;
+    let base_and_curves =
+        |(_, layer): (usize, &PsdLayer)| layer.name() == "Base" || layer.name() == "Curves";
+    let rgba = psd
+        .render()
+        .filter(base_and_curves)
+        .unsupported_placeholder(MAGENTA)
+        .to_rgba()?;
+    assert_eq!(rgba, [MAGENTA, [50, 150, 250, 255]].concat());
+
+    // Adjustments that get applied don't need a placeholder
+    let rgba = psd
+        .render()
+        .filter(base_and_curves)
+        .adjustment_layers()
+        .unsupported_placeholder(MAGENTA)
+        .to_rgba()?;
+    assert_eq!(rgba, [55, 155, 205, 255, 50, 150, 250, 255]);
+
+    Ok(())
+}
+
+/// The render plan lists the layers from the bottom up, wrapping the groups that blend their
+/// contents on their own.
+///
+/// cargo test --test render_pipeline render_plan -- --exact
+#[test]
+fn render_plan() -> Result<()> {
+    let psd = include_bytes!("./fixtures/group-blending/multiply-group.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let plan = psd.render().background(WHITE_PIXEL).to_plan()?;
+    assert_eq!((plan.width(), plan.height()), (1, 1));
+    assert_eq!(plan.background(), Some(WHITE_PIXEL));
+
+    let commands = plan.commands();
+    assert_eq!(commands.len(), 4);
+    match (&commands[0], &commands[1], &commands[2], &commands[3]) {
+        (
+            DrawCommand::Layer(blue),
+            DrawCommand::BeginGroup(group),
+            DrawCommand::Layer(red),
+            DrawCommand::EndGroup,
+        ) => {
+            assert_eq!(blue.layer().name(), "Blue");
+            assert_eq!(blue.layer_idx(), 1);
+            assert_eq!(blue.bounds(), Some(CropRect::new(0, 0, 1, 1)));
+            assert_eq!(blue.rgba(), BLUE_PIXEL);
+            assert_eq!(blue.blend_mode(), BlendMode::Normal);
+            assert_eq!(blue.opacity(), 255);
+            assert!(!blue.is_clipped());
+
+            assert_eq!(group.group().name(), "group");
+            assert_eq!(group.blend_mode(), BlendMode::Multiply);
+            assert_eq!(group.opacity(), 255);
+
+            assert_eq!(red.layer().name(), "Red");
+            assert_eq!(red.rgba(), [255, 0, 0, 255]);
+        }
+        _ => panic!("{:?}", commands),
+    }
+
+    let names: Vec<&str> = psd
+        .render()
+        .group("group")
+        .to_plan()?
+        .layers()
+        .map(|layer| layer.layer().name())
+        .collect();
+    assert_eq!(names, vec!["Red"]);
+
+    assert!(matches!(
+        psd.render().group("missing").to_plan(),
+        Err(PsdError::GroupNotFound(_))
+    ));
+
+    Ok(())
+}
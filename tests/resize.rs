@@ -0,0 +1,152 @@
+use anyhow::Result;
+use psd::{Psd, PsdError, ResizeFilter};
+
+const ASYMMETRIC: &[u8] = include_bytes!("./fixtures/transform/asymmetric-4x3.psd");
+
+/// Fixtures with different compressions, depths and color modes.
+const FIXTURES: [&[u8]; 10] = [
+    ASYMMETRIC,
+    include_bytes!("./fixtures/rle-3-layer-8x8.psd"),
+    include_bytes!("./fixtures/transparent-top-layer-2x1.psd"),
+    include_bytes!("./fixtures/layer-masks/red-3x3-mask-default-color-0.psd"),
+    include_bytes!("./fixtures/two-channel-8x8.psd"),
+    include_bytes!("./fixtures/zip-compression/rgb-3x1-zip-with-prediction.psd"),
+    include_bytes!("./fixtures/zip-compression/hdr-2x1-zip-with-prediction.psd"),
+    include_bytes!("./fixtures/bitmap-mode/bitmap-10x2-rle.psd"),
+    include_bytes!("./fixtures/cmyk/cmyk-2x1.psd"),
+    include_bytes!("./fixtures/indexed/indexed-3x1.psd"),
+];
+
+/// Doubling the size of a PSD with the nearest neighbor filter repeats every pixel of the final
+/// image and of every layer and mask, before and after writing the resized PSD.
+///
+/// cargo test --test resize double_nearest_neighbor -- --exact
+#[test]
+fn double_nearest_neighbor() -> Result<()> {
+    for bytes in FIXTURES.iter() {
+        let original = Psd::from_bytes(bytes)?;
+        let (width, height) = (original.width(), original.height());
+
+        let psd = original.resize(width * 2, height * 2, ResizeFilter::NearestNeighbor)?;
+        assert_eq!((psd.width(), psd.height()), (width * 2, height * 2));
+
+        let doubled = |pixels: &[u8], bytes_per_pixel: usize| -> Vec<u8> {
+            let row_len = width as usize * bytes_per_pixel;
+
+            pixels
+                .chunks(row_len)
+                .flat_map(|row| {
+                    let row: Vec<u8> = row
+                        .chunks(bytes_per_pixel)
+                        .flat_map(|pixel| pixel.repeat(2))
+                        .collect();
+                    row.repeat(2)
+                })
+                .collect()
+        };
+
+        let written = Psd::from_bytes(&psd.to_bytes())?;
+        for psd in [&psd, &written].iter() {
+            assert_eq!(psd.rgba(), doubled(&original.rgba(), 4));
+
+            for (layer, original_layer) in psd.layers().iter().zip(original.layers()) {
+                assert_eq!(layer.rgba(), doubled(&original_layer.rgba(), 4));
+                assert_eq!(
+                    layer.mask_pixels(),
+                    original_layer.mask_pixels().map(|mask| doubled(&mask, 1))
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Halving the size of a PSD with the bilinear filter averages each 2x2 block of the final image.
+///
+/// cargo test --test resize halve_bilinear -- --exact
+#[test]
+fn halve_bilinear() -> Result<()> {
+    let original = Psd::from_bytes(include_bytes!("./fixtures/rle-3-layer-8x8.psd"))?;
+    let psd = original.resize(4, 4, ResizeFilter::Bilinear)?;
+    let psd = Psd::from_bytes(&psd.to_bytes())?;
+
+    let original_rgba = original.rgba();
+    let rgba = psd.rgba();
+
+    for y in 0..4 {
+        for x in 0..4 {
+            for channel in 0..4 {
+                let sample = |x: usize, y: usize| original_rgba[(y * 8 + x) * 4 + channel] as f32;
+                let average = (sample(x * 2, y * 2)
+                    + sample(x * 2 + 1, y * 2)
+                    + sample(x * 2, y * 2 + 1)
+                    + sample(x * 2 + 1, y * 2 + 1))
+                    / 4.;
+
+                let resized = rgba[(y * 4 + x) * 4 + channel] as f32;
+                assert!((resized - average).abs() <= 1.);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Resizing scales the layer's rectangle, its mask's rectangle and the guides.
+///
+/// cargo test --test resize resize_rects_and_guides -- --exact
+#[test]
+fn resize_rects_and_guides() -> Result<()> {
+    let psd = Psd::from_bytes(ASYMMETRIC)?.resize(8, 6, ResizeFilter::Bilinear)?;
+    let psd = Psd::from_bytes(&psd.to_bytes())?;
+
+    let layer = psd.layer_by_name("Offset").unwrap();
+    assert_eq!(
+        (
+            layer.layer_top(),
+            layer.layer_left(),
+            layer.layer_bottom(),
+            layer.layer_right()
+        ),
+        (0, 4, 3, 7)
+    );
+
+    let mask_rect = layer.mask().unwrap().rect();
+    assert_eq!(
+        (
+            mask_rect.top(),
+            mask_rect.left(),
+            mask_rect.bottom(),
+            mask_rect.right()
+        ),
+        (2, 0, 6, 4)
+    );
+
+    // The vertical guide at x = 1 is now at x = 2, and the horizontal guide at y = 2 is now at
+    // y = 4
+    let guides = psd
+        .resource_blocks()
+        .iter()
+        .find(|block| block.id() == 1032)
+        .unwrap()
+        .data();
+    assert_eq!(&guides[16..], &[0, 0, 0, 64, 0, 0, 0, 0, 128, 1]);
+
+    Ok(())
+}
+
+/// Resizing to a width or height of zero is an error.
+///
+/// cargo test --test resize invalid_resize_dimensions -- --exact
+#[test]
+fn invalid_resize_dimensions() -> Result<()> {
+    let psd = Psd::from_bytes(ASYMMETRIC)?;
+
+    match psd.resize(0, 3, ResizeFilter::Bilinear) {
+        Err(PsdError::InvalidResizeDimensions(0, 3)) => {}
+        _ => panic!("Expected an invalid resize dimensions error"),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,106 @@
+use anyhow::Result;
+use psd::{Psd, PsdError, RgbaLayout};
+
+const TRANSPARENT_TOP_LAYER: &[u8] = include_bytes!("./fixtures/transparent-top-layer-2x1.psd");
+
+/// Copy tightly packed rows into rows that are `stride` bytes apart, filling the padding with 7s.
+fn pad_rows(rgba: &[u8], width: usize, stride: usize) -> Vec<u8> {
+    let mut padded = vec![];
+    for row in rgba.chunks_exact(width * 4) {
+        padded.extend_from_slice(row);
+        padded.resize(padded.len() + stride - row.len(), 7);
+    }
+    padded.truncate(padded.len() - (stride - width * 4));
+    padded
+}
+
+/// Verify that the final image, a layer and the flattened layers are written into padded rows
+/// without touching the padding.
+///
+/// cargo test --test rgba_layout padded_rows -- --exact
+#[test]
+fn padded_rows() -> Result<()> {
+    let psd = Psd::from_bytes(TRANSPARENT_TOP_LAYER)?;
+    let layout = RgbaLayout::row_major(16);
+    assert_eq!(layout.buffer_len(2, 1), 8);
+
+    let mut out = vec![7; 8];
+    psd.rgba_into(&mut out, layout)?;
+    assert_eq!(out, pad_rows(&psd.rgba(), 2, 16));
+
+    let mut out = vec![7; 8];
+    psd.layers()[0].rgba_into(&mut out, layout)?;
+    assert_eq!(out, pad_rows(&psd.layers()[0].rgba(), 2, 16));
+
+    let mut out = vec![7; 8];
+    psd.flatten_layers_rgba_into(&|_| true, &mut out, layout)?;
+    assert_eq!(out, psd.flatten_layers_rgba(&|_| true)?);
+
+    Ok(())
+}
+
+/// Verify that a render is written one column at a time.
+///
+/// cargo test --test rgba_layout column_major_render -- --exact
+#[test]
+fn column_major_render() -> Result<()> {
+    let psd = Psd::from_bytes(TRANSPARENT_TOP_LAYER)?;
+    let rgba = psd.render().to_rgba()?;
+
+    // A 2x1 image has one pixel per column, so with a stride of 8 every pixel is followed by 4
+    // bytes of padding
+    let mut out = vec![7; 12];
+    psd.render()
+        .to_rgba_into(&mut out, RgbaLayout::column_major(8))?;
+    assert_eq!(&out[0..4], &rgba[0..4]);
+    assert_eq!(&out[4..8], &[7; 4]);
+    assert_eq!(&out[8..12], &rgba[4..8]);
+
+    Ok(())
+}
+
+/// Buffers that are too small for the image are rejected before anything is written.
+///
+/// cargo test --test rgba_layout buffer_too_small -- --exact
+#[test]
+fn buffer_too_small() -> Result<()> {
+    let psd = Psd::from_bytes(TRANSPARENT_TOP_LAYER)?;
+
+    let mut out = vec![7; 7];
+    let err = psd
+        .render()
+        .to_rgba_into(&mut out, RgbaLayout::packed(2))
+        .unwrap_err();
+    assert_eq!(
+        err,
+        PsdError::RgbaBufferTooSmall {
+            width: 2,
+            height: 1,
+            stride: 8,
+            len: 7,
+        }
+    );
+    assert_eq!(out, vec![7; 7]);
+
+    Ok(())
+}
+
+/// The same frame buffer can be rendered into again and again, such as by a tool that redraws
+/// the PSD whenever a layer is toggled, without allocating a new one.
+///
+/// cargo test --test rgba_layout reuse_frame_buffer -- --exact
+#[test]
+fn reuse_frame_buffer() -> Result<()> {
+    let psd = Psd::from_bytes(include_bytes!("./fixtures/rle-3-layer-8x8.psd"))?;
+    let layout = RgbaLayout::packed(psd.width());
+    let mut frame = vec![0; layout.buffer_len(psd.width(), psd.height())];
+
+    for hidden_layer in 0..psd.layers().len() {
+        let filter = |(idx, _): (usize, &psd::PsdLayer)| idx != hidden_layer;
+
+        psd.flatten_layers_rgba_into(&filter, &mut frame, layout)?;
+        assert_eq!(frame, psd.flatten_layers_rgba(&filter)?);
+    }
+
+    Ok(())
+}
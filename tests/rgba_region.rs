@@ -0,0 +1,83 @@
+use anyhow::Result;
+use psd::{CropRect, Psd, PsdError};
+
+/// Copy a rectangle out of the RGBA pixels of a canvas of the given width.
+fn crop(rgba: &[u8], width: u32, rect: CropRect) -> Vec<u8> {
+    let mut cropped = vec![];
+    for top in rect.top()..rect.top() + rect.height() {
+        let start = ((top * width + rect.left()) * 4) as usize;
+        cropped.extend_from_slice(&rgba[start..start + rect.width() as usize * 4]);
+    }
+    cropped
+}
+
+/// Every rectangle of the canvas that is at most `step` pixels from each edge of the canvas,
+/// along with the whole canvas.
+fn rects(width: u32, height: u32, step: u32) -> Vec<CropRect> {
+    let mut rects = vec![CropRect::new(0, 0, width, height)];
+    for left in (0..width).step_by(step as usize) {
+        for top in (0..height).step_by(step as usize) {
+            for right in (left + 1..=width).step_by(step as usize) {
+                for bottom in (top + 1..=height).step_by(step as usize) {
+                    rects.push(CropRect::new(left, top, right - left, bottom - top));
+                }
+            }
+        }
+    }
+    rects
+}
+
+/// Verify that a region of the final image and of every layer matches the same rectangle cropped
+/// out of the full image, including layers that hang off the edges of the canvas.
+///
+/// cargo test --test rgba_region region_matches_cropped_image -- --exact
+#[test]
+fn region_matches_cropped_image() -> Result<()> {
+    let fixtures: [&[u8]; 9] = [
+        include_bytes!("./fixtures/rle-3-layer-8x8.psd"),
+        include_bytes!("./fixtures/16x16-rle-partially-opaque.psd"),
+        include_bytes!("./fixtures/negative-top-left-layer.psd"),
+        include_bytes!("./fixtures/layer-larger.psd"),
+        include_bytes!("./fixtures/two-channel-8x8.psd"),
+        include_bytes!("./fixtures/transparent-top-layer-2x1.psd"),
+        include_bytes!("./fixtures/cmyk/cmyk-2x1.psd"),
+        include_bytes!("./fixtures/indexed/indexed-3x1.psd"),
+        include_bytes!("./fixtures/high-bit-depth/rgba16-2x1.psd"),
+    ];
+
+    for fixture in fixtures.iter() {
+        let psd = Psd::from_bytes(fixture)?;
+        let (width, height) = (psd.width(), psd.height());
+
+        for rect in rects(width, height, 3) {
+            assert_eq!(psd.rgba_region(rect)?, crop(&psd.rgba(), width, rect));
+
+            for layer in psd.layers() {
+                if layer.is_empty() {
+                    continue;
+                }
+                assert_eq!(layer.rgba_region(rect)?, crop(&layer.rgba(), width, rect));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Verify that rectangles that are empty or that don't lie within the canvas are rejected.
+///
+/// cargo test --test rgba_region region_outside_of_canvas -- --exact
+#[test]
+fn region_outside_of_canvas() -> Result<()> {
+    let psd = Psd::from_bytes(include_bytes!("./fixtures/rle-3-layer-8x8.psd"))?;
+
+    for rect in [CropRect::new(0, 0, 0, 1), CropRect::new(4, 4, 5, 1)].iter() {
+        match psd.rgba_region(*rect) {
+            Err(PsdError::InvalidCropRect(invalid)) => assert_eq!(invalid, *rect),
+            other => panic!("Expected an invalid crop rect, got {:?}", other.map(|_| ())),
+        }
+        assert!(psd.layers()[0].rgba_region(*rect).is_err());
+    }
+
+    Ok(())
+}
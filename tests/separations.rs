@@ -0,0 +1,81 @@
+use anyhow::Result;
+use psd::{ChannelDisplayKind, ImageResource, Psd, SeparationKind};
+
+/// A CMYK image has a plane for each process ink followed by its spot channels. Alpha channels
+/// aren't printed so they're skipped.
+///
+/// cargo test --test separations cmyk_and_spot_separations -- --exact
+#[test]
+fn cmyk_and_spot_separations() -> Result<()> {
+    let psd = include_bytes!("./fixtures/separations/cmyk-spot-and-alpha.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let separations = psd.separations();
+    let names: Vec<&str> = separations.iter().map(|s| s.name()).collect();
+    assert_eq!(
+        names,
+        vec!["Cyan", "Magenta", "Yellow", "Black", "PANTONE 185 C"]
+    );
+
+    assert_eq!(separations[0].kind(), SeparationKind::Process);
+    assert_eq!(separations[4].kind(), SeparationKind::Spot);
+
+    assert_eq!(separations[0].pixels(), &[255, 0]);
+    assert_eq!(separations[1].pixels(), &[0, 0]);
+    assert_eq!(separations[2].pixels(), &[0, 191]);
+    assert_eq!(separations[3].pixels(), &[0, 0]);
+    assert_eq!(separations[4].pixels(), &[127, 0]);
+
+    Ok(())
+}
+
+/// An RGB image without spot channels doesn't have any separations.
+///
+/// cargo test --test separations rgb_without_spot_channels -- --exact
+#[test]
+fn rgb_without_spot_channels() -> Result<()> {
+    let psd = include_bytes!("./fixtures/green-1x1.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    assert!(psd.separations().is_empty());
+
+    Ok(())
+}
+
+/// The alpha and spot channels come with their names and display info, and can be found by name.
+///
+/// cargo test --test separations extra_channels_by_name -- --exact
+#[test]
+fn extra_channels_by_name() -> Result<()> {
+    let psd = include_bytes!("./fixtures/separations/cmyk-spot-and-alpha.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let channels = psd.extra_channels();
+    let names: Vec<Option<&str>> = channels.iter().map(|channel| channel.name()).collect();
+    assert_eq!(names, vec![Some("PANTONE 185 C"), Some("Alpha 1")]);
+
+    let spot = psd.extra_channel_by_name("PANTONE 185 C").unwrap();
+    assert!(spot.is_spot());
+    let info = spot.display_info().unwrap();
+    assert_eq!(info.kind(), ChannelDisplayKind::Spot);
+    assert_eq!(info.color(), [65535, 0, 0, 0]);
+    assert_eq!(info.opacity(), 100);
+    assert_eq!(spot.pixels(), &[128, 255]);
+
+    let alpha = psd.extra_channel_by_name("Alpha 1").unwrap();
+    assert!(!alpha.is_spot());
+    assert_eq!(
+        alpha.display_info().unwrap().kind(),
+        ChannelDisplayKind::SelectedAreas
+    );
+    assert!(psd.extra_channel_by_name("Missing").is_none());
+
+    assert!(psd
+        .resources()
+        .contains(&ImageResource::UnicodeAlphaNames(vec![
+            "PANTONE 185 C".to_string(),
+            "Alpha 1".to_string()
+        ])));
+
+    Ok(())
+}
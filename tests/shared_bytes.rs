@@ -0,0 +1,76 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use psd::{CropRect, Psd};
+
+/// PSDs that share their bytes with their layers parse, render and write out the same as PSDs
+/// that copy their layers' channels.
+///
+/// cargo test --test shared_bytes from_shared_bytes -- --exact
+#[test]
+fn from_shared_bytes() -> Result<()> {
+    let fixtures: [&[u8]; 4] = [
+        include_bytes!("./fixtures/rle-3-layer-8x8.psd"),
+        include_bytes!("./fixtures/groups/green-1x1-two-groups-two-layers-inside.psd"),
+        include_bytes!("./fixtures/high-bit-depth/lr16-2x1.psd"),
+        include_bytes!("./fixtures/zip-compression/rgb-3x1-zip-with-prediction.psd"),
+    ];
+
+    for bytes in fixtures.iter() {
+        let psd = Psd::from_bytes(bytes)?;
+        let shared = Psd::from_shared_bytes(Arc::from(*bytes))?;
+
+        assert_eq!(shared, psd);
+        for (shared_layer, layer) in shared.layers().iter().zip(psd.layers()) {
+            assert_eq!(shared_layer.rgba(), layer.rgba());
+        }
+        assert_eq!(
+            shared.flatten_layers_rgba(&|_| true)?,
+            psd.flatten_layers_rgba(&|_| true)?
+        );
+        assert_eq!(shared.to_bytes(), psd.to_bytes());
+    }
+
+    Ok(())
+}
+
+/// The layers' channels point into the shared bytes instead of copying them, keeping the bytes
+/// alive until the PSD is dropped.
+///
+/// cargo test --test shared_bytes layers_point_into_shared_bytes -- --exact
+#[test]
+fn layers_point_into_shared_bytes() -> Result<()> {
+    let bytes: Arc<[u8]> = Arc::from(&include_bytes!("./fixtures/rle-3-layer-8x8.psd")[..]);
+
+    let psd = Psd::from_shared_bytes(Arc::clone(&bytes))?;
+    assert!(Arc::strong_count(&bytes) > 1);
+
+    drop(psd);
+    assert_eq!(Arc::strong_count(&bytes), 1);
+
+    Ok(())
+}
+
+/// Layers keep their decoded pixels, and decode them again once the canvas is transformed.
+///
+/// cargo test --test shared_bytes shared_layers_cache_rgba -- --exact
+#[test]
+fn shared_layers_cache_rgba() -> Result<()> {
+    let bytes = include_bytes!("./fixtures/rle-3-layer-8x8.psd");
+    let psd = Psd::from_bytes(bytes)?;
+    let shared = Psd::from_shared_bytes(Arc::from(&bytes[..]))?;
+
+    for (shared_layer, layer) in shared.layers().iter().zip(psd.layers()) {
+        assert_eq!(shared_layer.rgba(), layer.rgba());
+        assert_eq!(shared_layer.rgba(), layer.rgba());
+    }
+
+    let rect = CropRect::new(2, 1, 4, 5);
+    let (cropped, shared_cropped) = (psd.crop(rect)?, shared.crop(rect)?);
+    for (shared_layer, layer) in shared_cropped.layers().iter().zip(cropped.layers()) {
+        assert_eq!(shared_layer.rgba().len(), 4 * 5 * 4);
+        assert_eq!(shared_layer.rgba(), layer.rgba());
+    }
+
+    Ok(())
+}
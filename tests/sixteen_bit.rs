@@ -0,0 +1,96 @@
+use anyhow::Result;
+use psd::{Psd, PsdDepth};
+
+/// The pixels of `fixtures/high-bit-depth/rgba16-2x1.psd`, see `fixtures/README.md`
+const RGBA16: [u16; 8] = [0x1234, 0x0101, 0xABCD, 65535, 0xFFFF, 0x8000, 0x0001, 32768];
+
+/// The samples of a raw 16 bit final image aren't mapped down to 8 bits.
+///
+/// cargo test --test sixteen_bit final_image_rgba16 -- --exact
+#[test]
+fn final_image_rgba16() -> Result<()> {
+    let psd = include_bytes!("./fixtures/high-bit-depth/rgba16-2x1.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    assert_eq!(psd.depth(), PsdDepth::Sixteen);
+    assert_eq!(psd.rgba16(), RGBA16);
+
+    Ok(())
+}
+
+/// Raw and ZIP compressed layer channels keep their 16 bit samples.
+///
+/// cargo test --test sixteen_bit layer_rgba16 -- --exact
+#[test]
+fn layer_rgba16() -> Result<()> {
+    let psd = include_bytes!("./fixtures/high-bit-depth/rgba16-2x1.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    assert_eq!(psd.layer_by_name("Layer").unwrap().rgba16(), RGBA16);
+
+    Ok(())
+}
+
+/// ZIP compressed 16 bit images are decompressed again for their original samples.
+///
+/// cargo test --test sixteen_bit zip_compressed_rgba16 -- --exact
+#[test]
+fn zip_compressed_rgba16() -> Result<()> {
+    let psd = include_bytes!("./fixtures/high-bit-depth/lr16-2x1.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let red = [65535, 0, 0, 65535];
+    let green = [0, 65535, 0, 65535];
+    assert_eq!(psd.rgba16(), [red, green].concat());
+    assert_eq!(
+        psd.layer_by_name("Red").unwrap().rgba16(),
+        [red, [0; 4]].concat()
+    );
+
+    Ok(())
+}
+
+/// The 8 bit pixels of 16 bit images are the high byte of every sample, for raw and ZIP
+/// compressed final images and layers.
+///
+/// cargo test --test sixteen_bit sixteen_bit_rgba -- --exact
+#[test]
+fn sixteen_bit_rgba() -> Result<()> {
+    let psd = Psd::from_bytes(include_bytes!("./fixtures/high-bit-depth/rgba16-2x1.psd"))?;
+    let high_bytes: Vec<u8> = RGBA16.iter().map(|sample| (sample >> 8) as u8).collect();
+    assert_eq!(psd.rgba(), high_bytes);
+    assert_eq!(psd.layer_by_name("Layer").unwrap().rgba(), high_bytes);
+
+    let psd = Psd::from_bytes(include_bytes!("./fixtures/high-bit-depth/lr16-2x1.psd"))?;
+    assert_eq!(psd.rgba(), [255, 0, 0, 255, 0, 255, 0, 255]);
+
+    Ok(())
+}
+
+/// Writing a raw 16 bit image keeps every sample, rather than the 8 bit red channel.
+///
+/// cargo test --test sixteen_bit write_raw_16_bit -- --exact
+#[test]
+fn write_raw_16_bit() -> Result<()> {
+    let bytes = include_bytes!("./fixtures/high-bit-depth/rgba16-2x1.psd");
+    let psd = Psd::from_bytes(bytes)?;
+
+    assert_eq!(psd.to_bytes(), bytes.to_vec());
+
+    Ok(())
+}
+
+/// 8 bit pixels are mapped onto the 16 bit range.
+///
+/// cargo test --test sixteen_bit eight_bit_rgba16 -- --exact
+#[test]
+fn eight_bit_rgba16() -> Result<()> {
+    let psd = include_bytes!("./fixtures/green-1x1.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let expected: Vec<u16> = psd.rgba().iter().map(|v| *v as u16 * 257).collect();
+    assert_eq!(psd.rgba16(), expected);
+    assert_eq!(psd.rgba16()[1], 65535);
+
+    Ok(())
+}
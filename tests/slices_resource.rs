@@ -1,7 +1,18 @@
 use anyhow::Result;
-use psd::{DescriptorField, ImageResource, Psd};
+use psd::{DescriptorField, ImageResource, Psd, SlicesImageResource};
 use std::path::PathBuf;
 
+/// The first slices resource of the PSD.
+fn slices_resource(psd: &Psd) -> &SlicesImageResource {
+    psd.resources()
+        .iter()
+        .find_map(|resource| match resource {
+            ImageResource::Slices(slices) => Some(slices),
+            _ => None,
+        })
+        .unwrap()
+}
+
 /// Verify that we properly read the name of a slices resources section.
 ///
 /// For a default PNG there is a slices resource section that has the same name of the PSD file.
@@ -26,11 +37,7 @@ fn name_of_slices_resource_group() {
         let psd = std::fs::read(&file).unwrap();
         let psd = Psd::from_bytes(&psd).unwrap();
 
-        match &psd.resources()[0] {
-            ImageResource::Slices(slices) => {
-                assert_eq!(slices.name().as_str(), expected_slices_name);
-            }
-        };
+        assert_eq!(slices_resource(&psd).name().as_str(), expected_slices_name);
     }
 }
 
@@ -44,15 +51,9 @@ fn slices_v7_8() -> Result<()> {
     let psd = include_bytes!("./fixtures/slices-v8.psd");
     let psd = Psd::from_bytes(psd)?;
 
-    match &psd.resources()[0] {
-        ImageResource::Slices(slices) => {
-            assert_eq!(slices.name().as_str(), "\u{0}");
-        }
-    };
+    assert_eq!(slices_resource(&psd).name().as_str(), "\u{0}");
 
-    let descriptors = match &psd.resources()[0] {
-        ImageResource::Slices(s) => s.descriptors(),
-    };
+    let descriptors = slices_resource(&psd).descriptors();
     let descriptor = descriptors.get(0).unwrap();
     let bounds = descriptor.fields.get("bounds").unwrap();
 
@@ -72,3 +73,62 @@ fn slices_v7_8() -> Result<()> {
 
     Ok(())
 }
+
+/// Verify that we expose the geometry of each slice and can crop it out of the final image.
+///
+/// cargo test --test slices_resource export_slices -- --exact
+#[test]
+fn export_slices() -> Result<()> {
+    let psd = std::fs::read(fixtures_dir().join("1234.psd"))?;
+    let psd = Psd::from_bytes(&psd)?;
+
+    let slices = slices_resource(&psd).slices();
+    assert_eq!(slices.len(), 1);
+    assert_eq!(
+        (
+            slices[0].top(),
+            slices[0].left(),
+            slices[0].bottom(),
+            slices[0].right()
+        ),
+        (0, 0, 1, 1)
+    );
+
+    let exported = psd.export_slices();
+    assert_eq!(exported.len(), 1);
+    assert_eq!(exported[0].file_name(), "1234_01");
+    assert_eq!((exported[0].width(), exported[0].height()), (1, 1));
+    assert_eq!(exported[0].rgba(), &psd.rgba()[..]);
+
+    Ok(())
+}
+
+/// Version 7 and 8 slices resources store their slices in a descriptor.
+///
+/// cargo test --test slices_resource export_slices_v7_8 -- --exact
+#[test]
+fn export_slices_v7_8() -> Result<()> {
+    let psd = include_bytes!("./fixtures/slices-v8.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let exported = psd.export_slices();
+    assert_eq!(exported.len(), 1);
+    assert_eq!(exported[0].slice().id(), 0);
+    assert_eq!(exported[0].file_name(), "User_01");
+    assert_eq!(exported[0].rgba(), &psd.rgba()[..]);
+
+    Ok(())
+}
+
+/// Photoshop writes a URL list resource (1054) even when none of the slices link anywhere, in
+/// which case it's empty.
+///
+/// cargo test --test slices_resource empty_url_list -- --exact
+#[test]
+fn empty_url_list() {
+    let psd = Psd::from_bytes(include_bytes!("fixtures/slices-v8.psd")).unwrap();
+
+    assert!(psd.resource_blocks().iter().any(|block| block.id() == 1054));
+    assert_eq!(psd.url_list(), vec![]);
+    assert_eq!(psd.workflow_url(), None);
+}
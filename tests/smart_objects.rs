@@ -0,0 +1,123 @@
+use anyhow::Result;
+use psd::{DescriptorField, LinkedFileKind, Psd, UnitFloatStructure};
+
+const SMART_OBJECTS: &[u8] = include_bytes!("./fixtures/smart-objects/smart-objects-2x2.psd");
+
+/// Verify that we list every smart object layer along with the file that it places.
+///
+/// cargo test --test smart_objects smart_objects -- --exact
+#[test]
+fn smart_objects() -> Result<()> {
+    let psd = Psd::from_bytes(SMART_OBJECTS)?;
+
+    let smart_objects = psd.smart_objects();
+    assert_eq!(smart_objects.len(), 2);
+
+    let find = |name: &str| {
+        smart_objects
+            .iter()
+            .find(|smart_object| smart_object.layer().name() == name)
+            .unwrap()
+    };
+
+    let embedded = find("Embedded");
+    assert_eq!(embedded.layer().name(), "Embedded");
+    assert_eq!(embedded.placed_layer().unique_id(), "embedded-uuid");
+    assert!(embedded.placed_layer().descriptor().is_some());
+    assert_eq!(embedded.file_name(), Some("logo.png"));
+    assert_eq!(embedded.file().unwrap().kind(), LinkedFileKind::Embedded);
+    assert_eq!(embedded.file().unwrap().file_type(), b"png ");
+    assert_eq!(embedded.data(), Some(&b"\x89PNG not really a png"[..]));
+    assert_eq!(
+        embedded.transform(),
+        [0.5, 0.25, 1.5, 0.25, 1.5, 1.75, 0.5, 1.75]
+    );
+
+    let legacy = find("Legacy");
+    assert_eq!(legacy.layer().name(), "Legacy");
+    assert_eq!(legacy.placed_layer().unique_id(), "external-uuid");
+    assert!(legacy.placed_layer().descriptor().is_none());
+    assert_eq!(legacy.file_name(), Some("photo.jpg"));
+    assert_eq!(legacy.file().unwrap().kind(), LinkedFileKind::External);
+    assert_eq!(legacy.data(), None);
+    assert_eq!(legacy.transform(), [0., 0., 2., 0., 2., 2., 0., 2.]);
+
+    Ok(())
+}
+
+/// Verify that we report the smart filters of smart object layers along with their parameters.
+///
+/// cargo test --test smart_objects smart_filters -- --exact
+#[test]
+fn smart_filters() -> Result<()> {
+    let psd = Psd::from_bytes(SMART_OBJECTS)?;
+
+    let filters = psd.layer_by_name("Embedded").unwrap().smart_filters();
+    assert_eq!(filters.len(), 2);
+
+    assert_eq!(filters[0].name(), "Gaussian Blur...");
+    assert!(filters[0].enabled());
+    let parameters = filters[0].parameters().unwrap();
+    assert_eq!(parameters.class_id, b"GsnB");
+    assert_eq!(
+        parameters.fields.get("Rds "),
+        Some(&DescriptorField::UnitFloat(UnitFloatStructure::Pixels(2.5)))
+    );
+
+    assert_eq!(filters[1].name(), "Invert");
+    assert!(!filters[1].enabled());
+    assert!(filters[1].parameters().is_none());
+
+    assert!(psd
+        .layer_by_name("Legacy")
+        .unwrap()
+        .smart_filters()
+        .is_empty());
+
+    Ok(())
+}
+
+/// Layers that aren't smart objects don't have a placed layer, and PSDs without smart objects
+/// don't have any linked files.
+///
+/// cargo test --test smart_objects no_smart_objects -- --exact
+#[test]
+fn no_smart_objects() -> Result<()> {
+    let psd = Psd::from_bytes(include_bytes!("./fixtures/green-1x1.psd"))?;
+
+    assert!(psd.smart_objects().is_empty());
+    assert!(psd.linked_files().is_empty());
+    assert!(psd.layers()[0].placed_layer().is_none());
+    assert!(psd.layers()[0].smart_filters().is_empty());
+
+    Ok(())
+}
+
+/// A placed layer descriptor with an OSType that we don't support (Photoshop CC writes "UnFl"
+/// in the warp meshes of smart objects) leaves that layer without a placed layer, rather than
+/// failing to parse the PSD.
+///
+/// cargo test --test smart_objects unreadable_placed_layer_descriptor -- --exact
+#[test]
+fn unreadable_placed_layer_descriptor() -> Result<()> {
+    let mut bytes = SMART_OBJECTS.to_vec();
+    let placed_layer_data = bytes.windows(4).position(|key| key == b"SoLd").unwrap();
+    let double = placed_layer_data
+        + bytes[placed_layer_data..]
+            .windows(4)
+            .position(|os_type| os_type == b"doub")
+            .unwrap();
+    bytes[double..double + 4].copy_from_slice(b"UnFl");
+
+    let psd = Psd::from_bytes(&bytes)?;
+
+    assert!(psd
+        .layer_by_name("Embedded")
+        .unwrap()
+        .placed_layer()
+        .is_none());
+    assert_eq!(psd.smart_objects().len(), 1);
+    assert_eq!(psd.smart_objects()[0].layer().name(), "Legacy");
+
+    Ok(())
+}
@@ -0,0 +1,78 @@
+#![cfg(feature = "sprite-sheet")]
+
+use anyhow::Result;
+use psd::Psd;
+
+const GREEN_PIXEL: [u8; 4] = [0, 255, 0, 255];
+
+/// Each top level group is rendered and packed left to right with padding between them.
+///
+/// cargo test --test sprite_sheet top_level_groups -- --exact
+#[test]
+fn top_level_groups() -> Result<()> {
+    let psd = include_bytes!("./fixtures/groups/green-1x1-two-groups-two-layers-inside.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let sheet = psd.sprite_sheet().pack()?;
+
+    assert_eq!((sheet.width(), sheet.height()), (3, 1));
+    assert_eq!(
+        sheet.rgba(),
+        [GREEN_PIXEL, [0; 4], GREEN_PIXEL].concat().as_slice()
+    );
+
+    let frames = sheet.frames();
+    assert_eq!(frames.len(), 2);
+    assert_eq!(frames[0].name(), "group");
+    assert_eq!((frames[0].x(), frames[0].y()), (0, 0));
+    assert_eq!(frames[1].name(), "group2");
+    assert_eq!((frames[1].x(), frames[1].y()), (2, 0));
+    assert_eq!((frames[1].width(), frames[1].height()), (1, 1));
+    assert_eq!(frames[1].offset(), (0, 0));
+
+    assert_eq!(
+        sheet.atlas_json(),
+        r#"{"width":3,"height":1,"frames":[{"name":"group","x":0,"y":0,"width":1,"height":1,"offset":[0,0]},{"name":"group2","x":2,"y":0,"width":1,"height":1,"offset":[0,0]}]}"#
+    );
+
+    Ok(())
+}
+
+/// Nested groups can be packed by path, and sprites wrap onto a new shelf once a shelf
+/// reaches the maximum width.
+///
+/// cargo test --test sprite_sheet nested_groups_wrap -- --exact
+#[test]
+fn nested_groups_wrap() -> Result<()> {
+    let psd = include_bytes!("./fixtures/groups/green-1x1-one-group-with-two-subgroups.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let sheet = psd
+        .sprite_sheet()
+        .groups(&[
+            "outside group/first group inside",
+            "outside group/second group inside",
+        ])
+        .max_width(2)
+        .padding(0)
+        .pack()?;
+
+    assert_eq!((sheet.width(), sheet.height()), (2, 1));
+    assert_eq!(sheet.frames()[1].x(), 1);
+
+    let sheet = psd
+        .sprite_sheet()
+        .groups(&[
+            "outside group/first group inside",
+            "outside group/second group inside",
+        ])
+        .max_width(2)
+        .pack()?;
+
+    assert_eq!((sheet.width(), sheet.height()), (1, 3));
+    assert_eq!((sheet.frames()[1].x(), sheet.frames()[1].y()), (0, 2));
+
+    assert!(psd.sprite_sheet().groups(&["missing"]).pack().is_err());
+
+    Ok(())
+}
@@ -0,0 +1,106 @@
+use anyhow::Result;
+use psd::{NodeId, Psd};
+
+/// The visibility column reflects `LayerProperties::visible`.
+///
+/// cargo test --test summary summary_tree -- --exact
+#[test]
+fn summary_tree() -> Result<()> {
+    let psd =
+        include_bytes!("./fixtures/groups/green-1x1-one-group-one-layer-inside-one-outside.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let layer = psd.layer_by_name("First Layer").unwrap();
+    let visibility = if layer.visible() { "visible" } else { "hidden" };
+
+    let expected = format!(
+        r#"1x1 Rgb Eight, 2 layers, 1 group
+group "group" PassThrough 100% {v}
+  layer "First Layer" 1x1 at (0, 0) Normal 100% {v}
+layer "Second Layer" 1x1 at (0, 0) Normal 100% {v}
+"#,
+        v = visibility
+    );
+
+    assert_eq!(psd.to_string(), expected);
+    assert_eq!(psd.summary().to_string(), expected);
+
+    Ok(())
+}
+
+/// Groups whose children are deeper than the max depth are collapsed into `...`.
+///
+/// cargo test --test summary summary_max_depth -- --exact
+#[test]
+fn summary_max_depth() -> Result<()> {
+    let psd = include_bytes!("./fixtures/groups/green-1x1-one-group-with-two-subgroups.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let lines: Vec<String> = psd
+        .summary()
+        .max_depth(0)
+        .to_string()
+        .lines()
+        // Drop the blend mode, opacity and visibility
+        .map(|line| {
+            let line = line.split(" Normal").next().unwrap();
+            line.split(" PassThrough").next().unwrap().to_string()
+        })
+        .collect();
+
+    assert_eq!(
+        lines,
+        vec![
+            "1x1 Rgb Eight, 6 layers, 6 groups",
+            r#"group "outside group""#,
+            "  ...",
+            r#"layer "Firth Layer" 1x1 at (0, 0)"#,
+            r#"group "outside group 2""#,
+            "  ...",
+        ]
+    );
+
+    let full = psd.summary().to_string();
+    assert!(full.contains(r#"      layer "Second Layer" 1x1 at (0, 0)"#));
+
+    Ok(())
+}
+
+/// The canonical summary has a line for the document and a line per group and layer, naming
+/// each by its path, and a change to one layer only changes that layer's line.
+///
+/// cargo test --test summary canonical_summary -- --exact
+#[test]
+fn canonical_summary() -> Result<()> {
+    let bytes =
+        include_bytes!("./fixtures/groups/green-1x1-one-group-one-layer-inside-one-outside.psd");
+    let mut psd = Psd::from_bytes(bytes)?;
+
+    let expected = r#"document 1x1 Rgb Eight layers=2 groups=1 composite_crc32=b2de047c
+group "group" visible=false blend=PassThrough opacity=255
+layer "group/First Layer" id=7 size=1x1 at=0,0 visible=false blend=Normal opacity=255 fill=255 clipping=true pixels_crc32=67e3be77
+layer "Second Layer" id=4 size=1x1 at=0,0 visible=false blend=Normal opacity=255 fill=255 clipping=true pixels_crc32=67e3be77
+"#;
+    let before = psd.canonical_summary();
+    assert_eq!(before, expected);
+    assert_eq!(Psd::from_bytes(bytes)?.canonical_summary(), before);
+
+    let idx = psd
+        .layers()
+        .iter()
+        .position(|layer| layer.name() == "Second Layer")
+        .unwrap();
+    assert!(psd.set_opacity_percent(NodeId::Layer(idx), 50.));
+    let after = psd.canonical_summary();
+
+    let changed: Vec<(&str, &str)> = before
+        .lines()
+        .zip(after.lines())
+        .filter(|(before, after)| before != after)
+        .collect();
+    assert_eq!(changed.len(), 1);
+    assert!(changed[0].1.starts_with(r#"layer "Second Layer""#));
+    assert!(changed[0].1.contains(" opacity=128 "));
+
+    Ok(())
+}
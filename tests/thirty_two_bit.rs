@@ -0,0 +1,52 @@
+use anyhow::Result;
+use psd::{Psd, PsdDepth};
+
+/// The linear pixels of the 32 bit fixtures, see `fixtures/README.md`
+const LINEAR: [f32; 8] = [2.0, 0.5, 0.0, 1.0, 0.2159, 0.0, 1.0, 1.0];
+
+/// Tonemapped into sRGB. 2.0 is clamped and linear 0.5 and 0.2159 are 188 and 128 in sRGB.
+const TONEMAPPED: [u8; 8] = [255, 188, 0, 255, 128, 0, 255, 255];
+
+/// cargo test --test thirty_two_bit raw_final_image -- --exact
+#[test]
+fn raw_final_image() -> Result<()> {
+    let psd = include_bytes!("./fixtures/zip-compression/hdr-2x1-raw.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    assert_eq!(psd.depth(), PsdDepth::ThirtyTwo);
+    assert_eq!(psd.rgba_f32(), LINEAR);
+    assert_eq!(psd.rgba(), TONEMAPPED);
+
+    Ok(())
+}
+
+/// 32 bit images are ZIP compressed with prediction in practice.
+///
+/// cargo test --test thirty_two_bit zip_with_prediction -- --exact
+#[test]
+fn zip_with_prediction() -> Result<()> {
+    let psd = include_bytes!("./fixtures/zip-compression/hdr-2x1-zip-with-prediction.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    assert_eq!(psd.rgba_f32(), LINEAR);
+    assert_eq!(psd.rgba(), TONEMAPPED);
+
+    // Layers are tonemapped when they're read
+    assert_eq!(psd.layer_by_name("HDR").unwrap().rgba(), TONEMAPPED);
+
+    Ok(())
+}
+
+/// The sRGB pixels of an 8 bit image are converted to linear.
+///
+/// cargo test --test thirty_two_bit eight_bit_rgba_f32 -- --exact
+#[test]
+fn eight_bit_rgba_f32() -> Result<()> {
+    let psd = include_bytes!("./fixtures/zip-compression/rgb-3x1-zip-with-prediction.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let rgba = psd.rgba_f32();
+    assert_eq!(&rgba[..8], &[1.0, 0.0, 0.0, 1.0, 0.0, 1.0, 0.0, 1.0]);
+
+    Ok(())
+}
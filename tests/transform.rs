@@ -0,0 +1,178 @@
+use anyhow::Result;
+use psd::Psd;
+
+const ASYMMETRIC: &[u8] = include_bytes!("./fixtures/transform/asymmetric-4x3.psd");
+
+/// Fixtures with different compressions, depths and color modes, all of whose layers are within
+/// the canvas.
+const FIXTURES: [&[u8]; 10] = [
+    ASYMMETRIC,
+    include_bytes!("./fixtures/rle-3-layer-8x8.psd"),
+    include_bytes!("./fixtures/transparent-top-layer-2x1.psd"),
+    include_bytes!("./fixtures/layer-masks/red-3x3-mask-default-color-0.psd"),
+    include_bytes!("./fixtures/two-channel-8x8.psd"),
+    include_bytes!("./fixtures/zip-compression/rgb-3x1-zip-with-prediction.psd"),
+    include_bytes!("./fixtures/zip-compression/hdr-2x1-zip-with-prediction.psd"),
+    include_bytes!("./fixtures/bitmap-mode/bitmap-10x2-rle.psd"),
+    include_bytes!("./fixtures/cmyk/cmyk-2x1.psd"),
+    include_bytes!("./fixtures/indexed/indexed-3x1.psd"),
+];
+
+/// Rotating by 90° moves the final image, every layer and every mask, and swaps the width and
+/// height.
+///
+/// cargo test --test transform rotate90 -- --exact
+#[test]
+fn rotate90() -> Result<()> {
+    check_transform(Psd::rotate90, |x, y, _, height| (height - 1 - y, x), true)
+}
+
+/// Rotating by 180° moves the final image, every layer and every mask.
+///
+/// cargo test --test transform rotate180 -- --exact
+#[test]
+fn rotate180() -> Result<()> {
+    check_transform(
+        Psd::rotate180,
+        |x, y, width, height| (width - 1 - x, height - 1 - y),
+        false,
+    )
+}
+
+/// Flipping mirrors the final image, every layer and every mask.
+///
+/// cargo test --test transform flip_horizontal -- --exact
+#[test]
+fn flip_horizontal() -> Result<()> {
+    check_transform(
+        Psd::flip_horizontal,
+        |x, y, width, _| (width - 1 - x, y),
+        false,
+    )
+}
+
+/// Rotating by 90° four times gives back the same pixels and layer positions.
+///
+/// cargo test --test transform rotate90_four_times -- --exact
+#[test]
+fn rotate90_four_times() -> Result<()> {
+    for bytes in FIXTURES.iter() {
+        let original = Psd::from_bytes(bytes)?;
+
+        let mut psd = original.clone();
+        for _ in 0..4 {
+            psd.rotate90();
+        }
+
+        assert_eq!(psd.rgba(), original.rgba());
+        for (layer, original_layer) in psd.layers().iter().zip(original.layers()) {
+            assert_eq!(layer.layer_top(), original_layer.layer_top());
+            assert_eq!(layer.layer_left(), original_layer.layer_left());
+            assert_eq!(layer.layer_bottom(), original_layer.layer_bottom());
+            assert_eq!(layer.layer_right(), original_layer.layer_right());
+            assert_eq!(layer.rgba(), original_layer.rgba());
+        }
+    }
+
+    Ok(())
+}
+
+/// Rotating by 90° moves the layer's rectangle, its mask's rectangle and the guides.
+///
+/// cargo test --test transform rotate90_rects_and_guides -- --exact
+#[test]
+fn rotate90_rects_and_guides() -> Result<()> {
+    let mut psd = Psd::from_bytes(ASYMMETRIC)?;
+    psd.rotate90();
+    let psd = Psd::from_bytes(&psd.to_bytes())?;
+
+    let layer = psd.layer_by_name("Offset").unwrap();
+    assert_eq!(
+        (
+            layer.layer_top(),
+            layer.layer_left(),
+            layer.layer_bottom(),
+            layer.layer_right()
+        ),
+        (2, 1, 3, 2)
+    );
+
+    let mask_rect = layer.mask().unwrap().rect();
+    assert_eq!(
+        (
+            mask_rect.top(),
+            mask_rect.left(),
+            mask_rect.bottom(),
+            mask_rect.right()
+        ),
+        (0, 0, 2, 2)
+    );
+
+    // The vertical guide at x = 1 is now a horizontal guide at y = 1, and the horizontal guide
+    // at y = 2 is now a vertical guide at x = 1
+    let guides = psd
+        .resource_blocks()
+        .iter()
+        .find(|block| block.id() == 1032)
+        .unwrap()
+        .data();
+    assert_eq!(&guides[16..], &[0, 0, 0, 32, 1, 0, 0, 0, 32, 0]);
+
+    Ok(())
+}
+
+/// Transform every fixture and verify that its pixels moved the same way that `move_pixel`
+/// moves them, before and after writing the transformed PSD.
+fn check_transform(
+    transform: fn(&mut Psd),
+    move_pixel: fn(usize, usize, usize, usize) -> (usize, usize),
+    swaps_dimensions: bool,
+) -> Result<()> {
+    for bytes in FIXTURES.iter() {
+        let original = Psd::from_bytes(bytes)?;
+        let (width, height) = (original.width() as usize, original.height() as usize);
+
+        let mut psd = original.clone();
+        transform(&mut psd);
+
+        if swaps_dimensions {
+            assert_eq!(
+                (psd.width(), psd.height()),
+                (original.height(), original.width())
+            );
+        } else {
+            assert_eq!(
+                (psd.width(), psd.height()),
+                (original.width(), original.height())
+            );
+        }
+
+        let moved = |pixels: &[u8], bytes_per_pixel: usize| {
+            let new_width = psd.width() as usize;
+            let mut moved = vec![0; pixels.len()];
+
+            for (idx, pixel) in pixels.chunks_exact(bytes_per_pixel).enumerate() {
+                let (x, y) = move_pixel(idx % width, idx / width, width, height);
+                let start = (y * new_width + x) * bytes_per_pixel;
+                moved[start..start + bytes_per_pixel].copy_from_slice(pixel);
+            }
+
+            moved
+        };
+
+        let written = Psd::from_bytes(&psd.to_bytes())?;
+        for transformed in [&psd, &written].iter() {
+            assert_eq!(transformed.rgba(), moved(&original.rgba(), 4));
+
+            for (layer, original_layer) in transformed.layers().iter().zip(original.layers()) {
+                assert_eq!(layer.rgba(), moved(&original_layer.rgba(), 4));
+                assert_eq!(
+                    layer.mask_pixels(),
+                    original_layer.mask_pixels().map(|mask| moved(&mask, 1))
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
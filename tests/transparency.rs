@@ -136,3 +136,61 @@ fn put_pixel(image: &mut Vec<u8>, width: usize, left: usize, top: usize, new: [u
     image[idx * 4 + 2] = new[2];
     image[idx * 4 + 3] = new[3];
 }
+
+/// Verify that we can find the layers under a pixel, ignoring transparent pixels.
+///
+/// cargo test --test transparency layers_at -- --exact
+#[test]
+fn layers_at() -> Result<()> {
+    let psd = include_bytes!("./fixtures/3x3-opaque-center.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let layer = psd.layer_by_name("OpaqueCenter").unwrap();
+    assert_eq!(layer.alpha_at(0, 0), None);
+    assert_eq!(layer.alpha_at(1, 0), Some(0));
+    assert_eq!(layer.alpha_at(2, 0), Some(255));
+
+    assert!(psd.layers_at(0, 0).is_empty());
+    assert!(psd.layers_at(1, 0).is_empty());
+    assert_eq!(psd.layers_at(2, 0)[0].name(), "OpaqueCenter");
+    assert!(psd.layers_at(-1, 0).is_empty());
+
+    assert!(psd.layers_at_with_alpha_threshold(2, 0, 255).is_empty());
+
+    let psd = include_bytes!("./fixtures/transparent-above-opaque.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let names: Vec<&str> = psd.layers_at(0, 0).iter().map(|l| l.name()).collect();
+    assert_eq!(names, vec!["opaque"]);
+
+    Ok(())
+}
+
+/// Verify that a layer's alpha summary tells apart opaque, transparent and mixed layers.
+///
+/// cargo test --test transparency alpha_summary -- --exact
+#[test]
+fn alpha_summary() -> Result<()> {
+    let psd = include_bytes!("./fixtures/3x3-opaque-center.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let summary = psd.layer_by_name("OpaqueCenter").unwrap().alpha_summary();
+    assert_eq!((summary.min(), summary.max()), (0, 255));
+    assert!(summary.is_mixed());
+
+    let psd = include_bytes!("./fixtures/transparent-above-opaque.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let opaque = psd.layer_by_name("opaque").unwrap().alpha_summary();
+    assert!(opaque.is_opaque());
+    assert!(!opaque.is_mixed());
+
+    let transparent = psd.layers()[0].alpha_summary();
+    assert!(transparent.is_transparent());
+    assert!(!transparent.is_opaque());
+
+    // Skipping the transparent layer doesn't change the result
+    assert_eq!(psd.flatten_layers_rgba(&|_| true)?, [0, 0, 255, 255]);
+
+    Ok(())
+}
@@ -0,0 +1,74 @@
+use anyhow::Result;
+use psd::Psd;
+
+const PATHS: &[u8] = include_bytes!("./fixtures/vector-masks/paths-4x4.psd");
+
+/// Verify that we read the knots of a layer's vector mask, along with its flags.
+///
+/// cargo test --test vector_masks vector_mask -- --exact
+#[test]
+fn vector_mask() -> Result<()> {
+    let psd = Psd::from_bytes(PATHS)?;
+
+    assert!(psd.layer_by_name("Plain").unwrap().vector_mask().is_none());
+
+    let shape = psd.layer_by_name("Shape").unwrap().vector_mask().unwrap();
+    assert!(shape.is_linked());
+    assert!(!shape.is_inverted());
+    assert!(!shape.is_disabled());
+
+    let path = shape.path();
+    assert!(!path.starts_with_all_pixels());
+    assert_eq!(path.subpaths().len(), 1);
+    let triangle = &path.subpaths()[0];
+    assert!(triangle.is_closed());
+    let anchors: Vec<_> = triangle
+        .knots()
+        .iter()
+        .map(|knot| (knot.anchor().x(), knot.anchor().y()))
+        .collect();
+    assert_eq!(anchors, vec![(0.5, 0.), (1., 1.), (0., 1.)]);
+
+    // "vsms" blocks hold vector masks too
+    let unlinked = psd
+        .layer_by_name("Unlinked")
+        .unwrap()
+        .vector_mask()
+        .unwrap();
+    assert!(!unlinked.is_linked());
+    assert!(unlinked.is_inverted());
+    assert!(unlinked.is_disabled());
+    assert_eq!(unlinked.path().subpaths().len(), 2);
+
+    Ok(())
+}
+
+/// Verify that we read the paths saved in the paths panel, and that they can be converted to SVG.
+///
+/// cargo test --test vector_masks saved_paths -- --exact
+#[test]
+fn saved_paths() -> Result<()> {
+    let psd = Psd::from_bytes(PATHS)?;
+
+    let paths = psd.paths();
+    let names: Vec<_> = paths.iter().map(|path| (path.id(), path.name())).collect();
+    assert_eq!(names, vec![(2000, "Triangle"), (2001, "Curve")]);
+
+    assert_eq!(
+        paths[0].path().to_svg_path_data(psd.width(), psd.height()),
+        "M 2 0 C 2 0 4 4 4 4 C 4 4 0 4 0 4 C 0 4 2 0 2 0 Z"
+    );
+
+    let curve = paths[1].path();
+    assert!(curve.starts_with_all_pixels());
+    let knot = curve.subpaths()[0].knots()[0];
+    assert!(knot.is_linked());
+    assert_eq!((knot.preceding().x(), knot.preceding().y()), (0.25, 0.75));
+    assert_eq!((knot.leaving().x(), knot.leaving().y()), (0.25, 0.25));
+    assert_eq!(
+        curve.to_svg_path_data(4, 4),
+        "M 1 2 C 1 1 3 1 3 2 C 3 3 1 3 1 2 Z M 0 0 C 0 0 4 0 4 0"
+    );
+
+    Ok(())
+}
@@ -0,0 +1,42 @@
+use anyhow::Result;
+use psd::{CropRect, Psd};
+
+/// Flattening the layers gives back the final image that Photoshop stored.
+///
+/// cargo test --test verify_composite matching_composite -- --exact
+#[test]
+fn matching_composite() -> Result<()> {
+    for bytes in [
+        &include_bytes!("./fixtures/two-layers-red-green-1x1.psd")[..],
+        &include_bytes!("./fixtures/transparent-top-layer-2x1.psd")[..],
+    ]
+    .iter()
+    {
+        let psd = Psd::from_bytes(bytes)?;
+        let diff = psd.verify_composite(0)?;
+
+        assert!(diff.is_match());
+        assert_eq!(diff.max_difference(), 0);
+        assert_eq!(diff.mismatch_bounds(), None);
+    }
+
+    Ok(())
+}
+
+/// Clipping masks aren't rendered, so the layers don't flatten into the final image.
+///
+/// cargo test --test verify_composite mismatched_composite -- --exact
+#[test]
+fn mismatched_composite() -> Result<()> {
+    let psd = include_bytes!("./fixtures/green-clipping-10x10.psd");
+    let psd = Psd::from_bytes(psd)?;
+
+    let diff = psd.verify_composite(2)?;
+    assert_eq!(diff.pixel_count(), 100);
+    assert_eq!(diff.mismatched_pixels(), 50);
+    assert_eq!(diff.mismatch_ratio(), 0.5);
+    assert_eq!(diff.max_difference(), 255);
+    assert_eq!(diff.mismatch_bounds(), Some(CropRect::new(0, 0, 10, 5)));
+
+    Ok(())
+}
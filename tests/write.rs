@@ -0,0 +1,208 @@
+use psd::{NodeId, Psd, PsdError, PsdVersion, ResizeFilter};
+
+/// Fixtures that were saved by Photoshop, which we write back out byte for byte.
+const PHOTOSHOP_FIXTURES: [&[u8]; 6] = [
+    include_bytes!("./fixtures/green-1x1.psd"),
+    include_bytes!("./fixtures/rle-3-layer-8x8.psd"),
+    include_bytes!("./fixtures/16x16-rle-partially-opaque.psd"),
+    include_bytes!("./fixtures/slices-v8.psd"),
+    include_bytes!("./fixtures/groups/green-1x1-one-group-inside-another.psd"),
+    include_bytes!("./fixtures/groups/green-1x1-one-group-with-two-subgroups.psd"),
+];
+
+/// Fixtures whose bytes change when written, but that parse into the same PSD.
+const OTHER_FIXTURES: [&[u8]; 11] = [
+    // 16 bit raw data, which we map down to 8 bits when reading
+    include_bytes!("./fixtures/two-channel-8x8.psd"),
+    // Extra data length that is larger than the data
+    include_bytes!("./fixtures/luni.psd"),
+    // RLE compressed channels without any data
+    include_bytes!("./fixtures/groups/rle-compressed-empty-channel.psd"),
+    // Odd length tagged block
+    include_bytes!("./fixtures/odd-length-tagged-block.psd"),
+    // Mac OS Roman layer and resource names
+    include_bytes!("./fixtures/non-utf8-pascal-string.psd"),
+    // ZIP compressed final image and layer channels
+    include_bytes!("./fixtures/zip-compression/rgb-3x1-zip-with-prediction.psd"),
+    // 32 bit depth
+    include_bytes!("./fixtures/zip-compression/hdr-2x1-zip-with-prediction.psd"),
+    // 1 bit depth, which we expand to 8 bits when reading
+    include_bytes!("./fixtures/bitmap-mode/bitmap-10x2-rle.psd"),
+    // CMYK layer with a black channel
+    include_bytes!("./fixtures/cmyk/cmyk-2x1.psd"),
+    // Indexed color table in the color mode data section
+    include_bytes!("./fixtures/indexed/indexed-3x1.psd"),
+    // Smart object layers and a linked layer block with an embedded and an external file
+    include_bytes!("./fixtures/smart-objects/smart-objects-2x2.psd"),
+];
+
+/// Verify that writing a PSD and parsing it again gives back the same PSD.
+///
+/// cargo test --test write round_trip -- --exact
+#[test]
+fn round_trip() -> anyhow::Result<()> {
+    for bytes in PHOTOSHOP_FIXTURES.iter().chain(OTHER_FIXTURES.iter()) {
+        let psd = Psd::from_bytes(bytes)?;
+
+        let written = Psd::from_bytes(&psd.to_bytes())?;
+
+        assert_eq!(written, psd);
+        assert_eq!(written.rgba(), psd.rgba());
+        assert_eq!(
+            written.flatten_layers_rgba(&|_| true)?,
+            psd.flatten_layers_rgba(&|_| true)?
+        );
+    }
+
+    Ok(())
+}
+
+/// Verify that we write the same bytes that Photoshop wrote.
+///
+/// cargo test --test write unchanged_bytes -- --exact
+#[test]
+fn unchanged_bytes() -> anyhow::Result<()> {
+    for bytes in PHOTOSHOP_FIXTURES.iter() {
+        let psd = Psd::from_bytes(bytes)?;
+
+        assert!(psd.to_bytes() == *bytes);
+    }
+
+    Ok(())
+}
+
+/// Verify that we can write a PSD into a writer.
+///
+/// cargo test --test write write_into_writer -- --exact
+#[test]
+fn write_into_writer() -> anyhow::Result<()> {
+    let psd = Psd::from_bytes(include_bytes!("./fixtures/green-1x1.psd"))?;
+
+    let mut file = vec![];
+    psd.write(&mut file)?;
+
+    assert_eq!(file, psd.to_bytes());
+
+    Ok(())
+}
+
+/// Verify that every PSD can be written as a PSB file, which parses into the same layers and
+/// final image.
+///
+/// cargo test --test write write_as_psb -- --exact
+#[test]
+fn write_as_psb() -> anyhow::Result<()> {
+    for bytes in PHOTOSHOP_FIXTURES.iter().chain(OTHER_FIXTURES.iter()) {
+        let psd = Psd::from_bytes(bytes)?;
+        assert_eq!(psd.version(), PsdVersion::One);
+
+        let psb = Psd::from_bytes(&psd.to_bytes_as(PsdVersion::Two)?)?;
+
+        assert_eq!(psb.version(), PsdVersion::Two);
+        assert_eq!(psb.layers(), psd.layers());
+        assert_eq!(psb.rgba(), psd.rgba());
+
+        // And back again
+        let written = psb.to_bytes_as(PsdVersion::One)?;
+        assert_eq!(Psd::from_bytes(&written)?, psd);
+    }
+
+    Ok(())
+}
+
+/// A PSD that grows wider than a PSD file allows is written as a PSB file, unless the version is
+/// pinned.
+///
+/// cargo test --test write promote_to_psb -- --exact
+#[test]
+fn promote_to_psb() -> anyhow::Result<()> {
+    let psd = Psd::from_bytes(include_bytes!("./fixtures/green-1x1.psd"))?;
+    let wide = psd.resize(30_001, 1, ResizeFilter::NearestNeighbor)?;
+
+    let written = Psd::from_bytes(&wide.to_bytes())?;
+    assert_eq!(written.version(), PsdVersion::Two);
+    assert_eq!((written.width(), written.height()), (30_001, 1));
+    assert_eq!(written.rgba(), wide.rgba());
+
+    assert_eq!(
+        wide.to_bytes_as(PsdVersion::One),
+        Err(PsdError::TooLargeForPsd {
+            width: 30_001,
+            height: 1
+        })
+    );
+
+    // Documents that fit stay PSD files
+    let fits = psd.resize(30_000, 1, ResizeFilter::NearestNeighbor)?;
+    assert_eq!(
+        Psd::from_bytes(&fits.to_bytes())?.version(),
+        PsdVersion::One
+    );
+
+    Ok(())
+}
+
+/// Opacities are set from percentages and written out as the nearest of the 256 opacities that a
+/// layer record can store.
+///
+/// cargo test --test write set_opacity_percent -- --exact
+#[test]
+fn set_opacity_percent() -> anyhow::Result<()> {
+    let psd = include_bytes!("./fixtures/groups/green-1x1-one-group-one-layer-inside.psd");
+    let mut psd = Psd::from_bytes(psd)?;
+    let group_id = psd.groups_in_order().next().unwrap().id();
+    assert_eq!(psd.layers()[0].opacity_percent(), 100.);
+
+    assert!(psd.set_opacity_percent(NodeId::Layer(0), 50.));
+    assert!(psd.set_opacity_percent(NodeId::Group(group_id), 25.));
+    assert!(!psd.set_opacity_percent(NodeId::Layer(100), 50.));
+
+    let written = Psd::from_bytes(&psd.to_bytes())?;
+    let layer = &written.layers()[0];
+    assert_eq!(layer.opacity(), 128);
+    assert!((layer.opacity_percent() - 50.2).abs() < 0.01);
+    assert_eq!(written.groups_in_order().next().unwrap().opacity(), 64);
+
+    // Percentages outside of 0 to 100 are clamped
+    assert!(psd.set_opacity_percent(NodeId::Layer(0), 150.));
+    assert_eq!(psd.layers()[0].opacity(), 255);
+
+    Ok(())
+}
+
+/// Raw tagged blocks that get replaced, added or removed are written out that way, for layers
+/// and for the records that open groups.
+///
+/// cargo test --test write patch_raw_tagged_blocks -- --exact
+#[test]
+fn patch_raw_tagged_blocks() -> anyhow::Result<()> {
+    let psd = include_bytes!("./fixtures/groups/green-1x1-one-group-one-layer-inside.psd");
+    let mut psd = Psd::from_bytes(psd)?;
+    let group_id = psd.groups_in_order().next().unwrap().id();
+
+    assert!(psd.set_raw_tagged_block(NodeId::Layer(0), *b"lspf", vec![0x80, 0, 0, 0]));
+    assert!(psd.set_raw_tagged_block(NodeId::Layer(0), *b"iOpa", vec![128, 0, 0, 0]));
+    assert!(psd.set_raw_tagged_block(NodeId::Group(group_id), *b"abcd", vec![1, 2, 3]));
+    assert!(!psd.set_raw_tagged_block(NodeId::Layer(100), *b"lspf", vec![0; 4]));
+
+    let written = Psd::from_bytes(&psd.to_bytes())?;
+    let layer = &written.layers()[0];
+    assert_eq!(layer.raw_tagged_block(b"lspf"), Some(&[0x80, 0, 0, 0][..]));
+    assert_eq!(layer.fill_opacity(), 128);
+    let group = written.groups_in_order().next().unwrap();
+    assert_eq!(group.raw_tagged_block(b"abcd"), Some(&[1, 2, 3][..]));
+
+    let mut written = written;
+    assert_eq!(
+        written.remove_raw_tagged_block(NodeId::Layer(0), b"lspf"),
+        Some(vec![0x80, 0, 0, 0])
+    );
+    assert_eq!(
+        written.remove_raw_tagged_block(NodeId::Layer(0), b"lspf"),
+        None
+    );
+    let written = Psd::from_bytes(&written.to_bytes())?;
+    assert_eq!(written.layers()[0].raw_tagged_block(b"lspf"), None);
+
+    Ok(())
+}